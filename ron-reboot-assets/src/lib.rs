@@ -0,0 +1,332 @@
+//! [`RonAssetLoader`], loading assets of a game engine or other application
+//! from RON files
+//!
+//! Engines like Bevy load assets with a loader per asset type, which reads
+//! the file and gets the path for error messages. [`AssetLoad`] is the part
+//! that is the same for every RON asset: implement it for the asset type and
+//! call the loader from the engine's loader.
+//!
+//! ```
+//! use ron_reboot_assets::{AssetLoad, RonAssetLoader};
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Level {
+//!     name: String,
+//!     spawns: Vec<(f32, f32)>,
+//! }
+//!
+//! impl AssetLoad for Level {
+//!     const EXTENSIONS: &'static [&'static str] = &["level.ron"];
+//! }
+//!
+//! let loader = RonAssetLoader::<Level>::new();
+//! let level = loader
+//!     .load_bytes("levels/1.level.ron", b"(name: \"Intro\", spawns: [(0, 1.5)])")
+//!     .unwrap();
+//! assert_eq!(level.spawns, [(0.0, 1.5)]);
+//! assert!(loader.handles("levels/1.level.ron"));
+//! ```
+//!
+//! Assets that don't deserialize give a [`LoadError`] that renders as the
+//! error snippet of [`ron_reboot::print_error`], e.g.
+//!
+//! ```text
+//! error: invalid type: string "up", expected f32
+//!  --> levels/1.level.ron:1:37
+//!   |
+//! 1 | (name: "Intro", spawns: [(0, 1.5), ("up", 2)])
+//!   |                                     ^^^^
+//!   |
+//! ```
+
+use std::{fmt, io, io::Read, marker::PhantomData};
+
+use futures_util::io::{AsyncRead, AsyncReadExt};
+use ron_reboot::{
+    utf8_parser::serde::{Options, Scope},
+    write_error, Error, Location,
+};
+use serde::de::DeserializeOwned;
+
+/// The size of the largest file [`RonAssetLoader::new`] loads, 16 MiB
+pub const DEFAULT_MAX_BYTES: u64 = 16 * 1024 * 1024;
+
+/// An asset type loaded from RON files
+pub trait AssetLoad: DeserializeOwned {
+    /// The extensions of the files, without the leading `.`, e.g.
+    /// `["level.ron"]`
+    const EXTENSIONS: &'static [&'static str];
+}
+
+/// Loads assets of type `A`, with the options and extensions of a [`Scope`]
+/// and a limit of the file size
+pub struct RonAssetLoader<A> {
+    scope: Scope,
+    max_bytes: u64,
+    asset: PhantomData<fn() -> A>,
+}
+
+/// Why an asset couldn't be loaded
+#[derive(Debug)]
+pub enum LoadError {
+    /// The file couldn't be read
+    Io(io::Error),
+    /// The file is larger than [`RonAssetLoader::with_max_bytes`] allows
+    TooLarge { max_bytes: u64 },
+    /// The file isn't RON, or not of the asset type
+    Invalid(Error),
+}
+
+impl<A: AssetLoad> RonAssetLoader<A> {
+    /// A loader with the default [`Options`], loading files up to
+    /// [`DEFAULT_MAX_BYTES`]
+    pub fn new() -> Self {
+        RonAssetLoader {
+            scope: Options::new().scoped(),
+            max_bytes: DEFAULT_MAX_BYTES,
+            asset: PhantomData,
+        }
+    }
+
+    /// Deserializes with the options, extensions and metrics callback of
+    /// `scope`, e.g. to reject duplicate fields or to log slow assets
+    #[must_use]
+    pub fn with_scope(mut self, scope: Scope) -> Self {
+        self.scope = scope;
+
+        self
+    }
+
+    /// Rejects files larger than `max_bytes` before parsing them; readers
+    /// stop reading after the limit
+    #[must_use]
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+
+        self
+    }
+
+    /// The file extensions of `A`
+    pub fn extensions(&self) -> &'static [&'static str] {
+        A::EXTENSIONS
+    }
+
+    /// Whether the file at `path` has one of the [`extensions`](Self::extensions)
+    pub fn handles(&self, path: &str) -> bool {
+        A::EXTENSIONS.iter().any(|extension| {
+            path.strip_suffix(extension)
+                .is_some_and(|stem| stem.ends_with('.'))
+        })
+    }
+
+    /// Deserializes the file at `path`, which has been read into `bytes`
+    pub fn load_bytes(&self, path: &str, bytes: &[u8]) -> Result<A, LoadError> {
+        if bytes.len() as u64 > self.max_bytes {
+            return Err(LoadError::TooLarge {
+                max_bytes: self.max_bytes,
+            });
+        }
+
+        std::str::from_utf8(bytes)
+            .map_err(|e| Error::invalid_utf8(bytes, e.valid_up_to()))
+            .and_then(|s| self.scope.from_str(s))
+            .map_err(|e| LoadError::Invalid(e.context_file_name(path.to_owned())))
+    }
+
+    /// Reads the file at `path` from `reader` and deserializes it
+    pub fn load(&self, path: &str, reader: impl Read) -> Result<A, LoadError> {
+        let mut bytes = vec![];
+        reader
+            .take(self.max_bytes.saturating_add(1))
+            .read_to_end(&mut bytes)
+            .map_err(LoadError::Io)?;
+
+        self.load_bytes(path, &bytes)
+    }
+
+    /// Like [`load`](Self::load), but reads without blocking the executor,
+    /// e.g. from the reader an engine passes to its asset loaders
+    pub async fn load_async<R>(&self, path: &str, reader: R) -> Result<A, LoadError>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let mut bytes = vec![];
+        reader
+            .take(self.max_bytes.saturating_add(1))
+            .read_to_end(&mut bytes)
+            .await
+            .map_err(LoadError::Io)?;
+
+        self.load_bytes(path, &bytes)
+    }
+}
+
+impl<A: AssetLoad> Default for RonAssetLoader<A> {
+    fn default() -> Self {
+        RonAssetLoader::new()
+    }
+}
+
+impl<A> Clone for RonAssetLoader<A> {
+    fn clone(&self) -> Self {
+        RonAssetLoader {
+            scope: self.scope.clone(),
+            max_bytes: self.max_bytes,
+            asset: PhantomData,
+        }
+    }
+}
+
+impl LoadError {
+    /// Where in the file the error is, e.g. for an editor to jump to
+    pub fn span(&self) -> Option<(Location, Location)> {
+        match self {
+            LoadError::Invalid(e) => e.start().zip(e.end()),
+            LoadError::Io(_) | LoadError::TooLarge { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Io(e) => write!(f, "could not read asset: {}", e),
+            LoadError::TooLarge { max_bytes } => {
+                write!(f, "asset is larger than the limit of {} bytes", max_bytes)
+            }
+            LoadError::Invalid(e) => {
+                let mut snippet = vec![];
+                let _ = write_error(&mut snippet, e);
+
+                write!(f, "{}", String::from_utf8_lossy(&snippet).trim_end())
+            }
+        }
+    }
+}
+
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LoadError::Io(e) => Some(e),
+            LoadError::Invalid(e) => Some(e),
+            LoadError::TooLarge { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use futures_executor::block_on;
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Level {
+        name: String,
+        spawns: Vec<(f32, f32)>,
+    }
+
+    impl AssetLoad for Level {
+        const EXTENSIONS: &'static [&'static str] = &["level.ron", "lvl"];
+    }
+
+    const LEVEL: &str = "(name: \"Intro\", spawns: [(0, 1.5)])";
+
+    fn intro() -> Level {
+        Level {
+            name: "Intro".to_owned(),
+            spawns: vec![(0.0, 1.5)],
+        }
+    }
+
+    #[test]
+    fn loads() {
+        let loader = RonAssetLoader::<Level>::new();
+
+        assert_eq!(
+            loader.load_bytes("a.level.ron", LEVEL.as_bytes()).unwrap(),
+            intro()
+        );
+        assert_eq!(
+            loader.load("a.level.ron", LEVEL.as_bytes()).unwrap(),
+            intro()
+        );
+        assert_eq!(
+            block_on(loader.load_async("a.level.ron", LEVEL.as_bytes())).unwrap(),
+            intro()
+        );
+
+        assert!(loader.handles("levels/a.level.ron"));
+        assert!(loader.handles("a.lvl"));
+        assert!(!loader.handles("a.ron"));
+        assert!(!loader.handles("level.ron"));
+    }
+
+    #[test]
+    fn limits() {
+        let loader = RonAssetLoader::<Level>::new().with_max_bytes(10);
+
+        assert!(matches!(
+            loader.load("a.lvl", LEVEL.as_bytes()),
+            Err(LoadError::TooLarge { max_bytes: 10 })
+        ));
+        assert!(matches!(
+            block_on(loader.load_async("a.lvl", LEVEL.as_bytes())),
+            Err(LoadError::TooLarge { max_bytes: 10 })
+        ));
+        assert!(RonAssetLoader::<Level>::new()
+            .with_max_bytes(LEVEL.len() as u64)
+            .load("a.lvl", LEVEL.as_bytes())
+            .is_ok());
+    }
+
+    #[test]
+    fn diagnostics() {
+        let loader = RonAssetLoader::<Level>::new();
+        let e = loader
+            .load_bytes(
+                "levels/1.level.ron",
+                b"(name: \"Intro\", spawns: [(0, 1.5), (\"up\", 2)])",
+            )
+            .unwrap_err();
+
+        assert_eq!(
+            e.span().map(|(start, end)| (start.column, end.column)),
+            Some((37, 41))
+        );
+        let text = e.to_string();
+        assert!(text.contains("--> levels/1.level.ron:1:37"), "{}", text);
+        assert!(text.contains("expected f32"), "{}", text);
+
+        assert!(matches!(
+            loader.load_bytes("a.lvl", b"(name: \"\xff\")"),
+            Err(LoadError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn scope() {
+        let documents = Arc::new(AtomicUsize::new(0));
+        let counter = documents.clone();
+        let scope = Options::new()
+            .deny_duplicate_keys(true)
+            .scoped()
+            .with_metrics(move |_| {
+                counter.fetch_add(1, Ordering::Relaxed);
+            });
+        let loader = RonAssetLoader::<Level>::new().with_scope(scope);
+
+        assert!(loader.load_bytes("a.lvl", LEVEL.as_bytes()).is_ok());
+        assert!(loader
+            .load_bytes("a.lvl", b"(name: \"a\", name: \"b\", spawns: [])")
+            .is_err());
+        assert_eq!(documents.load(Ordering::Relaxed), 2);
+    }
+}
@@ -0,0 +1,219 @@
+//! [`Ron`], for axum handlers taking and returning RON bodies
+//!
+//! ```no_run
+//! use ron_reboot_axum::Ron;
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Deserialize, Serialize)]
+//! struct Window {
+//!     width: u32,
+//!     height: u32,
+//! }
+//!
+//! async fn resize(Ron(window): Ron<Window>) -> Ron<Window> {
+//!     Ron(Window {
+//!         width: window.width * 2,
+//!         height: window.height * 2,
+//!     })
+//! }
+//! ```
+//!
+//! Bodies that aren't valid RON for the type are rejected with a `400` whose
+//! body is the error as [`ron_reboot::print_error`] renders it, e.g.
+//!
+//! ```text
+//! error: invalid type: boolean `true`, expected u32
+//!  --> request body:1:9
+//!   |
+//! 1 | (width: true, height: 600)
+//!   |         ^^^^
+//!   |
+//! ```
+
+use std::fmt;
+
+use axum_core::{
+    extract::{rejection::BytesRejection, FromRequest, Request},
+    response::{IntoResponse, Response},
+};
+use bytes::Bytes;
+use http::{header::CONTENT_TYPE, StatusCode};
+use ron_reboot::{from_bytes_serde, to_string_serde, write_error, Error};
+use serde::{de::DeserializeOwned, Serialize};
+
+/// The content type of RON bodies
+pub const RON_CONTENT_TYPE: &str = "application/ron";
+
+/// A RON body: as an extractor, deserializes the request body into `T`, as a
+/// response, serializes `T`
+///
+/// Requests need the [`RON_CONTENT_TYPE`], otherwise they're rejected with a
+/// `415`; responses have it.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Ron<T>(pub T);
+
+/// Why a request body couldn't be extracted as [`Ron`]
+#[derive(Debug)]
+pub enum RonRejection {
+    /// The `Content-Type` of the request isn't [`RON_CONTENT_TYPE`]
+    MissingRonContentType,
+    /// The body couldn't be read, e.g. because it's too large
+    Body(BytesRejection),
+    /// The body isn't RON, or not of the expected type
+    Invalid(Error),
+}
+
+impl<T, S> FromRequest<S> for Ron<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = RonRejection;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        if !is_ron(&req) {
+            return Err(RonRejection::MissingRonContentType);
+        }
+
+        let body = Bytes::from_request(req, state)
+            .await
+            .map_err(RonRejection::Body)?;
+
+        from_bytes_serde(&body)
+            .map(Ron)
+            .map_err(|e| RonRejection::Invalid(e.context_file_name("request body".to_owned())))
+    }
+}
+
+/// Whether the content type of `req` is RON, ignoring parameters like
+/// `charset`
+fn is_ron(req: &Request) -> bool {
+    req.headers()
+        .get(CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .and_then(|content_type| content_type.split(';').next())
+        .is_some_and(|mime| mime.trim().eq_ignore_ascii_case(RON_CONTENT_TYPE))
+}
+
+impl<T: Serialize> IntoResponse for Ron<T> {
+    fn into_response(self) -> Response {
+        match to_string_serde(&self.0) {
+            Ok(body) => ([(CONTENT_TYPE, RON_CONTENT_TYPE)], body).into_response(),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        }
+    }
+}
+
+impl IntoResponse for RonRejection {
+    fn into_response(self) -> Response {
+        match self {
+            RonRejection::MissingRonContentType => {
+                (StatusCode::UNSUPPORTED_MEDIA_TYPE, self.to_string()).into_response()
+            }
+            RonRejection::Body(rejection) => rejection.into_response(),
+            RonRejection::Invalid(e) => {
+                let mut body = vec![];
+                let _ = write_error(&mut body, &e);
+
+                (
+                    StatusCode::BAD_REQUEST,
+                    String::from_utf8_lossy(&body).into_owned(),
+                )
+                    .into_response()
+            }
+        }
+    }
+}
+
+impl fmt::Display for RonRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RonRejection::MissingRonContentType => write!(
+                f,
+                "expected a request with `Content-Type: {}`",
+                RON_CONTENT_TYPE
+            ),
+            RonRejection::Body(rejection) => write!(f, "{}", rejection),
+            RonRejection::Invalid(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for RonRejection {}
+
+#[cfg(test)]
+mod tests {
+    use axum_core::body::Body;
+    use futures_executor::block_on;
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Window {
+        width: u32,
+        height: u32,
+    }
+
+    fn extract(content_type: Option<&str>, body: &str) -> Result<Ron<Window>, RonRejection> {
+        let mut req = Request::builder();
+        if let Some(content_type) = content_type {
+            req = req.header(CONTENT_TYPE, content_type);
+        }
+        let req = req.body(Body::from(body.to_owned())).unwrap();
+
+        block_on(Ron::from_request(req, &()))
+    }
+
+    fn body_text(response: Response) -> String {
+        let body = block_on(Bytes::from_request(Request::new(response.into_body()), &())).unwrap();
+
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn extracts() {
+        for content_type in ["application/ron", "Application/RON; charset=utf-8"] {
+            assert_eq!(
+                extract(Some(content_type), "(width: 800, height: 600)").unwrap(),
+                Ron(Window {
+                    width: 800,
+                    height: 600
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn rejects() {
+        let response = extract(None, "(width: 800, height: 600)")
+            .unwrap_err()
+            .into_response();
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert!(matches!(
+            extract(Some("application/json"), "{}"),
+            Err(RonRejection::MissingRonContentType)
+        ));
+
+        let response = extract(Some(RON_CONTENT_TYPE), "(width: true, height: 600)")
+            .unwrap_err()
+            .into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let text = body_text(response);
+        assert!(text.contains("--> request body:1:9"), "{}", text);
+        assert!(text.contains("1 | (width: true, height: 600)"), "{}", text);
+    }
+
+    #[test]
+    fn responds() {
+        let response = Ron(Window {
+            width: 800,
+            height: 600,
+        })
+        .into_response();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[CONTENT_TYPE], RON_CONTENT_TYPE);
+        assert_eq!(body_text(response), "(width:800,height:600)");
+    }
+}
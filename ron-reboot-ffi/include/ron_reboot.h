@@ -0,0 +1,55 @@
+/*
+ * C bindings of ron-reboot, a Rusty Object Notation (RON) parser
+ *
+ * Link against the static or dynamic library built from `ron-reboot-ffi`.
+ * Strings passed in are NUL-terminated UTF-8; strings passed out are owned by
+ * the caller.
+ */
+
+#ifndef RON_REBOOT_H
+#define RON_REBOOT_H
+
+#include <stdbool.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* The first error of a document */
+typedef struct RonError {
+    /* What went wrong; free with `ron_error_free` */
+    char *message;
+    /* 1-based, 0 if the error has no location */
+    uint32_t line;
+    /* 1-based UTF-8 column, 0 if the error has no location */
+    uint32_t column;
+} RonError;
+
+/*
+ * Converts `source` to JSON; free the result with `ron_string_free`
+ *
+ * Returns NULL and fills `error`, if it isn't NULL, if `source` is invalid.
+ * Struct names are dropped, `None` and `()` become `null`.
+ */
+char *ron_to_json(const char *source, RonError *error);
+
+/*
+ * Checks the syntax of `source`
+ *
+ * Returns whether it is valid, and fills `error`, if it isn't NULL, with the
+ * first error otherwise.
+ */
+bool ron_validate(const char *source, RonError *error);
+
+/* Frees a string returned by `ron_to_json`; does nothing for NULL */
+void ron_string_free(char *s);
+
+/* Frees the message of `error`, which can then be filled again */
+void ron_error_free(RonError *error);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* RON_REBOOT_H */
@@ -0,0 +1,204 @@
+//! C bindings, declared in `include/ron_reboot.h`
+//!
+//! Strings passed in are NUL-terminated UTF-8. Strings passed out are owned
+//! by the caller, who frees them with [`ron_string_free`] or
+//! [`ron_error_free`].
+
+use std::{
+    ffi::{CStr, CString},
+    os::raw::c_char,
+    ptr,
+};
+
+use ron_reboot::{utf8_parser::ast_from_str_recovering, Error, ErrorKind, Value};
+use serde_json::Value as Json;
+
+/// The first error of a document
+#[repr(C)]
+#[derive(Debug)]
+pub struct RonError {
+    /// What went wrong
+    pub message: *mut c_char,
+    /// 1-based, 0 if the error has no location
+    pub line: u32,
+    /// 1-based UTF-8 column, 0 if the error has no location
+    pub column: u32,
+}
+
+impl RonError {
+    fn new(error: &Error) -> Self {
+        let (line, column) = error
+            .start()
+            .map_or((0, 0), |start| (start.line, start.column));
+
+        RonError {
+            message: into_c_string(error.kind.to_string()),
+            line,
+            column,
+        }
+    }
+}
+
+/// Converts `source` to JSON, see `ron_reboot::Value` for how
+///
+/// Returns NULL and fills `error`, if it isn't NULL, if `source` is invalid.
+///
+/// # Safety
+///
+/// `source` has to be a NUL-terminated string and `error` NULL or valid for
+/// writes.
+#[no_mangle]
+pub unsafe extern "C" fn ron_to_json(source: *const c_char, error: *mut RonError) -> *mut c_char {
+    let json = source_str(source).and_then(|source| source.parse::<Value>());
+
+    match json {
+        Ok(value) => into_c_string(Json::from(value).to_string()),
+        Err(e) => {
+            report(&e, error);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Checks the syntax of `source`
+///
+/// Returns whether it is valid, and fills `error`, if it isn't NULL, with the
+/// first error otherwise.
+///
+/// # Safety
+///
+/// `source` has to be a NUL-terminated string and `error` NULL or valid for
+/// writes.
+#[no_mangle]
+pub unsafe extern "C" fn ron_validate(source: *const c_char, error: *mut RonError) -> bool {
+    let errors = source_str(source)
+        .map_err(|e| vec![e])
+        .and_then(|source| ast_from_str_recovering(source).map(|_| ()));
+
+    match errors {
+        Ok(()) => true,
+        Err(errors) => {
+            report(&errors[0], error);
+            false
+        }
+    }
+}
+
+/// Frees a string returned by [`ron_to_json`]; does nothing for NULL
+///
+/// # Safety
+///
+/// `s` has to be NULL or returned by this library and not freed before.
+#[no_mangle]
+pub unsafe extern "C" fn ron_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Frees the message of `error`, which can then be reused
+///
+/// # Safety
+///
+/// `error` has to be NULL or filled in by this library and not freed before.
+#[no_mangle]
+pub unsafe extern "C" fn ron_error_free(error: *mut RonError) {
+    if let Some(error) = error.as_mut() {
+        ron_string_free(error.message);
+        error.message = ptr::null_mut();
+    }
+}
+
+unsafe fn source_str<'a>(source: *const c_char) -> Result<&'a str, Error> {
+    if source.is_null() {
+        return Err(Error {
+            kind: ErrorKind::Custom("source is NULL".to_owned()),
+            context: None,
+        });
+    }
+
+    let bytes = CStr::from_ptr(source).to_bytes();
+    std::str::from_utf8(bytes).map_err(|e| Error::invalid_utf8(bytes, e.valid_up_to()))
+}
+
+unsafe fn report(e: &Error, error: *mut RonError) {
+    if let Some(error) = error.as_mut() {
+        *error = RonError::new(e);
+    }
+}
+
+/// `s` as a C string; NUL bytes, which RON strings may contain, are dropped
+fn into_c_string(s: String) -> *mut c_char {
+    let s = CString::new(s).unwrap_or_else(|e| {
+        let mut bytes = e.into_vec();
+        bytes.retain(|&b| b != 0);
+        CString::new(bytes).expect("NUL bytes were removed")
+    });
+
+    s.into_raw()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn empty_error() -> RonError {
+        RonError {
+            message: ptr::null_mut(),
+            line: 0,
+            column: 0,
+        }
+    }
+
+    unsafe fn message(error: &RonError) -> &str {
+        CStr::from_ptr(error.message).to_str().unwrap()
+    }
+
+    #[test]
+    fn to_json() {
+        unsafe {
+            let mut error = empty_error();
+            let json = ron_to_json(
+                b"Point(x: 1, tags: [\"a\"], z: None)\0".as_ptr().cast(),
+                &mut error,
+            );
+            assert_eq!(
+                CStr::from_ptr(json).to_str(),
+                Ok(r#"{"tags":["a"],"x":1,"z":null}"#)
+            );
+            assert!(error.message.is_null());
+            ron_string_free(json);
+
+            let json = ron_to_json(b"[1,\n  2 3]\0".as_ptr().cast(), &mut error);
+            assert!(json.is_null());
+            assert_eq!((error.line, error.column), (2, 5));
+            assert!(message(&error).contains("','"));
+            ron_error_free(&mut error);
+            assert!(error.message.is_null());
+        }
+    }
+
+    #[test]
+    fn validate() {
+        unsafe {
+            let mut error = empty_error();
+            assert!(ron_validate(b"(a: 1)\0".as_ptr().cast(), &mut error));
+            assert!(ron_validate(b"(a: 1)\0".as_ptr().cast(), ptr::null_mut()));
+
+            assert!(!ron_validate(
+                b"[1, (a: ), 3]\0".as_ptr().cast(),
+                &mut error
+            ));
+            assert_eq!((error.line, error.column), (1, 9));
+            ron_error_free(&mut error);
+
+            assert!(!ron_validate(b"\"\xff\"\0".as_ptr().cast(), &mut error));
+            assert_eq!(message(&error), "invalid UTF-8");
+            ron_error_free(&mut error);
+
+            assert!(!ron_validate(ptr::null(), &mut error));
+            assert_eq!(message(&error), "source is NULL");
+            ron_error_free(&mut error);
+        }
+    }
+}
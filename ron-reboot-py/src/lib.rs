@@ -0,0 +1,361 @@
+//! The `ron_reboot` Python module
+//!
+//! ```python
+//! import ron_reboot
+//!
+//! level = ron_reboot.loads('Level(name: "cave", spawn: Point(1, 2))')
+//! assert level == {"name": "cave", "spawn": ron_reboot.Tagged("Point", (1, 2))}
+//! assert level.name == "Level"
+//! ```
+//!
+//! RON values become these Python values:
+//!
+//! * structs become [`Struct`]s, dicts with the struct name as `name`
+//! * named tuples and units, e.g. enum variants, become [`Tagged`]s
+//! * maps become dicts, lists lists and other tuples tuples
+//! * `Some(x)` becomes `x`, `None` and `()` become `None`
+//! * chars become strings
+//!
+//! `dumps` writes them back, it doesn't know which values were options.
+
+use pyo3::{
+    create_exception,
+    exceptions::{PyTypeError, PyValueError},
+    prelude::*,
+    types::{PyDict, PyFloat, PyList, PyString, PyTuple},
+    IntoPyObjectExt,
+};
+use ron_reboot::{to_string_serde, utf8_parser::is_valid_ident, Value};
+
+create_exception!(
+    ron_reboot,
+    RonError,
+    PyValueError,
+    "A document that isn't valid RON or a value that can't be written as RON"
+);
+
+/// A struct, a dict of its fields with the struct's name, if it has one
+///
+/// Takes the same arguments as `dict`, the name is set afterwards:
+/// `s = Struct(x=1); s.name = "Point"`.
+#[pyclass(extends = PyDict, module = "ron_reboot")]
+pub struct Struct {
+    #[pyo3(get, set)]
+    name: Option<String>,
+}
+
+#[pymethods]
+impl Struct {
+    #[new]
+    #[pyo3(signature = (*_args, **_kwargs))]
+    fn new(_args: &Bound<'_, PyTuple>, _kwargs: Option<&Bound<'_, PyDict>>) -> Self {
+        // `dict.__init__` takes the arguments
+        Struct { name: None }
+    }
+}
+
+/// A named tuple or unit, e.g. `Point(1, 2)` or the enum variant `Empty`
+#[pyclass(frozen, get_all, module = "ron_reboot")]
+pub struct Tagged {
+    name: String,
+    values: Py<PyTuple>,
+}
+
+#[pymethods]
+impl Tagged {
+    #[new]
+    #[pyo3(signature = (name, values = None))]
+    fn new(py: Python<'_>, name: String, values: Option<Py<PyTuple>>) -> Self {
+        Tagged {
+            name,
+            values: values.unwrap_or_else(|| PyTuple::empty(py).unbind()),
+        }
+    }
+
+    fn __eq__(&self, py: Python<'_>, other: &Bound<'_, PyAny>) -> PyResult<bool> {
+        match other.downcast::<Tagged>() {
+            Ok(other) => {
+                let other = other.get();
+                Ok(self.name == other.name && self.values.bind(py).eq(&other.values)?)
+            }
+            Err(_) => Ok(false),
+        }
+    }
+
+    fn __repr__(&self, py: Python<'_>) -> PyResult<String> {
+        Ok(format!(
+            "Tagged({:?}, {})",
+            self.name,
+            self.values.bind(py).repr()?
+        ))
+    }
+}
+
+/// Reads a RON document
+#[pyfunction]
+fn loads(py: Python<'_>, s: &str) -> PyResult<PyObject> {
+    let value = s
+        .parse::<Value>()
+        .map_err(|e| RonError::new_err(e.to_string()))?;
+
+    to_python(py, value)
+}
+
+/// Writes `obj` as RON, on a single line unless `pretty` is true
+#[pyfunction]
+#[pyo3(signature = (obj, pretty = false))]
+fn dumps(obj: &Bound<'_, PyAny>, pretty: bool) -> PyResult<String> {
+    let mut ron = String::new();
+    write(&mut ron, obj, pretty.then_some(0))?;
+
+    Ok(ron)
+}
+
+#[pymodule]
+#[pyo3(name = "ron_reboot")]
+fn python_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(loads, m)?)?;
+    m.add_function(wrap_pyfunction!(dumps, m)?)?;
+    m.add_class::<Struct>()?;
+    m.add_class::<Tagged>()?;
+    m.add("RonError", m.py().get_type::<RonError>())?;
+
+    Ok(())
+}
+
+fn to_python(py: Python<'_>, value: Value) -> PyResult<PyObject> {
+    let object = match value {
+        Value::Bool(b) => b.into_py_any(py)?,
+        Value::Char(c) => c.into_py_any(py)?,
+        Value::String(s) => s.into_py_any(py)?,
+        Value::Number(n) => n.map_to(|i| i.into_py_any(py), |f| f.into_py_any(py))?,
+        Value::Option(Some(value)) => to_python(py, *value)?,
+        Value::Option(None) | Value::Unit(None) => py.None(),
+        Value::List(elements) => PyList::new(py, to_python_vec(py, elements)?)?.into_py_any(py)?,
+        Value::Tuple(None, elements) => {
+            PyTuple::new(py, to_python_vec(py, elements)?)?.into_py_any(py)?
+        }
+        Value::Tuple(Some(name), elements) => {
+            let values = PyTuple::new(py, to_python_vec(py, elements)?)?.unbind();
+            Tagged { name, values }.into_py_any(py)?
+        }
+        Value::Unit(Some(name)) => Tagged::new(py, name, None).into_py_any(py)?,
+        Value::Map(entries) => {
+            let dict = PyDict::new(py);
+            for (key, value) in entries {
+                dict.set_item(to_python(py, key)?, to_python(py, value)?)?;
+            }
+            dict.into_py_any(py)?
+        }
+        Value::Struct(name, fields) => {
+            let s = Bound::new(py, Struct { name })?;
+            for (key, value) in fields {
+                s.set_item(key, to_python(py, value)?)?;
+            }
+            s.into_py_any(py)?
+        }
+    };
+
+    Ok(object)
+}
+
+fn to_python_vec(py: Python<'_>, values: Vec<Value>) -> PyResult<Vec<PyObject>> {
+    values
+        .into_iter()
+        .map(|value| to_python(py, value))
+        .collect()
+}
+
+/// Writes `obj`, pretty-printed if it has an indentation depth
+fn write(ron: &mut String, obj: &Bound<'_, PyAny>, depth: Option<usize>) -> PyResult<()> {
+    let inner = depth.map(|depth| depth + 1);
+
+    if let Ok(s) = obj.downcast::<Struct>() {
+        let name = s.borrow().name.clone();
+        let mut fields = vec![];
+        for (key, value) in s.as_any().downcast::<PyDict>()? {
+            let key = key.extract::<String>()?;
+            if !is_valid_ident(&key) {
+                return Err(RonError::new_err(format!(
+                    "cannot write `{}` as a field name",
+                    key
+                )));
+            }
+            fields.push(format!("{}:{}{}", key, sep(depth), written(&value, inner)?));
+        }
+        ron.push_str(name.as_deref().unwrap_or(""));
+        write_seq(ron, "(", fields, ")", depth);
+    } else if let Ok(tagged) = obj.downcast::<Tagged>() {
+        let tagged = tagged.get();
+        ron.push_str(&tagged.name);
+        let values = tagged.values.bind(obj.py());
+        if !values.is_empty() {
+            write_seq(ron, "(", written_all(values.iter(), inner)?, ")", depth);
+        }
+    } else if let Ok(dict) = obj.downcast::<PyDict>() {
+        let mut entries = vec![];
+        for (key, value) in dict {
+            entries.push(format!(
+                "{}:{}{}",
+                written(&key, inner)?,
+                sep(depth),
+                written(&value, inner)?
+            ));
+        }
+        write_seq(ron, "{", entries, "}", depth);
+    } else if let Ok(list) = obj.downcast::<PyList>() {
+        write_seq(ron, "[", written_all(list.iter(), inner)?, "]", depth);
+    } else if let Ok(tuple) = obj.downcast::<PyTuple>() {
+        write_seq(ron, "(", written_all(tuple.iter(), inner)?, ")", depth);
+    } else {
+        ron.push_str(&write_primitive(obj)?);
+    }
+
+    Ok(())
+}
+
+fn write_primitive(obj: &Bound<'_, PyAny>) -> PyResult<String> {
+    let ron = if obj.is_none() {
+        to_string_serde(&None::<()>)
+    } else if let Ok(b) = obj.extract::<bool>() {
+        to_string_serde(&b)
+    } else if let Ok(s) = obj.downcast::<PyString>() {
+        to_string_serde(s.to_str()?)
+    } else if obj.is_instance_of::<PyFloat>() {
+        to_string_serde(&obj.extract::<f64>()?)
+    } else if let Ok(i) = obj.extract::<i64>() {
+        to_string_serde(&i)
+    } else if let Ok(u) = obj.extract::<u64>() {
+        to_string_serde(&u)
+    } else {
+        return Err(PyTypeError::new_err(format!(
+            "cannot write a `{}` as RON",
+            obj.get_type().name()?
+        )));
+    };
+
+    ron.map_err(|e| RonError::new_err(e.to_string()))
+}
+
+fn written(obj: &Bound<'_, PyAny>, depth: Option<usize>) -> PyResult<String> {
+    let mut ron = String::new();
+    write(&mut ron, obj, depth)?;
+
+    Ok(ron)
+}
+
+fn written_all<'py>(
+    objects: impl Iterator<Item = Bound<'py, PyAny>>,
+    depth: Option<usize>,
+) -> PyResult<Vec<String>> {
+    objects.map(|obj| written(&obj, depth)).collect()
+}
+
+/// Puts each item on its own line when pretty-printing
+fn write_seq(ron: &mut String, open: &str, items: Vec<String>, close: &str, depth: Option<usize>) {
+    ron.push_str(open);
+    match depth {
+        Some(depth) if !items.is_empty() => {
+            for item in items {
+                ron.push('\n');
+                ron.push_str(&"    ".repeat(depth + 1));
+                ron.push_str(&item);
+                ron.push(',');
+            }
+            ron.push('\n');
+            ron.push_str(&"    ".repeat(depth));
+        }
+        _ => ron.push_str(&items.join(",")),
+    }
+    ron.push_str(close);
+}
+
+/// The separator after a field name or map key
+fn sep(depth: Option<usize>) -> &'static str {
+    match depth {
+        Some(_) => " ",
+        None => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+
+    use pyo3::types::IntoPyDict;
+
+    use super::*;
+
+    fn run(code: &str) {
+        Python::with_gil(|py| {
+            let ron_reboot = PyModule::new(py, "ron_reboot").unwrap();
+            python_module(&ron_reboot).unwrap();
+            let locals = [("ron_reboot", ron_reboot)].into_py_dict(py).unwrap();
+            let code = CString::new(code).unwrap();
+            if let Err(e) = py.run(&code, None, Some(&locals)) {
+                e.print(py);
+                panic!("Python code failed");
+            }
+        })
+    }
+
+    #[test]
+    fn loads_values() {
+        run(r#"
+level = ron_reboot.loads('''
+Level(
+    name: "cave",
+    spawn: Point(1, 2),
+    kind: Underground,
+    loot: {"gold": [1, 2.5]},
+    boss: Some((hp: 3)),
+    exit: None,
+)''')
+assert level == {
+    "name": "cave",
+    "spawn": ron_reboot.Tagged("Point", (1, 2)),
+    "kind": ron_reboot.Tagged("Underground"),
+    "loot": {"gold": [1, 2.5]},
+    "boss": {"hp": 3},
+    "exit": None,
+}
+assert level.name == "Level"
+assert level["boss"].name is None
+assert isinstance(level, dict)
+
+try:
+    ron_reboot.loads("[1, 2")
+    assert False
+except ron_reboot.RonError as e:
+    assert "1:6" in str(e)
+"#);
+    }
+
+    #[test]
+    fn dumps_values() {
+        run(r#"
+point = ron_reboot.Struct(x=1, y=-2.5)
+point.name = "Point"
+assert ron_reboot.dumps(point) == "Point(x:1,y:-2.5)"
+assert ron_reboot.dumps([None, True, "a\"b", (1,), {"k": ron_reboot.Tagged("A")}]) == \
+    '[None,true,"a\\"b",(1),{"k":A}]'
+assert ron_reboot.dumps({"a": [1, 2], "b": []}, pretty=True) == """{
+    "a": [
+        1,
+        2,
+    ],
+    "b": [],
+}"""
+
+source = 'Level(name:"cave",spawn:Point(1,2),loot:{"gold":[1,2.5]})'
+assert ron_reboot.dumps(ron_reboot.loads(source)) == source
+
+for invalid in [object(), float("nan"), ron_reboot.Struct({"a b": 1})]:
+    try:
+        ron_reboot.dumps(invalid)
+        assert False
+    except (TypeError, ron_reboot.RonError):
+        pass
+"#);
+    }
+}
@@ -0,0 +1,319 @@
+//! `#[derive(RonSchema)]` for `ron_utils::schema::RonSchema`
+//!
+//! Structs describe their fields, unit-only enums their variants. The field
+//! docs are taken from `///` comments and constraints from `#[ron(..)]`
+//! attributes, e.g. `#[ron(min = 0, max = 100)]`, `#[ron(pattern = "^v\\d+$")]`,
+//! `#[ron(one_of = ["low", "high"])]` or `#[ron(non_empty)]`, and rules
+//! relating the fields from `#[ron(rule = "max_hp >= hp")]` on the struct.
+//! The serde attributes `rename`, `default` and `skip` are respected.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{
+    parse::{Parse, ParseStream},
+    parse_macro_input, parse_quote,
+    punctuated::Punctuated,
+    Attribute, Data, DeriveInput, ExprArray, ExprLit, ExprUnary, Fields, Ident, Lit, LitStr, Meta,
+    NestedMeta, Token, UnOp,
+};
+
+#[proc_macro_derive(RonSchema, attributes(ron, serde))]
+pub fn derive_ron_schema(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(mut input: DeriveInput) -> syn::Result<TokenStream> {
+    for param in input.generics.type_params_mut() {
+        param
+            .bounds
+            .push(parse_quote!(::ron_utils::schema::RonSchema));
+    }
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let container = SerdeAttrs::parse(&input.attrs)?;
+    let name = container.rename.unwrap_or_else(|| ident.to_string());
+    let rules = rules(&input.attrs)?;
+
+    let schema = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => {
+                let mut field_schemas = vec![];
+                for field in &fields.named {
+                    let serde = SerdeAttrs::parse(&field.attrs)?;
+                    if serde.skip {
+                        continue;
+                    }
+
+                    let ident = field.ident.as_ref().expect("named field");
+                    let name = serde
+                        .rename
+                        .unwrap_or_else(|| ident.to_string().trim_start_matches("r#").to_owned());
+                    let ty = &field.ty;
+                    let doc = match doc(&field.attrs) {
+                        Some(doc) => quote!(::std::option::Option::Some(#doc.to_owned())),
+                        None => quote!(::std::option::Option::None),
+                    };
+                    let default = container.default || serde.default;
+                    let constraints = constraints(&field.attrs)?;
+
+                    field_schemas.push(quote! {{
+                        let mut field = ::ron_utils::schema::Field::new(
+                            #name,
+                            <#ty as ::ron_utils::schema::RonSchema>::schema(),
+                        );
+                        field.doc = #doc;
+                        field.default = #default;
+                        field.constraints =
+                            <#ty as ::ron_utils::schema::RonSchema>::constraints();
+                        field.constraints.extend(::std::vec![#(#constraints),*]);
+
+                        field
+                    }});
+                }
+
+                quote! {
+                    ::ron_utils::schema::Schema::Struct(::ron_utils::schema::StructSchema {
+                        name: ::std::option::Option::Some(#name.to_owned()),
+                        fields: ::std::vec![#(#field_schemas),*],
+                        rules: ::std::vec![#(
+                            ::std::str::FromStr::from_str(#rules)
+                                .expect("rules of `#[ron(rule = \"...\")]` are valid")
+                        ),*],
+                    })
+                }
+            }
+            Fields::Unnamed(fields) => {
+                let types = fields.unnamed.iter().map(|field| &field.ty);
+
+                quote! {
+                    ::ron_utils::schema::Schema::Tuple(::std::vec![
+                        #(<#types as ::ron_utils::schema::RonSchema>::schema()),*
+                    ])
+                }
+            }
+            Fields::Unit => quote!(::ron_utils::schema::Schema::Tuple(::std::vec![])),
+        },
+        Data::Enum(data) => {
+            if data
+                .variants
+                .iter()
+                .all(|variant| matches!(variant.fields, Fields::Unit))
+            {
+                let mut variants = vec![];
+                for variant in &data.variants {
+                    let serde = SerdeAttrs::parse(&variant.attrs)?;
+                    if !serde.skip {
+                        variants.push(serde.rename.unwrap_or_else(|| variant.ident.to_string()));
+                    }
+                }
+
+                quote! {
+                    ::ron_utils::schema::Schema::Enum(::std::vec![#(#variants.to_owned()),*])
+                }
+            } else {
+                // Enums with data can't be described by a schema yet
+                quote!(::ron_utils::schema::Schema::Any)
+            }
+        }
+        Data::Union(data) => {
+            return Err(syn::Error::new(
+                data.union_token.span,
+                "`RonSchema` can't be derived for unions",
+            ))
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::ron_utils::schema::RonSchema for #ident #ty_generics #where_clause {
+            fn schema() -> ::ron_utils::schema::Schema {
+                #schema
+            }
+        }
+    })
+}
+
+/// The `///` comments of an item, joined by newlines
+fn doc(attrs: &[Attribute]) -> Option<String> {
+    let lines = attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("doc"))
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(Meta::NameValue(meta)) => match meta.lit {
+                Lit::Str(s) => Some(s.value().trim().to_owned()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    match lines.is_empty() {
+        true => None,
+        false => Some(lines.join("\n")),
+    }
+}
+
+/// One constraint of a `#[ron(..)]` attribute, e.g. `min = 0` or `non_empty`
+enum ConstraintAttr {
+    Min(syn::Expr),
+    Max(syn::Expr),
+    Pattern(LitStr),
+    OneOf(Vec<String>),
+    NonEmpty,
+}
+
+impl Parse for ConstraintAttr {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let kind: Ident = input.parse()?;
+        if kind == "non_empty" {
+            return Ok(ConstraintAttr::NonEmpty);
+        }
+        input.parse::<Token![=]>()?;
+
+        match kind.to_string().as_str() {
+            "min" => Ok(ConstraintAttr::Min(input.parse()?)),
+            "max" => Ok(ConstraintAttr::Max(input.parse()?)),
+            "pattern" => Ok(ConstraintAttr::Pattern(input.parse()?)),
+            "one_of" => {
+                let array: ExprArray = input.parse()?;
+
+                array
+                    .elems
+                    .iter()
+                    .map(literal)
+                    .collect::<syn::Result<_>>()
+                    .map(ConstraintAttr::OneOf)
+            }
+            _ => Err(syn::Error::new(
+                kind.span(),
+                "expected `min`, `max`, `pattern`, `one_of` or `non_empty`",
+            )),
+        }
+    }
+}
+
+/// The text of an option of `one_of`, e.g. `"low"`, `-1` or `true`
+fn literal(expr: &syn::Expr) -> syn::Result<String> {
+    match expr {
+        syn::Expr::Lit(ExprLit {
+            lit: Lit::Str(s), ..
+        }) => Ok(s.value()),
+        syn::Expr::Lit(ExprLit { lit, .. }) => Ok(quote!(#lit).to_string()),
+        syn::Expr::Unary(ExprUnary {
+            op: UnOp::Neg(_),
+            expr,
+            ..
+        }) => literal(expr).map(|literal| format!("-{}", literal)),
+        syn::Expr::Path(path) => path
+            .path
+            .get_ident()
+            .map(Ident::to_string)
+            .ok_or_else(|| syn::Error::new_spanned(expr, "expected a literal")),
+        _ => Err(syn::Error::new_spanned(expr, "expected a literal")),
+    }
+}
+
+fn constraints(attrs: &[Attribute]) -> syn::Result<Vec<TokenStream>> {
+    let mut constraints = vec![];
+
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("ron")) {
+        for constraint in
+            attr.parse_args_with(Punctuated::<ConstraintAttr, Token![,]>::parse_terminated)?
+        {
+            constraints.push(match constraint {
+                ConstraintAttr::Min(min) => {
+                    quote!(::ron_utils::schema::Constraint::Min((#min) as f64))
+                }
+                ConstraintAttr::Max(max) => {
+                    quote!(::ron_utils::schema::Constraint::Max((#max) as f64))
+                }
+                ConstraintAttr::Pattern(pattern) => {
+                    quote!(::ron_utils::schema::Constraint::Pattern(#pattern.to_owned()))
+                }
+                ConstraintAttr::OneOf(options) => quote! {
+                    ::ron_utils::schema::Constraint::OneOf(::std::vec![#(#options.to_owned()),*])
+                },
+                ConstraintAttr::NonEmpty => quote!(::ron_utils::schema::Constraint::NonEmpty),
+            });
+        }
+    }
+
+    Ok(constraints)
+}
+
+/// `rule = "..."` of a `#[ron(..)]` attribute of a struct
+struct RuleAttr(LitStr);
+
+impl Parse for RuleAttr {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let kind: Ident = input.parse()?;
+        if kind != "rule" {
+            return Err(syn::Error::new(kind.span(), "expected `rule`"));
+        }
+        input.parse::<Token![=]>()?;
+
+        Ok(RuleAttr(input.parse()?))
+    }
+}
+
+fn rules(attrs: &[Attribute]) -> syn::Result<Vec<LitStr>> {
+    let mut rules = vec![];
+
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("ron")) {
+        for RuleAttr(rule) in
+            attr.parse_args_with(Punctuated::<RuleAttr, Token![,]>::parse_terminated)?
+        {
+            rules.push(rule);
+        }
+    }
+
+    Ok(rules)
+}
+
+/// The serde attributes that affect the RON representation
+#[derive(Default)]
+struct SerdeAttrs {
+    rename: Option<String>,
+    default: bool,
+    skip: bool,
+}
+
+impl SerdeAttrs {
+    fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut serde = SerdeAttrs::default();
+
+        for attr in attrs.iter().filter(|attr| attr.path.is_ident("serde")) {
+            let list = match attr.parse_meta()? {
+                Meta::List(list) => list,
+                _ => continue,
+            };
+
+            for nested in list.nested {
+                match nested {
+                    NestedMeta::Meta(Meta::NameValue(meta)) if meta.path.is_ident("rename") => {
+                        if let Lit::Str(s) = meta.lit {
+                            serde.rename = Some(s.value());
+                        }
+                    }
+                    NestedMeta::Meta(Meta::Path(path)) if path.is_ident("default") => {
+                        serde.default = true;
+                    }
+                    NestedMeta::Meta(Meta::NameValue(meta)) if meta.path.is_ident("default") => {
+                        serde.default = true;
+                    }
+                    NestedMeta::Meta(Meta::Path(path))
+                        if path.is_ident("skip") || path.is_ident("skip_deserializing") =>
+                    {
+                        serde.skip = true;
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(serde)
+    }
+}
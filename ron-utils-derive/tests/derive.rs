@@ -0,0 +1,105 @@
+use ron_utils::{
+    schema::{Constraint, Field, RonSchema, Schema, StructSchema},
+    validate::validate_schema_str,
+};
+
+#[allow(dead_code)]
+#[derive(RonSchema)]
+enum Kind {
+    Melee,
+    #[serde(rename = "Bow")]
+    Ranged,
+}
+
+#[allow(dead_code)]
+#[derive(RonSchema)]
+#[ron(rule = "hp >= level")]
+struct Unit {
+    /// Display name
+    #[ron(non_empty, pattern = "^[a-z]+$")]
+    name: String,
+    #[ron(min = 1, max = 100)]
+    hp: u8,
+    #[serde(default)]
+    speed: f32,
+    kind: Kind,
+    #[ron(one_of = [-1, 2.5, "x"])]
+    level: i64,
+    #[serde(skip)]
+    cache: Vec<u8>,
+    position: Option<(i32, i32)>,
+}
+
+#[test]
+fn schema() {
+    let mut name = Field::new("name", Schema::String);
+    name.doc = Some("Display name".to_owned());
+    name.constraints = vec![
+        Constraint::NonEmpty,
+        Constraint::Pattern("^[a-z]+$".to_owned()),
+    ];
+    let mut hp = Field::new("hp", Schema::Integer);
+    hp.constraints = vec![
+        Constraint::Min(0.0),
+        Constraint::Max(255.0),
+        Constraint::Min(1.0),
+        Constraint::Max(100.0),
+    ];
+    let mut speed = Field::new("speed", Schema::Float);
+    speed.default = true;
+    let mut level = Field::new("level", Schema::Integer);
+    level.constraints = vec![Constraint::OneOf(vec![
+        "-1".to_owned(),
+        "2.5".to_owned(),
+        "x".to_owned(),
+    ])];
+    let position = Field::new(
+        "position",
+        Schema::Option(Box::new(Schema::Tuple(vec![
+            Schema::Integer,
+            Schema::Integer,
+        ]))),
+    );
+
+    assert_eq!(
+        Unit::schema(),
+        Schema::Struct(StructSchema {
+            name: Some("Unit".to_owned()),
+            fields: vec![
+                name,
+                hp,
+                speed,
+                Field::new(
+                    "kind",
+                    Schema::Enum(vec!["Melee".to_owned(), "Bow".to_owned()])
+                ),
+                level,
+                position,
+            ],
+            rules: vec!["hp >= level".parse().unwrap()],
+        })
+    );
+}
+
+#[test]
+fn validate() {
+    let messages = validate_schema_str(
+        &Unit::schema(),
+        "Unit(name: \"Orc\", hp: 0, kind: Ranged, level: 2.5, position: Some((1, 2)))",
+    )
+    .unwrap()
+    .into_iter()
+    .map(|v| v.message)
+    .collect::<Vec<_>>();
+
+    assert_eq!(
+        messages,
+        [
+            "`name` must match `^[a-z]+$` [pattern]",
+            "`hp` must be at least 1, found 0 [min]",
+            "unknown variant `Ranged`, expected one of `Melee`, `Bow`",
+            "expected integer, found float",
+            "`hp >= level` doesn't hold, `hp` is 0 and `level` is 2.5 [rule]",
+        ]
+    );
+}
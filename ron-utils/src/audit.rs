@@ -0,0 +1,447 @@
+//! Which fields of a [`Schema`] a corpus of documents uses, e.g. to find
+//! fields that can be removed from the schema of a long-lived project
+//!
+//! Fields are named by their path from the root, like value paths, except
+//! that `*` stands for any element of a list or any value of a map, e.g.
+//! `layers.*.name`. Fields the schema doesn't know are recorded with a few
+//! places they're written at.
+
+use std::{collections::HashMap, fmt, mem};
+
+use ron_reboot::{
+    ast::{Expr, Ron, Spanned, Struct},
+    path::Step,
+    utf8_parser::ast_from_str,
+    Error, Location,
+};
+
+use crate::{
+    schema::{RonSchema, Schema, StructSchema},
+    validate::implicit_some,
+};
+
+/// How many places are kept for each unknown field
+pub const MAX_EXAMPLES: usize = 3;
+
+/// A field of the schema and how often it is written
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldUse {
+    /// The path of the field, e.g. `window.width`
+    pub path: String,
+    pub uses: usize,
+}
+
+/// A field the schema doesn't have
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnknownField {
+    /// The path of the field, e.g. `window.colour`
+    pub path: String,
+    pub uses: usize,
+    /// The first [`MAX_EXAMPLES`] places the field is written at
+    pub examples: Vec<FieldLocation>,
+}
+
+/// Where a field is written: the file and the span of its name
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldLocation {
+    pub file: String,
+    pub start: Location,
+    pub end: Location,
+}
+
+impl fmt::Display for FieldLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.file, self.start)
+    }
+}
+
+/// How often the documents of a corpus use each field of a schema
+#[derive(Clone, Debug)]
+pub struct FieldAudit {
+    schema: Schema,
+    /// The fields of the schema, in the order they're declared
+    pub fields: Vec<FieldUse>,
+    /// The fields that aren't in the schema, in the order they were found
+    pub unknown: Vec<UnknownField>,
+    /// How many documents were recorded
+    pub documents: usize,
+    /// The positions of paths in `fields` and `unknown`
+    field_index: HashMap<String, usize>,
+    unknown_index: HashMap<String, usize>,
+}
+
+impl FieldAudit {
+    pub fn new(schema: Schema) -> Self {
+        let mut fields = vec![];
+        collect_fields(&schema, "", &mut fields);
+        let field_index = fields
+            .iter()
+            .enumerate()
+            .map(|(i, field)| (field.path.clone(), i))
+            .collect();
+
+        FieldAudit {
+            schema,
+            fields,
+            unknown: vec![],
+            documents: 0,
+            field_index,
+            unknown_index: HashMap::new(),
+        }
+    }
+
+    /// An audit of the fields of `T`
+    pub fn of<T: RonSchema + ?Sized>() -> Self {
+        Self::new(T::schema())
+    }
+
+    /// Records the fields `source`, read from `file`, uses; nothing is
+    /// recorded if it is invalid
+    pub fn add_str(&mut self, file: &str, source: &str) -> Result<(), Error> {
+        self.add_ron(file, &ast_from_str(source)?);
+
+        Ok(())
+    }
+
+    /// Records the fields of `ron`, read from `file`
+    pub fn add_ron(&mut self, file: &str, ron: &Ron) {
+        // Taken out while the counts are updated, and put back afterwards
+        let schema = mem::replace(&mut self.schema, Schema::Any);
+        let mut record = Record {
+            file,
+            implicit_some: implicit_some(ron),
+            audit: self,
+        };
+        record.value(&schema, "", &ron.expr);
+        self.schema = schema;
+        self.documents += 1;
+    }
+
+    /// The fields of the schema that no document uses
+    pub fn unused(&self) -> impl Iterator<Item = &FieldUse> {
+        self.fields.iter().filter(|field| field.uses == 0)
+    }
+
+    /// Displays only the unused and unknown fields, followed by the summary
+    pub fn unused_report(&self) -> Unused<'_> {
+        Unused(self)
+    }
+
+    fn write(&self, f: &mut fmt::Formatter<'_>, only_unused: bool) -> fmt::Result {
+        writeln!(f, "Fields")?;
+        for field in &self.fields {
+            match field.uses {
+                0 => writeln!(f, "  {:<30}{:>8}", field.path, "never")?,
+                _ if only_unused => {}
+                uses => writeln!(f, "  {:<30}{:>8}", field.path, uses)?,
+            }
+        }
+
+        if !self.unknown.is_empty() {
+            writeln!(f, "Unknown fields")?;
+        }
+        for field in &self.unknown {
+            writeln!(f, "  {:<30}{:>8}", field.path, field.uses)?;
+            for example in &field.examples {
+                writeln!(f, "    {}", example)?;
+            }
+        }
+
+        writeln!(
+            f,
+            "\n{} of {} fields used and {} unknown fields in {} documents",
+            self.fields.len() - self.unused().count(),
+            self.fields.len(),
+            self.unknown.len(),
+            self.documents
+        )
+    }
+}
+
+impl fmt::Display for FieldAudit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, false)
+    }
+}
+
+/// See [`FieldAudit::unused_report`]
+pub struct Unused<'a>(&'a FieldAudit);
+
+impl fmt::Display for Unused<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.write(f, true)
+    }
+}
+
+/// `path` followed by the field `name`, which is quoted if needed
+fn join(path: &str, name: &str) -> String {
+    let step = match name {
+        "*" => name.to_owned(),
+        _ => Step::Key(name.to_owned()).to_string(),
+    };
+    extend(path, step)
+}
+
+/// `path` followed by the tuple element `i`
+fn join_index(path: &str, i: usize) -> String {
+    extend(path, Step::Index(i).to_string())
+}
+
+fn extend(path: &str, step: String) -> String {
+    match path {
+        "" => step,
+        _ => format!("{}.{}", path, step),
+    }
+}
+
+/// Adds the struct fields of `schema` to `fields`, with `path` leading to it
+fn collect_fields(schema: &Schema, path: &str, fields: &mut Vec<FieldUse>) {
+    match schema {
+        Schema::Option(inner) => collect_fields(inner, path, fields),
+        Schema::List(element) => collect_fields(element, &join(path, "*"), fields),
+        Schema::Map(_, value) => collect_fields(value, &join(path, "*"), fields),
+        Schema::Tuple(elements) => {
+            for (i, element) in elements.iter().enumerate() {
+                collect_fields(element, &join_index(path, i), fields);
+            }
+        }
+        Schema::Struct(s) => {
+            for field in &s.fields {
+                let path = join(path, &field.name);
+                fields.push(FieldUse {
+                    path: path.clone(),
+                    uses: 0,
+                });
+                collect_fields(&field.schema, &path, fields);
+            }
+        }
+        Schema::Any
+        | Schema::Bool
+        | Schema::Integer
+        | Schema::Float
+        | Schema::String
+        | Schema::Enum(_) => {}
+    }
+}
+
+/// Records the fields of one document
+struct Record<'f, 'a> {
+    file: &'f str,
+    implicit_some: bool,
+    audit: &'a mut FieldAudit,
+}
+
+impl Record<'_, '_> {
+    /// Records the fields of `expr`, which `schema` describes; values that
+    /// don't match it aren't looked into
+    fn value(&mut self, schema: &Schema, path: &str, expr: &Spanned<Expr>) {
+        match (schema, &expr.value) {
+            (Schema::Option(inner), Expr::Optional(Some(value))) => self.value(inner, path, value),
+            (Schema::Option(inner), _) if self.implicit_some => self.value(inner, path, expr),
+            (Schema::List(element), Expr::List(l)) => {
+                let path = join(path, "*");
+                for e in &l.elements {
+                    self.value(element, &path, e);
+                }
+            }
+            (Schema::Map(_, value), Expr::Map(m)) => {
+                let path = join(path, "*");
+                for entry in &m.entries {
+                    self.value(value, &path, &entry.value.value);
+                }
+            }
+            (Schema::Tuple(elements), _) => {
+                if let Some(t) = expr.value.as_tuple() {
+                    for (i, (schema, e)) in elements.iter().zip(&t.elements).enumerate() {
+                        self.value(schema, &join_index(path, i), e);
+                    }
+                }
+            }
+            (Schema::Struct(schema), _) => {
+                if let Some(s) = expr.value.as_struct() {
+                    self.structure(schema, path, s);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn structure(&mut self, schema: &StructSchema, path: &str, s: &Struct) {
+        for field in &s.fields {
+            let key = &field.value.key;
+            let path = join(path, key.value.as_str());
+            match schema.field(key.value.as_str()) {
+                Some(field_schema) => {
+                    if let Some(&i) = self.audit.field_index.get(&path) {
+                        self.audit.fields[i].uses += 1;
+                    }
+                    self.value(&field_schema.schema, &path, &field.value.value);
+                }
+                None => {
+                    let location = FieldLocation {
+                        file: self.file.to_owned(),
+                        start: key.start,
+                        end: key.end,
+                    };
+                    self.unknown(path, location);
+                }
+            }
+        }
+    }
+
+    fn unknown(&mut self, path: String, location: FieldLocation) {
+        let audit = &mut *self.audit;
+        let i = match audit.unknown_index.get(&path) {
+            Some(&i) => i,
+            None => {
+                audit
+                    .unknown_index
+                    .insert(path.clone(), audit.unknown.len());
+                audit.unknown.push(UnknownField {
+                    path,
+                    uses: 0,
+                    examples: vec![],
+                });
+                audit.unknown.len() - 1
+            }
+        };
+
+        let field = &mut audit.unknown[i];
+        field.uses += 1;
+        if field.examples.len() < MAX_EXAMPLES {
+            field.examples.push(location);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::Field;
+
+    fn schema() -> Schema {
+        let layer = Schema::Struct(StructSchema {
+            name: Some("Layer".to_owned()),
+            fields: vec![
+                Field::new("name", Schema::String),
+                Field::new("opacity", Schema::Float),
+            ],
+            rules: vec![],
+        });
+
+        Schema::Struct(StructSchema {
+            name: Some("Config".to_owned()),
+            fields: vec![
+                Field::new("title", Schema::Option(Box::new(Schema::String))),
+                Field::new("layers", Schema::List(Box::new(layer.clone()))),
+                Field::new(
+                    "named",
+                    Schema::Map(Box::new(Schema::String), Box::new(layer)),
+                ),
+                Field::new("legacy", Schema::Bool),
+            ],
+            rules: vec![],
+        })
+    }
+
+    fn paths(audit: &FieldAudit) -> Vec<(&str, usize)> {
+        audit
+            .fields
+            .iter()
+            .map(|field| (field.path.as_str(), field.uses))
+            .collect()
+    }
+
+    #[test]
+    fn records_fields() {
+        let mut audit = FieldAudit::new(schema());
+        audit
+            .add_str(
+                "a.ron",
+                r#"Config(
+    title: Some("a"),
+    layers: [Layer(name: "x", colour: 1), (name: "y", opacity: 0.5)],
+    colour: 2,
+)"#,
+            )
+            .unwrap();
+        audit
+            .add_str(
+                "b.ron",
+                "#![enable(implicit_some)]\n(title: \"b\", named: {\"z\": (colour: 3)})",
+            )
+            .unwrap();
+        assert!(audit.add_str("c.ron", "(title: ").is_err());
+
+        assert_eq!(
+            paths(&audit),
+            [
+                ("title", 2),
+                ("layers", 1),
+                ("layers.*.name", 2),
+                ("layers.*.opacity", 1),
+                ("named", 1),
+                ("named.*.name", 0),
+                ("named.*.opacity", 0),
+                ("legacy", 0),
+            ]
+        );
+        assert_eq!(audit.documents, 2);
+
+        let unknown = audit
+            .unknown
+            .iter()
+            .map(|field| (field.path.as_str(), field.uses))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            unknown,
+            [("layers.*.colour", 1), ("colour", 1), ("named.*.colour", 1)]
+        );
+        assert_eq!(audit.unknown[1].examples[0].to_string(), "a.ron:4:5");
+
+        let report = audit.to_string();
+        assert!(report.starts_with("Fields\n  title                                2\n"));
+        assert!(report.contains("  legacy                           never\n"));
+        assert!(report.contains(
+            "Unknown fields\n  layers.*.colour                      1\n    a.ron:3:31\n"
+        ));
+        assert!(report.ends_with("\n5 of 8 fields used and 3 unknown fields in 2 documents\n"));
+
+        let unused = audit.unused_report().to_string();
+        assert!(!unused.contains("title"));
+        assert!(unused.contains("named.*.name"));
+        assert!(unused.contains("Unknown fields"));
+    }
+
+    #[test]
+    fn examples_are_limited() {
+        let mut audit = FieldAudit::new(schema());
+        for i in 0..5 {
+            audit
+                .add_str(&format!("{}.ron", i), "(legacy: true, old: 1)")
+                .unwrap();
+        }
+
+        assert_eq!(audit.unknown.len(), 1);
+        assert_eq!(audit.unknown[0].uses, 5);
+        assert_eq!(audit.unknown[0].examples.len(), MAX_EXAMPLES);
+        assert_eq!(paths(&audit).last(), Some(&("legacy", 5)));
+    }
+
+    #[test]
+    fn tuple_paths() {
+        let schema = Schema::Tuple(vec![
+            Schema::Integer,
+            Schema::Struct(StructSchema {
+                name: None,
+                fields: vec![Field::new("a", Schema::Bool)],
+                rules: vec![],
+            }),
+        ]);
+        let mut audit = FieldAudit::new(schema);
+        audit.add_str("t.ron", "(1, (a: true, b: false))").unwrap();
+
+        assert_eq!(paths(&audit), [("1.a", 1)]);
+        assert_eq!(audit.unknown[0].path, "1.b");
+    }
+}
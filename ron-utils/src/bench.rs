@@ -0,0 +1,277 @@
+//! Timing the parser on sample documents, e.g. to compare machines or
+//! versions of the parser
+//!
+//! The samples are the same for every build, so timings of different builds
+//! can be compared directly.
+
+use std::{fmt, fmt::Write, time::Duration};
+
+use ron_reboot::{
+    utf8_parser::{
+        serde::{from_str_timed, Options},
+        Timings,
+    },
+    Error,
+};
+use serde::de::IgnoredAny;
+
+/// A document to time the parser on
+#[derive(Clone, Debug)]
+pub struct Sample {
+    pub name: String,
+    pub source: String,
+}
+
+/// The built-in samples: a game scene, lists of 1,000 and 100,000 tuples,
+/// deeply nested values, a document of mostly strings and one of short
+/// strings with escapes
+pub fn samples() -> Vec<Sample> {
+    let sample = |name: &str, source: String| Sample {
+        name: name.to_owned(),
+        source,
+    };
+
+    vec![
+        sample(
+            "game_scene",
+            include_str!("../bench/game_scene.ron").to_owned(),
+        ),
+        sample("large_list", large_list(1_000)),
+        // Long lists used to take quadratic time
+        sample("list_100k", large_list(100_000)),
+        sample("deeply_nested", deeply_nested(64)),
+        sample("string_heavy", string_heavy(500)),
+        sample("short_escapes", short_escapes(5_000)),
+    ]
+}
+
+/// How long each phase took for a sample, on average
+#[derive(Clone, Debug)]
+pub struct BenchResult {
+    pub name: String,
+    pub bytes: usize,
+    pub iterations: u32,
+    /// The mean of all iterations
+    pub timings: Timings,
+    /// How many times a load allocated, if the `alloc_counters` feature is
+    /// enabled and allocations are counted (see
+    /// `ron_reboot::alloc_counter`)
+    pub allocations: Option<u64>,
+}
+
+impl BenchResult {
+    /// Megabytes of the sample loaded per second
+    pub fn throughput(&self) -> f64 {
+        self.bytes as f64 / self.timings.total().as_secs_f64() / 1e6
+    }
+}
+
+/// Loads `sample` `iterations` times, deserializing it into
+/// [`IgnoredAny`] so that every value goes through the deserializer
+pub fn bench(sample: &Sample, iterations: u32) -> Result<BenchResult, Error> {
+    let iterations = iterations.max(1);
+    let mut sum = Timings::default();
+    for _ in 0..iterations {
+        let (value, timings) = from_str_timed::<IgnoredAny>(&sample.source, Options::default());
+        value.map_err(|e| e.context_file_name(sample.name.clone()))?;
+
+        sum.parse += timings.parse;
+        sum.convert += timings.convert;
+        sum.deserialize += timings.deserialize;
+        sum.backtracked += timings.backtracked;
+    }
+
+    Ok(BenchResult {
+        name: sample.name.clone(),
+        bytes: sample.source.len(),
+        iterations,
+        timings: Timings {
+            parse: sum.parse / iterations,
+            convert: sum.convert / iterations,
+            deserialize: sum.deserialize / iterations,
+            backtracked: sum.backtracked / iterations as usize,
+        },
+        allocations: allocations(sample),
+    })
+}
+
+/// Every load allocates the same, so one load is counted
+#[cfg(feature = "alloc_counters")]
+fn allocations(sample: &Sample) -> Option<u64> {
+    use ron_reboot::alloc_counter::{count_allocations, is_counting};
+
+    let load = || from_str_timed::<IgnoredAny>(&sample.source, Options::default());
+    is_counting().then(|| count_allocations(load).1.allocations)
+}
+
+#[cfg(not(feature = "alloc_counters"))]
+fn allocations(_sample: &Sample) -> Option<u64> {
+    None
+}
+
+/// The results as a table, one row per sample
+pub fn report(results: &[BenchResult]) -> String {
+    let width = results
+        .iter()
+        .map(|result| result.name.chars().count() + 2)
+        .fold(16, usize::max);
+    // Only with the `alloc_counters` feature
+    let allocations = results.iter().any(|result| result.allocations.is_some());
+    let mut table = format!(
+        "{:<width$}{:>10}{:>12}{:>12}{:>13}{:>12}{:>10}",
+        "sample",
+        "bytes",
+        "parse",
+        "convert",
+        "deserialize",
+        "total",
+        "MB/s",
+        width = width
+    );
+    if allocations {
+        let _ = write!(table, "{:>10}", "allocs");
+    }
+    table.push('\n');
+    for result in results {
+        let _ = write!(
+            table,
+            "{:<width$}{:>10}{:>12}{:>12}{:>13}{:>12}{:>10.1}",
+            result.name,
+            result.bytes,
+            Millis(result.timings.parse),
+            Millis(result.timings.convert),
+            Millis(result.timings.deserialize),
+            Millis(result.timings.total()),
+            result.throughput(),
+            width = width
+        );
+        if allocations {
+            let count = result.allocations.map(|count| count.to_string());
+            let _ = write!(table, "{:>10}", count.as_deref().unwrap_or("-"));
+        }
+        table.push('\n');
+    }
+
+    table
+}
+
+/// A duration in milliseconds, with the same unit in every row
+struct Millis(Duration);
+
+impl fmt::Display for Millis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(&format!("{:.3}ms", self.0.as_secs_f64() * 1e3))
+    }
+}
+
+fn large_list(len: usize) -> String {
+    let mut source = String::from("[\n");
+    for i in 0..len {
+        let _ = writeln!(
+            source,
+            "    ({}, {}.5, \"item_{}\", {}),",
+            i,
+            i % 1000,
+            i,
+            i % 3 == 0
+        );
+    }
+    source.push(']');
+
+    source
+}
+
+fn deeply_nested(depth: usize) -> String {
+    let mut source = String::new();
+    for i in 0..depth {
+        let _ = write!(source, "Node(id: {}, tags: [\"n{}\"], children: [", i, i);
+    }
+    source.push_str("Leaf");
+    source.push_str(&"])".repeat(depth));
+
+    source
+}
+
+fn string_heavy(len: usize) -> String {
+    let mut source = String::from("{\n");
+    for i in 0..len {
+        let _ = writeln!(
+            source,
+            "    \"key_{}\": (text: \"Line {} of the manual,\\n\\twith \\\"quotes\\\" and ünïcödé\", \
+             raw: r#\"C:\\path\\{}\"#),",
+            i, i, i
+        );
+    }
+    source.push('}');
+
+    source
+}
+
+/// Short strings with an escape each, which all need to be unescaped
+fn short_escapes(len: usize) -> String {
+    let mut source = String::from("[\n");
+    for i in 0..len {
+        let _ = writeln!(source, "    \"{}\\t{}\\n\",", i % 10, i % 7);
+    }
+    source.push(']');
+
+    source
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_load() {
+        // Smaller than the built-in ones, which are slow in debug builds
+        let sources = [
+            include_str!("../bench/game_scene.ron").to_owned(),
+            large_list(10),
+            deeply_nested(8),
+            string_heavy(10),
+            short_escapes(10),
+        ];
+        for source in sources {
+            let sample = Sample {
+                name: "sample".to_owned(),
+                source,
+            };
+            let result = bench(&sample, 2).unwrap();
+            assert_eq!(result.bytes, sample.source.len());
+            assert!(result.timings.parse > Duration::ZERO);
+        }
+        assert_eq!(samples().len(), 6);
+    }
+
+    #[test]
+    fn report_rows() {
+        let result = BenchResult {
+            name: "s".to_owned(),
+            bytes: 2_000_000,
+            iterations: 1,
+            timings: Timings {
+                parse: Duration::from_millis(500),
+                convert: Duration::from_millis(250),
+                deserialize: Duration::from_millis(250),
+                backtracked: 0,
+            },
+            allocations: None,
+        };
+        let report = report(std::slice::from_ref(&result));
+
+        assert_eq!(report.lines().count(), 2);
+        assert!(report
+            .lines()
+            .nth(1)
+            .unwrap()
+            .ends_with("1000.000ms       2.0"));
+
+        let report = super::report(&[BenchResult {
+            allocations: Some(1234),
+            ..result
+        }]);
+        assert!(report.lines().next().unwrap().ends_with("MB/s    allocs"));
+        assert!(report.lines().nth(1).unwrap().ends_with("2.0      1234"));
+    }
+}
@@ -0,0 +1,225 @@
+//! The terminal UI of `ron browse`
+
+use std::io::{self, stdout, Write};
+
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, List, ListItem, ListState, Paragraph},
+    DefaultTerminal, Frame,
+};
+use ron_utils::browse::Browser;
+
+const HELP: &str =
+    "↑↓ move  ←→ collapse/expand  enter toggle  / search  n/N next/previous  y copy path  q quit";
+
+struct App<'s> {
+    file: &'s str,
+    source: &'s str,
+    /// Byte offset of the start of every line
+    line_starts: Vec<usize>,
+    browser: Browser,
+    list: ListState,
+    /// The search being typed, if any
+    query: Option<String>,
+    status: String,
+}
+
+/// Shows `browser` until the user quits
+pub fn browse(file: &str, source: &str, browser: Browser) -> io::Result<()> {
+    let mut app = App {
+        file,
+        source,
+        line_starts: std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect(),
+        browser,
+        list: ListState::default(),
+        query: None,
+        status: HELP.to_owned(),
+    };
+
+    let mut terminal = ratatui::init();
+    let result = app.run(&mut terminal);
+    ratatui::restore();
+
+    result
+}
+
+impl App<'_> {
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        loop {
+            let mut page = 0;
+            terminal.draw(|frame| page = self.draw(frame))?;
+
+            let key = match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => key,
+                _ => continue,
+            };
+
+            if let Some(query) = &mut self.query {
+                match key.code {
+                    KeyCode::Enter => {
+                        let query = self.query.take().unwrap_or_default();
+                        let found = self.browser.search(&query);
+                        self.status = format!("{} found for `{}`", found, query);
+                    }
+                    KeyCode::Esc => {
+                        self.query = None;
+                        self.status = HELP.to_owned();
+                    }
+                    KeyCode::Backspace => {
+                        query.pop();
+                    }
+                    KeyCode::Char(c) => query.push(c),
+                    _ => {}
+                }
+                continue;
+            }
+
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Up | KeyCode::Char('k') => self.browser.move_by(-1),
+                KeyCode::Down | KeyCode::Char('j') => self.browser.move_by(1),
+                KeyCode::PageUp => self.browser.move_by(-page),
+                KeyCode::PageDown => self.browser.move_by(page),
+                KeyCode::Home => self.browser.move_by(isize::MIN / 2),
+                KeyCode::End => self.browser.move_by(isize::MAX / 2),
+                KeyCode::Left | KeyCode::Char('h') => self.browser.collapse(),
+                KeyCode::Right | KeyCode::Char('l') => self.browser.expand(),
+                KeyCode::Enter | KeyCode::Char(' ') => self.browser.toggle(),
+                KeyCode::Char('/') => self.query = Some(String::new()),
+                KeyCode::Char('n') => self.browser.next_match(true),
+                KeyCode::Char('N') => self.browser.next_match(false),
+                KeyCode::Char('y') => {
+                    let path = self.browser.tree.nodes[self.browser.selected()]
+                        .path
+                        .to_string();
+                    copy(&path)?;
+                    self.status = format!("copied `{}`", path);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Draws the tree, the source and doc comment of the selected value and
+    /// the status line, returning the number of rows of the tree
+    fn draw(&mut self, frame: &mut Frame) -> isize {
+        let [main, status] =
+            Layout::vertical([Constraint::Min(3), Constraint::Length(1)]).areas(frame.area());
+        let [tree, text] =
+            Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .areas(main);
+
+        let visible = self.browser.visible();
+        let items: Vec<ListItem> = visible
+            .iter()
+            .map(|&i| {
+                let node = &self.browser.tree.nodes[i];
+                let marker = match (node.children, self.browser.is_collapsed(i)) {
+                    (0, _) => "  ",
+                    (_, true) => "▸ ",
+                    (_, false) => "▾ ",
+                };
+
+                ListItem::new(format!(
+                    "{}{}{}",
+                    "  ".repeat(node.depth),
+                    marker,
+                    node.label
+                ))
+            })
+            .collect();
+        let selected = visible.iter().position(|&i| i == self.browser.selected());
+        self.list.select(selected);
+        frame.render_stateful_widget(
+            List::new(items)
+                .block(Block::bordered().title(self.file))
+                .highlight_style(Style::new().add_modifier(Modifier::REVERSED)),
+            tree,
+            &mut self.list,
+        );
+
+        let node = &self.browser.tree.nodes[self.browser.selected()];
+        let mut block = Block::bordered().title(format!("{} ({})", node.path, node.start));
+        if let Some(doc) = &node.doc {
+            block = block.title_bottom(doc.replace('\n', " "));
+        }
+        frame.render_widget(
+            Paragraph::new(self.source_lines(node.bytes, text)).block(block),
+            text,
+        );
+
+        let status_line = match &self.query {
+            Some(query) => format!("/{}", query),
+            None => self.status.clone(),
+        };
+        frame.render_widget(Paragraph::new(status_line), status);
+
+        tree.height.saturating_sub(2) as isize
+    }
+
+    /// The lines of the source around `bytes`, which are highlighted
+    fn source_lines(&self, (start, end): (usize, usize), area: Rect) -> Vec<Line<'_>> {
+        let first = self.line_starts.partition_point(|&i| i <= start) - 1;
+        let height = area.height.saturating_sub(2) as usize;
+        let top = first.saturating_sub(height / 3);
+
+        let highlight = Style::new().add_modifier(Modifier::REVERSED);
+        (top..self.line_starts.len())
+            .take(height)
+            .map(|line| {
+                let line_start = self.line_starts[line];
+                let line_end = self
+                    .line_starts
+                    .get(line + 1)
+                    .map_or(self.source.len(), |&next| next - 1);
+                let text = |from: usize, to: usize| {
+                    self.source[from..to].trim_end_matches('\r').to_owned()
+                };
+                let (from, to) = (
+                    start.clamp(line_start, line_end),
+                    end.clamp(line_start, line_end),
+                );
+
+                Line::from(vec![
+                    Span::raw(text(line_start, from)),
+                    Span::styled(text(from, to), highlight),
+                    Span::raw(text(to, line_end)),
+                ])
+            })
+            .collect()
+    }
+}
+
+/// Copies `text` to the clipboard with the OSC 52 escape sequence, which
+/// most terminals support, also over SSH
+fn copy(text: &str) -> io::Result<()> {
+    let mut stdout = stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", base64(text.as_bytes()))?;
+
+    stdout.flush()
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char),
+                false => encoded.push('='),
+            }
+        }
+    }
+
+    encoded
+}
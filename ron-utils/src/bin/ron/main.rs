@@ -1,12 +1,136 @@
-use std::process::exit;
+use std::{
+    env::{current_dir, var_os},
+    fs,
+    io::{stdout, IsTerminal, Write},
+    path::Path,
+    process::exit,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+    time::Instant,
+};
+
+use ron_reboot::{
+    ast::Ron,
+    utf8_parser::{
+        ast_from_input_timed, ast_from_str, extension::ExtensionRegistry, validate_input_timed,
+        Input, Timings,
+    },
+    ErrorKind,
+};
+use ron_utils::{
+    audit::FieldAudit,
+    bench::{bench, report, samples, Sample},
+    browse::{Browser, Tree},
+    checksum::{sign_str, verify_str},
+    complete::CompletionData,
+    config::Config,
+    convert::{convert_dir, ron_files, ConvertFormat, ConvertSummary},
+    corpus::{import, upstream_cases},
+    coverage::Coverage,
+    diff::render_token_diff,
+    doc::{document_str, DocFormat},
+    eval::eval_str,
+    format::{self, format_ron, Newline, Style},
+    head::read_head_file,
+    hook::{hooks_dir, install_pre_commit_hook},
+    infer::infer_schema,
+    lint::{apply_fixes, lint_str, StructNameStyle, UnitStyle},
+    merge::merge_str,
+    patch::{get, patch_str, Edit},
+    print_error, read_fs_string, render_diff,
+    schema::Schema,
+    spec::spec_check,
+    stats::stats_str,
+    template::template_schema,
+    validate::validate_schema,
+    write_fs_string, Error, ErrorFormat,
+};
+use structopt::{clap::AppSettings, StructOpt};
 
-use ron_utils::validate_file;
-use structopt::StructOpt;
+use crate::{
+    browse::browse,
+    print_opt::{FileLog, FileReport, PrintOpt},
+};
 
-use crate::print_opt::PrintOpt;
+/// Counts allocations for `ron bench`
+#[cfg(feature = "alloc_counters")]
+#[global_allocator]
+static ALLOCATOR: ron_reboot::alloc_counter::CountingAllocator =
+    ron_reboot::alloc_counter::CountingAllocator;
 
+#[cfg(feature = "browse")]
+mod browse;
 mod print_opt;
 
+/// Stands in for the terminal UI of `ron browse` without the `browse` feature
+#[cfg(not(feature = "browse"))]
+mod browse {
+    use std::io;
+
+    use ron_utils::browse::Browser;
+
+    pub fn browse(_: &str, _: &str, _: Browser) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "`ron` was built without the `browse` feature",
+        ))
+    }
+}
+
+/// Options of the commands that modify files in place
+#[derive(Debug, StructOpt)]
+struct WriteOpt {
+    #[structopt(long)]
+    /// Print a diff of the changes instead of writing them
+    dry_run: bool,
+    #[structopt(long, default_value = "in-place", possible_values = &WriteFormat::variants())]
+    /// Modify the files in place, or print the changes as a patch for e.g.
+    /// `git apply`
+    format: WriteFormat,
+    #[structopt(long, value_name = "SUFFIX", min_values = 0, require_equals = true)]
+    /// Copy the original file to its name with SUFFIX appended (`.bak` by
+    /// default) before modifying it
+    backup: Option<Option<String>>,
+}
+
+impl WriteOpt {
+    /// Whether files are modified, rather than their changes printed
+    fn in_place(&self) -> bool {
+        !self.dry_run && self.format == WriteFormat::InPlace
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum WriteFormat {
+    InPlace,
+    Patch,
+}
+
+impl WriteFormat {
+    fn variants() -> [&'static str; 2] {
+        ["in-place", "patch"]
+    }
+}
+
+impl FromStr for WriteFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "in-place" => Ok(WriteFormat::InPlace),
+            "patch" => Ok(WriteFormat::Patch),
+            _ => Err(format!(
+                "valid values: {}",
+                Self::variants().to_vec().join(", ")
+            )),
+        }
+    }
+}
+
 #[derive(Debug, StructOpt)]
 #[structopt(name = "ron-utils")]
 /// Rusty Object Notation (RON) utilities
@@ -19,10 +143,350 @@ enum Opt {
         #[structopt(long, required = false, default_value = "status-and-pretty-errors", possible_values = &PrintOpt::variants())]
         /// What to print
         print: PrintOpt,
+        #[structopt(long)]
+        /// Also check the files against this schema (see `schema infer`)
+        schema: Option<String>,
+        #[structopt(long)]
+        /// Print at most this many errors of each file
+        max_errors_per_file: Option<usize>,
+        #[structopt(long, name = "N")]
+        /// Only read and check the first N elements of each file's top-level
+        /// list (or struct, tuple or map), e.g. for a quick check of a huge
+        /// export
+        head: Option<usize>,
+        #[structopt(short, long, default_value = "1")]
+        /// Number of files to validate in parallel
+        jobs: usize,
+        #[structopt(long)]
+        /// Print how long parsing each file takes, per phase
+        timings: bool,
+        #[structopt(long)]
+        /// Show every "could not match .. because" line of syntax errors
+        /// instead of only the first and last ones, and the tokens that were
+        /// expected instead of e.g. "expected a value"
+        verbose_errors: bool,
+        #[structopt(long, default_value = "1", possible_values = &ErrorFormat::variants())]
+        /// The version of the error text to print, for tools that parse it;
+        /// it only changes with a new version
+        error_format: ErrorFormat,
+        #[structopt(long)]
+        /// Print one JSON object per line and file to stdout, with its `path`,
+        /// `duration_us`, `result` (`ok` or `error`) and `errors` (their
+        /// number), instead of the status and timings; errors still go to
+        /// stderr
+        log_json: bool,
         #[structopt(required = true)]
         /// The .ron files to validate
         files: Vec<String>,
     },
+    /// Check .ron file(s) for style issues (configured in `ron-utils.ron`)
+    Lint {
+        #[structopt(long)]
+        /// Apply fixes in place
+        fix: bool,
+        #[structopt(flatten)]
+        write: WriteOpt,
+        #[structopt(long, possible_values = &StructNameStyle::variants())]
+        /// Whether the root struct should be written with or without its name
+        struct_names: Option<StructNameStyle>,
+        #[structopt(long)]
+        /// Type name of the root expression
+        root_type: Option<String>,
+        #[structopt(long)]
+        /// Report unused and missing `#![enable(..)]`s of literal extensions
+        extensions: bool,
+        #[structopt(long)]
+        /// Report duplicate IDs, unknown IDs and cycles in references between
+        /// structs (`id` and `parent` unless configured)
+        references: bool,
+        #[structopt(long, possible_values = &UnitStyle::variants())]
+        /// How quantities like `1500ms` should be written: in the largest
+        /// unit with a whole amount, or as decimals like `1.5s`
+        units: Option<UnitStyle>,
+        #[structopt(required = true)]
+        /// The .ron files to check
+        files: Vec<String>,
+    },
+    /// Format .ron file(s) with one element of lists, maps and structs per
+    /// line; files with comments other than doc comments are left alone
+    Fmt {
+        #[structopt(long)]
+        /// Keep the indentation and trailing commas each file uses, and print
+        /// what was found, instead of using four spaces and trailing commas
+        detect_style: bool,
+        #[structopt(long, default_value = "preserve", possible_values = &Newline::variants())]
+        /// The line breaks to write; `preserve` keeps the ones each file
+        /// mostly uses
+        newline: Newline,
+        #[structopt(flatten)]
+        write: WriteOpt,
+        #[structopt(required = true)]
+        /// The .ron files to format
+        files: Vec<String>,
+    },
+    /// Generate reference documentation from an example .ron file
+    Doc {
+        #[structopt(long, default_value = "markdown", possible_values = &DocFormat::variants())]
+        format: DocFormat,
+        #[structopt(short, long)]
+        /// Write the documentation to this file instead of stdout (`-`)
+        output: Option<String>,
+        /// The example .ron file
+        file: String,
+    },
+    /// Generate a skeleton .ron file with all fields of a schema, their docs
+    /// and constraints as comments, and placeholder values
+    New {
+        #[structopt(long)]
+        /// The schema of the file (see `schema infer`)
+        schema: String,
+        #[structopt(short, long)]
+        /// Write the skeleton to this file instead of stdout (`-`)
+        output: Option<String>,
+        #[structopt(long)]
+        /// Replace an existing output file
+        force: bool,
+    },
+    /// Write the struct names, fields (with their docs) and enum variants of
+    /// a schema as JSON, for editor plugins that don't use the language
+    /// server
+    ExportCompletions {
+        #[structopt(long)]
+        /// The schema to export (see `schema infer`)
+        schema: String,
+        #[structopt(long = "type", name = "NAME")]
+        /// Only export the struct of this name and what it contains
+        ty: Option<String>,
+        #[structopt(short, long)]
+        /// Write the JSON to this file instead of stdout (`-`)
+        output: Option<String>,
+    },
+    /// Set a value in a .ron file, keeping its formatting and comments
+    #[structopt(setting = AppSettings::AllowNegativeNumbers)]
+    Set {
+        /// The .ron file to edit
+        file: String,
+        /// Path of the value, e.g. `window.width`, `layers.0` or `keys."a.b"`
+        path: String,
+        /// The new value (a RON expression)
+        value: String,
+        #[structopt(short, long)]
+        /// Write the edited file here, or to stdout (`-`), instead of in place
+        output: Option<String>,
+        #[structopt(flatten)]
+        write: WriteOpt,
+    },
+    /// Explore a .ron file as a tree whose values can be collapsed, searched
+    /// and copied as paths (needs the `browse` feature)
+    Browse {
+        /// The .ron file to explore
+        file: String,
+    },
+    /// Merge two versions of a .ron file field by field, as a git merge
+    /// driver
+    ///
+    /// Register it in `.gitattributes` with `*.ron merge=ron` and in the git
+    /// config with `git config merge.ron.driver "ron merge-driver %O %A %B"`.
+    /// Conflicts keep the value of ours after a comment with both versions,
+    /// and make the exit code 1.
+    MergeDriver {
+        /// The common ancestor
+        base: String,
+        /// The current version, which is replaced with the merged one unless
+        /// `--output` is given
+        ours: String,
+        /// The version merged into ours
+        theirs: String,
+        #[structopt(short, long)]
+        /// Write the merged file here, or to stdout (`-`)
+        output: Option<String>,
+    },
+    /// Print a value of a .ron file
+    Get {
+        /// The .ron file to read
+        file: String,
+        /// Path of the value, e.g. `window.width`, `layers.0` or `keys."a.b"`
+        path: String,
+    },
+    /// Convert .ron files, or directories of them, into a directory with the
+    /// same structure
+    Convert {
+        #[structopt(long, possible_values = &ConvertFormat::variants())]
+        /// The format to convert to
+        to: ConvertFormat,
+        #[structopt(long)]
+        /// The directory the converted files are written to
+        out_dir: String,
+        #[structopt(long)]
+        /// Also convert files whose output is newer than the .ron file
+        force: bool,
+        #[structopt(required = true)]
+        /// The .ron files and directories to convert
+        inputs: Vec<String>,
+    },
+    /// Print a .ron file as the deserializer sees it, with its extensions
+    /// resolved
+    Eval {
+        #[structopt(long)]
+        /// Resolve `implicit_some` with this schema (see `schema infer`)
+        schema: Option<String>,
+        /// The .ron file to print
+        file: String,
+    },
+    /// Summarize the structure of .ron file(s), e.g. to find out why one is large
+    Stats {
+        #[structopt(long, name = "N")]
+        /// Only read and summarize the first N elements of each file's
+        /// top-level list (or struct, tuple or map)
+        head: Option<usize>,
+        #[structopt(required = true)]
+        /// The .ron files to summarize
+        files: Vec<String>,
+    },
+    /// Time parsing and deserializing sample documents, to compare machines
+    /// and versions
+    Bench {
+        #[structopt(short, long, default_value = "5")]
+        /// How many times to load each document; the timings are the mean
+        iterations: u32,
+        /// .ron files to time instead of the built-in samples
+        files: Vec<String>,
+    },
+    /// Work with schemas
+    Schema(SchemaOpt),
+    /// Set up git hooks for the repository in the current directory
+    Hook(HookOpt),
+    /// Work with the regression corpus (`tests/corpus`)
+    Corpus(CorpusOpt),
+    /// Report which constructs of the RON grammar the parser supports
+    SpecCheck {
+        #[structopt(long)]
+        /// Only list the cases that don't conform
+        failures: bool,
+    },
+    /// Report which constructs of the RON grammar .ron files use, e.g. to
+    /// find out what a test corpus never exercises
+    Coverage {
+        #[structopt(long)]
+        /// Only list the constructs no file uses
+        uncovered: bool,
+        #[structopt(required = true)]
+        /// The .ron files of the corpus; invalid ones are reported and skipped
+        files: Vec<String>,
+    },
+    /// Report which fields of a schema .ron files never use, and which
+    /// fields they use that the schema doesn't have, e.g. to clean up the
+    /// schema
+    AuditFields {
+        #[structopt(long)]
+        /// The schema to audit (see `schema infer`)
+        schema: String,
+        #[structopt(long)]
+        /// Only list the unused and unknown fields
+        unused: bool,
+        #[structopt(required = true)]
+        /// The .ron files, or directories searched for them; invalid ones
+        /// are reported and skipped
+        inputs: Vec<String>,
+    },
+    /// Show the changes between two .ron files, ignoring whitespace between
+    /// tokens; the files don't need to parse
+    Diff {
+        /// The old file
+        old: String,
+        /// The new file
+        new: String,
+    },
+    /// Add or update the checksum trailer (`// sha256:..`) of .ron file(s),
+    /// so that `ron verify` notices later edits
+    Sign {
+        #[structopt(flatten)]
+        key: KeyOpt,
+        #[structopt(flatten)]
+        write: WriteOpt,
+        #[structopt(required = true)]
+        /// The .ron files to sign
+        files: Vec<String>,
+    },
+    /// Check the checksum trailers of .ron file(s) written by `ron sign`
+    Verify {
+        #[structopt(flatten)]
+        key: KeyOpt,
+        #[structopt(required = true)]
+        /// The .ron files to verify
+        files: Vec<String>,
+    },
+}
+
+/// The key of `ron sign` and `ron verify`
+#[derive(Debug, StructOpt)]
+struct KeyOpt {
+    #[structopt(long, value_name = "VAR")]
+    /// Sign with an HMAC whose key is the value of this environment variable,
+    /// instead of a plain checksum anyone can update
+    key_env: Option<String>,
+}
+
+impl KeyOpt {
+    fn key(&self) -> Result<Option<Vec<u8>>, Error> {
+        let var = match &self.key_env {
+            Some(var) => var,
+            None => return Ok(None),
+        };
+        match var_os(var) {
+            Some(key) if !key.is_empty() => {
+                Ok(Some(key.to_string_lossy().into_owned().into_bytes()))
+            }
+            _ => Err(Error {
+                kind: ErrorKind::Custom(format!("the environment variable `{}` isn't set", var)),
+                context: None,
+            }),
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+enum SchemaOpt {
+    /// Generate a schema from example .ron files
+    Infer {
+        #[structopt(short, long)]
+        /// Write the schema to this file instead of stdout (`-`)
+        output: Option<String>,
+        #[structopt(required = true)]
+        /// The example .ron files
+        files: Vec<String>,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+enum HookOpt {
+    /// Install a pre-commit hook that validates and lints the staged .ron
+    /// files, using the `ron-utils.ron` of the repository
+    Install {
+        #[structopt(long)]
+        /// Replace an existing pre-commit hook
+        force: bool,
+        #[structopt(long, default_value = "ron")]
+        /// The command the hook runs the `ron` tool with
+        ron: String,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+enum CorpusOpt {
+    /// Import the inputs of the test suite of the original RON implementation
+    /// into `valid/` of the corpus, reporting the ones that don't parse
+    Import {
+        #[structopt(long, default_value = "tests/corpus")]
+        /// The corpus to import into
+        corpus: String,
+        #[structopt(long)]
+        /// The commit of the suite, recorded in the imported files
+        revision: Option<String>,
+        /// A checkout of https://github.com/ron-rs/ron, or its `tests`
+        /// directory
+        upstream: String,
+    },
 }
 
 fn main() {
@@ -32,23 +496,490 @@ fn main() {
         Opt::Validate {
             files,
             print,
+            schema,
+            max_errors_per_file,
+            head,
+            jobs,
+            timings,
+            verbose_errors,
+            error_format,
+            log_json,
             fail_fast,
         } => {
+            let output = FileOutput {
+                print,
+                max_errors: max_errors_per_file,
+                timings,
+                error_format,
+                log_json,
+            };
+            let check = match schema.map(Schema::from_file).transpose() {
+                Ok(schema) => FileCheck { schema, head },
+                Err(e) => {
+                    let _ = print_error(&e);
+                    exit(1);
+                }
+            };
+            let next = AtomicUsize::new(0);
+            // Whether any file had errors; with `--fail-fast`, nothing is
+            // printed after the first such file
+            let error = Mutex::new(false);
+
+            thread::scope(|scope| {
+                for _ in 0..jobs.clamp(1, files.len()) {
+                    scope.spawn(|| {
+                        while let Some(file) = files.get(next.fetch_add(1, Ordering::Relaxed)) {
+                            if fail_fast && *error.lock().unwrap() {
+                                break;
+                            }
+
+                            let (ok, report) =
+                                validate_file(file, &check, &output, verbose_errors);
+                            let mut error = error.lock().unwrap();
+                            if fail_fast && *error {
+                                break;
+                            }
+                            report.print();
+                            *error |= !ok;
+                        }
+                    });
+                }
+            });
+
+            if error.into_inner().unwrap() {
+                exit(1);
+            }
+        }
+        Opt::Lint {
+            fix,
+            write,
+            struct_names,
+            root_type,
+            extensions,
+            references,
+            units,
+            files,
+        } => {
+            let mut config = match current_dir()
+                .map_err(Error::from)
+                .and_then(Config::discover)
+            {
+                Ok(config) => config.map(|(_, config)| config).unwrap_or_default(),
+                Err(e) => {
+                    let _ = print_error(&e);
+                    exit(1);
+                }
+            };
+            if struct_names.is_some() {
+                config.lint.struct_names = struct_names;
+            }
+            if root_type.is_some() {
+                config.lint.root_type = root_type;
+            }
+            config.lint.extensions |= extensions;
+            if references && config.lint.references.is_none() {
+                config.lint.references = Some(Default::default());
+            }
+            if units.is_some() {
+                config.lint.units = units;
+            }
+
             let mut error = false;
 
             for file in &files {
-                match validate_file(file) {
-                    Ok(_) => {
-                        print.print_ok(file);
-                    }
+                match lint_file(file, &config, fix, &write) {
+                    Ok(clean) => error |= !clean,
                     Err(e) => {
-                        print.print_err(file);
-                        print.print_pretty_error(&e);
-                        if fail_fast {
-                            exit(1);
-                        } else {
-                            error = true;
+                        let _ = print_error(&e.context_file_name(file.to_owned()));
+                        error = true;
+                    }
+                }
+            }
+
+            if error {
+                exit(1);
+            }
+        }
+        Opt::Fmt {
+            detect_style,
+            newline,
+            write,
+            files,
+        } => {
+            let mut error = false;
+            for file in &files {
+                if let Err(e) = format_file(file, detect_style, newline, &write) {
+                    let _ = print_error(&e.context_file_name(file.to_owned()));
+                    error = true;
+                }
+            }
+
+            if error {
+                exit(1);
+            }
+        }
+        Opt::Doc {
+            format,
+            output,
+            file,
+        } => {
+            if let Err(e) = doc_file(&file, format, output.as_deref()) {
+                let _ = print_error(&e.context_file_name(file));
+                exit(1);
+            }
+        }
+        Opt::New {
+            schema,
+            output,
+            force,
+        } => {
+            if let Err(e) = new_file(&schema, output.as_deref(), force) {
+                let _ = print_error(&e);
+                exit(1);
+            }
+        }
+        Opt::ExportCompletions { schema, ty, output } => {
+            if let Err(e) = export_completions(&schema, ty.as_deref(), output.as_deref()) {
+                let _ = print_error(&e);
+                exit(1);
+            }
+        }
+        Opt::Set {
+            file,
+            path,
+            value,
+            output,
+            write,
+        } => {
+            let edits = [Edit::Set { path, value }];
+            let result = read_fs_string(&file).and_then(|source| {
+                let patched =
+                    patch_str(&source, &edits).map_err(|e| e.context_file_name(file.clone()))?;
+                match output {
+                    Some(output) if write.in_place() => write_output(Some(&output), &patched),
+                    _ => write_in_place(&file, &source, &patched, &write),
+                }
+            });
+            if let Err(e) = result {
+                let _ = print_error(&e);
+                exit(1);
+            }
+        }
+        Opt::MergeDriver {
+            base,
+            ours,
+            theirs,
+            output,
+        } => {
+            let merge = read_fs_string(&base).and_then(|base| {
+                merge_str(&base, &read_fs_string(&ours)?, &read_fs_string(&theirs)?)
+            });
+            let merge = match merge {
+                Ok(merge) => merge,
+                Err(e) => {
+                    let _ = print_error(&e);
+                    exit(2);
+                }
+            };
+
+            let output = output.unwrap_or(ours);
+            let written = match output.as_str() {
+                "-" => {
+                    print!("{}", merge.merged);
+                    Ok(())
+                }
+                output => write_fs_string(output, &merge.merged),
+            };
+            if let Err(e) = written {
+                let _ = print_error(&e);
+                exit(2);
+            }
+
+            for path in &merge.conflicts {
+                eprintln!("conflict at `{}`", path);
+            }
+            if !merge.conflicts.is_empty() {
+                exit(1);
+            }
+        }
+        Opt::Get { file, path } => {
+            let value = read_fs_string(&file).and_then(|source| {
+                get(&source, &path)
+                    .map(str::to_owned)
+                    .map_err(|e| e.context_file_name(file.clone()))
+            });
+            match value {
+                Ok(value) => println!("{}", value),
+                Err(e) => {
+                    let _ = print_error(&e);
+                    exit(1);
+                }
+            }
+        }
+        Opt::Browse { file } => {
+            let result = read_fs_string(&file).and_then(|source| {
+                let tree = Tree::parse(&source).map_err(|e| e.context_file_name(file.clone()))?;
+                browse(&file, &source, Browser::new(tree)).map_err(Error::from)
+            });
+            if let Err(e) = result {
+                let _ = print_error(&e);
+                exit(1);
+            }
+        }
+        Opt::Convert {
+            to,
+            out_dir,
+            force,
+            inputs,
+        } => {
+            let mut summary = ConvertSummary::default();
+            for input in &inputs {
+                convert_dir(
+                    Path::new(input),
+                    Path::new(&out_dir),
+                    to,
+                    force,
+                    &mut summary,
+                );
+            }
+
+            for e in &summary.errors {
+                let _ = print_error(e);
+            }
+            println!("{}", summary);
+            if !summary.errors.is_empty() {
+                exit(1);
+            }
+        }
+        Opt::Eval { schema, file } => {
+            let output = schema
+                .map(Schema::from_file)
+                .transpose()
+                .and_then(|schema| {
+                    let source = read_fs_string(&file)?;
+                    eval_str(&source, schema.as_ref())
+                        .map_err(|e| e.context_file_name(file.clone()))
+                });
+            match output {
+                Ok(output) => println!("{}", output),
+                Err(e) => {
+                    let _ = print_error(&e);
+                    exit(1);
+                }
+            }
+        }
+        Opt::Stats { files, head } => {
+            let mut error = false;
+
+            for (i, file) in files.iter().enumerate() {
+                let stats = read_source(file, head).and_then(|source| {
+                    stats_str(&source).map_err(|e| e.context_file_name(file.clone()))
+                });
+                match stats {
+                    Ok(stats) if files.len() == 1 => print!("{}", stats),
+                    Ok(stats) => {
+                        if i > 0 {
+                            println!();
                         }
+                        print!("{}:\n{}", file, stats);
+                    }
+                    Err(e) => {
+                        let _ = print_error(&e);
+                        error = true;
+                    }
+                }
+            }
+
+            if error {
+                exit(1);
+            }
+        }
+        Opt::Bench { iterations, files } => {
+            let samples = match files.is_empty() {
+                true => Ok(samples()),
+                false => files
+                    .iter()
+                    .map(|file| {
+                        read_fs_string(file).map(|source| Sample {
+                            name: file.clone(),
+                            source,
+                        })
+                    })
+                    .collect(),
+            };
+            let results = samples.and_then(|samples| {
+                samples
+                    .iter()
+                    .map(|sample| bench(sample, iterations))
+                    .collect::<Result<Vec<_>, _>>()
+            });
+            match results {
+                Ok(results) => print!("{}", report(&results)),
+                Err(e) => {
+                    let _ = print_error(&e);
+                    exit(1);
+                }
+            }
+        }
+        Opt::Schema(SchemaOpt::Infer { output, files }) => {
+            if let Err(e) = infer_schema_file(&files, output.as_deref()) {
+                let _ = print_error(&e);
+                exit(1);
+            }
+        }
+        Opt::Corpus(CorpusOpt::Import {
+            corpus,
+            revision,
+            upstream,
+        }) => {
+            let mut errors = vec![];
+            let cases = upstream_cases(Path::new(&upstream), &mut errors);
+            let report = match import(&cases, Path::new(&corpus), revision.as_deref()) {
+                Ok(report) => report,
+                Err(e) => {
+                    let _ = print_error(&e);
+                    exit(1);
+                }
+            };
+            for e in errors.iter().chain(&report.disagreements) {
+                let _ = print_error(e);
+            }
+            print!("{}", report);
+            if !errors.is_empty() || !report.disagreements.is_empty() {
+                exit(1);
+            }
+        }
+        Opt::Hook(HookOpt::Install { force, ron }) => {
+            let installed = current_dir()
+                .map_err(Error::from)
+                .and_then(|dir| hooks_dir(&dir))
+                .and_then(|hooks| install_pre_commit_hook(&hooks, &ron, force));
+            match installed {
+                Ok(path) => println!("installed {}", path.display()),
+                Err(e) => {
+                    let _ = print_error(&e);
+                    exit(1);
+                }
+            }
+        }
+        Opt::SpecCheck { failures } => {
+            let report = spec_check();
+            match failures {
+                true => print!("{}", report.failures()),
+                false => print!("{}", report),
+            }
+        }
+        Opt::Coverage { uncovered, files } => {
+            let mut coverage = Coverage::new();
+            let mut error = false;
+            for file in &files {
+                let added = read_fs_string(file).and_then(|source| {
+                    coverage
+                        .add_str(&source)
+                        .map_err(|e| e.context_file_name(file.clone()))
+                });
+                if let Err(e) = added {
+                    let _ = print_error(&e);
+                    error = true;
+                }
+            }
+
+            match uncovered {
+                true => print!("{}", coverage.uncovered_report()),
+                false => print!("{}", coverage),
+            }
+            if error {
+                exit(1);
+            }
+        }
+        Opt::AuditFields {
+            schema,
+            unused,
+            inputs,
+        } => {
+            let mut audit = FieldAudit::new(Schema::from_file(&schema).unwrap_or_else(|e| {
+                let _ = print_error(&e);
+                exit(1);
+            }));
+            let mut errors = vec![];
+            let files = inputs.iter().flat_map(|input| match Path::new(input).is_dir() {
+                true => ron_files(Path::new(input), &mut errors),
+                false => vec![input.into()],
+            });
+            let files = files.collect::<Vec<_>>();
+            for file in &files {
+                let name = file.display().to_string();
+                let added = read_fs_string(file).and_then(|source| {
+                    audit
+                        .add_str(&name, &source)
+                        .map_err(|e| e.context_file_name(name.clone()))
+                });
+                if let Err(e) = added {
+                    errors.push(e);
+                }
+            }
+
+            for e in &errors {
+                let _ = print_error(e);
+            }
+            match unused {
+                true => print!("{}", audit.unused_report()),
+                false => print!("{}", audit),
+            }
+            if !errors.is_empty() {
+                exit(1);
+            }
+        }
+        Opt::Diff { old, new } => {
+            let diffed = read_fs_string(&old).and_then(|old_source| {
+                let new_source = read_fs_string(&new)?;
+                let color = stdout().is_terminal() && var_os("NO_COLOR").is_none();
+                write_output(
+                    None,
+                    &render_token_diff(&new, &old_source, &new_source, color),
+                )
+            });
+            if let Err(e) = diffed {
+                let _ = print_error(&e);
+                exit(1);
+            }
+        }
+        Opt::Sign { key, write, files } => {
+            let key = key.key().unwrap_or_else(|e| {
+                let _ = print_error(&e);
+                exit(1);
+            });
+            let mut error = false;
+            for file in &files {
+                let signed = read_fs_string(file).and_then(|source| {
+                    write_in_place(file, &source, &sign_str(&source, key.as_deref()), &write)
+                });
+                if let Err(e) = signed {
+                    let _ = print_error(&e);
+                    error = true;
+                }
+            }
+
+            if error {
+                exit(1);
+            }
+        }
+        Opt::Verify { key, files } => {
+            let key = key.key().unwrap_or_else(|e| {
+                let _ = print_error(&e);
+                exit(1);
+            });
+            let mut error = false;
+            for file in &files {
+                let verified = read_fs_string(file).and_then(|source| {
+                    verify_str(&source, key.as_deref())
+                        .map_err(|e| e.context_file_name(file.clone()))
+                });
+                match verified {
+                    Ok(()) => println!("{}: OK", file),
+                    Err(e) => {
+                        let _ = print_error(&e);
+                        error = true;
                     }
                 }
             }
@@ -59,3 +990,307 @@ fn main() {
         }
     }
 }
+
+/// What `ron validate` checks in each file
+struct FileCheck {
+    schema: Option<Schema>,
+    /// Only check the first this many elements, see [`read_head_file`]
+    head: Option<usize>,
+}
+
+/// What `ron validate` prints for each file
+struct FileOutput {
+    print: PrintOpt,
+    /// See `--max-errors-per-file`
+    max_errors: Option<usize>,
+    timings: bool,
+    error_format: ErrorFormat,
+    /// Print a [`FileLog`] instead of the status and timings
+    log_json: bool,
+}
+
+/// Validates `file`, buffering what to print; returns whether it is valid.
+fn validate_file(
+    file: &str,
+    check: &FileCheck,
+    output: &FileOutput,
+    verbose_errors: bool,
+) -> (bool, FileReport) {
+    let start = Instant::now();
+    let mut report = FileReport::default();
+    let mut timings = Timings::default();
+    let errors = read_source(file, check.head).map(|source| match &check.schema {
+        Some(schema) => validate_schema_source(file, &source, schema, &mut timings, verbose_errors),
+        None => validate_syntax_source(file, &source, &mut timings, verbose_errors),
+    });
+    let errors = errors.unwrap_or_else(|e| vec![e]);
+    let ok = errors.is_empty();
+    // The log replaces the status lines
+    let status = match output.log_json {
+        true => &PrintOpt::PrettyErrors,
+        false => &output.print,
+    };
+
+    if ok {
+        status.print_ok(&mut report, file);
+    } else {
+        let shown = output.max_errors.unwrap_or(errors.len()).min(errors.len());
+        status.print_err(&mut report, file);
+        for e in &errors[..shown] {
+            output
+                .print
+                .print_pretty_error(&mut report, e, output.error_format);
+        }
+        if shown < errors.len() {
+            output
+                .print
+                .print_omitted(&mut report, file, errors.len() - shown);
+        }
+    }
+
+    if output.log_json {
+        report.print_log(&FileLog {
+            path: file,
+            duration_us: start.elapsed().as_micros(),
+            result: if ok { "ok" } else { "error" },
+            errors: errors.len(),
+        });
+    } else if output.timings {
+        report.print_timings(file, &timings);
+    }
+
+    (ok, report)
+}
+
+/// Reads `file`, or only its first `head` elements
+fn read_source(file: &str, head: Option<usize>) -> Result<String, Error> {
+    match head {
+        Some(n) => read_head_file(file, n).map(|head| head.source),
+        None => read_fs_string(file),
+    }
+}
+
+/// Returns the syntax errors of `source`, all of them for invalid list
+/// elements.
+///
+/// Valid sources are only scanned, not parsed into a tree.
+fn validate_syntax_source(
+    file: &str,
+    source: &str,
+    timings: &mut Timings,
+    verbose_errors: bool,
+) -> Vec<Error> {
+    let (valid, validate_timings) = validate_input_timed(
+        recovering_input(source, verbose_errors),
+        &ExtensionRegistry::new(),
+    );
+    *timings = validate_timings;
+
+    valid
+        .err()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|e| e.context_file_name(file.to_owned()))
+        .collect()
+}
+
+/// Returns the syntax errors of `source`, or its values that don't match
+/// `schema`.
+fn validate_schema_source(
+    file: &str,
+    source: &str,
+    schema: &Schema,
+    timings: &mut Timings,
+    verbose_errors: bool,
+) -> Vec<Error> {
+    let with_file_name = |errors: Vec<Error>| {
+        errors
+            .into_iter()
+            .map(|e| e.context_file_name(file.to_owned()))
+            .collect()
+    };
+    let ron = match parse_recovering(source, timings, verbose_errors) {
+        Ok(ron) => ron,
+        Err(errors) => return with_file_name(errors),
+    };
+
+    with_file_name(
+        validate_schema(schema, &ron)
+            .iter()
+            .map(|violation| violation.to_error(source))
+            .collect(),
+    )
+}
+
+/// Parses `source` like `ast_from_str_recovering`, measuring its phases
+fn parse_recovering<'a>(
+    source: &'a str,
+    timings: &mut Timings,
+    verbose_errors: bool,
+) -> Result<Ron<'a>, Vec<Error>> {
+    let input = recovering_input(source, verbose_errors);
+    let (ron, parse_timings) = ast_from_input_timed(input, &ExtensionRegistry::new());
+    *timings = parse_timings;
+
+    ron
+}
+
+/// `source` as input reporting the errors of all invalid list elements
+fn recovering_input(source: &str, verbose_errors: bool) -> Input<'_> {
+    let input = Input::new(source).with_recovery(true);
+    match verbose_errors {
+        true => input
+            .with_max_error_contexts(None)
+            .with_verbose_expectations(true),
+        false => input,
+    }
+}
+
+fn export_completions(schema: &str, ty: Option<&str>, output: Option<&str>) -> Result<(), Error> {
+    let schema = Schema::from_file(schema)?;
+    let data = match ty {
+        Some(ty) => CompletionData::for_struct(&schema, ty).ok_or_else(|| Error {
+            kind: ErrorKind::Custom(format!("the schema has no struct named `{}`", ty)),
+            context: None,
+        })?,
+        None => CompletionData::new(&schema),
+    };
+
+    write_output(output, &(data.to_json_string() + "\n"))
+}
+
+fn infer_schema_file(files: &[String], output: Option<&str>) -> Result<(), Error> {
+    let sources = files
+        .iter()
+        .map(read_fs_string)
+        .collect::<Result<Vec<_>, _>>()?;
+    let documents = sources
+        .iter()
+        .zip(files)
+        .map(|(source, file)| {
+            ast_from_str(source).map_err(|e| e.context_file_name(file.to_owned()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let schema = infer_schema(
+        &documents
+            .iter()
+            .map(|ron| &ron.expr.value)
+            .collect::<Vec<_>>(),
+    )
+    .to_ron_string();
+
+    write_output(output, &format!("{}\n", schema))
+}
+
+fn doc_file(file: &str, format: DocFormat, output: Option<&str>) -> Result<(), Error> {
+    let source = read_fs_string(file)?;
+    let title = Path::new(file)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let doc = document_str(&source, &title, format)?;
+
+    write_output(output, &doc)
+}
+
+fn new_file(schema: &str, output: Option<&str>, force: bool) -> Result<(), Error> {
+    let template = template_schema(&Schema::from_file(schema)?);
+    match output {
+        Some(output) if output != "-" && !force && Path::new(output).exists() => Err(Error {
+            kind: ErrorKind::Custom(format!(
+                "`{}` already exists, replace it with `--force`",
+                output
+            )),
+            context: None,
+        }),
+        _ => write_output(output, &template),
+    }
+}
+
+/// Writes `contents` to the file `output` atomically, or to stdout if there
+/// is none or it is `-`
+fn write_output(output: Option<&str>, contents: &str) -> Result<(), Error> {
+    match output {
+        None | Some("-") => {
+            let mut out = stdout().lock();
+            out.write_all(contents.as_bytes())
+                .and_then(|_| out.flush())
+                .map_err(Error::from)
+        }
+        Some(output) => write_fs_string(output, contents),
+    }
+}
+
+/// Replaces the contents `old` of `file` with `new`, or only prints the diff
+/// with `--dry-run` (colored on terminals unless `NO_COLOR` is set) or
+/// `--format patch`
+fn write_in_place(file: &str, old: &str, new: &str, opt: &WriteOpt) -> Result<(), Error> {
+    if opt.dry_run {
+        let color = stdout().is_terminal() && var_os("NO_COLOR").is_none();
+        return write_output(None, &render_diff(file, old, new, color));
+    }
+    if opt.format == WriteFormat::Patch {
+        return write_output(None, &render_diff(file, old, new, false));
+    }
+    if old == new {
+        return Ok(());
+    }
+
+    if let Some(suffix) = &opt.backup {
+        let backup = format!("{}{}", file, suffix.as_deref().unwrap_or(".bak"));
+        fs::copy(file, &backup)
+            .map_err(Error::from)
+            .map_err(|e| e.context_file_name(backup))?;
+    }
+
+    write_fs_string(file, new)
+}
+
+/// Formats `file`, in the style it is written in with `detect_style`
+fn format_file(
+    file: &str,
+    detect_style: bool,
+    newline: Newline,
+    write: &WriteOpt,
+) -> Result<(), Error> {
+    let source = read_fs_string(file)?;
+    let ron = ast_from_str(&source)?;
+    let style = match detect_style {
+        true => {
+            let style = format::detect_style(&source, &ron);
+            eprintln!("{}: {}", file, style);
+            style
+        }
+        false => Style::default(),
+    };
+    let style = Style { newline, ..style };
+
+    write_in_place(file, &source, &format_ron(&source, &ron, &style)?, write)
+}
+
+/// Prints the diagnostics that remain after (optionally) fixing the file;
+/// returns whether there were none.
+///
+/// With `--dry-run` or `--format patch`, the fixes are printed as a diff
+/// instead, and all diagnostics remain.
+fn lint_file(file: &str, config: &Config, fix: bool, write: &WriteOpt) -> Result<bool, Error> {
+    let source = read_fs_string(file)?;
+    let mut diagnostics = lint_str(&source, &config.lint)?;
+
+    if (fix || !write.in_place()) && diagnostics.iter().any(|d| d.fix.is_some()) {
+        write_in_place(file, &source, &apply_fixes(&source, &diagnostics), write)?;
+        if write.in_place() {
+            diagnostics.retain(|d| d.fix.is_none());
+        }
+    }
+
+    for diagnostic in &diagnostics {
+        let _ = print_error(
+            &diagnostic
+                .to_error(&source)
+                .context_file_name(file.to_owned()),
+        );
+    }
+
+    Ok(diagnostics.is_empty())
+}
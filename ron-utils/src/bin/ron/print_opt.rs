@@ -1,6 +1,12 @@
-use std::{fmt, str::FromStr};
+use std::{
+    fmt,
+    io::{stderr, stdout, Write},
+    str::FromStr,
+};
 
-use ron_utils::print_error;
+use ron_reboot::{utf8_parser::Timings, DEFAULT_SNIPPET_WIDTH};
+use ron_utils::{write_error_with_format, ErrorFormat};
+use serde::Serialize;
 
 pub enum PrintOpt {
     PrettyErrors,
@@ -55,36 +61,115 @@ impl PrintOpt {
 }
 
 impl PrintOpt {
-    pub fn print_ok(&self, file_name: &str) {
+    pub fn print_ok(&self, report: &mut FileReport, file_name: &str) {
         use PrintOpt::*;
 
         match self {
             OkStatus | StatusAndPrettyError | Status => {
-                println!("{} ok", file_name)
+                let _ = writeln!(report.stdout, "{} ok", file_name);
             }
             _ => {}
         }
     }
 
-    pub fn print_err(&self, file_name: &str) {
+    pub fn print_err(&self, report: &mut FileReport, file_name: &str) {
         use PrintOpt::*;
 
         match self {
             ErrorStatus | StatusAndPrettyError | Status => {
-                println!("{} err", file_name)
+                let _ = writeln!(report.stdout, "{} err", file_name);
             }
             _ => {}
         }
     }
 
-    pub fn print_pretty_error(&self, error: &ron_utils::Error) {
+    pub fn print_pretty_error(
+        &self,
+        report: &mut FileReport,
+        error: &ron_utils::Error,
+        format: ErrorFormat,
+    ) {
         use PrintOpt::*;
 
         match self {
             PrettyErrors | StatusAndPrettyError => {
-                let _ = print_error(error);
+                let _ = write_error_with_format(
+                    &mut report.stderr,
+                    error,
+                    format,
+                    DEFAULT_SNIPPET_WIDTH,
+                );
             }
             _ => {}
         }
     }
+
+    /// Notes the errors left out because of `--max-errors-per-file`
+    pub fn print_omitted(&self, report: &mut FileReport, file_name: &str, omitted: usize) {
+        use PrintOpt::*;
+
+        match self {
+            PrettyErrors | StatusAndPrettyError => {
+                let _ = writeln!(
+                    report.stderr,
+                    "{}: {} more error{} omitted",
+                    file_name,
+                    omitted,
+                    if omitted == 1 { "" } else { "s" }
+                );
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The line `ron validate --log-json` prints for a file, e.g.
+/// `{"path":"a.ron","duration_us":120,"result":"error","errors":2}`
+#[derive(Serialize)]
+pub struct FileLog<'a> {
+    pub path: &'a str,
+    /// How long reading and validating the file took
+    pub duration_us: u128,
+    /// `ok` or `error`
+    pub result: &'static str,
+    /// How many errors the file has, including the ones that weren't printed
+    pub errors: usize,
+}
+
+/// The output for one file, printed at once so that the output of files
+/// validated in parallel doesn't interleave
+#[derive(Default)]
+pub struct FileReport {
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+}
+
+impl FileReport {
+    /// Notes how long parsing the file took, for `--timings`
+    pub fn print_timings(&mut self, file_name: &str, timings: &Timings) {
+        let _ = writeln!(
+            self.stdout,
+            "{} parse {:?}, convert {:?}, total {:?}, backtracked {} bytes",
+            file_name,
+            timings.parse,
+            timings.convert,
+            timings.total(),
+            timings.backtracked
+        );
+    }
+
+    /// Notes the outcome for `--log-json`
+    pub fn print_log(&mut self, log: &FileLog) {
+        if serde_json::to_writer(&mut self.stdout, log).is_ok() {
+            let _ = writeln!(self.stdout);
+        }
+    }
+
+    pub fn print(&self) {
+        let out = stdout();
+        let err = stderr();
+        let (mut out, mut err) = (out.lock(), err.lock());
+        let _ = out.write_all(&self.stdout).and_then(|_| out.flush());
+        let _ = err.write_all(&self.stderr);
+    }
 }
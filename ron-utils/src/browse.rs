@@ -0,0 +1,441 @@
+//! The state of `ron browse`, which shows a document as a tree whose nodes
+//! can be collapsed, searched and copied as paths
+//!
+//! The terminal UI itself is part of the `ron` binary (with the `browse`
+//! feature), this is what it shows.
+
+use std::collections::HashSet;
+
+use ron_reboot::{
+    ast::{Expr, Spanned, Struct, Tuple, Untagged},
+    path::{Step, ValuePath},
+    utf8_parser::ast_from_str,
+    Error, LineIndex, Location,
+};
+
+/// How many chars of a value's source the label of a node shows at most
+const MAX_TEXT: usize = 60;
+
+/// A value of a document
+#[derive(Clone, Debug, PartialEq)]
+pub struct Node {
+    pub path: ValuePath,
+    /// The last step of the path and a summary of the value, e.g.
+    /// `window: Window(..) 2 fields` or `0: "ui"`
+    pub label: String,
+    /// How many values this one is nested in
+    pub depth: usize,
+    /// Index of the node this one is nested in
+    pub parent: Option<usize>,
+    /// Number of nodes nested directly in this one
+    pub children: usize,
+    pub start: Location,
+    /// Byte range of the value in the source
+    pub bytes: (usize, usize),
+    /// The `///` doc comment of the field or map entry this is the value of
+    pub doc: Option<String>,
+}
+
+/// All values of a document, in the order of the document
+#[derive(Clone, Debug)]
+pub struct Tree {
+    pub nodes: Vec<Node>,
+}
+
+impl Tree {
+    pub fn parse(source: &str) -> Result<Self, Error> {
+        let ron = ast_from_str(source)?;
+        let mut tree = Tree { nodes: vec![] };
+        tree.add(
+            &LineIndex::new(source),
+            source,
+            &ron.expr,
+            ValuePath::root(),
+            None,
+            None,
+        );
+
+        Ok(tree)
+    }
+
+    fn add(
+        &mut self,
+        index: &LineIndex,
+        source: &str,
+        expr: &Spanned<Expr>,
+        path: ValuePath,
+        parent: Option<usize>,
+        doc: Option<String>,
+    ) {
+        let start = index.byte_offset(expr.start);
+        let end = start + source[start..index.byte_offset(expr.end)].trim_end().len();
+        let summary = summary(&expr.value, &source[start..end]);
+        let label = match path.steps().last() {
+            Some(step) => format!("{}: {}", step, summary),
+            None => summary,
+        };
+
+        let i = self.nodes.len();
+        let children = children(&expr.value, source, index);
+        self.nodes.push(Node {
+            path: path.clone(),
+            label,
+            depth: parent.map_or(0, |parent| self.nodes[parent].depth + 1),
+            parent,
+            children: children.len(),
+            start: expr.start,
+            bytes: (start, end),
+            doc,
+        });
+        for (step, doc, child) in children {
+            self.add(index, source, child, path.join(step), Some(i), doc);
+        }
+    }
+
+    /// The nodes with `query` in their label, ignoring case
+    pub fn search(&self, query: &str) -> Vec<usize> {
+        let query = query.to_lowercase();
+
+        (0..self.nodes.len())
+            .filter(|&i| self.nodes[i].label.to_lowercase().contains(&query))
+            .collect()
+    }
+
+    /// The nodes `node` is nested in, innermost first
+    pub fn ancestors(&self, node: usize) -> impl Iterator<Item = usize> + '_ {
+        std::iter::successors(self.nodes[node].parent, move |&i| self.nodes[i].parent)
+    }
+}
+
+/// The children of `expr` with the steps to them and their doc comments;
+/// the children of a `Some(..)` are those of its value
+fn children<'b, 'a>(
+    expr: &'b Expr<'a>,
+    source: &str,
+    index: &LineIndex,
+) -> Vec<(Step, Option<String>, &'b Spanned<Expr<'a>>)> {
+    let indexed = |elements: &'b [Spanned<Expr<'a>>]| {
+        elements
+            .iter()
+            .enumerate()
+            .map(|(i, element)| (Step::Index(i), None, element))
+            .collect()
+    };
+    let fields = |s: &'b Struct<'a>| {
+        s.fields
+            .iter()
+            .map(|field| {
+                (
+                    Step::Key(field.value.key.value.as_str().to_owned()),
+                    field.value.doc_string(),
+                    &field.value.value,
+                )
+            })
+            .collect()
+    };
+
+    match expr {
+        Expr::Optional(Some(inner)) => children(&inner.value, source, index),
+        Expr::Tagged(t) => match &t.untagged.value {
+            Untagged::Struct(s) => fields(s),
+            Untagged::Tuple(Tuple { elements }) => indexed(elements),
+            _ => vec![],
+        },
+        Expr::Struct(s) => fields(s),
+        Expr::List(ron_reboot::ast::List { elements }) | Expr::Tuple(Tuple { elements }) => {
+            indexed(elements)
+        }
+        Expr::Map(map) => map
+            .entries
+            .iter()
+            .map(|entry| {
+                let entry = &entry.value;
+                // Keys paths can't address are shown as written
+                let step = Step::from_key(&entry.key.value).unwrap_or_else(|| {
+                    let start = index.byte_offset(entry.key.start);
+                    let text = &source[start..index.byte_offset(entry.key.end)];
+
+                    Step::Key(text.trim_end().to_owned())
+                });
+
+                (step, entry.doc_string(), &entry.value)
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// What a node shows of `expr`, whose source is `text`: the kinds and number
+/// of children of containers, and the text of other values
+fn summary(expr: &Expr, text: &str) -> String {
+    let count = |n: usize, what: &str| match (n, what) {
+        (1, _) => format!("1 {}", what),
+        (n, "entry") => format!("{} entries", n),
+        (n, _) => format!("{} {}s", n, what),
+    };
+
+    match expr {
+        Expr::Optional(Some(inner)) => {
+            let inner_text = text
+                .strip_prefix("Some(")
+                .and_then(|text| text.strip_suffix(')'))
+                .unwrap_or(text);
+
+            format!("Some({})", summary(&inner.value, inner_text.trim()))
+        }
+        Expr::Struct(s) if !s.fields.is_empty() => {
+            format!("(..) {}", count(s.fields.len(), "field"))
+        }
+        Expr::Tuple(Tuple { elements }) if !elements.is_empty() => {
+            format!("(..) {}", count(elements.len(), "element"))
+        }
+        Expr::List(ron_reboot::ast::List { elements }) if !elements.is_empty() => {
+            format!("[..] {}", count(elements.len(), "element"))
+        }
+        Expr::Map(map) if !map.entries.is_empty() => {
+            format!("{{..}} {}", count(map.entries.len(), "entry"))
+        }
+        Expr::Tagged(t) => match &t.untagged.value {
+            Untagged::Struct(s) if !s.fields.is_empty() => {
+                format!("{}(..) {}", t.ident.value.0, count(s.fields.len(), "field"))
+            }
+            Untagged::Tuple(Tuple { elements }) if !elements.is_empty() => {
+                format!(
+                    "{}(..) {}",
+                    t.ident.value.0,
+                    count(elements.len(), "element")
+                )
+            }
+            _ => shorten(text),
+        },
+        _ => shorten(text),
+    }
+}
+
+/// The first line of `text`, cut after [`MAX_TEXT`] chars
+fn shorten(text: &str) -> String {
+    let line = text.lines().next().unwrap_or_default();
+    match line.char_indices().nth(MAX_TEXT) {
+        Some((i, _)) => format!("{}…", &line[..i]),
+        None if line.len() < text.len() => format!("{}…", line),
+        None => line.to_owned(),
+    }
+}
+
+/// A [`Tree`] with the nodes that are collapsed, selected and found
+#[derive(Clone, Debug)]
+pub struct Browser {
+    pub tree: Tree,
+    collapsed: HashSet<usize>,
+    selected: usize,
+    /// Nodes found by the last search
+    matches: Vec<usize>,
+}
+
+impl Browser {
+    /// Shows the root and its children, with everything deeper collapsed
+    pub fn new(tree: Tree) -> Self {
+        let collapsed = tree
+            .nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| node.depth >= 1 && node.children > 0)
+            .map(|(i, _)| i)
+            .collect();
+
+        Browser {
+            tree,
+            collapsed,
+            selected: 0,
+            matches: vec![],
+        }
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn is_collapsed(&self, node: usize) -> bool {
+        self.collapsed.contains(&node)
+    }
+
+    /// The nodes not nested in a collapsed one, in the order of the document
+    pub fn visible(&self) -> Vec<usize> {
+        let mut visible = vec![];
+        let mut i = 0;
+        while i < self.tree.nodes.len() {
+            visible.push(i);
+            i = match self.is_collapsed(i) {
+                true => self.after_descendants(i),
+                false => i + 1,
+            };
+        }
+
+        visible
+    }
+
+    /// The index after the last node nested in `node`
+    fn after_descendants(&self, node: usize) -> usize {
+        let depth = self.tree.nodes[node].depth;
+
+        self.tree.nodes[node + 1..]
+            .iter()
+            .position(|n| n.depth <= depth)
+            .map_or(self.tree.nodes.len(), |i| node + 1 + i)
+    }
+
+    /// Selects the visible node `delta` rows away from the selected one
+    pub fn move_by(&mut self, delta: isize) {
+        let visible = self.visible();
+        let row = visible
+            .iter()
+            .position(|&i| i == self.selected)
+            .unwrap_or(0) as isize;
+        let row = (row + delta).clamp(0, visible.len() as isize - 1);
+
+        self.selected = visible[row as usize];
+    }
+
+    /// Shows the children of the selected node
+    pub fn expand(&mut self) {
+        self.collapsed.remove(&self.selected);
+    }
+
+    /// Hides the children of the selected node, or selects its parent if they
+    /// already are
+    pub fn collapse(&mut self) {
+        let node = &self.tree.nodes[self.selected];
+        match node.children > 0 && !self.is_collapsed(self.selected) {
+            true => {
+                self.collapsed.insert(self.selected);
+            }
+            false => self.selected = node.parent.unwrap_or(self.selected),
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        match self.is_collapsed(self.selected) {
+            true => self.expand(),
+            false => self.collapse(),
+        }
+    }
+
+    /// Finds the nodes with `query` in their label and selects the first one
+    /// after the selected node; returns how many were found
+    pub fn search(&mut self, query: &str) -> usize {
+        self.matches = self.tree.search(query);
+        self.next_match(true);
+
+        self.matches.len()
+    }
+
+    /// Selects the next node found by [`search`](Self::search), or the
+    /// previous one unless `forward`, and expands the nodes it is nested in
+    pub fn next_match(&mut self, forward: bool) {
+        let next = match forward {
+            true => self
+                .matches
+                .iter()
+                .find(|&&i| i > self.selected)
+                .or_else(|| self.matches.first()),
+            false => self
+                .matches
+                .iter()
+                .rev()
+                .find(|&&i| i < self.selected)
+                .or_else(|| self.matches.last()),
+        };
+
+        if let Some(&next) = next {
+            self.selected = next;
+            for ancestor in self.tree.ancestors(next).collect::<Vec<_>>() {
+                self.collapsed.remove(&ancestor);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r#"Config(
+    window: Window(width: 800, title: "Game"),
+    layers: ["ui", "world"],
+    keys: {"a.b": Some((1, 2))},
+    empty: [], // none
+)"#;
+
+    #[test]
+    fn nodes() {
+        let tree = Tree::parse(SOURCE).unwrap();
+        let labels: Vec<_> = tree
+            .nodes
+            .iter()
+            .map(|node| format!("{}{}", "  ".repeat(node.depth), node.label))
+            .collect();
+        assert_eq!(
+            labels,
+            [
+                "Config(..) 4 fields",
+                "  window: Window(..) 2 fields",
+                "    width: 800",
+                "    title: \"Game\"",
+                "  layers: [..] 2 elements",
+                "    0: \"ui\"",
+                "    1: \"world\"",
+                "  keys: {..} 1 entry",
+                "    \"a.b\": Some((..) 2 elements)",
+                "      0: 1",
+                "      1: 2",
+                "  empty: []",
+            ]
+        );
+
+        let node = &tree.nodes[9];
+        assert_eq!(node.path.to_string(), "keys.\"a.b\".0");
+        assert_eq!(&SOURCE[node.bytes.0..node.bytes.1], "1");
+        assert_eq!((node.start.line, node.start.column), (4, 25));
+        assert_eq!(tree.ancestors(9).collect::<Vec<_>>(), [8, 7, 0]);
+    }
+
+    #[test]
+    fn docs() {
+        let tree = Tree::parse(
+            "(\n  /// Size in px\n  size: 1,\n  keys: {\n    /// First\n    \"a\": 2,\n  },\n)",
+        )
+        .unwrap();
+        let docs: Vec<_> = tree.nodes.iter().map(|node| node.doc.as_deref()).collect();
+        assert_eq!(docs, [None, Some("Size in px"), None, Some("First")]);
+    }
+
+    #[test]
+    fn collapse_and_search() {
+        let mut browser = Browser::new(Tree::parse(SOURCE).unwrap());
+        assert_eq!(browser.visible(), [0, 1, 4, 7, 11]);
+
+        browser.move_by(1);
+        browser.expand();
+        assert_eq!(browser.visible(), [0, 1, 2, 3, 4, 7, 11]);
+        browser.move_by(100);
+        assert_eq!(browser.selected(), 11);
+
+        // Collapsing a leaf selects its parent
+        browser.collapse();
+        assert_eq!(browser.selected(), 0);
+        browser.collapse();
+        assert_eq!(browser.visible(), [0]);
+
+        assert_eq!(browser.search("WORLD"), 1);
+        assert_eq!(browser.selected(), 6);
+        assert_eq!(browser.visible(), [0, 1, 2, 3, 4, 5, 6, 7, 11]);
+
+        // Wrapping around at both ends
+        assert_eq!(browser.search("2 "), 3);
+        assert_eq!(browser.selected(), 8);
+        browser.next_match(true);
+        assert_eq!(browser.selected(), 1);
+        browser.next_match(false);
+        assert_eq!(browser.selected(), 8);
+    }
+}
@@ -0,0 +1,236 @@
+//! Checksum trailers, e.g. for configs that may be edited but whose edits
+//! should be noticed
+//!
+//! The trailer is a line comment at the end of the document, like
+//! `// sha256:<hex digest>`, over all bytes before its line. Documents stay
+//! valid RON with it. Without a key anyone can update the checksum, which
+//! only catches accidental edits; with a key the trailer is an HMAC,
+//! `// hmac-sha256:<hex digest>`, which only those knowing the key can
+//! update.
+
+use hmac::{Hmac, Mac};
+use ron_reboot::{ErrorKind, LineIndex};
+use sha2::{Digest, Sha256};
+
+use crate::Error;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const SHA256_PREFIX: &str = "// sha256:";
+const HMAC_SHA256_PREFIX: &str = "// hmac-sha256:";
+
+/// The checksum trailer of a document
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Trailer {
+    /// Whether the digest is an HMAC, which needs a key to be checked
+    pub keyed: bool,
+    /// The digest as written, hex encoded
+    pub digest: String,
+    /// The byte offset of the trailer's line, the end of the checked bytes
+    pub start: usize,
+}
+
+/// The checksum trailer on the last line of `source`, if it has one
+///
+/// A line break may follow the trailer, but nothing else.
+pub fn trailer(source: &str) -> Option<Trailer> {
+    let content = source
+        .strip_suffix('\n')
+        .map(|s| s.strip_suffix('\r').unwrap_or(s))
+        .unwrap_or(source);
+    let start = content.rfind('\n').map_or(0, |i| i + 1);
+    let line = &content[start..];
+
+    let (keyed, digest) = match line.strip_prefix(HMAC_SHA256_PREFIX) {
+        Some(digest) => (true, digest),
+        None => (false, line.strip_prefix(SHA256_PREFIX)?),
+    };
+
+    Some(Trailer {
+        keyed,
+        digest: digest.trim_end().to_owned(),
+        start,
+    })
+}
+
+/// `source` with its checksum trailer updated, or added if it has none
+///
+/// The trailer is an HMAC if there is a `key`.
+pub fn sign_str(source: &str, key: Option<&[u8]>) -> String {
+    let mut signed = match trailer(source) {
+        Some(trailer) => source[..trailer.start].to_owned(),
+        None => source.to_owned(),
+    };
+    if !signed.is_empty() && !signed.ends_with('\n') {
+        signed.push('\n');
+    }
+
+    let (prefix, digest) = match key {
+        Some(key) => (HMAC_SHA256_PREFIX, hmac_sha256(key, signed.as_bytes())),
+        None => (SHA256_PREFIX, sha256(signed.as_bytes())),
+    };
+    signed.push_str(prefix);
+    signed.push_str(&hex(&digest));
+    signed.push('\n');
+
+    signed
+}
+
+/// Checks the checksum trailer of `source`, which has to be an HMAC if
+/// there is a `key` and mustn't be one otherwise
+pub fn verify_str(source: &str, key: Option<&[u8]>) -> Result<(), Error> {
+    let trailer = trailer(source)
+        .ok_or_else(|| custom("no checksum trailer, add one with `ron sign`".to_owned()))?;
+    let located = |message: String| {
+        let index = LineIndex::new(source);
+
+        custom(message)
+            .context_loc(index.location(trailer.start), index.location(source.len()))
+            .context_file_content(source.to_owned())
+    };
+
+    let checked = &source.as_bytes()[..trailer.start];
+    let digest = unhex(&trailer.digest);
+    let matches = match (key, trailer.keyed) {
+        // In constant time, so the HMAC can't be guessed byte by byte
+        (Some(key), true) => {
+            digest.is_some_and(|digest| hmac(key, checked).verify_slice(&digest).is_ok())
+        }
+        (None, false) => digest.is_some_and(|digest| digest == sha256(checked)),
+        (Some(_), false) => {
+            return Err(located(
+                "the checksum isn't keyed, the document isn't signed".to_owned(),
+            ))
+        }
+        (None, true) => {
+            return Err(located(
+                "the checksum is keyed, it can only be checked with the key".to_owned(),
+            ))
+        }
+    };
+
+    match matches {
+        true => Ok(()),
+        false => Err(located(
+            "the checksum doesn't match, the document was changed".to_owned(),
+        )),
+    }
+}
+
+fn custom(message: String) -> Error {
+    Error {
+        kind: ErrorKind::Custom(message),
+        context: None,
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// The bytes of the hex string `s`, if it is one
+fn unhex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) || !s.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// The SHA-256 digest of `bytes`
+pub fn sha256(bytes: &[u8]) -> [u8; 32] {
+    Sha256::digest(bytes).into()
+}
+
+/// The HMAC-SHA256 of `bytes` with `key` (RFC 2104)
+pub fn hmac_sha256(key: &[u8], bytes: &[u8]) -> [u8; 32] {
+    hmac(key, bytes).finalize().into_bytes().into()
+}
+
+fn hmac(key: &[u8], bytes: &[u8]) -> HmacSha256 {
+    let mut mac = <HmacSha256 as Mac>::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(bytes);
+
+    mac
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn digests() {
+        assert_eq!(
+            hex(&sha256(b"")),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            hex(&sha256(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        // Two blocks after padding
+        assert_eq!(
+            hex(&sha256(
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"
+            )),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+        // RFC 4231, test cases 2 and 6
+        assert_eq!(
+            hex(&hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert_eq!(
+            hex(&hmac_sha256(
+                &[0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            )),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+    }
+
+    #[test]
+    fn sign_and_verify() {
+        let source = "(speed: 2.5)";
+        let signed = sign_str(source, None);
+        assert!(signed.starts_with("(speed: 2.5)\n// sha256:"));
+        assert!(signed.ends_with('\n'));
+        assert!(ron_reboot::utf8_parser::ast_from_str(&signed).is_ok());
+        assert_eq!(verify_str(&signed, None), Ok(()));
+
+        // Signing again replaces the trailer
+        assert_eq!(sign_str(&signed, None), signed);
+        let edited = signed.replace("2.5", "9.5");
+        assert!(verify_str(&edited, None).is_err());
+        assert_eq!(verify_str(&sign_str(&edited, None), None), Ok(()));
+
+        let e = verify_str(&edited, None).unwrap_err();
+        assert_eq!(e.start().map(|start| start.line), Some(2));
+        assert!(verify_str(source, None).is_err());
+    }
+
+    #[test]
+    fn keyed() {
+        let signed = sign_str("(speed: 2.5)\n", Some(b"secret"));
+        assert!(trailer(&signed).unwrap().keyed);
+        assert_eq!(verify_str(&signed, Some(b"secret")), Ok(()));
+        assert!(verify_str(&signed, Some(b"guess")).is_err());
+
+        // Digests are compared as bytes, so their case doesn't matter, and
+        // ones that aren't hex don't match
+        let digest = trailer(&signed).unwrap().digest;
+        let upper = signed.replace(&digest, &digest.to_uppercase());
+        assert_eq!(verify_str(&upper, Some(b"secret")), Ok(()));
+        let garbled = signed.replace(&digest, &digest.replacen(char::is_alphanumeric, "g", 1));
+        assert!(verify_str(&garbled, Some(b"secret")).is_err());
+        assert!(verify_str(&signed.replace(&digest, &digest[1..]), Some(b"secret")).is_err());
+
+        // Replacing the HMAC with a plain checksum doesn't pass
+        assert!(verify_str(&signed, None).is_err());
+        let unkeyed = sign_str(&signed, None);
+        assert!(verify_str(&unkeyed, Some(b"secret")).is_err());
+    }
+}
@@ -0,0 +1,718 @@
+//! Completion suggestions for editors
+//!
+//! Documents being edited usually don't parse, so the source is only split
+//! into tokens and the nesting of delimiters is tracked to find out what the
+//! cursor is in.
+//!
+//! For editor plugins that complete on their own, [`CompletionData`] lists
+//! everything a schema knows up front, as JSON with `ron export-completions`.
+
+use ron_reboot::{
+    ast::Extension,
+    lex::{block_comment, line_comment_len, quoted_len, raw_string_len},
+    path::Step,
+};
+use serde::Serialize;
+
+use crate::schema::{RonSchema, Schema, StructSchema};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CompletionKind {
+    Field,
+    Variant,
+    Struct,
+    /// `true`, `false`, `Some`, `None` and attribute names
+    Keyword,
+    Extension,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CompletionItem {
+    pub label: String,
+    pub kind: CompletionKind,
+    /// The type of a field, if known
+    pub detail: Option<String>,
+}
+
+impl CompletionItem {
+    fn new(label: impl Into<String>, kind: CompletionKind) -> Self {
+        CompletionItem {
+            label: label.into(),
+            kind,
+            detail: None,
+        }
+    }
+}
+
+/// Suggests what to write at the byte offset `pos` of `source`, starting with
+/// the identifier the cursor is at the end of (if any).
+///
+/// Field names and values come from `schema` and from structs and values at
+/// the same path elsewhere in the document, e.g. other elements of a list.
+pub fn complete(source: &str, pos: usize, schema: Option<&Schema>) -> Vec<CompletionItem> {
+    let mut pos = pos.min(source.len());
+    while !source.is_char_boundary(pos) {
+        pos -= 1;
+    }
+
+    let tokens = tokenize(source);
+    let in_literal = tokens.iter().any(|t| match t.token {
+        Token::Literal => t.start < pos && pos < t.end,
+        Token::Comment => t.start < pos && pos <= t.end,
+        _ => false,
+    });
+    if in_literal {
+        return vec![];
+    }
+
+    let word_start = source[..pos].trim_end_matches(is_ident_char).len();
+    let word = &source[word_start..pos];
+
+    let mut before = Walker::default();
+    tokens
+        .iter()
+        .take_while(|t| t.end <= word_start)
+        .for_each(|t| before.push(source, t));
+    let mut all = Walker::default();
+    tokens.iter().for_each(|t| all.push(source, t));
+    all.finish();
+
+    if !before.at_value_start() {
+        return vec![];
+    }
+
+    let items = match before.stack.last() {
+        Some(frame) if frame.attribute => attribute_items(frame, &all),
+        Some(frame) if frame.delim == b'(' && !frame.in_value => {
+            match field_items(frame, &all, schema) {
+                items if items.is_empty() => {
+                    value_items(&frame.value_path(), word_start, &all, schema)
+                }
+                items => items,
+            }
+        }
+        Some(frame) => value_items(&frame.value_path(), word_start, &all, schema),
+        None => value_items(&[], word_start, &all, schema),
+    };
+
+    let mut completions: Vec<CompletionItem> = vec![];
+    for item in items {
+        if item.label.starts_with(word) && !completions.iter().any(|c| c.label == item.label) {
+            completions.push(item);
+        }
+    }
+
+    completions
+}
+
+fn attribute_items(frame: &Frame, all: &Walker) -> Vec<CompletionItem> {
+    match (frame.delim, frame.name) {
+        (b'[', _) => vec![CompletionItem::new("enable", CompletionKind::Keyword)],
+        (b'(', Some("enable")) => {
+            let enabled = all
+                .frame_at(frame.start)
+                .map(|f| &f.keys[..])
+                .unwrap_or(&[]);
+            Extension::ALL
+                .iter()
+                .map(Extension::name)
+                .filter(|name| !enabled.contains(name))
+                .map(|name| CompletionItem::new(name, CompletionKind::Extension))
+                .collect()
+        }
+        _ => vec![],
+    }
+}
+
+fn field_items(frame: &Frame, all: &Walker, schema: Option<&Schema>) -> Vec<CompletionItem> {
+    let present = all
+        .frame_at(frame.start)
+        .map(|f| &f.keys[..])
+        .unwrap_or(&[]);
+    let mut items = vec![];
+
+    if let Some(Schema::Struct(s)) = schema.and_then(|schema| resolve(schema, &frame.path)) {
+        items.extend(s.fields.iter().map(|field| CompletionItem {
+            label: field.name.clone(),
+            kind: CompletionKind::Field,
+            detail: Some(field.schema.to_string()),
+        }));
+    }
+    let siblings = all
+        .frames
+        .iter()
+        .filter(|f| f.start != frame.start && f.path == frame.path);
+    for sibling in siblings {
+        items.extend(
+            sibling
+                .keys
+                .iter()
+                .map(|key| CompletionItem::new(*key, CompletionKind::Field)),
+        );
+    }
+
+    items.retain(|item| !present.contains(&item.label.as_str()));
+
+    items
+}
+
+/// `word_start` is the offset of the identifier being completed, which isn't
+/// suggested itself
+fn value_items(
+    path: &[Segment],
+    word_start: usize,
+    all: &Walker,
+    schema: Option<&Schema>,
+) -> Vec<CompletionItem> {
+    fn schema_items(schema: Option<&Schema>, items: &mut Vec<CompletionItem>) {
+        match schema {
+            None | Some(Schema::Any) | Some(Schema::Bool) => {
+                items.push(CompletionItem::new("true", CompletionKind::Keyword));
+                items.push(CompletionItem::new("false", CompletionKind::Keyword));
+            }
+            Some(Schema::Option(inner)) => {
+                items.push(CompletionItem::new("Some", CompletionKind::Keyword));
+                items.push(CompletionItem::new("None", CompletionKind::Keyword));
+                schema_items(Some(inner), items);
+            }
+            Some(Schema::Enum(variants)) => items.extend(
+                variants
+                    .iter()
+                    .map(|variant| CompletionItem::new(variant.clone(), CompletionKind::Variant)),
+            ),
+            Some(Schema::Struct(s)) => items.extend(
+                s.name
+                    .iter()
+                    .map(|name| CompletionItem::new(name.clone(), CompletionKind::Struct)),
+            ),
+            Some(_) => {}
+        }
+    }
+
+    let schema = match schema {
+        Some(schema) => match resolve(schema, path) {
+            Some(schema) => Some(schema),
+            // Outside of the schema; better suggest nothing than wrong values
+            None => return vec![],
+        },
+        None => None,
+    };
+
+    let mut items = vec![];
+    schema_items(schema, &mut items);
+    let values = all
+        .values
+        .iter()
+        .filter(|(p, start, _)| p == path && *start != word_start);
+    for (_, _, value) in values {
+        let kind = match *value {
+            "true" | "false" | "Some" | "None" => CompletionKind::Keyword,
+            _ => CompletionKind::Variant,
+        };
+        items.push(CompletionItem::new(*value, kind));
+    }
+
+    items
+}
+
+fn resolve<'s>(schema: &'s Schema, path: &[Segment]) -> Option<&'s Schema> {
+    path.iter().try_fold(schema, child)
+}
+
+fn child<'s>(schema: &'s Schema, segment: &Segment) -> Option<&'s Schema> {
+    match (schema, segment) {
+        // `Some(..)`
+        (Schema::Option(inner), Segment::Index(0)) => Some(inner),
+        // Implicit `Some`
+        (Schema::Option(inner), _) => child(inner, segment),
+        (Schema::Struct(s), Segment::Field(name)) => s.field(name).map(|field| &field.schema),
+        (Schema::List(inner), Segment::Element) => Some(inner),
+        (Schema::Map(key, _), Segment::MapKey) => Some(key),
+        (Schema::Map(_, value), Segment::MapValue) => Some(value),
+        (Schema::Tuple(elements), Segment::Index(i)) => elements.get(*i),
+        (Schema::Any, _) => Some(schema),
+        _ => None,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Token {
+    Ident,
+    Punct(u8),
+    /// Strings and numbers
+    Literal,
+    Comment,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Tok {
+    start: usize,
+    end: usize,
+    token: Token,
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+fn tokenize(source: &str) -> Vec<Tok> {
+    let bytes = source.as_bytes();
+    let mut tokens = vec![];
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let start = i;
+        let rest = &bytes[i..];
+        let (len, token) = match rest[0] {
+            b if b.is_ascii_whitespace() => {
+                i += 1;
+                continue;
+            }
+            _ if rest.starts_with(b"//") => (line_comment_len(rest), Token::Comment),
+            _ if rest.starts_with(b"/*") => (block_comment(rest).len, Token::Comment),
+            b'"' => (quoted_len(rest), Token::Literal),
+            b'r' if raw_string_len(rest).is_some() => {
+                (raw_string_len(rest).unwrap_or_default(), Token::Literal)
+            }
+            b if b.is_ascii_alphabetic() || b == b'_' => (ident_len(rest), Token::Ident),
+            b if b.is_ascii_digit() || b == b'+' || b == b'-' || b == b'.' => {
+                let len = rest[1..]
+                    .iter()
+                    .position(|&b| !(b.is_ascii_alphanumeric() || b == b'_' || b == b'.'))
+                    .unwrap_or(rest.len() - 1);
+
+                (1 + len, Token::Literal)
+            }
+            b if b.is_ascii() => (1, Token::Punct(b)),
+            _ => {
+                let c = source[i..].chars().next().expect("not at the end");
+                (c.len_utf8(), Token::Punct(0))
+            }
+        };
+
+        i += len;
+        tokens.push(Tok {
+            start,
+            end: i,
+            token,
+        });
+    }
+
+    tokens
+}
+
+/// Length of the identifier at the start of `s`, which may be a raw identifier (`r#name`)
+fn ident_len(s: &[u8]) -> usize {
+    let prefix = match s.starts_with(b"r#") {
+        true => 2,
+        false => 0,
+    };
+
+    prefix
+        + s[prefix..]
+            .iter()
+            .position(|&b| !(b.is_ascii_alphanumeric() || b == b'_'))
+            .unwrap_or(s.len() - prefix)
+}
+
+/// Step from a value to one of its children
+#[derive(Clone, Debug, PartialEq)]
+enum Segment<'a> {
+    Field(&'a str),
+    /// Tuple element
+    Index(usize),
+    /// List element
+    Element,
+    MapKey,
+    MapValue,
+}
+
+#[derive(Clone, Debug)]
+struct Frame<'a> {
+    /// Offset of the opening delimiter
+    start: usize,
+    delim: u8,
+    /// Inside `#![..]`
+    attribute: bool,
+    /// Identifier in front of the delimiter, e.g. a struct name
+    name: Option<&'a str>,
+    path: Vec<Segment<'a>>,
+    /// Struct fields, or listed extensions in attributes
+    keys: Vec<&'a str>,
+    /// Number of commas
+    elements: usize,
+    key: Option<&'a str>,
+    /// After the `:` of a struct field or map entry
+    in_value: bool,
+}
+
+impl<'a> Frame<'a> {
+    /// Path of a value starting at the current position
+    fn value_path(&self) -> Vec<Segment<'a>> {
+        let segment = match (self.delim, self.key) {
+            (b'[', _) => Segment::Element,
+            (b'{', _) if self.in_value => Segment::MapValue,
+            (b'{', _) => Segment::MapKey,
+            (_, Some(key)) if self.in_value => Segment::Field(key),
+            _ => Segment::Index(self.elements),
+        };
+
+        let mut path = self.path.clone();
+        path.push(segment);
+
+        path
+    }
+}
+
+#[derive(Debug, Default)]
+struct Walker<'a> {
+    stack: Vec<Frame<'a>>,
+    /// Closed frames, and after `finish` also unclosed ones
+    frames: Vec<Frame<'a>>,
+    /// Identifiers written as values, with their path and offset
+    values: Vec<(Vec<Segment<'a>>, usize, &'a str)>,
+    prev: Option<(Token, &'a str)>,
+    /// After `#` or `#!`
+    attribute_start: bool,
+}
+
+impl<'a> Walker<'a> {
+    fn at_value_start(&self) -> bool {
+        match self.prev {
+            None => true,
+            Some((Token::Punct(b), _)) => b"([{,:".contains(&b),
+            Some(_) => false,
+        }
+    }
+
+    fn value_path(&self) -> Vec<Segment<'a>> {
+        self.stack.last().map(Frame::value_path).unwrap_or_default()
+    }
+
+    fn frame_at(&self, start: usize) -> Option<&Frame<'a>> {
+        self.frames.iter().find(|f| f.start == start)
+    }
+
+    fn push(&mut self, source: &'a str, tok: &Tok) {
+        let text = &source[tok.start..tok.end];
+        let attribute = self.stack.last().is_some_and(|f| f.attribute);
+
+        match tok.token {
+            Token::Comment => return,
+            Token::Ident if attribute => {
+                if let Some(frame) = self.stack.last_mut() {
+                    frame.keys.push(text);
+                }
+            }
+            Token::Ident if self.at_value_start() => {
+                self.values
+                    .push((self.value_path(), tok.start, text.trim_start_matches("r#")));
+            }
+            Token::Punct(delim @ b'(')
+            | Token::Punct(delim @ b'[')
+            | Token::Punct(delim @ b'{') => {
+                let name = match self.prev {
+                    Some((Token::Ident, name)) => Some(name),
+                    _ => None,
+                };
+                self.stack.push(Frame {
+                    start: tok.start,
+                    delim,
+                    attribute: attribute || (delim == b'[' && self.attribute_start),
+                    name,
+                    path: self.value_path(),
+                    keys: vec![],
+                    elements: 0,
+                    key: None,
+                    in_value: false,
+                });
+            }
+            Token::Punct(b')') | Token::Punct(b']') | Token::Punct(b'}') => {
+                self.frames.extend(self.stack.pop());
+            }
+            Token::Punct(b',') => {
+                if let Some(frame) = self.stack.last_mut() {
+                    frame.elements += 1;
+                    frame.key = None;
+                    frame.in_value = false;
+                }
+            }
+            Token::Punct(b':') => {
+                let key = match self.prev {
+                    Some((Token::Ident, key)) => Some(key.trim_start_matches("r#")),
+                    _ => None,
+                };
+                if let Some(frame) = self.stack.last_mut() {
+                    if frame.delim == b'(' {
+                        frame.key = key;
+                        frame.keys.extend(key);
+                    }
+                    frame.in_value = true;
+                }
+            }
+            _ => {}
+        }
+
+        self.attribute_start = match tok.token {
+            Token::Punct(b'#') => true,
+            Token::Punct(b'!') => self.attribute_start,
+            _ => false,
+        };
+        self.prev = Some((tok.token, text));
+    }
+
+    fn finish(&mut self) {
+        while let Some(frame) = self.stack.pop() {
+            self.frames.push(frame);
+        }
+    }
+}
+
+/// The struct names, fields and enum variants of a schema, for editor plugins
+/// that don't use the language server
+///
+/// Each struct and enum is listed with the path of its values, e.g.
+/// `layers.*.name`, where `*` stands for any element of a list or value of a
+/// map. Struct names, fields and tuple indices are written like in
+/// [`ValuePath`](ron_reboot::path::ValuePath)s.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct CompletionData {
+    pub structs: Vec<StructCompletions>,
+    pub enums: Vec<EnumCompletions>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct StructCompletions {
+    pub path: String,
+    /// The name the struct may be written with, e.g. `Config`
+    pub name: Option<String>,
+    pub fields: Vec<FieldCompletions>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct FieldCompletions {
+    pub name: String,
+    /// The type of the field, like the detail of [`CompletionItem`]s
+    #[serde(rename = "type")]
+    pub ty: String,
+    pub doc: Option<String>,
+    /// Whether the field may be omitted
+    pub optional: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct EnumCompletions {
+    pub path: String,
+    /// The names of the unit variants
+    pub variants: Vec<String>,
+}
+
+impl CompletionData {
+    pub fn new(schema: &Schema) -> Self {
+        let mut data = CompletionData::default();
+        data.add(schema, &mut vec![]);
+
+        data
+    }
+
+    /// The completions of the schema of `T`, e.g. a `#[derive(RonSchema)]`
+    /// config
+    pub fn of<T: RonSchema + ?Sized>() -> Self {
+        CompletionData::new(&T::schema())
+    }
+
+    /// Only the completions of the struct named `name` and its values, with
+    /// their paths starting at the struct; `None` if the schema has no such
+    /// struct
+    pub fn for_struct(schema: &Schema, name: &str) -> Option<Self> {
+        fn find<'s>(schema: &'s Schema, name: &str) -> Option<&'s Schema> {
+            match schema {
+                Schema::Struct(StructSchema { name: Some(n), .. }) if n == name => Some(schema),
+                Schema::Option(inner) | Schema::List(inner) => find(inner, name),
+                Schema::Map(key, value) => find(key, name).or_else(|| find(value, name)),
+                Schema::Tuple(elements) => elements.iter().find_map(|e| find(e, name)),
+                Schema::Struct(s) => s.fields.iter().find_map(|f| find(&f.schema, name)),
+                _ => None,
+            }
+        }
+
+        find(schema, name).map(CompletionData::new)
+    }
+
+    /// Renders the data as pretty-printed JSON.
+    pub fn to_json_string(&self) -> String {
+        serde_json::to_string_pretty(self).expect("completion data is always serializable")
+    }
+
+    fn add(&mut self, schema: &Schema, path: &mut Vec<String>) {
+        match schema {
+            Schema::Option(inner) => self.add(inner, path),
+            Schema::List(inner) => self.add_child(inner, path, "*".to_owned()),
+            Schema::Map(_, value) => self.add_child(value, path, "*".to_owned()),
+            Schema::Tuple(elements) => {
+                for (i, element) in elements.iter().enumerate() {
+                    self.add_child(element, path, i.to_string());
+                }
+            }
+            Schema::Struct(s) => {
+                self.structs.push(StructCompletions {
+                    path: path.join("."),
+                    name: s.name.clone(),
+                    fields: s
+                        .fields
+                        .iter()
+                        .map(|field| FieldCompletions {
+                            name: field.name.clone(),
+                            ty: field.schema.to_string(),
+                            doc: field.doc.clone(),
+                            optional: field.default || matches!(field.schema, Schema::Option(_)),
+                        })
+                        .collect(),
+                });
+                for field in &s.fields {
+                    let step = Step::Key(field.name.clone()).to_string();
+                    self.add_child(&field.schema, path, step);
+                }
+            }
+            Schema::Enum(variants) => self.enums.push(EnumCompletions {
+                path: path.join("."),
+                variants: variants.clone(),
+            }),
+            Schema::Any | Schema::Bool | Schema::Integer | Schema::Float | Schema::String => {}
+        }
+    }
+
+    fn add_child(&mut self, schema: &Schema, path: &mut Vec<String>, step: String) {
+        path.push(step);
+        self.add(schema, path);
+        path.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{Field, StructSchema};
+
+    fn labels(source: &str, schema: Option<&Schema>) -> Vec<String> {
+        let pos = source.find('|').expect("cursor");
+        let source = source.replace('|', "");
+
+        complete(&source, pos, schema)
+            .into_iter()
+            .map(|item| item.label)
+            .collect()
+    }
+
+    fn schema() -> Schema {
+        Schema::Struct(StructSchema {
+            name: Some("Config".to_owned()),
+            fields: vec![
+                Field::new("fullscreen", Schema::Bool),
+                Field::new(
+                    "mode",
+                    Schema::Enum(vec!["Windowed".to_owned(), "Borderless".to_owned()]),
+                ),
+                Field::new(
+                    "layers",
+                    Schema::List(Box::new(Schema::Struct(StructSchema {
+                        name: None,
+                        fields: vec![Field::new("name", Schema::String)],
+                        rules: vec![],
+                    }))),
+                ),
+            ],
+            rules: vec![],
+        })
+    }
+
+    #[test]
+    fn fields() {
+        let schema = schema();
+        assert_eq!(
+            labels("Config(mode: Windowed, |)", Some(&schema)),
+            ["fullscreen", "layers"]
+        );
+        assert_eq!(labels("(f|", Some(&schema)), ["fullscreen"]);
+        assert_eq!(labels("(layers: [(|", Some(&schema)), ["name"]);
+        assert_eq!(
+            labels("[(a: 1, b: 2), (b: 3, |), (c: 4)]", None),
+            ["a", "c"]
+        );
+    }
+
+    #[test]
+    fn values() {
+        let schema = schema();
+        assert_eq!(
+            labels("(mode: |)", Some(&schema)),
+            ["Windowed", "Borderless"]
+        );
+        assert_eq!(labels("(fullscreen: t|", Some(&schema)), ["true"]);
+        assert_eq!(labels("|", Some(&schema)), ["Config"]);
+        assert_eq!(labels("(layers: |)", Some(&schema)), Vec::<String>::new());
+        assert_eq!(
+            labels("[(mode: Fast), (mode: |), (mode: Slow)]", None),
+            ["true", "false", "Fast", "Slow"]
+        );
+        assert_eq!(labels("(a: \"|\")", None), Vec::<String>::new());
+        assert_eq!(labels("(a: 1 // |\n)", None), Vec::<String>::new());
+    }
+
+    #[test]
+    fn export() {
+        let mut schema = schema();
+        if let Schema::Struct(s) = &mut schema {
+            s.fields[0].doc = Some("Cover the whole screen".to_owned());
+        }
+        let data = CompletionData::new(&schema);
+
+        assert_eq!(
+            data.structs
+                .iter()
+                .map(|s| (s.path.as_str(), s.name.as_deref()))
+                .collect::<Vec<_>>(),
+            [("", Some("Config")), ("layers.*", None)]
+        );
+        assert_eq!(
+            data.structs[0].fields[0],
+            FieldCompletions {
+                name: "fullscreen".to_owned(),
+                ty: "bool".to_owned(),
+                doc: Some("Cover the whole screen".to_owned()),
+                optional: false,
+            }
+        );
+        assert_eq!(
+            data.enums,
+            [EnumCompletions {
+                path: "mode".to_owned(),
+                variants: vec!["Windowed".to_owned(), "Borderless".to_owned()],
+            }]
+        );
+        assert!(data.to_json_string().contains(r#""type": "list<struct>""#));
+
+        let layers = Schema::Map(Box::new(Schema::String), Box::new(schema));
+        let data = CompletionData::for_struct(&layers, "Config").expect("Config");
+        assert_eq!(data.structs[0].path, "");
+        assert_eq!(CompletionData::for_struct(&layers, "Layer"), None);
+    }
+
+    #[test]
+    fn extensions() {
+        assert_eq!(labels("#![|", None), ["enable"]);
+        assert_eq!(
+            labels("#![enable(|)]", None),
+            [
+                "unwrap_newtypes",
+                "implicit_some",
+                "unit_literals",
+                "color_literals"
+            ]
+        );
+        assert_eq!(
+            labels("#![enable(unwrap_newtypes, |)] ()", None),
+            ["implicit_some", "unit_literals", "color_literals"]
+        );
+    }
+}
@@ -0,0 +1,45 @@
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::{lint::LintConfig, Error};
+
+/// Name of the project configuration file
+pub const CONFIG_FILE_NAME: &str = "ron-utils.ron";
+
+/// Project configuration, read from a `ron-utils.ron` file.
+///
+/// ```text
+/// (
+///     lint: (
+///         struct_names: Some(Named),
+///         root_type: Some("Config"),
+///     ),
+/// )
+/// ```
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub lint: LintConfig,
+}
+
+impl Config {
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        ron_reboot::utf8_parser::serde::from_file(path)
+    }
+
+    /// Searches `dir` and its ancestors for a `ron-utils.ron`,
+    /// returning the path of the file that was found and its contents.
+    pub fn discover(dir: impl AsRef<Path>) -> Result<Option<(PathBuf, Self)>, Error> {
+        for dir in dir.as_ref().ancestors() {
+            let path = dir.join(CONFIG_FILE_NAME);
+            if path.is_file() {
+                let config = Config::from_file(&path)?;
+
+                return Ok(Some((path, config)));
+            }
+        }
+
+        Ok(None)
+    }
+}
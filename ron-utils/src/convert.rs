@@ -0,0 +1,269 @@
+//! Converting .ron files to other formats, e.g. as a step of an asset
+//! pipeline
+//!
+//! Directories are converted into a copy of their structure, in which only
+//! outputs older than their .ron file are written again.
+
+use std::{
+    fmt,
+    fs::{self, create_dir_all},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use ron_reboot::{utf8_parser::ast_from_str, Error, Value};
+
+use crate::{eval::eval_str, read_fs_string, write_fs_string};
+
+/// The format files are converted to
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ConvertFormat {
+    /// Pretty-printed JSON, losing what JSON can't express (see
+    /// `ron_reboot::value::json`)
+    Json,
+    /// Canonical RON with extensions resolved, see [`eval_str`]
+    Ron,
+}
+
+impl ConvertFormat {
+    pub fn variants() -> [&'static str; 2] {
+        ["json", "ron"]
+    }
+
+    /// The extension of converted files
+    pub fn extension(self) -> &'static str {
+        match self {
+            ConvertFormat::Json => "json",
+            ConvertFormat::Ron => "ron",
+        }
+    }
+}
+
+impl FromStr for ConvertFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(ConvertFormat::Json),
+            "ron" => Ok(ConvertFormat::Ron),
+            _ => Err(format!(
+                "valid values: {}",
+                Self::variants().to_vec().join(", ")
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ConvertFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.extension())
+    }
+}
+
+/// Converts the document `source` to `format`, ending with a newline
+pub fn convert_str(source: &str, format: ConvertFormat) -> Result<String, Error> {
+    let output = match format {
+        ConvertFormat::Json => {
+            let json = serde_json::Value::from(Value::from(ast_from_str(source)?));
+            serde_json::to_string_pretty(&json).expect("JSON values are always serializable")
+        }
+        ConvertFormat::Ron => eval_str(source, None)?,
+    };
+
+    Ok(output + "\n")
+}
+
+/// What [`convert_dir`] did
+#[derive(Debug, Default)]
+pub struct ConvertSummary {
+    /// Files written
+    pub converted: Vec<PathBuf>,
+    /// Files whose output was newer or already had the converted contents
+    pub up_to_date: usize,
+    /// Files that couldn't be read, converted or written
+    pub errors: Vec<Error>,
+}
+
+impl fmt::Display for ConvertSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} converted, {} up to date, {} failed",
+            self.converted.len(),
+            self.up_to_date,
+            self.errors.len()
+        )
+    }
+}
+
+/// Converts `input`, a .ron file or a directory searched for them, into
+/// `out_dir`, where each output has the path of its .ron file relative to
+/// `input` and the extension of `format`
+///
+/// Outputs newer than their .ron file are skipped unless `force` is set, and
+/// outputs that already have the converted contents aren't written again.
+/// Errors of single files are collected, so that the others are still
+/// converted.
+pub fn convert_dir(
+    input: &Path,
+    out_dir: &Path,
+    format: ConvertFormat,
+    force: bool,
+    summary: &mut ConvertSummary,
+) {
+    let files = match input.is_dir() {
+        true => ron_files(input, &mut summary.errors),
+        false => vec![input.to_owned()],
+    };
+
+    for file in files {
+        let relative = match input.is_dir() {
+            true => file.strip_prefix(input).unwrap_or(&file),
+            false => Path::new(file.file_name().unwrap_or_default()),
+        };
+        let output = out_dir.join(relative).with_extension(format.extension());
+        if !force && is_newer(&output, &file) {
+            summary.up_to_date += 1;
+            continue;
+        }
+
+        let converted = read_fs_string(&file).and_then(|source| {
+            convert_str(&source, format)
+                .map_err(|e| e.context_file_name(file.display().to_string()))
+        });
+        let written = converted.and_then(|converted| {
+            if fs::read_to_string(&output).ok().as_ref() == Some(&converted) {
+                return Ok(false);
+            }
+            if let Some(parent) = output.parent() {
+                create_dir_all(parent)
+                    .map_err(|e| Error::from(e).context_file_name(parent.display().to_string()))?;
+            }
+
+            write_fs_string(&output, &converted).map(|_| true)
+        });
+        match written {
+            Ok(true) => summary.converted.push(output),
+            Ok(false) => summary.up_to_date += 1,
+            Err(e) => summary.errors.push(e),
+        }
+    }
+}
+
+/// The .ron files below `dir`, sorted so that the output is the same on every
+/// platform; directories that can't be read are added to `errors`
+pub fn ron_files(dir: &Path, errors: &mut Vec<Error>) -> Vec<PathBuf> {
+    files_with_extension(dir, "ron", errors)
+}
+
+/// Like [`ron_files`], for files ending in `.<extension>`
+pub(crate) fn files_with_extension(
+    dir: &Path,
+    extension: &str,
+    errors: &mut Vec<Error>,
+) -> Vec<PathBuf> {
+    let mut files = vec![];
+    let mut dirs = vec![dir.to_owned()];
+    while let Some(dir) = dirs.pop() {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                errors.push(Error::from(e).context_file_name(dir.display().to_string()));
+                continue;
+            }
+        };
+        for path in entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+        {
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path.extension().is_some_and(|ext| ext == extension) {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+
+    files
+}
+
+/// Whether `output` was modified after `input`
+fn is_newer(output: &Path, input: &Path) -> bool {
+    let modified = |path: &Path| fs::metadata(path).and_then(|metadata| metadata.modified());
+
+    match (modified(output), modified(input)) {
+        (Ok(output), Ok(input)) => output > input,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{thread::sleep, time::Duration};
+
+    use super::*;
+
+    #[test]
+    fn formats() {
+        let source = "#![enable(unit_literals)]\nConfig(a: Some(1), b: [(), 2s])";
+        assert_eq!(
+            convert_str(source, ConvertFormat::Json).unwrap(),
+            "{\n  \"a\": 1,\n  \"b\": [\n    null,\n    \"2s\"\n  ]\n}\n"
+        );
+        assert_eq!(
+            convert_str(source, ConvertFormat::Ron).unwrap(),
+            "Config(\n    a: Some(1),\n    b: [\n        (),\n        \"2s\",\n    ],\n)\n"
+        );
+    }
+
+    #[test]
+    fn mirrored_dirs() {
+        let dir = std::env::temp_dir().join(format!("ron-utils-convert-{}", std::process::id()));
+        let (assets, out) = (dir.join("assets"), dir.join("out"));
+        create_dir_all(assets.join("levels")).unwrap();
+        fs::write(assets.join("game.ron"), "(a: 1)").unwrap();
+        fs::write(assets.join("levels/one.ron"), "[1]").unwrap();
+        fs::write(assets.join("levels/broken.ron"), "[1").unwrap();
+        fs::write(assets.join("readme.txt"), "not converted").unwrap();
+
+        let convert = || {
+            let mut summary = ConvertSummary::default();
+            convert_dir(&assets, &out, ConvertFormat::Json, false, &mut summary);
+            summary
+        };
+
+        let summary = convert();
+        assert_eq!(summary.to_string(), "2 converted, 0 up to date, 1 failed");
+        assert_eq!(
+            summary.converted,
+            [out.join("game.json"), out.join("levels/one.json")]
+        );
+        assert_eq!(
+            fs::read_to_string(out.join("levels/one.json")).unwrap(),
+            "[\n  1\n]\n"
+        );
+
+        // Only changed files are written again
+        sleep(Duration::from_millis(20));
+        fs::write(assets.join("game.ron"), "(a: 2)").unwrap();
+        assert_eq!(convert().to_string(), "1 converted, 1 up to date, 1 failed");
+        assert_eq!(
+            fs::read_to_string(out.join("game.json")).unwrap(),
+            "{\n  \"a\": 2\n}\n"
+        );
+
+        // A single file goes directly into the output directory
+        let mut summary = ConvertSummary::default();
+        convert_dir(
+            &assets.join("levels/one.ron"),
+            &out,
+            ConvertFormat::Ron,
+            false,
+            &mut summary,
+        );
+        assert_eq!(summary.converted, [out.join("one.ron")]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,422 @@
+//! Imports the test suite of the original RON implementation
+//! (<https://github.com/ron-rs/ron>) into the regression corpus of
+//! `tests/corpus`, to notice where the parsers disagree as it evolves
+//!
+//! The inputs are the suite's .ron files and the string literals its Rust
+//! tests pass to `from_str`. Literals of statements that expect an `Err` are
+//! left out: most of them test deserializing into the wrong type, which a
+//! corpus of syntax can't check. Everything else has to parse, so the inputs
+//! that do are written to `valid/upstream_*.ron` and the others are reported.
+//!
+//! The suite is licensed under MIT OR Apache-2.0. Each imported file starts
+//! with a comment naming where it's from and its license, so the notice the
+//! licenses ask for stays with the copy.
+
+use std::{
+    collections::HashSet,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use ron_reboot::{utf8_parser::ast_from_str, Error};
+
+use crate::{convert::files_with_extension, read_fs_string, write_fs_string};
+
+/// The prefix of imported files, which tells them apart from the corpus'
+/// own, e.g. `upstream_tests_escape_12.ron`
+pub const PREFIX: &str = "upstream_";
+
+const LICENSE: &str = "MIT OR Apache-2.0";
+
+/// An input of the upstream suite
+#[derive(Clone, Debug, PartialEq)]
+pub struct UpstreamCase {
+    /// Where the input is in the suite, e.g. `tests/escape.rs:12`
+    pub origin: String,
+    /// The name of its corpus file
+    pub file_name: String,
+    pub source: String,
+}
+
+/// The inputs of the suite checked out at `dir`, sorted by their file names;
+/// files that can't be read are added to `errors`
+pub fn upstream_cases(dir: &Path, errors: &mut Vec<Error>) -> Vec<UpstreamCase> {
+    let mut files = files_with_extension(dir, "ron", errors);
+    files.extend(files_with_extension(dir, "rs", errors));
+
+    let mut cases = vec![];
+    let mut names = HashSet::new();
+    for path in files {
+        let source = match read_fs_string(&path) {
+            Ok(source) => source,
+            Err(e) => {
+                errors.push(e);
+                continue;
+            }
+        };
+        let relative = path.strip_prefix(dir).unwrap_or(&path);
+        let origin = relative.display().to_string().replace('\\', "/");
+        let stem = stem(relative);
+
+        if path.extension().is_some_and(|ext| ext == "ron") {
+            names.insert(stem.clone());
+            cases.push(UpstreamCase {
+                origin,
+                file_name: format!("{}{}.ron", PREFIX, stem),
+                source,
+            });
+            continue;
+        }
+        for (line, literal) in from_str_literals(&source) {
+            // Literals on the same line are numbered
+            let name = (1..)
+                .map(|n| match n {
+                    1 => format!("{}_{}", stem, line),
+                    n => format!("{}_{}_{}", stem, line, n),
+                })
+                .find(|name| !names.contains(name))
+                .unwrap_or_default();
+            names.insert(name.clone());
+            cases.push(UpstreamCase {
+                origin: format!("{}:{}", origin, line),
+                file_name: format!("{}{}.ron", PREFIX, name),
+                source: literal,
+            });
+        }
+    }
+    cases.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+
+    cases
+}
+
+/// `path` without its extension, with `_` for separators and other chars
+/// that aren't ASCII letters or digits
+fn stem(path: &Path) -> String {
+    path.with_extension("")
+        .to_string_lossy()
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c.to_ascii_lowercase(),
+            false => '_',
+        })
+        .collect()
+}
+
+/// The lines and values of the string literals passed to `from_str` in the
+/// Rust code `source`, except in statements that expect an `Err`
+fn from_str_literals(source: &str) -> Vec<(usize, String)> {
+    let mut literals = vec![];
+    for (i, _) in source.match_indices("from_str") {
+        let before = &source[..i];
+        // Comparisons with other formats
+        if before.ends_with("json::") || before.ends_with("toml::") {
+            continue;
+        }
+        let after = &source[i + "from_str".len()..];
+        let args = match skip_turbofish(after).trim_start().strip_prefix('(') {
+            Some(args) => args,
+            None => continue,
+        };
+        let args = args.trim_start();
+        let (literal, len) = match rust_string(args) {
+            Some(literal) => literal,
+            None => continue,
+        };
+
+        let start = before.rfind([';', '{', '}']).map_or(0, |i| i + 1);
+        let rest = &args[len..];
+        let mut end = rest.find(';').map_or(rest.len(), |end| end + 1);
+        // The result of `let de = from_str(..);` is checked by the next one
+        if source[start..i].trim_start().starts_with("let ") {
+            end += rest[end..].find(';').unwrap_or(rest.len() - end);
+        }
+        let statement = format!("{}{}", &source[start..i], &rest[..end]);
+        if ["Err", "err(", "is_err", "unwrap_err"]
+            .iter()
+            .any(|err| statement.contains(err))
+        {
+            continue;
+        }
+
+        literals.push((before.matches('\n').count() + 1, literal));
+    }
+
+    literals
+}
+
+/// `input` after a leading `::<..>`
+fn skip_turbofish(input: &str) -> &str {
+    let generics = match input.strip_prefix("::<") {
+        Some(generics) => generics,
+        None => return input,
+    };
+    let mut depth = 1;
+    for (i, c) in generics.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            _ => {}
+        }
+        if depth == 0 {
+            return &generics[i + 1..];
+        }
+    }
+
+    input
+}
+
+/// The value of the Rust string literal `input` starts with, plain or raw,
+/// and the length of the literal
+fn rust_string(input: &str) -> Option<(String, usize)> {
+    if let Some(raw) = input.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        let body = raw[hashes..].strip_prefix('"')?;
+        let close = format!("\"{}", "#".repeat(hashes));
+        let end = body.find(&close)?;
+
+        return Some((body[..end].to_owned(), 1 + hashes + 1 + end + close.len()));
+    }
+
+    let body = input.strip_prefix('"')?;
+    let mut value = String::new();
+    let mut chars = body.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((value, i + 2)),
+            '\\' => match chars.next()?.1 {
+                'n' => value.push('\n'),
+                'r' => value.push('\r'),
+                't' => value.push('\t'),
+                '0' => value.push('\0'),
+                c @ ('\\' | '"' | '\'') => value.push(c),
+                'x' => {
+                    let hex: String = (0..2)
+                        .filter_map(|_| chars.next())
+                        .map(|(_, c)| c)
+                        .collect();
+                    value.push(char::from(u8::from_str_radix(&hex, 16).ok()?));
+                }
+                'u' => {
+                    chars.next().filter(|&(_, c)| c == '{')?;
+                    let hex: String = chars
+                        .by_ref()
+                        .map(|(_, c)| c)
+                        .take_while(|&c| c != '}')
+                        .collect();
+                    value.push(char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?);
+                }
+                // A line continuation skips the line break and indentation
+                '\n' => while chars.next_if(|(_, c)| c.is_whitespace()).is_some() {},
+                _ => return None,
+            },
+            c => value.push(c),
+        }
+    }
+
+    None
+}
+
+/// What [`import`] changed in the corpus
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub written: Vec<PathBuf>,
+    pub unchanged: usize,
+    /// Imported files whose input isn't in the suite any more
+    pub removed: Vec<PathBuf>,
+    /// Inputs that don't parse, although the suite expects them to
+    pub disagreements: Vec<Error>,
+}
+
+impl fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for path in &self.written {
+            writeln!(f, "wrote {}", path.display())?;
+        }
+        for path in &self.removed {
+            writeln!(f, "removed {}", path.display())?;
+        }
+        writeln!(
+            f,
+            "{} written, {} unchanged, {} removed, {} not parsing",
+            self.written.len(),
+            self.unchanged,
+            self.removed.len(),
+            self.disagreements.len()
+        )
+    }
+}
+
+/// Writes the `cases` that parse to `valid/` of the `corpus` and removes
+/// imported files that aren't among them any more; `revision` is the commit
+/// of the suite, recorded in the files
+///
+/// Inputs that are already in the corpus are skipped, so an input that
+/// appears more than once is only imported once.
+pub fn import(
+    cases: &[UpstreamCase],
+    corpus: &Path,
+    revision: Option<&str>,
+) -> Result<ImportReport, Error> {
+    let valid = corpus.join("valid");
+    fs::create_dir_all(&valid)
+        .map_err(|e| Error::from(e).context_file_name(valid.display().to_string()))?;
+
+    let mut report = ImportReport::default();
+    let mut imported = HashSet::new();
+    let mut sources = HashSet::new();
+    for case in cases {
+        if !sources.insert(case.source.as_str()) {
+            continue;
+        }
+        if let Err(e) = ast_from_str(&case.source) {
+            report.disagreements.push(
+                e.context_file_name(case.origin.clone())
+                    .context_file_content(case.source.clone()),
+            );
+            continue;
+        }
+
+        let path = valid.join(&case.file_name);
+        let contents = corpus_file(case, revision);
+        imported.insert(case.file_name.as_str());
+        match read_fs_string(&path) {
+            Ok(old) if old == contents => report.unchanged += 1,
+            _ => {
+                write_fs_string(&path, &contents)?;
+                report.written.push(path);
+            }
+        }
+    }
+
+    let entries = fs::read_dir(&valid)
+        .map_err(|e| Error::from(e).context_file_name(valid.display().to_string()))?;
+    let mut stale: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            name.starts_with(PREFIX) && name.ends_with(".ron") && !imported.contains(&*name)
+        })
+        .collect();
+    stale.sort();
+    for path in stale {
+        fs::remove_file(&path)
+            .map_err(|e| Error::from(e).context_file_name(path.display().to_string()))?;
+        report.removed.push(path);
+    }
+
+    Ok(report)
+}
+
+/// The contents of the corpus file of `case`, starting with where it's from
+fn corpus_file(case: &UpstreamCase, revision: Option<&str>) -> String {
+    let revision = revision
+        .map(|rev| format!(" at {}", rev))
+        .unwrap_or_default();
+    let mut contents = format!(
+        "// Imported from the RON test suite, https://github.com/ron-rs/ron\n// {}{}, licensed under {}\n{}",
+        case.origin, revision, LICENSE, case.source
+    );
+    if !contents.ends_with('\n') {
+        contents.push('\n');
+    }
+
+    contents
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn literals() {
+        let source = r##"
+fn test() {
+    let a: A = ron::from_str("(a: \"\u{1F600}\", b: '\x41')").unwrap();
+    assert_eq!(from_str::<Vec<u8>>(r#"[1, 2]"#), Ok(vec![1, 2]));
+    assert!(ron::from_str::<u8>("300").is_err());
+    assert_eq!(
+        from_str::<u8>("-1"),
+        Err(SpannedError { .. })
+    );
+    let json: J = serde_json::from_str("{}").unwrap();
+    let s = from_str_with_options("1");
+    let long: L = from_str("[\
+        1,
+    ]").unwrap();
+    let de: Result<u8> = from_str("#");
+    assert_eq!(de, err(ErrorCode::ExpectedAttribute, 1, 2));
+}
+"##;
+
+        assert_eq!(
+            from_str_literals(source),
+            vec![
+                (3, "(a: \"\u{1F600}\", b: 'A')".to_owned()),
+                (4, "[1, 2]".to_owned()),
+                (12, "[1,\n    ]".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn imports() {
+        let dir = std::env::temp_dir().join(format!("ron-utils-corpus-{}", std::process::id()));
+        let upstream = dir.join("upstream");
+        let corpus = dir.join("corpus");
+        fs::create_dir_all(upstream.join("tests")).unwrap();
+        fs::create_dir_all(corpus.join("valid")).unwrap();
+        fs::write(
+            upstream.join("tests/value.ron"),
+            "#![enable(implicit_some)]\n(a: 1)",
+        )
+        .unwrap();
+        fs::write(
+            upstream.join("tests/parse.rs"),
+            "from_str(\"[1]\"); from_str(\"[1]\");\nfrom_str(\"(a: )\");",
+        )
+        .unwrap();
+        fs::write(corpus.join("valid/upstream_old.ron"), "1").unwrap();
+        fs::write(corpus.join("valid/own.ron"), "1").unwrap();
+
+        let mut errors = vec![];
+        let cases = upstream_cases(&upstream, &mut errors);
+        assert!(errors.is_empty());
+        let names: Vec<_> = cases.iter().map(|case| case.file_name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "upstream_tests_parse_1.ron",
+                "upstream_tests_parse_1_2.ron",
+                "upstream_tests_parse_2.ron",
+                "upstream_tests_value.ron",
+            ]
+        );
+
+        let report = import(&cases, &corpus, Some("abc123")).unwrap();
+        assert_eq!(report.written.len(), 2);
+        assert_eq!(report.removed, [corpus.join("valid/upstream_old.ron")]);
+        assert_eq!(report.disagreements.len(), 1);
+        assert_eq!(
+            report.disagreements[0]
+                .context
+                .as_ref()
+                .unwrap()
+                .file_name
+                .as_deref(),
+            Some("tests/parse.rs:2")
+        );
+        let imported = fs::read_to_string(corpus.join("valid/upstream_tests_value.ron")).unwrap();
+        assert_eq!(
+            imported,
+            "// Imported from the RON test suite, https://github.com/ron-rs/ron\n// tests/value.ron at abc123, licensed under MIT OR Apache-2.0\n#![enable(implicit_some)]\n(a: 1)\n"
+        );
+        assert!(ast_from_str(&imported).is_ok());
+        assert!(corpus.join("valid/own.ron").exists());
+
+        let report = import(&cases, &corpus, Some("abc123")).unwrap();
+        assert_eq!((report.written.len(), report.unchanged), (0, 2));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
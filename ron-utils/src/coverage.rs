@@ -0,0 +1,486 @@
+//! Which constructs of the RON grammar a corpus of documents uses, e.g. to
+//! find out what a test corpus never exercises
+//!
+//! The constructs are grouped like the sections of the [`spec`](crate::spec)
+//! suite. Besides the values of the parsed document, the written text is
+//! looked at for what the parser doesn't keep, like comments, the radix of
+//! integers and trailing commas.
+
+use std::fmt;
+
+use ron_reboot::{
+    ast::{walk_expr, Attribute, Expr, Extension, Ron, Spanned, Tagged, Untagged, Visitor},
+    lex::{block_comment, comments, trivia_len},
+    utf8_parser::ast_from_str,
+    Error, LineIndex, Location,
+};
+
+/// A construct of the grammar, e.g. a raw string
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Construct {
+    /// Grammar section, e.g. `"Strings"`
+    pub section: &'static str,
+    /// The name, which is unique across all sections
+    pub name: &'static str,
+}
+
+const fn construct(section: &'static str, name: &'static str) -> Construct {
+    Construct { section, name }
+}
+
+/// All constructs that are recorded, grouped by section
+pub const CONSTRUCTS: &[Construct] = &[
+    construct("Whitespace and comments", "line comment"),
+    construct("Whitespace and comments", "doc comment"),
+    construct("Whitespace and comments", "block comment"),
+    construct("Whitespace and comments", "nested block comment"),
+    construct("Extensions", "implicit_some"),
+    construct("Extensions", "unwrap_newtypes"),
+    construct("Extensions", "unit_literals"),
+    construct("Extensions", "color_literals"),
+    construct("Extensions", "registered extension"),
+    construct("Numbers", "unsigned integer"),
+    construct("Numbers", "negative integer"),
+    construct("Numbers", "explicit plus sign"),
+    construct("Numbers", "hexadecimal"),
+    construct("Numbers", "octal"),
+    construct("Numbers", "binary"),
+    construct("Numbers", "float"),
+    construct("Numbers", "float without integer part"),
+    construct("Numbers", "exponent"),
+    construct("Extension literals", "quantity"),
+    construct("Extension literals", "color"),
+    construct("Strings", "string"),
+    construct("Strings", "escapes"),
+    construct("Strings", "unicode escape"),
+    construct("Strings", "escaped line break"),
+    construct("Strings", "raw string"),
+    construct("Booleans", "true"),
+    construct("Booleans", "false"),
+    construct("Options", "none"),
+    construct("Options", "some"),
+    construct("Lists", "list"),
+    construct("Lists", "empty list"),
+    construct("Lists", "trailing comma in list"),
+    construct("Maps", "map"),
+    construct("Maps", "empty map"),
+    construct("Maps", "complex key"),
+    construct("Maps", "trailing comma in map"),
+    construct("Tuples", "tuple"),
+    construct("Tuples", "unit"),
+    construct("Tuples", "trailing comma in tuple"),
+    construct("Structs", "named struct"),
+    construct("Structs", "struct without name"),
+    construct("Structs", "tuple struct"),
+    construct("Structs", "unit struct"),
+    construct("Structs", "empty named struct"),
+    construct("Structs", "trailing comma in struct"),
+    construct("Identifiers", "raw identifier"),
+];
+
+/// How often the documents of a corpus use each of the [`CONSTRUCTS`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Coverage {
+    /// The uses of each construct, in the order of [`CONSTRUCTS`]
+    pub hits: Vec<usize>,
+    /// How many documents were recorded
+    pub documents: usize,
+}
+
+impl Default for Coverage {
+    fn default() -> Self {
+        Coverage {
+            hits: vec![0; CONSTRUCTS.len()],
+            documents: 0,
+        }
+    }
+}
+
+impl Coverage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the constructs `source` uses; nothing is recorded if it is
+    /// invalid
+    pub fn add_str(&mut self, source: &str) -> Result<(), Error> {
+        self.add_ron(source, &ast_from_str(source)?);
+
+        Ok(())
+    }
+
+    /// Records the constructs of `ron`, parsed from `source`
+    pub fn add_ron(&mut self, source: &str, ron: &Ron) {
+        let extensions = ron
+            .attributes
+            .iter()
+            .flat_map(|attribute| match &attribute.value {
+                Attribute::Enable(extensions) => extensions.value.iter(),
+                _ => [].iter(),
+            });
+        for extension in extensions {
+            let name = match extension.value {
+                Extension::ImplicitSome => "implicit_some",
+                Extension::UnwrapNewtypes => "unwrap_newtypes",
+                Extension::UnitLiterals => "unit_literals",
+                Extension::ColorLiterals => "color_literals",
+                Extension::Other(_) => "registered extension",
+                _ => continue,
+            };
+            self.hit(name);
+        }
+
+        let mut record = Record {
+            source,
+            index: LineIndex::new(source),
+            coverage: self,
+        };
+        record.visit_ron(ron);
+        self.comments(source);
+        self.documents += 1;
+    }
+
+    /// How many constructs were used at least once
+    pub fn covered(&self) -> usize {
+        self.hits.iter().filter(|&&hits| hits > 0).count()
+    }
+
+    /// The constructs that were never used
+    pub fn uncovered(&self) -> impl Iterator<Item = Construct> + '_ {
+        CONSTRUCTS
+            .iter()
+            .zip(&self.hits)
+            .filter(|(_, &hits)| hits == 0)
+            .map(|(&construct, _)| construct)
+    }
+
+    /// Displays only the constructs that were never used, followed by the
+    /// summary
+    pub fn uncovered_report(&self) -> Uncovered<'_> {
+        Uncovered(self)
+    }
+
+    fn hit(&mut self, name: &str) {
+        match CONSTRUCTS
+            .iter()
+            .position(|construct| construct.name == name)
+        {
+            Some(i) => self.hits[i] += 1,
+            None => debug_assert!(false, "unknown construct `{}`", name),
+        }
+    }
+
+    /// Records the comments of `source`
+    fn comments(&mut self, source: &str) {
+        for comment in comments(source.as_bytes()) {
+            let text = &source[comment];
+            if text.starts_with("/*") {
+                self.hit("block comment");
+                if block_comment(text.as_bytes()).nested {
+                    self.hit("nested block comment");
+                }
+            } else if text.starts_with("///") && !text.starts_with("////") {
+                self.hit("doc comment");
+            } else {
+                self.hit("line comment");
+            }
+        }
+    }
+
+    fn write(&self, f: &mut fmt::Formatter<'_>, only_uncovered: bool) -> fmt::Result {
+        let mut section = None;
+        for (construct, &hits) in CONSTRUCTS.iter().zip(&self.hits) {
+            if only_uncovered && hits > 0 {
+                continue;
+            }
+            if section != Some(construct.section) {
+                section = Some(construct.section);
+                writeln!(f, "{}", construct.section)?;
+            }
+
+            match hits {
+                0 => writeln!(f, "  {:<30}{:>8}", construct.name, "never")?,
+                hits => writeln!(f, "  {:<30}{:>8}", construct.name, hits)?,
+            }
+        }
+
+        writeln!(
+            f,
+            "\n{} of {} constructs used by {} documents",
+            self.covered(),
+            CONSTRUCTS.len(),
+            self.documents
+        )
+    }
+}
+
+impl fmt::Display for Coverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, false)
+    }
+}
+
+/// See [`Coverage::uncovered_report`]
+pub struct Uncovered<'a>(&'a Coverage);
+
+impl fmt::Display for Uncovered<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.write(f, true)
+    }
+}
+
+/// Records the constructs of the values of a document
+struct Record<'s, 'c> {
+    source: &'s str,
+    index: LineIndex<'s>,
+    coverage: &'c mut Coverage,
+}
+
+impl<'s> Record<'s, '_> {
+    fn text(&self, start: Location, end: Location) -> &'s str {
+        &self.source[self.index.byte_offset(start)..self.index.byte_offset(end)]
+    }
+
+    /// Records a trailing comma if one follows `last`, the end of the last
+    /// element of a container ending at `end`
+    fn trailing_comma(&mut self, last: Option<Location>, end: Location, name: &str) {
+        if let Some(last) = last {
+            let between = self.text(last, end).as_bytes();
+            if between[trivia_len(between)..].starts_with(b",") {
+                self.coverage.hit(name);
+            }
+        }
+    }
+
+    fn ident(&mut self, start: Location, end: Location) {
+        if self.text(start, end).starts_with("r#") {
+            self.coverage.hit("raw identifier");
+        }
+    }
+
+    fn string(&mut self, expr: &Spanned<Expr>) {
+        let text = self.text(expr.start, expr.end);
+        if text.starts_with('r') {
+            self.coverage.hit("raw string");
+            return;
+        }
+
+        self.coverage.hit("string");
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                continue;
+            }
+            self.coverage.hit(match chars.next() {
+                Some('u') => "unicode escape",
+                Some(' ' | '\t' | '\n' | '\r') => "escaped line break",
+                _ => "escapes",
+            });
+        }
+    }
+
+    fn number(&mut self, expr: &Spanned<Expr>) {
+        let text = self.text(expr.start, expr.end);
+        let unsigned = text.trim_start_matches(['+', '-']);
+        let integer = matches!(expr.value, Expr::Integer(_));
+        match text.chars().next() {
+            Some('+') => self.coverage.hit("explicit plus sign"),
+            Some('-') if integer => self.coverage.hit("negative integer"),
+            _ if integer => self.coverage.hit("unsigned integer"),
+            _ => {}
+        }
+
+        match &expr.value {
+            Expr::Integer(_) => match unsigned.get(..2) {
+                Some("0x") => self.coverage.hit("hexadecimal"),
+                Some("0o") => self.coverage.hit("octal"),
+                Some("0b") => self.coverage.hit("binary"),
+                _ => {}
+            },
+            Expr::Decimal(decimal) => {
+                self.coverage.hit("float");
+                if decimal.whole.is_none() {
+                    self.coverage.hit("float without integer part");
+                }
+                if decimal.exponent.is_some() {
+                    self.coverage.hit("exponent");
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn tagged(&mut self, expr: &Spanned<Expr>, tagged: &Tagged) {
+        self.ident(tagged.ident.start, tagged.ident.end);
+
+        let untagged = &tagged.untagged;
+        match &untagged.value {
+            Untagged::Unit => self.coverage.hit("unit struct"),
+            Untagged::Tuple(tuple) if tuple.elements.is_empty() => {
+                self.coverage.hit("empty named struct")
+            }
+            Untagged::Tuple(tuple) => {
+                self.coverage.hit("tuple struct");
+                let last = tuple.elements.last().map(|element| element.end);
+                self.trailing_comma(last, expr.end, "trailing comma in tuple");
+            }
+            Untagged::Struct(s) => {
+                self.coverage.hit("named struct");
+                for field in &s.fields {
+                    self.ident(field.value.key.start, field.value.key.end);
+                }
+                let last = s.fields.last().map(|field| field.value.value.end);
+                self.trailing_comma(last, expr.end, "trailing comma in struct");
+            }
+        }
+    }
+}
+
+impl<'a> Visitor<'a> for Record<'_, '_> {
+    fn visit_expr(&mut self, expr: &Spanned<Expr<'a>>) {
+        match &expr.value {
+            Expr::Unit => self.coverage.hit("unit"),
+            Expr::Optional(None) => self.coverage.hit("none"),
+            Expr::Optional(Some(_)) => self.coverage.hit("some"),
+            Expr::Tagged(tagged) => self.tagged(expr, tagged),
+            Expr::Bool(true) => self.coverage.hit("true"),
+            Expr::Bool(false) => self.coverage.hit("false"),
+            Expr::Tuple(tuple) => {
+                self.coverage.hit("tuple");
+                let last = tuple.elements.last().map(|element| element.end);
+                self.trailing_comma(last, expr.end, "trailing comma in tuple");
+            }
+            Expr::List(list) => {
+                self.coverage.hit(match list.elements.is_empty() {
+                    true => "empty list",
+                    false => "list",
+                });
+                let last = list.elements.last().map(|element| element.end);
+                self.trailing_comma(last, expr.end, "trailing comma in list");
+            }
+            Expr::Map(map) => {
+                self.coverage.hit(match map.entries.is_empty() {
+                    true => "empty map",
+                    false => "map",
+                });
+                for entry in &map.entries {
+                    let complex = match &entry.value.key.value {
+                        Expr::Tagged(tagged) => !matches!(tagged.untagged.value, Untagged::Unit),
+                        key => matches!(
+                            key,
+                            Expr::Tuple(_) | Expr::List(_) | Expr::Map(_) | Expr::Struct(_)
+                        ),
+                    };
+                    if complex {
+                        self.coverage.hit("complex key");
+                    }
+                }
+                let last = map.entries.last().map(|entry| entry.value.value.end);
+                self.trailing_comma(last, expr.end, "trailing comma in map");
+            }
+            Expr::Struct(s) => {
+                self.coverage.hit("struct without name");
+                for field in &s.fields {
+                    self.ident(field.value.key.start, field.value.key.end);
+                }
+                let last = s.fields.last().map(|field| field.value.value.end);
+                self.trailing_comma(last, expr.end, "trailing comma in struct");
+            }
+            Expr::Integer(_) | Expr::Decimal(_) => self.number(expr),
+            Expr::Str(_) | Expr::String(_) | Expr::EscapedStr(_) => self.string(expr),
+            Expr::Quantity(_) => self.coverage.hit("quantity"),
+            Expr::Color(_) => self.coverage.hit("color"),
+            _ => {}
+        }
+
+        walk_expr(self, expr);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hits(coverage: &Coverage, name: &str) -> usize {
+        let i = CONSTRUCTS.iter().position(|c| c.name == name).unwrap();
+
+        coverage.hits[i]
+    }
+
+    #[test]
+    fn names_are_unique() {
+        for (i, construct) in CONSTRUCTS.iter().enumerate() {
+            assert!(!CONSTRUCTS[..i].iter().any(|c| c.name == construct.name));
+        }
+    }
+
+    #[test]
+    fn records_constructs() {
+        let mut coverage = Coverage::new();
+        coverage
+            .add_str(
+                r##"#![enable(implicit_some, unit_literals)]
+Config(
+    /// The name
+    name: "a \"b\" // not a comment", // a comment
+    /* outer /* inner */ */
+    raw: r#"/* not a comment */"#,
+    r#type: [0x1F, -2, +1.5e3, .5, 250ms, Some(()), None,],
+    lookup: {(1, 2): Point(x: 1,), "c": Empty(), "\u{e4}": Unit},
+)"##,
+            )
+            .unwrap();
+        assert!(coverage.add_str("[1").is_err());
+
+        for (name, expected) in [
+            ("implicit_some", 1),
+            ("unit_literals", 1),
+            ("doc comment", 1),
+            ("line comment", 1),
+            ("block comment", 1),
+            ("nested block comment", 1),
+            ("string", 3),
+            ("escapes", 2),
+            ("unicode escape", 1),
+            ("raw string", 1),
+            ("raw identifier", 1),
+            ("hexadecimal", 1),
+            ("unsigned integer", 4),
+            ("negative integer", 1),
+            ("explicit plus sign", 1),
+            ("float without integer part", 1),
+            ("exponent", 1),
+            ("quantity", 1),
+            ("some", 1),
+            ("unit", 1),
+            ("none", 1),
+            ("trailing comma in list", 1),
+            ("complex key", 1),
+            ("named struct", 2),
+            ("trailing comma in struct", 2),
+            ("empty named struct", 1),
+            ("unit struct", 1),
+            ("trailing comma in map", 0),
+            ("octal", 0),
+            ("color_literals", 0),
+        ] {
+            assert_eq!(hits(&coverage, name), expected, "{}", name);
+        }
+        assert_eq!(coverage.documents, 1);
+
+        let report = coverage.to_string();
+        assert!(report.starts_with("Whitespace and comments\n  line comment"));
+        assert!(report.contains("  octal                            never\n"));
+        let uncovered = coverage.uncovered_report().to_string();
+        assert!(!uncovered.contains("line comment"));
+        assert!(uncovered.ends_with(&format!(
+            "{} of {} constructs used by 1 documents\n",
+            coverage.covered(),
+            CONSTRUCTS.len()
+        )));
+        assert_eq!(
+            coverage.uncovered().count(),
+            CONSTRUCTS.len() - coverage.covered()
+        );
+    }
+}
@@ -0,0 +1,422 @@
+//! Line diffs, to show what a command would change in a file
+//!
+//! Every command that prints changes renders them with [`render_diff`], so
+//! they look the same everywhere. [`render_token_diff`] compares the tokens
+//! of lines instead, for files that may not parse.
+
+use ron_reboot::lex::{block_comment, line_comment_len, quoted_len, raw_string_len};
+
+/// How many unchanged lines are shown around changes
+const CONTEXT: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Line {
+    Same(usize, usize),
+    Removed(usize),
+    Added(usize),
+}
+
+/// ANSI escape codes of the parts of a diff
+const BOLD: &str = "\x1b[1m";
+const CYAN: &str = "\x1b[36m";
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const RESET: &str = "\x1b[0m";
+
+/// The changes from `old` to `new` in the unified diff format, with `name`
+/// in the header; empty if they are the same
+///
+/// With `color`, the lines are colored with ANSI escape codes like in
+/// `git diff`, for printing to a terminal. Without it, the diff is a patch
+/// that e.g. `git apply` or `patch -p1` can apply.
+pub fn render_diff(name: &str, old: &str, new: &str, color: bool) -> String {
+    let paint = |style: &str, line: String| paint(color, style, line);
+    // Lines keep their line breaks, so that a missing one at the end of a
+    // file counts as a change
+    let diff_line = |marker: char, style: &str, line: &str| match line.strip_suffix('\n') {
+        Some(line) => paint(style, format!("{}{}", marker, line)),
+        None => paint(style, format!("{}{}", marker, line)) + "\\ No newline at end of file\n",
+    };
+
+    let (old, new) = (
+        old.split_inclusive('\n').collect::<Vec<_>>(),
+        new.split_inclusive('\n').collect::<Vec<_>>(),
+    );
+    let lines = diff_lines(&old, &new);
+    if lines.iter().all(|line| matches!(line, Line::Same(..))) {
+        return String::new();
+    }
+
+    let mut diff = paint(BOLD, format!("--- a/{}", name)) + &paint(BOLD, format!("+++ b/{}", name));
+    for hunk in hunks(&lines) {
+        // How many lines of each file come before the hunk
+        let old_before = lines[..hunk.start]
+            .iter()
+            .filter(|line| !matches!(line, Line::Added(_)))
+            .count();
+        let new_before = lines[..hunk.start]
+            .iter()
+            .filter(|line| !matches!(line, Line::Removed(_)))
+            .count();
+        let hunk = &lines[hunk];
+        let old_len = hunk
+            .iter()
+            .filter(|line| !matches!(line, Line::Added(_)))
+            .count();
+        let new_len = hunk
+            .iter()
+            .filter(|line| !matches!(line, Line::Removed(_)))
+            .count();
+        // An empty range starts at the line before it, like in `diff -u`
+        let start = |before, len| if len == 0 { before } else { before + 1 };
+        diff += &paint(
+            CYAN,
+            format!(
+                "@@ -{},{} +{},{} @@",
+                start(old_before, old_len),
+                old_len,
+                start(new_before, new_len),
+                new_len
+            ),
+        );
+
+        for line in hunk {
+            diff += &match *line {
+                Line::Same(i, _) => diff_line(' ', "", old[i]),
+                Line::Removed(i) => diff_line('-', RED, old[i]),
+                Line::Added(j) => diff_line('+', GREEN, new[j]),
+            };
+        }
+    }
+
+    diff
+}
+
+/// The changes from `old` to `new` like [`render_diff`], ignoring changes of
+/// whitespace between tokens, e.g. indentation or `a:1` becoming `a: 1`
+///
+/// The files don't need to parse. Lines are compared by their tokens, so
+/// whitespace in strings still counts, and blank lines are left out. The diff
+/// shows the lines of `old` where they're the same and is meant to be read,
+/// not applied: the line numbers of hunks are where they start, and the
+/// lengths count the lines shown.
+pub fn render_token_diff(name: &str, old: &str, new: &str, color: bool) -> String {
+    let (old, new) = (token_lines(old), token_lines(new));
+    let lines = diff_lines(&keys(&old), &keys(&new));
+    if lines.iter().all(|line| matches!(line, Line::Same(..))) {
+        return String::new();
+    }
+
+    let mut diff = paint(color, BOLD, format!("--- a/{}", name))
+        + &paint(color, BOLD, format!("+++ b/{}", name));
+    for hunk in hunks(&lines) {
+        let hunk = &lines[hunk];
+        let side = |removed: bool| {
+            let numbers = hunk.iter().filter_map(|line| match (*line, removed) {
+                (Line::Same(i, _) | Line::Removed(i), true) => Some(old[i].number),
+                (Line::Same(_, j) | Line::Added(j), false) => Some(new[j].number),
+                _ => None,
+            });
+            let (len, first) = numbers.fold((0, None), |(len, first), number| {
+                (len + 1, first.or(Some(number)))
+            });
+            format!("{},{}", first.unwrap_or_default(), len)
+        };
+        diff += &paint(
+            color,
+            CYAN,
+            format!("@@ -{} +{} @@", side(true), side(false)),
+        );
+
+        for line in hunk {
+            diff += &match *line {
+                Line::Same(i, _) => paint(color, "", format!(" {}", old[i].text)),
+                Line::Removed(i) => paint(color, RED, format!("-{}", old[i].text)),
+                Line::Added(j) => paint(color, GREEN, format!("+{}", new[j].text)),
+            };
+        }
+    }
+
+    diff
+}
+
+/// `line` with a line break, colored with `style` if `color` is set
+fn paint(color: bool, style: &str, line: String) -> String {
+    match color && !style.is_empty() {
+        true => format!("{}{}{}\n", style, line, RESET),
+        false => line + "\n",
+    }
+}
+
+/// A line that isn't blank, with its tokens separated by single spaces
+struct TokenLine<'a> {
+    /// The number of the line, starting at 1
+    number: usize,
+    text: &'a str,
+    key: String,
+}
+
+fn keys<'b>(lines: &'b [TokenLine<'_>]) -> Vec<&'b str> {
+    lines.iter().map(|line| line.key.as_str()).collect()
+}
+
+/// The lines of `source` that have tokens
+///
+/// Tokens are strings, chars, comments, the punctuation `()[]{},:` and runs
+/// of other characters, like identifiers and numbers. Strings and comments
+/// spanning lines are split into a token on each line. Unclosed ones run to
+/// the end of the file.
+fn token_lines(source: &str) -> Vec<TokenLine<'_>> {
+    let mut keys = vec![String::new(); source.split('\n').count()];
+    let mut push = |line: usize, token: &str| {
+        if token.is_empty() {
+            return;
+        }
+        let key = &mut keys[line];
+        if !key.is_empty() {
+            key.push(' ');
+        }
+        key.push_str(token);
+    };
+    // Pushes the parts of a token spanning lines to each of its lines
+    let mut push_lines = |line: &mut usize, token: &str, trim: bool| {
+        for (k, part) in token.split('\n').enumerate() {
+            push(*line + k, if trim { part.trim() } else { part });
+        }
+        *line += token.matches('\n').count();
+    };
+
+    let mut line = 0;
+    let mut rest = source;
+    while let Some(c) = rest.chars().next() {
+        let len = if c == '\n' {
+            line += 1;
+            1
+        } else if c.is_whitespace() {
+            c.len_utf8()
+        } else if rest.starts_with("//") {
+            let len = line_comment_len(rest.as_bytes());
+            push_lines(&mut line, rest[..len].trim_end(), false);
+            len
+        } else if rest.starts_with("/*") {
+            let len = block_comment(rest.as_bytes()).len;
+            push_lines(&mut line, &rest[..len], true);
+            len
+        } else if let Some(len) = raw_string_len(rest.as_bytes()) {
+            push_lines(&mut line, &rest[..len], false);
+            len
+        } else if c == '"' || c == '\'' {
+            let len = quoted_len(rest.as_bytes());
+            push_lines(&mut line, &rest[..len], false);
+            len
+        } else if "()[]{},:".contains(c) {
+            push_lines(&mut line, &rest[..1], false);
+            1
+        } else {
+            let len = rest
+                .find(|c: char| c.is_whitespace() || "()[]{},:\"'".contains(c))
+                .unwrap_or(rest.len());
+            push_lines(&mut line, &rest[..len], false);
+            len
+        };
+        rest = &rest[len..];
+    }
+
+    source
+        .split('\n')
+        .zip(keys)
+        .enumerate()
+        .filter(|(_, (_, key))| !key.is_empty())
+        .map(|(i, (text, key))| TokenLine {
+            number: i + 1,
+            text: text.trim_end_matches('\r'),
+            key,
+        })
+        .collect()
+}
+
+/// The lines of both files, matched by their longest common subsequence
+fn diff_lines(old: &[&str], new: &[&str]) -> Vec<Line> {
+    // Edits usually touch a few lines, so only the lines between the common
+    // prefix and suffix are compared
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (old_mid, new_mid) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+
+    // `common[i][j]` is the length of the longest common subsequence of
+    // `old_mid[i..]` and `new_mid[j..]`
+    let mut common = vec![vec![0; new_mid.len() + 1]; old_mid.len() + 1];
+    for i in (0..old_mid.len()).rev() {
+        for j in (0..new_mid.len()).rev() {
+            common[i][j] = match old_mid[i] == new_mid[j] {
+                true => common[i + 1][j + 1] + 1,
+                false => common[i + 1][j].max(common[i][j + 1]),
+            };
+        }
+    }
+
+    let mut lines = (0..prefix).map(|i| Line::Same(i, i)).collect::<Vec<_>>();
+    let (mut i, mut j) = (0, 0);
+    while i < old_mid.len() || j < new_mid.len() {
+        if i < old_mid.len() && j < new_mid.len() && old_mid[i] == new_mid[j] {
+            lines.push(Line::Same(prefix + i, prefix + j));
+            i += 1;
+            j += 1;
+        } else if i < old_mid.len() && (j == new_mid.len() || common[i + 1][j] >= common[i][j + 1])
+        {
+            // Removed lines go before the lines added in their place
+            lines.push(Line::Removed(prefix + i));
+            i += 1;
+        } else {
+            lines.push(Line::Added(prefix + j));
+            j += 1;
+        }
+    }
+    lines.extend((0..suffix).map(|k| Line::Same(old.len() - suffix + k, new.len() - suffix + k)));
+
+    lines
+}
+
+/// The ranges of `lines` to show: the changes with up to [`CONTEXT`]
+/// unchanged lines around them, merged where they overlap
+fn hunks(lines: &[Line]) -> Vec<std::ops::Range<usize>> {
+    let mut hunks: Vec<std::ops::Range<usize>> = vec![];
+    for (k, line) in lines.iter().enumerate() {
+        if matches!(line, Line::Same(..)) {
+            continue;
+        }
+        let range = k.saturating_sub(CONTEXT)..(k + 1 + CONTEXT).min(lines.len());
+        match hunks.last_mut() {
+            Some(last) if last.end >= range.start => last.end = range.end,
+            _ => hunks.push(range),
+        }
+    }
+
+    hunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unified() {
+        let old =
+            "(\n    a: 1,\n    b: 2,\n    c: 3,\n    d: 4,\n    e: 5,\n    f: 6,\n    g: 7,\n)\n";
+        let new = old.replace("b: 2", "b: 20").replace("    g: 7,\n", "");
+
+        assert_eq!(render_diff("x.ron", old, old, false), "");
+        assert_eq!(
+            render_diff("x.ron", old, &new, false),
+            "--- a/x.ron\n+++ b/x.ron\n\
+             @@ -1,9 +1,8 @@\n (\n     a: 1,\n-    b: 2,\n+    b: 20,\n     c: 3,\n     d: 4,\n     \
+             e: 5,\n     f: 6,\n-    g: 7,\n )\n"
+        );
+
+        let diff = render_diff("x.ron", "a\n", "a\nb\n", false);
+        assert!(diff.ends_with("@@ -1,1 +1,2 @@\n a\n+b\n"), "{}", diff);
+        let diff = render_diff("x.ron", "", "a\n", false);
+        assert!(diff.ends_with("@@ -0,0 +1,1 @@\n+a\n"), "{}", diff);
+    }
+
+    #[test]
+    fn separate_hunks() {
+        let old = (1..=20).map(|i| format!("{}\n", i)).collect::<String>();
+        let new = old.replacen("2\n", "two\n", 1).replace("19\n", "");
+        let diff = render_diff("x", &old, &new, false);
+
+        assert!(
+            diff.contains("@@ -1,5 +1,5 @@\n 1\n-2\n+two\n 3\n 4\n 5\n"),
+            "{}",
+            diff
+        );
+        assert!(
+            diff.ends_with("@@ -16,5 +16,4 @@\n 16\n 17\n 18\n-19\n 20\n"),
+            "{}",
+            diff
+        );
+    }
+
+    #[test]
+    fn missing_final_newline() {
+        let diff = render_diff("x.ron", "a\nb", "a\nb\n", false);
+        assert!(
+            diff.ends_with("@@ -1,2 +1,2 @@\n a\n-b\n\\ No newline at end of file\n+b\n"),
+            "{}",
+            diff
+        );
+        let diff = render_diff("x.ron", "a\n", "b", false);
+        assert!(
+            diff.ends_with("-a\n+b\n\\ No newline at end of file\n"),
+            "{}",
+            diff
+        );
+    }
+
+    #[test]
+    fn token_diff() {
+        let old = "(\n  a:1,\n\n  b: \"x  y\", // note\n  c: [\n)\n";
+        let new = "(\n    a: 1,\n    b: \"x y\", //  note\n    c: [\n)";
+
+        assert_eq!(
+            render_token_diff("x.ron", old, &old.replace("\n  ", "\n\t"), false),
+            ""
+        );
+        assert_eq!(
+            render_token_diff("x.ron", old, new, false),
+            "--- a/x.ron\n+++ b/x.ron\n\
+             @@ -1,5 +1,5 @@\n (\n   a:1,\n-  b: \"x  y\", // note\n+    b: \"x y\", //  note\n   c: [\n )\n"
+        );
+    }
+
+    #[test]
+    fn tokens() {
+        let keys = |source| {
+            token_lines(source)
+                .into_iter()
+                .map(|line| (line.number, line.key))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            keys("Point(x:-1.5e3,r#type:r#\"a\"b\"#)"),
+            [(1, "Point ( x : -1.5e3 , r#type : r#\"a\"b\"# )".to_owned())]
+        );
+        assert_eq!(
+            keys("[\"a\n  b\", '\\'', /* c\n   d */ 1]"),
+            [
+                (1, "[ \"a".to_owned()),
+                (2, "  b\" , '\\'' , /* c".to_owned()),
+                (3, "d */ 1 ]".to_owned()),
+            ]
+        );
+        // Unclosed strings and comments run to the end
+        assert_eq!(
+            keys("(a: \"b,\n)"),
+            [(1, "( a : \"b,".to_owned()), (2, ")".to_owned())]
+        );
+        assert_eq!(
+            keys("/* a /* b */\n c"),
+            [(1, "/* a /* b */".to_owned()), (2, "c".to_owned())]
+        );
+    }
+
+    #[test]
+    fn colored() {
+        let diff = render_diff("x.ron", "a\nb\n", "a\nc\n", true);
+
+        assert_eq!(
+            diff,
+            "\x1b[1m--- a/x.ron\x1b[0m\n\x1b[1m+++ b/x.ron\x1b[0m\n\
+             \x1b[36m@@ -1,2 +1,2 @@\x1b[0m\n a\n\x1b[31m-b\x1b[0m\n\x1b[32m+c\x1b[0m\n"
+        );
+    }
+}
@@ -0,0 +1,273 @@
+//! Reference documentation generated from an example document
+//!
+//! Every struct field becomes one entry listing its type (inferred from the
+//! example value), the example value as default and its `///` doc comment.
+
+use std::{fmt, str::FromStr};
+
+use ron_reboot::{
+    ast::{Expr, Struct},
+    utf8_parser::ast_from_str,
+    Error,
+};
+
+use crate::edit::byte_offset;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DocFormat {
+    Markdown,
+    Html,
+}
+
+impl DocFormat {
+    pub fn variants() -> [&'static str; 2] {
+        ["markdown", "html"]
+    }
+}
+
+impl FromStr for DocFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "markdown" => Ok(DocFormat::Markdown),
+            "html" => Ok(DocFormat::Html),
+            _ => Err(format!(
+                "valid values: {}",
+                Self::variants().to_vec().join(", ")
+            )),
+        }
+    }
+}
+
+impl fmt::Display for DocFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DocFormat::Markdown => write!(f, "markdown"),
+            DocFormat::Html => write!(f, "html"),
+        }
+    }
+}
+
+/// One documented field
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldDoc {
+    /// Path from the root, e.g. `window.width` (see [`ron_reboot::path`])
+    pub path: String,
+    pub ty: String,
+    /// The example value, if it fits on one line
+    pub default: Option<String>,
+    pub doc: Option<String>,
+}
+
+/// Collects the documentation of all (nested) struct fields of `source`.
+pub fn field_docs(source: &str) -> Result<(Option<String>, Vec<FieldDoc>), Error> {
+    let ron = ast_from_str(source)?;
+    let mut fields = vec![];
+
+    let title = match &ron.expr.value {
+        Expr::Tagged(t) => Some(t.ident.value.as_str().to_owned()),
+        _ => None,
+    };
+    if let Some(s) = as_struct(&ron.expr.value) {
+        collect_fields(source, "", s, &mut fields);
+    }
+
+    Ok((title, fields))
+}
+
+/// Renders the reference documentation of the format `source` is an example of.
+///
+/// `title` is used if the root struct is anonymous.
+pub fn document_str(source: &str, title: &str, format: DocFormat) -> Result<String, Error> {
+    let (root_name, fields) = field_docs(source)?;
+    let title = root_name.as_deref().unwrap_or(title);
+
+    Ok(match format {
+        DocFormat::Markdown => render_markdown(title, &fields),
+        DocFormat::Html => render_html(title, &fields),
+    })
+}
+
+fn as_struct<'a, 'b>(expr: &'b Expr<'a>) -> Option<&'b Struct<'a>> {
+    match expr {
+        Expr::Optional(Some(inner)) => as_struct(&inner.value),
+        _ => expr.as_struct(),
+    }
+}
+
+fn collect_fields(source: &str, prefix: &str, s: &Struct, fields: &mut Vec<FieldDoc>) {
+    for field in &s.fields {
+        let field = &field.value;
+        let path = format!("{}{}", prefix, field.key.value.0);
+        let value = &field.value;
+
+        let nested = as_struct(&value.value);
+        let default = match nested {
+            Some(_) => None,
+            None => {
+                let text =
+                    &source[byte_offset(source, value.start)..byte_offset(source, value.end)];
+                let text = text.trim();
+                match text.contains('\n') {
+                    true => None,
+                    false => Some(text.to_owned()),
+                }
+            }
+        };
+
+        fields.push(FieldDoc {
+            path: path.clone(),
+            ty: type_name(&value.value),
+            default,
+            doc: field.doc_string(),
+        });
+
+        if let Some(nested) = nested {
+            collect_fields(source, &format!("{}.", path), nested, fields);
+        }
+    }
+}
+
+fn type_name(expr: &Expr) -> String {
+    match expr {
+        Expr::Unit => "unit".to_owned(),
+        Expr::Optional(Some(inner)) => format!("option<{}>", type_name(&inner.value)),
+        Expr::Optional(None) => "option".to_owned(),
+        Expr::Tagged(t) => t.ident.value.as_str().to_owned(),
+        Expr::Bool(_) => "bool".to_owned(),
+        Expr::Tuple(_) => "tuple".to_owned(),
+        Expr::List(_) => "list".to_owned(),
+        Expr::Map(_) => "map".to_owned(),
+        Expr::Struct(_) => "struct".to_owned(),
+        Expr::Integer(_) => "integer".to_owned(),
+        Expr::Str(_) | Expr::String(_) => "string".to_owned(),
+        Expr::Decimal(_) => "float".to_owned(),
+        Expr::Quantity(q) => q.unit.kind().name().to_owned(),
+        Expr::Color(_) => "color".to_owned(),
+        _ => "value".to_owned(),
+    }
+}
+
+fn render_markdown(title: &str, fields: &[FieldDoc]) -> String {
+    fn cell(s: &str) -> String {
+        s.replace('|', "\\|").replace('\n', " ")
+    }
+
+    let mut out = format!("# {}\n\n", title);
+    out.push_str("| Field | Type | Default | Description |\n");
+    out.push_str("| --- | --- | --- | --- |\n");
+    for field in fields {
+        out.push_str(&format!(
+            "| `{}` | {} | {} | {} |\n",
+            field.path,
+            cell(&field.ty),
+            field
+                .default
+                .as_deref()
+                .map(|d| format!("`{}`", cell(d)))
+                .unwrap_or_default(),
+            field.doc.as_deref().map(cell).unwrap_or_default(),
+        ));
+    }
+
+    out
+}
+
+fn render_html(title: &str, fields: &[FieldDoc]) -> String {
+    fn escape(s: &str) -> String {
+        s.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+    }
+
+    let mut out = format!("<h1>{}</h1>\n<table>\n", escape(title));
+    out.push_str("  <tr><th>Field</th><th>Type</th><th>Default</th><th>Description</th></tr>\n");
+    for field in fields {
+        out.push_str(&format!(
+            "  <tr><td><code>{}</code></td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            escape(&field.path),
+            escape(&field.ty),
+            field
+                .default
+                .as_deref()
+                .map(|d| format!("<code>{}</code>", escape(d)))
+                .unwrap_or_default(),
+            field
+                .doc
+                .as_deref()
+                .map(|d| escape(d).replace('\n', "<br>"))
+                .unwrap_or_default(),
+        ));
+    }
+    out.push_str("</table>\n");
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r#"Config(
+    /// Window size
+    /// in px
+    size: (1920, 1080),
+    title: Some("a | b"),
+    audio: (
+        /// Master volume
+        volume: 0.5,
+    ),
+)"#;
+
+    #[test]
+    fn fields() {
+        let (title, fields) = field_docs(SOURCE).unwrap();
+        assert_eq!(title.as_deref(), Some("Config"));
+        assert_eq!(
+            fields,
+            vec![
+                FieldDoc {
+                    path: "size".to_owned(),
+                    ty: "tuple".to_owned(),
+                    default: Some("(1920, 1080)".to_owned()),
+                    doc: Some("Window size\nin px".to_owned()),
+                },
+                FieldDoc {
+                    path: "title".to_owned(),
+                    ty: "option<string>".to_owned(),
+                    default: Some(r#"Some("a | b")"#.to_owned()),
+                    doc: None,
+                },
+                FieldDoc {
+                    path: "audio".to_owned(),
+                    ty: "struct".to_owned(),
+                    default: None,
+                    doc: None,
+                },
+                FieldDoc {
+                    path: "audio.volume".to_owned(),
+                    ty: "float".to_owned(),
+                    default: Some("0.5".to_owned()),
+                    doc: Some("Master volume".to_owned()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn markdown() {
+        let md = document_str(SOURCE, "unused", DocFormat::Markdown).unwrap();
+        assert!(md.starts_with("# Config\n"));
+        assert!(md.contains("| `size` | tuple | `(1920, 1080)` | Window size in px |\n"));
+        assert!(md.contains(r#"| `title` | option<string> | `Some("a \| b")` |  |"#));
+    }
+
+    #[test]
+    fn html() {
+        let html = document_str("(a: 1)", "Settings", DocFormat::Html).unwrap();
+        assert!(html.starts_with("<h1>Settings</h1>\n"));
+        assert!(html.contains("<td><code>a</code></td><td>integer</td><td><code>1</code></td>"));
+    }
+}
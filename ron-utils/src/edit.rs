@@ -0,0 +1,112 @@
+//! Byte-level edits of RON source text
+
+use ron_reboot::Location;
+
+/// Replaces the bytes `start..end` of the source with `replacement`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextEdit {
+    pub start: usize,
+    pub end: usize,
+    pub replacement: String,
+}
+
+impl TextEdit {
+    pub fn insert(at: usize, text: impl Into<String>) -> Self {
+        TextEdit {
+            start: at,
+            end: at,
+            replacement: text.into(),
+        }
+    }
+
+    pub fn delete(start: usize, end: usize) -> Self {
+        TextEdit {
+            start,
+            end,
+            replacement: String::new(),
+        }
+    }
+
+    /// Whether both edits touch the same bytes (or insert at the same position)
+    pub fn overlaps(&self, other: &TextEdit) -> bool {
+        self.start == other.start || (self.start < other.end && other.start < self.end)
+    }
+}
+
+/// Applies `edits` to `source`.
+///
+/// Edits overlapping an earlier edit are skipped, and edits equal to an
+/// earlier one are applied once.
+pub fn apply_edits<'a>(source: &str, edits: impl IntoIterator<Item = &'a TextEdit>) -> String {
+    let mut edits: Vec<&TextEdit> = edits.into_iter().collect();
+    edits.sort_by_key(|edit| (edit.start, edit.end));
+    edits.dedup();
+
+    let mut edited = String::with_capacity(source.len());
+    let mut copied_until = 0;
+    for edit in edits {
+        if edit.start < copied_until {
+            continue;
+        }
+
+        edited.push_str(&source[copied_until..edit.start]);
+        edited.push_str(&edit.replacement);
+        copied_until = edit.end;
+    }
+    edited.push_str(&source[copied_until..]);
+
+    edited
+}
+
+/// Byte offset of a (1-based line, 1-based char column) `Location` in `source`
+pub fn byte_offset(source: &str, location: Location) -> usize {
+    let line_start = match location.line {
+        0 | 1 => 0,
+        line => source
+            .match_indices('\n')
+            .nth(line as usize - 2)
+            .map(|(i, _)| i + 1)
+            .unwrap_or(source.len()),
+    };
+
+    source[line_start..]
+        .char_indices()
+        .nth(location.column.saturating_sub(1) as usize)
+        .map(|(i, _)| line_start + i)
+        .unwrap_or(source.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edits() {
+        let insert = TextEdit::insert(0, "a");
+        let edits = [
+            TextEdit::delete(1, 3),
+            insert.clone(),
+            TextEdit::delete(2, 4),
+            insert,
+        ];
+        assert_eq!(apply_edits("0123", &edits), "a03");
+    }
+
+    #[test]
+    fn offsets() {
+        let source = "(\n  a: \"ä\", b: 1,\n)";
+        assert_eq!(byte_offset(source, Location { line: 1, column: 1 }), 0);
+        assert_eq!(byte_offset(source, Location { line: 2, column: 3 }), 4);
+        assert_eq!(
+            byte_offset(
+                source,
+                Location {
+                    line: 2,
+                    column: 10
+                }
+            ),
+            12
+        );
+        assert_eq!(byte_offset(source, Location { line: 3, column: 1 }), 19);
+    }
+}
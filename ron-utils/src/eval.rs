@@ -0,0 +1,205 @@
+//! Printing documents the way the deserializer sees them, e.g. to find out
+//! what an extension did to a value
+
+use std::fmt::Write;
+
+use ron_reboot::{
+    utf8_parser::{ast_from_str, extension::ExtensionRegistry, serde::to_string},
+    Error, Value,
+};
+
+use crate::schema::Schema;
+
+/// The document `source` as canonical RON with its extensions resolved
+///
+/// Unit literals become the strings they are deserialized from and colors
+/// `(r, g, b, a)` tuples, and `#![enable(..)]` is dropped. With
+/// `implicit_some`, values are wrapped in `Some(..)` where `schema` expects
+/// an `Option`. Without a schema they are printed as written, like the
+/// values of `unwrap_newtypes` documents, which would need the Rust types.
+pub fn eval_str(source: &str, schema: Option<&Schema>) -> Result<String, Error> {
+    let ron = ast_from_str(source)?;
+    let implicit_some = ExtensionRegistry::new()
+        .deserialize_flags(&ron)
+        .implicit_some;
+
+    let mut value = Value::from(ron);
+    if let (true, Some(schema)) = (implicit_some, schema) {
+        value = wrap_some(schema, value);
+    }
+
+    let mut output = String::new();
+    write_value(&mut output, &value, 0)?;
+
+    Ok(output)
+}
+
+/// `value` with every value `schema` expects to be an `Option` in `Some(..)`
+fn wrap_some(schema: &Schema, value: Value) -> Value {
+    match (schema, value) {
+        (Schema::Option(_), Value::Option(None)) => Value::Option(None),
+        (Schema::Option(inner), Value::Option(Some(value))) => {
+            Value::Option(Some(Box::new(wrap_some(inner, *value))))
+        }
+        (Schema::Option(inner), value) => Value::Option(Some(Box::new(wrap_some(inner, value)))),
+        (Schema::List(element), Value::List(values)) => Value::List(
+            values
+                .into_iter()
+                .map(|value| wrap_some(element, value))
+                .collect(),
+        ),
+        (Schema::Map(key, value), Value::Map(entries)) => Value::Map(
+            entries
+                .into_iter()
+                .map(|(k, v)| (wrap_some(key, k), wrap_some(value, v)))
+                .collect(),
+        ),
+        (Schema::Tuple(elements), Value::Tuple(name, values)) => Value::Tuple(
+            name,
+            values
+                .into_iter()
+                .enumerate()
+                .map(|(i, value)| match elements.get(i) {
+                    Some(schema) => wrap_some(schema, value),
+                    None => value,
+                })
+                .collect(),
+        ),
+        (Schema::Struct(s), Value::Struct(name, fields)) => Value::Struct(
+            name,
+            fields
+                .into_iter()
+                .map(|(key, value)| match s.field(&key) {
+                    Some(field) => (key, wrap_some(&field.schema, value)),
+                    None => (key, value),
+                })
+                .collect(),
+        ),
+        (_, value) => value,
+    }
+}
+
+/// Writes `value` like `to_string_pretty` with the default config: lists,
+/// maps and structs with one element per line, tuples on one line
+fn write_value(output: &mut String, value: &Value, depth: usize) -> Result<(), Error> {
+    let indent = |depth: usize| "    ".repeat(depth);
+
+    match value {
+        Value::Bool(b) => output.push_str(&b.to_string()),
+        Value::Char(c) => output.push_str(&to_string(c)?),
+        Value::String(s) => output.push_str(&to_string(s)?),
+        Value::Number(n) => output.push_str(&n.map_to(|i| Ok(i.to_string()), |f| to_string(&f))?),
+        Value::Option(None) => output.push_str("None"),
+        Value::Option(Some(value)) => {
+            output.push_str("Some(");
+            write_value(output, value, depth)?;
+            output.push(')');
+        }
+        Value::Unit(name) => output.push_str(name.as_deref().unwrap_or("()")),
+        Value::Tuple(name, elements) => {
+            output.push_str(name.as_deref().unwrap_or_default());
+            output.push('(');
+            for (i, element) in elements.iter().enumerate() {
+                if i > 0 {
+                    output.push_str(", ");
+                }
+                write_value(output, element, depth)?;
+            }
+            output.push(')');
+        }
+        Value::List(elements) if elements.is_empty() => output.push_str("[]"),
+        Value::List(elements) => {
+            output.push_str("[\n");
+            for element in elements {
+                output.push_str(&indent(depth + 1));
+                write_value(output, element, depth + 1)?;
+                output.push_str(",\n");
+            }
+            let _ = write!(output, "{}]", indent(depth));
+        }
+        Value::Map(entries) if entries.is_empty() => output.push_str("{}"),
+        Value::Map(entries) => {
+            output.push_str("{\n");
+            for (key, value) in entries {
+                output.push_str(&indent(depth + 1));
+                write_value(output, key, depth + 1)?;
+                output.push_str(": ");
+                write_value(output, value, depth + 1)?;
+                output.push_str(",\n");
+            }
+            let _ = write!(output, "{}}}", indent(depth));
+        }
+        Value::Struct(name, fields) => {
+            output.push_str(name.as_deref().unwrap_or_default());
+            if fields.is_empty() {
+                output.push_str("()");
+                return Ok(());
+            }
+            output.push_str("(\n");
+            for (key, value) in fields {
+                let _ = write!(output, "{}{}: ", indent(depth + 1), key);
+                write_value(output, value, depth + 1)?;
+                output.push_str(",\n");
+            }
+            let _ = write!(output, "{})", indent(depth));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{Field, StructSchema};
+
+    #[test]
+    fn extensions_resolved() {
+        let source = "#![enable(unit_literals, color_literals)]
+// The window
+Window(timeout: 250ms, color: #ff800080, sizes: [(1, 2.5)], tags: {\"a\": Some(\"x\\ty\")})";
+        assert_eq!(
+            eval_str(source, None).unwrap(),
+            r#"Window(
+    timeout: "250ms",
+    color: (255, 128, 0, 128),
+    sizes: [
+        (1, 2.5),
+    ],
+    tags: {
+        "a": Some("x\ty"),
+    },
+)"#
+        );
+        assert_eq!(
+            eval_str("[(), Unit, Empty(), {}]", None).unwrap(),
+            "[\n    (),\n    Unit,\n    Empty(),\n    {},\n]"
+        );
+        assert!(eval_str("(a: 1s)", None).is_err());
+    }
+
+    #[test]
+    fn implicit_some() {
+        let schema = Schema::Struct(StructSchema {
+            fields: vec![
+                Field::new("a", Schema::Option(Box::new(Schema::Integer))),
+                Field::new("b", Schema::Option(Box::new(Schema::Integer))),
+                Field::new(
+                    "c",
+                    Schema::List(Box::new(Schema::Option(Box::new(Schema::Bool)))),
+                ),
+            ],
+            ..StructSchema::default()
+        });
+        let source = "#![enable(implicit_some)]\n(a: 1, b: None, c: [true, Some(false)], d: 2)";
+        assert_eq!(
+            eval_str(source, Some(&schema)).unwrap(),
+            "(\n    a: Some(1),\n    b: None,\n    c: [\n        Some(true),\n        Some(false),\n    ],\n    d: 2,\n)"
+        );
+        // Only documents that enable it
+        assert_eq!(
+            eval_str("(a: 1)", Some(&schema)).unwrap(),
+            "(\n    a: 1,\n)"
+        );
+    }
+}
@@ -0,0 +1,584 @@
+//! Formatting documents in a consistent layout, optionally in the style they
+//! are already written in
+//!
+//! Lists, maps and structs are written with one element per line, tuples on
+//! one line. Values like numbers and strings, attributes and the names of
+//! structs are kept as written, and so are `///` doc comments of fields and
+//! map entries. Other comments would be lost, so documents with them aren't
+//! formatted.
+//!
+//! [`detect_style`] infers the indentation and trailing commas of a
+//! document, so that files can be formatted one by one without changing the
+//! style they were written in.
+
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use ron_reboot::{
+    ast::{Expr, Ident, KeyValue, Ron, Spanned, Untagged},
+    lex::comments,
+    utf8_parser::ast_from_str,
+    ErrorKind, LineIndex,
+};
+
+use crate::{lint::StructNameStyle, Error};
+
+/// How a document is laid out
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Style {
+    /// The whitespace of one level of indentation, e.g. four spaces or a tab
+    pub indent: String,
+    /// Whether the last element of a list, map or struct is followed by a
+    /// comma
+    pub trailing_commas: bool,
+    /// Whether most structs are written with their names, if there are any
+    ///
+    /// Names are kept as written when formatting, since they can't be added
+    /// without the types and an enum variant can't be told apart from a
+    /// struct. The detected style can be enforced with the `struct_names`
+    /// lint.
+    pub struct_names: Option<StructNameStyle>,
+    /// The line breaks written between lines
+    ///
+    /// Line breaks in strings are kept as they are, since changing them
+    /// would change the strings.
+    pub newline: Newline,
+}
+
+impl Default for Style {
+    /// The layout of `to_string_pretty`: four spaces and trailing commas,
+    /// keeping the line breaks of the document
+    fn default() -> Self {
+        Style {
+            indent: "    ".to_owned(),
+            trailing_commas: true,
+            struct_names: None,
+            newline: Newline::Preserve,
+        }
+    }
+}
+
+/// Which line breaks a formatted document has
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Newline {
+    /// `\n`
+    Lf,
+    /// `\r\n`, as on Windows
+    CrLf,
+    /// The ones most lines of the document end with, `\n` for documents of
+    /// one line
+    Preserve,
+}
+
+impl Newline {
+    pub fn variants() -> [&'static str; 3] {
+        ["lf", "crlf", "preserve"]
+    }
+
+    /// The line ending of this style in `source`
+    fn line_break(self, source: &str) -> &'static str {
+        match self {
+            Newline::Lf => "\n",
+            Newline::CrLf => "\r\n",
+            Newline::Preserve => Newline::detect(source)
+                .unwrap_or(Newline::Lf)
+                .line_break(source),
+        }
+    }
+
+    /// The line ending most lines of `source` end with, if it has several
+    /// lines
+    fn detect(source: &str) -> Option<Newline> {
+        let mut votes = Votes::default();
+        for (i, _) in source.match_indices('\n') {
+            votes.vote(i > 0 && source.as_bytes()[i - 1] == b'\r');
+        }
+
+        votes.winner().map(|crlf| match crlf {
+            true => Newline::CrLf,
+            false => Newline::Lf,
+        })
+    }
+}
+
+impl FromStr for Newline {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lf" => Ok(Newline::Lf),
+            "crlf" => Ok(Newline::CrLf),
+            "preserve" => Ok(Newline::Preserve),
+            _ => Err(format!(
+                "valid values: {}",
+                Self::variants().to_vec().join(", ")
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Newline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Newline::Lf => write!(f, "lf"),
+            Newline::CrLf => write!(f, "crlf"),
+            Newline::Preserve => write!(f, "preserve"),
+        }
+    }
+}
+
+impl fmt::Display for Style {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.indent.as_str() {
+            "\t" => write!(f, "indent: tabs")?,
+            " " => write!(f, "indent: 1 space")?,
+            indent => write!(f, "indent: {} spaces", indent.len())?,
+        }
+        match self.trailing_commas {
+            true => write!(f, ", trailing commas")?,
+            false => write!(f, ", no trailing commas")?,
+        }
+        if let Some(style) = self.struct_names {
+            write!(f, ", struct names: {}", style)?;
+        }
+        write!(f, ", line breaks: {}", self.newline)
+    }
+}
+
+/// The style `source`, parsed as `ron`, is written in
+///
+/// The indentation is the most common step by which a line is indented
+/// more than the one before it, and trailing commas and struct names are
+/// what most lists, maps and structs have. Only lists, maps and structs
+/// spanning several lines count for trailing commas. The line breaks are the
+/// ones most lines end with. Whatever a document doesn't show is taken from
+/// [`Style::default`].
+pub fn detect_style(source: &str, ron: &Ron) -> Style {
+    let default = Style::default();
+    let mut detect = Detect {
+        source,
+        index: LineIndex::new(source),
+        trailing_commas: Votes::default(),
+        struct_names: Votes::default(),
+    };
+    detect.expr(&ron.expr);
+
+    Style {
+        indent: detect_indent(source).unwrap_or(default.indent),
+        trailing_commas: detect
+            .trailing_commas
+            .winner()
+            .unwrap_or(default.trailing_commas),
+        struct_names: detect.struct_names.winner().map(|named| match named {
+            true => StructNameStyle::Named,
+            false => StructNameStyle::Anonymous,
+        }),
+        newline: Newline::detect(source).unwrap_or(default.newline),
+    }
+}
+
+/// `source` formatted in `style`, which [`detect_style`] can infer
+///
+/// Fails if `source` is invalid or has comments that aren't doc comments of
+/// fields or map entries.
+pub fn format_str(source: &str, style: &Style) -> Result<String, Error> {
+    format_ron(source, &ast_from_str(source)?, style)
+}
+
+/// `source`, parsed as `ron`, formatted in `style`
+pub fn format_ron(source: &str, ron: &Ron, style: &Style) -> Result<String, Error> {
+    let mut writer = Writer {
+        source,
+        index: LineIndex::new(source),
+        style,
+        newline: style.newline.line_break(source),
+        output: String::new(),
+        kept: vec![],
+        doc_lines: vec![],
+    };
+    for attribute in &ron.attributes {
+        writer.copy(attribute);
+        writer.line_break();
+    }
+    writer.expr(&ron.expr, 0);
+    writer.line_break();
+
+    writer.check_comments()?;
+
+    Ok(writer.output)
+}
+
+/// The most common step by which a line of `source` is indented more than
+/// the one before it
+fn detect_indent(source: &str) -> Option<String> {
+    let mut steps = Votes::default();
+    let mut tabs = Votes::default();
+    let mut previous = 0;
+    for line in source.lines().filter(|line| !line.trim().is_empty()) {
+        let indent = &line[..line.len() - line.trim_start().len()];
+        if indent.len() > previous {
+            tabs.vote(indent.starts_with('\t'));
+            steps.vote(indent.len() - previous);
+        }
+        previous = indent.len();
+    }
+
+    match tabs.winner()? {
+        true => Some("\t".to_owned()),
+        false => Some(" ".repeat(steps.winner()?)),
+    }
+}
+
+/// Counts how often each value occurs
+#[derive(Default)]
+struct Votes<T>(HashMap<T, usize>);
+
+impl<T: Copy + Eq + Ord + std::hash::Hash> Votes<T> {
+    fn vote(&mut self, value: T) {
+        *self.0.entry(value).or_default() += 1;
+    }
+
+    /// The most common value, the smallest one of a tie
+    fn winner(&self) -> Option<T> {
+        self.0
+            .iter()
+            .max_by(|(a, a_votes), (b, b_votes)| a_votes.cmp(b_votes).then(b.cmp(a)))
+            .map(|(&value, _)| value)
+    }
+}
+
+struct Detect<'a> {
+    source: &'a str,
+    index: LineIndex<'a>,
+    trailing_commas: Votes<bool>,
+    /// Whether each struct has a name
+    struct_names: Votes<bool>,
+}
+
+impl Detect<'_> {
+    fn expr(&mut self, expr: &Spanned<Expr>) {
+        match &expr.value {
+            Expr::Struct(_) => self.struct_names.vote(false),
+            Expr::Tagged(t) if matches!(t.untagged.value, Untagged::Struct(_)) => {
+                self.struct_names.vote(true)
+            }
+            _ => {}
+        }
+
+        let last = match &expr.value {
+            Expr::List(l) => l.elements.last().map(|e| e.end),
+            Expr::Map(m) => m.entries.last().map(|e| e.end),
+            Expr::Struct(s) => s.fields.last().map(|f| f.end),
+            Expr::Tagged(t) => match &t.untagged.value {
+                Untagged::Struct(s) => s.fields.last().map(|f| f.end),
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some(last) = last.filter(|last| last.line < expr.end.line) {
+            let after = self.index.byte_offset(last);
+            let close = self.index.byte_offset(expr.end).saturating_sub(1);
+            let between = self.source.get(after..close).unwrap_or_default();
+            self.trailing_commas
+                .vote(between.trim_start().starts_with(','));
+        }
+
+        for child in expr.value.children() {
+            self.expr(child);
+        }
+    }
+}
+
+struct Writer<'a> {
+    source: &'a str,
+    index: LineIndex<'a>,
+    style: &'a Style,
+    /// The line break of `style` in the source
+    newline: &'static str,
+    output: String,
+    /// The byte ranges of the source that were copied, in order
+    kept: Vec<(usize, usize)>,
+    /// The (1-based) lines of the doc comments that were kept
+    doc_lines: Vec<u32>,
+}
+
+impl Writer<'_> {
+    /// Writes the source of `spanned` as it is
+    fn copy<T>(&mut self, spanned: &Spanned<T>) {
+        let (start, end) = (
+            self.index.byte_offset(spanned.start),
+            self.index.byte_offset(spanned.end),
+        );
+        self.output.push_str(&self.source[start..end]);
+        self.kept.push((start, end));
+    }
+
+    fn line_break(&mut self) {
+        self.output.push_str(self.newline);
+    }
+
+    fn indent(&mut self, depth: usize) {
+        for _ in 0..depth {
+            self.output.push_str(&self.style.indent);
+        }
+    }
+
+    fn expr(&mut self, expr: &Spanned<Expr>, depth: usize) {
+        match &expr.value {
+            Expr::Unit => self.output.push_str("()"),
+            Expr::Optional(Some(inner)) => {
+                self.output.push_str("Some(");
+                self.expr(inner, depth);
+                self.output.push(')');
+            }
+            Expr::Tagged(t) => {
+                self.copy(&t.ident);
+                match &t.untagged.value {
+                    Untagged::Unit => {}
+                    Untagged::Struct(s) => self.fields(&s.fields, depth),
+                    Untagged::Tuple(elements) => self.tuple(elements.iter(), depth),
+                }
+            }
+            Expr::Tuple(t) => self.tuple(t.iter(), depth),
+            Expr::List(l) => self.block(('[', ']'), l.iter(), depth, |writer, element, depth| {
+                writer.expr(element, depth)
+            }),
+            Expr::Map(m) => self.block(
+                ('{', '}'),
+                m.entries.iter(),
+                depth,
+                |writer, entry, depth| {
+                    writer.docs(&entry.value, entry.start.line, depth);
+                    writer.expr(&entry.value.key, depth);
+                    writer.output.push_str(": ");
+                    writer.expr(&entry.value.value, depth);
+                },
+            ),
+            Expr::Struct(s) => self.fields(&s.fields, depth),
+            _ => self.copy(expr),
+        }
+    }
+
+    fn tuple<'b, 'a: 'b>(
+        &mut self,
+        elements: impl Iterator<Item = &'b Spanned<Expr<'a>>>,
+        depth: usize,
+    ) {
+        self.output.push('(');
+        for (i, element) in elements.enumerate() {
+            if i > 0 {
+                self.output.push_str(", ");
+            }
+            self.expr(element, depth);
+        }
+        self.output.push(')');
+    }
+
+    fn fields(&mut self, fields: &[Spanned<KeyValue<Ident>>], depth: usize) {
+        self.block(('(', ')'), fields.iter(), depth, |writer, field, depth| {
+            writer.docs(&field.value, field.start.line, depth);
+            writer.copy(&field.value.key);
+            writer.output.push_str(": ");
+            writer.expr(&field.value.value, depth);
+        });
+    }
+
+    /// Writes `elements` between `open` and `close` with one per line,
+    /// indented one level deeper than `depth`
+    fn block<T>(
+        &mut self,
+        (open, close): (char, char),
+        elements: impl ExactSizeIterator<Item = T>,
+        depth: usize,
+        mut element: impl FnMut(&mut Self, T, usize),
+    ) {
+        self.output.push(open);
+        let len = elements.len();
+        for (i, e) in elements.enumerate() {
+            self.line_break();
+            self.indent(depth + 1);
+            element(self, e, depth + 1);
+            if i + 1 < len || self.style.trailing_commas {
+                self.output.push(',');
+            }
+        }
+        if len > 0 {
+            self.line_break();
+            self.indent(depth);
+        }
+        self.output.push(close);
+    }
+
+    /// Writes the doc comment of the key on `line`, each line of it followed
+    /// by a line break and the indentation of the key
+    fn docs<K>(&mut self, kv: &KeyValue<K>, line: u32, depth: usize) {
+        for (i, doc) in kv.doc.iter().enumerate() {
+            self.doc_lines
+                .push(line.saturating_sub((kv.doc.len() - i) as u32));
+            match doc.is_empty() {
+                true => self.output.push_str("///"),
+                false => {
+                    self.output.push_str("/// ");
+                    self.output.push_str(doc);
+                }
+            }
+            self.line_break();
+            self.indent(depth);
+        }
+    }
+
+    /// Fails at the first comment of the source that wasn't written
+    fn check_comments(&self) -> Result<(), Error> {
+        for comment in comments(self.source.as_bytes()) {
+            // Comments in what was copied, e.g. an attribute, are still there
+            let copied = self
+                .kept
+                .partition_point(|&(start, _)| start <= comment.start);
+            if copied > 0 && comment.start < self.kept[copied - 1].1 {
+                continue;
+            }
+
+            let text = &self.source[comment.clone()];
+            let start = self.index.location(comment.start);
+            let doc = text.starts_with("///") && !text.starts_with("////");
+            if !doc || !self.doc_lines.contains(&start.line) {
+                return Err(Error {
+                    kind: ErrorKind::Custom(
+                        "formatting would drop this comment, only doc comments of fields and map entries are kept".to_owned(),
+                    ),
+                    context: None,
+                }
+                .context_loc(start, self.index.location(comment.end))
+                .context_file_content(self.source.to_owned()));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect(source: &str) -> Style {
+        detect_style(source, &ast_from_str(source).unwrap())
+    }
+
+    #[test]
+    fn format() {
+        let source = "#![enable(unit_literals)]\nConfig(\n /// The title\n  title: r#\"a\"#, size: (800,600), timeout: 250ms,\nlayers: [Layer(name: \"x\\ty\",), None, Some(Fill)], keys: {1: (), \"a\": []},)";
+        assert_eq!(
+            format_str(source, &Style::default()).unwrap(),
+            r##"#![enable(unit_literals)]
+Config(
+    /// The title
+    title: r#"a"#,
+    size: (800, 600),
+    timeout: 250ms,
+    layers: [
+        Layer(
+            name: "x\ty",
+        ),
+        None,
+        Some(Fill),
+    ],
+    keys: {
+        1: (),
+        "a": [],
+    },
+)
+"##
+        );
+
+        let style = Style {
+            indent: "\t".to_owned(),
+            trailing_commas: false,
+            struct_names: None,
+            newline: Newline::Lf,
+        };
+        assert_eq!(
+            format_str("(a: [1, 2], b: ())", &style).unwrap(),
+            "(\n\ta: [\n\t\t1,\n\t\t2\n\t],\n\tb: ()\n)\n"
+        );
+
+        // Formatting twice changes nothing
+        let formatted = format_str(source, &style).unwrap();
+        assert_eq!(format_str(&formatted, &style).unwrap(), formatted);
+    }
+
+    #[test]
+    fn newlines() {
+        let source = "(a: [1],\r\n b: \"x\ny\")\r\n";
+        let crlf = "(\r\n    a: [\r\n        1,\r\n    ],\r\n    b: \"x\ny\",\r\n)\r\n";
+        assert_eq!(format_str(source, &Style::default()).unwrap(), crlf);
+        assert_eq!(format_str(crlf, &Style::default()).unwrap(), crlf);
+
+        let style = |newline| Style {
+            newline,
+            ..Style::default()
+        };
+        // The line break in the string stays
+        assert_eq!(
+            format_str(source, &style(Newline::Lf)).unwrap(),
+            "(\n    a: [\n        1,\n    ],\n    b: \"x\ny\",\n)\n"
+        );
+        assert_eq!(
+            format_str("[1]", &style(Newline::CrLf)).unwrap(),
+            "[\r\n    1,\r\n]\r\n"
+        );
+        assert_eq!(
+            format_str("[1]", &style(Newline::Preserve)).unwrap(),
+            "[\n    1,\n]\n"
+        );
+    }
+
+    #[test]
+    fn comments() {
+        let e = format_str(
+            "(\n    a: 1, // one\n    b: \"// no comment\",\n)",
+            &Style::default(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            e.context
+                .unwrap()
+                .start_end
+                .map(|(start, _)| (start.line, start.column)),
+            Some((2, 11))
+        );
+        assert!(format_str("[\n    /// not a field\n    1,\n]", &Style::default()).is_err());
+        assert!(format_str("/* root */ 1", &Style::default()).is_err());
+        assert_eq!(
+            format_str(
+                "(\n    /// A\n    ///\n    a: \"/* */\")",
+                &Style::default()
+            )
+            .unwrap(),
+            "(\n    /// A\n    ///\n    a: \"/* */\",\n)\n"
+        );
+    }
+
+    #[test]
+    fn detect_styles() {
+        assert_eq!(detect("1"), Style::default());
+        assert_eq!(
+            detect("Config(\n  a: [\n    1,\n    2\n  ],\n  b: Inner(\n    c: 1\n  )\n)"),
+            Style {
+                indent: "  ".to_owned(),
+                trailing_commas: false,
+                struct_names: Some(StructNameStyle::Named),
+                newline: Newline::Lf,
+            }
+        );
+        // Single-line containers don't count
+        assert_eq!(
+            detect("(\r\n\ta: [1, 2],\r\n\tb: (c: 1),\n)"),
+            Style {
+                indent: "\t".to_owned(),
+                trailing_commas: true,
+                struct_names: Some(StructNameStyle::Anonymous),
+                newline: Newline::CrLf,
+            }
+        );
+    }
+}
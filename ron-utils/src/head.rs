@@ -0,0 +1,287 @@
+//! Reading only the first elements of huge documents, e.g. to smoke check a
+//! multi-GB export without reading all of it
+//!
+//! The elements are those of the top-level list, or the fields and entries
+//! of a top-level struct, tuple or map. They are found by matching brackets,
+//! strings and comments, without parsing them, so the beginning of the
+//! document can be checked as if it ended after them.
+
+use std::{
+    fs::File,
+    io::{self, Read},
+    path::Path,
+};
+
+use crate::{text_from_bytes, Error};
+
+/// How many bytes are read at once
+const CHUNK_LEN: usize = 64 * 1024;
+
+/// How many bytes after a position have to be read to scan it, e.g. for the
+/// `#`s closing a raw string
+const LOOKAHEAD: usize = 256;
+
+/// The beginning of a document read by [`read_head`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Head {
+    /// The document up to the end of the last element that was read, closed
+    /// with the bracket of the top-level value if it was cut off
+    pub source: String,
+    /// Whether the document was cut off after the `n`th element; the rest
+    /// wasn't read
+    pub truncated: bool,
+}
+
+/// Reads the first `n` elements of the document `reader` reads, stopping
+/// after them
+///
+/// Documents without more than `n` elements are read completely.
+pub fn read_head(mut reader: impl Read, n: usize) -> Result<Head, Error> {
+    let mut bytes = vec![];
+    let mut scanner = Scanner::new(n);
+    loop {
+        let read = reader
+            .by_ref()
+            .take(CHUNK_LEN as u64)
+            .read_to_end(&mut bytes)?;
+        let eof = read < CHUNK_LEN;
+
+        if let Some(cut) = scanner.scan(&bytes, eof) {
+            bytes.truncate(cut);
+            bytes.push(scanner.closing[0]);
+
+            return Ok(Head {
+                source: text_from_bytes(bytes)?,
+                truncated: true,
+            });
+        }
+        if eof || scanner.done {
+            reader.read_to_end(&mut bytes)?;
+
+            return Ok(Head {
+                source: text_from_bytes(bytes)?,
+                truncated: false,
+            });
+        }
+    }
+}
+
+/// [`read_head`] of the file at `path`
+pub fn read_head_file(path: impl AsRef<Path>, n: usize) -> Result<Head, Error> {
+    let path = path.as_ref();
+
+    File::open(path)
+        .map_err(Error::from)
+        .and_then(|file| read_head(io::BufReader::new(file), n))
+        .map_err(|e| e.context_file_name(path.display().to_string()))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Mode {
+    Code,
+    String,
+    /// A raw string closed by `"` and this many `#`s
+    RawString(usize),
+    LineComment,
+    /// A block comment with this many nested ones open
+    BlockComment(usize),
+}
+
+/// Finds the end of the first elements of a document whose bytes become
+/// available chunk by chunk
+struct Scanner {
+    /// How many elements to find
+    n: usize,
+    /// Commas separating elements found so far
+    commas: usize,
+    /// The closing brackets of the open values, outermost first
+    closing: Vec<u8>,
+    mode: Mode,
+    pos: usize,
+    /// Where to cut the document off after `n` elements, unless only the end
+    /// of the top-level value follows
+    cut: Option<usize>,
+    /// Whether the brackets are those of an attribute like `#![enable(..)]`
+    attribute: bool,
+    /// Whether the top-level value ended without more than `n` elements
+    done: bool,
+}
+
+impl Scanner {
+    fn new(n: usize) -> Self {
+        Scanner {
+            n,
+            commas: 0,
+            closing: vec![],
+            mode: Mode::Code,
+            pos: 0,
+            cut: None,
+            attribute: false,
+            done: false,
+        }
+    }
+
+    /// Continues scanning `bytes`, which are all bytes of the document if
+    /// `eof`; returns where to cut it off after `n` elements
+    fn scan(&mut self, bytes: &[u8], eof: bool) -> Option<usize> {
+        let end = match eof {
+            true => bytes.len(),
+            false => bytes.len().saturating_sub(LOOKAHEAD),
+        };
+        let at = |i: usize, s: &[u8]| bytes[i..].starts_with(s);
+
+        while self.pos < end && !self.done {
+            let b = bytes[self.pos];
+            let mut len = 1;
+            if let (Mode::Code, Some(cut)) = (self.mode, self.cut) {
+                match b {
+                    // `/` starts a comment, anything else is invalid
+                    b' ' | b'\t' | b'\n' | b'\r' | b'/' => {}
+                    b')' | b']' | b'}' => self.cut = None,
+                    _ => return Some(cut),
+                }
+            }
+            match self.mode {
+                Mode::Code => match b {
+                    b'"' => self.mode = Mode::String,
+                    b'r' if self.pos == 0 || !is_ident_char(bytes[self.pos - 1]) => {
+                        let hashes = bytes[self.pos + 1..]
+                            .iter()
+                            .take_while(|&&b| b == b'#')
+                            .count();
+                        if bytes.get(self.pos + 1 + hashes) == Some(&b'"') {
+                            self.mode = Mode::RawString(hashes);
+                            len += hashes + 1;
+                        }
+                    }
+                    b'#' if self.closing.is_empty() => self.attribute = true,
+                    b'/' if at(self.pos, b"//") => self.mode = Mode::LineComment,
+                    b'/' if at(self.pos, b"/*") => {
+                        self.mode = Mode::BlockComment(1);
+                        len = 2;
+                    }
+                    b'(' | b'[' | b'{' => {
+                        self.closing.push(match b {
+                            b'(' => b')',
+                            b'[' => b']',
+                            _ => b'}',
+                        });
+                        if self.closing.len() == 1 && self.n == 0 && !self.attribute {
+                            self.cut = Some(self.pos + 1);
+                        }
+                    }
+                    b')' | b']' | b'}' => {
+                        self.closing.pop();
+                        if self.closing.is_empty() {
+                            self.done = !self.attribute;
+                            self.attribute = false;
+                        }
+                    }
+                    b',' if self.closing.len() == 1 && !self.attribute => {
+                        self.commas += 1;
+                        if self.commas == self.n {
+                            self.cut = Some(self.pos);
+                        }
+                    }
+                    _ => {}
+                },
+                Mode::String => match b {
+                    b'\\' => len = 2,
+                    b'"' => self.mode = Mode::Code,
+                    _ => {}
+                },
+                Mode::RawString(hashes) => {
+                    let closed = b == b'"'
+                        && bytes[self.pos + 1..]
+                            .iter()
+                            .take(hashes)
+                            .filter(|&&b| b == b'#')
+                            .count()
+                            == hashes;
+                    if closed {
+                        self.mode = Mode::Code;
+                        len += hashes;
+                    }
+                }
+                Mode::LineComment if b == b'\n' || b == b'\r' => self.mode = Mode::Code,
+                Mode::LineComment => {}
+                Mode::BlockComment(open) if at(self.pos, b"/*") => {
+                    self.mode = Mode::BlockComment(open + 1);
+                    len = 2;
+                }
+                Mode::BlockComment(open) if at(self.pos, b"*/") => {
+                    self.mode = match open {
+                        1 => Mode::Code,
+                        _ => Mode::BlockComment(open - 1),
+                    };
+                    len = 2;
+                }
+                Mode::BlockComment(_) => {}
+            }
+            self.pos += len;
+        }
+
+        self.cut.filter(|_| eof)
+    }
+}
+
+fn is_ident_char(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_'
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn head(source: &str, n: usize) -> Head {
+        read_head(source.as_bytes(), n).unwrap()
+    }
+
+    #[test]
+    fn elements() {
+        let source = r##"#![enable(implicit_some)]
+[
+    (a: 1, b: [2, 3]), // one, two
+    "x, \"y\"", /* three, /* nested, */ four */
+    r#"raw", "#, Some(4),
+]"##;
+        assert_eq!(
+            head(source, 1).source,
+            "#![enable(implicit_some)]\n[\n    (a: 1, b: [2, 3])]"
+        );
+        assert_eq!(
+            head(source, 3).source,
+            format!("{}]", &source[..source.find(", Some").unwrap()])
+        );
+        assert_eq!(head(source, 0).source, "#![enable(implicit_some)]\n[]");
+        assert!(head(source, 3).truncated);
+
+        // Documents without more elements are read completely
+        for n in [4, 5] {
+            assert_eq!(
+                head(source, n),
+                Head {
+                    source: source.to_owned(),
+                    truncated: false
+                }
+            );
+        }
+        assert_eq!(head("Config(a: 1, b: 2)", 1).source, "Config(a: 1)");
+        assert_eq!(head("42", 1).source, "42");
+        assert_eq!(head("[]", 0).source, "[]");
+        assert!(!head("[1, 2, /* end */ ]", 2).truncated);
+    }
+
+    #[test]
+    fn stops_reading() {
+        let source = format!("[{}]", "(a: 1), ".repeat(CHUNK_LEN));
+        let mut reader = Cursor::new(source.as_bytes());
+
+        let head = read_head(&mut reader, 2).unwrap();
+        assert_eq!(head.source, "[(a: 1), (a: 1)]");
+        assert!(head.truncated);
+        assert_eq!(reader.position(), CHUNK_LEN as u64);
+    }
+}
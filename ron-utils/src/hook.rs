@@ -0,0 +1,148 @@
+//! Git hooks running the `ron` tool, e.g. so that broken .ron files can't be
+//! committed
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use ron_reboot::ErrorKind;
+
+use crate::{read_fs_string, write_fs_string, Error};
+
+/// The line that marks hooks written by [`install_pre_commit_hook`], which
+/// may be replaced by it
+pub const HOOK_MARKER: &str = "# Installed by `ron hook install`";
+
+/// A pre-commit hook that validates and lints the staged .ron files with the
+/// `ron` command `ron`
+///
+/// The files are checked as they are in the working tree, and linted with the
+/// `ron-utils.ron` of the repository if it has one, because hooks run in its
+/// root directory.
+pub fn pre_commit_script(ron: &str) -> String {
+    format!(
+        r#"#!/bin/sh
+{marker}
+# Checks the staged .ron files, skip it with `git commit --no-verify`
+
+set -f
+IFS='
+'
+set -- $(git diff --cached --name-only --diff-filter=ACMR -- '*.ron')
+if [ $# -eq 0 ]; then
+    exit 0
+fi
+
+{ron} validate --print pretty-errors -- "$@" && {ron} lint -- "$@"
+"#,
+        marker = HOOK_MARKER,
+        ron = shell_quote(ron)
+    )
+}
+
+/// `s` as one word of a shell command
+fn shell_quote(s: &str) -> String {
+    let is_plain = |c: char| c.is_ascii_alphanumeric() || "-_./".contains(c);
+    match !s.is_empty() && s.chars().all(is_plain) {
+        true => s.to_owned(),
+        false => format!("'{}'", s.replace('\'', "'\\''")),
+    }
+}
+
+/// The directory the hooks of the git repository at `repo` are in, which is
+/// configurable with `core.hooksPath`
+pub fn hooks_dir(repo: &Path) -> Result<PathBuf, Error> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--git-path", "hooks"])
+        .current_dir(repo)
+        .output()?;
+    if !output.status.success() {
+        return Err(custom(format!(
+            "`{}` is not in a git repository: {}",
+            repo.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    let dir = String::from_utf8_lossy(&output.stdout);
+
+    Ok(repo.join(dir.trim()))
+}
+
+/// Writes [`pre_commit_script`] as the pre-commit hook into `hooks_dir`,
+/// returning its path
+///
+/// Hooks that weren't installed by this function are only replaced if
+/// `force` is set.
+pub fn install_pre_commit_hook(hooks_dir: &Path, ron: &str, force: bool) -> Result<PathBuf, Error> {
+    let path = hooks_dir.join("pre-commit");
+    if !force && path.exists() && !read_fs_string(&path)?.contains(HOOK_MARKER) {
+        return Err(custom(format!(
+            "`{}` already exists, replace it with `--force`",
+            path.display()
+        )));
+    }
+
+    fs::create_dir_all(hooks_dir)
+        .map_err(|e| Error::from(e).context_file_name(hooks_dir.display().to_string()))?;
+    write_fs_string(&path, &pre_commit_script(ron))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))
+            .map_err(|e| Error::from(e).context_file_name(path.display().to_string()))?;
+    }
+
+    Ok(path)
+}
+
+fn custom(message: String) -> Error {
+    Error {
+        kind: ErrorKind::Custom(message),
+        context: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn install() {
+        let dir = std::env::temp_dir().join(format!("ron-utils-hook-{}", std::process::id()));
+        let hooks = dir.join("hooks");
+
+        let path = install_pre_commit_hook(&hooks, "ron", false).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), pre_commit_script("ron"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o755);
+        }
+
+        // Its own hook is updated, others are only replaced when forced
+        assert!(install_pre_commit_hook(&hooks, "/bin/ron", false).is_ok());
+        fs::write(&path, "#!/bin/sh\nmake check\n").unwrap();
+        assert!(install_pre_commit_hook(&hooks, "ron", false).is_err());
+        assert!(install_pre_commit_hook(&hooks, "ron", true).is_ok());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn script_checks_staged_files() {
+        let script = pre_commit_script("ron");
+        assert!(script.starts_with("#!/bin/sh\n"));
+        assert!(
+            script.contains("ron validate --print pretty-errors -- \"$@\" && ron lint -- \"$@\"")
+        );
+
+        assert_eq!(shell_quote("/usr/bin/ron"), "/usr/bin/ron");
+        assert_eq!(shell_quote("C:/my tools/ron's"), "'C:/my tools/ron'\\''s'");
+    }
+}
@@ -0,0 +1,537 @@
+//! Structural type inference for documents without a schema
+//!
+//! Also used to bootstrap a [`Schema`] from example documents.
+
+use std::fmt;
+
+use ron_reboot::{
+    ast::{Expr, Integer, Sign, Spanned, Struct, Untagged},
+    utf8_parser::{ast_from_str, serde::encrypted::encrypted_payload},
+    Error,
+};
+
+use crate::{
+    edit::byte_offset,
+    schema::{Constraint, Field, Schema, StructSchema},
+};
+
+/// The type of a value as far as it can be told from the value itself
+#[derive(Clone, Debug, PartialEq)]
+pub enum TypeSketch {
+    /// Nothing is known, e.g. about the elements of an empty list
+    Unknown,
+    /// Values of incompatible types were found
+    Any,
+    Unit,
+    Bool,
+    Integer,
+    Float,
+    String,
+    Option(Box<TypeSketch>),
+    List(Box<TypeSketch>),
+    Map(Box<TypeSketch>, Box<TypeSketch>),
+    Tuple {
+        name: Option<String>,
+        elements: Vec<TypeSketch>,
+    },
+    Struct {
+        name: Option<String>,
+        fields: Vec<(String, TypeSketch)>,
+    },
+    /// Identifiers like `Red`, unit variants of an enum or unit structs
+    Enum(Vec<String>),
+}
+
+/// Infers the type of `expr`; the elements of lists and maps are unified.
+pub fn infer_type(expr: &Expr) -> TypeSketch {
+    match expr {
+        Expr::Unit => TypeSketch::Unit,
+        Expr::Optional(inner) => TypeSketch::Option(Box::new(
+            inner
+                .as_ref()
+                .map(|inner| infer_type(&inner.value))
+                .unwrap_or(TypeSketch::Unknown),
+        )),
+        // Decrypted into strings when loading
+        expr if encrypted_payload(expr).is_some() => TypeSketch::String,
+        Expr::Tagged(t) => {
+            let name = Some(t.ident.value.as_str().to_owned());
+            match &t.untagged.value {
+                Untagged::Unit => TypeSketch::Enum(vec![t.ident.value.as_str().to_owned()]),
+                Untagged::Struct(s) => TypeSketch::Struct {
+                    name,
+                    fields: struct_fields(s),
+                },
+                Untagged::Tuple(t) => TypeSketch::Tuple {
+                    name,
+                    elements: t.elements.iter().map(|e| infer_type(&e.value)).collect(),
+                },
+            }
+        }
+        Expr::Bool(_) => TypeSketch::Bool,
+        Expr::Tuple(t) => TypeSketch::Tuple {
+            name: None,
+            elements: t.elements.iter().map(|e| infer_type(&e.value)).collect(),
+        },
+        Expr::List(l) => TypeSketch::List(Box::new(unify_all(
+            l.elements.iter().map(|e| infer_type(&e.value)),
+        ))),
+        Expr::Map(m) => TypeSketch::Map(
+            Box::new(unify_all(
+                m.entries.iter().map(|e| infer_type(&e.value.key.value)),
+            )),
+            Box::new(unify_all(
+                m.entries.iter().map(|e| infer_type(&e.value.value.value)),
+            )),
+        ),
+        Expr::Struct(s) => TypeSketch::Struct {
+            name: None,
+            fields: struct_fields(s),
+        },
+        Expr::Integer(_) => TypeSketch::Integer,
+        Expr::Str(_) | Expr::String(_) => TypeSketch::String,
+        Expr::Decimal(_) => TypeSketch::Float,
+        // Deserialized as strings like `"250ms"`
+        Expr::Quantity(_) => TypeSketch::String,
+        // Deserialized as `(r, g, b, a)`
+        Expr::Color(_) => TypeSketch::Tuple {
+            name: None,
+            elements: vec![TypeSketch::Integer; 4],
+        },
+        _ => TypeSketch::Unknown,
+    }
+}
+
+/// Infers the type of the innermost value at the byte offset `pos` of `source`,
+/// e.g. for hovers.
+pub fn type_at(source: &str, pos: usize) -> Result<Option<TypeSketch>, Error> {
+    fn innermost<'b, 'a>(
+        source: &str,
+        expr: &'b Spanned<Expr<'a>>,
+        pos: usize,
+    ) -> Option<&'b Spanned<Expr<'a>>> {
+        if pos < byte_offset(source, expr.start) || pos >= byte_offset(source, expr.end) {
+            return None;
+        }
+
+        expr.value
+            .children()
+            .find_map(|child| innermost(source, child, pos))
+            .or(Some(expr))
+    }
+
+    let ron = ast_from_str(source)?;
+
+    Ok(innermost(source, &ron.expr, pos).map(|expr| infer_type(&expr.value)))
+}
+
+/// The doc comment of the innermost field or map entry at the byte offset
+/// `pos` of `source`, e.g. for hovers over its key or value
+pub fn doc_at(source: &str, pos: usize) -> Result<Option<String>, Error> {
+    fn innermost(source: &str, expr: &Expr, pos: usize) -> Option<String> {
+        let contains =
+            |start, end| byte_offset(source, start) <= pos && pos < byte_offset(source, end);
+        let entries: Vec<_> = match (expr, expr.as_struct()) {
+            (Expr::Optional(Some(inner)), _) => return innermost(source, &inner.value, pos),
+            (Expr::Map(m), _) => m
+                .entries
+                .iter()
+                .map(|e| (e.start, e.end, &e.value.doc, &e.value.value))
+                .collect(),
+            (_, Some(s)) => s
+                .fields
+                .iter()
+                .map(|f| (f.start, f.end, &f.value.doc, &f.value.value))
+                .collect(),
+            (_, None) => {
+                return expr
+                    .children()
+                    .find(|child| contains(child.start, child.end))
+                    .and_then(|child| innermost(source, &child.value, pos))
+            }
+        };
+
+        let (_, _, doc, value) = entries
+            .into_iter()
+            .find(|&(start, end, ..)| contains(start, end))?;
+
+        innermost(source, &value.value, pos).or_else(|| match doc.is_empty() {
+            true => None,
+            false => Some(doc.join("\n")),
+        })
+    }
+
+    let ron = ast_from_str(source)?;
+
+    Ok(innermost(source, &ron.expr.value, pos))
+}
+
+/// Generates a schema all of `documents` conform to.
+///
+/// Struct fields missing from some documents become optional and numeric
+/// fields are constrained to the range of the observed values.
+pub fn infer_schema(documents: &[&Expr]) -> Schema {
+    let sketch = unify_all(documents.iter().map(|expr| infer_type(expr)));
+
+    to_schema(sketch, documents)
+}
+
+/// [`infer_schema`] for documents that still need to be parsed
+pub fn infer_schema_str(sources: &[&str]) -> Result<Schema, Error> {
+    let documents = sources
+        .iter()
+        .map(|source| ast_from_str(source))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(infer_schema(
+        &documents
+            .iter()
+            .map(|ron| &ron.expr.value)
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// Converts the sketch of `values`, the values observed at one position of
+/// the documents.
+fn to_schema(sketch: TypeSketch, values: &[&Expr]) -> Schema {
+    match sketch {
+        TypeSketch::Unknown | TypeSketch::Any => Schema::Any,
+        TypeSketch::Unit => Schema::Tuple(vec![]),
+        TypeSketch::Bool => Schema::Bool,
+        TypeSketch::Integer => Schema::Integer,
+        TypeSketch::Float => Schema::Float,
+        TypeSketch::String => Schema::String,
+        TypeSketch::Option(inner) => {
+            Schema::Option(Box::new(to_schema(*inner, &unwrap_some(values))))
+        }
+        TypeSketch::List(inner) => {
+            let elements = values
+                .iter()
+                .flat_map(|value| match value {
+                    Expr::List(l) => l.elements.iter().map(|e| &e.value).collect(),
+                    _ => vec![],
+                })
+                .collect::<Vec<_>>();
+
+            Schema::List(Box::new(to_schema(*inner, &elements)))
+        }
+        TypeSketch::Map(key, value) => {
+            let entries = values
+                .iter()
+                .flat_map(|value| match value {
+                    Expr::Map(m) => m.entries.iter().map(|e| &e.value).collect(),
+                    _ => vec![],
+                })
+                .collect::<Vec<_>>();
+            let keys = entries.iter().map(|e| &e.key.value).collect::<Vec<_>>();
+            let values = entries.iter().map(|e| &e.value.value).collect::<Vec<_>>();
+
+            Schema::Map(
+                Box::new(to_schema(*key, &keys)),
+                Box::new(to_schema(*value, &values)),
+            )
+        }
+        TypeSketch::Tuple { elements, .. } => Schema::Tuple(
+            elements
+                .into_iter()
+                .enumerate()
+                .map(|(i, element)| {
+                    let values = values
+                        .iter()
+                        .filter_map(|value| value.as_tuple()?.elements.get(i))
+                        .map(|e| &e.value)
+                        .collect::<Vec<_>>();
+
+                    to_schema(element, &values)
+                })
+                .collect(),
+        ),
+        TypeSketch::Struct { name, fields } => Schema::Struct(StructSchema {
+            name,
+            fields: fields
+                .into_iter()
+                .map(|(key, sketch)| {
+                    let values = values
+                        .iter()
+                        .filter_map(|value| value.as_struct())
+                        .filter_map(|s| s.fields.iter().find(|f| f.value.key.value.0 == key))
+                        .map(|f| &f.value.value.value)
+                        .collect::<Vec<_>>();
+
+                    let mut field = Field::new(key, to_schema(sketch, &values));
+                    if let Some((min, max)) = range(&unwrap_some(&values)) {
+                        field.constraints = vec![Constraint::Min(min), Constraint::Max(max)];
+                    }
+
+                    field
+                })
+                .collect(),
+            rules: vec![],
+        }),
+        TypeSketch::Enum(variants) => Schema::Enum(variants),
+    }
+}
+
+fn unwrap_some<'b, 'a>(values: &[&'b Expr<'a>]) -> Vec<&'b Expr<'a>> {
+    values
+        .iter()
+        .filter_map(|value| match value {
+            Expr::Optional(inner) => inner.as_ref().map(|inner| &inner.value),
+            value => Some(*value),
+        })
+        .collect()
+}
+
+/// The smallest and largest of `values` if they are all numbers
+fn range(values: &[&Expr]) -> Option<(f64, f64)> {
+    let numbers = values
+        .iter()
+        .map(|value| match value {
+            Expr::Integer(Integer::Unsigned(u)) => Some(u.number as f64),
+            Expr::Integer(Integer::Signed(s)) => Some(match s.sign {
+                Sign::Positive => s.number as f64,
+                Sign::Negative => -(s.number as f64),
+            }),
+            Expr::Decimal(d) => Some(f64::from((**d).clone())),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    numbers.iter().fold(None, |range, &n| match range {
+        None => Some((n, n)),
+        Some((min, max)) => Some((n.min(min), n.max(max))),
+    })
+}
+
+fn struct_fields(s: &Struct) -> Vec<(String, TypeSketch)> {
+    s.fields
+        .iter()
+        .map(|f| {
+            (
+                f.value.key.value.as_str().to_owned(),
+                infer_type(&f.value.value.value),
+            )
+        })
+        .collect()
+}
+
+fn unify_all(types: impl Iterator<Item = TypeSketch>) -> TypeSketch {
+    types.fold(TypeSketch::Unknown, TypeSketch::unify)
+}
+
+impl TypeSketch {
+    /// The most specific type both `self` and `other` are instances of
+    ///
+    /// Struct fields missing on one side become optional.
+    pub fn unify(self, other: TypeSketch) -> TypeSketch {
+        use TypeSketch::*;
+
+        match (self, other) {
+            (Unknown, t) | (t, Unknown) => t,
+            (Integer, Float) | (Float, Integer) => Float,
+            (Option(a), Option(b)) => Option(Box::new(a.unify(*b))),
+            (Option(a), b) | (b, Option(a)) => Option(Box::new(a.unify(b))),
+            (List(a), List(b)) => List(Box::new(a.unify(*b))),
+            (Map(ak, av), Map(bk, bv)) => Map(Box::new(ak.unify(*bk)), Box::new(av.unify(*bv))),
+            (
+                Tuple {
+                    name: a_name,
+                    elements: a,
+                },
+                Tuple {
+                    name: b_name,
+                    elements: b,
+                },
+            ) if a.len() == b.len() && compatible_names(&a_name, &b_name) => Tuple {
+                name: a_name.or(b_name),
+                elements: a.into_iter().zip(b).map(|(a, b)| a.unify(b)).collect(),
+            },
+            (
+                Struct {
+                    name: a_name,
+                    fields: a,
+                },
+                Struct {
+                    name: b_name,
+                    fields: mut b,
+                },
+            ) if compatible_names(&a_name, &b_name) => {
+                let mut fields = vec![];
+                for (key, a) in a {
+                    let t = match b.iter().position(|(k, _)| *k == key) {
+                        Some(i) => a.unify(b.remove(i).1),
+                        None => a.optional(),
+                    };
+                    fields.push((key, t));
+                }
+                fields.extend(b.into_iter().map(|(key, b)| (key, b.optional())));
+
+                Struct {
+                    name: a_name.or(b_name),
+                    fields,
+                }
+            }
+            (Enum(mut a), Enum(b)) => {
+                for variant in b {
+                    if !a.contains(&variant) {
+                        a.push(variant);
+                    }
+                }
+
+                Enum(a)
+            }
+            (a, b) if a == b => a,
+            _ => Any,
+        }
+    }
+
+    fn optional(self) -> TypeSketch {
+        match self {
+            TypeSketch::Option(_) => self,
+            t => TypeSketch::Option(Box::new(t)),
+        }
+    }
+}
+
+fn compatible_names(a: &Option<String>, b: &Option<String>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => a == b,
+        _ => true,
+    }
+}
+
+impl fmt::Display for TypeSketch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn list<T>(
+            f: &mut fmt::Formatter<'_>,
+            items: &[T],
+            mut write: impl FnMut(&mut fmt::Formatter<'_>, &T) -> fmt::Result,
+        ) -> fmt::Result {
+            for (i, item) in items.iter().enumerate() {
+                if i != 0 {
+                    write!(f, ", ")?;
+                }
+                write(f, item)?;
+            }
+
+            Ok(())
+        }
+
+        match self {
+            TypeSketch::Unknown => write!(f, "_"),
+            TypeSketch::Any => write!(f, "any"),
+            TypeSketch::Unit => write!(f, "unit"),
+            TypeSketch::Bool => write!(f, "bool"),
+            TypeSketch::Integer => write!(f, "integer"),
+            TypeSketch::Float => write!(f, "float"),
+            TypeSketch::String => write!(f, "string"),
+            TypeSketch::Option(inner) => write!(f, "option<{}>", inner),
+            TypeSketch::List(inner) => write!(f, "list<{}>", inner),
+            TypeSketch::Map(key, value) => write!(f, "map<{}, {}>", key, value),
+            TypeSketch::Tuple { name, elements } => {
+                write!(f, "{}(", name.as_deref().unwrap_or_default())?;
+                list(f, elements, |f, e| write!(f, "{}", e))?;
+                write!(f, ")")
+            }
+            TypeSketch::Struct { name, fields } => {
+                write!(f, "{}", name.as_deref().unwrap_or("struct"))?;
+                write!(f, "{{")?;
+                list(f, fields, |f, (key, t)| write!(f, "{}: {}", key, t))?;
+                write!(f, "}}")
+            }
+            TypeSketch::Enum(variants) => {
+                write!(f, "enum{{")?;
+                list(f, variants, |f, v| write!(f, "{}", v))?;
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sketch(source: &str) -> String {
+        infer_type(&ast_from_str(source).unwrap().expr.value).to_string()
+    }
+
+    #[test]
+    fn infer() {
+        assert_eq!(sketch("[1, 2.5]"), "list<float>");
+        assert_eq!(sketch("[]"), "list<_>");
+        assert_eq!(sketch("[Some(1), None]"), "list<option<integer>>");
+        assert_eq!(sketch("[Red, Green, Red]"), "list<enum{Red, Green}>");
+        assert_eq!(sketch(r#"[1, "a"]"#), "list<any>");
+        assert_eq!(
+            sketch(r#"{"a": (1, true)}"#),
+            "map<string, (integer, bool)>"
+        );
+        assert_eq!(
+            sketch("[Layer(name: \"a\", z: 1), (name: \"b\", hidden: true)]"),
+            "list<Layer{name: string, z: option<integer>, hidden: option<bool>}>"
+        );
+        assert_eq!(sketch("[A(1), B(1)]"), "list<any>");
+    }
+
+    #[test]
+    fn schema() {
+        let schema = infer_schema_str(&[
+            "Unit(name: \"orc\", hp: 10, speed: 1.5, tags: [])",
+            "Unit(name: \"elf\", hp: 7, speed: 2, tags: [\"ranged\"], boss: false)",
+        ])
+        .unwrap();
+
+        let mut hp = Field::new("hp", Schema::Integer);
+        hp.constraints = vec![Constraint::Min(7.0), Constraint::Max(10.0)];
+        let mut speed = Field::new("speed", Schema::Float);
+        speed.constraints = vec![Constraint::Min(1.5), Constraint::Max(2.0)];
+        assert_eq!(
+            schema,
+            Schema::Struct(StructSchema {
+                name: Some("Unit".to_owned()),
+                fields: vec![
+                    Field::new("name", Schema::String),
+                    hp,
+                    speed,
+                    Field::new("tags", Schema::List(Box::new(Schema::String))),
+                    Field::new("boss", Schema::Option(Box::new(Schema::Bool))),
+                ],
+                rules: vec![],
+            })
+        );
+
+        let ron = schema.to_ron_string();
+        assert_eq!(
+            ron_reboot::utf8_parser::serde::from_str::<Schema>(&ron),
+            Ok(schema)
+        );
+    }
+
+    #[test]
+    fn hover() {
+        let source = "(size: (1920, 1080), layers: [])";
+        assert_eq!(type_at(source, 9), Ok(Some(TypeSketch::Integer)));
+        assert_eq!(
+            type_at(source, 30).map(|t| t.map(|t| t.to_string())),
+            Ok(Some("list<_>".to_owned()))
+        );
+        assert_eq!(
+            type_at(source, 0).map(|t| t.map(|t| t.to_string())),
+            Ok(Some(
+                "struct{size: (integer, integer), layers: list<_>}".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn hover_docs() {
+        let source = "(\n  /// Size\n  /// in px\n  size: (\n    w: 1,\n    /// Height\n    h: 2,\n  ),\n  n: 3,\n)";
+        let at = |text: &str| doc_at(source, source.find(text).unwrap());
+        assert_eq!(at("size"), Ok(Some("Size\nin px".to_owned())));
+        assert_eq!(at("1"), Ok(Some("Size\nin px".to_owned())));
+        assert_eq!(at("2"), Ok(Some("Height".to_owned())));
+        assert_eq!(at("3"), Ok(None));
+        assert_eq!(doc_at(source, 0), Ok(None));
+    }
+}
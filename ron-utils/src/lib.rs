@@ -1,33 +1,295 @@
-use std::{fs::read_to_string, path::Path};
+use std::{
+    fs::{self, read, File},
+    io::Write,
+    path::Path,
+};
 
-use ron_reboot::utf8_parser::ast_from_str;
-pub use ron_reboot::{print_error, Error};
+pub use ron_reboot::{print_error, write_error, write_error_with_format, Error, ErrorFormat};
+use ron_reboot::{utf8_parser::ast_from_str_recovering, ErrorKind};
 
-pub fn validate_str(s: &str) -> Result<(), ron_reboot::Error> {
-    ast_from_str(s).map(|_| ())
+pub use crate::diff::render_diff;
+use crate::{
+    lint::{lint_str, LintConfig},
+    stats::{stats_ron, Stats},
+};
+
+pub mod audit;
+pub mod bench;
+pub mod browse;
+pub mod checksum;
+pub mod complete;
+pub mod config;
+pub mod convert;
+pub mod corpus;
+pub mod coverage;
+pub mod diff;
+pub mod doc;
+pub mod edit;
+pub mod eval;
+pub mod format;
+pub mod head;
+pub mod hook;
+pub mod infer;
+pub mod lint;
+pub mod merge;
+pub mod patch;
+pub mod schema;
+pub mod spec;
+pub mod stats;
+pub mod template;
+pub mod validate;
+
+/// What validating a document found
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ValidationReport {
+    /// Why the document is invalid, e.g. syntax errors (all of them for
+    /// invalid list elements) or that it couldn't be read, see
+    /// [`Error::class`]
+    pub errors: Vec<Error>,
+    /// Problems that don't make the document invalid, e.g. lint diagnostics
+    pub warnings: Vec<Error>,
+    /// The structure of the document, if it could be parsed
+    pub stats: Option<Stats>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    fn error(error: Error) -> Self {
+        ValidationReport {
+            errors: vec![error],
+            ..Default::default()
+        }
+    }
+
+    fn context_file_name(mut self, path: &Path) -> Self {
+        for e in self.errors.iter_mut().chain(&mut self.warnings) {
+            *e = e.clone().context_file_name(path.display().to_string());
+        }
+
+        self
+    }
+}
+
+pub fn validate_str(s: &str) -> ValidationReport {
+    validate_str_with_lints(s, &LintConfig::default())
+}
+
+/// Like [`validate_str`], with the diagnostics of the lints enabled in
+/// `lints` as warnings
+pub fn validate_str_with_lints(s: &str, lints: &LintConfig) -> ValidationReport {
+    let ron = match ast_from_str_recovering(s) {
+        Ok(ron) => ron,
+        Err(errors) => {
+            return ValidationReport {
+                errors,
+                ..Default::default()
+            }
+        }
+    };
+
+    ValidationReport {
+        errors: vec![],
+        warnings: lint_str(s, lints)
+            .unwrap_or_default()
+            .iter()
+            .map(|diagnostic| diagnostic.to_error(s))
+            .collect(),
+        stats: Some(stats_ron(s, &ron)),
+    }
 }
 
-pub fn validate_file(p: impl AsRef<Path>) -> Result<(), ron_reboot::Error> {
-    ast_from_str(&read_fs_string(p)?).map(|_| ())
+pub fn validate_file(p: impl AsRef<Path>) -> ValidationReport {
+    let p = p.as_ref();
+    match read_fs_string(p) {
+        Ok(source) => validate_str(&source).context_file_name(p),
+        Err(e) => ValidationReport::error(e),
+    }
 }
 
+/// Like [`validate_str`], also deserializing the document into `T`
 #[cfg(feature = "serde1")]
-pub fn validate_typed_str<'a, T: serde::Deserialize<'a>>(
-    s: &'a str,
-) -> Result<(), ron_reboot::Error> {
-    ron_reboot::from_str_serde(s)
+pub fn validate_typed_str<'a, T: serde::Deserialize<'a>>(s: &'a str) -> ValidationReport {
+    let mut report = validate_str(s);
+    if report.is_valid() {
+        if let Err(e) = ron_reboot::from_str_serde::<T>(s) {
+            report.errors.push(e);
+        }
+    }
+
+    report
 }
 
 #[cfg(feature = "serde1")]
 pub fn validate_typed_file<T: serde::de::DeserializeOwned>(
     p: impl AsRef<Path>,
-) -> Result<(), ron_reboot::Error> {
-    ron_reboot::utf8_parser::serde::from_str(&read_fs_string(p)?)
+) -> ValidationReport {
+    let p = p.as_ref();
+    match read_fs_string(p) {
+        Ok(source) => validate_typed_str::<T>(&source).context_file_name(p),
+        Err(e) => ValidationReport::error(e),
+    }
 }
 
-fn read_fs_string(path: impl AsRef<Path>) -> Result<String, ron_reboot::Error> {
+/// Reads a text file, with a friendly error for binary files such as images
+pub fn read_fs_string(path: impl AsRef<Path>) -> Result<String, ron_reboot::Error> {
     let path = path.as_ref();
-    read_to_string(path)
+    read(path)
         .map_err(ron_reboot::Error::from)
+        .and_then(text_from_bytes)
         .map_err(|e: ron_reboot::Error| e.context_file_name(path.display().to_string()))
 }
+
+/// Writes `contents` to a file atomically: either the whole new contents are
+/// written or the file is left as it was, e.g. if the disk is full
+///
+/// The contents are written to a temporary file next to `path`, which then
+/// replaces it.
+pub fn write_fs_string(path: impl AsRef<Path>, contents: &str) -> Result<(), ron_reboot::Error> {
+    let path = path.as_ref();
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp = path.with_file_name(format!(".{}.{}.tmp", file_name, std::process::id()));
+
+    let written = File::create(&temp).and_then(|mut file| {
+        file.write_all(contents.as_bytes())?;
+        // Keep the permissions of the file being replaced
+        if let Ok(metadata) = fs::metadata(path) {
+            file.set_permissions(metadata.permissions())?;
+        }
+        file.sync_all()
+    });
+    let result = written.and_then(|_| fs::rename(&temp, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+
+    result
+        .map_err(ron_reboot::Error::from)
+        .map_err(|e| e.context_file_name(path.display().to_string()))
+}
+
+/// How many bytes at the start of a file are checked for binary content
+const BINARY_SNIFF_LEN: usize = 8 * 1024;
+
+/// A few invalid bytes are likely a text file in another encoding, more than
+/// one in this many bytes is binary content
+const BINARY_INVALID_RATIO: usize = 16;
+
+pub(crate) fn text_from_bytes(bytes: Vec<u8>) -> Result<String, ron_reboot::Error> {
+    let sniff = &bytes[..bytes.len().min(BINARY_SNIFF_LEN)];
+    let mut invalid = vec![];
+    let mut start = 0;
+    while let Err(e) = std::str::from_utf8(&sniff[start..]) {
+        let offset = start + e.valid_up_to();
+        invalid.push(offset);
+        // An incomplete sequence at the end may be cut off by the sniff length
+        start = match e.error_len() {
+            Some(len) => offset + len,
+            None => break,
+        };
+    }
+
+    let nul = sniff.iter().position(|&b| b == 0);
+    if nul.is_some() || invalid.len() * BINARY_INVALID_RATIO > sniff.len() {
+        let offset = nul.into_iter().chain(invalid).min().unwrap_or_default();
+
+        return Err(ron_reboot::Error {
+            kind: ErrorKind::NotText(offset),
+            context: None,
+        });
+    }
+
+    String::from_utf8(bytes).map_err(|e| {
+        let offset = e.utf8_error().valid_up_to();
+        ron_reboot::Error::invalid_utf8(e.as_bytes(), offset)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binary_files() {
+        assert_eq!(text_from_bytes(b"(a: 1)".to_vec()).unwrap(), "(a: 1)");
+
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+        assert_eq!(
+            text_from_bytes(png).unwrap_err().kind,
+            ErrorKind::NotText(0)
+        );
+        let nul = b"(a: 1)\0".to_vec();
+        assert_eq!(
+            text_from_bytes(nul).unwrap_err().kind,
+            ErrorKind::NotText(6)
+        );
+        let mostly_invalid = b"(\xff\xfe\xfd\xfc)".to_vec();
+        assert_eq!(
+            text_from_bytes(mostly_invalid).unwrap_err().kind,
+            ErrorKind::NotText(1)
+        );
+
+        // A single stray byte in text is reported where it is
+        let latin1 = b"(\n    name: \"caf\xe9\",\n    size: 1,\n    // more text\n)".to_vec();
+        let e = text_from_bytes(latin1).unwrap_err();
+        assert_eq!(e.kind, ErrorKind::InvalidUtf8);
+        assert_eq!(
+            e.start().map(|start| (start.line, start.column)),
+            Some((2, 15))
+        );
+    }
+
+    #[test]
+    fn atomic_writes() {
+        let dir = std::env::temp_dir().join(format!("ron-utils-write-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.ron");
+
+        write_fs_string(&path, "(a: 1)").unwrap();
+        write_fs_string(&path, "(a: 2)").unwrap();
+        assert_eq!(read_fs_string(&path).unwrap(), "(a: 2)");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        // Replacing a directory fails without touching it or leaving files
+        let e = write_fs_string(&dir, "(a: 3)").unwrap_err();
+        assert!(matches!(e.kind, ErrorKind::IoError(_)));
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn validation_reports() {
+        let report = validate_str("[1, (a: ), 3, {4}, 5]");
+        assert!(!report.is_valid());
+        assert_eq!(report.errors.len(), 2);
+        assert_eq!(report.stats, None);
+
+        let lints = LintConfig {
+            struct_names: Some(lint::StructNameStyle::Anonymous),
+            root_type: Some("Config".to_owned()),
+            ..LintConfig::default()
+        };
+        let report = validate_str_with_lints("Config(a: [1, 2])", &lints);
+        assert!(report.is_valid());
+        assert_eq!(report.warnings.len(), 1);
+        assert_eq!(report.stats.map(|stats| stats.values), Some(4));
+
+        let report = validate_file("does/not/exist.ron");
+        assert_eq!(report.errors[0].class(), ron_reboot::ErrorClass::Io);
+    }
+
+    #[cfg(feature = "serde1")]
+    #[test]
+    fn typed_validation_reports() {
+        assert!(validate_typed_str::<Vec<u8>>("[1, 2]").is_valid());
+        let report = validate_typed_str::<Vec<u8>>("[1, 256]");
+        assert_eq!(
+            report.errors[0].class(),
+            ron_reboot::ErrorClass::Deserialize
+        );
+        assert!(report.stats.is_some());
+    }
+}
@@ -0,0 +1,195 @@
+use ron_reboot::{
+    ast::{required_extensions, Attribute, Extension},
+    utf8_parser::ast_from_str,
+    Error, ErrorKind, Location,
+};
+
+use crate::{
+    edit::{byte_offset, TextEdit},
+    lint::Diagnostic,
+};
+
+const LINT: &str = "extensions";
+
+/// Checks that `source` enables exactly the literal extensions it uses.
+///
+/// Enabled `unit_literals` and `color_literals` without any of their literals
+/// are reported, as are literals of extensions that aren't enabled. All
+/// diagnostics share one fix, which rewrites `#![enable(..)]`. Whether `implicit_some` or
+/// `unwrap_newtypes` are needed depends on the type the document is
+/// deserialized into, so they are never reported (see
+/// [`required_extensions`]).
+///
+/// Errors other than missing extensions are returned.
+pub fn check_extensions(source: &str) -> Result<Vec<Diagnostic>, Error> {
+    // Parsing stops at the first literal of an extension that isn't enabled,
+    // so each one is replaced with a number of the same length until the rest
+    // of the document parses
+    let mut blanked = source.to_owned();
+    let mut missing = vec![];
+    let ron = loop {
+        match ast_from_str(&blanked) {
+            Ok(ron) => break ron,
+            Err(e) => match (&e.kind, e.start(), e.end()) {
+                (ErrorKind::ExtensionNotEnabled(name), Some(start), Some(end)) => {
+                    missing.push((Extension::from_name(name), start, end));
+                    blanked = blank(&blanked, start, end);
+                }
+                _ => return Err(e),
+            },
+        }
+    };
+
+    let enable = ron
+        .attributes
+        .iter()
+        .find_map(|attribute| match &attribute.value {
+            Attribute::Enable(extensions) => Some((attribute, extensions)),
+            _ => None,
+        });
+    let required = required_extensions(&ron);
+    let is_unused = |extension: &Extension| {
+        matches!(
+            extension,
+            Extension::UnitLiterals | Extension::ColorLiterals
+        ) && !required.contains(extension)
+    };
+
+    let mut names = vec![];
+    let mut diagnostics = vec![];
+    for extension in enable.iter().flat_map(|(_, extensions)| &extensions.value) {
+        match is_unused(&extension.value) {
+            true => diagnostics.push(Diagnostic {
+                lint: LINT,
+                message: format!(
+                    "extension `{}` is enabled but not used",
+                    extension.value.name()
+                ),
+                start: extension.start,
+                end: extension.end,
+                fix: None,
+            }),
+            false => names.push(extension.value.name()),
+        }
+    }
+    for (extension, start, end) in &missing {
+        diagnostics.push(Diagnostic {
+            lint: LINT,
+            message: format!("extension `{}` is not enabled", extension.name()),
+            start: *start,
+            end: *end,
+            fix: None,
+        });
+        if !names.contains(&extension.name()) {
+            names.push(extension.name());
+        }
+    }
+
+    // Every diagnostic gets the same edit, which rewrites the enabled
+    // extensions and is applied once
+    let edit = match enable {
+        // The whole attribute, e.g. `#![enable(unit_literals)]\n`
+        Some((attribute, _)) if names.is_empty() => {
+            let end = byte_offset(source, attribute.end);
+            let whitespace = source[end..]
+                .find(|c: char| !c.is_whitespace())
+                .unwrap_or(source.len() - end);
+
+            TextEdit::delete(byte_offset(source, attribute.start), end + whitespace)
+        }
+        Some((_, extensions)) => {
+            let start = byte_offset(source, extensions.start);
+            let end = byte_offset(source, extensions.end);
+
+            TextEdit {
+                start,
+                // Keeping the whitespace before `)`
+                end: start + source[start..end].trim_end().len(),
+                replacement: names.join(", "),
+            }
+        }
+        None => TextEdit::insert(0, format!("#![enable({})]\n", names.join(", "))),
+    };
+    for diagnostic in &mut diagnostics {
+        diagnostic.fix = Some(vec![edit.clone()]);
+    }
+
+    Ok(diagnostics)
+}
+
+/// `source` with the chars `start..end` replaced by `0` and spaces, which
+/// keeps the locations of everything else
+fn blank(source: &str, start: Location, end: Location) -> String {
+    let (start, end) = (byte_offset(source, start), byte_offset(source, end));
+    let len = source[start..end].chars().count();
+
+    format!(
+        "{}0{}{}",
+        &source[..start],
+        " ".repeat(len.saturating_sub(1)),
+        &source[end..]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lint::apply_fixes;
+
+    fn fix(source: &str) -> String {
+        apply_fixes(source, &check_extensions(source).unwrap())
+    }
+
+    #[test]
+    fn unused() {
+        assert_eq!(fix("#![enable(unit_literals)]\n(a: 1)"), "(a: 1)");
+        assert_eq!(
+            fix("#![enable(unit_literals, color_literals)]\n(a: 1s)"),
+            "#![enable(unit_literals)]\n(a: 1s)"
+        );
+        assert_eq!(
+            fix("#![enable(color_literals, unit_literals, implicit_some)]\n(a: #ffffff)"),
+            "#![enable(color_literals, implicit_some)]\n(a: #ffffff)"
+        );
+        assert_eq!(
+            fix("#![enable(implicit_some, unit_literals)] 1"),
+            "#![enable(implicit_some)] 1"
+        );
+    }
+
+    #[test]
+    fn missing() {
+        let source = "(a: 250ms, b: #ff8000, c: 1KiB)";
+        let diagnostics = check_extensions(source).unwrap();
+        assert_eq!(diagnostics.len(), 3);
+        assert_eq!(diagnostics[0].start.column, 5);
+        assert_eq!(diagnostics[0].end.column, 10);
+        assert_eq!(
+            fix(source),
+            "#![enable(unit_literals, color_literals)]\n(a: 250ms, b: #ff8000, c: 1KiB)"
+        );
+        assert_eq!(
+            fix("#![enable(implicit_some)]\n[#ffffff]"),
+            "#![enable(implicit_some, color_literals)]\n[#ffffff]"
+        );
+    }
+
+    #[test]
+    fn unused_and_missing() {
+        assert_eq!(
+            fix("#![enable(unit_literals)]\n(a: #ff8000)"),
+            "#![enable(color_literals)]\n(a: #ff8000)"
+        );
+        assert_eq!(
+            fix("#![ enable( color_literals,\n  implicit_some ) ] 1s"),
+            "#![ enable( implicit_some, unit_literals ) ] 1s"
+        );
+    }
+
+    #[test]
+    fn clean() {
+        let source = "#![enable(unit_literals, implicit_some)]\n(a: 1s, b: 2)";
+        assert!(check_extensions(source).unwrap().is_empty());
+        assert!(check_extensions("(a: ").is_err());
+    }
+}
@@ -0,0 +1,151 @@
+//! Lints for RON documents
+//!
+//! A lint inspects the AST of a document and reports [`Diagnostic`]s,
+//! optionally together with the [`TextEdit`]s that fix them. Projects can add
+//! their own lints, see [`LintRule`].
+
+use ron_reboot::{utf8_parser::ast_from_str, Error, ErrorKind, Location};
+use serde::Deserialize;
+
+pub use self::{
+    extensions::check_extensions,
+    references::{check_references, ReferenceKeys},
+    rules::{LintRule, LintRules},
+    struct_names::{check_struct_names, StructNameStyle},
+    units::{check_units, UnitStyle},
+};
+pub use crate::edit::TextEdit;
+use crate::edit::apply_edits;
+
+mod extensions;
+mod references;
+mod rules;
+mod struct_names;
+mod units;
+
+/// Which lints to run, configured in the `lint` section of `ron-utils.ron`
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct LintConfig {
+    /// Enforce a struct name style, see [`check_struct_names`]
+    pub struct_names: Option<StructNameStyle>,
+    /// Type name of the root expression
+    pub root_type: Option<String>,
+    /// Report unused and missing `#![enable(..)]`s of literal extensions,
+    /// see [`check_extensions`]
+    pub extensions: bool,
+    /// Check references between structs by their IDs, see
+    /// [`check_references`]
+    pub references: Option<ReferenceKeys>,
+    /// Enforce how the quantities of the `unit_literals` extension are
+    /// written, see [`check_units`]
+    pub units: Option<UnitStyle>,
+    /// Names of the project's [`LintRule`]s to run, which have to be
+    /// registered, see [`lint_str_with_rules`]
+    pub rules: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    /// Name of the lint that reported this diagnostic
+    pub lint: &'static str,
+    pub message: String,
+    pub start: Location,
+    pub end: Location,
+    /// Edits that resolve this diagnostic, if it can be fixed automatically
+    pub fix: Option<Vec<TextEdit>>,
+}
+
+impl Diagnostic {
+    /// Converts the diagnostic into an `Error` so it can be rendered with `print_error`.
+    pub fn to_error(&self, source: &str) -> Error {
+        Error {
+            kind: ErrorKind::Custom(format!("{} [{}]", self.message, self.lint)),
+            context: None,
+        }
+        .context_loc(self.start, self.end)
+        .context_file_content(source.to_owned())
+    }
+}
+
+/// Runs all lints enabled in `config` on `source`.
+pub fn lint_str(source: &str, config: &LintConfig) -> Result<Vec<Diagnostic>, Error> {
+    lint_str_with_rules(source, config, &LintRules::new())
+}
+
+/// [`lint_str`], also running the `rules` listed in `config`
+///
+/// Listing a rule that isn't registered is an error.
+pub fn lint_str_with_rules(
+    source: &str,
+    config: &LintConfig,
+    rules: &LintRules,
+) -> Result<Vec<Diagnostic>, Error> {
+    let ron = match ast_from_str(source) {
+        Ok(ron) => ron,
+        // The other lints need a document that parses
+        Err(e) if config.extensions && matches!(e.kind, ErrorKind::ExtensionNotEnabled(_)) => {
+            return check_extensions(source)
+        }
+        Err(e) => return Err(e),
+    };
+    let mut diagnostics = vec![];
+
+    if let Some(style) = config.struct_names {
+        diagnostics.extend(check_struct_names(
+            source,
+            &ron,
+            style,
+            config.root_type.as_deref(),
+        ));
+    }
+    if config.extensions {
+        diagnostics.extend(check_extensions(source)?);
+    }
+    if let Some(keys) = &config.references {
+        diagnostics.extend(check_references(&ron, keys));
+    }
+    if let Some(style) = config.units {
+        diagnostics.extend(check_units(source, &ron, style));
+    }
+    for name in &config.rules {
+        match rules.get(name) {
+            Some(rule) => rule.check(source, &ron, &mut diagnostics),
+            None => {
+                let names = rules
+                    .names()
+                    .map(|name| format!("`{}`", name))
+                    .collect::<Vec<_>>();
+                return Err(Error {
+                    kind: ErrorKind::Custom(format!(
+                        "unknown lint rule `{}`, registered are {}",
+                        name,
+                        match names.is_empty() {
+                            true => "none".to_owned(),
+                            false => names.join(", "),
+                        }
+                    )),
+                    context: None,
+                });
+            }
+        }
+    }
+
+    Ok(diagnostics)
+}
+
+/// Applies the fixes of all fixable `diagnostics` to `source`.
+///
+/// Edits overlapping an earlier edit are skipped; running the lints again
+/// will report them.
+pub fn apply_fixes(source: &str, diagnostics: &[Diagnostic]) -> String {
+    apply_edits(
+        source,
+        diagnostics.iter().filter_map(|d| d.fix.as_ref()).flatten(),
+    )
+}
+
+/// The location one char after `location`
+fn next_column(location: Location) -> Location {
+    location.add_columns(1)
+}
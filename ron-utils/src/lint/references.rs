@@ -0,0 +1,303 @@
+use std::collections::HashMap;
+
+use ron_reboot::{
+    ast::{walk_expr, Expr, Ron, Spanned, Struct, Visitor},
+    Location,
+};
+use serde::Deserialize;
+
+use crate::lint::Diagnostic;
+
+const LINT: &str = "references";
+
+/// The fields that identify structs and refer to them, e.g. in `ron-utils.ron`:
+///
+/// ```text
+/// lint: (references: Some((id: "id", references: ["parent", "targets"])))
+/// ```
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReferenceKeys {
+    /// The field holding the ID of a struct, `id` by default
+    pub id: String,
+    /// Fields holding the IDs of other structs, as a single ID, `Some(id)` or
+    /// a list of IDs; `parent` by default
+    pub references: Vec<String>,
+}
+
+impl Default for ReferenceKeys {
+    fn default() -> Self {
+        ReferenceKeys {
+            id: "id".to_owned(),
+            references: vec!["parent".to_owned()],
+        }
+    }
+}
+
+/// Checks the references between the structs of `ron`, e.g. of a scene graph
+/// whose nodes refer to their `parent` by its `id`.
+///
+/// IDs are strings, integers or identifiers. Reported are IDs used by more
+/// than one struct, references to IDs no struct has and references that lead
+/// back to the struct they start from.
+pub fn check_references(ron: &Ron, keys: &ReferenceKeys) -> Vec<Diagnostic> {
+    let mut collector = Collector {
+        keys,
+        ids: vec![],
+        references: vec![],
+    };
+    collector.visit_ron(ron);
+
+    let mut diagnostics = vec![];
+    let mut first: HashMap<&str, &Id> = HashMap::new();
+    for id in &collector.ids {
+        match first.get(id.name.as_str()) {
+            Some(first) => diagnostics.push(Diagnostic {
+                lint: LINT,
+                message: format!("duplicate id `{}`, first used at {}", id.name, first.start),
+                start: id.start,
+                end: id.end,
+                fix: None,
+            }),
+            None => {
+                first.insert(&id.name, id);
+            }
+        }
+    }
+
+    let mut graph: HashMap<&str, Vec<&Reference>> = HashMap::new();
+    for reference in &collector.references {
+        if !first.contains_key(reference.to.as_str()) {
+            diagnostics.push(Diagnostic {
+                lint: LINT,
+                message: format!("reference to unknown id `{}`", reference.to),
+                start: reference.start,
+                end: reference.end,
+                fix: None,
+            });
+        } else if let Some(from) = &reference.from {
+            graph.entry(from).or_default().push(reference);
+        }
+    }
+
+    let mut cycles = Cycles {
+        graph: &graph,
+        visited: HashMap::new(),
+        path: vec![],
+        diagnostics: &mut diagnostics,
+    };
+    for id in &collector.ids {
+        if !cycles.visited.contains_key(id.name.as_str()) {
+            cycles.visit(&id.name);
+        }
+    }
+
+    diagnostics
+}
+
+struct Id {
+    name: String,
+    start: Location,
+    end: Location,
+}
+
+struct Reference {
+    /// The ID of the struct with the reference, if it has one
+    from: Option<String>,
+    to: String,
+    start: Location,
+    end: Location,
+}
+
+struct Collector<'k> {
+    keys: &'k ReferenceKeys,
+    ids: Vec<Id>,
+    references: Vec<Reference>,
+}
+
+impl<'a> Visitor<'a> for Collector<'_> {
+    fn visit_expr(&mut self, expr: &Spanned<Expr<'a>>) {
+        if let Some(s) = expr.value.as_struct() {
+            self.collect(s);
+        }
+
+        walk_expr(self, expr);
+    }
+}
+
+impl Collector<'_> {
+    fn collect(&mut self, s: &Struct) {
+        let field = |key: &str| {
+            s.fields
+                .iter()
+                .find(|field| field.value.key.value.0 == key)
+                .map(|field| &field.value.value)
+        };
+
+        let from = field(&self.keys.id).and_then(|value| {
+            let name = id_name(&value.value)?;
+            self.ids.push(Id {
+                name: name.clone(),
+                start: value.start,
+                end: value.end,
+            });
+
+            Some(name)
+        });
+
+        for key in &self.keys.references {
+            let value = match field(key) {
+                Some(value) => value,
+                None => continue,
+            };
+            let targets = match &value.value {
+                Expr::List(list) => list.elements.iter().collect(),
+                _ => vec![value],
+            };
+            for target in targets {
+                let target = match &target.value {
+                    Expr::Optional(Some(inner)) => inner,
+                    _ => target,
+                };
+                if let Some(to) = id_name(&target.value) {
+                    self.references.push(Reference {
+                        from: from.clone(),
+                        to,
+                        start: target.start,
+                        end: target.end,
+                    });
+                }
+            }
+        }
+    }
+}
+
+fn id_name(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Str(s) => Some((*s).to_owned()),
+        Expr::String(s) => Some(s.clone()),
+        Expr::EscapedStr(s) => Some(s.unescape().into_owned()),
+        Expr::Integer(i) => Some(i.to_string()),
+        expr => expr
+            .as_unit_ident()
+            .map(|ident| ident.value.as_str().to_owned()),
+    }
+}
+
+/// Depth-first search for references back to an ID on the current path
+struct Cycles<'g, 'd> {
+    graph: &'g HashMap<&'g str, Vec<&'g Reference>>,
+    /// Whether the search is done with an ID, or still on its path
+    visited: HashMap<&'g str, bool>,
+    path: Vec<&'g str>,
+    diagnostics: &'d mut Vec<Diagnostic>,
+}
+
+impl<'g> Cycles<'g, '_> {
+    fn visit(&mut self, id: &'g str) {
+        self.visited.insert(id, false);
+        self.path.push(id);
+
+        for reference in self.graph.get(id).into_iter().flatten() {
+            let to = reference.to.as_str();
+            match self.visited.get(to) {
+                None => self.visit(to),
+                // Each cycle is reported once, at the reference closing it
+                Some(false) => {
+                    let start = self.path.iter().rposition(|&id| id == to).unwrap_or(0);
+                    let cycle = self.path[start..]
+                        .iter()
+                        .chain(Some(&to))
+                        .map(|id| format!("`{}`", id))
+                        .collect::<Vec<_>>();
+                    self.diagnostics.push(Diagnostic {
+                        lint: LINT,
+                        message: format!("reference cycle {}", cycle.join(" -> ")),
+                        start: reference.start,
+                        end: reference.end,
+                        fix: None,
+                    });
+                }
+                Some(true) => {}
+            }
+        }
+
+        self.path.pop();
+        self.visited.insert(id, true);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ron_reboot::utf8_parser::ast_from_str;
+
+    use super::*;
+
+    fn messages(source: &str, keys: &ReferenceKeys) -> Vec<String> {
+        let ron = ast_from_str(source).unwrap();
+
+        check_references(&ron, keys)
+            .into_iter()
+            .map(|d| format!("{}: {}", d.start, d.message))
+            .collect()
+    }
+
+    #[test]
+    fn scene_graph() {
+        let source = r#"[
+    (id: "root"),
+    Node(id: "camera", parent: "root"),
+    (id: "light", parent: Some("root")),
+    (id: "player", parent: "world"),
+    (id: "camera"),
+    (parent: None),
+]"#;
+        assert_eq!(
+            messages(source, &ReferenceKeys::default()),
+            [
+                "6:10: duplicate id `camera`, first used at 3:14",
+                "5:28: reference to unknown id `world`",
+            ]
+        );
+    }
+
+    #[test]
+    fn cycles() {
+        let source = "[
+    (id: 1, parent: 3),
+    (id: 2, parent: 1),
+    (id: 3, parent: 2),
+    (id: 4, parent: 4),
+    (id: 5, parent: 1),
+]";
+        assert_eq!(
+            messages(source, &ReferenceKeys::default()),
+            [
+                "3:21: reference cycle `1` -> `3` -> `2` -> `1`",
+                "5:21: reference cycle `4` -> `4`",
+            ]
+        );
+    }
+
+    #[test]
+    fn configured_keys() {
+        let keys = ReferenceKeys {
+            id: "name".to_owned(),
+            references: vec!["targets".to_owned(), "parent".to_owned()],
+        };
+        let source = "(
+    nodes: [
+        (name: Door, targets: [Lamp, Alarm]),
+        (name: Lamp, id: 1, parent: 1),
+    ],
+)";
+        assert_eq!(
+            messages(source, &keys),
+            [
+                "3:38: reference to unknown id `Alarm`",
+                // `id` isn't the ID key here
+                "4:37: reference to unknown id `1`",
+            ]
+        );
+    }
+}
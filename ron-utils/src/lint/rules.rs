@@ -0,0 +1,135 @@
+use ron_reboot::ast::Ron;
+
+use crate::lint::Diagnostic;
+
+/// A lint of a project, e.g. for the conventions of its game configs
+///
+/// Rules run in [`lint_str_with_rules`](crate::lint::lint_str_with_rules)
+/// if they are registered in its [`LintRules`] and listed in
+/// [`LintConfig::rules`](crate::lint::LintConfig::rules). The `ron` binary
+/// has none registered, so projects run theirs from their own tools or
+/// tests, with the same `ron-utils.ron`.
+pub trait LintRule {
+    /// The name listed in `rules` and reported with the diagnostics
+    fn name(&self) -> &'static str;
+
+    /// Adds the diagnostics of `ron`, which was parsed from `source`, e.g. for
+    /// the byte offsets of [`TextEdit`](crate::lint::TextEdit)s (see
+    /// [`byte_offset`](crate::edit::byte_offset))
+    fn check(&self, source: &str, ron: &Ron, diagnostics: &mut Vec<Diagnostic>);
+}
+
+/// The lint rules a config may list
+///
+/// Empty when created, rules are added with [`with`](LintRules::with).
+#[derive(Default)]
+pub struct LintRules {
+    rules: Vec<Box<dyn LintRule>>,
+}
+
+impl LintRules {
+    pub fn new() -> Self {
+        LintRules::default()
+    }
+
+    /// Registers `rule`, replacing any rule with the same name
+    #[must_use]
+    pub fn with(mut self, rule: impl LintRule + 'static) -> Self {
+        self.rules.retain(|r| r.name() != rule.name());
+        self.rules.push(Box::new(rule));
+
+        self
+    }
+
+    /// The rule called `name`
+    pub fn get(&self, name: &str) -> Option<&dyn LintRule> {
+        self.rules
+            .iter()
+            .find(|r| r.name() == name)
+            .map(|r| r.as_ref())
+    }
+
+    /// The names of all registered rules
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.rules.iter().map(|r| r.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ron_reboot::{
+        ast::{walk_expr, Expr, Spanned, Visitor},
+        ErrorKind,
+    };
+
+    use super::*;
+    use crate::{
+        edit::byte_offset,
+        lint::{apply_fixes, lint_str, lint_str_with_rules, LintConfig, TextEdit},
+    };
+
+    /// Rejects negative integers, fixed by dropping the sign
+    struct NoNegatives;
+
+    impl LintRule for NoNegatives {
+        fn name(&self) -> &'static str {
+            "no_negatives"
+        }
+
+        fn check(&self, source: &str, ron: &Ron, diagnostics: &mut Vec<Diagnostic>) {
+            struct Negatives<'d>(&'d str, &'d mut Vec<Diagnostic>);
+
+            impl<'a> Visitor<'a> for Negatives<'_> {
+                fn visit_expr(&mut self, expr: &Spanned<Expr<'a>>) {
+                    if let Expr::Integer(i) = &expr.value {
+                        if i.to_string().starts_with('-') {
+                            let start = byte_offset(self.0, expr.start);
+                            self.1.push(Diagnostic {
+                                lint: "no_negatives",
+                                message: "negative integer".to_owned(),
+                                start: expr.start,
+                                end: expr.end,
+                                fix: Some(vec![TextEdit::delete(start, start + 1)]),
+                            });
+                        }
+                    }
+                    walk_expr(self, expr);
+                }
+            }
+
+            Negatives(source, diagnostics).visit_ron(ron);
+        }
+    }
+
+    #[test]
+    fn registered_rules() {
+        let rules = LintRules::new().with(NoNegatives);
+        assert_eq!(rules.names().collect::<Vec<_>>(), ["no_negatives"]);
+
+        let source = "(a: -1, b: [2, -3])";
+        let config = LintConfig {
+            rules: vec!["no_negatives".to_owned()],
+            ..LintConfig::default()
+        };
+        let diagnostics = lint_str_with_rules(source, &config, &rules).unwrap();
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(apply_fixes(source, &diagnostics), "(a: 1, b: [2, 3])");
+
+        // Only listed rules run
+        let diagnostics = lint_str_with_rules(source, &LintConfig::default(), &rules).unwrap();
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn unknown_rule() {
+        let config = LintConfig {
+            rules: vec!["no_negatives".to_owned()],
+            ..LintConfig::default()
+        };
+        let e = lint_str("1", &config).unwrap_err();
+        assert_eq!(
+            e.kind,
+            ErrorKind::Custom("unknown lint rule `no_negatives`, registered are none".to_owned())
+        );
+    }
+}
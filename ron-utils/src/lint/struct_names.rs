@@ -0,0 +1,173 @@
+use std::{fmt, str::FromStr};
+
+use ron_reboot::ast::{Expr, Ron, Untagged};
+use serde::Deserialize;
+
+use crate::{
+    edit::{byte_offset, TextEdit},
+    lint::{next_column, Diagnostic},
+};
+
+const LINT: &str = "struct_names";
+
+/// Whether structs are written with or without their name
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+pub enum StructNameStyle {
+    /// `Config(width: 1920)`
+    Named,
+    /// `(width: 1920)`
+    Anonymous,
+}
+
+impl StructNameStyle {
+    pub fn variants() -> [&'static str; 2] {
+        ["named", "anonymous"]
+    }
+}
+
+impl FromStr for StructNameStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "named" => Ok(StructNameStyle::Named),
+            "anonymous" => Ok(StructNameStyle::Anonymous),
+            _ => Err(format!(
+                "valid values: {}",
+                Self::variants().to_vec().join(", ")
+            )),
+        }
+    }
+}
+
+impl fmt::Display for StructNameStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StructNameStyle::Named => write!(f, "named"),
+            StructNameStyle::Anonymous => write!(f, "anonymous"),
+        }
+    }
+}
+
+/// Checks that the root struct is written in the given `style`.
+///
+/// Without type information, neither the names of nested structs are known nor
+/// can a named struct be told apart from a struct-like enum variant, so only the
+/// root expression is checked. Names are only inserted or stripped if the
+/// `root_type` is known.
+pub fn check_struct_names(
+    source: &str,
+    ron: &Ron,
+    style: StructNameStyle,
+    root_type: Option<&str>,
+) -> Vec<Diagnostic> {
+    let expr = &ron.expr;
+
+    match &expr.value {
+        Expr::Struct(_) if style == StructNameStyle::Named => vec![Diagnostic {
+            lint: LINT,
+            message: match root_type {
+                Some(name) => format!("missing struct name `{}`", name),
+                None => "missing struct name".to_owned(),
+            },
+            start: expr.start,
+            end: next_column(expr.start),
+            fix: root_type
+                .map(|name| vec![TextEdit::insert(byte_offset(source, expr.start), name)]),
+        }],
+        Expr::Tagged(t) if matches!(t.untagged.value, Untagged::Struct(_)) => {
+            let ident = &t.ident;
+            let name = ident.value.as_str();
+            let ident_start = byte_offset(source, ident.start);
+
+            match (root_type, style) {
+                (Some(expected), StructNameStyle::Named) if expected != name => vec![Diagnostic {
+                    lint: LINT,
+                    message: format!("struct name `{}` does not match `{}`", name, expected),
+                    start: ident.start,
+                    end: ident.end,
+                    fix: Some(vec![TextEdit {
+                        start: ident_start,
+                        end: byte_offset(source, ident.end),
+                        replacement: expected.to_owned(),
+                    }]),
+                }],
+                (Some(expected), StructNameStyle::Anonymous) => vec![Diagnostic {
+                    lint: LINT,
+                    message: match expected == name {
+                        true => format!("redundant struct name `{}`", name),
+                        false => format!("struct name `{}` does not match `{}`", name, expected),
+                    },
+                    start: ident.start,
+                    end: ident.end,
+                    fix: Some(vec![TextEdit::delete(
+                        ident_start,
+                        byte_offset(source, t.untagged.start),
+                    )]),
+                }],
+                _ => vec![],
+            }
+        }
+        _ => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ron_reboot::utf8_parser::ast_from_str;
+
+    use super::*;
+    use crate::lint::apply_fixes;
+
+    fn fix(source: &str, style: StructNameStyle, root_type: Option<&str>) -> String {
+        let ron = ast_from_str(source).unwrap();
+
+        apply_fixes(source, &check_struct_names(source, &ron, style, root_type))
+    }
+
+    #[test]
+    fn insert_name() {
+        assert_eq!(
+            fix("(a: 1)", StructNameStyle::Named, Some("Config")),
+            "Config(a: 1)"
+        );
+        assert_eq!(
+            fix("\n  (a: 1)", StructNameStyle::Named, Some("Config")),
+            "\n  Config(a: 1)"
+        );
+        assert_eq!(
+            fix("Other (a: 1)", StructNameStyle::Named, Some("Config")),
+            "Config (a: 1)"
+        );
+        assert_eq!(
+            fix("Config(a: 1)", StructNameStyle::Named, Some("Config")),
+            "Config(a: 1)"
+        );
+    }
+
+    #[test]
+    fn strip_name() {
+        assert_eq!(
+            fix("Config (a: 1)", StructNameStyle::Anonymous, Some("Config")),
+            "(a: 1)"
+        );
+        assert_eq!(
+            fix("(a: 1)", StructNameStyle::Anonymous, Some("Config")),
+            "(a: 1)"
+        );
+    }
+
+    #[test]
+    fn unknown_root_type() {
+        let source = "(a: 1)";
+        let ron = ast_from_str(source).unwrap();
+        let diagnostics = check_struct_names(source, &ron, StructNameStyle::Named, None);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].fix, None);
+
+        // Might be a struct-like enum variant
+        let source = "Variant(a: 1)";
+        let ron = ast_from_str(source).unwrap();
+        assert!(check_struct_names(source, &ron, StructNameStyle::Anonymous, None).is_empty());
+    }
+}
@@ -0,0 +1,249 @@
+use std::{fmt, str::FromStr};
+
+use ron_reboot::ast::{walk_expr, Expr, Quantity, Ron, Spanned, Unit, UnitKind, Visitor};
+use serde::Deserialize;
+
+use crate::{
+    edit::{byte_offset, TextEdit},
+    lint::Diagnostic,
+};
+
+const LINT: &str = "units";
+
+/// Fractional digits of the amounts written in the [`UnitStyle::Decimal`]
+/// style
+const DECIMALS: u32 = 3;
+
+const DURATION_UNITS: [Unit; 7] = [
+    Unit::Nanoseconds,
+    Unit::Microseconds,
+    Unit::Milliseconds,
+    Unit::Seconds,
+    Unit::Minutes,
+    Unit::Hours,
+    Unit::Days,
+];
+
+const BINARY_UNITS: [Unit; 5] = [
+    Unit::Bytes,
+    Unit::Kibibytes,
+    Unit::Mebibytes,
+    Unit::Gibibytes,
+    Unit::Tebibytes,
+];
+
+const DECIMAL_UNITS: [Unit; 5] = [
+    Unit::Bytes,
+    Unit::Kilobytes,
+    Unit::Megabytes,
+    Unit::Gigabytes,
+    Unit::Terabytes,
+];
+
+/// How the quantities of the `unit_literals` extension are written
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+pub enum UnitStyle {
+    /// In the largest unit the amount is a whole number in, e.g. `1500ms`
+    /// and `2s`
+    Whole,
+    /// In the largest unit the amount is at least 1 in, with up to three
+    /// fractional digits, e.g. `1.5s` and `2s`
+    Decimal,
+}
+
+impl UnitStyle {
+    pub fn variants() -> [&'static str; 2] {
+        ["whole", "decimal"]
+    }
+}
+
+impl FromStr for UnitStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "whole" => Ok(UnitStyle::Whole),
+            "decimal" => Ok(UnitStyle::Decimal),
+            _ => Err(format!(
+                "valid values: {}",
+                Self::variants().to_vec().join(", ")
+            )),
+        }
+    }
+}
+
+impl fmt::Display for UnitStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnitStyle::Whole => write!(f, "whole"),
+            UnitStyle::Decimal => write!(f, "decimal"),
+        }
+    }
+}
+
+/// Checks that the quantities in `ron`, e.g. `250ms`, are written in the
+/// given `style`, so that e.g. `1500ms` and `1.5s` aren't mixed.
+///
+/// Sizes keep their decimal (`KB`) or binary (`KiB`) units; amounts in bytes
+/// may get either. Quantities that aren't a whole number of nanoseconds or
+/// bytes are left as they are, as are zeros.
+pub fn check_units(source: &str, ron: &Ron, style: UnitStyle) -> Vec<Diagnostic> {
+    struct Check<'s> {
+        source: &'s str,
+        style: UnitStyle,
+        diagnostics: Vec<Diagnostic>,
+    }
+
+    impl<'a> Visitor<'a> for Check<'_> {
+        fn visit_expr(&mut self, expr: &Spanned<Expr<'a>>) {
+            if let Expr::Quantity(quantity) = &expr.value {
+                let (start, end) = (
+                    byte_offset(self.source, expr.start),
+                    byte_offset(self.source, expr.end),
+                );
+                let written = &self.source[start..end];
+
+                match normalize(quantity, self.style) {
+                    Some(normalized) if normalized != written => {
+                        self.diagnostics.push(Diagnostic {
+                            lint: LINT,
+                            message: format!("`{}` should be written as `{}`", written, normalized),
+                            start: expr.start,
+                            end: expr.end,
+                            fix: Some(vec![TextEdit {
+                                start,
+                                end,
+                                replacement: normalized,
+                            }]),
+                        })
+                    }
+                    _ => {}
+                }
+            }
+
+            walk_expr(self, expr);
+        }
+    }
+
+    let mut check = Check {
+        source,
+        style,
+        diagnostics: vec![],
+    };
+    check.visit_ron(ron);
+
+    check.diagnostics
+}
+
+/// How `quantity` is written in `style`
+fn normalize(quantity: &Quantity, style: UnitStyle) -> Option<String> {
+    let amount = quantity.exact_base_units().filter(|&amount| amount > 0)?;
+    let units: &[Unit] = match quantity.unit {
+        unit if unit.kind() == UnitKind::Duration => &DURATION_UNITS,
+        Unit::Bytes => &[
+            Unit::Bytes,
+            Unit::Kilobytes,
+            Unit::Kibibytes,
+            Unit::Megabytes,
+            Unit::Mebibytes,
+            Unit::Gigabytes,
+            Unit::Gibibytes,
+            Unit::Terabytes,
+            Unit::Tebibytes,
+        ],
+        unit if BINARY_UNITS.contains(&unit) => &BINARY_UNITS,
+        _ => &DECIMAL_UNITS,
+    };
+    let scale = match style {
+        UnitStyle::Whole => 1,
+        UnitStyle::Decimal => 10u128.pow(DECIMALS),
+    };
+
+    let unit = units
+        .iter()
+        .map(|&unit| (unit, u128::from(unit.base_units())))
+        .filter(|&(_, factor)| factor <= amount)
+        .filter(|&(_, factor)| amount.checked_mul(scale).is_some_and(|a| a % factor == 0))
+        .max_by_key(|&(_, factor)| factor);
+
+    unit.map(|(unit, factor)| {
+        let fraction = match amount % factor {
+            0 => String::new(),
+            rest => {
+                let digits = format!(
+                    "{:0width$}",
+                    rest * scale / factor,
+                    width = DECIMALS as usize
+                );
+                format!(".{}", digits.trim_end_matches('0'))
+            }
+        };
+
+        format!("{}{}{}", amount / factor, fraction, unit.suffix())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use ron_reboot::utf8_parser::ast_from_str;
+
+    use super::*;
+    use crate::lint::apply_fixes;
+
+    fn fix(value: &str, style: UnitStyle) -> String {
+        let source = format!("#![enable(unit_literals)]\n{}", value);
+        let ron = ast_from_str(&source).unwrap();
+        let fixed = apply_fixes(&source, &check_units(&source, &ron, style));
+
+        fixed["#![enable(unit_literals)]\n".len()..].to_owned()
+    }
+
+    #[test]
+    fn whole() {
+        for (value, fixed) in [
+            ("1500ms", "1500ms"),
+            ("2000ms", "2s"),
+            ("120s", "2min"),
+            ("90s", "90s"),
+            ("0.5s", "500ms"),
+            ("1.0h", "1h"),
+            ("48h", "2d"),
+            ("2048B", "2KiB"),
+            ("3000B", "3KB"),
+            ("1024KB", "1024KB"),
+            ("1024KiB", "1MiB"),
+            ("0ms", "0ms"),
+        ] {
+            assert_eq!(fix(value, UnitStyle::Whole), fixed, "{}", value);
+        }
+    }
+
+    #[test]
+    fn decimal() {
+        for (value, fixed) in [
+            ("1500ms", "1.5s"),
+            ("1234ms", "1.234s"),
+            ("1001ms", "1.001s"),
+            ("1ms", "1ms"),
+            ("1000001us", "1000.001ms"),
+            ("90s", "1.5min"),
+            ("1.50s", "1.5s"),
+            ("1536B", "1.5KiB"),
+            ("1500B", "1.5KB"),
+            ("1536KB", "1.536MB"),
+        ] {
+            assert_eq!(fix(value, UnitStyle::Decimal), fixed, "{}", value);
+        }
+    }
+
+    #[test]
+    fn nested() {
+        assert_eq!(
+            fix(
+                "(timeouts: [1000ms, 2s], retry: Some(60000ms))",
+                UnitStyle::Whole
+            ),
+            "(timeouts: [1s, 2s], retry: Some(1min))"
+        );
+    }
+}
@@ -0,0 +1,488 @@
+//! Three-way merges of RON documents, e.g. as a git merge driver
+//!
+//! Changes are merged per struct field, map entry and element of lists and
+//! tuples, so that edits of different fields of the same struct don't
+//! conflict. The merged document is `ours` with the changes of `theirs`
+//! applied, keeping the formatting and comments of both.
+
+use ron_reboot::{
+    ast::{Expr, List, Spanned, Struct, Tuple, Untagged},
+    lex::trivia_len,
+    path::{Step, ValuePath},
+    utf8_parser::ast_from_str,
+    Error, Location,
+};
+
+use crate::{
+    edit::{byte_offset, TextEdit},
+    patch::{append_field, patch_str, span, Edit},
+};
+
+/// The result of [`merge_str`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Merge {
+    pub merged: String,
+    /// Paths of the values both sides changed in different ways
+    ///
+    /// `merged` has the value of `ours` for each of them, after a comment
+    /// with both versions between git's conflict markers, e.g.:
+    ///
+    /// ```text
+    /// // <<<<<<< ours `window.width`
+    /// // 800
+    /// // =======
+    /// // 1024
+    /// // >>>>>>> theirs
+    /// width: 800,
+    /// ```
+    pub conflicts: Vec<ValuePath>,
+}
+
+/// Merges the changes from `base` to `theirs` into `ours`.
+///
+/// Values are merged if only one side changed them, or both in the same
+/// way. Struct fields and map entries one side added are added, and those one
+/// side removed without the other changing them are removed. Lists are only
+/// merged element by element if they have the same length on all sides, and
+/// attributes like `#![enable(..)]` are those of `ours`.
+///
+/// Errors of the documents are reported with the file name `base`, `ours` or
+/// `theirs`.
+pub fn merge_str(base: &str, ours: &str, theirs: &str) -> Result<Merge, Error> {
+    let parse = |source, name: &str| {
+        ast_from_str(source).map_err(|e| {
+            e.context_file_name(name.to_owned())
+                .context_file_content(source.to_owned())
+        })
+    };
+    let (base_ron, ours_ron, theirs_ron) = (
+        parse(base, "base")?,
+        parse(ours, "ours")?,
+        parse(theirs, "theirs")?,
+    );
+
+    let mut merger = Merger {
+        ours,
+        theirs,
+        edits: vec![],
+        conflicts: vec![],
+    };
+    merger.merge(
+        ValuePath::root(),
+        Some(&base_ron.expr),
+        &ours_ron.expr,
+        &theirs_ron.expr,
+    );
+
+    Ok(Merge {
+        merged: patch_str(ours, &merger.edits)?,
+        conflicts: merger.conflicts,
+    })
+}
+
+struct Merger<'s> {
+    ours: &'s str,
+    theirs: &'s str,
+    edits: Vec<Edit>,
+    conflicts: Vec<ValuePath>,
+}
+
+/// A struct field, map entry or element
+struct Entry<'b, 'a> {
+    step: Step,
+    start: Location,
+    /// Source range of the key, `None` for elements
+    key: Option<(Location, Location)>,
+    value: &'b Spanned<Expr<'a>>,
+}
+
+/// Which values can be merged entry by entry
+#[derive(PartialEq)]
+enum Shape<'a> {
+    Struct(Option<&'a str>),
+    Map,
+    /// Tuples, with their name and length
+    Tuple(Option<&'a str>, usize),
+    List(usize),
+}
+
+fn entries<'b, 'a>(expr: &'b Expr<'a>) -> Option<(Shape<'b>, Vec<Entry<'b, 'a>>)> {
+    let fields = |name, s: &'b Struct<'a>| {
+        let fields = s
+            .fields
+            .iter()
+            .map(|field| Entry {
+                step: Step::Key(field.value.key.value.as_str().to_owned()),
+                start: field.start,
+                key: Some((field.value.key.start, field.value.key.end)),
+                value: &field.value.value,
+            })
+            .collect();
+
+        (Shape::Struct(name), fields)
+    };
+    let elements = |elements: &'b [Spanned<Expr<'a>>]| {
+        elements
+            .iter()
+            .enumerate()
+            .map(|(i, element)| Entry {
+                step: Step::Index(i),
+                start: element.start,
+                key: None,
+                value: element,
+            })
+            .collect()
+    };
+
+    match expr {
+        Expr::Struct(s) => Some(fields(None, s)),
+        Expr::Tagged(t) => match &t.untagged.value {
+            Untagged::Struct(s) => Some(fields(Some(t.ident.value.as_str()), s)),
+            Untagged::Tuple(Tuple { elements: e }) => Some((
+                Shape::Tuple(Some(t.ident.value.as_str()), e.len()),
+                elements(e),
+            )),
+            Untagged::Unit => None,
+        },
+        Expr::Tuple(Tuple { elements: e }) => Some((Shape::Tuple(None, e.len()), elements(e))),
+        Expr::List(List { elements: e }) => Some((Shape::List(e.len()), elements(e))),
+        // Only with keys paths can address, which are compared by their step
+        Expr::Map(map) => map
+            .entries
+            .iter()
+            .map(|entry| {
+                Some(Entry {
+                    step: Step::from_key(&entry.value.key.value)?,
+                    start: entry.start,
+                    key: Some((entry.value.key.start, entry.value.key.end)),
+                    value: &entry.value.value,
+                })
+            })
+            .collect::<Option<_>>()
+            .map(|entries| (Shape::Map, entries)),
+        _ => None,
+    }
+}
+
+impl Merger<'_> {
+    fn merge(
+        &mut self,
+        path: ValuePath,
+        base: Option<&Spanned<Expr>>,
+        ours: &Spanned<Expr>,
+        theirs: &Spanned<Expr>,
+    ) {
+        let is_base = |expr: &Spanned<Expr>| base.is_some_and(|base| base.value == expr.value);
+        if ours.value == theirs.value || is_base(theirs) {
+            return;
+        }
+        if is_base(ours) {
+            let (start, end) = span(self.ours, ours);
+            self.edits.push(Edit::Text(TextEdit {
+                start,
+                end,
+                replacement: self.text(theirs).to_owned(),
+            }));
+            return;
+        }
+
+        let merged = match (base.map(|base| &base.value), &ours.value, &theirs.value) {
+            (
+                Some(Expr::Optional(Some(base))),
+                Expr::Optional(Some(o)),
+                Expr::Optional(Some(t)),
+            ) => {
+                self.merge(path.clone(), Some(base), o, t);
+                true
+            }
+            (Some(base), _, _) => self.merge_entries(&path, base, ours, theirs),
+            (None, _, _) => false,
+        };
+        if !merged {
+            self.conflict(path, Some(ours), Some(theirs), ours.start);
+        }
+    }
+
+    /// Merges the entries of values with the same shape, returning whether
+    /// they have one
+    fn merge_entries(
+        &mut self,
+        path: &ValuePath,
+        base: &Expr,
+        ours: &Spanned<Expr>,
+        theirs: &Spanned<Expr>,
+    ) -> bool {
+        let (base, ours_entries, theirs_entries) =
+            match (entries(base), entries(&ours.value), entries(&theirs.value)) {
+                (Some((b, base)), Some((o, ours)), Some((t, theirs))) if b == o && o == t => {
+                    (base, ours, theirs)
+                }
+                _ => return false,
+            };
+        let find = |entries: &[Entry<'_, '_>], step: &Step| {
+            entries.iter().position(|entry| entry.step == *step)
+        };
+
+        let steps = ours_entries
+            .iter()
+            .chain(
+                theirs_entries
+                    .iter()
+                    .filter(|t| find(&ours_entries, &t.step).is_none()),
+            )
+            .map(|entry| entry.step.clone());
+        for step in steps.collect::<Vec<_>>() {
+            let b = find(&base, &step).map(|i| base[i].value);
+            let o = find(&ours_entries, &step);
+            let t = find(&theirs_entries, &step).map(|i| &theirs_entries[i]);
+            let entry_path = path.join(step);
+
+            match (b, o, t) {
+                (b, Some(o), Some(t)) => self.merge(entry_path, b, ours_entries[o].value, t.value),
+                // Added by theirs
+                (None, None, Some(t)) => match ours_entries.last() {
+                    Some(last) => {
+                        let (start, end) = t.key.expect("added entries have keys");
+                        let key = &self.theirs
+                            [byte_offset(self.theirs, start)..byte_offset(self.theirs, end)];
+                        let edits = append_field(
+                            self.ours,
+                            ours,
+                            (last.start, last.value),
+                            key.trim_end(),
+                            self.text(t.value),
+                        );
+                        self.edits.extend(edits.into_iter().map(Edit::Text));
+                    }
+                    None => self.conflict(entry_path, None, Some(t.value), ours.start),
+                },
+                // Removed by ours, changed by theirs
+                (Some(b), None, Some(t)) if b.value != t.value.value => {
+                    self.conflict(entry_path, None, Some(t.value), ours.start)
+                }
+                // Removed by theirs
+                (Some(b), Some(o), None) => match b.value == ours_entries[o].value.value {
+                    true => {
+                        let edit = self.remove(&ours_entries, o);
+                        self.edits.push(Edit::Text(edit));
+                    }
+                    false => {
+                        let o = ours_entries[o].value;
+                        self.conflict(entry_path, Some(o), None, o.start)
+                    }
+                },
+                _ => {}
+            }
+        }
+
+        true
+    }
+
+    /// The edit removing `entries[i]` of `self.ours` with its comma, and its
+    /// lines if it has them to itself
+    fn remove(&self, entries: &[Entry], i: usize) -> TextEdit {
+        let source = self.ours;
+        let start = byte_offset(source, entries[i].start);
+        let (_, value_end) = span(source, entries[i].value);
+        let after = value_end + trivia_len(&source.as_bytes()[value_end..]);
+        let end = match source[after..].starts_with(',') {
+            true => after + 1,
+            false => value_end,
+        };
+
+        let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+        let rest = &source[end..];
+        let line_rest = &rest[..rest.find('\n').map_or(rest.len(), |i| i + 1)];
+        if source[line_start..start].trim().is_empty() && line_rest.trim().is_empty() {
+            return TextEdit::delete(line_start, end + line_rest.len());
+        }
+
+        match (i + 1 == entries.len(), i.checked_sub(1)) {
+            // `a: 1, ` up to the next entry
+            (false, _) => TextEdit::delete(start, end + rest.len() - rest.trim_start().len()),
+            // `, b: 2` after the previous entry, keeping a trailing comma
+            (true, Some(previous)) => {
+                TextEdit::delete(span(source, entries[previous].value).1, value_end)
+            }
+            (true, None) => TextEdit::delete(start, end),
+        }
+    }
+
+    /// Marks `path` as a conflict with a comment before the line of `at`
+    fn conflict(
+        &mut self,
+        path: ValuePath,
+        ours: Option<&Spanned<Expr>>,
+        theirs: Option<&Spanned<Expr>>,
+        at: Location,
+    ) {
+        let source = self.ours;
+        let line_start = source[..byte_offset(source, at)]
+            .rfind('\n')
+            .map_or(0, |i| i + 1);
+        let indent: String = source[line_start..]
+            .chars()
+            .take_while(|&c| c == ' ' || c == '\t')
+            .collect();
+        let new_line = match source.contains("\r\n") {
+            true => "\r\n",
+            false => "\n",
+        };
+
+        let mut comment = String::new();
+        let mut line = |text: &str| comment.push_str(&format!("{}// {}{}", indent, text, new_line));
+        match path.is_root() {
+            true => line("<<<<<<< ours"),
+            false => line(&format!("<<<<<<< ours `{}`", path)),
+        }
+        let ours = ours.map_or("(removed)", |ours| {
+            let (start, end) = span(source, ours);
+            &source[start..end]
+        });
+        ours.lines().for_each(&mut line);
+        line("=======");
+        theirs
+            .map_or("(removed)", |theirs| self.text(theirs))
+            .lines()
+            .for_each(&mut line);
+        line(">>>>>>> theirs");
+
+        self.edits
+            .push(Edit::Text(TextEdit::insert(line_start, comment)));
+        self.conflicts.push(path);
+    }
+
+    /// The source of a value of `theirs`
+    fn text(&self, theirs: &Spanned<Expr>) -> &str {
+        let (start, end) = span(self.theirs, theirs);
+
+        &self.theirs[start..end]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = "// Game config
+Config(
+    window: (
+        width: 800,
+        height: 600,
+    ),
+    layers: [\"ui\", \"world\"],
+    keys: {
+        \"jump\": Space,
+        \"fire\": Ctrl,
+    },
+    volume: Some(0.5),
+)
+";
+
+    fn merge(ours: &str, theirs: &str) -> Merge {
+        merge_str(BASE, ours, theirs).unwrap()
+    }
+
+    #[test]
+    fn fields() {
+        let ours = BASE
+            .replace("800", "1024")
+            .replace("        \"fire\": Ctrl,\n", "");
+        let theirs = BASE
+            .replace("height: 600", "height: 768, // px")
+            .replace("Some(0.5)", "Some(0.8)")
+            .replace("Space,\n", "Space,\n        \"crouch\": C,\n");
+
+        let merge = merge(&ours, &theirs);
+        assert_eq!(merge.conflicts, []);
+        assert_eq!(
+            merge.merged,
+            ours.replace("600", "768")
+                .replace("0.5", "0.8")
+                .replace("Space,\n", "Space,\n        \"crouch\": C,\n")
+        );
+        // Merging is symmetric, apart from where additions go
+        assert_eq!(merge_str(BASE, &theirs, &ours).unwrap().merged, {
+            theirs
+                .replace("800", "1024")
+                .replace("        \"fire\": Ctrl,\n", "")
+        });
+    }
+
+    #[test]
+    fn additions_and_removals() {
+        let base = "(a: 1, b: [1, 2], c: (x: 1))";
+        let merge = merge_str(
+            base,
+            "(a: 1, b: [1, 3], c: (x: 1, y: 2))",
+            "(b: [0, 2], c: (x: 1), d: 4)",
+        )
+        .unwrap();
+        assert_eq!(merge.conflicts, []);
+        assert_eq!(merge.merged, "(b: [0, 3], c: (x: 1, y: 2), d: 4)");
+
+        // Removing the last field without a trailing comma
+        let merge = merge_str(base, "(a: 2, b: [1, 2], c: (x: 1),)", "(a: 1, b: [1, 2])").unwrap();
+        assert_eq!(merge.merged, "(a: 2, b: [1, 2],)");
+    }
+
+    #[test]
+    fn conflicts() {
+        let ours = BASE
+            .replace("800", "1024")
+            .replace("\"ui\", \"world\"", "\"ui\"");
+        let theirs = BASE
+            .replace("800", "1280")
+            .replace("\"ui\", \"world\"", "\"hud\", \"world\", \"sky\"")
+            .replace("    volume: Some(0.5),\n", "");
+
+        let merge = merge(&ours, &theirs);
+        assert_eq!(
+            merge
+                .conflicts
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            ["window.width", "layers"]
+        );
+        assert_eq!(
+            merge.merged,
+            ours.replace(
+                "        width",
+                "        // <<<<<<< ours `window.width`
+        // 1024
+        // =======
+        // 1280
+        // >>>>>>> theirs
+        width"
+            )
+            .replace(
+                "    layers",
+                "    // <<<<<<< ours `layers`
+    // [\"ui\"]
+    // =======
+    // [\"hud\", \"world\", \"sky\"]
+    // >>>>>>> theirs
+    layers"
+            )
+            .replace("    volume: Some(0.5),\n", "")
+        );
+        assert!(ast_from_str(&merge.merged).is_ok());
+
+        // Removed by one side, changed by the other
+        let merge = merge_str("(a: 1, b: 2)", "(a: 1, b: 3)", "(a: 1)").unwrap();
+        assert_eq!(
+            merge.merged,
+            "// <<<<<<< ours `b`\n// 3\n// =======\n// (removed)\n// >>>>>>> theirs\n(a: 1, b: 3)"
+        );
+    }
+
+    #[test]
+    fn invalid_documents() {
+        let e = merge_str(BASE, BASE, "(a: ").unwrap_err();
+        assert_eq!(
+            e.context.and_then(|context| context.file_name),
+            Some("theirs".to_owned())
+        );
+    }
+}
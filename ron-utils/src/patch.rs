@@ -0,0 +1,366 @@
+//! Editing RON documents in place
+//!
+//! Edits only replace the bytes they touch, so formatting and comments of the
+//! rest of the document stay byte-identical.
+
+use std::path::Path;
+
+use ron_reboot::{
+    ast::{Expr, Ron, Spanned},
+    lex::trivia_len,
+    path::{Step, ValuePath},
+    utf8_parser::ast_from_str,
+    Error, ErrorKind, Location,
+};
+
+use crate::{
+    edit::{apply_edits, byte_offset, TextEdit},
+    read_fs_string, write_fs_string,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Edit {
+    Text(TextEdit),
+    /// Sets the value at `path` (e.g. `window.width` or `layers.0.name`, see
+    /// [`ron_reboot::path`]) to the RON expression `value`.
+    ///
+    /// Missing struct fields are appended to their struct.
+    Set {
+        path: String,
+        value: String,
+    },
+}
+
+/// Applies `edits` to `source`, which have to be free of conflicts.
+pub fn patch_str(source: &str, edits: &[Edit]) -> Result<String, Error> {
+    let ron = ast_from_str(source)?;
+    let mut text_edits: Vec<TextEdit> = vec![];
+
+    for edit in edits {
+        let new = match edit {
+            Edit::Text(edit) => vec![edit.clone()],
+            Edit::Set { path, value } => set(source, &ron, path, value)?,
+        };
+
+        for new in new {
+            let is_insert = |edit: &TextEdit| edit.start == edit.end;
+            match text_edits.iter_mut().find(|edit| edit.overlaps(&new)) {
+                None => text_edits.push(new),
+                Some(edit) if *edit == new => {}
+                // Fields appended to the same struct; if the last field had no
+                // trailing comma, the ones before the new field don't either
+                Some(edit) if is_insert(edit) && is_insert(&new) && edit.start == new.start => {
+                    if !edit.replacement.ends_with(',') && !new.replacement.starts_with(',') {
+                        edit.replacement.push(',');
+                    }
+                    edit.replacement.push_str(&new.replacement)
+                }
+                Some(_) => return Err(custom(format!("conflicting edit: {:?}", edit))),
+            }
+        }
+    }
+
+    Ok(apply_edits(source, &text_edits))
+}
+
+/// Applies `edits` to the file at `path`, returning whether its contents changed.
+///
+/// The file is only written if they did, atomically (see [`write_fs_string`]).
+pub fn patch_file(path: impl AsRef<Path>, edits: &[Edit]) -> Result<bool, Error> {
+    let path = path.as_ref();
+    let source = read_fs_string(path)?;
+    let patched =
+        patch_str(&source, edits).map_err(|e| e.context_file_name(path.display().to_string()))?;
+
+    if patched == source {
+        return Ok(false);
+    }
+    write_fs_string(path, &patched)?;
+
+    Ok(true)
+}
+
+/// Returns the source text of the value at `path` (see [`ron_reboot::path`]).
+pub fn get<'a>(source: &'a str, path: &str) -> Result<&'a str, Error> {
+    let ron = ast_from_str(source)?;
+    let expr = resolve(source, &ron.expr, &path.parse()?)?;
+
+    let (start, end) = span(source, expr);
+
+    Ok(&source[start..end])
+}
+
+fn set(source: &str, ron: &Ron, path: &str, value: &str) -> Result<Vec<TextEdit>, Error> {
+    ast_from_str(value).map_err(|e| {
+        Error {
+            kind: e.kind,
+            context: None,
+        }
+        .context_file_name(format!("value of `{}`", path))
+        .context_file_content(value.to_owned())
+    })?;
+
+    let path: ValuePath = path.parse()?;
+    let replace = |expr| {
+        let (start, end) = span(source, expr);
+        vec![TextEdit {
+            start,
+            end,
+            replacement: value.to_owned(),
+        }]
+    };
+    let (parent_path, step) = match path.split_last() {
+        Some(split) => split,
+        None => return Ok(replace(&ron.expr)),
+    };
+    let parent = resolve(source, &ron.expr, &parent_path)?;
+
+    if let Some(expr) = step.select(parent) {
+        return Ok(replace(expr));
+    }
+
+    match (parent.value.as_struct(), step) {
+        (Some(s), Step::Key(field)) if !s.fields.is_empty() && is_ident(field) => {
+            let last = s.fields.last().expect("struct has fields");
+            let last = (last.start, &last.value.value);
+            Ok(append_field(source, parent, last, field, value))
+        }
+        _ => Err(resolve(source, &ron.expr, &path).expect_err("no value at `path`")),
+    }
+}
+
+/// Inserts `field: value` after the last field of the struct (or entry of the
+/// map) `parent`, which starts at `last_start` and has the value
+/// `last_value`, on its own line unless `parent` is written on one line.
+pub(crate) fn append_field(
+    source: &str,
+    parent: &Spanned<Expr>,
+    (last_start, last_value): (Location, &Spanned<Expr>),
+    field: &str,
+    value: &str,
+) -> Vec<TextEdit> {
+    let (_, last_end) = span(source, last_value);
+    let after_last = last_end + trivia_len(&source.as_bytes()[last_end..]);
+    let has_comma = source[after_last..].starts_with(',');
+
+    // The new field gets a trailing comma if the last one had it
+    let (search_from, trailing_comma) = match has_comma {
+        true => (after_last + 1, ","),
+        false => (last_end, ""),
+    };
+
+    if parent.start.line == last_start.line {
+        let separator = match has_comma {
+            true => "",
+            false => ",",
+        };
+        return vec![TextEdit::insert(
+            search_from,
+            format!("{} {}: {}{}", separator, field, value, trailing_comma),
+        )];
+    }
+
+    let line_start = source[..byte_offset(source, last_start)]
+        .rfind('\n')
+        .map(|i| i + 1)
+        .unwrap_or(0);
+    let indent: String = source[line_start..]
+        .chars()
+        .take_while(|&c| c == ' ' || c == '\t')
+        .collect();
+    let new_line = match source.contains("\r\n") {
+        true => "\r\n",
+        false => "\n",
+    };
+
+    // Keep comments trailing the last field on its line
+    let rest = &source[search_from..];
+    let line = &rest[..rest.find('\n').unwrap_or(rest.len())];
+    let at = match line.trim().is_empty() || line.trim().starts_with("//") {
+        true => search_from + line.trim_end_matches('\r').len(),
+        false => search_from,
+    };
+    let new_field = format!(
+        "{}{}{}: {}{}",
+        new_line, indent, field, value, trailing_comma
+    );
+
+    match (has_comma, at == last_end) {
+        (false, true) => vec![TextEdit::insert(at, format!(",{}", new_field))],
+        (false, false) => vec![
+            TextEdit::insert(last_end, ","),
+            TextEdit::insert(at, new_field),
+        ],
+        (true, _) => vec![TextEdit::insert(at, new_field)],
+    }
+}
+
+/// Byte range of the expression `spanned`, without the whitespace and comments
+/// its span may include at the end
+pub(crate) fn span(source: &str, spanned: &Spanned<Expr>) -> (usize, usize) {
+    let start = byte_offset(source, spanned.start);
+    let text = &source[start..byte_offset(source, spanned.end)];
+
+    // A comment-like suffix may also be part of a string, so the expression
+    // has to parse without it
+    let len = text
+        .char_indices()
+        .map(|(i, _)| i)
+        .find(|&i| {
+            trivia_len(&text.as_bytes()[i..]) == text.len() - i && ast_from_str(&text[..i]).is_ok()
+        })
+        .unwrap_or(text.len());
+
+    (start, start + len)
+}
+
+fn resolve<'b, 'a>(
+    source: &str,
+    root: &'b Spanned<Expr<'a>>,
+    path: &ValuePath,
+) -> Result<&'b Spanned<Expr<'a>>, Error> {
+    path.resolve(root)
+        .map_err(|e| e.context_file_content(source.to_owned()))
+}
+
+/// Whether `field` can be written as a struct field without quotes
+fn is_ident(field: &str) -> bool {
+    field.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && field.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn custom(message: String) -> Error {
+    Error {
+        kind: ErrorKind::Custom(message),
+        context: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r#"// Game config
+Config(
+    window: (
+        width: 800, // px
+        height: 600,
+    ),
+    layers: ["ui", "world"],
+    keys: {
+        "jump": Space,
+    },
+    volume: Some(0.5)
+)
+"#;
+
+    fn set(path: &str, value: &str) -> Edit {
+        Edit::Set {
+            path: path.to_owned(),
+            value: value.to_owned(),
+        }
+    }
+
+    #[test]
+    fn get_values() {
+        assert_eq!(get(SOURCE, "window.width"), Ok("800"));
+        assert_eq!(get(SOURCE, "layers.1"), Ok(r#""world""#));
+        assert_eq!(get(SOURCE, "keys.jump"), Ok("Space"));
+        assert_eq!(get(SOURCE, "volume"), Ok("Some(0.5)"));
+        assert!(get(SOURCE, "window.depth").is_err());
+        assert!(get(SOURCE, "window..width").is_err());
+
+        assert_eq!(get(SOURCE, r#"keys."jump""#), Ok("Space"));
+        assert_eq!(get(r#"{"a.b": [1], 2: 3}"#, r#""a.b".0"#), Ok("1"));
+        assert_eq!(get(r#"{"a.b": [1], 2: 3}"#, "2"), Ok("3"));
+        assert_eq!(get("(a: 1) // one", ""), Ok("(a: 1)"));
+    }
+
+    #[test]
+    fn set_values() {
+        let patched = patch_str(
+            SOURCE,
+            &[
+                set("window.width", "1920"),
+                set("layers.0", r#""hud""#),
+                set("keys.jump", "Enter"),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(
+            patched,
+            SOURCE
+                .replace("800", "1920")
+                .replace(r#""ui""#, r#""hud""#)
+                .replace("Space", "Enter")
+        );
+    }
+
+    #[test]
+    fn set_quoted_keys_and_root() {
+        assert_eq!(
+            patch_str(r#"{"a.b": 1}"#, &[set(r#""a.b""#, "2")]),
+            Ok(r#"{"a.b": 2}"#.to_owned())
+        );
+        assert_eq!(
+            patch_str("// one\n1\n", &[set("", "2")]),
+            Ok("// one\n2\n".to_owned())
+        );
+        // Only fields written without quotes can be appended
+        let e = patch_str("(a: 1)", &[set(r#""b c""#, "2")]).unwrap_err();
+        assert_eq!(
+            e.kind,
+            ErrorKind::Custom(r#"no value at `"b c"`"#.to_owned())
+        );
+    }
+
+    #[test]
+    fn append_fields() {
+        let patched = patch_str(
+            SOURCE,
+            &[set("window.fullscreen", "true"), set("vsync", "false")],
+        )
+        .unwrap();
+        assert_eq!(
+            patched,
+            SOURCE
+                .replace(
+                    "height: 600,\n",
+                    "height: 600,\n        fullscreen: true,\n"
+                )
+                .replace("Some(0.5)\n", "Some(0.5),\n    vsync: false\n")
+        );
+
+        let appended = [
+            (
+                "(\n  a: 1 // one\n)",
+                "(\n  a: 1, // one\n  b: 2,\n  c: 3\n)",
+            ),
+            ("(\n  a: 1\n)", "(\n  a: 1,\n  b: 2,\n  c: 3\n)"),
+            ("(\n  a: 1,\n)", "(\n  a: 1,\n  b: 2,\n  c: 3,\n)"),
+            ("(a: 1 /* one */)", "(a: 1, b: 2, c: 3 /* one */)"),
+            ("(a: 1,)", "(a: 1, b: 2, c: 3,)"),
+        ];
+        for (source, expected) in appended {
+            let patched = patch_str(source, &[set("b", "2"), set("c", "3")]).unwrap();
+            assert_eq!(patched, expected);
+            assert!(ast_from_str(&patched).is_ok(), "{}", patched);
+        }
+    }
+
+    #[test]
+    fn invalid_edits() {
+        assert!(patch_str(SOURCE, &[set("window.width", "(")]).is_err());
+        assert!(patch_str(SOURCE, &[set("layers.2", "\"x\"")]).is_err());
+        assert!(patch_str(
+            SOURCE,
+            &[set("window.width", "1"), set("window.width", "2")]
+        )
+        .is_err());
+        assert_eq!(
+            patch_str(SOURCE, &[Edit::Text(TextEdit::insert(0, "\n"))]),
+            Ok(format!("\n{}", SOURCE))
+        );
+    }
+}
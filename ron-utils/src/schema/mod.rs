@@ -0,0 +1,309 @@
+//! Description of the expected structure of documents
+//!
+//! Schemas are stored as RON themselves, e.g.
+//!
+//! ```text
+//! Struct((
+//!     name: Some("Config"),
+//!     fields: [
+//!         (name: "width", schema: Integer, constraints: [Min(1.0)]),
+//!         (name: "title", schema: Option(String)),
+//!         (name: "min_width", schema: Integer),
+//!     ],
+//!     rules: ["width >= min_width"],
+//! ))
+//! ```
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    fmt,
+    path::Path,
+};
+
+use ron_reboot::utf8_parser::serde::{from_file, to_string_pretty, PrettyConfig};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "derive")]
+pub use ron_utils_derive::RonSchema;
+
+pub use self::rule::{Comparison, Operand, Rule};
+use crate::Error;
+
+mod rule;
+
+/// Types that can describe their RON representation
+///
+/// Implemented for the std types serde supports; structs and unit-only enums
+/// can use `#[derive(RonSchema)]` with the `derive` feature, which takes
+/// the field docs from `///` comments and constraints from attributes like
+/// `#[ron(min = 0, max = 100)]` or `#[ron(non_empty)]`.
+pub trait RonSchema {
+    fn schema() -> Schema;
+
+    /// Constraints every value of the type satisfies, e.g. the range of `u8`
+    fn constraints() -> Vec<Constraint> {
+        vec![]
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum Schema {
+    /// Anything, e.g. where the structure is unknown
+    Any,
+    Bool,
+    Integer,
+    Float,
+    String,
+    Option(Box<Schema>),
+    List(Box<Schema>),
+    Map(Box<Schema>, Box<Schema>),
+    Tuple(Vec<Schema>),
+    Struct(StructSchema),
+    /// Enum with unit variants of these names
+    Enum(Vec<String>),
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct StructSchema {
+    /// The name the struct may be written with, e.g. `Config`
+    #[serde(default)]
+    pub name: Option<String>,
+    pub fields: Vec<Field>,
+    /// Assertions relating the fields, e.g. `max_hp >= hp`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<Rule>,
+}
+
+impl StructSchema {
+    pub fn field(&self, name: &str) -> Option<&Field> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Field {
+    pub name: String,
+    pub schema: Schema,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doc: Option<String>,
+    /// Whether the field may be omitted even though it isn't an `Option`,
+    /// e.g. because of `#[serde(default)]`
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub default: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub constraints: Vec<Constraint>,
+}
+
+impl Field {
+    pub fn new(name: impl Into<String>, schema: Schema) -> Self {
+        Field {
+            name: name.into(),
+            schema,
+            doc: None,
+            default: false,
+            constraints: vec![],
+        }
+    }
+}
+
+fn is_false(b: &bool) -> bool {
+    !b
+}
+
+/// Restriction of the values a field may have
+///
+/// Constraints only apply to the values they make sense for, e.g. `Min` to
+/// numbers; values of `Option` fields are checked if they are `Some`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum Constraint {
+    /// Numbers must be at least this large
+    Min(f64),
+    /// Numbers must be at most this large
+    Max(f64),
+    /// Strings must match this regular expression (use `^...$` to match
+    /// the whole string)
+    Pattern(String),
+    /// Strings, numbers, bools and unit variants must be one of these,
+    /// e.g. `OneOf(["low", "high"])` or `OneOf(["1", "2", "4"])`
+    OneOf(Vec<String>),
+    /// Strings, lists and maps must not be empty
+    NonEmpty,
+}
+
+impl Constraint {
+    /// The name violations of the constraint are reported with, e.g. `one_of`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Constraint::Min(_) => "min",
+            Constraint::Max(_) => "max",
+            Constraint::Pattern(_) => "pattern",
+            Constraint::OneOf(_) => "one_of",
+            Constraint::NonEmpty => "non_empty",
+        }
+    }
+}
+
+impl Schema {
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        from_file(path)
+    }
+
+    /// Renders the schema as pretty-printed RON.
+    pub fn to_ron_string(&self) -> String {
+        to_string_pretty(self, PrettyConfig::new()).expect("schemas are always serializable")
+    }
+}
+
+macro_rules! impl_schema {
+    ($schema:expr => $($ty:ty),*) => {
+        $(
+            impl RonSchema for $ty {
+                fn schema() -> Schema {
+                    $schema
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_integer_schema {
+    ($($ty:ty),*) => {
+        $(
+            impl RonSchema for $ty {
+                fn schema() -> Schema {
+                    Schema::Integer
+                }
+
+                fn constraints() -> Vec<Constraint> {
+                    vec![
+                        Constraint::Min(<$ty>::MIN as f64),
+                        Constraint::Max(<$ty>::MAX as f64),
+                    ]
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_unsigned_schema {
+    ($($ty:ty),*) => {
+        $(
+            impl RonSchema for $ty {
+                fn schema() -> Schema {
+                    Schema::Integer
+                }
+
+                fn constraints() -> Vec<Constraint> {
+                    vec![Constraint::Min(0.0)]
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_tuple_schema {
+    ($($name:ident)*) => {
+        impl<$($name: RonSchema),*> RonSchema for ($($name,)*) {
+            fn schema() -> Schema {
+                Schema::Tuple(vec![$($name::schema()),*])
+            }
+        }
+    };
+}
+
+impl_schema!(Schema::Bool => bool);
+impl_schema!(Schema::Float => f32, f64);
+impl_schema!(Schema::String => str, String);
+// Larger ranges aren't exactly representable as `f64` bounds
+impl_schema!(Schema::Integer => i64, i128, isize);
+impl_integer_schema!(i8, i16, i32, u8, u16, u32);
+impl_unsigned_schema!(u64, u128, usize);
+
+impl_tuple_schema!();
+impl_tuple_schema!(A);
+impl_tuple_schema!(A B);
+impl_tuple_schema!(A B C);
+impl_tuple_schema!(A B C D);
+impl_tuple_schema!(A B C D E);
+impl_tuple_schema!(A B C D E F);
+
+impl<T: RonSchema + ?Sized> RonSchema for Box<T> {
+    fn schema() -> Schema {
+        T::schema()
+    }
+
+    fn constraints() -> Vec<Constraint> {
+        T::constraints()
+    }
+}
+
+impl<T: RonSchema> RonSchema for Option<T> {
+    fn schema() -> Schema {
+        Schema::Option(Box::new(T::schema()))
+    }
+
+    fn constraints() -> Vec<Constraint> {
+        T::constraints()
+    }
+}
+
+macro_rules! impl_list_schema {
+    ($($ty:ident),*) => {
+        $(
+            impl<T: RonSchema> RonSchema for $ty<T> {
+                fn schema() -> Schema {
+                    Schema::List(Box::new(T::schema()))
+                }
+            }
+        )*
+    };
+}
+
+impl_list_schema!(Vec, VecDeque, BTreeSet, HashSet);
+
+impl<T: RonSchema> RonSchema for [T] {
+    fn schema() -> Schema {
+        Schema::List(Box::new(T::schema()))
+    }
+}
+
+macro_rules! impl_map_schema {
+    ($($ty:ident),*) => {
+        $(
+            impl<K: RonSchema, V: RonSchema> RonSchema for $ty<K, V> {
+                fn schema() -> Schema {
+                    Schema::Map(Box::new(K::schema()), Box::new(V::schema()))
+                }
+            }
+        )*
+    };
+}
+
+impl_map_schema!(BTreeMap, HashMap);
+
+impl fmt::Display for Schema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Schema::Any => write!(f, "any"),
+            Schema::Bool => write!(f, "bool"),
+            Schema::Integer => write!(f, "integer"),
+            Schema::Float => write!(f, "float"),
+            Schema::String => write!(f, "string"),
+            Schema::Option(inner) => write!(f, "option<{}>", inner),
+            Schema::List(inner) => write!(f, "list<{}>", inner),
+            Schema::Map(key, value) => write!(f, "map<{}, {}>", key, value),
+            Schema::Tuple(elements) => {
+                write!(f, "(")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", element)?;
+                }
+                write!(f, ")")
+            }
+            Schema::Struct(s) => write!(f, "{}", s.name.as_deref().unwrap_or("struct")),
+            Schema::Enum(_) => write!(f, "enum"),
+        }
+    }
+}
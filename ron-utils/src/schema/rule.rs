@@ -0,0 +1,165 @@
+use std::{convert::TryFrom, fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+/// Assertion relating fields of a struct, e.g. `max_hp >= hp` or `end > start`
+///
+/// Both sides are a dot-separated field path relative to the struct, a number
+/// or a `"string"`. The rule only applies if all fields it names are present.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Rule {
+    pub lhs: Operand,
+    pub op: Comparison,
+    pub rhs: Operand,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Operand {
+    /// Path of a (nested) field, e.g. `["window", "width"]`
+    Field(Vec<String>),
+    Number(f64),
+    String(String),
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Comparison {
+    const ALL: [(&'static str, Comparison); 6] = [
+        // Two-char operators first so `>=` isn't split into `>` and `=...`
+        ("==", Comparison::Eq),
+        ("!=", Comparison::Ne),
+        ("<=", Comparison::Le),
+        (">=", Comparison::Ge),
+        ("<", Comparison::Lt),
+        (">", Comparison::Gt),
+    ];
+
+    pub fn holds<T: PartialOrd + ?Sized>(self, lhs: &T, rhs: &T) -> bool {
+        match self {
+            Comparison::Eq => lhs == rhs,
+            Comparison::Ne => lhs != rhs,
+            Comparison::Lt => lhs < rhs,
+            Comparison::Le => lhs <= rhs,
+            Comparison::Gt => lhs > rhs,
+            Comparison::Ge => lhs >= rhs,
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        Self::ALL
+            .iter()
+            .find(|(_, op)| *op == self)
+            .map(|(symbol, _)| *symbol)
+            .expect("all comparisons have a symbol")
+    }
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (index, symbol, op) = Comparison::ALL
+            .iter()
+            .filter_map(|&(symbol, op)| s.find(symbol).map(|index| (index, symbol, op)))
+            .min_by_key(|&(index, ..)| index)
+            .ok_or_else(|| format!("expected a comparison like `a >= b` in rule `{}`", s))?;
+
+        Ok(Rule {
+            lhs: s[..index].parse()?,
+            op,
+            rhs: s[index + symbol.len()..].parse()?,
+        })
+    }
+}
+
+impl FromStr for Operand {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let is_ident = |segment: &str| {
+            segment.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                && segment.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        };
+
+        if s.len() >= 2 && s.starts_with('"') && s.ends_with('"') {
+            Ok(Operand::String(s[1..s.len() - 1].to_owned()))
+        } else if let Ok(number) = s.parse() {
+            Ok(Operand::Number(number))
+        } else if s.split('.').all(is_ident) {
+            Ok(Operand::Field(s.split('.').map(str::to_owned).collect()))
+        } else {
+            Err(format!(
+                "expected a field, number or string in a rule, found `{}`",
+                s
+            ))
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.lhs, self.op.symbol(), self.rhs)
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Field(path) => write!(f, "{}", path.join(".")),
+            Operand::Number(number) => write!(f, "{}", number),
+            Operand::String(s) => write!(f, "\"{}\"", s),
+        }
+    }
+}
+
+// Rules are written as strings in schema files
+impl TryFrom<String> for Rule {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Rule> for String {
+    fn from(rule: Rule) -> String {
+        rule.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            "max_hp >= hp".parse(),
+            Ok(Rule {
+                lhs: Operand::Field(vec!["max_hp".to_owned()]),
+                op: Comparison::Ge,
+                rhs: Operand::Field(vec!["hp".to_owned()]),
+            })
+        );
+        assert_eq!(
+            "window.width<-1.5".parse::<Rule>().map(|rule| rule.to_string()),
+            Ok("window.width < -1.5".to_owned())
+        );
+        assert_eq!(
+            "mode != \"fast\"".parse::<Rule>().map(|rule| rule.rhs),
+            Ok(Operand::String("fast".to_owned()))
+        );
+        assert!("hp".parse::<Rule>().is_err());
+        assert!("hp > max hp".parse::<Rule>().is_err());
+    }
+}
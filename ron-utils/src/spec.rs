@@ -0,0 +1,250 @@
+//! Conformance of the parser with the RON grammar
+//!
+//! The cases follow the sections of the grammar document of the original RON
+//! implementation (`docs/grammar.md`); each is a snippet that is either valid
+//! or invalid according to the grammar.
+
+use std::fmt;
+
+use ron_reboot::utf8_parser::ast_from_str;
+
+/// A snippet and whether the grammar accepts it
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SpecCase {
+    /// Grammar section, e.g. `"Numbers"`
+    pub section: &'static str,
+    pub name: &'static str,
+    pub source: &'static str,
+    pub valid: bool,
+}
+
+const fn valid(section: &'static str, name: &'static str, source: &'static str) -> SpecCase {
+    SpecCase {
+        section,
+        name,
+        source,
+        valid: true,
+    }
+}
+
+const fn invalid(section: &'static str, name: &'static str, source: &'static str) -> SpecCase {
+    SpecCase {
+        section,
+        name,
+        source,
+        valid: false,
+    }
+}
+
+/// The built-in suite, grouped by section
+pub const CASES: &[SpecCase] = &[
+    valid("Whitespace and comments", "line comment", "// comment\n5"),
+    valid(
+        "Whitespace and comments",
+        "block comment",
+        "/* comment */ 5",
+    ),
+    valid(
+        "Whitespace and comments",
+        "nested block comment",
+        "/* outer /* inner */ outer */ 5",
+    ),
+    invalid(
+        "Whitespace and comments",
+        "unclosed block comment",
+        "/* comment 5",
+    ),
+    valid("Extensions", "enable", "#![enable(implicit_some)] 5"),
+    valid(
+        "Extensions",
+        "several extensions",
+        "#![enable(implicit_some, unwrap_newtypes)] 5",
+    ),
+    invalid("Extensions", "unknown extension", "#![enable(nope)] 5"),
+    valid("Numbers", "unsigned integer", "42"),
+    valid("Numbers", "signed integer", "-42"),
+    valid("Numbers", "explicit plus sign", "+42"),
+    valid("Numbers", "digit separators", "1_000_000"),
+    valid("Numbers", "hexadecimal", "0xFF"),
+    valid("Numbers", "octal", "0o17"),
+    valid("Numbers", "binary", "0b101"),
+    valid("Numbers", "integer suffix", "42u8"),
+    valid("Numbers", "float", "1.5"),
+    valid("Numbers", "float without fraction", "1."),
+    valid("Numbers", "float without integer part", ".5"),
+    valid("Numbers", "exponent", "1.5e-3"),
+    valid("Numbers", "integer with exponent", "1e3"),
+    valid("Numbers", "infinity", "inf"),
+    valid("Numbers", "not a number", "NaN"),
+    valid("Numbers", "float suffix", "1.5f32"),
+    invalid("Numbers", "empty exponent", "1e"),
+    valid("Strings", "string", r#""hello""#),
+    valid("Strings", "escapes", r#""a\n\t\"\\b""#),
+    valid("Strings", "unicode escape", r#""\u{1F980}""#),
+    valid("Strings", "raw string", r##"r#"say "hi""#"##),
+    invalid("Strings", "unclosed string", r#""hello"#),
+    invalid("Strings", "unknown escape", r#""\q""#),
+    valid("Characters", "char", "'a'"),
+    valid("Characters", "escaped char", r"'\''"),
+    valid("Bytes", "byte", "b'a'"),
+    valid("Bytes", "byte string", r#"b"bytes""#),
+    valid("Booleans", "true", "true"),
+    valid("Booleans", "false", "false"),
+    valid("Options", "none", "None"),
+    valid("Options", "some", "Some(5)"),
+    valid("Lists", "list", "[1, 2, 3]"),
+    valid("Lists", "empty list", "[]"),
+    valid("Lists", "trailing comma", "[1, 2,]"),
+    invalid("Lists", "missing comma", "[1 2]"),
+    valid("Maps", "map", r#"{"a": 1, "b": 2}"#),
+    valid("Maps", "empty map", "{}"),
+    valid("Maps", "complex keys", "{(1, 2): true}"),
+    invalid("Maps", "missing value", r#"{"a"}"#),
+    valid("Tuples", "tuple", "(1, \"a\", true)"),
+    valid("Tuples", "unit", "()"),
+    valid("Structs", "named struct", "Point(x: 1, y: 2)"),
+    valid("Structs", "struct without name", "(x: 1, y: 2)"),
+    valid("Structs", "tuple struct", "Point(1, 2)"),
+    valid("Structs", "unit struct", "Unit"),
+    valid("Structs", "empty named struct", "Empty()"),
+    invalid("Structs", "missing field value", "(x: )"),
+    valid("Enums", "unit variant", "Variant"),
+    valid("Enums", "tuple variant", "Variant(1, 2)"),
+    valid("Enums", "struct variant", "Variant(a: 1)"),
+    valid("Identifiers", "identifier with underscore", "(_private: 1)"),
+    valid("Identifiers", "raw identifier", "(r#type: 1)"),
+    valid(
+        "Identifiers",
+        "raw identifier with dash",
+        "(r#kebab-case: 1)",
+    ),
+    invalid("Identifiers", "leading digit", "(1x: 1)"),
+];
+
+/// The result of one [`SpecCase`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct CaseResult {
+    pub case: SpecCase,
+    /// Whether the parser agrees with the grammar
+    pub passed: bool,
+    /// The innermost cause of the parser's error, for valid snippets it rejects
+    pub error: Option<String>,
+}
+
+/// The results of the whole suite
+#[derive(Clone, Debug, PartialEq)]
+pub struct Report {
+    pub results: Vec<CaseResult>,
+}
+
+impl Report {
+    pub fn passed(&self) -> usize {
+        self.results.iter().filter(|r| r.passed).count()
+    }
+
+    /// Displays only the cases that don't conform, followed by the summary
+    pub fn failures(&self) -> Failures<'_> {
+        Failures(self)
+    }
+
+    fn write(&self, f: &mut fmt::Formatter<'_>, only_failures: bool) -> fmt::Result {
+        let mut section = None;
+        for result in self.results.iter().filter(|r| !only_failures || !r.passed) {
+            if section != Some(result.case.section) {
+                section = Some(result.case.section);
+                writeln!(f, "{}", result.case.section)?;
+            }
+
+            let status = match (result.passed, result.case.valid) {
+                (true, _) => "ok",
+                (false, true) => "unsupported",
+                (false, false) => "accepted",
+            };
+            write!(
+                f,
+                "  {:<12}{:<30}{}",
+                status, result.case.name, result.case.source
+            )?;
+            match &result.error {
+                Some(error) => writeln!(f, "  ({})", error)?,
+                None => writeln!(f)?,
+            }
+        }
+
+        writeln!(
+            f,
+            "\n{} of {} cases conform to the grammar",
+            self.passed(),
+            self.results.len()
+        )
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, false)
+    }
+}
+
+/// See [`Report::failures`]
+pub struct Failures<'a>(&'a Report);
+
+impl fmt::Display for Failures<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.write(f, true)
+    }
+}
+
+/// Runs `case` against the parser
+pub fn check_case(case: SpecCase) -> CaseResult {
+    let error = ast_from_str(case.source).err();
+
+    CaseResult {
+        case,
+        passed: error.is_none() == case.valid,
+        error: error.filter(|_| case.valid).map(|e| {
+            let message = e.kind.to_string();
+
+            message.lines().last().unwrap_or_default().trim().to_owned()
+        }),
+    }
+}
+
+/// Runs the built-in suite against the parser
+pub fn spec_check() -> Report {
+    Report {
+        results: CASES.iter().copied().map(check_case).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report() {
+        let report = spec_check();
+        assert_eq!(report.results.len(), CASES.len());
+        assert!(report.passed() > 0);
+
+        let result = |name: &str| {
+            report
+                .results
+                .iter()
+                .find(|r| r.case.name == name)
+                .unwrap()
+                .clone()
+        };
+        assert!(result("named struct").passed);
+        assert!(result("missing comma").passed);
+
+        let rendered = report.to_string();
+        assert!(rendered.starts_with("Whitespace and comments\n  ok"));
+        assert!(!report.failures().to_string().contains("  ok "));
+        assert!(rendered.ends_with(&format!(
+            "{} of {} cases conform to the grammar\n",
+            report.passed(),
+            CASES.len()
+        )));
+    }
+}
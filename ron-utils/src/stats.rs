@@ -0,0 +1,313 @@
+//! Summaries of the structure of documents, e.g. to triage why a file is
+//! unexpectedly large or slow to load
+
+use std::{cmp::Reverse, fmt};
+
+use ron_reboot::{
+    ast::{Attribute, Expr, Ron, Spanned, Struct, Tuple, Untagged},
+    path::{Step, ValuePath},
+    utf8_parser::ast_from_str,
+    Error, Location,
+};
+
+use crate::edit::byte_offset;
+
+/// How many of the largest subtrees [`Stats`] lists
+const LARGEST: usize = 5;
+
+/// The structure of a document
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stats {
+    /// What the root value is, e.g. ``struct `Config` ``
+    pub root: String,
+    /// The extensions the document enables
+    pub extensions: Vec<String>,
+    pub bytes: usize,
+    /// The number of values, including nested ones and map keys
+    pub values: usize,
+    /// How many values of each kind the document has, most common first
+    pub counts: Vec<(&'static str, usize)>,
+    /// The most values nested in each other, e.g. 2 for `[1]`
+    pub max_depth: usize,
+    /// The subtrees with the most values, largest first
+    pub largest: Vec<Subtree>,
+}
+
+/// A value and everything nested in it
+#[derive(Clone, Debug, PartialEq)]
+pub struct Subtree {
+    /// The path of the value, as `ron get` takes it (see [`ron_reboot::path`])
+    pub path: String,
+    pub values: usize,
+    pub bytes: usize,
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "root: {}", self.root)?;
+        match self.extensions.is_empty() {
+            true => writeln!(f, "extensions: none")?,
+            false => writeln!(f, "extensions: {}", self.extensions.join(", "))?,
+        }
+        writeln!(
+            f,
+            "size: {} bytes, {} values, max depth {}",
+            self.bytes, self.values, self.max_depth
+        )?;
+
+        writeln!(f, "values:")?;
+        for (kind, count) in &self.counts {
+            writeln!(f, "  {:<12}{:>8}", kind, count)?;
+        }
+
+        if !self.largest.is_empty() {
+            writeln!(f, "largest subtrees:")?;
+            for subtree in &self.largest {
+                writeln!(
+                    f,
+                    "  {:<30}{:>8} values{:>10} bytes",
+                    subtree.path, subtree.values, subtree.bytes
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Summarizes the structure of `source`
+pub fn stats_str(source: &str) -> Result<Stats, Error> {
+    Ok(stats_ron(source, &ast_from_str(source)?))
+}
+
+/// Summarizes the structure of `ron`, parsed from `source`
+pub fn stats_ron(source: &str, ron: &Ron) -> Stats {
+    let mut walk = Walk::default();
+    let values = walk.expr(&ron.expr, Some(ValuePath::root()), 1);
+
+    let mut counts = walk.counts;
+    counts.sort_by_key(|count| Reverse(count.1));
+    walk.subtrees.sort_by_key(|subtree| Reverse(subtree.1));
+
+    Stats {
+        root: describe(&ron.expr.value),
+        extensions: ron
+            .attributes
+            .iter()
+            .flat_map(|attribute| match &attribute.value {
+                Attribute::Enable(extensions) => extensions
+                    .value
+                    .iter()
+                    .map(|extension| extension.value.name().to_owned())
+                    .collect(),
+                _ => vec![],
+            })
+            .collect(),
+        bytes: source.len(),
+        values,
+        counts,
+        max_depth: walk.max_depth,
+        // Only the listed subtrees are measured, finding byte offsets is slow
+        largest: walk
+            .subtrees
+            .into_iter()
+            .take(LARGEST)
+            .map(|(path, values, (start, end))| Subtree {
+                path,
+                values,
+                bytes: byte_offset(source, end) - byte_offset(source, start),
+            })
+            .collect(),
+    }
+}
+
+#[derive(Default)]
+struct Walk {
+    counts: Vec<(&'static str, usize)>,
+    max_depth: usize,
+    /// Path, values and span of every subtree with a path except the root
+    subtrees: Vec<(String, usize, (Location, Location))>,
+}
+
+impl Walk {
+    /// Counts the values of `expr`, which has no path if it is a map key
+    fn expr(&mut self, expr: &Spanned<Expr>, path: Option<ValuePath>, depth: usize) -> usize {
+        let kind = kind(&expr.value);
+        match self.counts.iter_mut().find(|(k, _)| *k == kind) {
+            Some((_, count)) => *count += 1,
+            None => self.counts.push((kind, 1)),
+        }
+        self.max_depth = self.max_depth.max(depth);
+
+        let mut values = 1;
+        for (segment, child) in children(&expr.value) {
+            let child_path = match (&path, segment) {
+                (Some(path), Segment::Same) => Some(path.clone()),
+                (Some(path), Segment::Step(step)) => Some(path.join(step)),
+                (_, _) => None,
+            };
+            values += self.expr(child, child_path, depth + 1);
+        }
+
+        if let Some(path) = path.filter(|path| !path.is_root() && values > 1) {
+            let path = path.to_string();
+            // The value of `Some(..)` is listed as the option
+            if self.subtrees.last().is_some_and(|last| last.0 == path) {
+                self.subtrees.pop();
+            }
+            self.subtrees.push((path, values, (expr.start, expr.end)));
+        }
+
+        values
+    }
+}
+
+enum Segment {
+    /// A struct field, map value or element
+    Step(Step),
+    /// The value of `Some(..)`, which has the path of the option
+    Same,
+    /// A map key
+    Key,
+}
+
+fn children<'b, 'a>(expr: &'b Expr<'a>) -> Vec<(Segment, &'b Spanned<Expr<'a>>)> {
+    let indexed = |elements: &'b [Spanned<Expr<'a>>]| {
+        elements
+            .iter()
+            .enumerate()
+            .map(|(i, element)| (Segment::Step(Step::Index(i)), element))
+            .collect()
+    };
+    let fields = |s: &'b Struct<'a>| {
+        s.fields
+            .iter()
+            .map(|field| {
+                let field = &field.value;
+                (
+                    Segment::Step(Step::Key(field.key.value.as_str().to_owned())),
+                    &field.value,
+                )
+            })
+            .collect()
+    };
+
+    match expr {
+        Expr::Optional(Some(inner)) => vec![(Segment::Same, inner.as_ref())],
+        Expr::Tagged(t) => match &t.untagged.value {
+            Untagged::Struct(s) => fields(s),
+            Untagged::Tuple(Tuple { elements }) => indexed(elements),
+            _ => vec![],
+        },
+        Expr::Struct(s) => fields(s),
+        Expr::List(ron_reboot::ast::List { elements }) | Expr::Tuple(Tuple { elements }) => {
+            indexed(elements)
+        }
+        Expr::Map(map) => map
+            .entries
+            .iter()
+            .enumerate()
+            .flat_map(|(i, entry)| {
+                let entry = &entry.value;
+                // Not reachable with `ron get`, but still tells where it is
+                let step = Step::from_key(&entry.key.value).unwrap_or(Step::Index(i));
+
+                vec![
+                    (Segment::Key, &entry.key),
+                    (Segment::Step(step), &entry.value),
+                ]
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
+
+fn kind(expr: &Expr) -> &'static str {
+    match expr {
+        Expr::Unit => "unit",
+        Expr::Optional(_) => "option",
+        Expr::Tagged(t) => match t.untagged.value {
+            Untagged::Unit => "identifier",
+            Untagged::Struct(_) => "struct",
+            Untagged::Tuple(_) => "tuple",
+        },
+        Expr::Bool(_) => "bool",
+        Expr::Tuple(_) => "tuple",
+        Expr::List(_) => "list",
+        Expr::Map(_) => "map",
+        Expr::Struct(_) => "struct",
+        Expr::Integer(_) => "integer",
+        Expr::Str(_) | Expr::String(_) => "string",
+        Expr::Decimal(_) => "float",
+        Expr::Quantity(_) => "quantity",
+        Expr::Color(_) => "color",
+        _ => "value",
+    }
+}
+
+fn describe(expr: &Expr) -> String {
+    match expr {
+        Expr::Tagged(t) => format!("{} `{}`", kind(expr), t.ident.value.0),
+        _ => kind(expr).to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats() {
+        let source = r#"#![enable(implicit_some)]
+Level(
+    name: "cave",
+    spawn: Some((1, 2)),
+    enemies: [
+        (kind: "bat", hp: 3),
+        (kind: "rat", hp: 1),
+    ],
+    loot: {"gold": [1, 2, 3]},
+)"#;
+        let stats = stats_str(source).unwrap();
+
+        assert_eq!(stats.root, "struct `Level`");
+        assert_eq!(stats.extensions, ["implicit_some"]);
+        assert_eq!(stats.bytes, source.len());
+        assert_eq!(stats.values, 19);
+        assert_eq!(stats.max_depth, 4);
+        assert_eq!(
+            stats.counts,
+            [
+                ("integer", 7),
+                ("string", 4),
+                ("struct", 3),
+                ("list", 2),
+                ("option", 1),
+                ("tuple", 1),
+                ("map", 1),
+            ]
+        );
+        let largest = stats
+            .largest
+            .iter()
+            .map(|subtree| (subtree.path.as_str(), subtree.values))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            largest,
+            [
+                ("enemies", 7),
+                ("loot", 6),
+                ("spawn", 4),
+                ("loot.gold", 4),
+                ("enemies.0", 3)
+            ]
+        );
+        assert_eq!(stats.largest[2].bytes, "Some((1, 2))".len());
+
+        assert_eq!(
+            stats_str("5").unwrap().to_string(),
+            "root: integer\nextensions: none\nsize: 1 bytes, 1 values, max depth 1\n\
+             values:\n  integer            1\n"
+        );
+    }
+}
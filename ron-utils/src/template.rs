@@ -0,0 +1,270 @@
+//! Commented skeletons of documents, generated from a [`Schema`] to start
+//! writing a document from
+//!
+//! Every struct field is written with its `///` doc and a comment with its
+//! type and constraints. Values are placeholders of the least effort that
+//! satisfy the constraints: `0` raised to `Min`, the first of `OneOf`, `None`
+//! for options and empty lists and maps unless they must not be empty. The
+//! skeleton is valid unless a `Pattern` or rule rejects the placeholders.
+
+use ron_reboot::utf8_parser::serde::to_string;
+
+use crate::schema::{Constraint, Field, RonSchema, Schema, StructSchema};
+
+/// The skeleton of documents of `T`
+pub fn template<T: RonSchema + ?Sized>() -> String {
+    template_schema(&T::schema())
+}
+
+/// The skeleton of documents of `schema`
+pub fn template_schema(schema: &Schema) -> String {
+    let mut template = Template::default();
+    template.value(schema, &[]);
+    template.out.push('\n');
+
+    template.out
+}
+
+#[derive(Default)]
+struct Template {
+    out: String,
+    indent: usize,
+}
+
+impl Template {
+    fn new_line(&mut self) {
+        self.out.push('\n');
+        for _ in 0..self.indent {
+            self.out.push_str("    ");
+        }
+    }
+
+    fn value(&mut self, schema: &Schema, constraints: &[Constraint]) {
+        let non_empty = constraints.contains(&Constraint::NonEmpty);
+        let one_of = constraints.iter().find_map(|constraint| match constraint {
+            Constraint::OneOf(values) => values.first(),
+            _ => None,
+        });
+
+        match schema {
+            Schema::Any => self.out.push_str("()"),
+            Schema::Bool => self.out.push_str(one_of.map_or("false", String::as_str)),
+            Schema::Integer | Schema::Float | Schema::Enum(_) if one_of.is_some() => {
+                self.out.push_str(one_of.map_or("", String::as_str))
+            }
+            Schema::Integer => {
+                let (min, max) = bounds(constraints);
+                let n = 0f64.max(min.ceil()).min(max.floor());
+                self.out.push_str(&(n as i64).to_string());
+            }
+            Schema::Float => {
+                let (min, max) = bounds(constraints);
+                self.out.push_str(&format!("{:?}", 0f64.max(min).min(max)));
+            }
+            Schema::String => {
+                let s = match (one_of, non_empty) {
+                    (Some(s), _) => s.as_str(),
+                    (None, true) => "...",
+                    (None, false) => "",
+                };
+                self.out
+                    .push_str(&to_string(s).expect("strings are always serializable"));
+            }
+            Schema::Option(_) => self.out.push_str("None"),
+            Schema::List(element) if non_empty => {
+                self.out.push('[');
+                self.value(element, &[]);
+                self.out.push(']');
+            }
+            Schema::List(_) => self.out.push_str("[]"),
+            Schema::Map(key, value) if non_empty => {
+                self.out.push('{');
+                self.value(key, &[]);
+                self.out.push_str(": ");
+                self.value(value, &[]);
+                self.out.push('}');
+            }
+            Schema::Map(..) => self.out.push_str("{}"),
+            Schema::Tuple(elements) => {
+                self.out.push('(');
+                for (i, element) in elements.iter().enumerate() {
+                    if i != 0 {
+                        self.out.push_str(", ");
+                    }
+                    self.value(element, &[]);
+                }
+                self.out.push(')');
+            }
+            Schema::Struct(s) => self.structure(s),
+            Schema::Enum(variants) => match variants.first() {
+                Some(variant) => self.out.push_str(variant),
+                None => self.out.push_str("()"),
+            },
+        }
+    }
+
+    fn structure(&mut self, s: &StructSchema) {
+        if let Some(name) = &s.name {
+            self.out.push_str(name);
+        }
+        self.out.push('(');
+        self.indent += 1;
+        for rule in &s.rules {
+            self.new_line();
+            self.out.push_str(&format!("// rule: {}", rule));
+        }
+        for field in &s.fields {
+            self.field(field);
+        }
+        self.indent -= 1;
+        if !s.fields.is_empty() || !s.rules.is_empty() {
+            self.new_line();
+        }
+        self.out.push(')');
+    }
+
+    fn field(&mut self, field: &Field) {
+        for line in field.doc.iter().flat_map(|doc| doc.lines()) {
+            self.new_line();
+            self.out.push_str(format!("/// {}", line).trim_end());
+        }
+        self.new_line();
+        self.out.push_str(&format!("// {}", describe(field)));
+        self.new_line();
+        self.out.push_str(&field.name);
+        self.out.push_str(": ");
+        self.value(&field.schema, &field.constraints);
+        self.out.push(',');
+    }
+}
+
+/// The `Min` and `Max` of `constraints`
+fn bounds(constraints: &[Constraint]) -> (f64, f64) {
+    constraints.iter().fold(
+        (f64::NEG_INFINITY, f64::INFINITY),
+        |(min, max), constraint| match constraint {
+            Constraint::Min(n) => (min.max(*n), max),
+            Constraint::Max(n) => (min, max.min(*n)),
+            _ => (min, max),
+        },
+    )
+}
+
+/// The type and constraints of `field`, e.g. `integer, min 1, optional`
+fn describe(field: &Field) -> String {
+    let mut parts = vec![field.schema.to_string()];
+    if let Schema::Enum(variants) = &field.schema {
+        parts.push(format!("one of {}", variants.join(", ")));
+    }
+    parts.extend(field.constraints.iter().map(|constraint| match constraint {
+        Constraint::Min(n) => format!("min {}", n),
+        Constraint::Max(n) => format!("max {}", n),
+        Constraint::Pattern(pattern) => format!("matching `{}`", pattern),
+        Constraint::OneOf(values) => format!("one of {}", values.join(", ")),
+        Constraint::NonEmpty => "not empty".to_owned(),
+    }));
+    if field.default || matches!(field.schema, Schema::Option(_)) {
+        parts.push("optional".to_owned());
+    }
+
+    parts.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validate::validate_schema_str;
+
+    fn schema() -> Schema {
+        let mut width = Field::new("width", Schema::Integer);
+        width.doc = Some("Width of the window\nin pixels".to_owned());
+        width.constraints = vec![Constraint::Min(320.0), Constraint::Max(7680.0)];
+        let mut mode = Field::new(
+            "mode",
+            Schema::Enum(vec!["Windowed".to_owned(), "Fullscreen".to_owned()]),
+        );
+        mode.default = true;
+        let mut scale = Field::new("scale", Schema::Float);
+        scale.constraints = vec![Constraint::Min(0.5)];
+        let mut layers = Field::new("layers", Schema::List(Box::new(Schema::String)));
+        layers.constraints = vec![Constraint::NonEmpty];
+        let mut level = Field::new("log", Schema::String);
+        level.constraints = vec![Constraint::OneOf(vec![
+            "warn".to_owned(),
+            "info".to_owned(),
+        ])];
+        let position = Field::new(
+            "position",
+            Schema::Tuple(vec![Schema::Integer, Schema::Integer]),
+        );
+        let title = Field::new("title", Schema::Option(Box::new(Schema::String)));
+        let keys = Field::new(
+            "keys",
+            Schema::Map(Box::new(Schema::String), Box::new(Schema::Bool)),
+        );
+        let font = Field::new(
+            "font",
+            Schema::Struct(StructSchema {
+                name: Some("Font".to_owned()),
+                fields: vec![Field::new("size", Schema::Float)],
+                rules: vec!["size >= 0".parse().unwrap()],
+            }),
+        );
+
+        Schema::Struct(StructSchema {
+            name: Some("Config".to_owned()),
+            fields: vec![
+                width, mode, scale, layers, level, position, title, keys, font,
+            ],
+            rules: vec![],
+        })
+    }
+
+    #[test]
+    fn commented_skeleton() {
+        let template = template_schema(&schema());
+
+        assert_eq!(
+            template,
+            r#"Config(
+    /// Width of the window
+    /// in pixels
+    // integer, min 320, max 7680
+    width: 320,
+    // enum, one of Windowed, Fullscreen, optional
+    mode: Windowed,
+    // float, min 0.5
+    scale: 0.5,
+    // list<string>, not empty
+    layers: [""],
+    // string, one of warn, info
+    log: "warn",
+    // (integer, integer)
+    position: (0, 0),
+    // option<string>, optional
+    title: None,
+    // map<string, bool>
+    keys: {},
+    // Font
+    font: Font(
+        // rule: size >= 0
+        // float
+        size: 0.0,
+    ),
+)
+"#
+        );
+        assert_eq!(validate_schema_str(&schema(), &template), Ok(vec![]));
+    }
+
+    #[test]
+    fn types() {
+        assert_eq!(template::<Vec<(u8, bool)>>(), "[]\n");
+        assert_eq!(template::<Option<u8>>(), "None\n");
+        assert_eq!(template::<i8>(), "0\n");
+        assert_eq!(
+            template_schema(&Schema::Struct(StructSchema::default())),
+            "()\n"
+        );
+    }
+}
@@ -0,0 +1,605 @@
+//! Checks documents against a [`Schema`] without deserializing them
+
+use regex::Regex;
+use std::fmt;
+
+use ron_reboot::{
+    ast::{
+        Attribute, Expr, Extension, Integer, Ron, Sign, Spanned, Struct, Tagged, Tuple, Untagged,
+    },
+    utf8_parser::{ast_from_str, serde::encrypted::encrypted_payload},
+    Error, ErrorKind, Location,
+};
+
+use crate::schema::{Constraint, Field, Operand, RonSchema, Schema, StructSchema};
+
+/// A value that doesn't match the schema
+#[derive(Clone, Debug, PartialEq)]
+pub struct Violation {
+    pub message: String,
+    pub start: Location,
+    pub end: Location,
+}
+
+impl Violation {
+    /// Converts the violation into an `Error` so it can be rendered with `print_error`.
+    pub fn to_error(&self, source: &str) -> Error {
+        Error {
+            kind: ErrorKind::Custom(self.message.clone()),
+            context: None,
+        }
+        .context_loc(self.start, self.end)
+        .context_file_content(source.to_owned())
+    }
+}
+
+/// Checks `ron` against the schema of `T`.
+pub fn validate<T: RonSchema + ?Sized>(ron: &Ron) -> Vec<Violation> {
+    validate_schema(&T::schema(), ron)
+}
+
+/// Checks `ron` against `schema`, returning all values that don't match it.
+pub fn validate_schema(schema: &Schema, ron: &Ron) -> Vec<Violation> {
+    let mut validator = Validator {
+        implicit_some: implicit_some(ron),
+        violations: vec![],
+    };
+    validator.check(schema, &ron.expr);
+
+    validator.violations
+}
+
+/// Whether `ron` enables `implicit_some`, so that options may be written
+/// without `Some(..)`
+pub(crate) fn implicit_some(ron: &Ron) -> bool {
+    ron.attributes
+        .iter()
+        .any(|attribute| match &attribute.value {
+            Attribute::Enable(extensions) => extensions
+                .value
+                .iter()
+                .any(|extension| extension.value == Extension::ImplicitSome),
+            _ => false,
+        })
+}
+
+/// [`validate_schema`] for a document that still needs to be parsed
+pub fn validate_schema_str(schema: &Schema, source: &str) -> Result<Vec<Violation>, Error> {
+    Ok(validate_schema(schema, &ast_from_str(source)?))
+}
+
+struct Validator {
+    implicit_some: bool,
+    violations: Vec<Violation>,
+}
+
+impl Validator {
+    fn report(&mut self, expr: &Spanned<Expr>, message: String) {
+        self.violations.push(Violation {
+            message,
+            start: expr.start,
+            end: expr.end,
+        });
+    }
+
+    fn mismatch(&mut self, schema: &Schema, expr: &Spanned<Expr>) {
+        self.report(
+            expr,
+            format!("expected {}, found {}", schema, describe(&expr.value)),
+        );
+    }
+
+    fn check(&mut self, schema: &Schema, expr: &Spanned<Expr>) {
+        match (schema, &expr.value) {
+            (Schema::Any, _)
+            | (Schema::Bool, Expr::Bool(_))
+            | (Schema::Integer, Expr::Integer(_))
+            | (Schema::Float, Expr::Integer(_) | Expr::Decimal(_))
+            | (Schema::String, Expr::Str(_) | Expr::String(_) | Expr::Quantity(_))
+            | (Schema::Option(_), Expr::Optional(None)) => {}
+            // Decrypted into strings when loading, their constraints can't be
+            // checked
+            (Schema::String, value) if encrypted_payload(value).is_some() => {}
+            (Schema::Option(inner), Expr::Optional(Some(value))) => self.check(inner, value),
+            (Schema::Option(inner), _) if self.implicit_some => self.check(inner, expr),
+            (Schema::List(element), Expr::List(l)) => {
+                for e in &l.elements {
+                    self.check(element, e);
+                }
+            }
+            (Schema::Map(key, value), Expr::Map(m)) => {
+                for entry in &m.entries {
+                    self.check(key, &entry.value.key);
+                    self.check(value, &entry.value.value);
+                }
+            }
+            (Schema::Tuple(elements), Expr::Unit) if elements.is_empty() => {}
+            // Colors are deserialized as `(r, g, b, a)`
+            (Schema::Tuple(elements), Expr::Color(_))
+                if elements.len() == 4
+                    && elements
+                        .iter()
+                        .all(|e| matches!(e, Schema::Any | Schema::Integer | Schema::Float)) => {}
+            (Schema::Tuple(elements), Expr::Tuple(t)) => self.check_tuple(elements, expr, t),
+            (Schema::Struct(s), Expr::Struct(fields)) => self.check_struct(s, expr, fields),
+            (_, Expr::Tagged(t)) => self.check_tagged(schema, expr, t),
+            _ => self.mismatch(schema, expr),
+        }
+    }
+
+    fn check_tuple(&mut self, elements: &[Schema], expr: &Spanned<Expr>, t: &Tuple) {
+        if t.elements.len() != elements.len() {
+            self.report(
+                expr,
+                format!(
+                    "expected {} elements, found {}",
+                    elements.len(),
+                    t.elements.len()
+                ),
+            );
+        }
+        for (schema, e) in elements.iter().zip(&t.elements) {
+            self.check(schema, e);
+        }
+    }
+
+    fn check_tagged(&mut self, schema: &Schema, expr: &Spanned<Expr>, t: &Tagged) {
+        let ident = &t.ident;
+        match (schema, &t.untagged.value) {
+            (Schema::Tuple(elements), Untagged::Tuple(tuple)) => {
+                self.check_tuple(elements, expr, tuple)
+            }
+            (Schema::Struct(s), Untagged::Struct(fields)) => {
+                match &s.name {
+                    Some(name) if name != ident.value.as_str() => self.violations.push(Violation {
+                        message: format!("expected struct `{}`, found `{}`", name, ident.value.0),
+                        start: ident.start,
+                        end: ident.end,
+                    }),
+                    _ => {}
+                }
+                self.check_struct(s, expr, fields);
+            }
+            (Schema::Enum(variants), Untagged::Unit) => {
+                if !variants
+                    .iter()
+                    .any(|variant| variant == ident.value.as_str())
+                {
+                    self.report(
+                        expr,
+                        format!(
+                            "unknown variant `{}`, expected {}",
+                            ident.value.0,
+                            one_of(variants.iter().map(String::as_str))
+                        ),
+                    );
+                }
+            }
+            _ => self.mismatch(schema, expr),
+        }
+    }
+
+    fn check_struct(&mut self, schema: &StructSchema, expr: &Spanned<Expr>, s: &Struct) {
+        for field in &s.fields {
+            let key = &field.value.key;
+            match schema.field(key.value.as_str()) {
+                Some(field_schema) => {
+                    self.check(&field_schema.schema, &field.value.value);
+                    self.check_constraints(field_schema, &field.value.value);
+                }
+                None => self.violations.push(Violation {
+                    message: format!(
+                        "unknown field `{}`, expected {}",
+                        key.value.0,
+                        one_of(schema.fields.iter().map(|f| f.name.as_str()))
+                    ),
+                    start: key.start,
+                    end: key.end,
+                }),
+            }
+        }
+
+        self.check_rules(schema, expr, s);
+
+        for field in &schema.fields {
+            let optional = field.default || matches!(field.schema, Schema::Option(_) | Schema::Any);
+            if !optional && !s.fields.iter().any(|f| f.value.key.value.0 == field.name) {
+                self.report(expr, format!("missing field `{}`", field.name));
+            }
+        }
+    }
+
+    fn check_rules(&mut self, schema: &StructSchema, expr: &Spanned<Expr>, s: &Struct) {
+        enum Scalar<'b> {
+            Number(f64),
+            Text(&'b str),
+        }
+
+        impl fmt::Display for Scalar<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                match self {
+                    Scalar::Number(n) => write!(f, "{}", n),
+                    Scalar::Text(text) => write!(f, "\"{}\"", text),
+                }
+            }
+        }
+
+        for rule in &schema.rules {
+            // The value of each side, and where it was written if it's a field
+            let operands = [&rule.lhs, &rule.rhs]
+                .iter()
+                .map(|operand| match operand {
+                    Operand::Field(path) => {
+                        let value = resolve(s, path)?;
+                        let scalar = match as_number(&value.value) {
+                            Some(n) => Scalar::Number(n),
+                            None => Scalar::Text(as_text(&value.value)?),
+                        };
+
+                        Some((scalar, Some((path.join("."), value))))
+                    }
+                    Operand::Number(n) => Some((Scalar::Number(*n), None)),
+                    Operand::String(text) => Some((Scalar::Text(text), None)),
+                })
+                .collect::<Option<Vec<_>>>();
+            // Rules don't apply to missing fields and mismatched types are
+            // reported by the type checks
+            let holds = match operands.as_deref() {
+                Some([(Scalar::Number(lhs), _), (Scalar::Number(rhs), _)]) => {
+                    rule.op.holds(lhs, rhs)
+                }
+                Some([(Scalar::Text(lhs), _), (Scalar::Text(rhs), _)]) => rule.op.holds(lhs, rhs),
+                _ => continue,
+            };
+            if holds {
+                continue;
+            }
+
+            let operands = operands.unwrap_or_default();
+            let fields = operands
+                .iter()
+                .filter_map(|(scalar, field)| {
+                    field
+                        .as_ref()
+                        .map(|(path, _)| format!("`{}` is {}", path, scalar))
+                })
+                .collect::<Vec<_>>();
+            let value = operands
+                .iter()
+                .find_map(|(_, field)| field.as_ref().map(|(_, value)| *value))
+                .unwrap_or(expr);
+
+            let message = match fields.is_empty() {
+                true => format!("`{}` doesn't hold [rule]", rule),
+                false => format!("`{}` doesn't hold, {} [rule]", rule, fields.join(" and ")),
+            };
+            self.report(value, message);
+        }
+    }
+
+    fn check_constraints(&mut self, field: &Field, expr: &Spanned<Expr>) {
+        let value = match &expr.value {
+            Expr::Optional(Some(value)) => value,
+            _ => expr,
+        };
+        let number = as_number(&value.value);
+        let text = as_text(&value.value);
+
+        for constraint in &field.constraints {
+            let problem = match (constraint, number, text) {
+                (Constraint::Min(min), Some(n), _) if n < *min => {
+                    format!("must be at least {}, found {}", min, n)
+                }
+                (Constraint::Max(max), Some(n), _) if n > *max => {
+                    format!("must be at most {}, found {}", max, n)
+                }
+                (Constraint::Pattern(pattern), _, Some(text)) => match Regex::new(pattern) {
+                    Ok(regex) if regex.is_match(text) => continue,
+                    Ok(_) => format!("must match `{}`", pattern),
+                    Err(e) => format!("has an invalid pattern: {}", e),
+                },
+                (Constraint::OneOf(options), ..) => {
+                    let found = match (&value.value, number, text) {
+                        (_, Some(n), _) => n.to_string(),
+                        (_, _, Some(text)) => text.to_owned(),
+                        (Expr::Bool(b), ..) => b.to_string(),
+                        (expr, ..) => match expr.as_unit_ident() {
+                            Some(ident) => ident.value.as_str().to_owned(),
+                            None => continue,
+                        },
+                    };
+                    let allowed = |option: &String| match number {
+                        Some(n) => option.parse() == Ok(n),
+                        None => *option == found,
+                    };
+                    if options.iter().any(allowed) {
+                        continue;
+                    }
+
+                    format!(
+                        "must be {}, found `{}`",
+                        one_of(options.iter().map(String::as_str)),
+                        found
+                    )
+                }
+                (Constraint::NonEmpty, ..) => {
+                    let empty = match &value.value {
+                        Expr::Str(s) => s.is_empty(),
+                        Expr::String(s) => s.is_empty(),
+                        Expr::List(l) => l.elements.is_empty(),
+                        Expr::Map(m) => m.entries.is_empty(),
+                        _ => false,
+                    };
+                    if !empty {
+                        continue;
+                    }
+
+                    "must not be empty".to_owned()
+                }
+                _ => continue,
+            };
+
+            self.report(
+                value,
+                format!("`{}` {} [{}]", field.name, problem, constraint.name()),
+            );
+        }
+    }
+}
+
+fn as_number(expr: &Expr) -> Option<f64> {
+    match expr {
+        Expr::Integer(Integer::Unsigned(u)) => Some(u.number as f64),
+        Expr::Integer(Integer::Signed(s)) => Some(match s.sign {
+            Sign::Positive => s.number as f64,
+            Sign::Negative => -(s.number as f64),
+        }),
+        Expr::Decimal(d) => Some(f64::from((**d).clone())),
+        _ => None,
+    }
+}
+
+fn as_text<'b>(expr: &'b Expr) -> Option<&'b str> {
+    match expr {
+        Expr::Str(s) => Some(s),
+        Expr::String(s) => Some(s.as_str()),
+        _ => None,
+    }
+}
+
+/// The value of a field path of `s`, looking through `Some`s and struct names
+fn resolve<'b, 'a>(s: &'b Struct<'a>, path: &[String]) -> Option<&'b Spanned<Expr<'a>>> {
+    let (first, rest) = path.split_first()?;
+    let mut value = &s
+        .fields
+        .iter()
+        .find(|f| f.value.key.value.as_str() == first)?
+        .value
+        .value;
+    if let Expr::Optional(Some(inner)) = &value.value {
+        value = inner;
+    }
+
+    match rest.is_empty() {
+        true => Some(value),
+        false => resolve(value.value.as_struct()?, rest),
+    }
+}
+
+fn describe(expr: &Expr) -> &'static str {
+    match expr {
+        Expr::Unit => "unit",
+        Expr::Optional(_) => "option",
+        Expr::Tagged(t) => match t.untagged.value {
+            Untagged::Unit => "identifier",
+            Untagged::Struct(_) => "struct",
+            Untagged::Tuple(_) => "tuple",
+        },
+        Expr::Bool(_) => "bool",
+        Expr::Tuple(_) => "tuple",
+        Expr::List(_) => "list",
+        Expr::Map(_) => "map",
+        Expr::Struct(_) => "struct",
+        Expr::Integer(_) => "integer",
+        Expr::Str(_) | Expr::String(_) => "string",
+        Expr::Decimal(_) => "float",
+        Expr::Quantity(q) => q.unit.kind().name(),
+        Expr::Color(_) => "color",
+        _ => "value",
+    }
+}
+
+fn one_of<'a>(names: impl Iterator<Item = &'a str>) -> String {
+    let names = names.map(|name| format!("`{}`", name)).collect::<Vec<_>>();
+
+    match names.len() {
+        0 => "none".to_owned(),
+        1 => names[0].clone(),
+        _ => format!("one of {}", names.join(", ")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(schema: &Schema, source: &str) -> Vec<String> {
+        validate_schema_str(schema, source)
+            .unwrap()
+            .into_iter()
+            .map(|v| v.message)
+            .collect()
+    }
+
+    #[test]
+    fn structs() {
+        let mut hp = Field::new("hp", Schema::Integer);
+        hp.constraints = vec![Constraint::Min(1.0)];
+        let mut speed = Field::new("speed", Schema::Float);
+        speed.default = true;
+        let schema = Schema::Struct(StructSchema {
+            name: Some("Unit".to_owned()),
+            fields: vec![
+                Field::new("name", Schema::String),
+                hp,
+                speed,
+                Field::new(
+                    "kind",
+                    Schema::Enum(vec!["Melee".to_owned(), "Ranged".to_owned()]),
+                ),
+                Field::new("boss", Schema::Option(Box::new(Schema::Bool))),
+            ],
+            rules: vec![],
+        });
+
+        assert!(messages(&schema, "Unit(name: \"orc\", hp: 10, kind: Melee)").is_empty());
+        assert!(messages(
+            &schema,
+            "(name: \"elf\", hp: 7, speed: 2, kind: Ranged, boss: Some(true))"
+        )
+        .is_empty());
+        assert_eq!(
+            messages(&schema, "Units(name: 1, hp: 0, kind: Mage, level: 3)"),
+            [
+                "expected struct `Unit`, found `Units`",
+                "expected string, found integer",
+                "`hp` must be at least 1, found 0 [min]",
+                "unknown variant `Mage`, expected one of `Melee`, `Ranged`",
+                "unknown field `level`, expected one of `name`, `hp`, `speed`, `kind`, `boss`",
+            ]
+        );
+        assert_eq!(
+            messages(&schema, "(hp: 1, kind: Melee, boss: true)"),
+            ["expected option<bool>, found bool", "missing field `name`"]
+        );
+        assert!(messages(
+            &schema,
+            "#![enable(implicit_some)]\n(name: \"\", hp: 1, kind: Melee, boss: true)"
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn constraints() {
+        let mut version = Field::new("version", Schema::String);
+        version.constraints = vec![Constraint::Pattern(r"^v\d+$".to_owned())];
+        let mut level = Field::new("level", Schema::Option(Box::new(Schema::Integer)));
+        level.constraints = vec![Constraint::OneOf(vec!["1".to_owned(), "2".to_owned()])];
+        let mut mode = Field::new(
+            "mode",
+            Schema::Enum(vec!["Fast".to_owned(), "Slow".to_owned()]),
+        );
+        mode.constraints = vec![Constraint::OneOf(vec!["Fast".to_owned()])];
+        let mut tags = Field::new("tags", Schema::List(Box::new(Schema::String)));
+        tags.constraints = vec![Constraint::NonEmpty];
+        let schema = Schema::Struct(StructSchema {
+            name: None,
+            fields: vec![version, level, mode, tags],
+            rules: vec![],
+        });
+
+        assert!(messages(
+            &schema,
+            "(version: \"v2\", level: Some(2), mode: Fast, tags: [\"a\"])"
+        )
+        .is_empty());
+        assert_eq!(
+            messages(
+                &schema,
+                "(version: \"2\", level: Some(3), mode: Slow, tags: [])"
+            ),
+            [
+                "`version` must match `^v\\d+$` [pattern]",
+                "`level` must be one of `1`, `2`, found `3` [one_of]",
+                "`mode` must be `Fast`, found `Slow` [one_of]",
+                "`tags` must not be empty [non_empty]",
+            ]
+        );
+        // Encrypted strings are opaque until they are loaded
+        assert!(messages(
+            &schema,
+            "(version: Encrypted(\"djI=\"), level: None, mode: Fast, tags: [\"a\"])"
+        )
+        .is_empty());
+
+        let violations = validate_schema_str(
+            &schema,
+            "(version: \"v1\", level: Some(3), mode: Fast, tags: [\"\"])",
+        )
+        .unwrap();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(
+            (violations[0].start.column, violations[0].end.column),
+            (29, 30)
+        );
+    }
+
+    #[test]
+    fn rules() {
+        let schema = Schema::Struct(StructSchema {
+            name: None,
+            fields: vec![
+                Field::new("hp", Schema::Integer),
+                Field::new("max_hp", Schema::Option(Box::new(Schema::Integer))),
+                Field::new(
+                    "range",
+                    Schema::Struct(StructSchema {
+                        name: Some("Range".to_owned()),
+                        fields: vec![
+                            Field::new("start", Schema::Float),
+                            Field::new("end", Schema::Float),
+                        ],
+                        rules: vec!["end > start".parse().unwrap()],
+                    }),
+                ),
+                Field::new("mode", Schema::String),
+            ],
+            rules: vec![
+                "max_hp >= hp".parse().unwrap(),
+                "range.start >= 0".parse().unwrap(),
+                "mode != \"off\"".parse().unwrap(),
+            ],
+        });
+
+        assert!(messages(
+            &schema,
+            "(hp: 5, max_hp: Some(5), range: Range(start: 0, end: 1.5), mode: \"on\")"
+        )
+        .is_empty());
+        assert!(messages(&schema, "(hp: 5, range: (start: 1, end: 2), mode: \"on\")").is_empty());
+        assert_eq!(
+            messages(
+                &schema,
+                "(hp: 5, max_hp: Some(4), range: (start: -1, end: -2), mode: \"off\")"
+            ),
+            [
+                "`end > start` doesn't hold, `end` is -2 and `start` is -1 [rule]",
+                "`max_hp >= hp` doesn't hold, `max_hp` is 4 and `hp` is 5 [rule]",
+                "`range.start >= 0` doesn't hold, `range.start` is -1 [rule]",
+                "`mode != \"off\"` doesn't hold, `mode` is \"off\" [rule]",
+            ]
+        );
+
+        let violations = validate_schema_str(
+            &schema,
+            "(hp: 5, max_hp: Some(4), range: (start: 0, end: 1), mode: \"\")",
+        )
+        .unwrap();
+        assert_eq!(
+            (violations[0].start.column, violations[0].end.column),
+            (22, 23)
+        );
+    }
+
+    #[test]
+    fn span() {
+        let violations = validate::<Vec<(u8, bool)>>(&ast_from_str("[(1, true), (2, 3)]").unwrap());
+
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].message, "expected bool, found integer");
+        assert_eq!(
+            (violations[0].start.column, violations[0].end.column),
+            (17, 18)
+        );
+    }
+}
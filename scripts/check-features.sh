@@ -0,0 +1,16 @@
+#!/bin/sh
+
+# Checks that features combine, see "Cargo features" in the README
+
+set -e
+
+if ! cargo install --list | grep -q "cargo-hack"; then
+  echo "Installing cargo-hack because it was not found..."
+  cargo install cargo-hack
+fi
+
+# `test` only bundles other features for this crate's tests
+cargo hack check --workspace --all-targets --feature-powerset --depth 2 --exclude-features test
+cargo hack test --workspace --each-feature --exclude-features test,integers_only
+# Most tests parse floats, which `integers_only` rejects
+cargo test --features integers_only deny_floats
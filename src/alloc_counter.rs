@@ -0,0 +1,116 @@
+//! Counting allocations, e.g. to check that a change doesn't make parsing
+//! allocate more
+//!
+//! The counts come from [`CountingAllocator`], which has to be the global
+//! allocator of the test or benchmark binary:
+//!
+//! ```
+//! use ron_reboot::{
+//!     alloc_counter::{count_allocations, CountingAllocator},
+//!     utf8_parser::ast_from_str,
+//! };
+//!
+//! #[global_allocator]
+//! static ALLOCATOR: CountingAllocator = CountingAllocator;
+//!
+//! let (ron, stats) = count_allocations(|| ast_from_str("[1, 2, 3]"));
+//! assert!(ron.is_ok());
+//! assert!(stats.allocations > 0);
+//! ```
+//!
+//! Allocations are counted per thread, so tests running in parallel don't
+//! count each other's.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+/// The system allocator, counting the allocations of each thread for
+/// [`count_allocations`]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CountingAllocator;
+
+/// How much a thread allocated, see [`count_allocations`]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct AllocStats {
+    /// New allocations, not counting reallocations
+    pub allocations: u64,
+    /// Allocations that were grown or shrunk, e.g. by a growing `Vec`
+    pub reallocations: u64,
+    /// Bytes requested by allocations and by growing reallocations
+    pub bytes: u64,
+}
+
+thread_local! {
+    static STATS: Cell<AllocStats> = const {
+        Cell::new(AllocStats {
+            allocations: 0,
+            reallocations: 0,
+            bytes: 0,
+        })
+    };
+}
+
+/// Adds to the counts of the current thread, unless it is being torn down
+fn record(allocations: u64, reallocations: u64, bytes: usize) {
+    let _ = STATS.try_with(|stats| {
+        let old = stats.get();
+        stats.set(AllocStats {
+            allocations: old.allocations + allocations,
+            reallocations: old.reallocations + reallocations,
+            bytes: old.bytes + bytes as u64,
+        });
+    });
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        record(1, 0, layout.size());
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        record(1, 0, layout.size());
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        record(0, 1, new_size.saturating_sub(layout.size()));
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+/// Runs `f`, returning its result and how much it allocated on the current
+/// thread
+///
+/// The counts are all zero unless [`CountingAllocator`] is the global
+/// allocator, see [`is_counting`].
+pub fn count_allocations<R>(f: impl FnOnce() -> R) -> (R, AllocStats) {
+    let before = STATS.with(Cell::get);
+    let result = f();
+    let after = STATS.with(Cell::get);
+
+    (
+        result,
+        AllocStats {
+            allocations: after.allocations - before.allocations,
+            reallocations: after.reallocations - before.reallocations,
+            bytes: after.bytes - before.bytes,
+        },
+    )
+}
+
+/// Whether [`CountingAllocator`] is the global allocator, so that tests
+/// asserting few allocations don't pass because nothing is counted
+pub fn is_counting() -> bool {
+    // `black_box` keeps the allocation from being optimized out
+    count_allocations(|| drop(std::hint::black_box(Box::new(0u8))))
+        .1
+        .allocations
+        == 1
+}
@@ -0,0 +1,85 @@
+//! The entry points that follow semver, for code that wants to keep compiling
+//! across minor releases
+//!
+//! The parser, its `Input` and the deserializer internals change between
+//! minor releases while the crate is experimental; these functions keep their
+//! signatures. The [`ast`](crate::ast) they return only grows, its enums are
+//! `#[non_exhaustive]`.
+
+#[cfg(feature = "utf8_parser_serde1")]
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "utf8_parser_serde1")]
+pub use crate::utf8_parser::serde::{Options, PrettyConfig};
+#[cfg(feature = "utf8_parser")]
+use crate::{ast::Ron, utf8_parser};
+pub use crate::{ColumnMode, Error, ErrorKind, Location};
+
+/// Parses `s` into its abstract syntax tree
+#[cfg(feature = "utf8_parser")]
+pub fn parse(s: &str) -> Result<Ron<'_>, Error> {
+    utf8_parser::ast_from_str(s)
+}
+
+/// Checks the syntax of `s`, reporting the errors of all invalid list
+/// elements at once
+#[cfg(feature = "utf8_parser")]
+pub fn validate(s: &str) -> Result<(), Vec<Error>> {
+    utf8_parser::validate_str(s)
+}
+
+/// Deserializes `s` as a `T`
+#[cfg(feature = "utf8_parser_serde1")]
+pub fn deserialize<'a, T>(s: &'a str, options: Options) -> Result<T, Error>
+where
+    T: Deserialize<'a>,
+{
+    utf8_parser::serde::from_str_with_options(s, options)
+}
+
+/// Serializes `value` on a single line
+#[cfg(feature = "utf8_parser_serde1")]
+pub fn serialize<T>(value: &T) -> Result<String, Error>
+where
+    T: ?Sized + Serialize,
+{
+    utf8_parser::serde::to_string(value)
+}
+
+/// Serializes `value` over multiple lines, formatted as `config` says
+#[cfg(feature = "utf8_parser_serde1")]
+pub fn serialize_pretty<T>(value: &T, config: PrettyConfig) -> Result<String, Error>
+where
+    T: ?Sized + Serialize,
+{
+    utf8_parser::serde::to_string_pretty(value, config)
+}
+
+#[cfg(all(test, feature = "utf8_parser_serde1"))]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let source = r#"{"a":[1,2],"b":[]}"#;
+        let map: BTreeMap<String, Vec<u8>> = deserialize(source, Options::default()).unwrap();
+
+        assert_eq!(serialize(&map).unwrap(), source);
+        assert_eq!(
+            deserialize::<BTreeMap<String, Vec<u8>>>(
+                &serialize_pretty(&map, PrettyConfig::default()).unwrap(),
+                Options::default()
+            ),
+            Ok(map)
+        );
+        assert!(parse(source).is_ok());
+    }
+
+    #[test]
+    fn validate_reports_all_errors() {
+        assert_eq!(validate("[1, 2]"), Ok(()));
+        assert_eq!(validate("[1, (a: ), 3, {4}, 5]").unwrap_err().len(), 2);
+    }
+}
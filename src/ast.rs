@@ -1,4 +1,12 @@
-use std::mem::replace;
+use std::{
+    borrow::Cow,
+    collections::HashSet,
+    convert::TryFrom,
+    fmt,
+    mem::replace,
+    num::{ParseIntError, TryFromIntError},
+    time::Duration,
+};
 
 #[cfg(feature = "serde1_ast_derives")]
 use serde::Serialize;
@@ -6,17 +14,79 @@ use serde::Serialize;
 use crate::location::Location;
 
 /// IMPORTANT: Equality operators do NOT compare the start & end spans!
+///
+/// Serializes as just its value, unless serialized inside of [`WithSpans`].
 #[derive(Clone, Debug)]
-#[cfg_attr(feature = "serde1_ast_derives", derive(Serialize))]
-#[cfg_attr(feature = "serde1_ast_derives", serde(transparent))]
 pub struct Spanned<T> {
-    #[cfg_attr(feature = "serde1_ast_derives", serde(skip))]
     pub start: Location,
     pub value: T,
-    #[cfg_attr(feature = "serde1_ast_derives", serde(skip))]
     pub end: Location,
 }
 
+#[cfg(feature = "serde1_ast_derives")]
+thread_local! {
+    /// Whether a [`WithSpans`] is being serialized on this thread
+    static SERIALIZE_SPANS: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+}
+
+/// Serializes a node of the AST with the spans of its [`Spanned`] values,
+/// e.g. to export a document's tree for a visualization tool
+///
+/// Each `Spanned` becomes a struct of `start`, `value` and `end`, where the
+/// locations have a `line` and a `column`.
+///
+/// ```
+/// use ron_reboot::{ast::WithSpans, utf8_parser::ast_from_str};
+///
+/// let ron = ast_from_str("1").unwrap();
+/// assert_eq!(
+///     ron::to_string(&WithSpans(&ron.expr)).unwrap(),
+///     "(start:(line:1,column:1),value:Integer(Unsigned((number:1,text:Some(\"1\")))),end:(line:1,column:2))"
+/// );
+/// assert_eq!(
+///     ron::to_string(&ron.expr).unwrap(),
+///     "Integer(Unsigned((number:1,text:Some(\"1\"))))"
+/// );
+/// ```
+#[cfg(feature = "serde1_ast_derives")]
+#[derive(Clone, Copy, Debug)]
+pub struct WithSpans<'a, T: ?Sized>(pub &'a T);
+
+#[cfg(feature = "serde1_ast_derives")]
+impl<T: Serialize + ?Sized> Serialize for WithSpans<'_, T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        /// Restores the flag of an outer `WithSpans`, also when unwinding
+        struct Restore(bool);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                SERIALIZE_SPANS.with(|spans| spans.set(self.0));
+            }
+        }
+
+        let _restore = Restore(SERIALIZE_SPANS.with(|spans| spans.replace(true)));
+
+        self.0.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde1_ast_derives")]
+impl<T: Serialize> Serialize for Spanned<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        if !SERIALIZE_SPANS.with(std::cell::Cell::get) {
+            return self.value.serialize(serializer);
+        }
+
+        let mut s = serializer.serialize_struct("Spanned", 3)?;
+        s.serialize_field("start", &self.start)?;
+        s.serialize_field("value", &self.value)?;
+        s.serialize_field("end", &self.end)?;
+        s.end()
+    }
+}
+
 /// IMPORTANT: Equality operators do NOT compare the start & end spans!
 impl<T> PartialEq for Spanned<T>
 where
@@ -28,9 +98,18 @@ where
 }
 
 impl<T> Spanned<T> {
+    /// Applies `f` to the value, keeping the span
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Spanned<U> {
+        Spanned {
+            start: self.start,
+            value: f(self.value),
+            end: self.end,
+        }
+    }
+
     #[cfg(test)]
     pub fn new_test(value: T) -> Self {
-        use crate::utf8_parser::test_util::TestMockNew;
+        use crate::util::TestMockNew;
 
         Spanned {
             start: Location::new_mocked(),
@@ -47,10 +126,64 @@ pub struct Ron<'a> {
     pub expr: Spanned<Expr<'a>>,
 }
 
+impl Ron<'_> {
+    /// Copies what the document borrows from the source, e.g. to keep it
+    /// after the source is dropped; strings become [`Expr::String`], and
+    /// numbers other than big integers lose their `text`
+    pub fn into_owned(self) -> Ron<'static> {
+        Ron {
+            attributes: self.attributes,
+            expr: self.expr.map(Expr::into_owned),
+        }
+    }
+
+    /// The version of `#![version(..)]`, if the document has one
+    pub fn version(&self) -> Option<u64> {
+        self.version_attribute().map(|version| version.value)
+    }
+
+    /// Sets the version of `#![version(..)]`, adding the attribute if the
+    /// document doesn't have one yet
+    pub fn set_version(&mut self, version: u64) {
+        let attribute =
+            self.attributes
+                .iter_mut()
+                .find_map(|attribute| match &mut attribute.value {
+                    Attribute::Version(version) => Some(version),
+                    _ => None,
+                });
+        match attribute {
+            Some(attribute) => attribute.value = version,
+            None => self.attributes.push(Spanned {
+                start: self.expr.start,
+                value: Attribute::Version(Spanned {
+                    start: self.expr.start,
+                    value: version,
+                    end: self.expr.start,
+                }),
+                end: self.expr.start,
+            }),
+        }
+    }
+
+    pub(crate) fn version_attribute(&self) -> Option<&Spanned<u64>> {
+        self.attributes
+            .iter()
+            .find_map(|attribute| match &attribute.value {
+                Attribute::Version(version) => Some(version),
+                _ => None,
+            })
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "serde1_ast_derives", derive(Serialize))]
+#[non_exhaustive]
 pub enum Attribute {
     Enable(Spanned<Vec<Spanned<Extension>>>),
+    /// The version of the document's format, `#![version(2)]`, see
+    /// [`Migrations`](crate::migrate::Migrations)
+    Version(Spanned<u64>),
 }
 
 impl Attribute {
@@ -64,22 +197,79 @@ impl Attribute {
 
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "serde1_ast_derives", derive(Serialize))]
+#[non_exhaustive]
 pub enum Extension {
     UnwrapNewtypes,
     ImplicitSome,
+    /// Numbers with a duration or size suffix, e.g. `250ms` or `64KiB`
+    UnitLiterals,
+    /// Hex colors, e.g. `#ff8000` or `#ff800080`
+    ColorLiterals,
+    /// An extension that isn't built in, which has to be registered to be used
+    Other(String),
+}
+
+impl Extension {
+    pub const ALL: [Extension; 4] = [
+        Extension::UnwrapNewtypes,
+        Extension::ImplicitSome,
+        Extension::UnitLiterals,
+        Extension::ColorLiterals,
+    ];
+
+    /// The built-in extension called `name`, or [`Extension::Other`]
+    pub fn from_name(name: &str) -> Self {
+        Extension::ALL
+            .iter()
+            .find(|extension| extension.name() == name)
+            .cloned()
+            .unwrap_or_else(|| Extension::Other(name.to_owned()))
+    }
+
+    /// The name used in `#![enable(..)]`
+    pub fn name(&self) -> &str {
+        match self {
+            Extension::UnwrapNewtypes => "unwrap_newtypes",
+            Extension::ImplicitSome => "implicit_some",
+            Extension::UnitLiterals => "unit_literals",
+            Extension::ColorLiterals => "color_literals",
+            Extension::Other(name) => name,
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "serde1_ast_derives", derive(Serialize))]
-pub struct Ident<'a>(pub &'a str);
+pub struct Ident<'a>(pub Cow<'a, str>);
 
 impl<'a> Ident<'a> {
     pub fn from_str(input: &'a str) -> Self {
-        Ident(input)
+        Ident(Cow::Borrowed(input))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_owned(self) -> Ident<'static> {
+        Ident(Cow::Owned(self.0.into_owned()))
     }
 
     pub fn into_string(self) -> String {
-        self.0.to_owned()
+        self.0.into_owned()
+    }
+}
+
+/// The text between the quotes of a string with escapes, which are only
+/// resolved by [`unescape`](Self::unescape)
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde1_ast_derives", derive(Serialize))]
+pub struct EscapedStr<'a>(pub &'a str);
+
+impl<'a> EscapedStr<'a> {
+    /// The string the escapes stand for, borrowed if there are none
+    pub fn unescape(&self) -> Cow<'a, str> {
+        crate::str::unescape(self.0)
     }
 }
 
@@ -105,66 +295,105 @@ impl From<Sign> for i8 {
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+/// IMPORTANT: Equality operators do NOT compare the `text`, so `0x2A` equals
+/// `42`!
+#[derive(Clone, Copy, Debug)]
 #[cfg_attr(feature = "serde1_ast_derives", derive(Serialize))]
-pub struct UnsignedInteger {
+pub struct UnsignedInteger<'a> {
     pub number: u64,
+    /// The integer as it is written in the document, e.g. `0x2A`, `None` if
+    /// it wasn't parsed
+    pub text: Option<&'a str>,
 }
 
-impl UnsignedInteger {
-    #[cfg(test)]
+impl UnsignedInteger<'_> {
+    /// An integer that wasn't parsed, without a `text`
     pub const fn new(number: u64) -> Self {
-        UnsignedInteger { number }
+        UnsignedInteger { number, text: None }
     }
 
     pub fn into_u64(self) -> u64 {
         self.into()
     }
+}
 
-    pub fn to_expr(self) -> Expr<'static> {
+impl<'a> UnsignedInteger<'a> {
+    pub fn to_expr(self) -> Expr<'a> {
         Expr::Integer(Integer::Unsigned(self))
     }
 }
 
-impl From<UnsignedInteger> for u64 {
+impl PartialEq for UnsignedInteger<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.number == other.number
+    }
+}
+
+impl From<UnsignedInteger<'_>> for u64 {
     fn from(u: UnsignedInteger) -> u64 {
         u.number
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+/// IMPORTANT: Equality operators do NOT compare the `text`!
+#[derive(Clone, Debug)]
 #[cfg_attr(feature = "serde1_ast_derives", derive(Serialize))]
-pub struct SignedInteger {
+pub struct SignedInteger<'a> {
     pub sign: Sign,
     pub number: u64,
+    /// The integer as it is written in the document, e.g. `+7`, `None` if it
+    /// wasn't parsed
+    pub text: Option<&'a str>,
 }
 
-impl SignedInteger {
+impl<'a> SignedInteger<'a> {
     #[cfg(test)]
     pub fn new_test(sign: Sign, number: u64) -> Self {
-        SignedInteger { sign, number }
+        SignedInteger {
+            sign,
+            number,
+            text: None,
+        }
     }
 
-    pub fn to_expr(self) -> Expr<'static> {
+    pub fn to_expr(self) -> Expr<'a> {
         Expr::Integer(Integer::Signed(self))
     }
 }
 
-impl From<SignedInteger> for i64 {
-    fn from(s: SignedInteger) -> i64 {
-        // TODO: conversion to i64 doesn't work for 2^63 (which, with a negative sign is still in bounds)
-        s.sign.into_i8() as i64 * s.number as i64
+impl PartialEq for SignedInteger<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        (self.sign, self.number) == (other.sign, other.number)
+    }
+}
+
+impl From<SignedInteger<'_>> for i128 {
+    fn from(s: SignedInteger) -> i128 {
+        i128::from(s.sign.into_i8()) * i128::from(s.number)
+    }
+}
+
+impl TryFrom<SignedInteger<'_>> for i64 {
+    type Error = TryFromIntError;
+
+    /// Fails for numbers out of the range of `i64`, e.g. `-10000000000000000000`
+    fn try_from(s: SignedInteger) -> Result<i64, TryFromIntError> {
+        i64::try_from(i128::from(s))
     }
 }
 
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "serde1_ast_derives", derive(Serialize))]
-pub enum Integer {
-    Signed(SignedInteger),
-    Unsigned(UnsignedInteger),
+pub enum Integer<'a> {
+    Signed(SignedInteger<'a>),
+    Unsigned(UnsignedInteger<'a>),
+    /// An integer too large for a `u64` as it is written in the document, e.g.
+    /// `-123456789012345678901234567890`, only parsed with
+    /// [`Input::with_exact_numbers`](crate::utf8_parser::Input::with_exact_numbers)
+    Big(Cow<'a, str>),
 }
 
-impl Integer {
+impl<'a> Integer<'a> {
     #[cfg(test)]
     pub fn new_test(sign: Option<Sign>, number: u64) -> Self {
         match sign {
@@ -173,30 +402,95 @@ impl Integer {
         }
     }
 
-    pub fn into_i64(self) -> i64 {
+    /// The integer as it is written in the document, `None` if it wasn't
+    /// parsed
+    pub fn text(&self) -> Option<&str> {
         match self {
-            Integer::Signed(s) => s.into(),
-            Integer::Unsigned(u) => u.into_u64() as i64,
+            Integer::Signed(s) => s.text,
+            Integer::Unsigned(u) => u.text,
+            Integer::Big(text) => Some(text),
+        }
+    }
+
+    /// The integer as an `i64`, or `None` if it is out of its range
+    pub fn into_i64(self) -> Option<i64> {
+        i128::try_from(self)
+            .ok()
+            .and_then(|i| i64::try_from(i).ok())
+    }
+
+    /// Copies the text of a [`Big`](Integer::Big) integer, the others lose
+    /// their `text`
+    pub fn into_owned(self) -> Integer<'static> {
+        match self {
+            Integer::Signed(s) => Integer::Signed(SignedInteger {
+                sign: s.sign,
+                number: s.number,
+                text: None,
+            }),
+            Integer::Unsigned(u) => Integer::Unsigned(UnsignedInteger::new(u.number)),
+            Integer::Big(text) => Integer::Big(Cow::Owned(text.into_owned())),
         }
     }
 
     #[cfg(test)]
-    pub fn to_expr(self) -> Expr<'static> {
+    pub fn to_expr(self) -> Expr<'a> {
         Expr::Integer(self)
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+impl TryFrom<Integer<'_>> for i128 {
+    type Error = ParseIntError;
+
+    /// Only fails for [`Big`](Integer::Big) integers beyond the range of `i128`
+    fn try_from(i: Integer) -> Result<i128, ParseIntError> {
+        match i {
+            Integer::Signed(s) => Ok(s.into()),
+            Integer::Unsigned(u) => Ok(u.number.into()),
+            Integer::Big(text) => text.parse(),
+        }
+    }
+}
+
+impl fmt::Display for Integer<'_> {
+    /// Writes the integer in decimal, e.g. `42` for `0x2A`, exactly also
+    /// where it doesn't fit an `i64`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Integer::Signed(SignedInteger {
+                sign: Sign::Negative,
+                number,
+                ..
+            }) if *number != 0 => write!(f, "-{}", number),
+            Integer::Signed(SignedInteger { number, .. })
+            | Integer::Unsigned(UnsignedInteger { number, .. }) => write!(f, "{}", number),
+            Integer::Big(text) => f.write_str(text.strip_prefix('+').unwrap_or(text)),
+        }
+    }
+}
+
+/// IMPORTANT: Equality operators do NOT compare the `text`!
+#[derive(Clone, Debug)]
 #[cfg_attr(feature = "serde1_ast_derives", derive(Serialize))]
-pub struct Decimal {
+pub struct Decimal<'a> {
     pub sign: Option<Sign>,
+    /// The whole part, `None` for decimals like `.5`
+    ///
+    /// With
+    /// [`Input::with_exact_numbers`](crate::utf8_parser::Input::with_exact_numbers),
+    /// a whole part too large for a `u64` is cut to its first 19 digits and
+    /// the others are counted in the `exponent`, and fractional digits after
+    /// the 19th are cut off. Only the `text` has all of them then.
     pub whole: Option<u64>,
     pub fractional: u64,
     pub fractional_digits: u16,
     pub exponent: Option<(Option<Sign>, u16)>,
+    /// The decimal as it is written in the document, e.g. `1.50`, `None` if
+    /// it wasn't parsed
+    pub text: Option<&'a str>,
 }
 
-impl Decimal {
+impl Decimal<'_> {
     pub fn new(
         sign: Option<Sign>,
         whole: Option<u64>,
@@ -210,31 +504,311 @@ impl Decimal {
             fractional,
             fractional_digits,
             exponent,
+            text: None,
         }
     }
+
+    /// This decimal without its `text`, see [`Ron::into_owned`]
+    pub fn into_owned(self) -> Decimal<'static> {
+        Decimal { text: None, ..self }
+    }
 }
 
-impl From<Decimal> for f64 {
+impl PartialEq for Decimal<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        (
+            self.sign,
+            self.whole,
+            self.fractional,
+            self.fractional_digits,
+            self.exponent,
+        ) == (
+            other.sign,
+            other.whole,
+            other.fractional,
+            other.fractional_digits,
+            other.exponent,
+        )
+    }
+}
+
+impl fmt::Display for Decimal<'_> {
+    /// Writes the decimal in scientific notation, e.g. `-1.5e3`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = match self.sign {
+            Some(Sign::Negative) => "-",
+            _ => "",
+        };
+        let (exp_sign, exp) = match self.exponent {
+            Some((Some(Sign::Negative), exp)) => ("-", exp),
+            Some((_, exp)) => ("", exp),
+            None => ("", 0),
+        };
+
+        write!(
+            f,
+            "{}{}.{:0width$}e{}{}",
+            sign,
+            self.whole.unwrap_or_default(),
+            self.fractional,
+            exp_sign,
+            exp,
+            width = self.fractional_digits as usize
+        )
+    }
+}
+
+impl From<Decimal<'_>> for f64 {
+    /// Correctly rounded, so floats written with their shortest representation
+    /// parse back to the exact same value. Decimals with more digits than
+    /// their fields hold are converted from their `text`.
     fn from(d: Decimal) -> f64 {
-        let sign = d.sign.map(Into::into).unwrap_or(1i8);
-        let whole = d.whole.unwrap_or_default();
+        d.text
+            .and_then(|text| text.parse().ok())
+            .unwrap_or_else(|| {
+                d.to_string()
+                    .parse()
+                    .expect("decimal is always a valid float")
+            })
+    }
+}
+
+/// A number with a unit suffix, e.g. `250ms` or `64KiB`
+///
+/// Only valid with the `unit_literals` extension.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde1_ast_derives", derive(Serialize))]
+pub struct Quantity<'a> {
+    pub amount: Amount<'a>,
+    pub unit: Unit,
+}
+
+impl Quantity<'_> {
+    /// This quantity without the `text` of its amount, see [`Ron::into_owned`]
+    pub fn into_owned(self) -> Quantity<'static> {
+        Quantity {
+            amount: match self.amount {
+                Amount::Integer(i) => Amount::Integer(UnsignedInteger::new(i.number)),
+                Amount::Decimal(d) => Amount::Decimal(d.into_owned()),
+            },
+            unit: self.unit,
+        }
+    }
+
+    /// The duration if this is one, rounded to nanoseconds
+    pub fn to_duration(&self) -> Option<Duration> {
+        const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+        match self.unit.kind() {
+            UnitKind::Duration => {
+                let nanos = self.base_units(true)?;
+                let secs = u64::try_from(nanos / NANOS_PER_SEC).ok()?;
+
+                Some(Duration::new(secs, (nanos % NANOS_PER_SEC) as u32))
+            }
+            UnitKind::Size => None,
+        }
+    }
+
+    /// The number of bytes if this is a size and a whole number of bytes
+    pub fn to_bytes(&self) -> Option<u64> {
+        match self.unit.kind() {
+            UnitKind::Duration => None,
+            UnitKind::Size => u64::try_from(self.base_units(false)?).ok(),
+        }
+    }
+
+    /// The amount in nanoseconds or bytes if it's a whole number of them
+    pub fn exact_base_units(&self) -> Option<u128> {
+        self.base_units(false)
+    }
+
+    /// The amount in nanoseconds or bytes
+    fn base_units(&self, round: bool) -> Option<u128> {
+        let factor = self.unit.base_units();
+
+        match &self.amount {
+            Amount::Integer(i) => u128::from(i.number).checked_mul(u128::from(factor)),
+            Amount::Decimal(d) => {
+                let units = f64::from(d.clone()) * factor as f64;
+                let units = if round { units.round() } else { units };
+
+                // Also rejects infinity, whose fractional part is NaN
+                (units.fract() == 0.0 && units < u128::MAX as f64).then_some(units as u128)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Quantity<'_> {
+    /// Writes the quantity like it's written in RON, e.g. `1.5GiB`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.amount {
+            Amount::Integer(i) => write!(f, "{}{}", i.number, self.unit.suffix()),
+            Amount::Decimal(d) => write!(f, "{}{}", f64::from(d.clone()), self.unit.suffix()),
+        }
+    }
+}
+
+/// The number of a [`Quantity`], which has no sign
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde1_ast_derives", derive(Serialize))]
+pub enum Amount<'a> {
+    Integer(UnsignedInteger<'a>),
+    Decimal(Decimal<'a>),
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde1_ast_derives", derive(Serialize))]
+pub enum Unit {
+    Nanoseconds,
+    Microseconds,
+    Milliseconds,
+    Seconds,
+    Minutes,
+    Hours,
+    Days,
+    Bytes,
+    Kilobytes,
+    Kibibytes,
+    Megabytes,
+    Mebibytes,
+    Gigabytes,
+    Gibibytes,
+    Terabytes,
+    Tebibytes,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum UnitKind {
+    Duration,
+    Size,
+}
+
+impl UnitKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            UnitKind::Duration => "duration",
+            UnitKind::Size => "size",
+        }
+    }
+}
+
+impl Unit {
+    pub const ALL: [Unit; 16] = [
+        Unit::Nanoseconds,
+        Unit::Microseconds,
+        Unit::Milliseconds,
+        Unit::Seconds,
+        Unit::Minutes,
+        Unit::Hours,
+        Unit::Days,
+        Unit::Bytes,
+        Unit::Kilobytes,
+        Unit::Kibibytes,
+        Unit::Megabytes,
+        Unit::Mebibytes,
+        Unit::Gigabytes,
+        Unit::Gibibytes,
+        Unit::Terabytes,
+        Unit::Tebibytes,
+    ];
+
+    /// The suffixes of [`ALL`](Self::ALL), in the same order
+    pub const SUFFIXES: [&'static str; 16] = [
+        "ns", "us", "ms", "s", "min", "h", "d", "B", "KB", "KiB", "MB", "MiB", "GB", "GiB", "TB",
+        "TiB",
+    ];
+
+    pub fn from_suffix(suffix: &str) -> Option<Unit> {
+        Self::SUFFIXES
+            .iter()
+            .position(|&s| s == suffix)
+            .map(|i| Self::ALL[i])
+    }
 
-        let (exp_sign, exp) = d.exponent.unwrap_or((None, 0));
+    pub fn suffix(self) -> &'static str {
+        Self::SUFFIXES[self as usize]
+    }
 
-        let exp_sign = exp_sign.map(Into::into).unwrap_or(1i8);
-        let exp = exp as i32 * exp_sign as i32;
+    pub fn kind(self) -> UnitKind {
+        match self {
+            Unit::Nanoseconds
+            | Unit::Microseconds
+            | Unit::Milliseconds
+            | Unit::Seconds
+            | Unit::Minutes
+            | Unit::Hours
+            | Unit::Days => UnitKind::Duration,
+            _ => UnitKind::Size,
+        }
+    }
 
-        let mut f: f64 = sign as f64 * whole as f64;
-        f *= (10.0f64).powi(exp);
-        f += d.fractional as f64 * (10.0f64).powi(exp - d.fractional_digits as i32) * sign as f64;
+    /// Nanoseconds or bytes per unit
+    pub fn base_units(self) -> u64 {
+        const SEC: u64 = 1_000_000_000;
 
-        f
+        match self {
+            Unit::Nanoseconds => 1,
+            Unit::Microseconds => 1_000,
+            Unit::Milliseconds => 1_000_000,
+            Unit::Seconds => SEC,
+            Unit::Minutes => 60 * SEC,
+            Unit::Hours => 60 * 60 * SEC,
+            Unit::Days => 24 * 60 * 60 * SEC,
+            Unit::Bytes => 1,
+            Unit::Kilobytes => 1_000,
+            Unit::Kibibytes => 1 << 10,
+            Unit::Megabytes => 1_000_000,
+            Unit::Mebibytes => 1 << 20,
+            Unit::Gigabytes => 1_000_000_000,
+            Unit::Gibibytes => 1 << 30,
+            Unit::Terabytes => 1_000_000_000_000,
+            Unit::Tebibytes => 1 << 40,
+        }
+    }
+}
+
+/// A color like `#ff8000`, whose alpha is `255` unless written as `#ff800080`
+///
+/// Only valid with the `color_literals` extension.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde1_ast_derives", derive(Serialize))]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    /// `[r, g, b, a]`, which is also how colors are deserialized
+    pub fn to_array(self) -> [u8; 4] {
+        [self.r, self.g, self.b, self.a]
+    }
+}
+
+impl fmt::Display for Color {
+    /// Writes the color in lowercase hex, omitting an opaque alpha
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)?;
+
+        match self.a {
+            255 => Ok(()),
+            a => write!(f, "{:02x}", a),
+        }
     }
 }
 
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "serde1_ast_derives", derive(Serialize))]
 pub struct KeyValue<'a, K: 'a> {
+    /// Lines of the `///` doc comment preceding the key, if any
+    #[cfg_attr(
+        feature = "serde1_ast_derives",
+        serde(skip_serializing_if = "Vec::is_empty")
+    )]
+    pub doc: Vec<Cow<'a, str>>,
     pub key: Spanned<K>,
     pub value: Spanned<Expr<'a>>,
 }
@@ -243,10 +817,31 @@ impl<'a, K: 'a> KeyValue<'a, K> {
     #[cfg(test)]
     pub fn new_test(key: K, value: Expr<'a>) -> Self {
         KeyValue {
+            doc: vec![],
             key: Spanned::new_test(key),
             value: Spanned::new_test(value),
         }
     }
+
+    fn into_owned<K2: 'static>(self, key: impl FnOnce(K) -> K2) -> KeyValue<'static, K2> {
+        KeyValue {
+            doc: self
+                .doc
+                .into_iter()
+                .map(|line| Cow::Owned(line.into_owned()))
+                .collect(),
+            key: self.key.map(key),
+            value: self.value.map(Expr::into_owned),
+        }
+    }
+
+    /// The doc comment as one string, lines separated by `\n`
+    pub fn doc_string(&self) -> Option<String> {
+        match self.doc.is_empty() {
+            true => None,
+            false => Some(self.doc.join("\n")),
+        }
+    }
 }
 
 pub type SpannedKvs<'a, K> = Vec<Spanned<KeyValue<'a, K>>>;
@@ -257,6 +852,35 @@ pub struct Struct<'a> {
     pub fields: SpannedKvs<'a, Ident<'a>>,
 }
 
+impl<'a> Struct<'a> {
+    /// The keys and values of the fields in the order of the document
+    pub fn fields(
+        &self,
+    ) -> impl ExactSizeIterator<Item = (&Spanned<Ident<'a>>, &Spanned<Expr<'a>>)> {
+        self.fields
+            .iter()
+            .map(|field| (&field.value.key, &field.value.value))
+    }
+
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    pub fn into_owned(self) -> Struct<'static> {
+        Struct {
+            fields: self
+                .fields
+                .into_iter()
+                .map(|field| field.map(|field| field.into_owned(Ident::into_owned)))
+                .collect(),
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "serde1_ast_derives", derive(Serialize))]
 pub struct Map<'a> {
@@ -264,12 +888,40 @@ pub struct Map<'a> {
 }
 
 impl<'a> Map<'a> {
+    /// The keys and values of the entries in the order of the document
+    pub fn entries(
+        &self,
+    ) -> impl ExactSizeIterator<Item = (&Spanned<Expr<'a>>, &Spanned<Expr<'a>>)> {
+        self.entries
+            .iter()
+            .map(|entry| (&entry.value.key, &entry.value.value))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn into_owned(self) -> Map<'static> {
+        Map {
+            entries: self
+                .entries
+                .into_iter()
+                .map(|entry| entry.map(|entry| entry.into_owned(Expr::into_owned)))
+                .collect(),
+        }
+    }
+
     #[cfg(test)]
     pub fn new_test(kvs: Vec<(Expr<'a>, Expr<'a>)>) -> Self {
         Map {
             entries: kvs
                 .into_iter()
                 .map(|(k, v)| KeyValue {
+                    doc: vec![],
                     key: Spanned::new_test(k),
                     value: Spanned::new_test(v),
                 })
@@ -286,6 +938,24 @@ pub struct List<'a> {
 }
 
 impl<'a> List<'a> {
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = &Spanned<Expr<'a>>> {
+        self.elements.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    pub fn into_owned(self) -> List<'static> {
+        List {
+            elements: owned_elements(self.elements),
+        }
+    }
+
     #[cfg(test)]
     pub fn new_test(kvs: Vec<Expr<'a>>) -> Self {
         List {
@@ -300,6 +970,33 @@ pub struct Tuple<'a> {
     pub elements: Vec<Spanned<Expr<'a>>>,
 }
 
+impl<'a> Tuple<'a> {
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = &Spanned<Expr<'a>>> {
+        self.elements.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    pub fn into_owned(self) -> Tuple<'static> {
+        Tuple {
+            elements: owned_elements(self.elements),
+        }
+    }
+}
+
+fn owned_elements(elements: Vec<Spanned<Expr>>) -> Vec<Spanned<Expr<'static>>> {
+    elements
+        .into_iter()
+        .map(|element| element.map(Expr::into_owned))
+        .collect()
+}
+
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "serde1_ast_derives", derive(Serialize))]
 pub enum Untagged<'a> {
@@ -312,6 +1009,14 @@ impl<'a> Untagged<'a> {
     pub fn take(&mut self) -> Self {
         replace(self, Untagged::Unit)
     }
+
+    pub fn into_owned(self) -> Untagged<'static> {
+        match self {
+            Untagged::Unit => Untagged::Unit,
+            Untagged::Struct(s) => Untagged::Struct(s.into_owned()),
+            Untagged::Tuple(t) => Untagged::Tuple(t.into_owned()),
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -321,23 +1026,41 @@ pub struct Tagged<'a> {
     pub untagged: Spanned<Untagged<'a>>,
 }
 
+impl Tagged<'_> {
+    pub fn into_owned(self) -> Tagged<'static> {
+        Tagged {
+            ident: self.ident.map(Ident::into_owned),
+            untagged: self.untagged.map(Untagged::into_owned),
+        }
+    }
+}
+
+/// An expression; new kinds of expressions may be added, so code matching all
+/// of them needs a wildcard arm, or a [`Visitor`]
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "serde1_ast_derives", derive(Serialize))]
+#[non_exhaustive]
 pub enum Expr<'a> {
     Unit,
     Optional(Option<Box<Spanned<Expr<'a>>>>),
-    Tagged(Tagged<'a>),
+    Tagged(Box<Tagged<'a>>),
     Bool(bool),
     Tuple(Tuple<'a>),
     List(List<'a>),
     Map(Map<'a>),
     Struct(Struct<'a>),
-    Integer(Integer),
+    Integer(Integer<'a>),
     /// String without escapes (zero-copy)
     Str(&'a str),
     /// Escaped string
     String(String),
-    Decimal(Decimal),
+    /// Escaped string that wasn't unescaped yet, only parsed with
+    /// [`Input::with_lazy_escapes`](crate::utf8_parser::Input::with_lazy_escapes)
+    EscapedStr(EscapedStr<'a>),
+    /// Boxed like quantities, which keeps lists of other values small
+    Decimal(Box<Decimal<'a>>),
+    Quantity(Box<Quantity<'a>>),
+    Color(Color),
 }
 
 impl<'a> Expr<'a> {
@@ -345,4 +1068,266 @@ impl<'a> Expr<'a> {
     pub fn take(&mut self) -> Self {
         replace(self, Expr::Unit)
     }
+
+    /// See [`Ron::into_owned`]
+    pub fn into_owned(self) -> Expr<'static> {
+        match self {
+            Expr::Unit => Expr::Unit,
+            Expr::Optional(inner) => {
+                Expr::Optional(inner.map(|inner| Box::new(inner.map(Expr::into_owned))))
+            }
+            Expr::Tagged(t) => Expr::Tagged(Box::new(t.into_owned())),
+            Expr::Bool(b) => Expr::Bool(b),
+            Expr::Tuple(t) => Expr::Tuple(t.into_owned()),
+            Expr::List(l) => Expr::List(l.into_owned()),
+            Expr::Map(m) => Expr::Map(m.into_owned()),
+            Expr::Struct(s) => Expr::Struct(s.into_owned()),
+            Expr::Integer(i) => Expr::Integer(i.into_owned()),
+            Expr::Str(s) => Expr::String(s.to_owned()),
+            Expr::String(s) => Expr::String(s),
+            Expr::EscapedStr(s) => Expr::String(s.unescape().into_owned()),
+            Expr::Decimal(d) => Expr::Decimal(Box::new((*d).into_owned())),
+            Expr::Quantity(q) => Expr::Quantity(Box::new((*q).into_owned())),
+            Expr::Color(c) => Expr::Color(c),
+        }
+    }
+
+    pub fn kind(&self) -> ExprKind {
+        match self {
+            Expr::Unit => ExprKind::Unit,
+            Expr::Optional(_) => ExprKind::Optional,
+            Expr::Tagged(_) => ExprKind::Tagged,
+            Expr::Bool(_) => ExprKind::Bool,
+            Expr::Tuple(_) => ExprKind::Tuple,
+            Expr::List(_) => ExprKind::List,
+            Expr::Map(_) => ExprKind::Map,
+            Expr::Struct(_) => ExprKind::Struct,
+            Expr::Integer(_) => ExprKind::Integer,
+            Expr::Str(_) => ExprKind::Str,
+            Expr::String(_) | Expr::EscapedStr(_) => ExprKind::String,
+            Expr::Decimal(_) => ExprKind::Decimal,
+            Expr::Quantity(_) => ExprKind::Quantity,
+            Expr::Color(_) => ExprKind::Color,
+        }
+    }
+
+    /// The fields if this is a struct, with or without a name
+    pub fn as_struct(&self) -> Option<&Struct<'a>> {
+        match self {
+            Expr::Struct(s) => Some(s),
+            Expr::Tagged(t) => match &t.untagged.value {
+                Untagged::Struct(s) => Some(s),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// See [`as_struct`](Self::as_struct)
+    pub fn as_struct_mut(&mut self) -> Option<&mut Struct<'a>> {
+        match self {
+            Expr::Struct(s) => Some(s),
+            Expr::Tagged(t) => match &mut t.untagged.value {
+                Untagged::Struct(s) => Some(s),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// The elements if this is a tuple, with or without a name
+    pub fn as_tuple(&self) -> Option<&Tuple<'a>> {
+        match self {
+            Expr::Tuple(t) => Some(t),
+            Expr::Tagged(t) => match &t.untagged.value {
+                Untagged::Tuple(t) => Some(t),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// See [`as_tuple`](Self::as_tuple)
+    pub fn as_tuple_mut(&mut self) -> Option<&mut Tuple<'a>> {
+        match self {
+            Expr::Tuple(t) => Some(t),
+            Expr::Tagged(t) => match &mut t.untagged.value {
+                Untagged::Tuple(t) => Some(t),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// The name if this is a unit struct or variant, like `Empty`
+    pub fn as_unit_ident(&self) -> Option<&Spanned<Ident<'a>>> {
+        match self {
+            Expr::Tagged(t) if matches!(t.untagged.value, Untagged::Unit) => Some(&t.ident),
+            _ => None,
+        }
+    }
+
+    /// The directly contained expressions in the order of the document,
+    /// including map keys
+    pub fn children(&self) -> impl Iterator<Item = &Spanned<Expr<'a>>> {
+        let children: Vec<_> = match self {
+            Expr::Optional(inner) => inner.iter().map(AsRef::as_ref).collect(),
+            Expr::Tagged(t) => match &t.untagged.value {
+                Untagged::Unit => vec![],
+                Untagged::Struct(s) => s.fields.iter().map(|f| &f.value.value).collect(),
+                Untagged::Tuple(t) => t.elements.iter().collect(),
+            },
+            Expr::Tuple(t) => t.elements.iter().collect(),
+            Expr::List(l) => l.elements.iter().collect(),
+            Expr::Map(m) => m
+                .entries
+                .iter()
+                .flat_map(|e| vec![&e.value.key, &e.value.value])
+                .collect(),
+            Expr::Struct(s) => s.fields.iter().map(|f| &f.value.value).collect(),
+            _ => vec![],
+        };
+
+        children.into_iter()
+    }
+
+    /// See [`children`](Self::children)
+    pub fn children_mut(&mut self) -> impl Iterator<Item = &mut Spanned<Expr<'a>>> {
+        let children: Vec<_> = match self {
+            Expr::Optional(inner) => inner.iter_mut().map(AsMut::as_mut).collect(),
+            Expr::Tagged(t) => match &mut t.untagged.value {
+                Untagged::Unit => vec![],
+                Untagged::Struct(s) => s.fields.iter_mut().map(|f| &mut f.value.value).collect(),
+                Untagged::Tuple(t) => t.elements.iter_mut().collect(),
+            },
+            Expr::Tuple(t) => t.elements.iter_mut().collect(),
+            Expr::List(l) => l.elements.iter_mut().collect(),
+            Expr::Map(m) => m
+                .entries
+                .iter_mut()
+                .flat_map(|e| vec![&mut e.value.key, &mut e.value.value])
+                .collect(),
+            Expr::Struct(s) => s.fields.iter_mut().map(|f| &mut f.value.value).collect(),
+            _ => vec![],
+        };
+
+        children.into_iter()
+    }
+}
+
+/// Parses a document that borrows from `s`, like
+/// [`ast_from_str`](crate::utf8_parser::ast_from_str)
+#[cfg(feature = "utf8_parser")]
+impl<'a> TryFrom<&'a str> for Ron<'a> {
+    type Error = crate::Error;
+
+    fn try_from(s: &'a str) -> Result<Self, crate::Error> {
+        crate::utf8_parser::ast_from_str(s)
+    }
+}
+
+/// Parses a document that owns its strings, see [`Ron::into_owned`]
+#[cfg(feature = "utf8_parser")]
+impl std::str::FromStr for Ron<'static> {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self, crate::Error> {
+        Ron::try_from(s).map(Ron::into_owned)
+    }
+}
+
+/// Parses the value of a document that borrows from `s`, ignoring its
+/// attributes
+#[cfg(feature = "utf8_parser")]
+impl<'a> TryFrom<&'a str> for Expr<'a> {
+    type Error = crate::Error;
+
+    fn try_from(s: &'a str) -> Result<Self, crate::Error> {
+        Ron::try_from(s).map(|ron| ron.expr.value)
+    }
+}
+
+/// Parses the value of a document, ignoring its attributes, see
+/// [`Expr::into_owned`]
+#[cfg(feature = "utf8_parser")]
+impl std::str::FromStr for Expr<'static> {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self, crate::Error> {
+        Expr::try_from(s).map(Expr::into_owned)
+    }
+}
+
+/// Visits the expressions of a document, parents before their children
+///
+/// The default of each method visits the children, so implementations only
+/// override the methods for what they are interested in. Unlike a `match` on
+/// [`Expr`], this keeps compiling when kinds of expressions are added.
+pub trait Visitor<'a> {
+    fn visit_ron(&mut self, ron: &Ron<'a>) {
+        self.visit_expr(&ron.expr);
+    }
+
+    fn visit_expr(&mut self, expr: &Spanned<Expr<'a>>) {
+        walk_expr(self, expr);
+    }
+}
+
+/// Visits the children of `expr`, for [`Visitor`] implementations that
+/// override [`visit_expr`](Visitor::visit_expr) but still want to descend
+pub fn walk_expr<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, expr: &Spanned<Expr<'a>>) {
+    expr.value
+        .children()
+        .for_each(|child| visitor.visit_expr(child));
+}
+
+/// The built-in extensions whose syntax `ron` uses, e.g. `unit_literals` for
+/// `250ms`, in the order of [`Extension::ALL`]
+///
+/// Only literals tell which extensions a document needs. Whether it needs
+/// `implicit_some` or `unwrap_newtypes` depends on the type it is
+/// deserialized into, so they are never listed. The extensions a document
+/// enables are in [`Ron::attributes`].
+pub fn required_extensions(ron: &Ron) -> Vec<Extension> {
+    struct Kinds(HashSet<ExprKind>);
+
+    impl<'a> Visitor<'a> for Kinds {
+        fn visit_expr(&mut self, expr: &Spanned<Expr<'a>>) {
+            self.0.insert(expr.value.kind());
+            walk_expr(self, expr);
+        }
+    }
+
+    let mut kinds = Kinds(HashSet::new());
+    kinds.visit_ron(ron);
+
+    Extension::ALL
+        .iter()
+        .filter(|extension| match extension {
+            Extension::UnitLiterals => kinds.0.contains(&ExprKind::Quantity),
+            Extension::ColorLiterals => kinds.0.contains(&ExprKind::Color),
+            _ => false,
+        })
+        .cloned()
+        .collect()
+}
+
+/// The variant of an [`Expr`], for code that only needs to tell them apart
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum ExprKind {
+    Unit,
+    Optional,
+    Tagged,
+    Bool,
+    Tuple,
+    List,
+    Map,
+    Struct,
+    Integer,
+    Str,
+    String,
+    Decimal,
+    Quantity,
+    Color,
 }
@@ -0,0 +1,123 @@
+//! Parsing of byte slices, e.g. memory-mapped files, which may contain
+//! comments in other encodings than UTF-8
+//!
+//! Valid UTF-8 is parsed without copying. Otherwise, only comments may contain
+//! invalid bytes, which are replaced by `?` in a copy of the input, so that
+//! locations stay the same. Invalid bytes anywhere else, e.g. in string
+//! literals, are an [`InvalidUtf8`](ErrorKind::InvalidUtf8) error.
+
+use std::borrow::Cow;
+
+use crate::{
+    ast::Ron,
+    lex::comments,
+    utf8_parser::{ast_from_str_with_extensions, extension::ExtensionRegistry},
+    Error,
+};
+
+/// The text of a byte slice, ready to be parsed
+#[derive(Clone, Debug)]
+pub struct BytesSource<'a> {
+    text: Cow<'a, str>,
+}
+
+impl<'a> BytesSource<'a> {
+    pub fn new(bytes: &'a [u8]) -> Result<Self, Error> {
+        if let Ok(text) = std::str::from_utf8(bytes) {
+            return Ok(BytesSource {
+                text: Cow::Borrowed(text),
+            });
+        }
+
+        let mut text = bytes.to_vec();
+        let mut code_start = 0;
+        let end = bytes.len()..bytes.len();
+        for comment in comments(bytes).chain(Some(end)) {
+            if let Err(e) = std::str::from_utf8(&bytes[code_start..comment.start]) {
+                return Err(Error::invalid_utf8(bytes, code_start + e.valid_up_to()));
+            }
+            replace_invalid(&mut text[comment.clone()]);
+            code_start = comment.end;
+        }
+
+        Ok(BytesSource {
+            text: Cow::Owned(String::from_utf8(text).expect("invalid bytes were replaced")),
+        })
+    }
+
+    /// The text, with invalid bytes in comments replaced
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// Whether the text is the byte slice itself, which was valid UTF-8
+    pub fn is_borrowed(&self) -> bool {
+        matches!(self.text, Cow::Borrowed(_))
+    }
+
+    pub fn ast(&self) -> Result<Ron<'_>, Error> {
+        self.ast_with_extensions(&ExtensionRegistry::new())
+    }
+
+    /// Parses the text, which may enable any extension of `registry`
+    pub fn ast_with_extensions(&self, registry: &ExtensionRegistry) -> Result<Ron<'_>, Error> {
+        ast_from_str_with_extensions(&self.text, registry)
+    }
+}
+
+fn replace_invalid(bytes: &mut [u8]) {
+    let mut start = 0;
+    while let Err(e) = std::str::from_utf8(&bytes[start..]) {
+        let invalid = start + e.valid_up_to();
+        start = e.error_len().map_or(bytes.len(), |len| invalid + len);
+        bytes[invalid..start].iter_mut().for_each(|b| *b = b'?');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ErrorKind, Location};
+
+    #[test]
+    fn valid() {
+        let source = BytesSource::new(b"(a: \"\xc3\xa4\")").unwrap();
+        assert!(source.is_borrowed());
+        assert!(source.ast().is_ok());
+    }
+
+    #[test]
+    fn invalid_in_comments() {
+        let bytes =
+            b"// caf\xe9\n(a: /* \xff /* \xfe */ */ 1, b: r#\"//\"#, c: \"/*\") // \xe2\x82";
+        let source = BytesSource::new(bytes).unwrap();
+        assert!(!source.is_borrowed());
+        assert_eq!(source.as_str().len(), bytes.len());
+        assert!(source.as_str().starts_with("// caf?\n"));
+        assert!(source.as_str().ends_with("// ??"));
+        assert!(source.ast().is_ok());
+
+        // Locations are the same as in the bytes
+        let err = BytesSource::new(b"/* \xff */ [1 2]")
+            .unwrap()
+            .ast()
+            .unwrap_err();
+        assert_eq!(
+            err.start(),
+            Some(Location {
+                line: 1,
+                column: 12
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_outside_comments() {
+        let err = BytesSource::new(b"[\n  \"\xc3\xa4 // \xff\",\n]").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidUtf8);
+        assert_eq!(err.start(), Some(Location { line: 2, column: 9 }));
+
+        let err = BytesSource::new(b"// \xff\n[\xff]").unwrap_err();
+        assert_eq!(err.start(), Some(Location { line: 2, column: 2 }));
+    }
+}
@@ -0,0 +1,662 @@
+use std::{
+    fmt::{Display, Formatter},
+    io::{stderr, Write},
+    str::FromStr,
+};
+
+#[cfg(feature = "utf8_parser")]
+use std::sync::Arc;
+
+#[cfg(not(feature = "tiny"))]
+use self::snippet::write_error_snippets;
+use crate::location::{ColumnMode, LineIndex, Location};
+#[cfg(feature = "utf8_parser")]
+use crate::utf8_parser::ErrorTree;
+
+#[cfg(not(feature = "tiny"))]
+mod snippet;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ErrorContext {
+    pub start_end: Option<(Location, Location)>,
+    pub file_name: Option<String>,
+    pub file_content: Option<String>,
+    /// What the parser expected at the error location
+    pub expected: Vec<ExpectedToken>,
+    /// Whether the parser could have backtracked from the error, for parsing
+    /// errors
+    pub severity: Option<Severity>,
+    /// Other locations the error involves, e.g. where the unclosed bracket
+    /// was opened
+    pub labels: Vec<Label>,
+    /// All branches the parser tried and the contexts they failed in, for
+    /// parsing errors
+    #[cfg(feature = "utf8_parser")]
+    pub error_tree: Option<Arc<ErrorTree<Location>>>,
+}
+
+/// A location related to an error, shown with its `message` below the
+/// error's own location, see [`Error::context_label`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Label {
+    pub start: Location,
+    pub end: Location,
+    /// What happened there, e.g. "first used here"
+    pub message: String,
+}
+
+/// Whether the parser could have tried another branch of the grammar after a
+/// parsing error, see [`Error::severity`]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Severity {
+    /// No branch matched, e.g. a `:` where an expression starts
+    Recoverable,
+    /// A branch matched far enough to rule out the others and then failed,
+    /// e.g. a string without its closing quote, or input remained after the
+    /// expression
+    Fatal,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Recoverable => write!(f, "recoverable"),
+            Severity::Fatal => write!(f, "fatal"),
+        }
+    }
+}
+
+/// A token the parser expected, e.g. for completion hints
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum ExpectedToken {
+    /// A keyword or punctuation, e.g. `"true"` or `"enable"`
+    Tag(&'static str),
+    Char(char),
+    /// An ASCII letter
+    Letter,
+    /// A decimal digit
+    Digit,
+    /// A decimal digit other than `0`
+    NonZeroDigit,
+    HexDigit,
+    OctDigit,
+    /// An ASCII letter or decimal digit
+    AlphaNumeric,
+    /// A valid unicode code point in a `\u{..}` escape
+    UnicodeEscape,
+    Whitespace,
+    /// The `"#..` closing a raw string
+    RawStringEnd,
+    /// The `*/` closing a block comment
+    BlockCommentEnd,
+    /// The end of the input
+    Eof,
+    /// Anything but the end of the input
+    Something,
+}
+
+impl Display for ExpectedToken {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExpectedToken::Tag(tag) => write!(f, "{:?}", tag),
+            ExpectedToken::Char(c) => write!(f, "{:?}", c),
+            ExpectedToken::Letter => write!(f, "an ascii letter"),
+            ExpectedToken::Digit => write!(f, "an ascii digit"),
+            ExpectedToken::NonZeroDigit => write!(f, "a non-zero ascii digit"),
+            ExpectedToken::HexDigit => write!(f, "a hexadecimal digit"),
+            ExpectedToken::OctDigit => write!(f, "an octal digit"),
+            ExpectedToken::AlphaNumeric => write!(f, "an ascii alphanumeric character"),
+            ExpectedToken::UnicodeEscape => write!(f, "a valid unicode hex sequence"),
+            ExpectedToken::Whitespace => write!(f, "whitespace"),
+            ExpectedToken::RawStringEnd => write!(f, "closing raw string sequence"),
+            ExpectedToken::BlockCommentEnd => write!(f, "end of block comment (`*/`)"),
+            ExpectedToken::Eof => write!(f, "eof"),
+            ExpectedToken::Something => write!(f, "not eof"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Error {
+    pub kind: ErrorKind,
+    pub context: Option<Box<ErrorContext>>,
+}
+
+impl Error {
+    /// Set locations for this error, if they are `None`.
+    /// Keeps already set locations.
+    pub fn context_loc(self, start: Location, end: Location) -> Self {
+        let mut context = self.context.unwrap_or_default();
+        context.start_end.get_or_insert((start, end));
+
+        Error {
+            kind: self.kind,
+            context: Some(context),
+        }
+    }
+
+    /// Set file name for this error, if they are `None`.
+    /// Keeps already set file name.
+    pub fn context_file_name(self, file_name: String) -> Self {
+        let mut context = self.context.unwrap_or_default();
+        context.file_name.get_or_insert(file_name);
+
+        Error {
+            kind: self.kind,
+            context: Some(context),
+        }
+    }
+
+    /// Set file content for this error, if they are `None`.
+    /// Keeps already set file contents.
+    ///
+    /// With the `tiny` feature, the content isn't kept, errors are rendered
+    /// without the lines of the document.
+    pub fn context_file_content(self, file_content: String) -> Self {
+        if cfg!(feature = "tiny") {
+            return self;
+        }
+
+        let mut context = self.context.unwrap_or_default();
+        context.file_content.get_or_insert(file_content);
+
+        Error {
+            kind: self.kind,
+            context: Some(context),
+        }
+    }
+
+    /// Adds a [`Label`] at another location the error involves.
+    pub fn context_label(self, start: Location, end: Location, message: String) -> Self {
+        let mut context = self.context.unwrap_or_default();
+        context.labels.push(Label {
+            start,
+            end,
+            message,
+        });
+
+        Error {
+            kind: self.kind,
+            context: Some(context),
+        }
+    }
+
+    /// The other locations the error involves, see [`Error::context_label`]
+    pub fn labels(&self) -> &[Label] {
+        self.context.as_ref().map_or(&[], |c| &c.labels)
+    }
+
+    /// The tokens the parser expected at the error location; empty for
+    /// errors other than parsing errors
+    pub fn expected_tokens(&self) -> Vec<ExpectedToken> {
+        self.context
+            .as_ref()
+            .map(|c| c.expected.clone())
+            .unwrap_or_default()
+    }
+
+    /// Whether the parser could have backtracked from this error; `None` for
+    /// errors other than parsing errors
+    pub fn severity(&self) -> Option<Severity> {
+        self.context.as_ref().and_then(|c| c.severity)
+    }
+
+    /// The branches the parser tried and the contexts they failed in, e.g.
+    /// for diagnostics with a label at every location involved; `None` for
+    /// errors other than parsing errors
+    ///
+    /// Its locations count columns in chars, also after
+    /// [`with_column_mode`](Self::with_column_mode).
+    #[cfg(feature = "utf8_parser")]
+    pub fn error_tree(&self) -> Option<&ErrorTree<Location>> {
+        self.context.as_ref().and_then(|c| c.error_tree.as_deref())
+    }
+
+    /// This error with the columns of its locations in `source` counted as
+    /// `mode` counts them, see [`Location::with_column_mode`]
+    pub fn with_column_mode(mut self, mode: ColumnMode, source: &str) -> Self {
+        if let Some(context) = self.context.as_mut() {
+            let index = LineIndex::new(source);
+            let spans = context
+                .start_end
+                .iter_mut()
+                .map(|(start, end)| (start, end))
+                .chain(
+                    context
+                        .labels
+                        .iter_mut()
+                        .map(|label| (&mut label.start, &mut label.end)),
+                );
+            for (start, end) in spans {
+                *start = start.with_column_mode(mode, &index);
+                *end = end.with_column_mode(mode, &index);
+            }
+        }
+
+        self
+    }
+
+    /// An [`InvalidUtf8`](ErrorKind::InvalidUtf8) error at the byte `offset`
+    pub fn invalid_utf8(bytes: &[u8], offset: usize) -> Self {
+        let before = &bytes[..offset];
+        let line_start = before
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |i| i + 1);
+        let start = Location::clamped(
+            before.iter().filter(|&&b| b == b'\n').count() + 1,
+            // Continuation bytes don't start a new char
+            before[line_start..]
+                .iter()
+                .filter(|&&b| b & 0xC0 != 0x80)
+                .count()
+                + 1,
+        );
+        let end = start.add_columns(1);
+
+        Error {
+            kind: ErrorKind::InvalidUtf8,
+            context: None,
+        }
+        .context_loc(start, end)
+        .context_file_content(String::from_utf8_lossy(bytes).into_owned())
+    }
+
+    /// Set locations for this error, if they are `None`.
+    /// Keeps already set locations.
+    pub fn start(&self) -> Option<Location> {
+        self.context
+            .as_ref()
+            .and_then(|c| c.start_end)
+            .map(|se| se.0)
+    }
+
+    /// Set locations for this error, if they are `None`.
+    /// Keeps already set locations.
+    pub fn end(&self) -> Option<Location> {
+        self.context
+            .as_ref()
+            .and_then(|c| c.start_end)
+            .map(|se| se.1)
+    }
+
+    /// What failed, e.g. to handle syntax errors differently from values of
+    /// the wrong type
+    pub fn class(&self) -> ErrorClass {
+        self.kind.class()
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error {
+            kind: ErrorKind::IoError(e.to_string()),
+            context: None,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::de::Error for Error {
+    fn custom<T>(msg: T) -> Self
+    where
+        T: Display,
+    {
+        Error {
+            kind: ErrorKind::Deserialize(msg.to_string()),
+            context: None,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::ser::Error for Error {
+    fn custom<T>(msg: T) -> Self
+    where
+        T: Display,
+    {
+        Error {
+            kind: ErrorKind::Serialize(msg.to_string()),
+            context: None,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // TODO: any way to do this more elegantly?
+        match self.context.as_ref() {
+            Some(context) => match (
+                context.start_end.as_ref(),
+                context.file_name.as_ref(),
+                context.file_content.as_ref(),
+            ) {
+                (Some((start, _)), _, _) => {
+                    write!(f, "error at {}: {}", start, self.kind)
+                }
+                (_, _, _) => {
+                    write!(f, "error: {}", self.kind)
+                }
+            },
+            None => write!(f, "error: {}", self.kind),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// How many chars of a line [`print_error`] shows around the error location
+pub const DEFAULT_SNIPPET_WIDTH: usize = 120;
+
+pub fn print_error(e: &Error) -> std::io::Result<()> {
+    write_error(&mut stderr().lock(), e)
+}
+
+/// Writes `e` the way [`print_error`] prints it, e.g. to buffer the output
+pub fn write_error(f: &mut impl Write, e: &Error) -> std::io::Result<()> {
+    write_error_with_width(f, e, DEFAULT_SNIPPET_WIDTH)
+}
+
+/// Like [`write_error`], but shows `width` chars of long lines around the
+/// error location, with `…` where they are cut off
+///
+/// Long lines of the message, e.g. quoting a huge string, are cut down to
+/// `width` chars as well, but no fewer than 200, keeping their start and end.
+///
+/// Use `usize::MAX` to always show whole lines.
+pub fn write_error_with_width(f: &mut impl Write, e: &Error, width: usize) -> std::io::Result<()> {
+    write_error_with_format(f, e, ErrorFormat::LATEST, width)
+}
+
+/// A version of the text [`write_error`] renders errors as
+///
+/// Rendered errors are meant for people. Tools should read the fields of
+/// [`Error`] instead, e.g. [`Error::class`], [`Error::kind`](Error),
+/// [`Error::start`], [`Error::end`] and [`Error::expected_tokens`]. Tools
+/// that parse rendered errors anyway can pin a version with
+/// [`write_error_with_format`] (or `ron validate --error-format`): messages
+/// are always in English, and the layout of a version stays the same, so
+/// changes to it come as a new version instead of breaking such tools
+/// silently.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum ErrorFormat {
+    /// `error: <message>`, then `--> <file>:<line>:<column>` and the lines
+    /// of the error with `^` under it
+    V1,
+    /// Like [`V1`](Self::V1), followed by the line of each [`Label`] with
+    /// `-` under its location and its message
+    V2,
+}
+
+impl ErrorFormat {
+    /// The version [`write_error`] uses
+    pub const LATEST: Self = ErrorFormat::V2;
+
+    pub fn variants() -> [&'static str; 2] {
+        ["1", "2"]
+    }
+}
+
+impl Display for ErrorFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErrorFormat::V1 => write!(f, "1"),
+            ErrorFormat::V2 => write!(f, "2"),
+        }
+    }
+}
+
+impl FromStr for ErrorFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "1" => Ok(ErrorFormat::V1),
+            "2" => Ok(ErrorFormat::V2),
+            _ => Err(format!(
+                "valid values: {}",
+                Self::variants().to_vec().join(", ")
+            )),
+        }
+    }
+}
+
+/// Like [`write_error_with_width`], but in the given version of the format
+pub fn write_error_with_format(
+    f: &mut impl Write,
+    e: &Error,
+    format: ErrorFormat,
+    width: usize,
+) -> std::io::Result<()> {
+    match format {
+        ErrorFormat::V1 => write_error_snippets(f, e, width, &[]),
+        ErrorFormat::V2 => write_error_snippets(f, e, width, e.labels()),
+    }
+}
+
+/// Writes `e` on one line, without the lines of the document, which the
+/// `tiny` feature doesn't keep
+#[cfg(feature = "tiny")]
+fn write_error_snippets(
+    f: &mut impl Write,
+    e: &Error,
+    _width: usize,
+    _labels: &[Label],
+) -> std::io::Result<()> {
+    match e.context.as_ref().and_then(|c| c.file_name.as_ref()) {
+        Some(file_name) => writeln!(f, "file \"{}\": {}", file_name, e),
+        None => writeln!(f, "{}", e),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    ExpectedBool,
+    ExpectedString,
+    ExpectedStrGotEscapes,
+    ExpectedList,
+
+    ParseError(String),
+
+    /// More than one `#![enable(..)]` attribute
+    DuplicateEnableAttribute,
+    /// An extension enabled more than once
+    DuplicateExtension(String),
+    /// More than one `#![version(..)]` attribute
+    DuplicateVersionAttribute,
+    /// An extension that is neither built in nor registered
+    UnknownExtension(String),
+    /// Syntax of an extension that isn't enabled
+    ExtensionNotEnabled(String),
+    /// A literal like `12px` that no enabled extension supports
+    UnsupportedLiteral(String),
+    /// Attributes have to come before the expression
+    AttributeAfterExpression,
+    /// `true` or `false` as the name of a struct, variant or field, e.g.
+    /// `true(1)` or `(true: 1)`
+    KeywordAsName(String),
+    /// Alternatives that didn't match read more than this many bytes, see
+    /// [`Input::with_backtrack_budget`](crate::utf8_parser::Input::with_backtrack_budget)
+    BacktrackBudgetExceeded(usize),
+    /// Values nested deeper than this, see
+    /// [`Input::with_max_depth`](crate::utf8_parser::Input::with_max_depth)
+    NestingTooDeep(usize),
+    /// A float where only integers are allowed, see
+    /// [`Input::with_deny_floats`](crate::utf8_parser::Input::with_deny_floats)
+    FloatNotAllowed,
+    /// A float that isn't finite, e.g. because it's too large for `f64`, see
+    /// [`Input::with_deny_non_finite`](crate::utf8_parser::Input::with_deny_non_finite)
+    NonFiniteFloat,
+    /// Bytes that aren't UTF-8 where text is expected
+    InvalidUtf8,
+    /// Content that looks binary, e.g. an image, with the byte offset of its
+    /// first NUL byte or invalid UTF-8 sequence
+    NotText(usize),
+    /// A document of a newer version than the latest one migrations upgrade
+    /// to, see [`Migrations`](crate::migrate::Migrations)
+    UnsupportedVersion {
+        version: u64,
+        latest: u64,
+    },
+    /// No migration upgrades documents of this version to the next one
+    MissingMigration(u64),
+
+    IoError(String),
+    /// A value that doesn't fit the type it's deserialized into, e.g. from
+    /// `serde::de::Error::custom`
+    Deserialize(String),
+    /// A value that can't be written as RON, e.g. a non-finite float
+    Serialize(String),
+    Custom(String),
+}
+
+impl ErrorKind {
+    pub fn class(&self) -> ErrorClass {
+        match self {
+            ErrorKind::ParseError(_)
+            | ErrorKind::DuplicateEnableAttribute
+            | ErrorKind::DuplicateExtension(_)
+            | ErrorKind::DuplicateVersionAttribute
+            | ErrorKind::UnknownExtension(_)
+            | ErrorKind::ExtensionNotEnabled(_)
+            | ErrorKind::UnsupportedLiteral(_)
+            | ErrorKind::AttributeAfterExpression
+            | ErrorKind::KeywordAsName(_)
+            | ErrorKind::BacktrackBudgetExceeded(_)
+            | ErrorKind::NestingTooDeep(_)
+            | ErrorKind::FloatNotAllowed
+            | ErrorKind::NonFiniteFloat => ErrorClass::Parse,
+            ErrorKind::ExpectedBool
+            | ErrorKind::ExpectedString
+            | ErrorKind::ExpectedStrGotEscapes
+            | ErrorKind::ExpectedList
+            | ErrorKind::UnsupportedVersion { .. }
+            | ErrorKind::MissingMigration(_)
+            | ErrorKind::Deserialize(_) => ErrorClass::Deserialize,
+            ErrorKind::Serialize(_) => ErrorClass::Serialize,
+            ErrorKind::InvalidUtf8 | ErrorKind::NotText(_) | ErrorKind::IoError(_) => {
+                ErrorClass::Io
+            }
+            ErrorKind::Custom(_) => ErrorClass::Other,
+        }
+    }
+}
+
+/// The kind of failure of an [`Error`], see [`Error::class`]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum ErrorClass {
+    /// The input isn't valid RON, e.g. a missing `)` or an extension that
+    /// isn't enabled
+    Parse,
+    /// The input is valid RON, but doesn't match the type it's deserialized
+    /// into
+    Deserialize,
+    /// A value couldn't be serialized
+    Serialize,
+    /// The input couldn't be read, or isn't text
+    Io,
+    /// Any other error, e.g. from an extension or a tool built on this crate
+    Other,
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErrorKind::ExpectedBool => write!(f, "expected bool"),
+            ErrorKind::ExpectedStrGotEscapes => {
+                write!(f, "expected zero-copy string which doesn't support escapes")
+            }
+            ErrorKind::ExpectedString => write!(f, "expected string"),
+            ErrorKind::ExpectedList => write!(f, "expected list"),
+            ErrorKind::ParseError(e) => write!(f, "parsing error: {}", e),
+            ErrorKind::DuplicateEnableAttribute => write!(
+                f,
+                "duplicate `enable` attribute, list all extensions in the first one"
+            ),
+            ErrorKind::DuplicateExtension(name) => {
+                write!(f, "extension `{}` is already enabled", name)
+            }
+            ErrorKind::DuplicateVersionAttribute => write!(f, "duplicate `version` attribute"),
+            ErrorKind::UnknownExtension(name) => write!(f, "unknown extension `{}`", name),
+            ErrorKind::ExtensionNotEnabled(name) => write!(
+                f,
+                "extension `{}` isn't enabled, add `#![enable({})]`",
+                name, name
+            ),
+            ErrorKind::UnsupportedLiteral(literal) => {
+                write!(f, "no enabled extension supports the literal `{}`", literal)
+            }
+            ErrorKind::AttributeAfterExpression => {
+                write!(f, "attributes have to come before the expression")
+            }
+            ErrorKind::KeywordAsName(name) => write!(
+                f,
+                "`{}` is a keyword and can't name a struct, variant or field, write `r#{}` instead",
+                name, name
+            ),
+            ErrorKind::BacktrackBudgetExceeded(budget) => write!(
+                f,
+                "parsing gave up here after backtracking more than {} bytes",
+                budget
+            ),
+            ErrorKind::NestingTooDeep(max_depth) => {
+                write!(f, "values are nested more than {} deep here", max_depth)
+            }
+            ErrorKind::FloatNotAllowed => {
+                write!(f, "floats aren't allowed here, only integers")
+            }
+            ErrorKind::NonFiniteFloat => {
+                write!(f, "float is out of range, only finite floats are allowed here")
+            }
+            ErrorKind::InvalidUtf8 => write!(f, "invalid UTF-8"),
+            ErrorKind::NotText(offset) => write!(
+                f,
+                "this does not look like a text RON file (binary content at byte {})",
+                offset
+            ),
+            ErrorKind::UnsupportedVersion { version, latest } => write!(
+                f,
+                "version {} is newer than the latest supported version {}",
+                version, latest
+            ),
+            ErrorKind::MissingMigration(version) => write!(
+                f,
+                "no migration from version {} to {}",
+                version,
+                version + 1
+            ),
+            ErrorKind::IoError(e) => write!(f, "io error: {}", e),
+            ErrorKind::Deserialize(s) | ErrorKind::Serialize(s) | ErrorKind::Custom(s) => {
+                write!(f, "{}", s)
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "tiny"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_line() {
+        let error = Error {
+            kind: ErrorKind::ExpectedList,
+            context: None,
+        }
+        .context_loc(Location { line: 2, column: 3 }, Location { line: 2, column: 4 })
+        .context_file_content("(\n  1)".to_owned())
+        .context_file_name("a.ron".to_owned());
+        let mut out = vec![];
+        write_error(&mut out, &error).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "file \"a.ron\": error at 2:3: expected list\n"
+        );
+        assert_eq!(error.context.unwrap().file_content, None);
+    }
+}
@@ -0,0 +1,429 @@
+//! Rendering errors with the lines of the document they are in
+
+use std::{convert::TryFrom, io::Write};
+
+use super::{Error, Label};
+use crate::location::Location;
+
+/// How many chars of a line of the message are shown at least, however
+/// narrow the snippet is
+const MIN_MESSAGE_WIDTH: usize = 200;
+
+/// Writes `e` with the lines of its location, and of the `labels`
+pub(super) fn write_error_snippets(
+    f: &mut impl Write,
+    e: &Error,
+    width: usize,
+    labels: &[Label],
+) -> std::io::Result<()> {
+    let message_width = width.max(MIN_MESSAGE_WIDTH);
+    match e.context.as_ref() {
+        Some(context) => match (
+            context.start_end.as_ref(),
+            context.file_name.as_ref(),
+            context.file_content.as_ref(),
+        ) {
+            (Some((start, end)), file_name, Some(file_content)) => {
+                let max_line_col_width = labels
+                    .iter()
+                    .map(|label| label.start.line)
+                    .fold(start.line.max(end.line), u32::max)
+                    .to_string()
+                    .len();
+                let col_ws_rep = " ".repeat(max_line_col_width);
+                writeln!(
+                    f,
+                    "error: {}",
+                    elide_lines(&e.kind.to_string(), message_width)
+                )?;
+                writeln!(
+                    f,
+                    "{}--> {}:{}:{}",
+                    col_ws_rep,
+                    file_name.map(AsRef::as_ref).unwrap_or("string"),
+                    start.line,
+                    start.column
+                )?;
+
+                writeln!(f, "{} |", col_ws_rep)?;
+                let mut lines = file_content.lines().skip(start.line as usize - 1);
+                let start_line_string = start.line.to_string();
+                let start_line_padding = " ".repeat(max_line_col_width - start_line_string.len());
+
+                // Clamped columns are marked right after the end of their line,
+                // and lines past the end of the document end at its last line
+                let column = |location: &Location, after: usize| match location.column {
+                    Location::MAX => {
+                        file_content
+                            .lines()
+                            .nth((location.line as usize).saturating_sub(1))
+                            .map_or(0, |line| line.chars().count())
+                            + after
+                    }
+                    column => column as usize,
+                };
+                let (start_column, end_column) = (column(start, 1), column(end, 2));
+                let last_line = u32::try_from(file_content.lines().count()).unwrap_or(u32::MAX);
+                let end_line = end.line.min(last_line).max(start.line);
+
+                if start.line == end_line {
+                    // The first line
+                    let line = Snippet::new(
+                        lines.next().unwrap_or_default(),
+                        start_column,
+                        end_column,
+                        width,
+                    );
+                    writeln!(f, "{}{} | {}", start_line_padding, start.line, line.text)?;
+                    // it's just one line, mark the whole span with ^
+                    writeln!(
+                        f,
+                        "{} | {}{}",
+                        col_ws_rep,
+                        " ".repeat(start_column - 1 - line.shift),
+                        "^".repeat(end_column.min(line.end).saturating_sub(start_column))
+                    )?;
+                } else {
+                    // The first line
+                    let line = Snippet::new(
+                        lines.next().unwrap_or_default(),
+                        start_column,
+                        start_column + 1,
+                        width,
+                    );
+                    writeln!(f, "{}{} |   {}", start_line_padding, start.line, line.text)?;
+                    writeln!(
+                        f,
+                        "{} |  {}^",
+                        col_ws_rep,
+                        "_".repeat(start_column - 1 - line.shift),
+                    )?;
+                    for line_number in start.line + 1..=end_line {
+                        let line_nr_string = line_number.to_string();
+                        let line_padding = " ".repeat(max_line_col_width - line_nr_string.len());
+                        let line = lines.next().unwrap_or_default();
+                        let line = match line_number == end_line {
+                            true => Snippet::new(line, end_column, end_column + 1, width),
+                            false => Snippet::new(line, 1, 1, width),
+                        };
+                        writeln!(f, "{}{} | | {}", line_padding, line_nr_string, line.text)?;
+                        if line_number == end_line {
+                            writeln!(
+                                f,
+                                "{} | |{}^",
+                                col_ws_rep,
+                                "_".repeat(end_column - 1 - line.shift)
+                            )?;
+                        }
+                    }
+                }
+
+                writeln!(f, "{} |", col_ws_rep)?;
+                if start.is_clamped() || end.is_clamped() {
+                    writeln!(
+                        f,
+                        "{} = note: lines and columns are only counted up to {}",
+                        col_ws_rep,
+                        Location::MAX
+                    )?;
+                }
+                for label in labels {
+                    let line_string = label.start.line.to_string();
+                    let line = file_content
+                        .lines()
+                        .nth((label.start.line as usize).saturating_sub(1))
+                        .unwrap_or_default();
+                    // Labels spanning lines are marked until the end of the first
+                    let (start_column, end_column) = match label.start.line == label.end.line {
+                        true => (label.start.column as usize, label.end.column as usize),
+                        false => (label.start.column as usize, line.chars().count() + 1),
+                    };
+                    let line = Snippet::new(line, start_column, end_column, width);
+                    writeln!(
+                        f,
+                        "{}{} | {}",
+                        " ".repeat(max_line_col_width - line_string.len()),
+                        line_string,
+                        line.text
+                    )?;
+                    writeln!(
+                        f,
+                        "{} | {}{} {}",
+                        col_ws_rep,
+                        " ".repeat(start_column - 1 - line.shift),
+                        "-".repeat(end_column.min(line.end).saturating_sub(start_column).max(1)),
+                        elide_lines(&label.message, message_width)
+                    )?;
+                    writeln!(f, "{} |", col_ws_rep)?;
+                }
+
+                Ok(())
+            }
+            (_, Some(file_name), _) => writeln!(
+                f,
+                "{}",
+                elide_lines(&format!("file \"{}\": {}", file_name, e), message_width)
+            ),
+            _ => writeln!(f, "{}", elide_lines(&e.to_string(), message_width)),
+        },
+        _ => writeln!(f, "{}", elide_lines(&e.to_string(), message_width)),
+    }
+}
+
+/// The part of a line shown in an error snippet
+struct Snippet {
+    text: String,
+    /// How many columns `text` is shifted left by cutting off the start
+    shift: usize,
+    /// The column after the last one in `text`, if the line is cut off there
+    end: usize,
+}
+
+impl Snippet {
+    /// Cuts `line` down to `width` chars around the columns `start..end`
+    fn new(line: &str, start: usize, end: usize, width: usize) -> Self {
+        let len = line.chars().count();
+        if len <= width {
+            return Snippet {
+                text: line.to_owned(),
+                shift: 0,
+                end: usize::MAX,
+            };
+        }
+
+        // Center the span, but always show where it starts
+        let middle = (start + end) / 2;
+        let from = middle
+            .saturating_sub(width / 2)
+            .min(start - 1)
+            .min(len - width);
+        let to = from + width;
+
+        let mut text = String::new();
+        if from > 0 {
+            text.push('…');
+        }
+        text.extend(line.chars().skip(from).take(width));
+        if to < len {
+            text.push('…');
+        }
+
+        Snippet {
+            text,
+            shift: from.saturating_sub(1),
+            // The column after the end of the line can still be marked
+            end: if to < len { to + 1 } else { usize::MAX },
+        }
+    }
+}
+
+/// `text` with the middle of lines longer than `width` chars replaced by `…`
+fn elide_lines(text: &str, width: usize) -> String {
+    text.split('\n')
+        .map(|line| {
+            let len = line.chars().count();
+            if len <= width {
+                return line.to_owned();
+            }
+
+            let head = width / 2;
+            let mut elided = line.chars().take(head).collect::<String>();
+            elided.push('…');
+            elided.extend(line.chars().skip(len - (width - head)));
+
+            elided
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::{
+            write_error, write_error_with_format, write_error_with_width, ErrorFormat, ErrorKind,
+            DEFAULT_SNIPPET_WIDTH,
+        },
+        location::Location,
+    };
+
+    fn render(source: &str, start: (u32, u32), end: (u32, u32), width: usize) -> String {
+        let location = |(line, column)| Location { line, column };
+        let error = Error {
+            kind: ErrorKind::ExpectedList,
+            context: None,
+        }
+        .context_loc(location(start), location(end))
+        .context_file_content(source.to_owned());
+        let mut out = vec![];
+        write_error_with_width(&mut out, &error, width).unwrap();
+
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn long_lines_are_truncated() {
+        let source = format!("[{}true{}]", "1, ".repeat(1000), ", 2".repeat(1000));
+        let snippet = render(&source, (1, 3002), (1, 3006), 20);
+        assert_eq!(
+            snippet,
+            "error: expected list\n --> string:1:3002\n  |\n\
+             1 | … 1, 1, true, 2, 2, 2…\n  |         ^^^^\n  |\n"
+        );
+
+        // Short lines and the start of long ones aren't cut off in front
+        assert_eq!(
+            render("[1, 2]", (1, 5), (1, 6), 20),
+            "error: expected list\n --> string:1:5\n  |\n1 | [1, 2]\n  |     ^\n  |\n"
+        );
+        assert!(render(&source, (1, 1), (1, 2), 20).contains("1 | [1, 1, 1, 1, 1, 1, 1…\n  | ^\n"));
+
+        // Multiple lines are cut around the start and end
+        let source = format!(
+            "{}[\n{}\n]{}",
+            "a".repeat(50),
+            "b".repeat(50),
+            "c".repeat(50)
+        );
+        assert_eq!(
+            render(&source, (1, 51), (3, 2), 10),
+            "error: expected list\n --> string:1:51\n  |\n\
+             1 |   …aaaaaaaaa[\n  |  __________^\n\
+             2 | | bbbbbbbbbb…\n\
+             3 | | ]ccccccccc…\n  | |_^\n  |\n"
+        );
+    }
+
+    #[test]
+    fn huge_lines_and_messages_are_elided() {
+        let huge = "a".repeat(1_000_000);
+        let source = format!("(name: \"{}\")", huge);
+        let render = |kind: ErrorKind, start: u32, end: u32| {
+            let location = |column| Location { line: 1, column };
+            let error = Error {
+                kind,
+                context: None,
+            }
+            .context_loc(location(start), location(end))
+            .context_file_content(source.clone());
+            let mut out = vec![];
+            write_error_with_width(&mut out, &error, 20).unwrap();
+
+            String::from_utf8(out).unwrap()
+        };
+
+        // The whole string, quoted in the message too
+        let message = format!("invalid type: string \"{}\", expected u8", huge);
+        let snippet = render(ErrorKind::Custom(message), 8, 1_000_010);
+        let (message, snippet) = snippet.split_once('\n').unwrap();
+        assert!(message.starts_with("error: invalid type: string \"aaa"));
+        assert!(message.contains("aaa…aaa") && message.ends_with("aaa\", expected u8"));
+        assert_eq!(
+            message.chars().count(),
+            "error: ".len() + MIN_MESSAGE_WIDTH + 1
+        );
+        assert_eq!(
+            snippet,
+            " --> string:1:8\n  |\n1 | …\"aaaaaaaaaaaaaaaaaaa…\n  |  ^^^^^^^^^^^^^^^^^^^^\n  |\n"
+        );
+
+        // Right after the end of a huge line, e.g. a missing closing quote
+        let snippet = render(ErrorKind::ExpectedString, 1_000_011, 1_000_012);
+        assert!(snippet.ends_with("1 | …aaaaaaaaaaaaaaaaaa\")\n  |                      ^\n  |\n"));
+
+        let error = Error {
+            kind: ErrorKind::UnknownExtension(huge),
+            context: None,
+        }
+        .context_file_name("a.ron".to_owned());
+        let mut out = vec![];
+        write_error_with_width(&mut out, &error, 20).unwrap();
+        let message = String::from_utf8(out).unwrap();
+        assert!(message.starts_with("file \"a.ron\": error: unknown extension `aaa"));
+        assert!(message.contains("aaa…aaa") && message.ends_with("aaa`\n"));
+        assert_eq!(message.chars().count(), MIN_MESSAGE_WIDTH + 2);
+    }
+
+    #[test]
+    fn clamped_locations() {
+        // As if the line were longer than `Location::MAX` chars
+        let max = Location::MAX;
+        assert_eq!(
+            render("[true]", (1, max), (1, max), DEFAULT_SNIPPET_WIDTH),
+            format!(
+                "error: expected list\n --> string:1:{}\n  |\n1 | [true]\n  |       ^\n  |\n  \
+                 = note: lines and columns are only counted up to {}\n",
+                max, max
+            )
+        );
+        assert!(render("[\ntrue]", (1, 2), (max, 3), 20).contains("= note:"));
+    }
+
+    #[test]
+    fn labels() {
+        let location = |line, column| Location { line, column };
+        let error = Error {
+            kind: ErrorKind::ExpectedList,
+            context: None,
+        }
+        .context_loc(location(10, 1), location(10, 2))
+        .context_file_content(format!("{}[1,\n  2\n", "\n".repeat(8)))
+        .context_label(location(9, 1), location(9, 2), "opened here".to_owned())
+        .context_label(location(9, 2), location(10, 2), "spans lines".to_owned());
+        let mut out = vec![];
+        write_error(&mut out, &error).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "error: expected list\n  --> string:10:1\n   |\n10 |   2\n   | ^\n   |\n \
+             9 | [1,\n   | - opened here\n   |\n \
+             9 | [1,\n   |  -- spans lines\n   |\n"
+        );
+    }
+
+    /// Changing any of these means the layout changed, which needs a new
+    /// [`ErrorFormat`] version
+    #[test]
+    fn format_v1_is_stable() {
+        let v1 = |error: &Error| {
+            let mut out = vec![];
+            write_error_with_format(&mut out, error, ErrorFormat::V1, DEFAULT_SNIPPET_WIDTH)
+                .unwrap();
+
+            String::from_utf8(out).unwrap()
+        };
+        let location = |line, column| Location { line, column };
+        let error = Error {
+            kind: ErrorKind::ExtensionNotEnabled("implicit_some".to_owned()),
+            context: None,
+        };
+
+        assert_eq!(
+            v1(&error),
+            "error: extension `implicit_some` isn't enabled, add `#![enable(implicit_some)]`\n"
+        );
+        let error = error.context_file_name("a.ron".to_owned());
+        assert_eq!(
+            v1(&error),
+            "file \"a.ron\": error: extension `implicit_some` isn't enabled, add \
+             `#![enable(implicit_some)]`\n"
+        );
+        let error = error
+            .context_loc(location(9, 4), location(10, 2))
+            .context_file_content(format!("{}(\n  a: 1,\n)\n", "\n".repeat(8)));
+        assert_eq!(
+            v1(&error),
+            "error: extension `implicit_some` isn't enabled, add `#![enable(implicit_some)]`\n  \
+             --> a.ron:9:4\n   |\n 9 |   (\n   |  ___^\n10 | |   a: 1,\n   | |_^\n   |\n"
+        );
+        assert_eq!("1".parse(), Ok(ErrorFormat::V1));
+        assert_eq!("2".parse(), Ok(ErrorFormat::V2));
+
+        // V1 leaves out labels
+        let error = error.context_label(location(1, 1), location(1, 2), "here".to_owned());
+        assert!(!v1(&error).contains("here"));
+        assert_eq!(ErrorFormat::LATEST.to_string(), "2");
+        assert!("3".parse::<ErrorFormat>().is_err());
+    }
+}
@@ -0,0 +1,205 @@
+//! Adapters for `#[serde(with = "..")]` writing times the way RON documents
+//! usually do, e.g. `timeout: 1.5` instead of `timeout: (secs: 1, nanos:
+//! 500000000)`
+//!
+//! ```
+//! use std::time::{Duration, SystemTime};
+//!
+//! use ron_reboot::{from_str_serde, helpers, to_string_serde};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Deserialize, Serialize)]
+//! struct Spawner {
+//!     #[serde(with = "helpers::duration_secs")]
+//!     interval: Duration,
+//!     #[serde(with = "helpers::duration_tuple")]
+//!     cooldown: Duration,
+//!     #[serde(with = "helpers::system_time_secs")]
+//!     created: SystemTime,
+//! }
+//!
+//! let spawner: Spawner =
+//!     from_str_serde("(interval: 1.5, cooldown: (2, 250000000), created: 1700000000)").unwrap();
+//! assert_eq!(spawner.interval, Duration::from_millis(1500));
+//! assert_eq!(spawner.cooldown, Duration::from_millis(2250));
+//! assert_eq!(
+//!     to_string_serde(&spawner).unwrap(),
+//!     "(interval:1.5,cooldown:(2,250000000),created:1700000000)"
+//! );
+//! ```
+//!
+//! Seconds are written as integers if they are whole, so they can be read
+//! with the `integers_only` feature.
+
+use std::{
+    fmt,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{
+    de::{Error, Unexpected, Visitor},
+    Deserializer, Serializer,
+};
+
+/// A [`Duration`] as seconds, e.g. `30` or `1.5`
+///
+/// Fractional seconds are written as `f64`, which is exact to the nanosecond
+/// for durations up to about 100 days.
+pub mod duration_secs {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_secs(false, *duration, serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        match deserializer.deserialize_any(SecondsVisitor("a duration in seconds, e.g. `1.5`"))? {
+            (false, duration) => Ok(duration),
+            (true, _) => Err(D::Error::custom("a duration cannot be negative")),
+        }
+    }
+}
+
+/// A [`Duration`] as a tuple of seconds and nanoseconds, e.g. `(1, 500000000)`,
+/// which is exact for all durations
+pub mod duration_tuple {
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        (duration.as_secs(), duration.subsec_nanos()).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        let (secs, nanos) = <(u64, u32)>::deserialize(deserializer)?;
+        if nanos >= 1_000_000_000 {
+            return Err(D::Error::invalid_value(
+                Unexpected::Unsigned(nanos.into()),
+                &"less than 1000000000 nanoseconds",
+            ));
+        }
+
+        Ok(Duration::new(secs, nanos))
+    }
+}
+
+/// A [`SystemTime`] as seconds since the Unix epoch, e.g. `1700000000` or
+/// `1700000000.25`, negative before the epoch
+pub mod system_time_secs {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(time: &SystemTime, serializer: S) -> Result<S::Ok, S::Error> {
+        match time.duration_since(UNIX_EPOCH) {
+            Ok(duration) => serialize_secs(false, duration, serializer),
+            Err(e) => serialize_secs(true, e.duration(), serializer),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<SystemTime, D::Error> {
+        let visitor = SecondsVisitor("seconds since the Unix epoch, e.g. `1700000000`");
+        let time = match deserializer.deserialize_any(visitor)? {
+            (false, duration) => UNIX_EPOCH.checked_add(duration),
+            (true, duration) => UNIX_EPOCH.checked_sub(duration),
+        };
+
+        time.ok_or_else(|| D::Error::custom("time is out of range for `SystemTime`"))
+    }
+}
+
+/// Writes `duration` as seconds, negative if `negative`
+fn serialize_secs<S: Serializer>(
+    negative: bool,
+    duration: Duration,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let secs = duration.as_secs();
+
+    match (negative, duration.subsec_nanos()) {
+        (false, 0) => serializer.serialize_u64(secs),
+        (true, 0) if secs <= i64::MAX as u64 => serializer.serialize_i64(-(secs as i64)),
+        (false, _) => serializer.serialize_f64(duration.as_secs_f64()),
+        (true, _) => serializer.serialize_f64(-duration.as_secs_f64()),
+    }
+}
+
+/// Visits seconds as whether they are negative and their absolute value
+struct SecondsVisitor(&'static str);
+
+impl<'de> Visitor<'de> for SecondsVisitor {
+    type Value = (bool, Duration);
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+
+    fn visit_u64<E: Error>(self, v: u64) -> Result<Self::Value, E> {
+        Ok((false, Duration::from_secs(v)))
+    }
+
+    fn visit_i64<E: Error>(self, v: i64) -> Result<Self::Value, E> {
+        Ok((v < 0, Duration::from_secs(v.unsigned_abs())))
+    }
+
+    fn visit_f64<E: Error>(self, v: f64) -> Result<Self::Value, E> {
+        Duration::try_from_secs_f64(v.abs())
+            .map(|duration| (v.is_sign_negative() && duration != Duration::ZERO, duration))
+            .map_err(|_| E::invalid_value(Unexpected::Float(v), &self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::utf8_parser::serde::{from_str, to_string};
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Durations {
+        #[serde(with = "duration_secs")]
+        secs: Duration,
+        #[serde(with = "duration_tuple")]
+        tuple: Duration,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Time(#[serde(with = "system_time_secs")] SystemTime);
+
+    #[test]
+    fn durations() {
+        let durations = Durations {
+            secs: Duration::from_millis(250),
+            tuple: Duration::new(3, 7),
+        };
+        let ron = to_string(&durations).unwrap();
+        assert_eq!(ron, "(secs:0.25,tuple:(3,7))");
+        assert_eq!(from_str::<Durations>(&ron), Ok(durations));
+
+        assert_eq!(
+            from_str::<Durations>("(secs: 30, tuple: (0, 0))").map(|d| d.secs),
+            Ok(Duration::from_secs(30))
+        );
+        for invalid in [
+            "(secs: -1, tuple: (0, 0))",
+            "(secs: -0.5, tuple: (0, 0))",
+            "(secs: 1e30, tuple: (0, 0))",
+            "(secs: \"1s\", tuple: (0, 0))",
+            "(secs: 1, tuple: (0, 1000000000))",
+        ] {
+            assert!(from_str::<Durations>(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn system_times() {
+        let time = Time(UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+        assert_eq!(to_string(&time).unwrap(), "(1700000000)");
+        assert_eq!(from_str::<Time>("(1700000000)"), Ok(time));
+
+        let before = Time(UNIX_EPOCH - Duration::from_millis(1500));
+        assert_eq!(to_string(&before).unwrap(), "(-1.5)");
+        assert_eq!(from_str::<Time>("(-1.5)"), Ok(before));
+        assert_eq!(from_str::<Time>("(-0.0)"), Ok(Time(UNIX_EPOCH)));
+    }
+}
@@ -0,0 +1,229 @@
+//! Finding comments and literals in RON text without parsing it, for tools
+//! that work on documents which may not parse, or on their bytes
+//!
+//! Comments, strings and chars start and end with ASCII, so the functions
+//! scan bytes and every length they return ends on a char boundary. Each
+//! takes the text from where the comment or literal starts, and unclosed ones
+//! run to the end of it, like the parser reads them before it reports them.
+//!
+//! ```
+//! use ron_reboot::lex::comments;
+//!
+//! let source = "(a: \"// not a comment\", /* b /* nested */ */ b: 'c') // end";
+//! let found: Vec<_> = comments(source.as_bytes())
+//!     .map(|range| &source[range])
+//!     .collect();
+//! assert_eq!(found, ["/* b /* nested */ */", "// end"]);
+//! ```
+
+use std::ops::Range;
+
+/// A block comment, see [`block_comment`]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BlockComment {
+    /// Length in bytes, with the `/*` and `*/`
+    pub len: usize,
+    /// Whether other block comments are nested in it
+    pub nested: bool,
+    /// Whether it ends before the text does
+    pub closed: bool,
+}
+
+/// Whether `b` is whitespace between tokens, `[\t\n\r ]`
+pub fn is_ws(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\n' | b'\r')
+}
+
+/// Length of the line comment `s` starts with, without the line break
+pub fn line_comment_len(s: &[u8]) -> usize {
+    s.iter()
+        .position(|&b| b == b'\n' || b == b'\r')
+        .unwrap_or(s.len())
+}
+
+/// The block comment `s` starts with, which ends where the comments nested in
+/// it do
+pub fn block_comment(s: &[u8]) -> BlockComment {
+    let (mut i, mut open, mut nested) = (2, 1, false);
+    while open > 0 && i < s.len() {
+        if s[i..].starts_with(b"/*") {
+            open += 1;
+            nested = true;
+            i += 2;
+        } else if s[i..].starts_with(b"*/") {
+            open -= 1;
+            i += 2;
+        } else {
+            i += 1;
+        }
+    }
+
+    BlockComment {
+        len: i.min(s.len()),
+        nested,
+        closed: open == 0,
+    }
+}
+
+/// Length of the string or char `s` starts with, which ends at the next
+/// unescaped quote like the one it starts with
+pub fn quoted_len(s: &[u8]) -> usize {
+    let mut i = 1;
+    while i < s.len() {
+        match s[i] {
+            b'\\' => i += 2,
+            b if b == s[0] => return i + 1,
+            _ => i += 1,
+        }
+    }
+
+    s.len()
+}
+
+/// Length of the raw string `s` starts with, e.g. `r#"a "quote""#`, or `None`
+/// if it doesn't start with one
+///
+/// `s` may start with a raw identifier like `r#name` instead, which is why
+/// this is `None` rather than a length for anything but a raw string.
+pub fn raw_string_len(s: &[u8]) -> Option<usize> {
+    let hashes = s
+        .strip_prefix(b"r")?
+        .iter()
+        .take_while(|&&b| b == b'#')
+        .count();
+    if s.get(1 + hashes) != Some(&b'"') {
+        return None;
+    }
+
+    let content_start = 2 + hashes;
+    let len = s[content_start..]
+        .windows(1 + hashes)
+        .position(|w| w[0] == b'"' && w[1..].iter().all(|&b| b == b'#'))
+        .map_or(s.len(), |i| content_start + i + 1 + hashes);
+
+    Some(len)
+}
+
+/// Length of the whitespace and comments `s` starts with
+pub fn trivia_len(s: &[u8]) -> usize {
+    let mut i = 0;
+    loop {
+        let rest = &s[i..];
+        i += match rest {
+            [b, ..] if is_ws(*b) => 1,
+            [b'/', b'/', ..] => line_comment_len(rest),
+            [b'/', b'*', ..] => block_comment(rest).len,
+            _ => return i,
+        };
+    }
+}
+
+/// The byte ranges of the comments in `s`, skipping over strings and chars
+pub fn comments(s: &[u8]) -> Comments<'_> {
+    Comments { s, i: 0 }
+}
+
+/// Iterator of [`comments`]
+#[derive(Clone, Debug)]
+pub struct Comments<'a> {
+    s: &'a [u8],
+    i: usize,
+}
+
+impl Iterator for Comments<'_> {
+    type Item = Range<usize>;
+
+    fn next(&mut self) -> Option<Range<usize>> {
+        while self.i < self.s.len() {
+            let start = self.i;
+            let rest = &self.s[start..];
+            // `r` in the middle of an identifier can't start a raw string
+            let word_start = start == 0 || !is_ident_byte(self.s[start - 1]);
+            let (len, comment) = match rest {
+                [b'/', b'/', ..] => (line_comment_len(rest), true),
+                [b'/', b'*', ..] => (block_comment(rest).len, true),
+                [b'"' | b'\'', ..] => (quoted_len(rest), false),
+                [b'r', ..] if word_start => (raw_string_len(rest).unwrap_or(1), false),
+                _ => (1, false),
+            };
+
+            self.i = (start + len).min(self.s.len());
+            if comment {
+                return Some(start..self.i);
+            }
+        }
+
+        None
+    }
+}
+
+fn is_ident_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all_comments(s: &str) -> Vec<&str> {
+        comments(s.as_bytes()).map(|range| &s[range]).collect()
+    }
+
+    #[test]
+    fn block_comments() {
+        assert_eq!(
+            block_comment(b"/* a */ b"),
+            BlockComment {
+                len: 7,
+                nested: false,
+                closed: true
+            }
+        );
+        assert_eq!(
+            block_comment(b"/* a /* b */ c */ d"),
+            BlockComment {
+                len: 17,
+                nested: true,
+                closed: true
+            }
+        );
+        assert_eq!(
+            block_comment(b"/* a /* b */"),
+            BlockComment {
+                len: 12,
+                nested: true,
+                closed: false
+            }
+        );
+        // The `/` of `*/` doesn't start another comment
+        assert_eq!(block_comment(b"/**/*").len, 4);
+    }
+
+    #[test]
+    fn literals() {
+        assert_eq!(quoted_len(br#""a \" b" c"#), 8);
+        assert_eq!(quoted_len(br"'\'' c"), 4);
+        assert_eq!(quoted_len(br#""unclosed \"#), 11);
+        assert_eq!(raw_string_len(br##"r#"a "b" c"# d"##), Some(12));
+        assert_eq!(raw_string_len(br#"r"a\" b"#), Some(5));
+        assert_eq!(raw_string_len(b"r#name"), None);
+        assert_eq!(raw_string_len(b"rust"), None);
+    }
+
+    #[test]
+    fn trivia() {
+        assert_eq!(trivia_len(b" \t// a\r\n/* b /* c */ */\n, d"), 24);
+        assert_eq!(trivia_len(b"d"), 0);
+        assert_eq!(trivia_len(b"/* unclosed"), 11);
+    }
+
+    #[test]
+    fn comments_outside_literals() {
+        assert_eq!(
+            all_comments("// a\r\n(b: \"/* c\", d: '\"', e: r#\"// f\"#) /* g */"),
+            ["// a", "/* g */"]
+        );
+        assert_eq!(all_comments("(r#type: \"//\") // h"), ["// h"]);
+        assert_eq!(all_comments("\"unclosed // i"), Vec::<&str>::new());
+    }
+}
@@ -1,15 +1,56 @@
+// Library code never panics on any input, see "Panics" in the README
+#![deny(unsafe_code)]
+#![cfg_attr(
+    not(test),
+    deny(clippy::panic, clippy::unwrap_used, clippy::todo, clippy::unimplemented)
+)]
+
+#[cfg(feature = "utf8_parser_serde1")]
+pub use self::utf8_parser::serde::from_bytes as from_bytes_serde;
+#[cfg(feature = "utf8_parser_serde1")]
+pub use self::utf8_parser::serde::from_file;
+#[cfg(feature = "utf8_parser_serde1")]
+pub use self::utf8_parser::serde::from_reader;
 #[cfg(feature = "utf8_parser_serde1")]
 pub use self::utf8_parser::serde::from_str as from_str_serde;
+#[cfg(feature = "utf8_parser_serde1")]
+pub use self::utf8_parser::serde::to_file;
+#[cfg(feature = "utf8_parser_serde1")]
+pub use self::utf8_parser::serde::to_string as to_string_serde;
+#[cfg(feature = "utf8_parser_serde1")]
+pub use self::utf8_parser::serde::to_writer;
+#[cfg(feature = "utf8_parser_serde1")]
+pub use self::utf8_parser::serde::RawRon;
+#[cfg(feature = "utf8_parser_serde1")]
+pub use self::utf8_parser::serde::TaggedSection;
 #[cfg(feature = "value")]
-pub use self::value::Value;
+pub use self::value::{FloatEq, StdEnums, Value};
 pub use self::{
-    error::{print_error, Error},
-    location::Location,
+    error::{
+        print_error, write_error, write_error_with_format, write_error_with_width, Error,
+        ErrorClass, ErrorContext, ErrorFormat, ErrorKind, ExpectedToken, Label, Severity,
+        DEFAULT_SNIPPET_WIDTH,
+    },
+    location::{ColumnMode, LineIndex, Location, LspPosition},
 };
 
-mod ast;
+#[cfg(feature = "alloc_counters")]
+#[allow(unsafe_code)]
+pub mod alloc_counter;
+pub mod api;
+pub mod ast;
+#[cfg(feature = "bytes_parser")]
+pub mod bytes_parser;
 mod error;
+#[cfg(feature = "utf8_parser_serde1")]
+pub mod helpers;
+pub mod lex;
 mod location;
+pub mod migrate;
+pub mod path;
+pub mod snapshot;
+pub mod sniff;
+pub mod str;
 #[cfg(feature = "utf8_parser")]
 pub mod utf8_parser;
 mod util;
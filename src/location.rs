@@ -1,24 +1,336 @@
-use std::fmt::{Display, Formatter};
+use std::{
+    convert::TryFrom,
+    fmt::{Display, Formatter},
+};
+
+#[cfg(feature = "unicode_segmentation")]
+use unicode_segmentation::UnicodeSegmentation;
 
 #[cfg(test)]
-use crate::utf8_parser::test_util::TestMockNew;
+use crate::util::TestMockNew;
 
 #[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde1_ast_derives", derive(serde::Serialize))]
 pub struct Location {
+    /// 1-based line
     pub line: u32,
-    /// UTF-8 column
+    /// 1-based column, counted in chars
     pub column: u32,
 }
 
+impl Location {
+    /// The largest line and column a `Location` holds
+    ///
+    /// Lines and columns past it, e.g. in a generated single-line document of
+    /// more than 4 GiB, are clamped to it, so a location with it is at least
+    /// that far into the document, see [`is_clamped`](Self::is_clamped).
+    pub const MAX: u32 = u32::MAX;
+
+    /// The location of a 1-based `line` and `column`, each clamped to
+    /// [`MAX`](Self::MAX)
+    pub fn clamped(line: usize, column: usize) -> Location {
+        Location {
+            line: u32::try_from(line).unwrap_or(Self::MAX),
+            column: u32::try_from(column).unwrap_or(Self::MAX),
+        }
+    }
+
+    /// Whether the line or column was too large to be counted, so that this
+    /// location is only where counting stopped
+    pub fn is_clamped(self) -> bool {
+        self.line == Self::MAX || self.column == Self::MAX
+    }
+
+    /// The location `columns` columns after this one on the same line
+    pub fn add_columns(self, columns: usize) -> Location {
+        Location {
+            line: self.line,
+            column: u32::try_from(columns)
+                .map_or(Self::MAX, |columns| self.column.saturating_add(columns)),
+        }
+    }
+
+    /// The position of this location as the Language Server Protocol counts
+    /// it, with a 0-based line and a 0-based column in UTF-16 code units
+    pub fn to_lsp(self, index: &LineIndex) -> LspPosition {
+        let (line, before) = index.line_prefix(self);
+
+        LspPosition {
+            line: u32::try_from(line - 1).unwrap_or(u32::MAX),
+            character: u32::try_from(before.chars().map(char::len_utf16).sum::<usize>())
+                .unwrap_or(u32::MAX),
+        }
+    }
+
+    /// This location with its column counted as `mode` counts them
+    ///
+    /// The parser counts columns in chars, so this should only be applied
+    /// once to the locations it reports.
+    pub fn with_column_mode(self, mode: ColumnMode, index: &LineIndex) -> Location {
+        let (line, before) = index.line_prefix(self);
+        let column = match mode {
+            ColumnMode::Chars => before.chars().count(),
+            ColumnMode::Bytes => before.len(),
+            #[cfg(feature = "unicode_segmentation")]
+            ColumnMode::Graphemes => before.graphemes(true).count(),
+        };
+
+        Location::clamped(line, column + 1)
+    }
+
+    /// The location of an LSP position, the inverse of [`to_lsp`](Self::to_lsp)
+    ///
+    /// A character inside a surrogate pair is moved to the start of its char.
+    pub fn from_lsp(position: LspPosition, index: &LineIndex) -> Location {
+        let line = index.line_starts.len().min(position.line as usize + 1);
+        let line_text = index.line_text(line);
+
+        let mut units = 0;
+        let column = line_text
+            .chars()
+            .take_while(|c| {
+                units += c.len_utf16();
+                units <= position.character as usize
+            })
+            .count();
+
+        Location::clamped(line, column + 1)
+    }
+}
+
 impl Display for Location {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}:{}", self.line, self.column)
     }
 }
 
+/// How the columns of a [`Location`] are counted
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum ColumnMode {
+    /// Chars, i.e. Unicode scalar values, as the parser counts them
+    #[default]
+    Chars,
+    /// UTF-8 bytes, for tools that index into the source
+    Bytes,
+    /// Grapheme clusters, which is what a reader sees as one character, e.g.
+    /// an `e` followed by a combining accent, for error messages
+    #[cfg(feature = "unicode_segmentation")]
+    Graphemes,
+}
+
+/// A position as the Language Server Protocol counts it
+///
+/// Unlike [`Location`], both fields are 0-based and the column is counted in
+/// UTF-16 code units.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct LspPosition {
+    pub line: u32,
+    pub character: u32,
+}
+
+/// The line starts of a source, to convert between byte offsets, [`Location`]s
+/// and [`LspPosition`]s
+///
+/// Locations past the end of their line are clamped to it, and lines past the
+/// end of the source to the last line.
+#[derive(Clone, Debug)]
+pub struct LineIndex<'a> {
+    source: &'a str,
+    /// Byte offset of the start of every line
+    line_starts: Vec<usize>,
+    /// Whether every line is ASCII, so columns are byte offsets in it
+    ascii_lines: Vec<bool>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(source: &'a str) -> Self {
+        let line_starts: Vec<usize> = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        let ascii_lines = line_starts
+            .iter()
+            .zip(line_starts.iter().skip(1).chain(Some(&source.len())))
+            .map(|(&start, &end)| source[start..end].is_ascii())
+            .collect();
+
+        LineIndex {
+            source,
+            line_starts,
+            ascii_lines,
+        }
+    }
+
+    /// The text from `start` to `end`
+    pub fn slice(&self, start: Location, end: Location) -> &'a str {
+        &self.source[self.byte_offset(start)..self.byte_offset(end)]
+    }
+
+    /// Byte offset of `location` in the source
+    pub fn byte_offset(&self, location: Location) -> usize {
+        let line = (location.line.max(1) as usize).min(self.line_starts.len());
+        let line_start = self.line_starts[line - 1];
+        let line_text = self.line_text(line);
+        let column = location.column.saturating_sub(1) as usize;
+
+        if self.ascii_lines[line - 1] {
+            return line_start + column.min(line_text.len());
+        }
+
+        line_text
+            .char_indices()
+            .nth(column)
+            .map_or(line_start + line_text.len(), |(i, _)| line_start + i)
+    }
+
+    /// The location of the char at `offset`, which is clamped to the source
+    /// and moved back to a char boundary
+    pub fn location(&self, offset: usize) -> Location {
+        let mut offset = offset.min(self.source.len());
+        while !self.source.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        let line = self.line_of(offset);
+
+        Location::clamped(
+            line + 1,
+            self.source[self.line_starts[line]..offset].chars().count() + 1,
+        )
+    }
+
+    /// The 1-based line of `location` and its text before `location`
+    fn line_prefix(&self, location: Location) -> (usize, &'a str) {
+        let offset = self.byte_offset(location);
+        let line = self.line_of(offset);
+
+        (line + 1, &self.source[self.line_starts[line]..offset])
+    }
+
+    /// 0-based line of the byte at `offset`
+    fn line_of(&self, offset: usize) -> usize {
+        self.line_starts.partition_point(|&start| start <= offset) - 1
+    }
+
+    /// The text of the 1-based `line`, without its line break
+    fn line_text(&self, line: usize) -> &'a str {
+        let start = self.line_starts[line - 1];
+        let end = self
+            .line_starts
+            .get(line)
+            .map_or(self.source.len(), |next| next - 1);
+        let text = &self.source[start..end];
+
+        text.strip_suffix('\r').unwrap_or(text)
+    }
+}
+
 #[cfg(test)]
 impl TestMockNew for Location {
     fn new_mocked() -> Self {
         Location { line: 1, column: 1 }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn location(line: u32, column: u32) -> Location {
+        Location { line, column }
+    }
+
+    fn lsp(line: u32, character: u32) -> LspPosition {
+        LspPosition { line, character }
+    }
+
+    #[test]
+    fn clamped() {
+        // Counts of a document of more than 4 GiB
+        let past_max = Location::MAX as usize + 10;
+
+        assert_eq!(Location::clamped(2, 7), location(2, 7));
+        assert!(!Location::clamped(2, 7).is_clamped());
+        assert_eq!(Location::clamped(1, past_max), location(1, Location::MAX));
+        assert_eq!(Location::clamped(past_max, 1), location(Location::MAX, 1));
+        assert!(Location::clamped(1, past_max).is_clamped());
+
+        assert_eq!(location(3, 4).add_columns(2), location(3, 6));
+        assert_eq!(
+            location(1, Location::MAX - 1).add_columns(5),
+            location(1, Location::MAX)
+        );
+        assert_eq!(
+            location(1, 1).add_columns(past_max),
+            location(1, Location::MAX)
+        );
+    }
+
+    #[test]
+    fn byte_offsets() {
+        let source = "(\n  a: \"ä\",\r\n)";
+        let index = LineIndex::new(source);
+
+        assert_eq!(index.byte_offset(location(1, 1)), 0);
+        assert_eq!(index.byte_offset(location(2, 3)), 4);
+        // After the multi-byte `ä`
+        assert_eq!(index.byte_offset(location(2, 8)), 10);
+        // Clamped to the end of the line, before `\r\n`
+        assert_eq!(index.byte_offset(location(2, 40)), 12);
+        assert_eq!(index.byte_offset(location(9, 1)), 14);
+
+        for offset in [0, 4, 8, 10, 12, 14, source.len()] {
+            assert_eq!(index.byte_offset(index.location(offset)), offset);
+        }
+        assert_eq!(index.location(8), location(2, 7));
+        // Inside `ä`
+        assert_eq!(index.location(9), location(2, 7));
+        assert_eq!(index.slice(location(2, 3), location(2, 40)), "a: \"ä\",");
+
+        // ASCII lines are clamped before `\r\n` too
+        let index = LineIndex::new("[1,\r\n2]");
+        assert_eq!(index.byte_offset(location(1, 9)), 3);
+        assert_eq!(index.slice(location(1, 2), location(2, 2)), "1,\r\n2");
+    }
+
+    #[test]
+    fn lsp_positions() {
+        let source = "(\n  a: \"ä🦀\", b: 1,\n)";
+        let index = LineIndex::new(source);
+
+        assert_eq!(location(1, 1).to_lsp(&index), lsp(0, 0));
+        // `🦀` is a surrogate pair in UTF-16
+        assert_eq!(location(2, 8).to_lsp(&index), lsp(1, 7));
+        assert_eq!(location(2, 9).to_lsp(&index), lsp(1, 9));
+        assert_eq!(location(2, 13).to_lsp(&index), lsp(1, 13));
+
+        for column in 1..=17 {
+            let location = location(2, column);
+            assert_eq!(
+                Location::from_lsp(location.to_lsp(&index), &index),
+                location
+            );
+        }
+        assert_eq!(Location::from_lsp(lsp(1, 8), &index), location(2, 8));
+        assert_eq!(Location::from_lsp(lsp(1, 99), &index), location(2, 17));
+        assert_eq!(Location::from_lsp(lsp(7, 0), &index), location(3, 1));
+    }
+
+    #[test]
+    fn column_modes() {
+        // `e\u{301}` is `é` written with a combining accent
+        let source = "[\n  \"e\u{301}ä\", x]";
+        let index = LineIndex::new(source);
+        let x = location(2, 10);
+
+        assert_eq!(x.with_column_mode(ColumnMode::Chars, &index), x);
+        assert_eq!(
+            x.with_column_mode(ColumnMode::Bytes, &index),
+            location(2, 12)
+        );
+        #[cfg(feature = "unicode_segmentation")]
+        assert_eq!(
+            x.with_column_mode(ColumnMode::Graphemes, &index),
+            location(2, 9)
+        );
+    }
+}
@@ -0,0 +1,159 @@
+//! Upgrading documents written for older versions of a format
+//!
+//! Documents state the version of their format with an attribute like
+//! `#![version(2)]`; documents without one are version
+//! [`UNVERSIONED`]. [`Migrations`] upgrades them one version at a time, so
+//! that only the latest version has to be deserialized.
+//!
+//! ```
+//! use ron_reboot::{ast::Ron, migrate::Migrations, utf8_parser::ast_from_str};
+//!
+//! /// `size: 2` became `width: 2, height: 2`
+//! fn migrate_v1_to_v2(ron: &mut Ron) {
+//!     // ...
+//! }
+//!
+//! let migrations = Migrations::new().with(1, migrate_v1_to_v2);
+//! let mut ron = ast_from_str("(size: 2)").unwrap();
+//! migrations.migrate(&mut ron).unwrap();
+//! assert_eq!(ron.version(), Some(2));
+//! ```
+
+use std::collections::BTreeMap;
+
+use crate::{
+    ast::Ron,
+    error::{Error, ErrorKind},
+};
+
+/// The version of documents without a `version` attribute
+pub const UNVERSIONED: u64 = 1;
+
+/// Upgrades a document from one version to the next
+type Migration = Box<dyn Fn(&mut Ron)>;
+
+/// The migrations from each version of a format to the next
+///
+/// The latest version is the one after the last migration. Every version
+/// from that of a document up to the latest one needs a migration.
+#[derive(Default)]
+pub struct Migrations {
+    /// The migrations by the version they upgrade from
+    migrations: BTreeMap<u64, Migration>,
+}
+
+impl Migrations {
+    /// No migrations, which leave documents of any version as they are
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `migration`, which upgrades documents of version `from` to
+    /// `from + 1`, replacing any migration from the same version
+    #[must_use]
+    pub fn with(mut self, from: u64, migration: impl Fn(&mut Ron) + 'static) -> Self {
+        self.migrations.insert(from, Box::new(migration));
+
+        self
+    }
+
+    /// The version documents are upgraded to, `None` without migrations
+    pub fn latest(&self) -> Option<u64> {
+        self.migrations.keys().next_back().map(|from| from + 1)
+    }
+
+    /// Upgrades `ron` to the [`latest`](Migrations::latest) version and sets
+    /// its `version` attribute to it
+    ///
+    /// Fails for documents newer than the latest version, and for versions
+    /// without a migration to the next one.
+    pub fn migrate(&self, ron: &mut Ron) -> Result<(), Error> {
+        let latest = match self.latest() {
+            Some(latest) => latest,
+            None => return Ok(()),
+        };
+        let version = ron.version().unwrap_or(UNVERSIONED);
+        let error = |kind| {
+            let error = Error {
+                kind,
+                context: None,
+            };
+
+            match ron.version_attribute() {
+                Some(attribute) => error.context_loc(attribute.start, attribute.end),
+                None => error,
+            }
+        };
+
+        if version > latest {
+            return Err(error(ErrorKind::UnsupportedVersion { version, latest }));
+        }
+        if let Some(missing) = (version..latest).find(|v| !self.migrations.contains_key(v)) {
+            return Err(error(ErrorKind::MissingMigration(missing)));
+        }
+
+        for (_, migration) in self.migrations.range(version..latest) {
+            migration(ron);
+        }
+        if version < latest {
+            ron.set_version(latest);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ast::{Expr, Spanned},
+        utf8_parser::ast_from_str,
+    };
+
+    fn wrap(ron: &mut Ron) {
+        let expr = ron.expr.value.take();
+        ron.expr.value = Expr::Optional(Some(Box::new(Spanned {
+            start: ron.expr.start,
+            value: expr,
+            end: ron.expr.end,
+        })));
+    }
+
+    #[test]
+    fn migrate() {
+        let migrations = Migrations::new().with(1, wrap).with(2, wrap);
+        assert_eq!(migrations.latest(), Some(3));
+
+        let mut ron = ast_from_str("5").unwrap();
+        migrations.migrate(&mut ron).unwrap();
+        assert_eq!(ron, ast_from_str("#![version(3)] Some(Some(5))").unwrap());
+
+        let mut ron = ast_from_str("#![version(2)] 5").unwrap();
+        migrations.migrate(&mut ron).unwrap();
+        assert_eq!(ron, ast_from_str("#![version(3)] Some(5)").unwrap());
+
+        // The latest version isn't changed
+        let mut ron = ast_from_str("#![version(3)] 5").unwrap();
+        migrations.migrate(&mut ron).unwrap();
+        assert_eq!(ron, ast_from_str("#![version(3)] 5").unwrap());
+
+        let mut ron = ast_from_str("#![version(4)] 5").unwrap();
+        let error = migrations.migrate(&mut ron).unwrap_err();
+        assert_eq!(
+            error.kind,
+            ErrorKind::UnsupportedVersion {
+                version: 4,
+                latest: 3
+            }
+        );
+        assert!(error.context.is_some());
+
+        let mut ron = ast_from_str("#![version(0)] 5").unwrap();
+        assert_eq!(
+            migrations.migrate(&mut ron).unwrap_err().kind,
+            ErrorKind::MissingMigration(0)
+        );
+        assert_eq!(ron.version(), Some(0));
+    }
+}
@@ -0,0 +1,404 @@
+//! Paths of values in a document, e.g. `window.width` or `layers.0.name`
+//!
+//! A path is a list of steps separated by `.`, starting at the root value,
+//! which the empty path addresses. Each step is one of:
+//!
+//! - a number without leading zeros, the element at this index of a list or
+//!   tuple, or the value of the map entry with this integer key
+//! - other text, the struct field or the value of the map entry with this
+//!   key, which may be a string, an integer, `true`, `false` or a unit
+//!   identifier like `Space`
+//! - a string literal with the escapes of RON strings, a key like the one
+//!   above; needed for keys that are empty, made of digits or contain `.`,
+//!   `"`, `\` or whitespace
+//!
+//! Struct and variant names aren't part of paths: `width` is a field of both
+//! `(width: 1)` and `Window(width: 1)`, and `0` the first element of
+//! `Circle(1.0)`. Neither is `Some(..)`, a step into an option continues in
+//! its value.
+//!
+//! ```
+//! use ron_reboot::path::{Step, ValuePath};
+//!
+//! let path: ValuePath = r#"keys."a.b".0"#.parse().unwrap();
+//! assert_eq!(
+//!     path.steps(),
+//!     [
+//!         Step::Key("keys".to_owned()),
+//!         Step::Key("a.b".to_owned()),
+//!         Step::Index(0),
+//!     ]
+//! );
+//! assert_eq!(path.to_string(), r#"keys."a.b".0"#);
+//! ```
+
+use std::{convert::TryFrom, fmt, str::FromStr};
+
+use crate::{
+    ast::{Expr, List, Spanned, Tuple, Untagged},
+    error::{Error, ErrorKind},
+    str::{escape, unescape, EscapePolicy},
+};
+
+/// One step of a [`ValuePath`]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Step {
+    /// Element of a list or tuple, or map value with an integer key
+    Index(usize),
+    /// Struct field, or map value with a string, integer, bool or unit
+    /// identifier key written like this
+    Key(String),
+}
+
+impl Step {
+    /// The step to the value of the map entry with `key`, if it is a key
+    /// paths can address
+    pub fn from_key(key: &Expr) -> Option<Step> {
+        let text = match key {
+            Expr::Integer(i) => match i128::try_from(i.clone()).map(usize::try_from) {
+                Ok(Ok(index)) => return Some(Step::Index(index)),
+                _ => i.to_string(),
+            },
+            Expr::Str(s) => (*s).to_owned(),
+            Expr::String(s) => s.clone(),
+            Expr::EscapedStr(s) => s.unescape().into_owned(),
+            Expr::Bool(b) => b.to_string(),
+            Expr::Tagged(t) if matches!(t.untagged.value, Untagged::Unit) => {
+                t.ident.value.as_str().to_owned()
+            }
+            _ => return None,
+        };
+
+        Some(Step::Key(text))
+    }
+
+    /// The child of `expr` this step leads to
+    pub fn select<'b, 'a>(&self, expr: &'b Spanned<Expr<'a>>) -> Option<&'b Spanned<Expr<'a>>> {
+        let (fields, elements) = match &expr.value {
+            Expr::Optional(Some(inner)) => return self.select(inner),
+            Expr::Map(map) => {
+                return map
+                    .entries
+                    .iter()
+                    .map(|entry| &entry.value)
+                    .find(|entry| self.matches_key(&entry.key.value))
+                    .map(|entry| &entry.value)
+            }
+            Expr::Struct(s) => (Some(s), None),
+            Expr::List(List { elements }) | Expr::Tuple(Tuple { elements }) => {
+                (None, Some(elements))
+            }
+            Expr::Tagged(t) => match &t.untagged.value {
+                Untagged::Struct(s) => (Some(s), None),
+                Untagged::Tuple(tuple) => (None, Some(&tuple.elements)),
+                Untagged::Unit => return None,
+            },
+            _ => return None,
+        };
+
+        match (self, fields, elements) {
+            (Step::Key(key), Some(s), _) => s
+                .fields
+                .iter()
+                .map(|field| &field.value)
+                .find(|field| field.key.value.as_str() == key)
+                .map(|field| &field.value),
+            (Step::Index(i), _, Some(elements)) => elements.get(*i),
+            _ => None,
+        }
+    }
+
+    fn matches_key(&self, key: &Expr) -> bool {
+        Step::from_key(key).as_ref() == Some(self)
+    }
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Step::Index(i) => write!(f, "{}", i),
+            Step::Key(key) if needs_quotes(key) => {
+                write!(f, "\"{}\"", escape(key, EscapePolicy::Minimal))
+            }
+            Step::Key(key) => write!(f, "{}", key),
+        }
+    }
+}
+
+/// Whether `key` has to be written as a string literal
+fn needs_quotes(key: &str) -> bool {
+    key.is_empty()
+        || index(key).is_some()
+        || key
+            .chars()
+            .any(|c| matches!(c, '.' | '"' | '\\') || c.is_whitespace())
+}
+
+/// The index `step` stands for, if it is a number without leading zeros
+fn index(step: &str) -> Option<usize> {
+    match step.bytes().all(|b| b.is_ascii_digit()) && (step == "0" || !step.starts_with('0')) {
+        true => step.parse().ok(),
+        false => None,
+    }
+}
+
+/// A path from the root value of a document to one of its values, see the
+/// [module docs](self) for the syntax
+///
+/// [`Display`](fmt::Display) writes the path so that parsing it gives the
+/// same steps.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct ValuePath {
+    steps: Vec<Step>,
+}
+
+impl ValuePath {
+    /// The empty path, which addresses the root value
+    pub fn root() -> Self {
+        ValuePath::default()
+    }
+
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    /// Whether this is the path of the root value
+    pub fn is_root(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    pub fn push(&mut self, step: Step) {
+        self.steps.push(step);
+    }
+
+    /// This path extended by `step`
+    #[must_use]
+    pub fn join(&self, step: Step) -> Self {
+        let mut path = self.clone();
+        path.push(step);
+
+        path
+    }
+
+    /// The path of the parent and the last step, unless this is the root
+    pub fn split_last(&self) -> Option<(ValuePath, &Step)> {
+        let (last, parent) = self.steps.split_last()?;
+
+        Some((
+            ValuePath {
+                steps: parent.to_vec(),
+            },
+            last,
+        ))
+    }
+
+    /// The value at this path, starting at `root`
+    ///
+    /// The error names the first prefix of this path without a value and is
+    /// located at the last value found.
+    pub fn resolve<'b, 'a>(
+        &self,
+        root: &'b Spanned<Expr<'a>>,
+    ) -> Result<&'b Spanned<Expr<'a>>, Error> {
+        let mut expr = root;
+        for (i, step) in self.steps.iter().enumerate() {
+            expr = step.select(expr).ok_or_else(|| {
+                let prefix = ValuePath {
+                    steps: self.steps[..=i].to_vec(),
+                };
+
+                custom(format!("no value at `{}`", prefix)).context_loc(expr.start, expr.end)
+            })?;
+        }
+
+        Ok(expr)
+    }
+}
+
+impl FromStr for ValuePath {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: &str| custom(format!("invalid path `{}`: {}", s, reason));
+
+        let mut steps = vec![];
+        let mut rest = s;
+        if s.is_empty() {
+            return Ok(ValuePath { steps });
+        }
+        loop {
+            let (step, after) = match rest.strip_prefix('"') {
+                Some(quoted) => {
+                    let end = closing_quote(quoted).ok_or_else(|| invalid("unclosed quote"))?;
+
+                    (
+                        Step::Key(unescape(&quoted[..end]).into_owned()),
+                        &quoted[end + 1..],
+                    )
+                }
+                None => {
+                    let end = rest.find('.').unwrap_or(rest.len());
+                    let step = &rest[..end];
+                    if step.is_empty() {
+                        return Err(invalid("empty step, keys can be written as `\"\"`"));
+                    }
+                    if step.contains(['"', '\\']) {
+                        return Err(invalid("quotes and backslashes need a quoted step"));
+                    }
+
+                    let step = match index(step) {
+                        Some(i) => Step::Index(i),
+                        None => Step::Key(step.to_owned()),
+                    };
+
+                    (step, &rest[end..])
+                }
+            };
+            steps.push(step);
+
+            if after.is_empty() {
+                break;
+            }
+            rest = after
+                .strip_prefix('.')
+                .ok_or_else(|| invalid("expected `.` after a quoted step"))?;
+        }
+
+        Ok(ValuePath { steps })
+    }
+}
+
+/// Byte offset of the `"` ending the string literal `s` starts with
+fn closing_quote(s: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        match (escaped, c) {
+            (false, '"') => return Some(i),
+            (false, '\\') => escaped = true,
+            _ => escaped = false,
+        }
+    }
+
+    None
+}
+
+impl fmt::Display for ValuePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, step) in self.steps.iter().enumerate() {
+            if i > 0 {
+                write!(f, ".")?;
+            }
+            write!(f, "{}", step)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl From<Vec<Step>> for ValuePath {
+    fn from(steps: Vec<Step>) -> Self {
+        ValuePath { steps }
+    }
+}
+
+fn custom(message: String) -> Error {
+    Error {
+        kind: ErrorKind::Custom(message),
+        context: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(key: &str) -> Step {
+        Step::Key(key.to_owned())
+    }
+
+    #[test]
+    fn parse() {
+        let path = |s: &str| s.parse::<ValuePath>().map(|path| path.steps);
+
+        assert_eq!(path(""), Ok(vec![]));
+        assert_eq!(
+            path("layers.0.name"),
+            Ok(vec![key("layers"), Step::Index(0), key("name")])
+        );
+        assert_eq!(
+            path("a.-1.01.true"),
+            Ok(vec![key("a"), key("-1"), key("01"), key("true")])
+        );
+        assert_eq!(
+            path(r#""a.b"."0".""."\"\\""#),
+            Ok(vec![key("a.b"), key("0"), key(""), key("\"\\")])
+        );
+        assert_eq!(path("ä b"), Ok(vec![key("ä b")]));
+
+        for invalid in ["a..b", ".a", "a.", r#""a"b"#, r#""a"#, r#"a"b"#, r"a\b"] {
+            assert!(path(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn format_round_trips() {
+        let paths = [
+            vec![],
+            vec![key("window"), key("width")],
+            vec![Step::Index(3), key("-1"), key("01")],
+            vec![key("a.b"), key("0"), key(""), key("x y"), key("\"\\\n")],
+        ];
+
+        for steps in paths {
+            let path = ValuePath::from(steps);
+            assert_eq!(path.to_string().parse(), Ok(path.clone()), "{}", path);
+        }
+        assert_eq!(
+            ValuePath::from(vec![key("a.b"), Step::Index(0), key("0"), key("")]).to_string(),
+            r#""a.b".0."0"."""#
+        );
+    }
+
+    #[cfg(feature = "utf8_parser")]
+    #[test]
+    fn resolve() {
+        use crate::utf8_parser::ast_from_str;
+
+        let ron = ast_from_str(
+            r#"Config(
+    window: Some(Window(width: 800)),
+    shapes: [Circle(1.5), (2, 3)],
+    keys: {"a.b": 1, 2: 2, -1: 3, Space: 4, true: 5},
+)"#,
+        )
+        .unwrap();
+        let get = |path: &str| {
+            path.parse::<ValuePath>()
+                .unwrap()
+                .resolve(&ron.expr)
+                .map(|expr| expr.value.clone())
+        };
+
+        assert!(matches!(get("window.width"), Ok(Expr::Integer(_))));
+        assert!(matches!(get("shapes.0.0"), Ok(Expr::Decimal(_))));
+        assert!(matches!(get("shapes.1.1"), Ok(Expr::Integer(_))));
+        for key in [
+            r#"keys."a.b""#,
+            "keys.2",
+            "keys.-1",
+            "keys.Space",
+            "keys.true",
+        ] {
+            assert!(matches!(get(key), Ok(Expr::Integer(_))), "{}", key);
+        }
+
+        let e = get("window.depth.x").unwrap_err();
+        assert_eq!(
+            e.kind,
+            ErrorKind::Custom("no value at `window.depth`".to_owned())
+        );
+        assert_eq!(e.start().map(|start| start.line), Some(2));
+        assert!(get(r#"keys."2""#).is_err());
+        assert!(get("shapes.2").is_err());
+    }
+}
@@ -0,0 +1,250 @@
+//! Stable text of documents and errors for snapshot tests, e.g. with
+//! `insta::assert_snapshot!`
+//!
+//! [`ast`] writes one line per node of the tree, indented by its depth, with
+//! the span of the node as `line:col..line:col`. Strings are written with
+//! their escapes resolved and integers with their sign, so the text only
+//! changes when the document does, not when the parser stores a value
+//! differently. [`ast_without_spans`] leaves the spans out, for snapshots
+//! that shouldn't change when a document is only reformatted.
+//!
+//! ```
+//! use ron_reboot::{snapshot, utf8_parser::ast_from_str};
+//!
+//! let ron = ast_from_str("Window(title: \"a\\tb\", size: (800, 600))").unwrap();
+//! assert_eq!(
+//!     snapshot::ast_without_spans(&ron),
+//!     r#"struct Window
+//!   field title
+//!     string "a\tb"
+//!   field size
+//!     tuple
+//!       integer 800
+//!       integer 600
+//! "#
+//! );
+//! ```
+//!
+//! [`error`] renders errors in a pinned version of [`ErrorFormat`], so that
+//! snapshots of errors don't change when the default version does.
+
+use crate::{
+    ast::{Attribute, Expr, Ident, Integer, KeyValue, Ron, Sign, Spanned, Untagged},
+    error::{write_error_with_format, Error, ErrorFormat},
+};
+
+/// The version of the error format [`error`] renders
+pub const ERROR_FORMAT: ErrorFormat = ErrorFormat::V2;
+
+/// The tree of `ron` with the spans of its nodes
+pub fn ast(ron: &Ron) -> String {
+    Tree::new(true).ron(ron)
+}
+
+/// The tree of `ron` without spans
+pub fn ast_without_spans(ron: &Ron) -> String {
+    Tree::new(false).ron(ron)
+}
+
+/// `e` rendered in [`ERROR_FORMAT`], with whole lines and without trailing
+/// whitespace
+pub fn error(e: &Error) -> String {
+    let mut out = vec![];
+    // Writing to a `Vec` doesn't fail
+    let _ = write_error_with_format(&mut out, e, ERROR_FORMAT, usize::MAX);
+
+    String::from_utf8_lossy(&out)
+        .lines()
+        .map(|line| format!("{}\n", line.trim_end()))
+        .collect()
+}
+
+/// The [`ast`] of `source` if it parses, its [`error`] otherwise
+#[cfg(feature = "utf8_parser")]
+pub fn parse(source: &str) -> String {
+    match crate::utf8_parser::ast_from_str(source) {
+        Ok(ron) => ast(&ron),
+        Err(e) => error(&e),
+    }
+}
+
+struct Tree {
+    spans: bool,
+    out: String,
+    depth: usize,
+}
+
+impl Tree {
+    fn new(spans: bool) -> Self {
+        Tree {
+            spans,
+            out: String::new(),
+            depth: 0,
+        }
+    }
+
+    fn ron(mut self, ron: &Ron) -> String {
+        for attribute in &ron.attributes {
+            let text = match &attribute.value {
+                Attribute::Enable(extensions) => {
+                    let names: Vec<_> = extensions.value.iter().map(|e| e.value.name()).collect();
+                    format!("enable {}", names.join(", "))
+                }
+                Attribute::Version(version) => format!("version {}", version.value),
+            };
+            self.node(text, attribute);
+        }
+        self.expr(&ron.expr);
+
+        self.out
+    }
+
+    fn line(&mut self, text: &str) {
+        for _ in 0..self.depth {
+            self.out.push_str("  ");
+        }
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    /// Writes a line for `spanned`
+    fn node<T>(&mut self, text: impl AsRef<str>, spanned: &Spanned<T>) {
+        match self.spans {
+            true => self.line(&format!(
+                "{} @ {}..{}",
+                text.as_ref(),
+                spanned.start,
+                spanned.end
+            )),
+            false => self.line(text.as_ref()),
+        }
+    }
+
+    /// Writes a line for `spanned` and, one level deeper, its children
+    fn parent<T>(
+        &mut self,
+        text: impl AsRef<str>,
+        spanned: &Spanned<T>,
+        children: impl FnOnce(&mut Self),
+    ) {
+        self.node(text, spanned);
+        self.depth += 1;
+        children(self);
+        self.depth -= 1;
+    }
+
+    fn expr(&mut self, expr: &Spanned<Expr>) {
+        match &expr.value {
+            Expr::Unit => self.node("unit", expr),
+            Expr::Optional(None) => self.node("none", expr),
+            Expr::Optional(Some(inner)) => self.parent("some", expr, |tree| tree.expr(inner)),
+            Expr::Tagged(t) => match &t.untagged.value {
+                Untagged::Unit => self.node(format!("ident {}", t.ident.value.as_str()), expr),
+                Untagged::Struct(s) => {
+                    self.parent(format!("struct {}", t.ident.value.as_str()), expr, |tree| {
+                        tree.fields(&s.fields)
+                    })
+                }
+                Untagged::Tuple(elements) => {
+                    self.parent(format!("tuple {}", t.ident.value.as_str()), expr, |tree| {
+                        tree.exprs(elements.iter())
+                    })
+                }
+            },
+            Expr::Bool(b) => self.node(format!("bool {}", b), expr),
+            Expr::Tuple(t) => self.parent("tuple", expr, |tree| tree.exprs(t.iter())),
+            Expr::List(l) => self.parent("list", expr, |tree| tree.exprs(l.iter())),
+            Expr::Map(m) => self.parent("map", expr, |tree| {
+                for entry in &m.entries {
+                    tree.parent("entry", entry, |tree| {
+                        tree.docs(&entry.value);
+                        tree.expr(&entry.value.key);
+                        tree.expr(&entry.value.value);
+                    });
+                }
+            }),
+            Expr::Struct(s) => self.parent("struct", expr, |tree| tree.fields(&s.fields)),
+            Expr::Integer(Integer::Unsigned(u)) => self.node(format!("integer {}", u.number), expr),
+            Expr::Integer(Integer::Signed(s)) => {
+                let sign = match s.sign {
+                    Sign::Positive => '+',
+                    Sign::Negative => '-',
+                };
+                self.node(format!("integer {}{}", sign, s.number), expr);
+            }
+            Expr::Integer(Integer::Big(text)) => self.node(format!("integer {}", text), expr),
+            Expr::Str(s) => self.string(s, expr),
+            Expr::String(s) => self.string(s, expr),
+            Expr::EscapedStr(s) => self.string(&s.unescape(), expr),
+            Expr::Decimal(d) => self.node(format!("decimal {}", d), expr),
+            Expr::Quantity(q) => self.node(format!("quantity {}", q), expr),
+            Expr::Color(c) => self.node(format!("color {}", c), expr),
+        }
+    }
+
+    fn exprs<'b, 'a: 'b>(&mut self, exprs: impl Iterator<Item = &'b Spanned<Expr<'a>>>) {
+        for expr in exprs {
+            self.expr(expr);
+        }
+    }
+
+    fn fields(&mut self, fields: &[Spanned<KeyValue<Ident>>]) {
+        for field in fields {
+            self.parent(
+                format!("field {}", field.value.key.value.as_str()),
+                field,
+                |tree| {
+                    tree.docs(&field.value);
+                    tree.expr(&field.value.value);
+                },
+            );
+        }
+    }
+
+    fn docs<K>(&mut self, kv: &KeyValue<K>) {
+        if let Some(doc) = kv.doc_string() {
+            self.line(&format!("doc {:?}", doc));
+        }
+    }
+
+    fn string(&mut self, s: &str, expr: &Spanned<Expr>) {
+        self.node(format!("string {:?}", s), expr);
+    }
+}
+
+#[cfg(all(test, feature = "utf8_parser"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spans() {
+        let source =
+            "#![enable(implicit_some)]\n(\n    /// Size\n    a: [1, -2],\n    b: {\"k\": None},\n)";
+
+        assert_eq!(
+            parse(source),
+            r#"enable implicit_some @ 1:1..1:26
+struct @ 2:1..6:2
+  field a @ 4:5..4:15
+    doc "Size"
+    list @ 4:8..4:15
+      integer 1 @ 4:9..4:10
+      integer -2 @ 4:12..4:14
+  field b @ 5:5..5:19
+    map @ 5:8..5:19
+      entry @ 5:9..5:18
+        string "k" @ 5:9..5:12
+        none @ 5:14..5:18
+"#
+        );
+    }
+
+    #[test]
+    #[cfg(not(feature = "tiny"))]
+    fn errors() {
+        assert_eq!(
+            parse("(a: 1,\n b: [)"),
+            "error: parsing error: expected a value at 2:6\n --> string:2:6\n  |\n2 |  b: [)\n  |      ^\n  |\n"
+        );
+    }
+}
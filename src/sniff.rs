@@ -0,0 +1,260 @@
+//! Telling RON apart from other formats without parsing it, e.g. for loaders
+//! that accept several formats and pick a parser by the content of a file
+//!
+//! [`looks_like_ron`] scans the start of a document for syntax only RON has,
+//! like struct fields `(width: 800)` and named structs, and for syntax it
+//! can't have, like top-level YAML keys and unbalanced delimiters. It doesn't
+//! validate anything: a document it is sure about can still be invalid.
+//!
+//! ```
+//! use ron_reboot::sniff::{looks_like_ron, Confidence};
+//!
+//! assert_eq!(looks_like_ron("Window(title: \"Main\")"), Confidence::High);
+//! assert_eq!(looks_like_ron("[1, 2, 3]"), Confidence::Low);
+//! assert_eq!(looks_like_ron("title: Main\nwidth: 800\n"), Confidence::No);
+//! ```
+
+use crate::lex::{block_comment, line_comment_len, quoted_len, raw_string_len};
+
+/// How many bytes of a document [`looks_like_ron`] looks at
+pub const SNIFF_LEN: usize = 64 * 1024;
+
+/// How sure [`looks_like_ron`] is that a document is RON
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Confidence {
+    /// The document has syntax RON doesn't have, e.g. YAML keys at the top
+    /// level, `null` or unbalanced delimiters, or is empty
+    No,
+    /// The document may be RON, but also another format, e.g. a JSON array or
+    /// object, which are RON lists and maps
+    Low,
+    /// The document has syntax only RON has: attributes like
+    /// `#![enable(..)]`, struct fields, names of structs and variants, `Some`
+    /// and raw strings
+    High,
+}
+
+/// How likely `s` is RON, judging by its first [`SNIFF_LEN`] bytes
+///
+/// This only scans the bytes, so it's much faster than parsing `s`, and is
+/// meant to pick the parser to try first.
+pub fn looks_like_ron(s: &str) -> Confidence {
+    let s = s.strip_prefix('\u{feff}').unwrap_or(s);
+    let complete = s.len() <= SNIFF_LEN;
+    let bytes = &s.as_bytes()[..s.len().min(SNIFF_LEN)];
+
+    let mut sniffer = Sniffer {
+        bytes,
+        i: 0,
+        open: vec![],
+        values: 0,
+        ron_only: false,
+    };
+    let scanned = sniffer.scan();
+
+    match scanned {
+        Some(()) if sniffer.values == 0 => Confidence::No,
+        // A complete document closes everything it opens
+        Some(()) if complete && !sniffer.open.is_empty() => Confidence::No,
+        Some(()) if sniffer.ron_only => Confidence::High,
+        Some(()) => Confidence::Low,
+        None => Confidence::No,
+    }
+}
+
+struct Sniffer<'a> {
+    bytes: &'a [u8],
+    i: usize,
+    /// The delimiters that are open
+    open: Vec<u8>,
+    /// How many values start at the top level
+    values: usize,
+    /// Whether syntax only RON has was found
+    ron_only: bool,
+}
+
+impl Sniffer<'_> {
+    fn peek(&self, offset: usize) -> Option<u8> {
+        self.bytes.get(self.i + offset).copied()
+    }
+
+    fn rest(&self) -> &[u8] {
+        self.bytes.get(self.i..).unwrap_or_default()
+    }
+
+    /// Scans the bytes; `None` if they can't be RON
+    fn scan(&mut self) -> Option<()> {
+        if self.rest().starts_with(b"---") || self.rest().starts_with(b"%YAML") {
+            return None;
+        }
+
+        while let Some(b) = self.peek(0) {
+            if b.is_ascii_whitespace() {
+                self.i += 1;
+                continue;
+            }
+            if self.rest().starts_with(b"//") {
+                self.i += line_comment_len(self.rest());
+                continue;
+            }
+            if self.rest().starts_with(b"/*") {
+                self.i += block_comment(self.rest()).len;
+                continue;
+            }
+
+            if self.open.is_empty() && !matches!(b, b')' | b']' | b'}') {
+                if self.rest().starts_with(b"#![") && self.values == 0 {
+                    self.ron_only = true;
+                    self.i += 3;
+                    self.open.push(b'[');
+                    continue;
+                }
+                // A document is one value, anything after it is another format
+                self.values += 1;
+                if self.values > 1 {
+                    return None;
+                }
+            }
+
+            match b {
+                b'(' | b'[' | b'{' => {
+                    self.open.push(b);
+                    self.i += 1;
+                }
+                b')' | b']' | b'}' => {
+                    let opener = match b {
+                        b')' => b'(',
+                        b']' => b'[',
+                        _ => b'{',
+                    };
+                    if self.open.pop() != Some(opener) {
+                        return None;
+                    }
+                    self.i += 1;
+                    // An attribute isn't a value
+                    if self.open.is_empty() && b == b']' && self.values == 0 {
+                        continue;
+                    }
+                }
+                b'"' | b'\'' => self.i += quoted_len(self.rest()),
+                b'r' if matches!(self.peek(1), Some(b'"' | b'#')) => {
+                    self.ron_only = true;
+                    // A raw identifier goes on with its `#`
+                    self.i += raw_string_len(self.rest()).unwrap_or(1);
+                }
+                b if b.is_ascii_alphabetic() || b == b'_' => self.ident()?,
+                // Colors like `#ff8000`, or a YAML comment
+                b'#' => match self.peek(1) {
+                    Some(b) if b.is_ascii_alphanumeric() => self.skip_word(),
+                    _ => return None,
+                },
+                b'0'..=b'9' | b'-' | b'+' | b'.' => self.skip_word(),
+                _ => self.i += 1,
+            }
+        }
+
+        Some(())
+    }
+
+    /// Checks an identifier and what follows it
+    fn ident(&mut self) -> Option<()> {
+        let start = self.i;
+        while matches!(self.peek(0), Some(b) if b.is_ascii_alphanumeric() || b == b'_') {
+            self.i += 1;
+        }
+        let ident = self.bytes.get(start..self.i).unwrap_or_default();
+
+        let next = self.rest().iter().find(|b| !b.is_ascii_whitespace());
+        match (ident, next) {
+            (b"null", _) => None,
+            (b"true" | b"false", _) => Some(()),
+            // `key: value` outside of a struct is YAML
+            (_, Some(b':')) if self.open.is_empty() => None,
+            (_, Some(b':')) if self.open.last() == Some(&b'(') => {
+                self.ron_only = true;
+                Some(())
+            }
+            // The name of a struct, tuple or variant, which is the same value
+            (_, Some(b'(')) => {
+                self.ron_only = true;
+                while self.peek(0) != Some(b'(') {
+                    self.i += 1;
+                }
+                self.open.push(b'(');
+                self.i += 1;
+                Some(())
+            }
+            (b"None", _) => {
+                self.ron_only = true;
+                Some(())
+            }
+            _ => Some(()),
+        }
+    }
+
+    /// Skips a number or literal, e.g. `-1.5e3` or `250ms`
+    fn skip_word(&mut self) {
+        let in_word = |b: u8| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'.' | b'+' | b'-');
+
+        self.i += 1;
+        while self.peek(0).is_some_and(in_word) {
+            self.i += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ron() {
+        for source in [
+            "#![enable(implicit_some)]\n[1]",
+            "(width: 800, height: 600)",
+            "// Window\nWindow(title: \"a: b\")",
+            "[Some(1), None]",
+            "{\"a\": Point(1, 2)}",
+            "[r#\"a \" b\"#]",
+            "\u{feff}Config()",
+        ] {
+            assert_eq!(looks_like_ron(source), Confidence::High, "{}", source);
+        }
+    }
+
+    #[test]
+    fn ambiguous() {
+        for source in [
+            "[1, 2, 3]",
+            "{\"a\": [true, false], \"b\": \"x: y\"}",
+            "(1, \"(\")",
+            "5",
+            "\"text\"",
+            "/* a /* nested */ comment */ [1]",
+        ] {
+            assert_eq!(looks_like_ron(source), Confidence::Low, "{}", source);
+        }
+    }
+
+    #[test]
+    fn not_ron() {
+        for source in [
+            "",
+            "  // only a comment",
+            "---\na: 1",
+            "title: Main\nwidth: 800",
+            "- 1\n- 2",
+            "{\"a\": null}",
+            "[1, 2",
+            "(1, 2]",
+            "[1] [2]",
+            "# comment\na: 1",
+        ] {
+            assert_eq!(looks_like_ron(source), Confidence::No, "{}", source);
+        }
+
+        // Only the start of a long document is scanned
+        let long = format!("[{}", "1, ".repeat(SNIFF_LEN));
+        assert_eq!(looks_like_ron(&long), Confidence::Low);
+    }
+}
@@ -0,0 +1,199 @@
+//! Escaping of string literals, for tools that edit RON as text
+//!
+//! [`escape`] and [`unescape`] work on the text between the quotes of a
+//! string literal and return it unchanged if there is nothing to escape or
+//! unescape. [`quote`] and [`needs_quoting`] are for templates that insert
+//! values into RON text.
+
+use std::borrow::Cow;
+
+/// Which chars [`escape`] escapes besides quotes, backslashes and control chars
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum EscapePolicy {
+    /// Write all other chars literally
+    #[default]
+    Minimal,
+    /// Escape all non-ASCII chars as `\u{..}`
+    Ascii,
+}
+
+/// The string the escapes of `s` stand for
+///
+/// Escapes the parser rejects, e.g. `\q` or `\u{110000}`, are kept as they
+/// are.
+pub fn unescape(s: &str) -> Cow<'_, str> {
+    if !s.contains('\\') {
+        return Cow::Borrowed(s);
+    }
+
+    let mut string = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            string.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => string.push('\n'),
+            Some('r') => string.push('\r'),
+            Some('t') => string.push('\t'),
+            Some('b') => string.push('\u{08}'),
+            Some('f') => string.push('\u{0C}'),
+            Some(c @ ('\\' | '/' | '"')) => string.push(c),
+            Some('u') => {
+                let rest = chars.as_str();
+                let unicode = rest.strip_prefix('{').and_then(|hex| {
+                    let end = hex.find('}')?;
+                    let c = u32::from_str_radix(&hex[..end], 16).ok()?;
+
+                    Some((std::char::from_u32(c)?, &hex[end + 1..]))
+                });
+                match unicode {
+                    Some((c, rest)) => {
+                        string.push(c);
+                        chars = rest.chars();
+                    }
+                    None => string.push_str("\\u"),
+                }
+            }
+            // An escaped line break skips the whitespace after it
+            Some(' ' | '\t' | '\n' | '\r') => {
+                chars = chars
+                    .as_str()
+                    .trim_start_matches([' ', '\t', '\n', '\r'])
+                    .chars();
+            }
+            Some(c) => {
+                string.push('\\');
+                string.push(c);
+            }
+            None => string.push('\\'),
+        }
+    }
+
+    Cow::Owned(string)
+}
+
+/// `s` with quotes, backslashes and control chars escaped, so that it can be
+/// written between the quotes of a string literal
+pub fn escape(s: &str, policy: EscapePolicy) -> Cow<'_, str> {
+    let escape_unicode = policy == EscapePolicy::Ascii;
+    let mut string = String::new();
+    // Copy the chars between escapes as one slice
+    let mut unescaped_start = 0;
+    for (i, c) in s.char_indices() {
+        let escape = match c {
+            '"' => "\\\"",
+            '\\' => "\\\\",
+            '\n' => "\\n",
+            '\r' => "\\r",
+            '\t' => "\\t",
+            '\u{08}' => "\\b",
+            '\u{0C}' => "\\f",
+            c if c.is_control() || (escape_unicode && !c.is_ascii()) => "",
+            _ => continue,
+        };
+
+        string.push_str(&s[unescaped_start..i]);
+        match escape {
+            "" => string.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            escape => string.push_str(escape),
+        }
+        unescaped_start = i + c.len_utf8();
+    }
+
+    match unescaped_start {
+        0 => Cow::Borrowed(s),
+        _ => {
+            string.push_str(&s[unescaped_start..]);
+
+            Cow::Owned(string)
+        }
+    }
+}
+
+/// `s` as a string literal, e.g. `"say \"hi\""` for `say "hi"`
+pub fn quote(s: &str) -> String {
+    format!("\"{}\"", escape(s, EscapePolicy::Minimal))
+}
+
+/// Whether `s` has to be written as a string literal by [`quote`], because
+/// the parser doesn't read it as a unit like `Foo` when it's written as is
+///
+/// This is the case for everything that isn't an identifier, and for
+/// identifiers the parser reads as something else, e.g. `true`, `None`,
+/// `r#type` or a mistyped bool like `fals`.
+#[cfg(feature = "utf8_parser")]
+pub fn needs_quoting(s: &str) -> bool {
+    use crate::ast::{Expr, Untagged};
+
+    // Anything around the identifier, like whitespace, makes it differ from `s`
+    let is_unit = crate::utf8_parser::ast_from_str(s).is_ok_and(|ron| match ron.expr.value {
+        Expr::Tagged(tagged) => {
+            tagged.ident.value.0 == s && matches!(tagged.untagged.value, Untagged::Unit)
+        }
+        _ => false,
+    });
+
+    !is_unit
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unescape_escapes() {
+        assert!(matches!(unescape("plain"), Cow::Borrowed("plain")));
+        assert_eq!(
+            unescape(r#"a\tb \"c\" \\ \/ \u{1F980}\b\f"#),
+            "a\tb \"c\" \\ / \u{1F980}\u{08}\u{0C}"
+        );
+        assert_eq!(unescape("line \\\n    continued"), "line continued");
+        assert_eq!(unescape(r"\q \u{110000} \u{zz \"), r"\q \u{110000} \u{zz \");
+    }
+
+    #[test]
+    fn escape_round_trips() {
+        assert!(matches!(
+            escape("plain ä", EscapePolicy::Minimal),
+            Cow::Borrowed("plain ä")
+        ));
+        assert_eq!(escape("ä", EscapePolicy::Ascii), r"\u{e4}");
+
+        let s = "tab\t \"quoted\" \\ \u{7} ä\r\n";
+        assert_eq!(
+            escape(s, EscapePolicy::Minimal),
+            r#"tab\t \"quoted\" \\ \u{7} ä\r\n"#
+        );
+        for policy in [EscapePolicy::Minimal, EscapePolicy::Ascii] {
+            assert_eq!(unescape(&escape(s, policy)), s);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "utf8_parser")]
+    fn quoting() {
+        use crate::{ast::Expr, utf8_parser::ast_from_str};
+
+        for s in ["Foo", "bar_2", "_", "inf", "Some"] {
+            assert!(!needs_quoting(s), "{}", s);
+        }
+        for s in [
+            "", "true", "None", "fals", "r#type", "2d", "a-b", " Foo", "Foo // x", "ä", "\"",
+        ] {
+            assert!(needs_quoting(s), "{}", s);
+        }
+
+        for s in ["", "say \"hi\"", "a\\b\n\u{7}", "ä // not a comment"] {
+            let quoted = quote(s);
+            match ast_from_str(&quoted).unwrap().expr.value {
+                Expr::Str(parsed) => assert_eq!(parsed, s),
+                Expr::String(parsed) => assert_eq!(parsed, s),
+                other => panic!("`{}` parsed as {:?}", quoted, other),
+            }
+        }
+    }
+}
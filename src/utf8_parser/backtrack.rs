@@ -0,0 +1,70 @@
+use crate::utf8_parser::{ErrorTree, Expectation, Input, InputParseErr, InputParseError};
+
+/// Counts the bytes a parser got through before it failed with the
+/// recoverable `e` at `input`, which the next parser reads again
+///
+/// Returns `input` counting them, for the next parser. Fails once the
+/// [budget](Input::with_backtrack_budget) of `input` is used up, so that
+/// parsing ends right away.
+pub(crate) fn record<'a>(
+    input: Input<'a>,
+    e: &InputParseError<'a>,
+) -> Result<Input<'a>, InputParseErr<'a>> {
+    let bytes = e.max_offset().saturating_sub(input.offset().bytes());
+    let input = carry(input, e);
+    let input = input.with_backtracked(input.backtracked().saturating_add(bytes));
+
+    match input.backtrack_budget() {
+        Some(budget) if input.backtracked() > budget => Err(InputParseErr::fatal(
+            ErrorTree::expected(input, Expectation::Something),
+        )),
+        _ => Ok(input),
+    }
+}
+
+/// `input` counting what was backtracked before the recoverable `e`, for
+/// parsing on from `input` after discarding `e`
+pub(crate) fn carry<'a>(input: Input<'a>, e: &InputParseError<'a>) -> Input<'a> {
+    input.with_backtracked(input.backtracked().max(backtracked(e)))
+}
+
+/// `e` counting at least `backtracked` bytes, e.g. those of an error it was
+/// merged with
+pub(crate) fn carry_err(mut e: InputParseError, backtracked: usize) -> InputParseError {
+    // The first location is enough, `backtracked` takes the most of all
+    let mut tree = &mut e;
+    let location = loop {
+        tree = match tree {
+            ErrorTree::Base { location, .. } => break location,
+            ErrorTree::Stack { base, .. } => base,
+            ErrorTree::Alt(alternatives) => {
+                alternatives.first_mut().expect("alternatives aren't empty")
+            }
+        };
+    };
+    *location = location.with_backtracked(location.backtracked().max(backtracked));
+
+    e
+}
+
+/// Bytes backtracked before `e`, see [`Timings::backtracked`]
+///
+/// [`Timings::backtracked`]: crate::utf8_parser::Timings::backtracked
+pub(crate) fn backtracked(e: &InputParseError) -> usize {
+    match e {
+        ErrorTree::Base { location, .. } => location.backtracked(),
+        ErrorTree::Stack { base, .. } => backtracked(base),
+        ErrorTree::Alt(alternatives) => alternatives
+            .iter()
+            .map(backtracked)
+            .max()
+            .expect("alternatives aren't empty"),
+    }
+}
+
+/// Whether the budget of `input` was used up before `e`
+pub(crate) fn exceeded(input: Input, e: &InputParseError) -> bool {
+    input
+        .backtrack_budget()
+        .is_some_and(|budget| backtracked(e) > budget)
+}
@@ -1,4 +1,5 @@
 use crate::utf8_parser::{
+    backtrack,
     char_categories::is_ws,
     combinators,
     combinators::{alt2, lookahead, pair, recognize, take_while},
@@ -22,7 +23,10 @@ pub fn multispacews0(input: Input) -> IResultLookahead<()> {
         let mult_rem = mult.remaining;
         ok = mult.then_res(&mut any_comment, |_, res| match res {
             Ok(ok) => Ok(ok.replace(true)),
-            Err(e) if e.is_recoverable() => Ok((mult_rem, false).into()), // TODO keep error?
+            // TODO keep error?
+            Err(InputParseErr::Recoverable(e)) => {
+                Ok((backtrack::carry(mult_rem, &e), false).into())
+            }
             Err(e) => Err(e),
         })?;
 
@@ -53,9 +57,15 @@ pub fn block_comment(input: Input) -> IResultLookahead<Input> {
 }
 
 fn block_comment_tail(input: Input) -> IResultLookahead<()> {
-    let comment_end = input.fragment().find("*/").ok_or_else(|| {
-        base_err::<()>(input.slice(input.len() - 1..), Expectation::BlockCommentEnd).unwrap_err()
-    })?;
+    let comment_end = match input.fragment().find("*/") {
+        Some(comment_end) => comment_end,
+        // At the last char, which may be the end of the input
+        None => {
+            let last = input.fragment().char_indices().last().map_or(0, |(i, _)| i);
+
+            return base_err(input.slice(last..), Expectation::BlockCommentEnd);
+        }
+    };
     let nested_start = input.fragment().find("/*");
 
     if let Some(nested_start) = nested_start {
@@ -102,12 +112,10 @@ pub fn repeat_char<'a>(
                 InputParseErr::fatal(ErrorTree::expected(input, Expectation::Char(c)))
             })?;
 
-        if n == char_index + 1 {
-            Ok(input.take_split(char_byte_offset + c.len_utf8()))
-        } else if char_index + 1 < n {
-            base_err(input.slice(char_byte_offset..), Expectation::Char(c))
-        } else {
-            unimplemented!()
+        // At most `n` are matched
+        match n == char_index + 1 {
+            true => Ok(input.take_split(char_byte_offset + c.len_utf8())),
+            false => base_err(input.slice(char_byte_offset..), Expectation::Char(c)),
         }
     }
 }
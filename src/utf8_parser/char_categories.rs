@@ -1,10 +1,17 @@
+//! The classes of chars the parser uses, e.g. for syntax highlighters and
+//! validators which have to agree with it
+//!
+//! All classes are ASCII-only, the Unicode identifiers of the `unicode_ident`
+//! feature are only allowed with `Input::with_unicode_idents`. Comments are
+//! skipped like whitespace but aren't a class of their own.
+
 // From https://github.com/ron-rs/ron/blob/59df2d32422d2334176cdf76fbf40f07b17c2ad9/src/parse.rs
 
-#![allow(non_upper_case_globals, unused)]
+#![allow(non_upper_case_globals)]
 
 // We have the following char categories.
 const DIGIT_FIRST: u8 = 1 << 0; // [1-9]
-const DIGIT_ANY: u8 = 1 << 1; // [0-9\.Ee+-]
+const DIGIT_ANY: u8 = 1 << 1; // [0-9]
 const IDENT_FIRST_CHAR: u8 = 1 << 2; // [A-Za-z_]
 const IDENT_OTHER_CHAR: u8 = 1 << 3; // [A-Za-z_0-9]
 const IDENT_RAW_CHAR: u8 = 1 << 4; // [A-Za-z_0-9\.+-]
@@ -54,30 +61,93 @@ const ENCODINGS: [u8; 256] = [
     /* 250+: ·········· */ _____, _____, _____, _____, _____, _____
 ];
 
+/// The categories of `c`, none for non-ASCII chars
+const fn encoding(c: char) -> u8 {
+    match c.is_ascii() {
+        true => ENCODINGS[c as usize],
+        false => _____,
+    }
+}
+
+/// `[1-9]`, the first digit of a decimal number with more than one digit
 pub const fn is_digit_first(c: char) -> bool {
-    ENCODINGS[c as u8 as usize] & DIGIT_FIRST != 0
+    encoding(c) & DIGIT_FIRST != 0
 }
 
+/// `[0-9]`, a decimal digit
 pub const fn is_digit(c: char) -> bool {
-    ENCODINGS[c as u8 as usize] & DIGIT_ANY != 0
+    encoding(c) & DIGIT_ANY != 0
 }
 
+/// `[\t\n\r ]`, whitespace between tokens
 pub const fn is_ws(c: char) -> bool {
-    ENCODINGS[c as u8 as usize] & WHITESPACE_CHAR != 0
+    encoding(c) & WHITESPACE_CHAR != 0
 }
 
+/// `[A-Za-z_]`, the first char of an identifier
 pub const fn is_ident_first_char(c: char) -> bool {
-    ENCODINGS[c as u8 as usize] & IDENT_FIRST_CHAR != 0
+    encoding(c) & IDENT_FIRST_CHAR != 0
 }
 
+/// `[A-Za-z0-9_]`, a char of an identifier after the first one
 pub const fn is_ident_other_char(c: char) -> bool {
-    ENCODINGS[c as u8 as usize] & IDENT_OTHER_CHAR != 0
+    encoding(c) & IDENT_OTHER_CHAR != 0
+}
+
+/// Like [`is_ident_first_char`], also for chars starting Unicode identifiers
+/// (`XID_Start`) with the `unicode_ident` feature
+pub fn is_unicode_ident_first_char(c: char) -> bool {
+    #[cfg(feature = "unicode_ident")]
+    if unicode_ident::is_xid_start(c) {
+        return true;
+    }
+
+    is_ident_first_char(c)
 }
 
+/// Like [`is_ident_other_char`], also for chars continuing Unicode identifiers
+/// (`XID_Continue`) with the `unicode_ident` feature
+pub fn is_unicode_ident_other_char(c: char) -> bool {
+    #[cfg(feature = "unicode_ident")]
+    if unicode_ident::is_xid_continue(c) {
+        return true;
+    }
+
+    is_ident_other_char(c)
+}
+
+/// `[A-Za-z0-9_.+-]`, a char of a raw identifier after the `r#`, e.g. of
+/// `r#kebab-case`
 pub const fn is_ident_raw_char(c: char) -> bool {
-    ENCODINGS[c as u8 as usize] & IDENT_RAW_CHAR != 0
+    encoding(c) & IDENT_RAW_CHAR != 0
 }
 
-const fn is_whitespace_char(c: char) -> bool {
-    ENCODINGS[c as u8 as usize] & WHITESPACE_CHAR != 0
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn documented_classes() {
+        for c in (0..=0x80).filter_map(std::char::from_u32) {
+            let ident_other = c.is_ascii_alphanumeric() || c == '_';
+
+            assert_eq!(is_digit_first(c), ('1'..='9').contains(&c), "{:?}", c);
+            assert_eq!(is_digit(c), c.is_ascii_digit(), "{:?}", c);
+            assert_eq!(is_ws(c), "\t\n\r ".contains(c), "{:?}", c);
+            assert_eq!(
+                is_ident_first_char(c),
+                ident_other && !c.is_ascii_digit(),
+                "{:?}",
+                c
+            );
+            assert_eq!(is_ident_other_char(c), ident_other, "{:?}", c);
+            assert_eq!(
+                is_ident_raw_char(c),
+                ident_other || ".+-".contains(c),
+                "{:?}",
+                c
+            );
+        }
+        assert!(!is_ident_first_char('\u{e4}'));
+    }
 }
@@ -1,5 +1,5 @@
 use crate::utf8_parser::{
-    basic,
+    backtrack, basic,
     basic::{multispacews0, one_char},
     input::position,
     pt::Spanned,
@@ -69,17 +69,32 @@ where
 
         parser(copy_of_input)?.and_then(
             |remaining_input| {
-                Ok((
-                    remaining_input,
-                    input.slice(..input.offset_to(&remaining_input)),
-                )
-                    .into())
+                // Parsers only consume input, so the remaining input is never
+                // before it
+                let len = input.offset_to(&remaining_input).unwrap_or_default();
+
+                Ok((remaining_input, input.slice(..len)).into())
             },
             |_first, second| second,
         )
     }
 }
 
+/// Like [`recognize`], but keeps what `parser` parsed next to its text
+pub fn with_text<'a, O, F>(
+    mut parser: F,
+) -> impl FnMut(Input<'a>) -> IResultLookahead<'a, (O, &'a str)>
+where
+    F: FnMut(Input<'a>) -> IResultLookahead<'a, O>,
+{
+    move |input: Input<'a>| {
+        let ok = parser(input)?;
+        let len = input.offset_to(&ok.remaining).unwrap_or_default();
+
+        Ok(ok.map(|parsed| (parsed, input.slice(..len).fragment())))
+    }
+}
+
 pub fn lookahead<'a, O, F>(mut parser: F) -> impl FnMut(Input<'a>) -> IResultLookahead<'a, O>
 where
     F: FnMut(Input<'a>) -> IResultLookahead<'a, O>,
@@ -110,15 +125,22 @@ where
     G: FnMut(Input<'a>) -> IResultLookahead<'a, O>,
 {
     move |input: Input| match f(input) {
-        Err(InputParseErr::Recoverable(first)) => match g(input) {
-            Err(InputParseErr::Recoverable(second)) => {
-                Err(InputParseErr::recoverable(ErrorTree::alt(first, second)))
-            }
-            Err(InputParseErr::Fatal(second)) => {
-                Err(InputParseErr::fatal(second))
+        Err(InputParseErr::Recoverable(first)) => {
+            match backtrack::record(input, &first).and_then(&mut g) {
+                Err(InputParseErr::Recoverable(second)) => {
+                    // `second` counts what `first` backtracked too
+                    let backtracked = backtrack::backtracked(&second);
+                    let merged = input.alt_errors().merge(first, second);
+
+                    Err(InputParseErr::recoverable(backtrack::carry_err(
+                        merged,
+                        backtracked,
+                    )))
+                }
+                Err(InputParseErr::Fatal(second)) => Err(InputParseErr::fatal(second)),
+                res => res,
             }
-            res => res,
-        },
+        }
         res => res,
     }
 }
@@ -132,11 +154,15 @@ where
         let i = input;
         match f(input) {
             Ok(ok) => Ok(ok.map(Some)),
-            Err(InputParseErr::Recoverable(e)) => Ok(IOk {
-                remaining: i,
-                parsed: None,
-                discarded_error: Some(e),
-            }),
+            Err(InputParseErr::Recoverable(e)) => {
+                let remaining = backtrack::record(i, &e)?;
+
+                Ok(IOk {
+                    remaining,
+                    parsed: None,
+                    discarded_error: Some(e),
+                })
+            }
             Err(e) => Err(e),
         }
     }
@@ -147,10 +173,11 @@ pub fn take_until<'a, O, F>(mut f: F) -> impl FnMut(Input<'a>) -> IResultLookahe
 where
     F: FnMut(Input<'a>) -> IResultLookahead<'a, O>,
 {
-    move |input: Input| {
+    move |mut input: Input| {
         let mut last_discarded_err = None;
 
-        for i in 0..input.len() {
+        let boundaries = input.fragment().char_indices().map(|(i, _)| i);
+        for i in boundaries {
             match input
                 .take_split(i)
                 .then_res(&mut f, |input, res| res.map(|ok| ok.replace(input)))
@@ -158,6 +185,7 @@ where
                 Err(InputParseErr::Fatal(e)) => return Err(InputParseErr::Fatal(e)),
                 // TODO: discarding many errors here
                 Err(InputParseErr::Recoverable(e)) => {
+                    input = backtrack::carry(input, &e);
                     last_discarded_err = Some(e);
                 }
                 Ok(input_before_f) => return Ok(input_before_f),
@@ -211,22 +239,26 @@ where
             match f(i) {
                 Err(InputParseErr::Recoverable(e)) => {
                     return Ok(IOk {
-                        remaining: i,
+                        remaining: backtrack::carry(i, &e),
                         parsed: acc,
                         discarded_error: Some(e),
                     })
                 }
                 Err(e) => return Err(e),
                 Ok(ok) => {
-                    // infinite loop check: the utf8_parser must always consume
-                    if ok.remaining.len() == len {
-                        unimplemented!("infinite loop - utf8_parser not consuming?");
-                    }
-
                     // TODO: if there was a discarded error, we forget it here
                     // TODO: is that correct?
                     i = ok.remaining;
                     acc.push(ok.parsed);
+
+                    // A parser that doesn't consume would match forever
+                    if i.len() == len {
+                        return Ok(IOk {
+                            remaining: i,
+                            parsed: acc,
+                            discarded_error: None,
+                        });
+                    }
                 }
             }
         }
@@ -314,19 +346,22 @@ where
             let len = input.len();
             match f(copy_of_input) {
                 Ok(ok) => {
-                    // infinite loop check: the utf8_parser must always consume
-                    if ok.remaining.len() == len {
-                        todo!()
-                        //return Err(InputParseErr::Error(E::from_error_kind(input, ErrorKind::Many0)));
-                    }
-
                     // TODO: again, forgetting discarded error
                     res = g(res, ok.parsed);
                     input = ok.remaining;
+
+                    // A parser that doesn't consume would match forever
+                    if input.len() == len {
+                        return Ok(IOk {
+                            remaining: input,
+                            parsed: res,
+                            discarded_error: None,
+                        });
+                    }
                 }
                 Err(InputParseErr::Recoverable(e)) => {
                     return Ok(IOk {
-                        remaining: input,
+                        remaining: backtrack::carry(input, &e),
                         parsed: res,
                         discarded_error: Some(e),
                     });
@@ -352,24 +387,14 @@ pub fn take1_if(
     }
 }
 
+/// Elements parsed by `f`, separated by commas, with an optional trailing comma
 pub fn comma_list0<'a, F: 'a, O: 'a>(
     f: F,
 ) -> impl FnMut(Input<'a>) -> IResultLookahead<Vec<Spanned<O>>>
 where
-    F: FnMut(Input<'a>) -> IResultLookahead<O> + Clone,
+    F: FnMut(Input<'a>) -> IResultLookahead<O>,
 {
-    let with_trailing = many0(terminated(
-        spanned(f.clone()),
-        lookahead(basic::one_char(',')),
-    ));
-
-    map(
-        pair(with_trailing, opt(spanned(f))),
-        |(mut list, last): (Vec<_>, Option<_>)| {
-            list.extend(last);
-            list
-        },
-    )
+    comma_list(f, false)
 }
 
 #[cfg(test)]
@@ -382,26 +407,79 @@ where
     comma_list0(move |input| lookahead(f.clone())(input))
 }
 
+/// Like [`comma_list0`], but with at least one element
 pub fn comma_list1<'a, F: 'a, O: 'a>(
     f: F,
 ) -> impl FnMut(Input<'a>) -> IResultLookahead<Vec<Spanned<O>>>
 where
-    F: FnMut(Input<'a>) -> IResultLookahead<O> + Clone,
+    F: FnMut(Input<'a>) -> IResultLookahead<O>,
+{
+    comma_list(f, true)
+}
+
+/// Upper bound for the capacity reserved up front, so the many short lists of
+/// big documents don't waste memory
+const COMMA_LIST_CAPACITY_HINT: usize = 16;
+
+/// Parses the elements in a single pass, so each element is parsed once
+///
+/// Stops at the first element or comma that doesn't match, returning its
+/// error as discarded error. With `first_required`, a first element that
+/// doesn't match is an error.
+fn comma_list<'a, F, O: 'a>(
+    f: F,
+    first_required: bool,
+) -> impl FnMut(Input<'a>) -> IResultLookahead<Vec<Spanned<O>>>
+where
+    F: FnMut(Input<'a>) -> IResultLookahead<O> + 'a,
 {
-    let comma = basic::one_char(',');
-    map(
-        pair(
-            spanned(f.clone()),
-            opt(preceded(lookahead(comma), comma_list0(f))),
-        ),
-        |(head, tail): (_, Option<Vec<_>>)| match tail {
-            None => vec![head],
-            Some(mut tail) => {
-                tail.insert(0, head);
-                tail
+    let mut element = spanned(f);
+    let mut comma = lookahead(basic::one_char(','));
+
+    move |input: Input<'a>| {
+        let mut list = Vec::new();
+        let mut first_discarded = None;
+        let mut remaining = input;
+
+        let error = loop {
+            let ok = match element(remaining) {
+                Ok(ok) => ok,
+                Err(InputParseErr::Recoverable(e)) if !(first_required && list.is_empty()) => {
+                    break e;
+                }
+                Err(e) => return Err(e),
+            };
+            if list.is_empty() {
+                first_discarded = ok.discarded_error;
             }
-        },
-    )
+            list.push(ok.parsed);
+
+            remaining = ok.remaining;
+            match comma(remaining) {
+                Ok(ok) => {
+                    // Guess the length from the size of the first element
+                    let element_len = match list.len() {
+                        1 => input.offset_to(&ok.remaining).filter(|&len| len > 0),
+                        _ => None,
+                    };
+                    if let Some(element_len) = element_len {
+                        list.reserve(
+                            (ok.remaining.len() / element_len).min(COMMA_LIST_CAPACITY_HINT),
+                        );
+                    }
+                    remaining = ok.remaining;
+                }
+                Err(InputParseErr::Recoverable(e)) => break e,
+                Err(e) => return Err(e),
+            }
+        };
+
+        Ok(IOk {
+            remaining: backtrack::carry(remaining, &error),
+            parsed: list,
+            discarded_error: first_discarded.or(Some(error)),
+        })
+    }
 }
 
 #[cfg(test)]
@@ -464,6 +542,21 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_comma_list_parses_once() {
+        for (input, elements, calls) in [("a, a, a]", 3, 3), ("a, a,]", 2, 3), ("]", 0, 1)] {
+            let mut count = 0;
+            let ok = comma_list0(|input| {
+                count += 1;
+                lookahead(tag("a"))(input)
+            })(Input::new(input))
+            .unwrap();
+            assert_eq!(ok.parsed.len(), elements);
+            assert_eq!(ok.remaining.fragment(), "]");
+            assert_eq!(count, calls);
+        }
+    }
+
     #[test]
     fn test_take_while() {
         assert_eq!(
@@ -613,16 +706,29 @@ mod tests {
     }
 }
 
-pub fn spanned<'a, F: 'a, O>(mut inner: F) -> impl FnMut(Input<'a>) -> IResultLookahead<Spanned<O>>
+pub fn spanned<'a, F: 'a, O>(inner: F) -> impl FnMut(Input<'a>) -> IResultLookahead<Spanned<O>>
 where
     F: FnMut(Input<'a>) -> IResultLookahead<O>,
     O: 'a,
 {
-    ws(move |input: Input<'a>| {
+    ws(unpadded_spanned(inner))
+}
+
+/// Like [`spanned`], but without skipping whitespace and comments around
+/// `inner`, e.g. for a part of an expression that may be followed by a
+/// comment that isn't part of the expression
+pub fn unpadded_spanned<'a, F, O>(
+    mut inner: F,
+) -> impl FnMut(Input<'a>) -> IResultLookahead<Spanned<O>>
+where
+    F: FnMut(Input<'a>) -> IResultLookahead<O> + 'a,
+    O: 'a,
+{
+    move |input: Input<'a>| {
         position(input)?.then_res(&mut inner, |start, second: IResultLookahead<O>| {
             second?.and_then(position, |value, end| Spanned { start, value, end })
         })
-    })
+    }
 }
 
 pub fn ws<'a, F: 'a, O>(inner: F) -> impl FnMut(Input<'a>) -> IResultLookahead<O>
@@ -632,6 +738,19 @@ where
     delimited(multispacews0, inner, multispacews0)
 }
 
+/// Whether only whitespace and comments are left in `input`
+pub fn at_end(input: Input) -> bool {
+    multispacews0(input).is_ok_and(|ok| ok.remaining.is_empty())
+}
+
+/// The error `e` at the end of the input, in the bracket `start_tag` at
+/// `input` that was never closed
+pub fn unclosed<'a>(input: Input<'a>, start_tag: char, e: InputParseErr<'a>) -> InputParseErr<'a> {
+    let (InputParseErr::Recoverable(e) | InputParseErr::Fatal(e)) = e;
+
+    InputParseErr::fatal(ErrorTree::unclosed(input, start_tag, e))
+}
+
 /// Like
 ///
 /// ```text
@@ -643,7 +762,8 @@ where
 /// ```
 ///
 /// but respects the discarded error of `inner` in case of error
-/// and forgets it in case of success.
+/// and forgets it in case of success. If the input ends before `end_tag`,
+/// the error tells where `start_tag` is, see [`unclosed`].
 pub fn block<'a, F: 'a, O>(
     start_tag: char,
     mut inner: F,
@@ -656,10 +776,17 @@ where
         let ok = preceded(lookahead(one_char(start_tag)), &mut inner)(input)?;
         match one_char(end_tag)(ok.remaining) {
             Ok(ok_end) => Ok(ok_end.replace(ok.parsed)),
-            Err(e) => Err(ok
-                .discarded_error
-                .map(InputParseErr::Recoverable)
-                .unwrap_or(e)), // TODO: maybe alt?
+            Err(e) => {
+                let e = ok
+                    .discarded_error
+                    .map(InputParseErr::Recoverable)
+                    .unwrap_or(e); // TODO: maybe alt?
+
+                match at_end(ok.remaining) {
+                    true => Err(unclosed(input, start_tag, e)),
+                    false => Err(e),
+                }
+            }
         }
     }
 }
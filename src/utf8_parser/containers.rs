@@ -1,14 +1,17 @@
 use crate::{
     utf8_parser,
     utf8_parser::{
-        basic::{nothing, one_char},
+        backtrack,
+        basic::{multispacews0, nothing, one_char},
         combinators,
         combinators::{
-            alt2, comma_list0, comma_list1, context, cut, lookahead, map, pair, spanned, terminated,
+            alt2, comma_list0, comma_list1, context, cut, lookahead, map, pair, preceded,
+            terminated, unpadded_spanned,
         },
-        primitive::ident,
+        primitive::{ident, raw_str},
         pt::{Expr, Ident, KeyValue, List, Map, Spanned, Struct, Tagged, Tuple, Untagged},
-        IResultLookahead, Input,
+        ron::{is_keyword_field, keyword_name_error, too_deep},
+        util, ErrorTree, Expectation, IResultLookahead, Input, InputParseErr, InputParseError,
     },
 };
 
@@ -20,7 +23,19 @@ fn ident_val_pair(input: Input) -> IResultLookahead<KeyValue<Ident>> {
         )),
         combinators::spanned(cut(utf8_parser::expr)),
     );
-    map(pair, |(k, v)| KeyValue { key: k, value: v })(input)
+    // `true` and `false` can only name fields as raw identifiers, e.g. `r#true`
+    let keyword = is_keyword_field(input);
+
+    map(pair, move |(key, value)| match keyword {
+        true => KeyValue {
+            value: Spanned {
+                value: keyword_name_error(&key),
+                ..value
+            },
+            key,
+        },
+        false => KeyValue { key, value },
+    })(input)
 }
 
 fn untagged_struct_inner(input: Input) -> IResultLookahead<Vec<Spanned<KeyValue<Ident>>>> {
@@ -56,6 +71,10 @@ pub fn rmap(input: Input) -> IResultLookahead<Map> {
 }
 
 pub fn list(input: Input) -> IResultLookahead<List> {
+    if input.recovery() {
+        return recovering_list(input);
+    }
+
     context(
         "list",
         combinators::block(
@@ -69,25 +88,166 @@ pub fn list(input: Input) -> IResultLookahead<List> {
     )(input)
 }
 
+/// Parses a list like [`list`], but an element that fails to parse becomes an
+/// [`Invalid`](Expr::Invalid) one and parsing continues after it
+fn recovering_list(start: Input) -> IResultLookahead<List> {
+    let ws = |input| multispacews0(input).map(|ok| ok.remaining);
+
+    let mut input = ws(lookahead(one_char('['))(start)?.remaining)?;
+    let mut elements = vec![];
+    while !input.is_empty() && !input.fragment().starts_with(']') {
+        match cut(utf8_parser::expr)(input) {
+            Ok(ok) => {
+                elements.push(Spanned {
+                    start: input,
+                    value: ok.parsed,
+                    end: ok.remaining,
+                });
+                input = ws(ok.remaining)?;
+            }
+            Err(InputParseErr::Recoverable(e) | InputParseErr::Fatal(e)) => {
+                let (element, next) = skip_invalid(start, backtrack::carry(input, &e), e);
+                elements.push(element);
+                input = next;
+            }
+        }
+
+        if !input.is_empty() && !input.fragment().starts_with(&[',', ']'][..]) {
+            let e = ErrorTree::expected(input, Expectation::OneOfChars(",]"));
+            let (element, next) = skip_invalid(start, input, e);
+            elements.push(element);
+            input = next;
+        }
+        if input.fragment().starts_with(',') {
+            input = ws(input.slice(1..))?;
+        }
+    }
+
+    match one_char(']')(input) {
+        Ok(ok) => Ok(ok.replace(List { elements })),
+        Err(InputParseErr::Recoverable(e) | InputParseErr::Fatal(e)) => {
+            let e = match input.is_empty() {
+                true => ErrorTree::unclosed(start, '[', e),
+                false => e,
+            };
+
+            Err(InputParseErr::fatal(InputParseError::add_context(
+                start, "list", false, e,
+            )))
+        }
+    }
+}
+
+/// The invalid element failing with `e` at `input`, in the list starting at
+/// `list`, and the input after it
+///
+/// Skips to the next `,` or `]` that isn't nested in brackets, a string or a
+/// comment, or to the end of the input. Brackets that are never closed end at
+/// the `]` of the list.
+fn skip_invalid<'a>(
+    list: Input<'a>,
+    input: Input<'a>,
+    e: InputParseError<'a>,
+) -> (Spanned<'a, Expr<'a>>, Input<'a>) {
+    let error = InputParseError::add_context(list, "list", false, e);
+
+    let mut end = input;
+    let mut open = vec![];
+    loop {
+        end = match multispacews0(end) {
+            Ok(ok) => ok.remaining,
+            Err(_) => end.slice(end.len()..),
+        };
+        let c = match end.chars().next() {
+            Some(c) => c,
+            None => break,
+        };
+        match c {
+            ',' if open.is_empty() => break,
+            '[' => open.push(']'),
+            '(' => open.push(')'),
+            '{' => open.push('}'),
+            ']' | ')' | '}' => match open.iter().rposition(|&close| close == c) {
+                Some(i) => open.truncate(i),
+                // Stray brackets are skipped, unless it's the end of the list
+                None if c == ']' => break,
+                None => {}
+            },
+            '"' => {
+                end = string_end(end);
+                continue;
+            }
+            'r' => {
+                if let Ok(ok) = raw_str(end) {
+                    end = ok.remaining;
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        end = end.slice(c.len_utf8()..);
+    }
+
+    let element = Spanned {
+        start: input,
+        value: Expr::Invalid(Box::new(error.into())),
+        end,
+    };
+
+    (element, end)
+}
+
+/// The input after the string starting at `input`, without checking escapes
+fn string_end(input: Input) -> Input {
+    let mut escaped = false;
+    for (i, c) in input.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return input.slice(i + 1..),
+            _ => {}
+        }
+    }
+
+    input.slice(input.len()..)
+}
+
 pub fn tagged(input: Input) -> IResultLookahead<Tagged> {
     context(
         "tagged expr",
         map(
+            // Whitespace after the expression isn't part of its span
             pair(
-                spanned(ident),
-                spanned(alt2(
-                    map(untagged_struct, Untagged::Struct),
-                    alt2(
-                        map(tuple, Untagged::Tuple),
-                        map(nothing, |_| Untagged::Unit),
-                    ),
-                )),
+                unpadded_spanned(ident),
+                alt2(
+                    preceded(multispacews0, unpadded_spanned(struct_or_tuple)),
+                    unpadded_spanned(map(nothing, |_| Untagged::Unit)),
+                ),
             ),
             |(ident, untagged)| Tagged { ident, untagged },
         ),
     )(input)
 }
 
+/// The struct or tuple after the name of a tagged expression
+///
+/// A name followed by `(` can only start a struct or tuple, so it is cut
+/// there: its errors are reported as they are, instead of backtracking to a
+/// unit and failing at the `(`.
+fn struct_or_tuple(input: Input) -> IResultLookahead<Untagged> {
+    if !input.fragment().starts_with('(') {
+        return Err(InputParseErr::recoverable(ErrorTree::expected(
+            input,
+            Expectation::Char('('),
+        )));
+    }
+
+    cut(alt2(
+        map(untagged_struct, Untagged::Struct),
+        map(tuple, Untagged::Tuple),
+    ))(input)
+}
+
 pub fn tuple(input: Input) -> IResultLookahead<Tuple> {
     context(
         "tuple",
@@ -97,3 +257,186 @@ pub fn tuple(input: Input) -> IResultLookahead<Tuple> {
         ),
     )(input)
 }
+
+/// A list or tuple, see [`nested_sequence`]
+#[derive(Clone, Copy, Debug)]
+pub enum Sequence {
+    List,
+    Tuple,
+}
+
+impl Sequence {
+    /// The sequence `input` starts with, `None` for a `(` starting a struct
+    pub fn starting(input: Input) -> Option<Self> {
+        let field_name = terminated(combinators::spanned(ident::ident), one_char(':'));
+
+        match input.fragment().chars().next()? {
+            '[' => Some(Sequence::List),
+            '(' if lookahead(preceded(one_char('('), field_name))(input).is_err() => {
+                Some(Sequence::Tuple)
+            }
+            _ => None,
+        }
+    }
+
+    fn start(self) -> char {
+        match self {
+            Sequence::List => '[',
+            Sequence::Tuple => '(',
+        }
+    }
+
+    fn end(self) -> char {
+        match self {
+            Sequence::List => ']',
+            Sequence::Tuple => ')',
+        }
+    }
+
+    fn context(self) -> &'static str {
+        match self {
+            Sequence::List => "list",
+            Sequence::Tuple => "tuple",
+        }
+    }
+
+    fn to_expr<'a>(self, elements: Vec<Spanned<'a, Expr<'a>>>) -> Expr<'a> {
+        match self {
+            Sequence::List => Expr::List(List { elements }),
+            Sequence::Tuple => Expr::Tuple(Tuple { elements }),
+        }
+    }
+}
+
+/// A sequence [`nested_sequence`] hasn't reached the end of yet
+struct Frame<'a> {
+    sequence: Sequence,
+    start: Input<'a>,
+    elements: Vec<Spanned<'a, Expr<'a>>>,
+}
+
+impl<'a> Frame<'a> {
+    fn error(&self, e: InputParseError<'a>) -> InputParseErr<'a> {
+        InputParseErr::fatal(InputParseError::add_context(
+            self.start,
+            self.sequence.context(),
+            false,
+            e,
+        ))
+    }
+
+    /// Whether this is a list continuing after invalid elements
+    fn recovers(&self) -> bool {
+        matches!(self.sequence, Sequence::List) && self.start.recovery()
+    }
+}
+
+/// Parses lists and tuples nested in each other with a stack of the open
+/// ones instead of recursion, so the depth isn't limited by the call stack
+///
+/// Elements which aren't lists or tuples are parsed by
+/// [`expr`](utf8_parser::expr). The result is the same as for [`list`] and
+/// [`tuple`], but errors only have the context of the innermost sequence.
+pub fn nested_sequence(mut input: Input) -> IResultLookahead<Expr> {
+    let ws = |input| multispacews0(input).map(|ok| ok.remaining);
+    let mut stack: Vec<Frame> = vec![];
+    // The depth of the outermost sequence, each open one adds one
+    let depth = input.depth();
+
+    'element: loop {
+        while let Some(sequence) = Sequence::starting(input) {
+            if depth + stack.len() > input.max_depth() {
+                return Err(too_deep(input));
+            }
+            stack.push(Frame {
+                sequence,
+                start: input,
+                elements: vec![],
+            });
+            input = ws(input.slice(1..))?;
+        }
+
+        let element_depth = depth + stack.len();
+        let frame = match stack.last_mut() {
+            Some(frame) => frame,
+            None => return util::base_err(input, Expectation::OneOfChars("[(")),
+        };
+        if !input.fragment().starts_with(frame.sequence.end()) {
+            match cut(utf8_parser::expr)(input.with_depth(element_depth)) {
+                Ok(ok) => {
+                    frame.elements.push(Spanned {
+                        start: input,
+                        value: ok.parsed,
+                        end: ok.remaining,
+                    });
+                    input = ws(ok.remaining)?;
+                }
+                Err(InputParseErr::Fatal(e)) if frame.recovers() && !input.is_empty() => {
+                    let (element, next) = skip_invalid(frame.start, input, e);
+                    frame.elements.push(element);
+                    input = next;
+                }
+                Err(e) => return Err(e),
+            }
+            if input.fragment().starts_with(',') {
+                input = ws(input.slice(1..))?;
+                continue;
+            }
+        }
+
+        // Close sequences until one continues with a comma
+        while let Some(mut frame) = stack.pop() {
+            if !input.fragment().starts_with(frame.sequence.end()) {
+                let expected = match frame.sequence {
+                    Sequence::List => Expectation::OneOfChars(",]"),
+                    Sequence::Tuple => Expectation::OneOfChars(",)"),
+                };
+                let e = ErrorTree::expected(input, expected);
+                if input.is_empty() {
+                    return Err(frame.error(ErrorTree::unclosed(
+                        frame.start,
+                        frame.sequence.start(),
+                        e,
+                    )));
+                }
+                if !frame.recovers() {
+                    return Err(frame.error(e));
+                }
+
+                let (element, next) = skip_invalid(frame.start, input, e);
+                frame.elements.push(element);
+                input = next;
+                if input.fragment().starts_with(',') {
+                    input = ws(input.slice(1..))?;
+                    stack.push(frame);
+                    continue 'element;
+                } else if input.is_empty() {
+                    let e = ErrorTree::expected(input, expected);
+                    return Err(frame.error(ErrorTree::unclosed(
+                        frame.start,
+                        frame.sequence.start(),
+                        e,
+                    )));
+                }
+            }
+            input = input.slice(1..);
+
+            let value = frame.sequence.to_expr(frame.elements);
+            let parent = match stack.last_mut() {
+                Some(parent) => parent,
+                None => return Ok((input.with_depth(depth), value).into()),
+            };
+            parent.elements.push(Spanned {
+                start: frame.start,
+                value,
+                end: input,
+            });
+
+            input = ws(input)?;
+            if input.fragment().starts_with(',') {
+                input = ws(input.slice(1..))?;
+                continue 'element;
+            }
+        }
+    }
+}
@@ -2,14 +2,25 @@
 //! information about parse failures than the built-in nom error types.
 
 use std::{
+    cmp::Ordering,
     error::Error,
     fmt::{self, Debug, Display, Formatter},
+    sync::Arc,
 };
 
-use crate::{location::Location, utf8_parser::Input, util::write_pretty_list};
+use crate::{
+    error::{ErrorContext, ExpectedToken, Severity},
+    location::Location,
+    utf8_parser::Input,
+    util::write_pretty_list,
+};
 
 pub type InputParseError<'a> = ErrorTree<Input<'a>>;
 
+/// How many contexts of each chain error messages show by default, see
+/// [`Input::with_max_error_contexts`]
+pub const DEFAULT_MAX_ERROR_CONTEXTS: usize = 4;
+
 #[derive(Debug)]
 pub struct _PrivateConstructor {
     private: (),
@@ -35,15 +46,39 @@ impl<'a> InputParseErr<'a> {
     }
 
     pub fn is_recoverable(&self) -> bool {
+        self.severity() == Severity::Recoverable
+    }
+
+    pub fn severity(&self) -> Severity {
         match self {
-            InputParseErr::Recoverable(_) => true,
-            InputParseErr::Fatal(_) => false,
+            InputParseErr::Recoverable(_) => Severity::Recoverable,
+            InputParseErr::Fatal(_) => Severity::Fatal,
         }
     }
+
+    pub(crate) fn into_error(self) -> crate::error::Error {
+        let severity = self.severity();
+        let (InputParseErr::Recoverable(e) | InputParseErr::Fatal(e)) = self;
+        let mut error = match e.furthest_external() {
+            Some(external) => external.clone(),
+            None => crate::error::Error::from(e),
+        };
+        if let Some(context) = &mut error.context {
+            context.severity = Some(severity);
+        }
+
+        error
+    }
 }
 
+/// The alternate form, `{:#}`, starts with the [`Severity`], e.g. to debug
+/// why `alt2` did or didn't try another branch
 impl<'a> Display for InputParseErr<'a> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            write!(f, "{}: ", self.severity())?;
+        }
+
         match self {
             InputParseErr::Recoverable(e) => write!(f, "{}", e),
             InputParseErr::Fatal(e) => write!(f, "{}", e),
@@ -51,6 +86,36 @@ impl<'a> Display for InputParseErr<'a> {
     }
 }
 
+/// How `alt2` merges the errors of two branches that both failed,
+/// see [`Input::with_alt_errors`]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum AltErrors {
+    /// Keep the errors of both branches
+    All,
+    /// Keep only the error of the branch that got further into the input,
+    /// both if they got equally far ("longest match wins")
+    #[default]
+    Furthest,
+}
+
+impl AltErrors {
+    pub(crate) fn merge<'a>(
+        self,
+        first: InputParseError<'a>,
+        second: InputParseError<'a>,
+    ) -> InputParseError<'a> {
+        if self == AltErrors::Furthest {
+            match first.max_offset().cmp(&second.max_offset()) {
+                Ordering::Greater => return first,
+                Ordering::Less => return second,
+                Ordering::Equal => {}
+            }
+        }
+
+        ErrorTree::alt(first, second)
+    }
+}
+
 #[non_exhaustive]
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum Expectation {
@@ -112,6 +177,8 @@ pub enum Expectation {
     Something,
 }
 
+/// The alternate form, `{:#}`, names what is expected instead of listing its
+/// tokens where it can, e.g. "an identifier"
 impl Display for Expectation {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match *self {
@@ -123,6 +190,12 @@ impl Display for Expectation {
             Expectation::OneOfTags(one_of) => {
                 write_pretty_list(f, one_of.iter(), |f, c| write!(f, "{:?}", c))
             }
+            // The chars an identifier starts with
+            Expectation::OneOfExpectations([Expectation::Alpha, Expectation::Char('_')])
+                if f.alternate() =>
+            {
+                write!(f, "an identifier")
+            }
             Expectation::OneOfExpectations(one_of) => {
                 write_pretty_list(f, one_of.iter(), |f, c| write!(f, "{}", c))
             }
@@ -149,6 +222,8 @@ impl Display for Expectation {
 /// These are the different specific things that can go wrong at a particular
 /// location during a nom parse. Many of these are collected into an
 /// [`ErrorTree`].
+///
+/// External errors are equal if they display the same.
 #[derive(Debug)]
 pub enum BaseErrorKind {
     /// Something specific was expected, such as a specific
@@ -159,6 +234,18 @@ pub enum BaseErrorKind {
     External(Box<dyn Error + Send + Sync + 'static>),
 }
 
+impl PartialEq for BaseErrorKind {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (BaseErrorKind::Expected(a), BaseErrorKind::Expected(b)) => a == b,
+            (BaseErrorKind::External(a), BaseErrorKind::External(b)) => {
+                a.to_string() == b.to_string()
+            }
+            _ => false,
+        }
+    }
+}
+
 impl Display for BaseErrorKind {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match *self {
@@ -176,17 +263,27 @@ pub enum StackContext {
     /// The [`context`][crate::parser_ext::ParserExt::context] combinator
     /// attached a message as context for a subparser error.
     Context(&'static str),
+    /// The input ended before the bracket opened at the context's location
+    /// was closed
+    Unclosed(char),
 }
 
 impl Display for StackContext {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match *self {
             StackContext::Context(ctx) => write!(f, "could not match {:?}", ctx),
+            StackContext::Unclosed(c) => write!(f, "unclosed {:?} opened", c),
         }
     }
 }
 
-#[derive(Debug)]
+/// The errors of all branches of the grammar that were tried, with the
+/// contexts they failed in
+///
+/// The tree of a parsing error is kept in the [`Error`](crate::Error), see
+/// [`Error::error_tree`](crate::Error::error_tree), and can be traversed
+/// with an [`ErrorTreeVisitor`], e.g. to label all locations involved.
+#[derive(Debug, PartialEq)]
 pub enum ErrorTree<I> {
     /// A specific error event at a specific location. Often this will indicate
     /// that something like a tag or character was expected at that location.
@@ -222,15 +319,112 @@ pub enum ErrorTree<I> {
     //   (Context or Kind)
 }
 
+impl<'a> ErrorTree<Input<'a>> {
+    /// Byte offset of the furthest location in the tree
+    pub(crate) fn max_offset(&self) -> usize {
+        match self {
+            ErrorTree::Base { location, .. } => location.offset().bytes(),
+            ErrorTree::Stack { base, .. } => base.max_offset(),
+            ErrorTree::Alt(v) => v
+                .iter()
+                .map(ErrorTree::max_offset)
+                .max()
+                .expect("alternatives aren't empty"),
+        }
+    }
+
+    /// The furthest location in the tree
+    pub(crate) fn max_input(&self) -> Input<'a> {
+        match self {
+            ErrorTree::Base { location, .. } => *location,
+            ErrorTree::Stack { base, .. } => base.max_input(),
+            ErrorTree::Alt(v) => v
+                .iter()
+                .map(ErrorTree::max_input)
+                .max_by_key(|input| input.offset().bytes())
+                .expect("alternatives aren't empty"),
+        }
+    }
+
+    /// The error of this crate at the furthest location, see
+    /// [`external`](Self::external)
+    fn furthest_external(&self) -> Option<&crate::error::Error> {
+        match self {
+            ErrorTree::Base {
+                kind: BaseErrorKind::External(e),
+                ..
+            } => e.downcast_ref(),
+            ErrorTree::Base { .. } => None,
+            ErrorTree::Stack { base, .. } => base.furthest_external(),
+            ErrorTree::Alt(v) => v
+                .iter()
+                .max_by_key(|e| e.max_offset())
+                .and_then(ErrorTree::furthest_external),
+        }
+    }
+
+    /// The contexts of the furthest error, innermost first
+    fn furthest_contexts(&self) -> Vec<&(Input<'a>, StackContext)> {
+        match self {
+            ErrorTree::Base { .. } => vec![],
+            ErrorTree::Stack { base, contexts, .. } => {
+                let mut furthest = base.furthest_contexts();
+                furthest.extend(contexts);
+                furthest
+            }
+            ErrorTree::Alt(v) => v
+                .iter()
+                .max_by_key(|e| e.max_offset())
+                .map_or(vec![], ErrorTree::furthest_contexts),
+        }
+    }
+
+    /// Where the bracket or string that is still open at the end of the
+    /// input was opened, if the error is there, with a label for it
+    fn unclosed_label(&self) -> Option<(Location, String)> {
+        let contexts = self.furthest_contexts();
+        let unclosed = contexts
+            .iter()
+            .find_map(|(location, context)| match context {
+                StackContext::Unclosed(c) => Some((*location, *c)),
+                _ => None,
+            });
+        if let Some((location, c)) = unclosed {
+            return Some((location.into(), format!("unclosed `{}` opened here", c)));
+        }
+
+        let (location, _) = contexts.first()?;
+        match self.max_input().fragment().is_empty() && location.fragment().starts_with('"') {
+            true => Some(((*location).into(), "unclosed string starts here".to_owned())),
+            false => None,
+        }
+    }
+}
+
 impl<I> ErrorTree<I> {
-    pub(crate) fn max_location(&self) -> &I
+    /// The furthest location in the tree, which is the location of the
+    /// [`Error`](crate::Error) it becomes
+    pub fn max_location(&self) -> &I
     where
         I: Ord,
     {
         match self {
             ErrorTree::Base { location, .. } => location,
             ErrorTree::Stack { base, .. } => base.max_location(),
-            ErrorTree::Alt(v) => v.iter().map(ErrorTree::max_location).max().unwrap(),
+            ErrorTree::Alt(v) => v
+                .iter()
+                .map(ErrorTree::max_location)
+                .max()
+                .expect("alternatives aren't empty"),
+        }
+    }
+
+    /// The location of the first error in the tree
+    fn any_location(&self) -> &I {
+        match self {
+            ErrorTree::Base { location, .. } => location,
+            ErrorTree::Stack { base, .. } => base.any_location(),
+            ErrorTree::Alt(v) => v[0].any_location(),
         }
     }
 
@@ -241,15 +435,28 @@ impl<I> ErrorTree<I> {
         }
     }
 
+    /// An error of this crate at `location`, which is reported as it is
+    /// instead of as a parse error, e.g. that values are nested too deep
+    pub(crate) fn external(location: I, error: crate::error::Error) -> Self {
+        ErrorTree::Base {
+            location,
+            kind: BaseErrorKind::External(Box::new(error)),
+        }
+    }
+
     pub(crate) fn alt(first: Self, second: Self) -> Self {
         match (first, second) {
             (ErrorTree::Alt(mut alt), ErrorTree::Alt(alt2)) => {
                 alt.extend(alt2);
                 ErrorTree::Alt(alt)
             }
-            (ErrorTree::Alt(mut alt), x) | (x, ErrorTree::Alt(mut alt)) => {
-                // TODO: should we preserve order?
-                alt.push(x);
+            (ErrorTree::Alt(mut alt), second) => {
+                alt.push(second);
+                ErrorTree::Alt(alt)
+            }
+            // Keeping the order the alternatives were tried in
+            (first, ErrorTree::Alt(mut alt)) => {
+                alt.insert(0, first);
                 ErrorTree::Alt(alt)
             }
             (first, second) => ErrorTree::Alt(vec![first, second]),
@@ -295,20 +502,143 @@ impl<I> ErrorTree<I> {
     }
 }
 
-impl<I: Display> Display for ErrorTree<I> {
+/// Visits the errors of an [`ErrorTree`], e.g. to build a diagnostic with a
+/// label for every location involved
+///
+/// Stacks are visited before their base, their contexts outermost first;
+/// alternatives in the order they were tried in. Like
+/// [`ast::Visitor`](crate::ast::Visitor), the defaults visit everything, so
+/// implementations only override what they are interested in.
+pub trait ErrorTreeVisitor<I> {
+    fn visit_tree(&mut self, tree: &ErrorTree<I>) {
+        walk_error_tree(self, tree);
+    }
+
+    /// An error at `location`, a leaf of the tree
+    fn visit_base(&mut self, _location: &I, _kind: &BaseErrorKind) {}
+
+    /// A context the errors below it happened in, which started at
+    /// `location`, e.g. `"struct"` where the struct failing to parse starts
+    fn visit_context(&mut self, _location: &I, _context: StackContext) {}
+}
+
+/// Visits the contexts and children of `tree`, for [`ErrorTreeVisitor`]
+/// implementations that override [`visit_tree`](ErrorTreeVisitor::visit_tree)
+/// but still want to descend
+pub fn walk_error_tree<I, V: ErrorTreeVisitor<I> + ?Sized>(visitor: &mut V, tree: &ErrorTree<I>) {
+    match tree {
+        ErrorTree::Base { location, kind } => visitor.visit_base(location, kind),
+        ErrorTree::Stack { base, contexts, .. } => {
+            for (location, context) in contexts.iter().rev() {
+                visitor.visit_context(location, *context);
+            }
+            visitor.visit_tree(base);
+        }
+        ErrorTree::Alt(siblings) => siblings.iter().for_each(|tree| visitor.visit_tree(tree)),
+    }
+}
+
+/// Shows all contexts and expected tokens, e.g. to debug the parser
+impl<I: Display + PartialEq> Display for ErrorTree<I> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.render(None, true))
+    }
+}
+
+/// An [`ErrorTree`] displayed with at most `max_contexts` contexts of each
+/// chain, see [`Input::with_max_error_contexts`], and with
+/// [`Input::with_verbose_expectations`] unless `verbose`
+struct Rendered<'a, I> {
+    tree: &'a ErrorTree<I>,
+    max_contexts: Option<usize>,
+    verbose: bool,
+}
+
+impl<I> ErrorTree<I> {
+    fn render(&self, max_contexts: Option<usize>, verbose: bool) -> Rendered<'_, I> {
+        Rendered {
+            tree: self,
+            max_contexts,
+            verbose,
+        }
+    }
+
+    /// Whether all errors in the tree are at `location`
+    fn all_at(&self, location: &I) -> bool
+    where
+        I: PartialEq,
+    {
         match self {
+            ErrorTree::Base { location: l, .. } => l == location,
+            ErrorTree::Stack { base, .. } => base.all_at(location),
+            ErrorTree::Alt(v) => v.iter().all(|e| e.all_at(location)),
+        }
+    }
+}
+
+impl<I: Display + PartialEq> Display for Rendered<'_, I> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.tree {
+            ErrorTree::Base {
+                location,
+                kind: BaseErrorKind::Expected(expectation),
+            } if !self.verbose => write!(f, "expected {:#} at {:#}", expectation, location),
             ErrorTree::Base { location, kind } => write!(f, "{} at {:#}", kind, location),
             ErrorTree::Stack {
                 contexts,
                 base,
                 finalized: _,
             } => {
-                contexts.iter().rev().try_for_each(|(location, context)| {
-                    writeln!(f, "{} at {:#} because", context, location)
-                })?;
+                // Nothing of the expression matched, so the tokens that could
+                // start one don't help
+                let no_value = match contexts.first() {
+                    Some((location, StackContext::Context("expression")))
+                        if !self.verbose && base.all_at(location) =>
+                    {
+                        Some(location)
+                    }
+                    _ => None,
+                };
+                // The input ended in an unclosed bracket, which tells more
+                // than what was expected there
+                let unclosed = match contexts.first() {
+                    Some((location, context @ StackContext::Unclosed(_))) => {
+                        Some((location, context))
+                    }
+                    _ => None,
+                };
+                let contexts = &contexts[(no_value.is_some() || unclosed.is_some()) as usize..];
+
+                // Keep the outermost and innermost contexts, they tell what
+                // was parsed and what failed
+                let (shown_first, collapsed) = match self.max_contexts {
+                    Some(max) if contexts.len() > max => (max - max / 2, contexts.len() - max),
+                    _ => (contexts.len(), 0),
+                };
+                for (i, (location, context)) in contexts.iter().rev().enumerate() {
+                    if i == shown_first && collapsed > 0 {
+                        writeln!(f, "... {} more contexts ...", collapsed)?;
+                    }
+                    if (shown_first..shown_first + collapsed).contains(&i) {
+                        continue;
+                    }
+                    writeln!(f, "{} at {:#} because", context, location)?;
+                }
 
-                write!(f, "{}", indent(base))
+                let innermost = match (no_value, unclosed) {
+                    (Some(location), _) => format!("expected a value at {:#}", location),
+                    (None, Some((location, context))) => format!(
+                        "{} at {:#}, {}",
+                        context,
+                        location,
+                        base.render(self.max_contexts, self.verbose)
+                    ),
+                    (None, None) => base.render(self.max_contexts, self.verbose).to_string(),
+                };
+                match contexts.is_empty() && (no_value.is_some() || unclosed.is_some()) {
+                    true => write!(f, "{}", innermost),
+                    false => write!(f, "{}", indent(innermost)),
+                }
             }
             ErrorTree::Alt(siblings) => {
                 writeln!(f, "none of these matched:")?;
@@ -318,7 +648,9 @@ impl<I: Display> Display for ErrorTree<I> {
                     indent(
                         siblings
                             .iter()
-                            .map(ToString::to_string)
+                            .map(|sibling| {
+                                sibling.render(self.max_contexts, self.verbose).to_string()
+                            })
                             .collect::<Vec<_>>()
                             .join(" or\n")
                     )
@@ -328,9 +660,22 @@ impl<I: Display> Display for ErrorTree<I> {
     }
 }
 
-impl<I: Display + Debug> Error for ErrorTree<I> {}
+impl<I: Display + Debug + PartialEq> Error for ErrorTree<I> {}
 
 impl<I> ErrorTree<I> {
+    /// `other`, which failed at the end of the input, with the context of the
+    /// `delimiter` opened at `location` that wasn't closed
+    ///
+    /// Unlike [`add_context`](Self::add_context), this also adds the context
+    /// to stacks that were finalized.
+    pub fn unclosed(location: I, delimiter: char, other: Self) -> Self {
+        ErrorTree::Stack {
+            base: Box::new(other),
+            finalized: false,
+            contexts: vec![(location, StackContext::Unclosed(delimiter))],
+        }
+    }
+
     /// Similar to append: Create a new error with some added context
     pub fn add_context(location: I, ctx: &'static str, final_context: bool, other: Self) -> Self {
         let context = (location, StackContext::Context(ctx));
@@ -364,28 +709,108 @@ impl<I> ErrorTree<I> {
     }
 }
 
+impl Expectation {
+    fn push_tokens(self, tokens: &mut Vec<ExpectedToken>) {
+        let token = match self {
+            Expectation::Tag(tag) => ExpectedToken::Tag(tag),
+            Expectation::Char(c) => ExpectedToken::Char(c),
+            Expectation::OneOfChars(one_of) => {
+                one_of
+                    .chars()
+                    .for_each(|c| Expectation::Char(c).push_tokens(tokens));
+                return;
+            }
+            Expectation::OneOfTags(one_of) => {
+                one_of
+                    .iter()
+                    .for_each(|tag| Expectation::Tag(tag).push_tokens(tokens));
+                return;
+            }
+            Expectation::OneOfExpectations(one_of) => {
+                one_of.iter().for_each(|e| e.push_tokens(tokens));
+                return;
+            }
+            Expectation::Alpha => ExpectedToken::Letter,
+            Expectation::Digit => ExpectedToken::Digit,
+            Expectation::DigitFirst => ExpectedToken::NonZeroDigit,
+            Expectation::HexDigit => ExpectedToken::HexDigit,
+            Expectation::UnicodeHexSequence { .. } => ExpectedToken::UnicodeEscape,
+            Expectation::OctDigit => ExpectedToken::OctDigit,
+            Expectation::AlphaNumeric => ExpectedToken::AlphaNumeric,
+            Expectation::Space | Expectation::Multispace | Expectation::CrLf => {
+                ExpectedToken::Whitespace
+            }
+            Expectation::RawStringEnd => ExpectedToken::RawStringEnd,
+            Expectation::BlockCommentEnd => ExpectedToken::BlockCommentEnd,
+            Expectation::Eof => ExpectedToken::Eof,
+            Expectation::Something => ExpectedToken::Something,
+        };
+
+        if !tokens.contains(&token) {
+            tokens.push(token);
+        }
+    }
+}
+
+impl<I: PartialEq> ErrorTree<I> {
+    /// The tokens expected by the branches that failed at `location`
+    fn expected_tokens_at(&self, location: &I, tokens: &mut Vec<ExpectedToken>) {
+        match self {
+            ErrorTree::Base {
+                location: l,
+                kind: BaseErrorKind::Expected(expectation),
+            } if l == location => expectation.push_tokens(tokens),
+            ErrorTree::Base { .. } => {}
+            ErrorTree::Stack { base, .. } => base.expected_tokens_at(location, tokens),
+            ErrorTree::Alt(siblings) => siblings
+                .iter()
+                .for_each(|e| e.expected_tokens_at(location, tokens)),
+        }
+    }
+}
+
+impl ErrorTree<Location> {
+    /// The error with a message showing at most `max_contexts` contexts of
+    /// each chain, and all expected tokens if `verbose`
+    fn into_error(self, max_contexts: Option<usize>, verbose: bool) -> crate::error::Error {
+        let max_location = *self.max_location();
+        let mut expected = vec![];
+        self.expected_tokens_at(&max_location, &mut expected);
+        let message = self.render(max_contexts, verbose).to_string();
+
+        crate::error::Error {
+            kind: crate::error::ErrorKind::ParseError(message),
+            context: Some(Box::new(ErrorContext {
+                expected,
+                error_tree: Some(Arc::new(self)),
+                ..Default::default()
+            })),
+        }
+        .context_loc(max_location, max_location.add_columns(1))
+    }
+}
+
 impl From<ErrorTree<Location>> for crate::error::Error {
     fn from(e: ErrorTree<Location>) -> Self {
-        let max_location = *e.max_location();
-        let max_location: Location = max_location.into();
-
-        Self {
-            kind: crate::error::ErrorKind::ParseError(e.to_string()),
-            context: None,
-        }
-        .context_loc(
-            max_location,
-            Location {
-                line: max_location.line,
-                column: max_location.column + 1,
-            },
-        )
+        e.into_error(None, true)
     }
 }
 
+/// Shows as many contexts and expected tokens as the input allows, see
+/// [`Input::with_max_error_contexts`] and
+/// [`Input::with_verbose_expectations`]
 impl From<InputParseError<'_>> for crate::error::Error {
     fn from(e: InputParseError) -> Self {
-        e.calc_locations().into()
+        let input = *e.any_location();
+        let unclosed = e.unclosed_label();
+
+        let error = e
+            .calc_locations()
+            .into_error(input.max_error_contexts(), input.verbose_expectations());
+        match unclosed {
+            Some((start, message)) => error.context_label(start, start.add_columns(1), message),
+            None => error,
+        }
     }
 }
 
@@ -436,3 +861,22 @@ impl Display for Indented {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alt_order() {
+        let expected = |c| ErrorTree::expected(0, Expectation::Char(c));
+        let alt = ErrorTree::alt(expected('a'), ErrorTree::alt(expected('b'), expected('c')));
+        let alt = ErrorTree::alt(alt, expected('d'));
+
+        let mut tokens = vec![];
+        alt.expected_tokens_at(&0, &mut tokens);
+        assert_eq!(
+            tokens,
+            ['a', 'b', 'c', 'd'].map(ExpectedToken::Char).to_vec()
+        );
+    }
+}
@@ -0,0 +1,254 @@
+//! Extensions which documents enable with `#![enable(..)]`
+//!
+//! The built-in extensions are implemented the same way as user-registered
+//! ones, see [`RonExtension`] and [`ExtensionRegistry`].
+
+use crate::{
+    ast::{self, Attribute, Extension},
+    error::{Error, ErrorKind},
+    utf8_parser::{
+        primitive::{color, quantity},
+        pt,
+        util::parse_all,
+    },
+};
+
+/// An extension, which only takes effect in documents enabling it
+///
+/// Registries are shared between threads, e.g. by the deserializer's `Scope`,
+/// so extensions have to be `Send` and `Sync`.
+pub trait RonExtension: Send + Sync {
+    /// The name used in `#![enable(..)]`
+    fn name(&self) -> &str;
+
+    /// Parses a literal plain RON doesn't have: a number directly followed by
+    /// a suffix, e.g. `250ms`, or a `#` directly followed by letters and
+    /// digits, e.g. `#ff8000`.
+    ///
+    /// Returns `None` for literals this extension doesn't support and an error
+    /// message for malformed ones it does support.
+    fn parse_literal<'a>(&self, literal: &'a str) -> Option<Result<ast::Expr<'a>, String>> {
+        let _ = literal;
+        None
+    }
+
+    /// Modifies the document after parsing, before it is deserialized
+    fn transform(&self, ron: &mut ast::Ron) -> Result<(), Error> {
+        let _ = ron;
+        Ok(())
+    }
+
+    /// Changes to how the deserializer treats the document
+    fn deserialize_flags(&self) -> DeserializeFlags {
+        DeserializeFlags::default()
+    }
+}
+
+/// Deserializer behavior an extension turns on, all off by default
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct DeserializeFlags {
+    /// Accept `x` where `Some(x)` is expected
+    pub implicit_some: bool,
+    /// Accept the inner value where a newtype struct is expected
+    pub unwrap_newtypes: bool,
+}
+
+impl DeserializeFlags {
+    fn union(self, other: Self) -> Self {
+        DeserializeFlags {
+            implicit_some: self.implicit_some || other.implicit_some,
+            unwrap_newtypes: self.unwrap_newtypes || other.unwrap_newtypes,
+        }
+    }
+}
+
+/// The extensions documents may enable
+///
+/// Created with the built-in extensions, more can be added with
+/// [`with`](ExtensionRegistry::with).
+pub struct ExtensionRegistry {
+    extensions: Vec<Box<dyn RonExtension>>,
+}
+
+impl ExtensionRegistry {
+    /// A registry with only the built-in extensions
+    pub fn new() -> Self {
+        ExtensionRegistry {
+            extensions: Extension::ALL
+                .iter()
+                .cloned()
+                .map(|extension| Box::new(Builtin(extension)) as Box<dyn RonExtension>)
+                .collect(),
+        }
+    }
+
+    /// Registers `extension`, replacing any extension with the same name
+    #[must_use]
+    pub fn with(mut self, extension: impl RonExtension + 'static) -> Self {
+        self.extensions.retain(|e| e.name() != extension.name());
+        self.extensions.push(Box::new(extension));
+
+        self
+    }
+
+    /// The extension called `name`
+    pub fn get(&self, name: &str) -> Option<&dyn RonExtension> {
+        self.extensions
+            .iter()
+            .find(|e| e.name() == name)
+            .map(|e| e.as_ref())
+    }
+
+    /// The names of all registered extensions
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.extensions.iter().map(|e| e.name())
+    }
+
+    /// The registered extensions `ron` enables
+    pub fn enabled(&self, ron: &ast::Ron) -> Vec<&dyn RonExtension> {
+        ron.attributes
+            .iter()
+            .flat_map(|attribute| match &attribute.value {
+                Attribute::Enable(extensions) => extensions.value.iter(),
+                Attribute::Version(_) => [].iter(),
+            })
+            .filter_map(|extension| self.get(extension.value.name()))
+            .collect()
+    }
+
+    /// The deserializer flags of all extensions `ron` enables
+    pub fn deserialize_flags(&self, ron: &ast::Ron) -> DeserializeFlags {
+        self.enabled(ron)
+            .iter()
+            .map(|extension| extension.deserialize_flags())
+            .fold(DeserializeFlags::default(), DeserializeFlags::union)
+    }
+
+    /// Checks that all enabled extensions are registered and lets them parse
+    /// the literals of `ron`
+    pub(crate) fn resolve_literals(&self, ron: &mut pt::Ron) -> Result<(), Error> {
+        let mut enabled = vec![];
+        for attribute in &ron.attributes {
+            match &attribute.value {
+                pt::Attribute::Enable(extensions) => {
+                    for extension in &extensions.value {
+                        match self.get(extension.value.name()) {
+                            Some(registered) => enabled.push(registered),
+                            None => {
+                                return Err(error(
+                                    ErrorKind::UnknownExtension(extension.value.name().to_owned()),
+                                    extension,
+                                ))
+                            }
+                        }
+                    }
+                }
+                pt::Attribute::Version(_) => {}
+            }
+        }
+
+        self.resolve_expr(&mut ron.expr, &enabled)
+    }
+
+    fn resolve_expr<'a>(
+        &self,
+        expr: &mut pt::Spanned<'a, pt::Expr<'a>>,
+        enabled: &[&dyn RonExtension],
+    ) -> Result<(), Error> {
+        if let pt::Expr::Literal(literal) = expr.value {
+            let resolved = self
+                .resolve_literal(literal, enabled)
+                .map_err(|kind| error(kind, expr))?;
+            expr.value = pt::Expr::Resolved(Box::new(resolved));
+        }
+
+        expr.value
+            .children_mut()
+            .into_iter()
+            .try_for_each(|child| self.resolve_expr(child, enabled))
+    }
+
+    fn resolve_literal<'a>(
+        &self,
+        literal: &'a str,
+        enabled: &[&dyn RonExtension],
+    ) -> Result<ast::Expr<'a>, ErrorKind> {
+        match enabled.iter().find_map(|e| e.parse_literal(literal)) {
+            Some(Ok(expr)) => Ok(expr),
+            Some(Err(message)) => Err(ErrorKind::Custom(format!(
+                "invalid literal `{}`, {}",
+                literal, message
+            ))),
+            // Point to the extension to enable, if any
+            None => match self
+                .extensions
+                .iter()
+                .find(|e| e.parse_literal(literal).is_some())
+            {
+                Some(extension) => Err(ErrorKind::ExtensionNotEnabled(extension.name().to_owned())),
+                None => Err(ErrorKind::UnsupportedLiteral(literal.to_owned())),
+            },
+        }
+    }
+
+    /// Runs the transforms of the extensions `ron` enables
+    pub(crate) fn transform(&self, ron: &mut ast::Ron) -> Result<(), Error> {
+        self.enabled(ron)
+            .into_iter()
+            .try_for_each(|extension| extension.transform(ron))
+    }
+}
+
+impl Default for ExtensionRegistry {
+    fn default() -> Self {
+        ExtensionRegistry::new()
+    }
+}
+
+fn error<T>(kind: ErrorKind, spanned: &pt::Spanned<T>) -> Error {
+    Error {
+        kind,
+        context: None,
+    }
+    .context_loc(spanned.start.into(), spanned.end.into())
+}
+
+/// One of the extensions built into this crate
+struct Builtin(Extension);
+
+impl RonExtension for Builtin {
+    fn name(&self) -> &str {
+        self.0.name()
+    }
+
+    fn parse_literal<'a>(&self, literal: &'a str) -> Option<Result<ast::Expr<'a>, String>> {
+        match self.0 {
+            Extension::UnitLiterals => {
+                parse_all(quantity, literal).map(|q| Ok(ast::Expr::Quantity(Box::new(q.into()))))
+            }
+            Extension::ColorLiterals => {
+                let digits = literal.strip_prefix('#')?;
+                if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return None;
+                }
+
+                Some(
+                    parse_all(color, literal)
+                        .map(ast::Expr::Color)
+                        .ok_or_else(|| {
+                            format!("expected 6 or 8 hex digits, found {}", digits.len())
+                        }),
+                )
+            }
+            _ => None,
+        }
+    }
+
+    fn deserialize_flags(&self) -> DeserializeFlags {
+        DeserializeFlags {
+            implicit_some: self.0 == Extension::ImplicitSome,
+            unwrap_newtypes: self.0 == Extension::UnwrapNewtypes,
+        }
+    }
+}
@@ -1,79 +1,165 @@
 use std::{
-    cmp::Ordering,
+    cell::Cell,
+    convert::TryFrom,
     fmt::{Debug, Display, Formatter},
-    ops::Add,
+    ops::{Add, Range},
     slice::SliceIndex,
 };
 
 use crate::{
     location::Location,
-    utf8_parser::{IOk, IResultLookahead},
+    utf8_parser::{AltErrors, IOk, IResultLookahead, DEFAULT_MAX_ERROR_CONTEXTS},
 };
 
+/// Byte offset of a fragment in the text of its [`Input`]
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum Offset {
+    /// Offset in the complete document
     Absolute(usize),
+    /// Offset in a window cut out of a document that isn't available
     Relative(usize),
 }
 
+impl Offset {
+    /// The offset, regardless of what it's relative to
+    pub fn bytes(self) -> usize {
+        match self {
+            Offset::Absolute(x) | Offset::Relative(x) => x,
+        }
+    }
+}
+
 impl Add<usize> for Offset {
     type Output = Offset;
 
     fn add(self, offset: usize) -> Self::Output {
         match self {
             Offset::Absolute(x) => Offset::Absolute(x + offset),
-            _ => todo!(),
+            Offset::Relative(x) => Offset::Relative(x + offset),
         }
     }
 }
 
+/// The location in the [`source`](Input::source), so relative to the
+/// window for relative offsets
 impl<'a> From<Input<'a>> for Location {
     fn from(i: Input<'a>) -> Self {
-        match i.offset {
-            Offset::Absolute(offset) => {
-                assert!(
-                    i.input.is_char_boundary(offset),
-                    "offset not at char boundary"
-                );
-
-                let line = i.input.bytes().take(offset).filter(|&b| b == b'\n').count() + 1;
-
-                let (byte_ind, char_ind, _c) = get_char_at_offset(i.input, offset);
-
-                if byte_ind != offset {
-                    println!("Input {:?}", i);
-                    assert_eq!(byte_ind, offset, "offset not at char boundary");
-                }
-
-                let line_start = i
-                    .input
-                    .char_indices()
-                    .take(char_ind)
-                    .filter(|(_, c)| *c == '\n')
-                    .map(|(i, _c)| i)
-                    .last()
-                    .map(|i| i + 1)
-                    .unwrap_or(0);
-
-                Location {
-                    line: line as u32,
-                    column: (char_ind - line_start + 1) as u32,
-                }
+        let mut offset = i.offset.bytes();
+        // Offsets come from slicing the input, so they are on char boundaries;
+        // errors still get a location if one isn't
+        debug_assert!(
+            i.input.is_char_boundary(offset),
+            "offset not at char boundary"
+        );
+        if !i.input.is_char_boundary(offset) {
+            offset = get_char_at_offset(i.input, offset).0;
+        }
+
+        let text = (i.input.as_ptr() as usize, i.input.len());
+        let cache = LOCATION_CACHE.get();
+        // Counting from the last location if it is before this one
+        let (mut line, mut column, from) = match cache {
+            Some(Some(last)) if last.text == text && last.offset <= offset => {
+                (last.line, last.column, last.offset)
+            }
+            _ => (1, 1, 0),
+        };
+
+        let read = &i.input[from..offset];
+        match read.rfind('\n') {
+            Some(newline) => {
+                line += read.bytes().filter(|&b| b == b'\n').count();
+                // Byte offset, so columns are counted in chars from there
+                column = read[newline + 1..].chars().count() + 1;
             }
-            Offset::Relative(_) => todo!(),
+            None => column += read.chars().count(),
+        }
+
+        if cache.is_some() {
+            LOCATION_CACHE.set(Some(Some(CachedLocation {
+                text,
+                offset,
+                line,
+                column,
+            })));
         }
+
+        Location::clamped(line, column)
     }
 }
 
+/// A location computed in [`with_location_cache`]
+#[derive(Clone, Copy)]
+struct CachedLocation {
+    /// Address and length of the text `offset` is in
+    text: (usize, usize),
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+thread_local! {
+    /// `None` outside of [`with_location_cache`], `Some(None)` before its
+    /// first location
+    static LOCATION_CACHE: Cell<Option<Option<CachedLocation>>> = const { Cell::new(None) };
+}
+
+/// Runs `f`, computing locations from the previous one if it's before them
+/// in the same text
+///
+/// Converting a parse tree computes the locations of its spans in the order
+/// of the document, which then reads the document once instead of once per
+/// span.
+pub(crate) fn with_location_cache<T>(f: impl FnOnce() -> T) -> T {
+    /// Restores the cache of an enclosing call, also if `f` panics
+    struct Restore(Option<Option<CachedLocation>>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            LOCATION_CACHE.set(self.0);
+        }
+    }
+
+    let _restore = Restore(LOCATION_CACHE.replace(Some(None)));
+
+    f()
+}
+
+/// How deep values may be nested by default, see [`Input::with_max_depth`]
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
 #[derive(Clone, Copy, Eq, PartialEq)]
 pub struct Input<'a> {
     offset: Offset,
 
-    /// the complete input
+    /// the text `offset` is relative to: the complete input if `offset` is
+    /// absolute, the window otherwise
     ///
-    /// if `offset` is absolute
+    /// Ends where parsing has to stop.
     input: &'a str,
     fragment: &'a str,
+    alt_errors: AltErrors,
+    max_error_contexts: Option<u32>,
+    verbose_expectations: bool,
+    /// How many expressions enclose this fragment
+    ///
+    /// It, `max_depth`, `max_error_contexts` and `backtracked` are `u32`s to
+    /// keep `Input`, which errors contain, small.
+    depth: u32,
+    max_depth: u32,
+    /// Bytes backtracked by the parsers that ran before this fragment, see
+    /// [`Timings::backtracked`](crate::utf8_parser::Timings::backtracked)
+    ///
+    /// Each parse counts its own, also if one parse starts another.
+    backtracked: u32,
+    recovery: bool,
+    lazy_escapes: bool,
+    unicode_idents: bool,
+    deny_floats: bool,
+    deny_non_finite: bool,
+    exact_numbers: bool,
+    backtrack_budget: Option<usize>,
+    doc_comments: bool,
 }
 
 impl<'a> Input<'a> {
@@ -82,9 +168,273 @@ impl<'a> Input<'a> {
             offset: Offset::Absolute(0),
             input,
             fragment: input,
+            alt_errors: AltErrors::default(),
+            max_error_contexts: Some(DEFAULT_MAX_ERROR_CONTEXTS as u32),
+            verbose_expectations: false,
+            depth: 0,
+            max_depth: DEFAULT_MAX_DEPTH as u32,
+            backtracked: 0,
+            recovery: false,
+            lazy_escapes: false,
+            unicode_idents: false,
+            deny_floats: false,
+            deny_non_finite: false,
+            exact_numbers: false,
+            backtrack_budget: None,
+            doc_comments: true,
+        }
+    }
+
+    /// Input for parsing only `range` of `source`, with locations in all of
+    /// `source`
+    ///
+    /// `None` if `range` is out of bounds or not on char boundaries.
+    pub fn new_window(source: &'a str, range: Range<usize>) -> Option<Self> {
+        let input = source.get(..range.end)?;
+
+        Some(Input {
+            offset: Offset::Absolute(range.start),
+            fragment: input.get(range.start..)?,
+            ..Input::new(input)
+        })
+    }
+
+    /// Input for a window cut out of a document that isn't available,
+    /// with locations relative to the start of the window
+    pub fn new_relative(window: &'a str) -> Self {
+        Input {
+            offset: Offset::Relative(0),
+            ..Input::new(window)
+        }
+    }
+
+    /// Changes how errors of alternatives are merged, [`AltErrors::Furthest`]
+    /// by default
+    ///
+    /// Keeping [`All`](AltErrors::All) errors is mostly useful for debugging
+    /// the parser; the error messages get long for nested structures.
+    pub fn with_alt_errors(self, alt_errors: AltErrors) -> Self {
+        Input { alt_errors, ..self }
+    }
+
+    pub fn alt_errors(&self) -> AltErrors {
+        self.alt_errors
+    }
+
+    /// Limits how many "could not match .. because" lines an error message
+    /// has for each chain of contexts, [`DEFAULT_MAX_ERROR_CONTEXTS`] by
+    /// default; `None` shows all of them
+    ///
+    /// The first and last contexts are kept, the ones in between are
+    /// collapsed into a `... n more contexts ...` line.
+    pub fn with_max_error_contexts(self, max_error_contexts: Option<usize>) -> Self {
+        Input {
+            max_error_contexts: max_error_contexts
+                .map(|max| u32::try_from(max).unwrap_or(u32::MAX)),
+            ..self
+        }
+    }
+
+    pub fn max_error_contexts(&self) -> Option<usize> {
+        self.max_error_contexts.map(|max| max as usize)
+    }
+
+    /// Makes error messages list the tokens that could have started a value
+    /// or an identifier, instead of saying "expected a value" or "expected an
+    /// identifier", off by default
+    ///
+    /// [`Error::expected_tokens`](crate::Error::expected_tokens) always lists
+    /// them, e.g. for completions.
+    pub fn with_verbose_expectations(self, verbose_expectations: bool) -> Self {
+        Input {
+            verbose_expectations,
+            ..self
+        }
+    }
+
+    pub fn verbose_expectations(&self) -> bool {
+        self.verbose_expectations
+    }
+
+    /// Fails parsing with
+    /// [`ErrorKind::NestingTooDeep`](crate::ErrorKind::NestingTooDeep) at
+    /// values nested in more than `max_depth` others, [`DEFAULT_MAX_DEPTH`]
+    /// by default
+    ///
+    /// Parsing deeply nested lists and tuples needs little stack, but
+    /// converting, deserializing and dropping a value recurses into what it
+    /// contains. Raising the limit needs a thread with a larger stack.
+    pub fn with_max_depth(self, max_depth: usize) -> Self {
+        Input {
+            max_depth: u32::try_from(max_depth).unwrap_or(u32::MAX),
+            ..self
+        }
+    }
+
+    pub fn max_depth(&self) -> usize {
+        self.max_depth as usize
+    }
+
+    /// Makes lists continue after elements that fail to parse, off by default
+    ///
+    /// The parse tree then contains the errors in place of the elements, see
+    /// [`ast_from_str_recovering`](crate::utf8_parser::ast_from_str_recovering).
+    pub fn with_recovery(self, recovery: bool) -> Self {
+        Input { recovery, ..self }
+    }
+
+    pub fn recovery(&self) -> bool {
+        self.recovery
+    }
+
+    /// Makes strings with escapes [`EscapedStr`](crate::ast::EscapedStr)s,
+    /// which are only unescaped when needed, off by default
+    ///
+    /// The escapes are still checked while parsing.
+    pub fn with_lazy_escapes(self, lazy_escapes: bool) -> Self {
+        Input {
+            lazy_escapes,
+            ..self
+        }
+    }
+
+    pub fn lazy_escapes(&self) -> bool {
+        self.lazy_escapes
+    }
+
+    /// Allows identifiers of Unicode letters and digits (`XID_Start` followed
+    /// by `XID_Continue` chars) besides the ASCII ones, off by default
+    #[cfg(feature = "unicode_ident")]
+    pub fn with_unicode_idents(self, unicode_idents: bool) -> Self {
+        Input {
+            unicode_idents,
+            ..self
+        }
+    }
+
+    pub fn unicode_idents(&self) -> bool {
+        self.unicode_idents
+    }
+
+    /// Rejects floats like `1.5` or `2.5s` with
+    /// [`ErrorKind::FloatNotAllowed`](crate::ErrorKind::FloatNotAllowed),
+    /// e.g. for configs that may only contain integers, off by default
+    ///
+    /// With the `integers_only` feature, floats are always rejected.
+    pub fn with_deny_floats(self, deny_floats: bool) -> Self {
+        Input {
+            deny_floats,
+            ..self
         }
     }
 
+    pub fn deny_floats(&self) -> bool {
+        cfg!(feature = "integers_only") || self.deny_floats
+    }
+
+    /// Rejects floats that aren't finite, e.g. `1e400`, which is too large
+    /// for `f64`, with
+    /// [`ErrorKind::NonFiniteFloat`](crate::ErrorKind::NonFiniteFloat), off
+    /// by default
+    ///
+    /// For users who need every number to be exact, e.g. amounts of money or
+    /// deterministic simulations. Serializing always rejects non-finite
+    /// floats.
+    pub fn with_deny_non_finite(self, deny_non_finite: bool) -> Self {
+        Input {
+            deny_non_finite,
+            ..self
+        }
+    }
+
+    pub fn deny_non_finite(&self) -> bool {
+        self.deny_non_finite
+    }
+
+    /// Accepts numbers with more digits than a `u64` holds, e.g. for big
+    /// integers and decimals that only need their
+    /// [`text`](crate::ast::Integer::text), off by default
+    ///
+    /// Integers too large for a `u64` become
+    /// [`Integer::Big`](crate::ast::Integer::Big), which only deserializes as
+    /// an `i128`, a `u128` or a string.
+    pub fn with_exact_numbers(self, exact_numbers: bool) -> Self {
+        Input {
+            exact_numbers,
+            ..self
+        }
+    }
+
+    pub fn exact_numbers(&self) -> bool {
+        self.exact_numbers
+    }
+
+    /// Fails parsing with
+    /// [`ErrorKind::BacktrackBudgetExceeded`](crate::ErrorKind::BacktrackBudgetExceeded)
+    /// once alternatives that didn't match read more than `budget` bytes in
+    /// total, unlimited by default
+    ///
+    /// Parsing reads each byte a bounded number of times, but a budget of a
+    /// few times the length of the document guards servers against inputs
+    /// that take a long time to reject anyway.
+    /// [`Timings::backtracked`](crate::utf8_parser::Timings::backtracked)
+    /// tells how much of it a document used.
+    pub fn with_backtrack_budget(self, backtrack_budget: Option<usize>) -> Self {
+        Input {
+            backtrack_budget,
+            ..self
+        }
+    }
+
+    pub fn backtrack_budget(&self) -> Option<usize> {
+        self.backtrack_budget
+    }
+
+    /// This input with `backtracked` bytes backtracked before it, clamped to
+    /// `u32::MAX`
+    pub(crate) fn with_backtracked(self, backtracked: usize) -> Self {
+        Input {
+            backtracked: u32::try_from(backtracked).unwrap_or(u32::MAX),
+            ..self
+        }
+    }
+
+    pub(crate) fn backtracked(&self) -> usize {
+        self.backtracked as usize
+    }
+
+    /// Keeps the `///` doc comments preceding fields and map entries as
+    /// their [`doc`](crate::ast::KeyValue::doc), on by default
+    ///
+    /// Documents that are only deserialized don't need them, and every key
+    /// looks back for a comment otherwise.
+    pub fn with_doc_comments(self, doc_comments: bool) -> Self {
+        Input {
+            doc_comments,
+            ..self
+        }
+    }
+
+    pub fn doc_comments(&self) -> bool {
+        self.doc_comments
+    }
+
+    pub(crate) fn depth(&self) -> usize {
+        self.depth as usize
+    }
+
+    pub(crate) fn with_depth(self, depth: usize) -> Self {
+        Input {
+            depth: u32::try_from(depth).unwrap_or(u32::MAX),
+            ..self
+        }
+    }
+
+    /// The text locations refer to, see [`Offset`]
+    pub fn source(&self) -> &'a str {
+        self.input
+    }
+
     pub fn is_empty(&self) -> bool {
         self.fragment.is_empty()
     }
@@ -97,8 +447,10 @@ impl<'a> Input<'a> {
         self.offset
     }
 
-    pub fn offset_to(&self, other: &Self) -> usize {
-        str_offset(self.fragment, other.fragment)
+    /// Bytes from this fragment to `other`, a later part of the same input,
+    /// or `None` if `other` comes before this fragment
+    pub fn offset_to(&self, other: &Self) -> Option<usize> {
+        other.offset.bytes().checked_sub(self.offset.bytes())
     }
 
     pub fn fragment(&self) -> &'a str {
@@ -113,26 +465,51 @@ impl<'a> Input<'a> {
         self.fragment.char_indices()
     }
 
+    /// Everything of the source before this fragment
+    pub fn preceding(&self) -> &'a str {
+        &self.input[..self.offset.bytes()]
+    }
+
+    /// The `///` doc comment lines directly preceding this fragment,
+    /// with the `///` and one following space stripped.
+    ///
+    /// Doc comments have to be on their own lines; a blank line or
+    /// any other token ends the doc comment.
+    pub fn doc_comment(&self) -> Vec<&'a str> {
+        let mut lines = self.preceding().rsplit('\n');
+
+        // The current line may only be indented
+        match lines.next() {
+            Some(line) if line.trim().is_empty() => {}
+            _ => return vec![],
+        }
+
+        let mut doc: Vec<&'a str> = lines
+            .map(str::trim)
+            .take_while(|line| line.starts_with("///") && !line.starts_with("////"))
+            .map(|line| {
+                let line = &line[3..];
+                line.strip_prefix(' ').unwrap_or(line)
+            })
+            .collect();
+        doc.reverse();
+
+        doc
+    }
+
+    #[doc(hidden)]
     pub fn take_split(self, count: usize) -> IOk<'a, Self> {
         (self.slice(count..), self.slice(..count)).into()
     }
 
+    #[doc(hidden)]
     pub fn slice(&self, range: impl SliceIndex<str, Output = str>) -> Self {
         let next_fragment = &self.fragment[range];
-        let consumed_len = str_offset(self.fragment, next_fragment);
-        if consumed_len == 0 {
-            return Input {
-                offset: self.offset,
-                input: self.input,
-                fragment: next_fragment,
-            };
-        }
-        let next_offset = self.offset + consumed_len;
 
         Input {
-            offset: next_offset,
-            input: self.input,
+            offset: self.offset + str_offset(self.fragment, next_fragment),
             fragment: next_fragment,
+            ..*self
         }
     }
 }
@@ -161,30 +538,6 @@ impl<'a> Display for Input<'a> {
     }
 }
 
-impl PartialOrd for Input<'_> {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        let first = self.fragment.as_ptr();
-        let second = other.fragment.as_ptr();
-
-        let first = first as usize;
-        let second = second as usize;
-
-        first.partial_cmp(&second)
-    }
-}
-
-impl Ord for Input<'_> {
-    fn cmp(&self, other: &Self) -> Ordering {
-        let first = self.fragment.as_ptr();
-        let second = other.fragment.as_ptr();
-
-        let first = first as usize;
-        let second = second as usize;
-
-        first.cmp(&second)
-    }
-}
-
 pub fn position(input: Input) -> IResultLookahead<Input> {
     Ok(input.take_split(0))
 }
@@ -213,7 +566,10 @@ fn str_offset(first: &str, second: &str) -> usize {
 mod tests {
     use crate::{
         location::Location,
-        utf8_parser::{input::get_char_at_offset, Input},
+        utf8_parser::{
+            input::{get_char_at_offset, with_location_cache, Offset},
+            Input,
+        },
     };
 
     #[test]
@@ -241,6 +597,88 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_location_cache() {
+        let input = Input::new("Foo(\n  a: \"ä\",\n  b: 2)");
+        let other = Input::new("x\ny");
+        // Forwards, backwards and past the end of the line with the `ä`
+        let offsets = [0, 9, 13, 14, 4, 16, 23, 2, 13];
+        let locations = |interleaved: bool| {
+            offsets
+                .iter()
+                .map(|&offset| {
+                    if interleaved {
+                        let _ = Location::from(other.take_split(2).remaining);
+                    }
+                    Location::from(input.take_split(offset).remaining)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let uncached = locations(false);
+        assert_eq!(uncached[2], Location { line: 2, column: 8 });
+        assert_eq!(with_location_cache(|| locations(false)), uncached);
+        assert_eq!(with_location_cache(|| locations(true)), uncached);
+    }
+
+    #[test]
+    fn test_offset_to() {
+        let input = Input::new("(a: 1)");
+        let later = input.take_split(4).remaining;
+
+        assert_eq!(input.offset_to(&later), Some(4));
+        assert_eq!(later.offset_to(&later), Some(0));
+        assert_eq!(later.offset_to(&input), None);
+    }
+
+    #[test]
+    fn test_window() {
+        let source = "x = (\n  a: 1,\n)\ny = 2";
+        let input = Input::new_window(source, 4..15).unwrap();
+        assert_eq!(input.fragment(), "(\n  a: 1,\n)");
+        assert_eq!(
+            Location::from(input.take_split(4).remaining),
+            Location { line: 2, column: 3 }
+        );
+
+        assert!(Input::new_window(source, 4..99).is_none());
+        assert!(Input::new_window("ä", 1..2).is_none());
+    }
+
+    #[test]
+    fn test_relative() {
+        let input = Input::new_relative("(\n  a: 1,\n)");
+        let key = input.take_split(4).remaining;
+        assert_eq!(key.offset(), Offset::Relative(4));
+        assert_eq!(Location::from(key), Location { line: 2, column: 3 });
+        assert_eq!(key.preceding(), "(\n  ");
+    }
+
+    #[test]
+    fn test_doc_comment() {
+        let source = "(\n    // not a doc\n    /// Width\n    ///in px\n    //// ruler\n    /// Height\r\n    height: 1,\n)";
+        let key = Input::new(source)
+            .take_split(source.find("height").unwrap())
+            .remaining;
+        assert_eq!(key.doc_comment(), vec!["Height"]);
+
+        let source = "(\n    /// Width\n    ///in px\n    width: 1, height: 2,\n)";
+        let key = Input::new(source)
+            .take_split(source.find("width").unwrap())
+            .remaining;
+        assert_eq!(key.doc_comment(), vec!["Width", "in px"]);
+        let key = Input::new(source)
+            .take_split(source.find("height").unwrap())
+            .remaining;
+        assert!(key.doc_comment().is_empty());
+
+        let source = "(\n    /// Width\n\n    width: 1,\n)";
+        let key = Input::new(source)
+            .take_split(source.find("width").unwrap())
+            .remaining;
+        assert!(key.doc_comment().is_empty());
+    }
+
     #[test]
     fn test_char_offset_basic() {
         assert_eq!(get_char_at_offset("123", 1), (1, 1, '2'));
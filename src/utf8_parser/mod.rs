@@ -1,21 +1,39 @@
-pub use self::error::{ErrorTree, InputParseError};
 use self::{
     containers::{list, rmap, tuple, untagged_struct},
-    error::{BaseErrorKind, Expectation, InputParseErr},
-    input::Input,
-    primitive::{bool, decimal, escaped_string, signed_integer, unescaped_str, unsigned_integer},
+    error::InputParseErr,
+    primitive::{
+        bool, decimal, escaped_string, ident::misspelled_keyword, signed_integer, unescaped_str,
+        unsigned_integer, unsigned_or_big_integer,
+    },
     ron::expr,
 };
-use crate::{ast, ast::Ron, utf8_parser::ok::IOk, Error};
+pub use self::{
+    error::{
+        walk_error_tree, AltErrors, BaseErrorKind, ErrorTree, ErrorTreeVisitor, Expectation,
+        InputParseError, StackContext, DEFAULT_MAX_ERROR_CONTEXTS,
+    },
+    input::{Input, Offset, DEFAULT_MAX_DEPTH},
+    primitive::ident::is_valid_ident,
+    timings::Timings,
+};
+use crate::{
+    ast,
+    ast::Ron,
+    location::LineIndex,
+    utf8_parser::{extension::ExtensionRegistry, ok::IOk, timings::timed},
+    Error, ErrorKind,
+};
 
 //pub type IResultFatal<'a, O> = Result<(Input<'a>, O), InputParseError<'a>>;
 type IResultLookahead<'a, O> = Result<IOk<'a, O>, InputParseErr<'a>>;
 type OutputResult<'a, O> = Result<O, InputParseErr<'a>>;
 
+/// Counting the input read again after alternatives fail
+mod backtrack;
 /// Basic parsers which receive `Input`
 mod basic;
-/// Tables for fast lookup of char categories
-mod char_categories;
+/// The char classes of the grammar, e.g. of identifiers
+pub mod char_categories;
 /// Parser combinators which take one or more parsers and modify / combine them
 mod combinators;
 /// RON container parsers
@@ -23,6 +41,8 @@ mod containers;
 /// Parser error collection
 mod error;
 mod error_fmt;
+/// Built-in and user-registered extensions
+pub mod extension;
 /// `Input` abstraction to slice the input that is being parsed and keep track of the line + column
 mod input;
 mod ok;
@@ -34,21 +54,302 @@ mod pt;
 mod ron;
 #[cfg(feature = "utf8_parser_serde1")]
 pub mod serde;
+/// Syntax check without a parse tree, for validating many documents
+mod syntax;
 #[cfg(test)]
 mod tests;
+/// Time spent in each phase of parsing and deserializing
+mod timings;
 /// Utility functions for parsing
 mod util;
 // Integration tests cannot import this without the feature gate
 // (not sure why that is...)
 #[cfg(any(test, feature = "test"))]
+#[doc(hidden)]
+#[allow(clippy::panic, clippy::unwrap_used)]
 pub mod test_util;
 
 pub fn ast_from_str(input: &str) -> Result<Ron, crate::error::Error> {
-    let pt: pt::Ron = ron::ron(input)
-        .map_err(ErrorTree::calc_locations)
-        .map_err(Error::from)
-        .map_err(|e| e.context_file_content(input.to_owned()))?;
-    let ast: ast::Ron = pt.into();
+    ast_from_str_with_extensions(input, &ExtensionRegistry::new())
+}
+
+/// Parses `input`, which may enable any extension of `registry`
+pub fn ast_from_str_with_extensions<'a>(
+    input: &'a str,
+    registry: &ExtensionRegistry,
+) -> Result<Ron<'a>, crate::error::Error> {
+    ast_from_input_with_extensions(Input::new(input), registry)
+}
+
+/// Parses `input`, e.g. a window of a larger document (see [`Input::new_window`])
+pub fn ast_from_input(input: Input) -> Result<Ron, crate::error::Error> {
+    ast_from_input_with_extensions(input, &ExtensionRegistry::new())
+}
+
+/// Parses `input`, which may enable any extension of `registry`
+pub fn ast_from_input_with_extensions<'a>(
+    input: Input<'a>,
+    registry: &ExtensionRegistry,
+) -> Result<Ron<'a>, crate::error::Error> {
+    recovering_ast_from_input(input, registry).map_err(|mut errors| errors.remove(0))
+}
+
+/// Parses only the attributes `input` starts with, and returns them with the
+/// byte offset of the rest of the document (after the whitespace following
+/// them), e.g. for loaders that look at the extensions or the `version` of a
+/// large document before parsing all of it
+///
+/// The attributes are checked like those of [`ast_from_str`], except that the
+/// names of the extensions aren't: they may be registered later.
+///
+/// ```
+/// use ron_reboot::{ast::Attribute, utf8_parser::parse_header};
+///
+/// let source = "#![version(2)]\n[1, 2, 3]";
+/// let (attributes, rest) = parse_header(source).unwrap();
+/// assert!(matches!(attributes[0].value, Attribute::Version(ref v) if v.value == 2));
+/// assert_eq!(&source[rest..], "[1, 2, 3]");
+/// ```
+pub fn parse_header(input: &str) -> Result<(Vec<ast::Spanned<ast::Attribute>>, usize), Error> {
+    let with_source = |e: Error| e.context_file_content(input.to_owned());
+    let (attributes, rest) = ron::header(Input::new(input)).map_err(|e| with_source(e.into_error()))?;
+    ron::check_header(&attributes).map_err(with_source)?;
+
+    Ok((
+        attributes.into_iter().map(Into::into).collect(),
+        rest.offset().bytes(),
+    ))
+}
+
+/// Parses `input` like [`ast_from_str`], but continues after list elements
+/// that fail to parse, so that all their errors are reported at once
+///
+/// The errors are in the order of the document. Only list elements are
+/// skipped; other errors end parsing like they do for [`ast_from_str`].
+pub fn ast_from_str_recovering(input: &str) -> Result<Ron<'_>, Vec<crate::error::Error>> {
+    recovering_ast_from_input(
+        Input::new(input).with_recovery(true),
+        &ExtensionRegistry::new(),
+    )
+}
+
+/// Parses `input`, with all errors of list elements if it's in recovery mode
+/// (see [`Input::with_recovery`])
+fn recovering_ast_from_input<'a>(
+    input: Input<'a>,
+    registry: &ExtensionRegistry,
+) -> Result<Ron<'a>, Vec<crate::error::Error>> {
+    ast_from_input_timed(input, registry).0
+}
+
+/// Parses `input` like [`recovering_ast_from_input`], measuring how long each
+/// phase takes, e.g. to find out why a document is slow to load
+///
+/// The errors are all errors of list elements if `input` is in recovery mode
+/// (see [`Input::with_recovery`]), otherwise only the first.
+pub fn ast_from_input_timed<'a>(
+    input: Input<'a>,
+    registry: &ExtensionRegistry,
+) -> (Result<Ron<'a>, Vec<crate::error::Error>>, Timings) {
+    let mut timings = Timings::default();
+    let with_source = |errors: Vec<Error>| with_source(errors, input.source());
+
+    let parsed = timed(&mut timings.parse, || {
+        ron::ron(input).map(|(mut pt, backtracked)| {
+            let errors = pt.expr.value.errors();
+            (pt, errors, backtracked)
+        })
+    });
+    timings.backtracked = match &parsed {
+        Ok((_, _, backtracked)) => *backtracked,
+        Err(InputParseErr::Recoverable(e) | InputParseErr::Fatal(e)) => backtrack::backtracked(e),
+    };
+    let pt: pt::Ron = match parsed {
+        Ok((pt, errors, _)) if errors.is_empty() => pt,
+        Ok((_, errors, _)) => return (Err(with_source(errors)), timings),
+        Err(InputParseErr::Recoverable(e) | InputParseErr::Fatal(e))
+            if backtrack::exceeded(input, &e) =>
+        {
+            let location = e.max_input().into();
+            let budget = input.backtrack_budget().unwrap_or_default();
+            let error = Error {
+                kind: ErrorKind::BacktrackBudgetExceeded(budget),
+                context: None,
+            }
+            .context_loc(location, location);
+
+            return (Err(with_source(vec![error])), timings);
+        }
+        Err(e) => return (Err(with_source(vec![e.into_error()])), timings),
+    };
+    let ron = timed(&mut timings.convert, || convert(pt, input, registry));
+
+    (ron.map_err(|e| with_source(vec![e])), timings)
+}
+
+/// Parses the value `input` starts with, and returns it with its length in
+/// bytes, e.g. for RON followed by other content
+///
+/// Unlike [`ast_from_str`], anything may follow the value: the length ends
+/// right after it, before any whitespace or comments. Attributes after the
+/// value aren't parsed.
+///
+/// ```
+/// use ron_reboot::{ast::Expr, utf8_parser::ast_from_str_partial};
+///
+/// let source = "(x: 1, y: 2) and more";
+/// let (ron, len) = ast_from_str_partial(source).unwrap();
+/// assert!(matches!(ron.expr.value, Expr::Struct(_)));
+/// assert_eq!(&source[len..], " and more");
+/// ```
+pub fn ast_from_str_partial(input: &str) -> Result<(Ron<'_>, usize), Error> {
+    let first_error = |errors: Vec<Error>| with_source(errors, input).remove(0);
+
+    let (mut pt, rest) =
+        ron::ron_partial(Input::new(input)).map_err(|e| first_error(vec![e.into_error()]))?;
+    let errors = pt.expr.value.errors();
+    if !errors.is_empty() {
+        return Err(first_error(errors));
+    }
+    let ron = convert(pt, Input::new(input), &ExtensionRegistry::new())
+        .map_err(|e| first_error(vec![e]))?;
+
+    Ok((ron, rest.offset().bytes()))
+}
+
+/// Converts the parse tree of `input` to the AST, and checks what can only be
+/// checked after parsing, e.g. the attributes
+fn convert<'a>(
+    mut pt: pt::Ron<'a>,
+    input: Input<'a>,
+    registry: &ExtensionRegistry,
+) -> Result<Ron<'a>, Error> {
+    ron::check_attributes(&pt)
+        .and_then(|_| registry.resolve_literals(&mut pt))
+        .and_then(|_| {
+            let mut ast: ast::Ron = input::with_location_cache(|| pt.into());
+            registry.transform(&mut ast).map(|_| ast)
+        })
+        .and_then(|ast| match input.deny_floats() {
+            true => check_no_floats(&ast.expr).map(|_| ast),
+            false => Ok(ast),
+        })
+        .and_then(|ast| match input.deny_non_finite() {
+            true => check_finite_floats(&ast.expr).map(|_| ast),
+            false => Ok(ast),
+        })
+}
+
+/// Adds the document to `errors`, and hints at misspelled keywords
+fn with_source(errors: Vec<Error>, source: &str) -> Vec<Error> {
+    let index = LineIndex::new(source);
+
+    errors
+        .into_iter()
+        .map(|e| hint_misspelled_keyword(e, &index, source))
+        .map(|e| e.context_file_content(source.to_owned()))
+        .collect()
+}
+
+/// Adds a label to `e` if it's at a misspelled keyword, e.g. `ture`
+fn hint_misspelled_keyword(e: Error, index: &LineIndex, source: &str) -> Error {
+    let start = match e.start() {
+        Some(start) => start,
+        None => return e,
+    };
+    let offset = index.byte_offset(start);
+    let word = source.get(offset..).unwrap_or_default();
+    let len = word
+        .find(|c| !char_categories::is_ident_other_char(c))
+        .unwrap_or(word.len());
+
+    match misspelled_keyword(&word[..len]) {
+        Some(keyword) => e.context_label(
+            start,
+            index.location(offset + len),
+            format!("did you mean `{}`?", keyword),
+        ),
+        None => e,
+    }
+}
+
+/// Checks the syntax of `input` like [`ast_from_str_recovering`], without
+/// building a parse tree or AST
+///
+/// Documents are first scanned without allocating. Only the ones the scan
+/// can't accept (because they are invalid, or use syntax it doesn't know like
+/// attributes and raw strings) are parsed, so the errors are the same as those
+/// of [`ast_from_str_recovering`].
+pub fn validate_str(input: &str) -> Result<(), Vec<crate::error::Error>> {
+    match syntax::is_valid(input, true) {
+        true => Ok(()),
+        false => ast_from_str_recovering(input).map(|_| ()),
+    }
+}
+
+/// Like [`validate_str`] for `input`, with the errors of
+/// [`ast_from_input_timed`] for invalid documents
+///
+/// The time of the scan is part of [`Timings::parse`].
+pub fn validate_input_timed(
+    input: Input,
+    registry: &ExtensionRegistry,
+) -> (Result<(), Vec<crate::error::Error>>, Timings) {
+    let mut timings = Timings::default();
+    let valid = timed(&mut timings.parse, || {
+        // Documents with floats are parsed to check them
+        syntax::is_valid(
+            input.fragment(),
+            !input.deny_floats() && !input.deny_non_finite(),
+        )
+    });
+    if valid {
+        return (Ok(()), timings);
+    }
+
+    let (ron, parse_timings) = ast_from_input_timed(input, registry);
+    timings.parse += parse_timings.parse;
+    timings.convert = parse_timings.convert;
+
+    (ron.map(|_| ()), timings)
+}
+
+/// Rejects the first float in `expr`, see [`Input::with_deny_floats`]
+fn check_no_floats(expr: &ast::Spanned<ast::Expr>) -> Result<(), Error> {
+    let float = match &expr.value {
+        ast::Expr::Decimal(_) => true,
+        ast::Expr::Quantity(quantity) => matches!(quantity.amount, ast::Amount::Decimal(_)),
+        _ => false,
+    };
+    if float {
+        return Err(Error {
+            kind: ErrorKind::FloatNotAllowed,
+            context: None,
+        }
+        .context_loc(expr.start, expr.end));
+    }
+
+    expr.value.children().try_for_each(check_no_floats)
+}
+
+/// Rejects the first float in `expr` that isn't finite, see
+/// [`Input::with_deny_non_finite`]
+fn check_finite_floats(expr: &ast::Spanned<ast::Expr>) -> Result<(), Error> {
+    let decimal = match &expr.value {
+        ast::Expr::Decimal(d) => Some(&**d),
+        ast::Expr::Quantity(quantity) => match &quantity.amount {
+            ast::Amount::Decimal(d) => Some(d),
+            ast::Amount::Integer(_) => None,
+        },
+        _ => None,
+    };
+    if decimal.is_some_and(|d| !f64::from(d.clone()).is_finite()) {
+        return Err(Error {
+            kind: ErrorKind::NonFiniteFloat,
+            context: None,
+        }
+        .context_loc(expr.start, expr.end));
+    }
 
-    Ok(ast)
+    expr.value.children().try_for_each(check_finite_floats)
 }
@@ -0,0 +1,59 @@
+use crate::utf8_parser::{
+    basic::one_char,
+    combinators::{context, map_res, preceded, take_while_m_n},
+    pt::Color,
+    util::base_err_res,
+    Expectation, IResultLookahead, Input,
+};
+
+/// A hex color with or without alpha, e.g. `#ff8000` or `#ff800080`
+pub fn color(input: Input) -> IResultLookahead<Color> {
+    let digits = take_while_m_n(6, 8, |c| c.is_ascii_hexdigit(), Expectation::HexDigit);
+
+    context(
+        "color",
+        preceded(
+            one_char('#'),
+            map_res(digits, |digits: Input| {
+                if digits.len() == 7 {
+                    return base_err_res(digits.slice(7..), Expectation::HexDigit);
+                }
+
+                let hex = digits.fragment();
+                let channel = |i: usize| {
+                    hex.get(2 * i..2 * i + 2)
+                        .map(|c| u8::from_str_radix(c, 16).expect("only hex digits"))
+                };
+
+                Ok(Color {
+                    r: channel(0).expect("at least 6 digits"),
+                    g: channel(1).expect("at least 6 digits"),
+                    b: channel(2).expect("at least 6 digits"),
+                    a: channel(3).unwrap_or(255),
+                })
+            }),
+        ),
+    )(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utf8_parser::test_util::eval;
+
+    #[test]
+    fn colors() {
+        assert_eq!(
+            eval!(color, "#Ff8000"),
+            Color {
+                r: 255,
+                g: 128,
+                b: 0,
+                a: 255
+            }
+        );
+        assert_eq!(eval!(color, "#ff800080").a, 128);
+        assert!(eval!(@result color, "#ff80").is_err());
+        assert!(eval!(@result color, "#ff80008").is_err());
+    }
+}
@@ -1,5 +1,8 @@
 use crate::utf8_parser::{
-    char_categories::{is_ident_first_char, is_ident_other_char},
+    char_categories::{
+        is_ident_first_char, is_ident_other_char, is_unicode_ident_first_char,
+        is_unicode_ident_other_char,
+    },
     combinators::{context, map, preceded, recognize, take1_if, take_while},
     pt::Ident,
     Expectation, IResultLookahead, Input,
@@ -8,9 +11,19 @@ use crate::utf8_parser::basic::tag;
 use crate::utf8_parser::char_categories::is_ident_raw_char;
 use crate::utf8_parser::combinators::{alt2, lookahead};
 
+type CharPredicate = fn(char) -> bool;
+
+/// The predicates for the first and other chars of identifiers in `input`
+pub(crate) fn ident_chars(input: &Input) -> (CharPredicate, CharPredicate) {
+    match input.unicode_idents() {
+        true => (is_unicode_ident_first_char, is_unicode_ident_other_char),
+        false => (is_ident_first_char, is_ident_other_char),
+    }
+}
+
 fn ident_first_char(input: Input) -> IResultLookahead<Input> {
     take1_if(
-        is_ident_first_char,
+        ident_chars(&input).0,
         Expectation::OneOfExpectations(&[Expectation::Alpha, Expectation::Char('_')]),
     )(input)
 }
@@ -20,7 +33,10 @@ fn raw_ident_inner(input: Input) -> IResultLookahead<Input> {
 }
 
 fn ident_inner(input: Input) -> IResultLookahead<Input> {
-    recognize(preceded(ident_first_char, take_while(is_ident_other_char)))(input)
+    recognize(preceded(
+        ident_first_char,
+        take_while(ident_chars(&input).1),
+    ))(input)
 }
 
 fn ast_ident_from_input(input: Input) -> Ident {
@@ -31,6 +47,62 @@ pub fn ident(input: Input) -> IResultLookahead<Ident> {
     context("ident", map(alt2(raw_ident_inner, ident_inner), ast_ident_from_input))(input)
 }
 
+/// Whether `s` can be written as an identifier, e.g. a field name, without
+/// the `r#` prefix
+pub fn is_valid_ident(s: &str) -> bool {
+    let mut chars = s.chars();
+
+    chars.next().is_some_and(is_ident_first_char) && chars.all(is_ident_other_char)
+}
+
+/// Whether `s` can be written as a raw identifier, e.g. `r#kebab-case`
+#[cfg(any(test, feature = "utf8_parser_serde1"))]
+pub(crate) fn is_valid_raw_ident(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(is_ident_raw_char)
+}
+
+/// The keyword `s` is probably misspelled from, e.g. `None` for `Nonee`
+///
+/// Only `Some`, `None`, `true` and `false` are considered. A misspelling
+/// differs in case, or by one char added, removed or replaced, or two
+/// neighbouring chars swapped.
+pub(crate) fn misspelled_keyword(s: &str) -> Option<&'static str> {
+    const KEYWORDS: [&str; 4] = ["Some", "None", "true", "false"];
+
+    if KEYWORDS.contains(&s) {
+        return None;
+    }
+
+    let s: Vec<char> = s.chars().flat_map(char::to_lowercase).collect();
+    KEYWORDS.iter().copied().find(|keyword| {
+        let keyword: Vec<char> = keyword.chars().flat_map(char::to_lowercase).collect();
+
+        s == keyword || one_edit_apart(&s, &keyword)
+    })
+}
+
+/// Whether `a` becomes `b` with one char added, removed or replaced, or two
+/// neighbouring chars swapped
+fn one_edit_apart(a: &[char], b: &[char]) -> bool {
+    let (shorter, longer) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    let prefix = shorter
+        .iter()
+        .zip(longer)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    match longer.len() - shorter.len() {
+        0 => {
+            let (a, b) = (&shorter[prefix..], &longer[prefix..]);
+
+            a[1.min(a.len())..] == b[1.min(b.len())..]
+                || (a.len() >= 2 && a[0] == b[1] && a[1] == b[0] && a[2..] == b[2..])
+        }
+        1 => shorter[prefix..] == longer[prefix + 1..],
+        _ => false,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -64,6 +136,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn valid_ident() {
+        assert!(is_valid_ident("_type1"));
+        assert!(!is_valid_ident(""));
+        assert!(!is_valid_ident("1a"));
+        assert!(!is_valid_ident("kebab-case"));
+        assert!(!is_valid_ident("\u{141}"));
+        assert!(is_valid_raw_ident("kebab-case"));
+        assert!(!is_valid_raw_ident("with space"));
+    }
+
+    #[test]
+    fn misspelled_keywords() {
+        assert_eq!(misspelled_keyword("Nonee"), Some("None"));
+        assert_eq!(misspelled_keyword("none"), Some("None"));
+        assert_eq!(misspelled_keyword("Smoe"), Some("Some"));
+        assert_eq!(misspelled_keyword("ture"), Some("true"));
+        assert_eq!(misspelled_keyword("False"), Some("false"));
+        assert_eq!(misspelled_keyword("fase"), Some("false"));
+        assert_eq!(misspelled_keyword("None"), None);
+        assert_eq!(misspelled_keyword("true"), None);
+        assert_eq!(misspelled_keyword("Nothing"), None);
+        assert_eq!(misspelled_keyword("tr"), None);
+        assert_eq!(misspelled_keyword(""), None);
+    }
+
     #[test]
     fn raw_ident() {
         assert_eq!(eval!(ident, "r#Config"), Ident("Config"));
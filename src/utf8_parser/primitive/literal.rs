@@ -0,0 +1,71 @@
+use crate::utf8_parser::{
+    basic::one_char,
+    char_categories::is_ident_other_char,
+    combinators::{
+        alt2, context, map, map_res, pair, preceded, recognize, take_while, take_while1,
+    },
+    primitive::{decimal, signed_integer, unsigned_or_big_integer},
+    ErrorTree, Expectation, IResultLookahead, Input, InputParseErr,
+};
+
+/// The suffix directly following the number of a literal
+fn suffix(input: Input) -> IResultLookahead<Input> {
+    map_res(take_while(is_ident_other_char), |suffix: Input| {
+        match suffix.is_empty() {
+            // Without a suffix it's a plain number
+            true => Err(InputParseErr::recoverable(ErrorTree::expected(
+                suffix,
+                Expectation::AlphaNumeric,
+            ))),
+            false => Ok(suffix),
+        }
+    })(input)
+}
+
+/// A number directly followed by a suffix, e.g. `250ms` or `-90deg`,
+/// which has to be supported by an extension
+pub fn number_literal<'a>(input: Input<'a>) -> IResultLookahead<'a, &'a str> {
+    let number = alt2(
+        recognize(decimal),
+        alt2(
+            recognize(signed_integer),
+            recognize(unsigned_or_big_integer),
+        ),
+    );
+
+    context(
+        "literal",
+        map(recognize(pair(number, suffix)), |literal: Input| {
+            literal.fragment()
+        }),
+    )(input)
+}
+
+/// A `#` directly followed by alphanumeric characters, e.g. `#ff8000`,
+/// which has to be supported by an extension
+pub fn hash_literal<'a>(input: Input<'a>) -> IResultLookahead<'a, &'a str> {
+    let rest = take_while1(is_ident_other_char, Expectation::AlphaNumeric);
+
+    context(
+        "literal",
+        map(
+            recognize(preceded(one_char('#'), rest)),
+            |literal: Input| literal.fragment(),
+        ),
+    )(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utf8_parser::test_util::eval;
+
+    #[test]
+    fn literals() {
+        assert_eq!(eval!(number_literal, "250ms"), "250ms");
+        assert_eq!(eval!(number_literal, "-1.5e3px"), "-1.5e3px");
+        assert!(eval!(@result number_literal, "250").is_err());
+        assert_eq!(eval!(hash_literal, "#ff8000"), "#ff8000");
+        assert!(eval!(@result hash_literal, "#").is_err());
+    }
+}
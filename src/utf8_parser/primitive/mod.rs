@@ -1,14 +1,20 @@
 pub use self::{
+    color::color,
     ident::ident,
-    number::{decimal, signed_integer, unsigned_integer},
+    literal::{hash_literal, number_literal},
+    number::{decimal, signed_integer, unsigned_integer, unsigned_or_big_integer},
+    quantity::quantity,
     raw_str::parse_raw_string as raw_str,
     str::unescaped_str,
-    string::parse_string as escaped_string,
+    string::{parse_escaped_str as lazy_escaped_str, parse_string as escaped_string},
 };
 use crate::utf8_parser::{basic::one_of_tags, combinators::context, IResultLookahead, Input};
 
+mod color;
 pub mod ident;
+mod literal;
 pub mod number;
+mod quantity;
 mod raw_str;
 mod str;
 mod string;
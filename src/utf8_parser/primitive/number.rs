@@ -1,16 +1,19 @@
-use std::str::FromStr;
+use std::{convert::TryFrom, str::FromStr};
 
 use crate::utf8_parser::{
     basic::{one_char, one_of_chars},
     char_categories::{is_digit, is_digit_first},
     combinators::{
         alt2, context, lookahead, map, map_res, opt, pair, preceded, recognize, take1_if,
-        take_while, terminated,
+        take_while, terminated, with_text,
     },
-    pt::{Decimal, Sign, SignedInteger, UnsignedInteger},
+    pt::{Decimal, Integer, Sign, SignedInteger, UnsignedInteger},
     BaseErrorKind, ErrorTree, Expectation, IResultLookahead, Input, InputParseErr, OutputResult,
 };
 
+/// Decimal digits that always fit into a `u64`
+const U64_DIGITS: usize = 19;
+
 pub fn sign(input: Input) -> IResultLookahead<Sign> {
     one_of_chars("+-", &[Sign::Positive, Sign::Negative])(input)
 }
@@ -33,27 +36,27 @@ fn parse_u64_dec(input: Input) -> OutputResult<u64> {
     })
 }
 
-fn decimal_unsigned(input: Input) -> IResultLookahead<u64> {
-    map_res(take_while(is_digit), parse_u64_dec)(input)
+/// Like `parse_u64_dec`, but `None` for digits too many for a `u64` with
+/// [`Input::with_exact_numbers`]
+fn parse_digits(digits: Input) -> OutputResult<Option<u64>> {
+    match parse_u64_dec(digits) {
+        Err(_) if digits.exact_numbers() && !digits.is_empty() => Ok(None),
+        result => result.map(Some),
+    }
 }
 
-fn fractional_part(input: Input) -> IResultLookahead<(u64, u16)> {
-    map_res(take_while(is_digit), |input| {
-        Ok((parse_u64_dec(input)?, input.len() as u16))
-    })(input)
+fn decimal_unsigned(input: Input) -> IResultLookahead<u64> {
+    map_res(take_while(is_digit), parse_u64_dec)(input)
 }
 
-fn decimal_unsigned_no_leading_zero(input: Input) -> IResultLookahead<u64> {
-    map_res(
-        recognize(alt2(
-            recognize(lookahead(one_char('0'))),
-            preceded(
-                take1_if(is_digit_first, Expectation::DigitFirst),
-                take_while(is_digit),
-            ),
-        )),
-        parse_u64_dec,
-    )(input)
+fn digits_no_leading_zero(input: Input) -> IResultLookahead<Input> {
+    recognize(alt2(
+        recognize(lookahead(one_char('0'))),
+        preceded(
+            take1_if(is_digit_first, Expectation::DigitFirst),
+            take_while(is_digit),
+        ),
+    ))(input)
 }
 
 fn alt_radix_unsigned(input: Input) -> IResultLookahead<u64> {
@@ -66,29 +69,53 @@ fn alt_radix_unsigned(input: Input) -> IResultLookahead<u64> {
     )(input)
 }
 
+/// An integer without a sign, which has to fit a `u64`, e.g. the amount of a
+/// quantity
 pub fn unsigned_integer(input: Input) -> IResultLookahead<UnsignedInteger> {
-    map(alt2(alt_radix_unsigned, decimal_unsigned_no_leading_zero), |number| UnsignedInteger {
-        number,
-    })(input)
+    map(
+        with_text(alt2(
+            alt_radix_unsigned,
+            map_res(digits_no_leading_zero, parse_u64_dec),
+        )),
+        |(number, text)| UnsignedInteger { number, text },
+    )(input)
 }
 
-pub fn signed_integer(input: Input) -> IResultLookahead<SignedInteger> {
-    map(pair(lookahead(sign), decimal_unsigned), |(sign, number)| {
-        SignedInteger { sign, number }
-    })(input)
+/// An integer without a sign, which is [`Integer::Big`] if it's too large for
+/// a `u64` with [`Input::with_exact_numbers`]
+pub fn unsigned_or_big_integer(input: Input) -> IResultLookahead<Integer> {
+    alt2(
+        map(with_text(alt_radix_unsigned), |(number, text)| {
+            Integer::Unsigned(UnsignedInteger { number, text })
+        }),
+        map_res(with_text(digits_no_leading_zero), |(digits, text)| {
+            Ok(match parse_digits(digits)? {
+                Some(number) => Integer::Unsigned(UnsignedInteger { number, text }),
+                None => Integer::Big(text),
+            })
+        }),
+    )(input)
 }
 
-#[cfg(test)]
-pub fn integer(input: Input) -> IResultLookahead<crate::utf8_parser::pt::Integer> {
-    context(
-        "integer",
-        alt2(
-            map(signed_integer, crate::utf8_parser::pt::Integer::Signed),
-            map(unsigned_integer, crate::utf8_parser::pt::Integer::Unsigned),
-        ),
+/// An integer with a sign, which is [`Integer::Big`] if it's too large for a
+/// `u64` with [`Input::with_exact_numbers`]
+pub fn signed_integer(input: Input) -> IResultLookahead<Integer> {
+    map_res(
+        with_text(pair(lookahead(sign), take_while(is_digit))),
+        |((sign, digits), text)| {
+            Ok(match parse_digits(digits)? {
+                Some(number) => Integer::Signed(SignedInteger { sign, number, text }),
+                None => Integer::Big(text),
+            })
+        },
     )(input)
 }
 
+#[cfg(test)]
+pub fn integer(input: Input) -> IResultLookahead<Integer> {
+    context("integer", alt2(signed_integer, unsigned_or_big_integer))(input)
+}
+
 fn decimal_exp(input: Input) -> IResultLookahead<Option<(Option<Sign>, u16)>> {
     opt(lookahead(preceded(
         one_of_chars("eE", &[(), ()]),
@@ -96,37 +123,94 @@ fn decimal_exp(input: Input) -> IResultLookahead<Option<(Option<Sign>, u16)>> {
     )))(input)
 }
 
+/// The decimal of the `whole` and `fractional` digits
+///
+/// With [`Input::with_exact_numbers`], digits that don't fit a `u64` are cut
+/// off, so that the fields hold the first 19 digits and only `text` has all.
+fn decimal_from_digits<'a>(
+    sign: Option<Sign>,
+    whole: Option<Input<'a>>,
+    fractional: Input<'a>,
+    exponent: Option<(Option<Sign>, u16)>,
+    text: &'a str,
+) -> OutputResult<'a, Decimal<'a>> {
+    let whole = match whole {
+        None => None,
+        Some(digits) => match parse_digits(digits)? {
+            Some(whole) => Some(whole),
+            // Keep the leading digits and count the others in the exponent
+            None => {
+                return Ok(Decimal {
+                    sign,
+                    whole: Some(parse_u64_dec(digits.slice(..U64_DIGITS))?),
+                    fractional: 0,
+                    fractional_digits: 0,
+                    exponent: Some(shift_exponent(exponent, digits.len() - U64_DIGITS)),
+                    text,
+                })
+            }
+        },
+    };
+    let fractional = match parse_digits(fractional)? {
+        Some(_) => fractional,
+        None => fractional.slice(..U64_DIGITS),
+    };
+
+    Ok(Decimal {
+        sign,
+        whole,
+        fractional: parse_u64_dec(fractional)?,
+        fractional_digits: fractional.len() as u16,
+        exponent,
+        text,
+    })
+}
+
+/// `exponent` increased by `digits`
+fn shift_exponent(exponent: Option<(Option<Sign>, u16)>, digits: usize) -> (Option<Sign>, u16) {
+    let exponent = match exponent {
+        Some((Some(Sign::Negative), exponent)) => -i64::from(exponent),
+        Some((_, exponent)) => i64::from(exponent),
+        None => 0,
+    }
+    .saturating_add(i64::try_from(digits).unwrap_or(i64::MAX));
+    let magnitude = u16::try_from(exponent.unsigned_abs()).unwrap_or(u16::MAX);
+
+    match exponent < 0 {
+        true => (Some(Sign::Negative), magnitude),
+        false => (None, magnitude),
+    }
+}
+
 /// e.g.
 ///
 /// * `+1.23e3`
 /// * `-5.0`
 /// * `1222.00`
 fn decimal_std(input: Input) -> IResultLookahead<Decimal> {
-    map(
-        pair(
+    map_res(
+        with_text(pair(
             opt(lookahead(sign)),
             pair(
-                terminated(decimal_unsigned, lookahead(one_char('.'))),
-                pair(fractional_part, decimal_exp),
+                terminated(take_while(is_digit), lookahead(one_char('.'))),
+                pair(take_while(is_digit), decimal_exp),
             ),
-        ),
-        |(sign, (whole, ((fractional, fractional_digits), exp)))| {
-            Decimal::new(sign, Some(whole), fractional, fractional_digits, exp)
+        )),
+        |((sign, (whole, (fractional, exp))), text)| {
+            decimal_from_digits(sign, Some(whole), fractional, exp, text)
         },
     )(input)
 }
 
 /// A decimal without a whole part e.g. `.01`
 fn decimal_frac(input: Input) -> IResultLookahead<Decimal> {
-    // Need to create temp var for borrow checker
-    let x = map(
-        preceded(lookahead(one_char('.')), pair(fractional_part, decimal_exp)),
-        |((fractional, fractional_digits), exp)| {
-            Decimal::new(None, None, fractional, fractional_digits, exp)
-        },
-    )(input);
-
-    x
+    map_res(
+        with_text(preceded(
+            lookahead(one_char('.')),
+            pair(take_while(is_digit), decimal_exp),
+        )),
+        |((fractional, exp), text)| decimal_from_digits(None, None, fractional, exp, text),
+    )(input)
 }
 
 pub fn decimal(input: Input) -> IResultLookahead<Decimal> {
@@ -136,12 +220,19 @@ pub fn decimal(input: Input) -> IResultLookahead<Decimal> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::utf8_parser::{expr, pt::Expr, test_util::eval};
+    use crate::utf8_parser::{
+        expr,
+        pt::Expr,
+        test_util::{eval, unwrap_pr1},
+    };
 
     #[test]
     fn exprs_decimals() {
         for input in ["-41.23", "11.1", ".1E-4"] {
-            assert_eq!(Expr::Decimal(eval!(decimal, input)), eval!(expr, input));
+            assert_eq!(
+                Expr::Decimal(Box::new(eval!(decimal, input).into())),
+                eval!(expr, input)
+            );
         }
     }
 
@@ -230,4 +321,52 @@ mod tests {
             Decimal::new(None, None, 123, 3, Some((Some(Sign::Negative), 3)))
         );
     }
+
+    #[test]
+    fn exact_numbers() {
+        let exact = |s| Input::new(s).with_exact_numbers(true);
+        let big = "123456789012345678901234567890";
+
+        assert!(eval!(@result integer, big).is_err());
+        assert_eq!(unwrap_pr1(integer(exact(big))), Integer::Big(big));
+        assert_eq!(
+            unwrap_pr1(integer(exact("-99999999999999999999"))),
+            Integer::Big("-99999999999999999999")
+        );
+        assert_eq!(
+            unwrap_pr1(integer(exact("+12"))),
+            Integer::new_test(Some(Sign::Positive), 12)
+        );
+        assert!(unsigned_integer(exact(big)).is_err());
+
+        // The fields hold the first 19 digits, the text all of them
+        let long = "1.12345678901234567890123";
+        assert!(eval!(@result decimal, long).is_err());
+        let parsed = unwrap_pr1(decimal(exact(long)));
+        assert_eq!(
+            parsed,
+            Decimal::new(None, Some(1), 1234567890123456789, 19, None)
+        );
+        assert_eq!(parsed.text, long);
+        assert_eq!(
+            unwrap_pr1(decimal(exact("-123456789012345678901.5e-2"))),
+            Decimal::new(
+                Some(Sign::Negative),
+                Some(1234567890123456789),
+                0,
+                0,
+                Some((None, 0))
+            )
+        );
+        assert_eq!(
+            unwrap_pr1(decimal(exact("12345678901234567890123.5e-9"))),
+            Decimal::new(
+                None,
+                Some(1234567890123456789),
+                0,
+                0,
+                Some((Some(Sign::Negative), 5))
+            )
+        );
+    }
 }
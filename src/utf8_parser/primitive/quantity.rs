@@ -0,0 +1,56 @@
+use crate::utf8_parser::{
+    char_categories::is_ident_other_char,
+    combinators::{alt2, context, map, map_res, pair, take_while},
+    primitive::{decimal, unsigned_integer},
+    pt::{Amount, Quantity, Unit},
+    ErrorTree, Expectation, IResultLookahead, Input, InputParseErr,
+};
+
+/// The suffix directly following the number of a quantity
+fn unit(input: Input) -> IResultLookahead<Unit> {
+    map_res(take_while(is_ident_other_char), |suffix: Input| {
+        let error = ErrorTree::expected(suffix, Expectation::OneOfTags(&Unit::SUFFIXES));
+
+        match Unit::from_suffix(suffix.fragment()) {
+            Some(unit) => Ok(unit),
+            // Without a suffix it's a plain number
+            None if suffix.is_empty() => Err(InputParseErr::recoverable(error)),
+            None => Err(InputParseErr::fatal(error)),
+        }
+    })(input)
+}
+
+/// A number with a unit suffix, e.g. `250ms`, `1.5s` or `64KiB`
+pub fn quantity(input: Input) -> IResultLookahead<Quantity> {
+    let amount = alt2(
+        map(decimal, Amount::Decimal),
+        map(unsigned_integer, Amount::Integer),
+    );
+
+    context(
+        "quantity",
+        map(pair(amount, unit), |(amount, unit)| Quantity {
+            amount,
+            unit,
+        }),
+    )(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utf8_parser::{pt::UnsignedInteger, test_util::eval};
+
+    #[test]
+    fn quantities() {
+        assert_eq!(
+            eval!(quantity, "250ms"),
+            Quantity {
+                amount: Amount::Integer(UnsignedInteger::new(250)),
+                unit: Unit::Milliseconds,
+            }
+        );
+        assert_eq!(eval!(quantity, "64KiB").unit, Unit::Kibibytes);
+        assert_eq!(eval!(quantity, "1.5min").unit, Unit::Minutes);
+    }
+}
@@ -1,8 +1,8 @@
 use crate::utf8_parser::{
     basic::{multispace1, one_char, one_of_chars},
     combinators::{
-        alt2, context, cut, delimited, fold_many0, lookahead, map, map_res, preceded, take_while,
-        take_while_m_n,
+        alt2, context, cut, delimited, fold_many0, lookahead, map, map_res, preceded, recognize,
+        take_while, take_while_m_n,
     },
     util::base_err_res,
     BaseErrorKind, ErrorTree, Expectation, IResultLookahead, Input, InputParseErr,
@@ -88,6 +88,12 @@ enum StringFragment<'a> {
 /// Combine parse_literal, parse_escaped_whitespace, and parse_escaped_char
 /// into a StringFragment.
 fn parse_fragment<'a>(input: Input<'a>) -> IResultLookahead<StringFragment<'a>> {
+    // Ends the fragments of every string, with an error that isn't merged
+    // from one of each alternative
+    if input.fragment().starts_with('"') {
+        return base_err_res(input, Expectation::Something);
+    }
+
     alt2(
         // The `map` combinator runs a utf8_parser, then applies a function to the output
         // of that utf8_parser.
@@ -104,13 +110,16 @@ fn parse_fragment<'a>(input: Input<'a>) -> IResultLookahead<StringFragment<'a>>
 }
 
 fn inner_string(input: Input) -> IResultLookahead<String> {
+    // Unescaping never makes text longer, so the string is allocated once
+    let capacity = escaped_len(input.fragment());
+
     // fold_many0 is the equivalent of iterator::fold. It runs a utf8_parser in a loop,
     // and for each output value, calls a folding function on each output value.
     fold_many0(
         // Our utf8_parser function– parses a single string fragment
         lookahead(parse_fragment),
-        // Our init value, an empty string
-        String::new,
+        // Our init value, an empty string with room for all of it
+        move || String::with_capacity(capacity),
         // Our folding function. For each fragment, append the fragment to the
         // string.
         |mut string, fragment| {
@@ -124,6 +133,23 @@ fn inner_string(input: Input) -> IResultLookahead<String> {
     )(input)
 }
 
+/// The length of the escaped text `s` starts with, up to the first `"` that
+/// isn't escaped
+fn escaped_len(s: &str) -> usize {
+    let mut bytes = s.bytes().enumerate();
+    while let Some((i, b)) = bytes.next() {
+        match b {
+            b'\\' => {
+                bytes.next();
+            }
+            b'"' => return i,
+            _ => {}
+        }
+    }
+
+    s.len()
+}
+
 /// Parse a string. Use a loop of parse_fragment and push all of the fragments
 /// into an output string.
 pub fn parse_string(input: Input) -> IResultLookahead<String> {
@@ -136,3 +162,18 @@ pub fn parse_string(input: Input) -> IResultLookahead<String> {
         delimited(one_char('"'), inner_string, one_char('"')),
     )(input)
 }
+
+/// Parse a string like [`parse_string`], but only check its escapes, returning
+/// the text between the quotes
+pub fn parse_escaped_str<'a>(input: Input<'a>) -> IResultLookahead<'a, &'a str> {
+    let check_fragments = fold_many0(lookahead(parse_fragment), || (), |(), _| ());
+
+    context(
+        "string",
+        delimited(
+            one_char('"'),
+            map(recognize(check_fragments), |s| s.fragment()),
+            one_char('"'),
+        ),
+    )(input)
+}
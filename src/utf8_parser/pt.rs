@@ -1,7 +1,9 @@
 //! Parse tree
 
-pub use crate::ast::Extension;
-use crate::{ast, utf8_parser::input::Input};
+use std::borrow::Cow;
+
+pub use crate::ast::{Color, Extension, Unit};
+use crate::{ast, utf8_parser::input::Input, Error};
 
 /// IMPORTANT: Equality operators do NOT compare the start & end spans!
 #[derive(Clone, Debug)]
@@ -59,6 +61,8 @@ where
 pub struct Ron<'a> {
     pub attributes: Vec<Spanned<'a, Attribute<'a>>>,
     pub expr: Spanned<'a, Expr<'a>>,
+    /// Attributes following the expression, which are invalid
+    pub trailing_attributes: Vec<Spanned<'a, Attribute<'a>>>,
 }
 
 impl<'a> From<Ron<'a>> for ast::Ron<'a> {
@@ -73,6 +77,7 @@ impl<'a> From<Ron<'a>> for ast::Ron<'a> {
 #[derive(Clone, Debug, PartialEq)]
 pub enum Attribute<'a> {
     Enable(Spanned<'a, Vec<Spanned<'a, Extension>>>),
+    Version(Spanned<'a, u64>),
 }
 
 impl Attribute<'_> {
@@ -91,6 +96,7 @@ impl<'a> From<Attribute<'a>> for ast::Attribute {
                 e.map(|v| v.into_iter().map(Into::into).collect::<Vec<_>>())
                     .into(),
             ),
+            Attribute::Version(version) => ast::Attribute::Version(version.into()),
         }
     }
 }
@@ -106,7 +112,7 @@ impl<'a> Ident<'a> {
 
 impl<'a> From<Ident<'a>> for ast::Ident<'a> {
     fn from(i: Ident<'a>) -> Self {
-        ast::Ident(i.0)
+        ast::Ident::from_str(i.0)
     }
 }
 
@@ -140,61 +146,83 @@ impl From<Sign> for ast::Sign {
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
-pub struct UnsignedInteger {
+/// IMPORTANT: Equality operators do NOT compare the `text`!
+#[derive(Clone, Copy, Debug)]
+pub struct UnsignedInteger<'a> {
     pub number: u64,
+    pub text: &'a str,
 }
 
-impl UnsignedInteger {
+impl<'a> UnsignedInteger<'a> {
     #[cfg(test)]
     pub const fn new(number: u64) -> Self {
-        UnsignedInteger { number }
+        UnsignedInteger { number, text: "" }
     }
 
-    pub fn to_expr(self) -> Expr<'static> {
+    pub fn to_expr(self) -> Expr<'a> {
         Expr::Integer(Integer::Unsigned(self))
     }
 }
 
-impl From<UnsignedInteger> for ast::UnsignedInteger {
-    fn from(s: UnsignedInteger) -> Self {
-        ast::UnsignedInteger { number: s.number }
+impl PartialEq for UnsignedInteger<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.number == other.number
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
-pub struct SignedInteger {
+impl<'a> From<UnsignedInteger<'a>> for ast::UnsignedInteger<'a> {
+    fn from(u: UnsignedInteger<'a>) -> Self {
+        ast::UnsignedInteger {
+            number: u.number,
+            text: Some(u.text),
+        }
+    }
+}
+
+/// IMPORTANT: Equality operators do NOT compare the `text`!
+#[derive(Clone, Debug)]
+pub struct SignedInteger<'a> {
     pub sign: Sign,
     pub number: u64,
+    pub text: &'a str,
 }
 
-impl SignedInteger {
+impl SignedInteger<'_> {
     #[cfg(test)]
     pub fn new_test(sign: Sign, number: u64) -> Self {
-        SignedInteger { sign, number }
+        SignedInteger {
+            sign,
+            number,
+            text: "",
+        }
     }
+}
 
-    pub fn to_expr(self) -> Expr<'static> {
-        Expr::Integer(Integer::Signed(self))
+impl PartialEq for SignedInteger<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        (self.sign, self.number) == (other.sign, other.number)
     }
 }
 
-impl From<SignedInteger> for ast::SignedInteger {
-    fn from(s: SignedInteger) -> Self {
+impl<'a> From<SignedInteger<'a>> for ast::SignedInteger<'a> {
+    fn from(s: SignedInteger<'a>) -> Self {
         ast::SignedInteger {
             sign: s.sign.into(),
             number: s.number,
+            text: Some(s.text),
         }
     }
 }
 
 #[derive(Clone, Debug, PartialEq)]
-pub enum Integer {
-    Signed(SignedInteger),
-    Unsigned(UnsignedInteger),
+pub enum Integer<'a> {
+    Signed(SignedInteger<'a>),
+    Unsigned(UnsignedInteger<'a>),
+    /// Too large for a `u64`, only with [`Input::with_exact_numbers`]
+    Big(&'a str),
 }
 
-impl Integer {
+impl<'a> Integer<'a> {
     #[cfg(test)]
     pub fn new_test(sign: Option<Sign>, number: u64) -> Self {
         match sign {
@@ -204,30 +232,34 @@ impl Integer {
     }
 
     #[cfg(test)]
-    pub fn to_expr(self) -> Expr<'static> {
+    pub fn to_expr(self) -> Expr<'a> {
         Expr::Integer(self)
     }
 }
 
-impl From<Integer> for ast::Integer {
-    fn from(i: Integer) -> Self {
+impl<'a> From<Integer<'a>> for ast::Integer<'a> {
+    fn from(i: Integer<'a>) -> Self {
         match i {
             Integer::Signed(s) => ast::Integer::Signed(s.into()),
             Integer::Unsigned(u) => ast::Integer::Unsigned(u.into()),
+            Integer::Big(text) => ast::Integer::Big(Cow::Borrowed(text)),
         }
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
-pub struct Decimal {
+/// IMPORTANT: Equality operators do NOT compare the `text`!
+#[derive(Clone, Debug)]
+pub struct Decimal<'a> {
     pub sign: Option<Sign>,
     pub whole: Option<u64>,
     pub fractional: u64,
     pub fractional_digits: u16,
     pub exponent: Option<(Option<Sign>, u16)>,
+    pub text: &'a str,
 }
 
-impl Decimal {
+impl Decimal<'_> {
+    #[cfg(test)]
     pub fn new(
         sign: Option<Sign>,
         whole: Option<u64>,
@@ -241,18 +273,68 @@ impl Decimal {
             fractional,
             fractional_digits,
             exponent,
+            text: "",
         }
     }
 }
 
-impl From<Decimal> for ast::Decimal {
-    fn from(d: Decimal) -> ast::Decimal {
+impl PartialEq for Decimal<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        (
+            self.sign,
+            self.whole,
+            self.fractional,
+            self.fractional_digits,
+            self.exponent,
+        ) == (
+            other.sign,
+            other.whole,
+            other.fractional,
+            other.fractional_digits,
+            other.exponent,
+        )
+    }
+}
+
+impl<'a> From<Decimal<'a>> for ast::Decimal<'a> {
+    fn from(d: Decimal<'a>) -> ast::Decimal<'a> {
         ast::Decimal {
             sign: d.sign.map(Into::into),
             whole: d.whole,
             fractional: d.fractional,
             fractional_digits: d.fractional_digits,
             exponent: d.exponent.map(|(s, e)| (s.map(Into::into), e)),
+            text: Some(d.text),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Quantity<'a> {
+    pub amount: Amount<'a>,
+    pub unit: Unit,
+}
+
+impl<'a> From<Quantity<'a>> for ast::Quantity<'a> {
+    fn from(q: Quantity<'a>) -> Self {
+        ast::Quantity {
+            amount: q.amount.into(),
+            unit: q.unit,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Amount<'a> {
+    Integer(UnsignedInteger<'a>),
+    Decimal(Decimal<'a>),
+}
+
+impl<'a> From<Amount<'a>> for ast::Amount<'a> {
+    fn from(a: Amount<'a>) -> Self {
+        match a {
+            Amount::Integer(i) => ast::Amount::Integer(i.into()),
+            Amount::Decimal(d) => ast::Amount::Decimal(d.into()),
         }
     }
 }
@@ -279,6 +361,16 @@ where
 {
     fn from(m: KeyValue<'a, K>) -> Self {
         ast::KeyValue {
+            doc: match m.key.start.doc_comments() {
+                true => m
+                    .key
+                    .start
+                    .doc_comment()
+                    .into_iter()
+                    .map(Cow::Borrowed)
+                    .collect(),
+                false => vec![],
+            },
             key: m.key.into(),
             value: m.value.into(),
         }
@@ -417,6 +509,10 @@ pub struct Tagged<'a> {
 }
 
 impl<'a> Tagged<'a> {
+    /// Whether this is `Some(x)` or `None`, which the AST has as an
+    /// [`Optional`](ast::Expr::Optional)
+    ///
+    /// Deserializing an enum still accepts them as variants of that name.
     pub fn is_optional(&self) -> bool {
         match (self.ident.value.0, &self.untagged.value) {
             ("Some", Untagged::Tuple(Tuple { elements })) if elements.len() == 1 => true,
@@ -425,13 +521,15 @@ impl<'a> Tagged<'a> {
         }
     }
 
-    pub fn into_optional(self) -> Option<Spanned<'a, Expr<'a>>> {
-        match (self.ident.value.0, self.untagged.value) {
-            ("Some", Untagged::Tuple(Tuple { mut elements })) if elements.len() == 1 => {
-                Some(elements.remove(0))
-            }
-            ("None", Untagged::Unit) => None,
-            _ => unimplemented!(),
+    /// The value of `Some(x)`, or `None`; other values are given back
+    pub fn into_optional(self: Box<Self>) -> Result<Option<Spanned<'a, Expr<'a>>>, Box<Self>> {
+        if !self.is_optional() {
+            return Err(self);
+        }
+
+        match self.untagged.value {
+            Untagged::Tuple(Tuple { mut elements }) => Ok(elements.pop()),
+            _ => Ok(None),
         }
     }
 }
@@ -445,29 +543,74 @@ impl<'a> From<Tagged<'a>> for ast::Tagged<'a> {
     }
 }
 
+/// Large variants are boxed, so that a list of small values isn't as large
+/// as a list of tagged ones
 #[derive(Clone, Debug, PartialEq)]
 pub enum Expr<'a> {
-    Tagged(Tagged<'a>),
+    Tagged(Box<Tagged<'a>>),
     Bool(bool),
     Tuple(Tuple<'a>),
     List(List<'a>),
     Map(Map<'a>),
     Struct(Struct<'a>),
-    Integer(Integer),
+    Integer(Integer<'a>),
     /// String without escapes (zero-copy)
     Str(&'a str),
     /// Escaped string
     String(String),
-    Decimal(Decimal),
+    /// Escaped string in lazy mode (see [`Input::with_lazy_escapes`])
+    EscapedStr(ast::EscapedStr<'a>),
+    Decimal(Box<ast::Decimal<'a>>),
+    /// Literal of an extension, e.g. `250ms` or `#ff8000`
+    Literal(&'a str),
+    /// Literal after an extension parsed it
+    Resolved(Box<ast::Expr<'a>>),
+    /// List element that failed to parse, skipped in recovery mode
+    Invalid(Box<Error>),
+}
+
+impl<'a> Expr<'a> {
+    /// The directly contained expressions, including map keys
+    pub fn children_mut(&mut self) -> Vec<&mut Spanned<'a, Expr<'a>>> {
+        match self {
+            Expr::Tagged(t) => match &mut t.untagged.value {
+                Untagged::Unit => vec![],
+                Untagged::Struct(s) => s.fields.iter_mut().map(|f| &mut f.value.value).collect(),
+                Untagged::Tuple(t) => t.elements.iter_mut().collect(),
+            },
+            Expr::Tuple(t) => t.elements.iter_mut().collect(),
+            Expr::List(l) => l.elements.iter_mut().collect(),
+            Expr::Map(m) => m
+                .entries
+                .iter_mut()
+                .flat_map(|e| vec![&mut e.value.key, &mut e.value.value])
+                .collect(),
+            Expr::Struct(s) => s.fields.iter_mut().map(|f| &mut f.value.value).collect(),
+            _ => vec![],
+        }
+    }
+
+    /// The errors of all invalid expressions in this one, in the order of
+    /// the document
+    pub fn errors(&mut self) -> Vec<Error> {
+        match self {
+            Expr::Invalid(e) => vec![(**e).clone()],
+            _ => self
+                .children_mut()
+                .into_iter()
+                .flat_map(|child| child.value.errors())
+                .collect(),
+        }
+    }
 }
 
 impl<'a> From<Expr<'a>> for ast::Expr<'a> {
     fn from(e: Expr<'a>) -> Self {
         match e {
-            Expr::Tagged(t) if t.is_optional() => {
-                ast::Expr::Optional(t.into_optional().map(|e| Box::new(e.into())))
-            }
-            Expr::Tagged(t) => ast::Expr::Tagged(t.into()),
+            Expr::Tagged(t) => match t.into_optional() {
+                Ok(inner) => ast::Expr::Optional(inner.map(|e| Box::new(e.into()))),
+                Err(t) => ast::Expr::Tagged(Box::new((*t).into())),
+            },
             Expr::Bool(x) => ast::Expr::Bool(x),
             Expr::Tuple(x) if x.elements.is_empty() => ast::Expr::Unit,
             Expr::Tuple(x) => ast::Expr::Tuple(x.into()),
@@ -477,7 +620,11 @@ impl<'a> From<Expr<'a>> for ast::Expr<'a> {
             Expr::Integer(x) => ast::Expr::Integer(x.into()),
             Expr::Str(x) => ast::Expr::Str(x.into()),
             Expr::String(x) => ast::Expr::String(x.into()),
-            Expr::Decimal(x) => ast::Expr::Decimal(x.into()),
+            Expr::EscapedStr(x) => ast::Expr::EscapedStr(x),
+            Expr::Decimal(x) => ast::Expr::Decimal(x),
+            Expr::Literal(_) => unreachable!("literals are resolved before conversion"),
+            Expr::Resolved(x) => *x,
+            Expr::Invalid(_) => unreachable!("invalid expressions aren't converted"),
         }
     }
 }
@@ -1,29 +1,30 @@
 use crate::utf8_parser::{
-    basic::{one_char, one_of_chars, one_of_tags, tag},
+    basic::{multispacews0, one_char, one_of_chars, tag},
     bool,
-    char_categories::is_ident_first_char,
+    char_categories::is_ident_other_char,
     combinators,
     combinators::{
         alt2, comma_list1, context, context_final, cut, delimited, lookahead, many0, map, pair,
         preceded, take1_if,
     },
-    containers::tagged,
+    containers::{nested_sequence, tagged, Sequence},
     decimal, escaped_string, list,
-    primitive::raw_str,
-    pt::{Attribute, Expr, Extension, Ron, SignedInteger, UnsignedInteger},
-    rmap, signed_integer, tuple, unescaped_str, unsigned_integer, untagged_struct, ErrorTree,
-    Expectation, IResultLookahead, Input, InputParseErr, InputParseError,
+    primitive::{
+        hash_literal, ident, ident::ident_chars, lazy_escaped_str, number_literal, raw_str,
+    },
+    pt::{Attribute, Decimal, Expr, Extension, Ident, Ron, Spanned},
+    rmap, signed_integer, tuple, unescaped_str, unsigned_integer, unsigned_or_big_integer,
+    untagged_struct, util, ErrorTree, Expectation, IOk, IResultLookahead, Input, InputParseErr,
 };
+use crate::{ast::EscapedStr, location::Location, Error, ErrorKind};
 
+/// Name of a built-in or registered extension, which is checked after parsing
 fn extension_name(input: Input) -> IResultLookahead<Extension> {
-    one_of_tags(
-        &["unwrap_newtypes", "implicit_some"],
-        &[Extension::UnwrapNewtypes, Extension::ImplicitSome],
-    )(input)
+    map(ident, |ident| Extension::from_name(ident.0))(input)
 }
 
 fn attribute_enable(input: Input) -> IResultLookahead<Attribute> {
-    let start = preceded(tag("enable"), combinators::ws(one_char('(')));
+    let start = preceded(lookahead(tag("enable")), combinators::ws(one_char('(')));
     let end = one_char(')');
 
     delimited(
@@ -36,16 +37,42 @@ fn attribute_enable(input: Input) -> IResultLookahead<Attribute> {
     )(input)
 }
 
+fn attribute_version(input: Input) -> IResultLookahead<Attribute> {
+    let start = preceded(lookahead(tag("version")), combinators::ws(one_char('(')));
+    let end = one_char(')');
+
+    delimited(
+        start,
+        map(
+            combinators::ws(combinators::spanned(unsigned_integer)),
+            |version| Attribute::Version(version.map(|version| version.number)),
+        ),
+        end,
+    )(input)
+}
+
+/// The `#` of an attribute, unless it's the start of a literal like `#ff8000`
+fn attribute_hash(input: Input) -> IResultLookahead<char> {
+    match input.fragment().chars().nth(1) {
+        Some(c) if is_ident_other_char(c) => util::base_err(input, Expectation::Tag("#!")),
+        _ => one_char('#')(input),
+    }
+}
+
 fn attribute(input: Input) -> IResultLookahead<Attribute> {
     let start = preceded(
-        preceded(lookahead(one_char('#')), combinators::ws(one_char('!'))),
+        preceded(lookahead(attribute_hash), combinators::ws(one_char('!'))),
         combinators::ws(one_char('[')),
     );
     let end = one_char(']');
 
     context(
         "attribute",
-        delimited(start, combinators::ws(attribute_enable), end),
+        delimited(
+            start,
+            combinators::ws(alt2(attribute_enable, attribute_version)),
+            end,
+        ),
     )(input)
 }
 
@@ -61,13 +88,15 @@ enum ExprClass {
     Dec,
     /// Unsigned or Decimal
     UnsignedDec,
+    /// Extension literal starting with `#`
+    Hash,
     LeadingIdent,
 }
 
 impl ExprClass {
     pub fn parse(input: Input) -> IResultLookahead<Self> {
         let all_but_ident = one_of_chars(
-            "({\"[tf+-.0123456789",
+            "({\"[tf+-.0123456789#",
             &[
                 ExprClass::StructTuple,
                 ExprClass::Map,
@@ -88,6 +117,7 @@ impl ExprClass {
                 ExprClass::UnsignedDec,
                 ExprClass::UnsignedDec,
                 ExprClass::UnsignedDec,
+                ExprClass::Hash,
             ],
         );
 
@@ -95,7 +125,7 @@ impl ExprClass {
             lookahead(all_but_ident),
             map(
                 take1_if(
-                    is_ident_first_char,
+                    ident_chars(&input).0,
                     Expectation::OneOfExpectations(&[Expectation::Alpha, Expectation::Char('_')]),
                 ),
                 |_| ExprClass::LeadingIdent,
@@ -117,51 +147,253 @@ fn expr_inner(input: Input) -> IResultLookahead<Expr> {
             map(tuple, Expr::Tuple),
         ))(input),
         ExprClass::Map => map(rmap, Expr::Map)(input),
+        ExprClass::StrString if input.lazy_escapes() => alt2(
+            map(lookahead(unescaped_str), Expr::Str),
+            map(lazy_escaped_str, |s| Expr::EscapedStr(EscapedStr(s))),
+        )(input),
         ExprClass::StrString => alt2(
             map(lookahead(unescaped_str), Expr::Str),
             map(escaped_string, Expr::String),
         )(input),
         ExprClass::List => map(list, Expr::List)(input),
+        ExprClass::Bool if is_keyword_name(input) => {
+            map(tagged, |tagged| keyword_name_error(&tagged.ident))(input)
+        }
         ExprClass::Bool => map(bool, Expr::Bool)(input),
         ExprClass::SignedDec => alt2(
-            map(decimal, Expr::Decimal),
-            map(signed_integer, SignedInteger::to_expr),
+            map(number_literal, Expr::Literal),
+            alt2(
+                map(decimal, decimal_expr),
+                map(signed_integer, Expr::Integer),
+            ),
+        )(input),
+        ExprClass::Dec => alt2(
+            map(number_literal, Expr::Literal),
+            map(decimal, decimal_expr),
         )(input),
-        ExprClass::Dec => map(decimal, Expr::Decimal)(input),
         ExprClass::UnsignedDec => alt2(
-            map(decimal, Expr::Decimal),
-            map(unsigned_integer, UnsignedInteger::to_expr),
+            map(number_literal, Expr::Literal),
+            alt2(
+                map(decimal, decimal_expr),
+                map(unsigned_or_big_integer, Expr::Integer),
+            ),
+        )(input),
+        ExprClass::Hash => map(hash_literal, Expr::Literal)(input),
+        ExprClass::LeadingIdent => alt2(
+            map(raw_str, Expr::Str),
+            map(tagged, |t| Expr::Tagged(Box::new(t))),
         )(input),
-        ExprClass::LeadingIdent => {
-            alt2(map(raw_str, Expr::Str), map(tagged, Expr::Tagged))(input)
-        }
     }
 }
 
+fn decimal_expr(decimal: Decimal) -> Expr {
+    Expr::Decimal(Box::new(decimal.into()))
+}
+
+/// What follows `true` or `false` at the start of `input`
+fn after_keyword<'a>(input: Input<'a>) -> Option<&'a str> {
+    ["true", "false"]
+        .iter()
+        .find_map(|keyword| input.fragment().strip_prefix(keyword))
+}
+
+/// Whether `input` starts with `true(` or `false(`, a keyword used as the name
+/// of a struct or variant
+fn is_keyword_name(input: Input) -> bool {
+    after_keyword(input).is_some_and(|rest| rest.starts_with('('))
+}
+
+/// Whether `input` starts with `true:` or `false:`, a keyword used as the
+/// name of a field
+pub(crate) fn is_keyword_field(input: Input) -> bool {
+    after_keyword(input).is_some_and(|rest| rest.trim_start().starts_with(':'))
+}
+
+/// Reports a keyword name at the keyword, instead of at the `(` or `:` after
+/// it
+pub(crate) fn keyword_name_error<'a>(ident: &Spanned<Ident>) -> Expr<'a> {
+    let error = Error {
+        kind: ErrorKind::KeywordAsName(ident.value.0.to_owned()),
+        context: None,
+    };
+
+    Expr::Invalid(Box::new(
+        error.context_loc(ident.start.into(), ident.end.into()),
+    ))
+}
+
+/// Nesting depth up to which expressions are parsed by recursive descent
+///
+/// Deeper lists and tuples are parsed by [`nested_sequence`], so that deeply
+/// nested data doesn't need a huge stack.
+const MAX_RECURSION_DEPTH: usize = 16;
+
+/// Stack left below which [`expr`] continues on a new segment of
+/// [`STACK_SEGMENT_SIZE`] bytes, more than a nested struct or map needs
+/// before the next `expr`
+const STACK_RED_ZONE: usize = 256 * 1024;
+const STACK_SEGMENT_SIZE: usize = 2 * 1024 * 1024;
+
 pub fn expr(input: Input) -> IResultLookahead<Expr> {
-    context_final("expression", true, expr_inner)(input)
+    let depth = input.depth();
+    if depth > input.max_depth() {
+        return Err(too_deep(input));
+    }
+    if depth >= MAX_RECURSION_DEPTH && Sequence::starting(input).is_some() {
+        return context_final("expression", true, nested_sequence)(input);
+    }
+
+    let ok = stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT_SIZE, || {
+        context_final("expression", true, expr_inner)(input.with_depth(depth + 1))
+    })?;
+
+    Ok(IOk {
+        remaining: ok.remaining.with_depth(depth),
+        ..ok
+    })
+}
+
+/// Ends parsing at the expression `input` starts with, which is nested
+/// deeper than [`Input::with_max_depth`] allows
+pub(crate) fn too_deep(input: Input) -> InputParseErr {
+    let location = Location::from(input);
+    let error = Error {
+        kind: ErrorKind::NestingTooDeep(input.max_depth()),
+        context: None,
+    };
+
+    InputParseErr::fatal(ErrorTree::external(
+        input,
+        error.context_loc(location, location.add_columns(1)),
+    ))
 }
 
 fn ron_inner(input: Input) -> IResultLookahead<Ron> {
     map(
         pair(
+            pair(
+                many0(combinators::spanned(attribute)),
+                combinators::spanned(expr),
+            ),
             many0(combinators::spanned(attribute)),
-            combinators::spanned(expr),
         ),
-        |(attributes, expr)| Ron { attributes, expr },
+        |((attributes, expr), trailing_attributes)| Ron {
+            attributes,
+            expr,
+            trailing_attributes,
+        },
     )(input)
 }
 
-pub fn ron(input: &str) -> Result<Ron, InputParseError> {
-    let input = Input::new(input);
+fn error<T>(kind: ErrorKind, spanned: &Spanned<T>) -> Error {
+    Error {
+        kind,
+        context: None,
+    }
+    .context_loc(spanned.start.into(), spanned.end.into())
+}
+
+/// Checks the attributes like [`check_header`], and that no attribute
+/// follows the expression.
+pub fn check_attributes(ron: &Ron) -> Result<(), Error> {
+    if let Some(attribute) = ron.trailing_attributes.first() {
+        return Err(error(ErrorKind::AttributeAfterExpression, attribute));
+    }
+
+    check_header(&ron.attributes)
+}
 
+/// Checks that there is at most one `enable` attribute, which enables each
+/// extension only once, and at most one `version` attribute.
+///
+/// Whether the extensions exist is checked by the
+/// [`ExtensionRegistry`](crate::utf8_parser::extension::ExtensionRegistry).
+pub fn check_header(attributes: &[Spanned<Attribute>]) -> Result<(), Error> {
+    fn label<T>(error: Error, spanned: &Spanned<T>, message: &str) -> Error {
+        error.context_label(spanned.start.into(), spanned.end.into(), message.to_owned())
+    }
+
+    let mut enable = None;
+    let mut enabled: Vec<&Spanned<_>> = vec![];
+    let mut version = None;
+    for attribute in attributes {
+        match &attribute.value {
+            Attribute::Enable(extensions) => {
+                if let Some(first) = enable {
+                    return Err(label(
+                        error(ErrorKind::DuplicateEnableAttribute, attribute),
+                        first,
+                        "first `enable` attribute",
+                    ));
+                }
+                enable = Some(attribute);
+                for extension in &extensions.value {
+                    if let Some(first) = enabled.iter().find(|e| e.value == extension.value) {
+                        return Err(label(
+                            error(
+                                ErrorKind::DuplicateExtension(extension.value.name().to_owned()),
+                                extension,
+                            ),
+                            first,
+                            "first enabled here",
+                        ));
+                    }
+                    enabled.push(extension);
+                }
+            }
+            Attribute::Version(_) => {
+                if let Some(first) = version {
+                    return Err(label(
+                        error(ErrorKind::DuplicateVersionAttribute, attribute),
+                        first,
+                        "first `version` attribute",
+                    ));
+                }
+                version = Some(attribute);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The attributes `input` starts with, and the input after them
+pub fn header(input: Input) -> Result<(Vec<Spanned<Attribute>>, Input), InputParseErr> {
+    many0(combinators::spanned(attribute))(input).map(|ok| (ok.parsed, ok.remaining))
+}
+
+/// The document `input` holds, and the bytes backtracked parsing it, see
+/// [`Timings::backtracked`](crate::utf8_parser::Timings::backtracked)
+pub fn ron(input: Input) -> Result<(Ron, usize), InputParseErr> {
     match ron_inner(input) {
-        Ok(ok) if ok.remaining.is_empty() => Ok(ok.parsed),
-        Ok(ok) => Err(ErrorTree::expected(ok.remaining, Expectation::Eof)),
-        Err(InputParseErr::Fatal(e)) | Err(InputParseErr::Recoverable(e)) => Err(e),
+        Ok(ok) if ok.remaining.is_empty() => Ok((ok.parsed, ok.remaining.backtracked())),
+        Ok(ok) => Err(InputParseErr::fatal(ErrorTree::expected(
+            ok.remaining,
+            Expectation::Eof,
+        ))),
+        Err(e) => Err(e),
     }
 }
 
+/// Parses the attributes and the expression `input` starts with, and returns
+/// them with the rest of the input, which starts right after the expression
+pub fn ron_partial(input: Input) -> Result<(Ron, Input), InputParseErr> {
+    pair(
+        many0(combinators::spanned(attribute)),
+        preceded(multispacews0, combinators::unpadded_spanned(expr)),
+    )(input)
+    .map(|ok| {
+        let (attributes, expr) = ok.parsed;
+        let ron = Ron {
+            attributes,
+            expr,
+            trailing_attributes: vec![],
+        };
+
+        (ron, ok.remaining)
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -185,5 +417,11 @@ mod tests {
             ),
             Attribute::enables_test(vec![Extension::ImplicitSome, Extension::UnwrapNewtypes])
         );
+
+        assert_eq!(
+            eval!(attribute, "# ! [ version ( 2 ) ]"),
+            Attribute::Version(Spanned::new_test(2))
+        );
+        assert!(eval!(@result attribute, "#![version(two)]").is_err());
     }
 }
@@ -0,0 +1,227 @@
+//! Types for values that may be written in more than one way
+
+use std::{
+    fmt,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+    str::FromStr,
+};
+
+use serde::{
+    de::{
+        value::{MapAccessDeserializer, SeqAccessDeserializer},
+        Error, MapAccess, SeqAccess, Visitor,
+    },
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+/// The newtype struct name [`OneOrMany`] is deserialized with, which the RON
+/// deserializer recognizes
+pub(super) const ONE_OR_MANY: &str = "$ron_reboot::OneOrMany";
+
+/// Values written either as a list or, if there is just one, as the value
+/// itself, e.g. `tags: "a"` or `tags: ["a", "b"]`
+///
+/// Only `[..]` is a list of values: a tuple like `(1, 2)` is one value, so
+/// `T` may be a tuple. Serializing writes a single value without the list.
+/// Other deserializers only read lists.
+///
+/// ```
+/// use ron_reboot::utf8_parser::serde::{from_str, OneOrMany};
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Config {
+///     hosts: OneOrMany<String>,
+///     ranges: OneOrMany<(u16, u16)>,
+/// }
+///
+/// let config: Config = from_str(r#"(hosts: "a", ranges: [(1, 2), (8, 9)])"#).unwrap();
+/// assert_eq!(*config.hosts, ["a"]);
+/// assert_eq!(config.ranges.len(), 2);
+///
+/// let config: Config = from_str(r#"(hosts: ["a", "b"], ranges: (1, 2))"#).unwrap();
+/// assert_eq!(config.hosts.into_vec(), vec!["a", "b"]);
+/// assert_eq!(*config.ranges, [(1, 2)]);
+/// ```
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct OneOrMany<T>(pub Vec<T>);
+
+impl<T> OneOrMany<T> {
+    pub fn into_vec(self) -> Vec<T> {
+        self.0
+    }
+}
+
+impl<T> Deref for OneOrMany<T> {
+    type Target = Vec<T>;
+
+    fn deref(&self) -> &Vec<T> {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for OneOrMany<T> {
+    fn deref_mut(&mut self) -> &mut Vec<T> {
+        &mut self.0
+    }
+}
+
+impl<T> From<Vec<T>> for OneOrMany<T> {
+    fn from(values: Vec<T>) -> Self {
+        OneOrMany(values)
+    }
+}
+
+impl<T> From<OneOrMany<T>> for Vec<T> {
+    fn from(values: OneOrMany<T>) -> Self {
+        values.0
+    }
+}
+
+impl<T> IntoIterator for OneOrMany<T> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<T: Serialize> Serialize for OneOrMany<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0.as_slice() {
+            [one] => one.serialize(serializer),
+            many => many.serialize(serializer),
+        }
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for OneOrMany<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_newtype_struct(ONE_OR_MANY, OneOrManyVisitor(PhantomData))
+    }
+}
+
+struct OneOrManyVisitor<T>(PhantomData<T>);
+
+impl<'de, T: Deserialize<'de>> Visitor<'de> for OneOrManyVisitor<T> {
+    type Value = OneOrMany<T>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a value or a list of values")
+    }
+
+    /// The RON deserializer gives a single value as a list of one
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+        Vec::deserialize(SeqAccessDeserializer::new(seq)).map(OneOrMany)
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(self, d: D) -> Result<Self::Value, D::Error> {
+        Vec::deserialize(d).map(OneOrMany)
+    }
+}
+
+/// A value written either as a string, which is parsed with [`FromStr`], or
+/// as the value itself, e.g. `server: "localhost:80"` or
+/// `server: (host: "localhost", port: 80)`
+///
+/// Errors of `FromStr` point at the string. Serializing writes the value.
+///
+/// ```
+/// use std::str::FromStr;
+///
+/// use ron_reboot::utf8_parser::serde::{from_str, StringOrStruct};
+/// use serde::Deserialize;
+///
+/// #[derive(Debug, Deserialize, PartialEq)]
+/// struct Server {
+///     host: String,
+///     port: u16,
+/// }
+///
+/// impl FromStr for Server {
+///     type Err = String;
+///
+///     fn from_str(s: &str) -> Result<Self, String> {
+///         let (host, port) = s.split_once(':').ok_or("expected `host:port`")?;
+///         let port = port.parse().map_err(|_| format!("invalid port `{}`", port))?;
+///
+///         Ok(Server { host: host.to_owned(), port })
+///     }
+/// }
+///
+/// let server = Server { host: "localhost".to_owned(), port: 80 };
+/// let parsed: StringOrStruct<Server> = from_str(r#""localhost:80""#).unwrap();
+/// assert_eq!(parsed.0, server);
+/// let parsed: StringOrStruct<Server> = from_str(r#"(host: "localhost", port: 80)"#).unwrap();
+/// assert_eq!(parsed.0, server);
+///
+/// let e = from_str::<StringOrStruct<Server>>(r#""localhost:http""#).unwrap_err();
+/// assert_eq!(e.kind.to_string(), "invalid port `http`");
+/// ```
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct StringOrStruct<T>(pub T);
+
+impl<T> StringOrStruct<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for StringOrStruct<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for StringOrStruct<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T: Serialize> Serialize for StringOrStruct<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de, T> Deserialize<'de> for StringOrStruct<T>
+where
+    T: Deserialize<'de> + FromStr,
+    T::Err: fmt::Display,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(StringOrStructVisitor(PhantomData))
+    }
+}
+
+struct StringOrStructVisitor<T>(PhantomData<T>);
+
+impl<'de, T> Visitor<'de> for StringOrStructVisitor<T>
+where
+    T: Deserialize<'de> + FromStr,
+    T::Err: fmt::Display,
+{
+    type Value = StringOrStruct<T>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a string or a struct")
+    }
+
+    fn visit_str<E: Error>(self, v: &str) -> Result<Self::Value, E> {
+        v.parse().map(StringOrStruct).map_err(E::custom)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+        T::deserialize(MapAccessDeserializer::new(map)).map(StringOrStruct)
+    }
+
+    /// Tuple structs
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+        T::deserialize(SeqAccessDeserializer::new(seq)).map(StringOrStruct)
+    }
+}
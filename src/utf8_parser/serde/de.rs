@@ -1,9 +1,14 @@
 #![allow(clippy::type_complexity)]
 
+use std::{
+    any::type_name, borrow::Cow, cell::OnceCell, cmp::Ordering, convert::TryFrom, fmt::Display,
+    rc::Rc, str::FromStr, sync::Arc,
+};
+
 use serde::{
     de::{
-        DeserializeSeed, EnumAccess, Error as SerdeErrorTrait, MapAccess, SeqAccess, VariantAccess,
-        Visitor,
+        value::U32Deserializer, DeserializeSeed, EnumAccess, Error as SerdeErrorTrait, MapAccess,
+        SeqAccess, Unexpected, VariantAccess, Visitor,
     },
     forward_to_deserialize_any, Deserialize, Deserializer,
 };
@@ -12,73 +17,885 @@ use serde::{
 //use crate::error::{ron_err, ErrorKind};
 use crate::{
     ast::Untagged,
-    error::Error,
+    error::{Error, ErrorKind},
+    location::{ColumnMode, LineIndex, Location},
+    migrate::Migrations,
     utf8_parser::{
         ast,
         ast::{Expr::*, Integer},
-        ast_from_str,
+        ast_from_input_timed, ast_from_input_with_extensions,
+        extension::{DeserializeFlags, ExtensionRegistry},
+        primitive::ident::misspelled_keyword,
+        timings::timed,
+        Input, Timings,
     },
 };
-use crate::ast::{Attribute, Extension};
+
+use super::{
+    adapters::ONE_OR_MANY,
+    intercept::{intercept, ValueInterceptor},
+    metrics::Metrics,
+    raw::{RawRon, RawRonAccess, RAW_RON},
+    section::TAGGED_SECTION,
+};
 
 pub fn from_str<'a, T>(s: &'a str) -> Result<T, crate::error::Error>
 where
     T: Deserialize<'a>,
 {
-    let mut ron = ast_from_str(s)
-        .map_err(Error::from)
-        .map_err(|e| e.context_file_content(s.to_owned()))?;
+    from_str_with_options(s, Options::default())
+}
 
-    T::deserialize(RonDeserializer::from_ron(&mut ron))
-        .map_err(|e| e.context_file_content(s.to_owned()))
+pub fn from_str_with_options<'a, T>(s: &'a str, options: Options) -> Result<T, crate::error::Error>
+where
+    T: Deserialize<'a>,
+{
+    from_str_with_extensions(s, options, &ExtensionRegistry::new())
 }
 
-#[derive(Clone, Copy, Debug, Default)]
-struct Extensions {
-    implicit_some: bool,
-    unwrap_newtypes: bool,
+/// Deserializes `s`, which may enable any extension of `registry`.
+pub fn from_str_with_extensions<'a, T>(
+    s: &'a str,
+    options: Options,
+    registry: &ExtensionRegistry,
+) -> Result<T, crate::error::Error>
+where
+    T: Deserialize<'a>,
+{
+    let mut ron = ast_from_input_with_extensions(options.input(s), registry)
+        .map_err(|e| options.error(s, e))?;
+
+    T::deserialize(
+        RonDeserializer::from_ron_with_extensions(&mut ron, registry)
+            .with_options(options.clone())
+            .with_source(s),
+    )
+    .map_err(|e| options.error(s, e))
 }
 
-impl Extensions {
-    fn from_attrs(ron: &ast::Ron) -> Self {
-        let mut extensions = Extensions::default();
+/// Deserializes `s` like [`from_str_with_options`], but continues after
+/// struct fields that fail, so that all their errors are reported at once,
+/// e.g. to fix a whole section of a config in one go
+///
+/// A field that fails is left out and the document is deserialized again, so
+/// each field reports at most one error; errors of list elements and map
+/// entries are reported for the field containing them. Fields left out
+/// aren't reported again as `missing field`. The errors are in the order they
+/// are found, which is the order of the document unless
+/// [`sort_keys`](Options::sort_keys) is enabled.
+pub fn from_str_all_errors<'a, T>(
+    s: &'a str,
+    options: Options,
+) -> Result<T, Vec<crate::error::Error>>
+where
+    T: Deserialize<'a>,
+{
+    let registry = ExtensionRegistry::new();
+    let ron = ast_from_input_with_extensions(options.input(s), &registry)
+        .map_err(|e| vec![options.error(s, e)])?;
+    // The starts of the structs with fields left out, and the fields
+    let mut left_out: Vec<(Location, &ast::Spanned<ast::KeyValue<ast::Ident>>)> = vec![];
+    let mut errors = vec![];
+
+    loop {
+        let mut pass = ron.clone();
+        for (_, field) in &left_out {
+            leave_out(&mut pass.expr, field.start);
+        }
+        let e = match T::deserialize(
+            RonDeserializer::from_ron_with_extensions(&mut pass, &registry)
+                .with_options(options.clone())
+                .with_source(s),
+        ) {
+            Ok(value) if errors.is_empty() => return Ok(value),
+            Ok(_) => return Err(errors),
+            Err(e) => e,
+        };
+
+        let start = e.start();
+        let missing_left_out = left_out.iter().any(|(s, field)| {
+            let message = format!("missing field `{}`", field.value.key.value.as_str());
+            Some(*s) == start && e.kind == ErrorKind::Deserialize(message)
+        });
+        if !missing_left_out {
+            errors.push(options.error(s, e));
+        }
+        match start.and_then(|start| field_containing(&ron.expr, start)) {
+            Some((s, field)) if !left_out.iter().any(|(_, f)| f.start == field.start) => {
+                left_out.push((s, field))
+            }
+            _ => return Err(errors),
+        }
+    }
+}
 
-        for attribute in &ron.attributes {
-            match &attribute.value {
-                Attribute::Enable(list) => for extension in &list.value {
-                    match extension.value {
-                        Extension::UnwrapNewtypes => {
-                            extensions.unwrap_newtypes = true;
-                        }
-                        Extension::ImplicitSome => {
-                            extensions.implicit_some = true;
-                        }
-                    }
-                }
+/// The innermost struct field containing `location`, with the start of its
+/// struct
+fn field_containing<'a, 'de>(
+    expr: &'a ast::Spanned<ast::Expr<'de>>,
+    location: Location,
+) -> Option<(
+    Location,
+    &'a ast::Spanned<ast::KeyValue<'de, ast::Ident<'de>>>,
+)> {
+    let contains = |start: Location, end: Location| start <= location && location < end;
+    let field = expr
+        .value
+        .as_struct()
+        .and_then(|s| s.fields.iter().find(|f| contains(f.start, f.end)));
+
+    match field {
+        Some(f) => field_containing(&f.value.value, location).or(Some((expr.start, f))),
+        None => expr
+            .value
+            .children()
+            .filter(|child| contains(child.start, child.end))
+            .find_map(|child| field_containing(child, location)),
+    }
+}
+
+/// Removes the struct field starting at `field` from `expr`
+fn leave_out(expr: &mut ast::Spanned<ast::Expr>, field: Location) {
+    if let Some(s) = expr.value.as_struct_mut() {
+        s.fields.retain(|f| f.start != field);
+    }
+    for child in expr.value.children_mut() {
+        if child.start <= field && field < child.end {
+            leave_out(child, field);
+        }
+    }
+}
+
+/// Deserializes `s` after upgrading it to the latest version of `migrations`,
+/// see [`migrate`](crate::migrate)
+pub fn from_str_versioned<'a, T>(
+    s: &'a str,
+    migrations: &Migrations,
+) -> Result<T, crate::error::Error>
+where
+    T: Deserialize<'a>,
+{
+    let options = Options::default();
+    let registry = ExtensionRegistry::new();
+    let mut ron = ast_from_input_with_extensions(options.input(s), &registry)
+        .map_err(|e| options.error(s, e))?;
+
+    migrations
+        .migrate(&mut ron)
+        .and_then(|_| {
+            T::deserialize(
+                RonDeserializer::from_ron_with_extensions(&mut ron, &registry)
+                    .with_options(options.clone())
+                    .with_source(s),
+            )
+        })
+        .map_err(|e| options.error(s, e))
+}
+
+/// Deserializes `s` like [`from_str_with_options`], measuring how long each
+/// phase takes, e.g. to find out why a document is slow to load
+pub fn from_str_timed<'a, T>(
+    s: &'a str,
+    options: Options,
+) -> (Result<T, crate::error::Error>, Timings)
+where
+    T: Deserialize<'a>,
+{
+    let registry = ExtensionRegistry::new();
+    let (ron, mut timings) = ast_from_input_timed(options.input(s), &registry);
+    let mut ron = match ron {
+        Ok(ron) => ron,
+        Err(mut errors) => return (Err(options.error(s, errors.remove(0))), timings),
+    };
+    let value = timed(&mut timings.deserialize, || {
+        T::deserialize(
+            RonDeserializer::from_ron_with_extensions(&mut ron, &registry)
+                .with_options(options.clone())
+                .with_source(s),
+        )
+    })
+    .map_err(|e| options.error(s, e));
+
+    (value, timings)
+}
+
+/// Deserializes `s` after passing each of its values to `interceptor`.
+pub fn from_str_with_interceptor<'a, T>(
+    s: &'a str,
+    options: Options,
+    interceptor: &mut dyn ValueInterceptor,
+) -> Result<T, crate::error::Error>
+where
+    T: Deserialize<'a>,
+{
+    from_str_intercepted(s, options, &ExtensionRegistry::new(), interceptor)
+}
+
+fn from_str_intercepted<'a, T>(
+    s: &'a str,
+    options: Options,
+    registry: &ExtensionRegistry,
+    interceptor: &mut dyn ValueInterceptor,
+) -> Result<T, crate::error::Error>
+where
+    T: Deserialize<'a>,
+{
+    let mut ron = ast_from_input_with_extensions(options.input(s), registry)
+        .map_err(|e| options.error(s, e))?;
+
+    intercept(&mut ron, interceptor)
+        .and_then(|_| {
+            T::deserialize(
+                RonDeserializer::from_ron_with_extensions(&mut ron, registry)
+                    .with_options(options.clone())
+                    .with_source(s),
+            )
+        })
+        .map_err(|e| options.error(s, e))
+}
+
+/// Deserializes `s` like [`from_str_with_options`], then passes what it took
+/// to `on_metrics`, also if it fails, e.g. to monitor the cost of loading
+/// configs
+pub fn from_str_with_metrics<'a, T>(
+    s: &'a str,
+    options: Options,
+    on_metrics: &mut dyn FnMut(&Metrics),
+) -> Result<T, crate::error::Error>
+where
+    T: Deserialize<'a>,
+{
+    let (value, metrics) = from_str_measured(s, options, &ExtensionRegistry::new(), None);
+    on_metrics(&metrics);
+
+    value
+}
+
+/// Deserializes `s` like [`from_str_intercepted`], measuring the document
+fn from_str_measured<'a, T>(
+    s: &'a str,
+    options: Options,
+    registry: &ExtensionRegistry,
+    interceptor: Option<&mut dyn ValueInterceptor>,
+) -> (Result<T, crate::error::Error>, Metrics)
+where
+    T: Deserialize<'a>,
+{
+    let (ron, mut timings) = ast_from_input_timed(options.input(s), registry);
+    let mut metrics = Metrics {
+        bytes: s.len(),
+        ..Default::default()
+    };
+    let mut ron = match ron {
+        Ok(ron) => ron,
+        Err(mut errors) => {
+            metrics.timings = timings;
+            return (Err(options.error(s, errors.remove(0))), metrics);
+        }
+    };
+    metrics.count(&ron);
+
+    let value = timed(&mut timings.deserialize, || {
+        if let Some(interceptor) = interceptor {
+            intercept(&mut ron, interceptor)?;
+        }
+
+        T::deserialize(
+            RonDeserializer::from_ron_with_extensions(&mut ron, registry)
+                .with_options(options.clone())
+                .with_source(s),
+        )
+    })
+    .map_err(|e| options.error(s, e));
+    metrics.timings = timings;
+
+    (value, metrics)
+}
+
+/// [`Options`] with the extensions documents may enable, for choosing the
+/// policy per document, e.g. per request of a server
+///
+/// Nothing is configured globally, so documents deserialized with different
+/// scopes at the same time don't affect each other. Cloning a scope is cheap,
+/// the clones share its extensions and metrics callback.
+#[derive(Clone)]
+pub struct Scope {
+    options: Options,
+    registry: Arc<ExtensionRegistry>,
+    on_metrics: Option<Arc<dyn Fn(&Metrics) + Send + Sync>>,
+}
+
+impl Scope {
+    /// Lets documents enable the extensions of `registry` instead of only
+    /// the built-in ones
+    #[must_use]
+    pub fn with_extensions(mut self, registry: impl Into<Arc<ExtensionRegistry>>) -> Self {
+        self.registry = registry.into();
+
+        self
+    }
+
+    /// Passes what deserializing took to `on_metrics` after each document,
+    /// see [`from_str_with_metrics`]
+    #[must_use]
+    pub fn with_metrics(mut self, on_metrics: impl Fn(&Metrics) + Send + Sync + 'static) -> Self {
+        self.on_metrics = Some(Arc::new(on_metrics));
+
+        self
+    }
+
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
+
+    pub fn extensions(&self) -> &ExtensionRegistry {
+        &self.registry
+    }
+
+    /// Deserializes `s` like [`from_str_with_extensions`]
+    pub fn from_str<'a, T>(&self, s: &'a str) -> Result<T, crate::error::Error>
+    where
+        T: Deserialize<'a>,
+    {
+        match &self.on_metrics {
+            Some(on_metrics) => {
+                let (value, metrics) =
+                    from_str_measured(s, self.options.clone(), &self.registry, None);
+                on_metrics(&metrics);
+
+                value
+            }
+            None => from_str_with_extensions(s, self.options.clone(), &self.registry),
+        }
+    }
+
+    /// Deserializes `s` like [`from_str_with_interceptor`]
+    pub fn from_str_with_interceptor<'a, T>(
+        &self,
+        s: &'a str,
+        interceptor: &mut dyn ValueInterceptor,
+    ) -> Result<T, crate::error::Error>
+    where
+        T: Deserialize<'a>,
+    {
+        match &self.on_metrics {
+            Some(on_metrics) => {
+                let (value, metrics) =
+                    from_str_measured(s, self.options.clone(), &self.registry, Some(interceptor));
+                on_metrics(&metrics);
+
+                value
+            }
+            None => from_str_intercepted(s, self.options.clone(), &self.registry, interceptor),
+        }
+    }
+}
+
+/// Checks and settings for deserializing, all disabled by default
+///
+/// Independent of these, the parser always rejects unknown extensions,
+/// extensions enabled more than once and any content after the expression.
+///
+/// Map entries and struct fields are visited in the order they are written,
+/// unless [`sort_keys`](Options::sort_keys) is enabled.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct Options {
+    /// Reject maps with a key appearing more than once and structs with a field
+    /// appearing more than once
+    pub deny_duplicate_keys: bool,
+    /// Reject structs written without their name, e.g. `(x: 1)` instead of
+    /// `Point(x: 1)` (mismatching names are always rejected)
+    pub require_struct_names: bool,
+    /// Reject numbers deserialized as float that change when written back with
+    /// the shortest representation of the float type, e.g. `16777217` or
+    /// `0.10000000001` as `f32`
+    pub deny_precision_loss: bool,
+    /// Reject tuples and lists with more elements than the type consumes,
+    /// e.g. `(1, 2, 3)` as `(i32, i32)`
+    pub deny_trailing_content: bool,
+    /// Visit map entries and struct fields sorted by key instead of in the
+    /// order they are written, for consumers that want a canonical order
+    ///
+    /// Map keys are sorted booleans first, then numbers, then strings; other
+    /// keys come last in the order they are written.
+    pub sort_keys: bool,
+    /// Unescape strings only when they are deserialized, so that strings of
+    /// ignored fields aren't copied
+    ///
+    /// Values given to a [`ValueInterceptor`] are then
+    /// [`EscapedStr`](ast::Expr::EscapedStr) instead of `String`.
+    pub lazy_escapes: bool,
+    /// Accept non-ASCII identifiers, see [`Input::with_unicode_idents`]
+    ///
+    /// [`strict`](Options::strict) keeps identifiers ASCII-only.
+    #[cfg(feature = "unicode_ident")]
+    pub unicode_idents: bool,
+    /// Reject floats, e.g. for configs that may only contain integers, see
+    /// [`Input::with_deny_floats`]
+    ///
+    /// This is a policy rather than a check, so [`strict`](Options::strict)
+    /// still accepts floats.
+    pub deny_floats: bool,
+    /// Reject floats that aren't finite, e.g. `1e400`, or `1e39` as `f32`,
+    /// see [`Input::with_deny_non_finite`]
+    pub deny_non_finite: bool,
+    /// Deserialize numbers as they are written where a string is expected,
+    /// e.g. for `rust_decimal::serde::str` or `serde_with::DisplayFromStr`,
+    /// so that types like big integers and decimals parse them exactly
+    /// instead of through `f64`
+    ///
+    /// Integers written in another radix are converted to decimal, and `+`s
+    /// of integers dropped, decimals are as they are in the document. Numbers
+    /// may have more digits than a `u64` holds, see
+    /// [`Input::with_exact_numbers`].
+    pub numbers_as_str: bool,
+    /// How the columns of error locations are counted, chars by default
+    ///
+    /// Error snippets count columns the same way, so byte columns don't line
+    /// up with non-ASCII text there.
+    pub column_mode: ColumnMode,
+    /// Match struct fields written in another case, e.g. `Width` or `WIDTH`
+    /// for `width`, if the struct has no field written exactly like them
+    pub case_insensitive_fields: bool,
+    /// Old names of struct fields with the names that replaced them, e.g.
+    /// `[("colour", "color")]`, for documents written before fields were
+    /// renamed
+    ///
+    /// A written field is renamed if it isn't a field of the struct, but the
+    /// new name is. Aliases apply to the fields of all structs. They are
+    /// shared, so cloning the options doesn't copy them.
+    pub field_aliases: Arc<[(std::string::String, std::string::String)]>,
+}
+
+impl Options {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// These options as a [`Scope`], with the built-in extensions
+    pub fn scoped(self) -> Scope {
+        Scope {
+            options: self,
+            registry: Arc::new(ExtensionRegistry::new()),
+            on_metrics: None,
+        }
+    }
+
+    /// Enables all checks, e.g. for CI while keeping development lenient
+    pub fn strict() -> Self {
+        Options {
+            deny_duplicate_keys: true,
+            require_struct_names: true,
+            deny_precision_loss: true,
+            deny_trailing_content: true,
+            sort_keys: false,
+            lazy_escapes: false,
+            #[cfg(feature = "unicode_ident")]
+            unicode_idents: false,
+            deny_floats: false,
+            deny_non_finite: true,
+            numbers_as_str: false,
+            column_mode: ColumnMode::Chars,
+            case_insensitive_fields: false,
+            field_aliases: Arc::new([]),
+        }
+    }
+
+    #[must_use]
+    pub fn deny_duplicate_keys(mut self, deny_duplicate_keys: bool) -> Self {
+        self.deny_duplicate_keys = deny_duplicate_keys;
+
+        self
+    }
+
+    #[must_use]
+    pub fn require_struct_names(mut self, require_struct_names: bool) -> Self {
+        self.require_struct_names = require_struct_names;
+
+        self
+    }
+
+    #[must_use]
+    pub fn deny_precision_loss(mut self, deny_precision_loss: bool) -> Self {
+        self.deny_precision_loss = deny_precision_loss;
+
+        self
+    }
+
+    #[must_use]
+    pub fn deny_trailing_content(mut self, deny_trailing_content: bool) -> Self {
+        self.deny_trailing_content = deny_trailing_content;
+
+        self
+    }
+
+    #[must_use]
+    pub fn sort_keys(mut self, sort_keys: bool) -> Self {
+        self.sort_keys = sort_keys;
+
+        self
+    }
+
+    #[must_use]
+    pub fn lazy_escapes(mut self, lazy_escapes: bool) -> Self {
+        self.lazy_escapes = lazy_escapes;
+
+        self
+    }
+
+    #[cfg(feature = "unicode_ident")]
+    #[must_use]
+    pub fn unicode_idents(mut self, unicode_idents: bool) -> Self {
+        self.unicode_idents = unicode_idents;
+
+        self
+    }
+
+    #[must_use]
+    pub fn deny_floats(mut self, deny_floats: bool) -> Self {
+        self.deny_floats = deny_floats;
+
+        self
+    }
+
+    #[must_use]
+    pub fn deny_non_finite(mut self, deny_non_finite: bool) -> Self {
+        self.deny_non_finite = deny_non_finite;
+
+        self
+    }
+
+    #[must_use]
+    pub fn numbers_as_str(mut self, numbers_as_str: bool) -> Self {
+        self.numbers_as_str = numbers_as_str;
+
+        self
+    }
+
+    #[must_use]
+    pub fn column_mode(mut self, column_mode: ColumnMode) -> Self {
+        self.column_mode = column_mode;
+
+        self
+    }
+
+    #[must_use]
+    pub fn case_insensitive_fields(mut self, case_insensitive_fields: bool) -> Self {
+        self.case_insensitive_fields = case_insensitive_fields;
+
+        self
+    }
+
+    #[must_use]
+    pub fn field_aliases<I, O, N>(mut self, field_aliases: I) -> Self
+    where
+        I: IntoIterator<Item = (O, N)>,
+        O: Into<std::string::String>,
+        N: Into<std::string::String>,
+    {
+        self.field_aliases = field_aliases
+            .into_iter()
+            .map(|(old, new)| (old.into(), new.into()))
+            .collect();
+
+        self
+    }
+
+    /// The input for parsing `s` with these options
+    fn input<'a>(&self, s: &'a str) -> Input<'a> {
+        let input = Input::new(s)
+            .with_lazy_escapes(self.lazy_escapes)
+            .with_deny_floats(self.deny_floats)
+            .with_deny_non_finite(self.deny_non_finite)
+            .with_exact_numbers(self.numbers_as_str);
+        #[cfg(feature = "unicode_ident")]
+        let input = input.with_unicode_idents(self.unicode_idents);
+
+        input
+    }
+
+    /// `e`, an error in `s`, with the context these options add
+    fn error(&self, s: &str, e: Error) -> Error {
+        e.context_file_content(s.to_owned())
+            .with_column_mode(self.column_mode, s)
+    }
+
+    /// Renames the written fields of `s` that aren't in `fields` to the field
+    /// they are an alias of, or that they match ignoring case
+    fn match_fields(&self, s: &mut ast::Struct, fields: &'static [&'static str]) {
+        if !self.case_insensitive_fields && self.field_aliases.is_empty() {
+            return;
+        }
+
+        for field in &mut s.fields {
+            let key = &mut field.value.key.value;
+            if fields.contains(&key.as_str()) {
+                continue;
+            }
+
+            let alias = self
+                .field_aliases
+                .iter()
+                .filter(|(old, _)| old == key.as_str())
+                .find_map(|(_, new)| fields.iter().copied().find(|field| field == new));
+            let other_case = || match self.case_insensitive_fields {
+                true => fields.iter().copied().find(|field| eq_ignoring_case(field, key.as_str())),
+                false => None,
+            };
+            if let Some(field) = alias.or_else(other_case) {
+                key.0 = Cow::Borrowed(field);
+            }
+        }
+    }
+
+    fn check_duplicate_keys<K: PartialEq>(
+        &self,
+        kvs: &[ast::Spanned<ast::KeyValue<'_, K>>],
+    ) -> Result<(), Error> {
+        if !self.deny_duplicate_keys {
+            return Ok(());
+        }
+
+        for (i, kv) in kvs.iter().enumerate() {
+            let key = &kv.value.key;
+            if let Some(other) = kvs[..i].iter().find(|other| other.value.key == *key) {
+                let first = &other.value.key;
+
+                return Err(Error::custom("duplicate key")
+                    .context_loc(key.start, key.end)
+                    .context_label(first.start, first.end, "first used here".to_owned()));
             }
         }
 
-        extensions
+        Ok(())
+    }
+
+    /// Checks that `expr` is finite as a float `finite` accepts, if it is a
+    /// number
+    fn check_finite(
+        &self,
+        expr: &ast::Spanned<ast::Expr>,
+        type_name: &str,
+        finite: fn(f64) -> bool,
+    ) -> Result<(), Error> {
+        if !self.deny_non_finite {
+            return Ok(());
+        }
+
+        match &expr.value {
+            Decimal(d) if !finite(f64::from((**d).clone())) => Err(Error::custom(format!(
+                "number is out of range for `{}`, only finite floats are allowed",
+                type_name
+            ))
+            .context_loc(expr.start, expr.end)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks that the float type `F` can represent `expr` exactly, if it is a number
+    fn check_precision<F>(&self, expr: &ast::Spanned<ast::Expr>) -> Result<(), Error>
+    where
+        F: Display + FromStr,
+    {
+        if !self.deny_precision_loss {
+            return Ok(());
+        }
+
+        // All digits of decimals too long for their fields are in their text
+        let written = match &expr.value {
+            Decimal(d) => d.text.map_or_else(
+                || d.to_string(),
+                |text| text.strip_prefix('+').unwrap_or(text).to_owned(),
+            ),
+            Integer(i) => i.to_string(),
+            _ => return Ok(()),
+        };
+
+        let float = match written.parse::<F>() {
+            Ok(float) => float.to_string(),
+            Err(_) => return Ok(()),
+        };
+
+        match digits(&written) == digits(&float) {
+            true => Ok(()),
+            false => Err(Error::custom(format!(
+                "number can't be represented exactly as `{}`, the closest value is `{}`",
+                type_name::<F>(),
+                float
+            ))
+            .context_loc(expr.start, expr.end)),
+        }
+    }
+}
+
+/// Visits `ident`, borrowed from the document unless it was renamed
+fn visit_ident<'de, V>(ident: &ast::Ident<'de>, visitor: V) -> Result<V::Value, Error>
+where
+    V: Visitor<'de>,
+{
+    match &ident.0 {
+        Cow::Borrowed(s) => visitor.visit_borrowed_str(s),
+        Cow::Owned(s) => visitor.visit_str(s),
+    }
+}
+
+/// The text of `integer` that types parsing integers from strings take, which
+/// is `None` for integers written in another radix
+fn written_integer<'de>(integer: &ast::Integer<'de>) -> Option<&'de str> {
+    match integer {
+        Integer::Signed(s) => s.text,
+        Integer::Unsigned(u) => u
+            .text
+            .filter(|text| text.bytes().all(|b| b.is_ascii_digit())),
+        Integer::Big(Cow::Borrowed(text)) => Some(text),
+        Integer::Big(Cow::Owned(_)) => None,
+    }
+}
+
+/// Whether `a` and `b` are the same when both are lowercase
+fn eq_ignoring_case(a: &str, b: &str) -> bool {
+    a.chars()
+        .flat_map(char::to_lowercase)
+        .eq(b.chars().flat_map(char::to_lowercase))
+}
+
+/// The order of map keys with [`Options::sort_keys`]
+fn compare_map_keys(a: &ast::Expr, b: &ast::Expr) -> Ordering {
+    fn key<'a>(expr: &'a ast::Expr) -> (u8, f64, Cow<'a, str>) {
+        match expr {
+            Bool(b) => (0, f64::from(u8::from(*b)), "".into()),
+            Integer(Integer::Unsigned(u)) => (1, u.number as f64, "".into()),
+            Integer(Integer::Signed(s)) => (
+                1,
+                f64::from(s.sign.into_i8()) * s.number as f64,
+                "".into(),
+            ),
+            Decimal(d) => (1, f64::from((**d).clone()), "".into()),
+            Str(s) => (2, 0.0, (*s).into()),
+            String(s) => (2, 0.0, s.as_str().into()),
+            EscapedStr(s) => (2, 0.0, s.unescape()),
+            _ => (3, 0.0, "".into()),
+        }
+    }
+
+    let (a, b) = (key(a), key(b));
+
+    a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)).then(a.2.cmp(&b.2))
+}
+
+/// The sign, significant digits and exponent of the decimal number `s`, such that
+/// `1.50e2` and `150` have the same representation
+fn digits(s: &str) -> (bool, std::string::String, i64) {
+    let (negative, s) = match s.strip_prefix('-') {
+        Some(s) => (true, s),
+        None => (false, s),
+    };
+    let (mantissa, exponent) = match s.find(['e', 'E']) {
+        Some(i) => (&s[..i], s[i + 1..].parse().unwrap_or(i64::MAX)),
+        None => (s, 0),
+    };
+    let (whole, fractional) = match mantissa.find('.') {
+        Some(i) => (&mantissa[..i], &mantissa[i + 1..]),
+        None => (mantissa, ""),
+    };
+
+    let digits = format!("{}{}", whole, fractional);
+    let significant = digits.trim_start_matches('0').trim_end_matches('0');
+    let trailing_zeros = digits.len() - digits.trim_end_matches('0').len();
+    let exponent = exponent - fractional.len() as i64 + trailing_zeros as i64;
+
+    match significant.is_empty() {
+        true => (false, std::string::String::new(), 0),
+        false => (negative, significant.to_owned(), exponent),
     }
 }
 
 pub struct RonDeserializer<'a, 'de> {
-    extensions: Extensions,
+    shared: Rc<Shared<'de>>,
     expr: &'a mut ast::Spanned<ast::Expr<'de>>,
 }
 
+/// What the deserializers of all values of a document share
+#[derive(Clone)]
+struct Shared<'de> {
+    extensions: DeserializeFlags,
+    options: Options,
+    /// The deserialized text, if known, for [`RawRon`] values
+    source: Option<&'de str>,
+    /// The line index of `source`, built for the first value that needs it
+    index: OnceCell<LineIndex<'de>>,
+}
+
+impl<'de> Shared<'de> {
+    /// The indexed source, see [`RonDeserializer::with_source`]
+    fn source(&self) -> Option<&LineIndex<'de>> {
+        self.source
+            .map(|source| self.index.get_or_init(|| LineIndex::new(source)))
+    }
+}
+
 impl<'a, 'de> RonDeserializer<'a, 'de> {
-    /// Create a deserializer from a ron ast
+    /// Create a deserializer from a ron ast, with the deserializer flags of
+    /// the extensions in `registry`
     ///
     /// The ast will be completely replaced with empty exprs,
     /// thus cannot be used anymore.
-    pub fn from_ron(ron: &'a mut ast::Ron<'de>) -> Self {
+    pub fn from_ron_with_extensions(
+        ron: &'a mut ast::Ron<'de>,
+        registry: &ExtensionRegistry,
+    ) -> Self {
         RonDeserializer {
-            extensions: Extensions::from_attrs(ron),
+            shared: Rc::new(Shared {
+                extensions: registry.deserialize_flags(ron),
+                options: Options::default(),
+                source: None,
+                index: OnceCell::new(),
+            }),
             expr: &mut ron.expr,
         }
     }
 
+    #[must_use]
+    pub fn with_options(mut self, options: Options) -> Self {
+        Rc::make_mut(&mut self.shared).options = options;
+
+        self
+    }
+
+    /// Sets the text the AST was parsed from, which [`RawRon`] values borrow
+    #[must_use]
+    pub fn with_source(mut self, source: &'de str) -> Self {
+        let shared = Rc::make_mut(&mut self.shared);
+        shared.source = Some(source);
+        shared.index = OnceCell::new();
+
+        self
+    }
+
+    /// The deserializer of `expr`, a value in this one
+    fn child<'b>(&self, expr: &'b mut ast::Spanned<ast::Expr<'de>>) -> RonDeserializer<'b, 'de> {
+        RonDeserializer {
+            shared: self.shared.clone(),
+            expr,
+        }
+    }
+
+    /// The number this deserializes as text, see [`Options::numbers_as_str`]
+    fn number_text(&self) -> Option<Cow<'de, str>> {
+        match &self.expr.value {
+            Integer(i) => Some(match written_integer(i) {
+                Some(text) => Cow::Borrowed(text.strip_prefix('+').unwrap_or(text)),
+                None => Cow::Owned(i.to_string()),
+            }),
+            Decimal(d) => Some(
+                d.text
+                    .map_or_else(|| Cow::Owned(d.to_string()), Cow::Borrowed),
+            ),
+            _ => None,
+        }
+    }
+
     /*
     fn err<V>(&self, kind: ErrorKind) -> Result<V, crate::error::Error> {
         Err(dbg!(ron_err(kind, self.expr.start, self.expr.end)))
@@ -93,59 +910,118 @@ impl<'a, 'de> Deserializer<'de> for RonDeserializer<'a, 'de> {
     where
         V: Visitor<'de>,
     {
+        // `Nonee` or `False` for an option or `bool`
+        let misspelled = match &self.expr.value {
+            Tagged(t) => {
+                misspelled_keyword(&t.ident.value.0).map(|k| (k, t.ident.start, t.ident.end))
+            }
+            _ => None,
+        };
         let res = match self.expr.value.take() {
             Unit => visitor.visit_unit(),
-            Optional(Some(mut o)) => visitor.visit_some(RonDeserializer { extensions: self.extensions, expr: &mut *o }),
+            Optional(Some(mut o)) => visitor.visit_some(self.child(&mut o)),
             Optional(None) => visitor.visit_none(),
             Bool(b) => visitor.visit_bool(b),
-            Tuple(mut t) => visitor.visit_seq(SeqDeserializer {
-                extensions: self.extensions,
+            Tuple(mut t) => SeqDeserializer {
+                shared: self.shared.clone(),
                 iter: t.elements.iter_mut(),
-            }),
-            List(mut l) => visitor.visit_seq(SeqDeserializer {
-                extensions: self.extensions,
+            }
+            .visit(visitor),
+            List(mut l) => SeqDeserializer {
+                shared: self.shared.clone(),
                 iter: l.elements.iter_mut(),
-            }),
-            Map(mut m) => visitor.visit_map(MapDeserializer {
-                extensions: self.extensions,
+            }
+            .visit(visitor),
+            Map(mut m) => MapDeserializer {
+                shared: self.shared.clone(),
                 iter: m.entries.iter_mut(),
                 value: None,
-            }),
-            Struct(mut s) => visitor.visit_map(StructDeserializer {
-                extensions: self.extensions,
+            }
+            .visit(visitor),
+            Struct(mut s) => StructDeserializer {
+                shared: self.shared.clone(),
                 iter: s.fields.iter_mut(),
                 value: None,
-            }),
+            }
+            .visit(visitor),
             Integer(i) => match i {
-                Integer::Signed(s) => visitor.visit_i64(s.into()),
+                // Negative numbers below `i64::MIN` are left to the visitor,
+                // which rejects them unless it takes an `i128`
+                Integer::Signed(s) => match i64::try_from(s.clone()) {
+                    Ok(i) => visitor.visit_i64(i),
+                    Err(_) => visitor.visit_i128(s.into()),
+                },
                 Integer::Unsigned(u) => visitor.visit_u64(u.into()),
+                // Only parsed with `numbers_as_str`, for types like big
+                // integers, which take the ones that fit an `i128` or `u128`
+                Integer::Big(text) => match (text.parse::<i128>(), text.parse::<u128>()) {
+                    (Ok(i), _) => visitor.visit_i128(i),
+                    (_, Ok(u)) => visitor.visit_u128(u),
+                    _ => Err(Error::custom(
+                        "integer is too large for `i128` and `u128`, only a string can hold it",
+                    )),
+                },
             },
+            // Strings without escapes are borrowed from the source, so `&str`
+            // and `#[serde(borrow)] Cow<str>` don't copy them. Unescaped ones
+            // are owned, which `Cow` takes but `&str` can't.
             Str(s) => visitor.visit_borrowed_str(s),
             String(s) => visitor.visit_string(s),
-            Decimal(d) => visitor.visit_f64(d.into()),
+            EscapedStr(s) => match s.unescape() {
+                Cow::Borrowed(s) => visitor.visit_borrowed_str(s),
+                Cow::Owned(s) => visitor.visit_string(s),
+            },
+            Decimal(d) => visitor.visit_f64((*d).into()),
+            Quantity(q) => visitor.visit_string(q.to_string()),
+            // Like the tuple `(r, g, b, a)`
+            Color(c) => {
+                let mut channels = c
+                    .to_array()
+                    .iter()
+                    .map(|&channel| ast::Spanned {
+                        start: self.expr.start,
+                        value: ast::UnsignedInteger::new(channel.into()).to_expr(),
+                        end: self.expr.end,
+                    })
+                    .collect::<Vec<_>>();
+
+                SeqDeserializer {
+                    shared: self.shared.clone(),
+                    iter: channels.iter_mut(),
+                }
+                .visit(visitor)
+            }
             // TODO: deserialize as enum?
             Tagged(t) => match t.untagged.value {
-                Untagged::Struct(mut s) => visitor.visit_map(StructDeserializer {
-                    extensions: self.extensions,
+                Untagged::Struct(mut s) => StructDeserializer {
+                    shared: self.shared.clone(),
                     iter: s.fields.iter_mut(),
                     value: None,
-                }),
-                Untagged::Tuple(mut t) => visitor.visit_seq(SeqDeserializer {
-                    extensions: self.extensions,
+                }
+                .visit(visitor),
+                Untagged::Tuple(mut t) => SeqDeserializer {
+                    shared: self.shared.clone(),
                     iter: t.elements.iter_mut(),
-                }),
-                Untagged::Unit => visitor.visit_borrowed_str(t.ident.value.0),
+                }
+                .visit(visitor),
+                Untagged::Unit => visit_ident(&t.ident.value, visitor),
                 //_ => visitor.visit_enum(EnumDeserializer { tagged: &mut t }),
             },
         };
 
-        res.map_err(|e| e.context_loc(self.expr.start.into(), self.expr.end.into()))
+        res.map_err(|e| match misspelled {
+            // Only for errors about this value, not about what's in it
+            Some((keyword, start, end)) if e.start().is_none() => e
+                .context_loc(self.expr.start, self.expr.end)
+                .context_label(start, end, format!("did you mean `{}`?", keyword)),
+            _ => e.context_loc(self.expr.start, self.expr.end),
+        })
     }
 
     fn deserialize_struct<V>(
         self,
         name: &'static str,
-        _fields: &'static [&'static str],
+        fields: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value, Self::Error>
     where
@@ -154,36 +1030,63 @@ impl<'a, 'de> Deserializer<'de> for RonDeserializer<'a, 'de> {
         let start_loc = self.expr.start;
         let end_loc = self.expr.end;
         let res = match self.expr.value.take() {
-            ast::Expr::Tagged(ast::Tagged { ident, .. }) if ident.value.0 != name => {
+            ast::Expr::Tagged(t) if t.ident.value.0 != name => {
                 Err(Error::custom(format!(
                     "invalid struct type: `{}`, expected `{}`",
-                    ident.value.0, name
+                    t.ident.value.0, name
                 ))
-                .context_loc(ident.start, ident.end))
+                .context_loc(t.ident.start, t.ident.end))
             }
-            ast::Expr::Tagged(ast::Tagged {
-                untagged:
-                    ast::Spanned {
-                        value: Untagged::Struct(mut s),
-                        ..
-                    },
-                ..
-            })
-            | ast::Expr::Struct(mut s) => visitor.visit_map(StructDeserializer {
-                extensions: self.extensions,
-                iter: s.fields.iter_mut(),
-                value: None,
-            }),
-            x => {
-                self.expr.value = x;
+            ast::Expr::Struct(_) if self.shared.options.require_struct_names => Err(Error::custom(
+                format!("missing struct name, expected `{}`", name),
+            )),
+            mut x => match x.as_struct_mut() {
+                Some(s) => {
+                    self.shared.options.match_fields(s, fields);
+
+                    StructDeserializer {
+                        shared: self.shared.clone(),
+                        iter: s.fields.iter_mut(),
+                        value: None,
+                    }
+                    .visit(visitor)
+                }
+                None => {
+                    self.expr.value = x;
 
-                self.deserialize_any(visitor)
+                    self.deserialize_any(visitor)
+                }
             },
         };
 
         res.map_err(|e| e.context_loc(start_loc, end_loc))
     }
 
+    fn deserialize_unit_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.expr.value.take() {
+            ast::Expr::Tagged(t) if t.ident.value.0 != name => {
+                Err(Error::custom(format!(
+                    "invalid unit struct type: `{}`, expected `{}`",
+                    t.ident.value.0, name
+                ))
+                .context_loc(t.ident.start, t.ident.end))
+            }
+            ast::Expr::Tagged(t) if matches!(t.untagged.value, Untagged::Unit) => {
+                visitor.visit_unit()
+            }
+            ast::Expr::Unit => visitor.visit_unit(),
+            Optional(None) if name == "None" => visitor.visit_unit(),
+            x => {
+                self.expr.value = x;
+
+                self.deserialize_any(visitor)
+            },
+        }
+    }
+
     fn deserialize_enum<V>(
         self,
         _name: &'static str,
@@ -196,7 +1099,15 @@ impl<'a, 'de> Deserializer<'de> for RonDeserializer<'a, 'de> {
         let start_loc = self.expr.start;
         let end_loc = self.expr.end;
         let res = match self.expr.value.take() {
-            Tagged(mut t) => visitor.visit_enum(EnumDeserializer { extensions: self.extensions, tagged: &mut t }),
+            Tagged(mut t) => visitor.visit_enum(EnumDeserializer {
+                shared: self.shared.clone(),
+                tagged: &mut t,
+            }),
+            // `Some(..)` and `None` are only options if an option is expected
+            Optional(inner) => visitor.visit_enum(EnumDeserializer {
+                shared: self.shared.clone(),
+                tagged: &mut optional_variant(inner, start_loc, end_loc),
+            }),
             // probably no enum and will error
             x => {
                 self.expr.value = x;
@@ -228,72 +1139,178 @@ impl<'a, 'de> Deserializer<'de> for RonDeserializer<'a, 'de> {
     fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error> where V: Visitor<'de> {
         match self.expr.value.take() {
             Optional(None) => visitor.visit_none(),
-            Optional(Some(mut e)) => visitor.visit_some(RonDeserializer {
-                extensions: self.extensions,
-                expr: &mut e,
-            }),
+            Optional(Some(mut e)) => visitor.visit_some(self.child(&mut e)),
             x => {
-                self.expr.value = x;
-
-                let de = RonDeserializer {
-                    extensions: self.extensions,
-                    expr: self.expr,
+                // `Some` and `None` that aren't options, e.g. `Some(1, 2)`
+                let keyword = match &x {
+                    Tagged(t) if t.ident.value.0 == "Some" || t.ident.value.0 == "None" => {
+                        Some((t.ident.value.0 == "Some", t.ident.start, t.ident.end))
+                    }
+                    _ => None,
                 };
+                self.expr.value = x;
 
-                if self.extensions.implicit_some {
-                    visitor.visit_some(de)
+                if self.shared.extensions.implicit_some {
+                    visitor.visit_some(self)
                 } else {
-                    de.deserialize_any(visitor)
+                    self.deserialize_any(visitor).map_err(|e| match keyword {
+                        Some((true, start, end)) => Error::custom(
+                            "`Some` takes exactly one value, e.g. `Some(5)` or `Some((x: 1))`",
+                        )
+                        .context_loc(start, end),
+                        Some((_, start, end)) => {
+                            Error::custom("`None` takes no value").context_loc(start, end)
+                        }
+                        None => e,
+                    })
                 }
             }
         }
     }
 
     fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value, Self::Error> where V: Visitor<'de> {
+        if name == RAW_RON {
+            let (start, end) = (self.expr.start, self.expr.end);
+            let source = self.shared.source().ok_or_else(|| {
+                Error::custom("`RawRon` can only borrow from a string, e.g. with `from_str`")
+                    .context_loc(start, end)
+            })?;
+
+            return visitor.visit_seq(RawRonAccess::new(RawRon::from_span(source, start, end)));
+        }
+        if name == TAGGED_SECTION {
+            let ident = match &self.expr.value {
+                Tagged(t) => t.ident.clone(),
+                _ => {
+                    return Err(Error::custom("expected a section like `Name(..)`")
+                        .context_loc(self.expr.start, self.expr.end))
+                }
+            };
+
+            return visitor.visit_seq(SectionAccess {
+                ident,
+                value: Some(self),
+                next: 0,
+            });
+        }
+        if name == ONE_OR_MANY {
+            // Only `[..]` is many values, tuples are one
+            if let List(list) = &mut self.expr.value {
+                return SeqDeserializer {
+                    shared: self.shared.clone(),
+                    iter: list.elements.iter_mut(),
+                }
+                .visit(visitor);
+            }
+
+            return visitor.visit_seq(OneAccess(Some(self)));
+        }
+
         match self.expr.value.take() {
-            ast::Expr::Tagged(ast::Tagged { ident, .. }) if ident.value.0 != name => {
+            Optional(Some(mut e)) if name == "Some" => {
+                visitor.visit_newtype_struct(self.child(&mut e))
+            }
+            ast::Expr::Tagged(t) if t.ident.value.0 != name => {
                 Err(Error::custom(format!(
                     "invalid newtype struct type: `{}`, expected `{}`",
-                    ident.value.0, name
+                    t.ident.value.0, name
                 ))
-                    .context_loc(ident.start, ident.end))
+                    .context_loc(t.ident.start, t.ident.end))
             }
-            ast::Expr::Tagged(ast::Tagged {
-                                  untagged:
-                                  ast::Spanned {
-                                      value: Untagged::Tuple(mut t),
-                                      ..
-                                  },
-                                  ..
-                              })
-            | ast::Expr::Tuple(mut t) if t.elements.len() == 1 => visitor.visit_newtype_struct(RonDeserializer {
-                extensions: self.extensions,
-                expr: t.elements.iter_mut().next().unwrap(),
-            }),
-            x => {
-                self.expr.value = x;
-
-                if self.extensions.unwrap_newtypes {
-                    visitor.visit_newtype_struct(self)
-                } else {
-                    self.deserialize_any(visitor)
+            mut x => match x.as_tuple_mut() {
+                Some(t) if t.elements.len() == 1 => visitor.visit_newtype_struct(
+                    self.child(t.elements.iter_mut().next().expect("one element")),
+                ),
+                _ => {
+                    self.expr.value = x;
+
+                    if self.shared.extensions.unwrap_newtypes {
+                        visitor.visit_newtype_struct(self)
+                    } else {
+                        self.deserialize_any(visitor)
+                    }
                 }
             },
         }
     }
 
+    fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.shared.options.check_precision::<f32>(self.expr)?;
+        self.shared
+            .options
+            .check_finite(self.expr, "f32", |v| (v as f32).is_finite())?;
+
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.shared.options.check_precision::<f64>(self.expr)?;
+
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let number = match self.shared.options.numbers_as_str {
+            true => self.number_text(),
+            false => None,
+        };
+
+        match number {
+            Some(Cow::Borrowed(s)) => visitor.visit_borrowed_str(s),
+            Some(Cow::Owned(s)) => visitor.visit_string(s),
+            None => return self.deserialize_any(visitor),
+        }
+        .map_err(|e: Error| e.context_loc(self.expr.start, self.expr.end))
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
     forward_to_deserialize_any! {
-        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char string str
-        bytes byte_buf unit unit_struct seq tuple
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 char
+        bytes byte_buf unit seq tuple
         tuple_struct map
     }
 }
 
 struct SeqDeserializer<'a, 'de> {
-    extensions: Extensions,
+    shared: Rc<Shared<'de>>,
     iter: std::slice::IterMut<'a, ast::Spanned<ast::Expr<'de>>>,
 }
 
+impl<'a, 'de> SeqDeserializer<'a, 'de> {
+    fn visit<V>(mut self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        let value = visitor.visit_seq(&mut self)?;
+
+        match self.iter.next() {
+            Some(x) if self.shared.options.deny_trailing_content => {
+                Err(Error::custom(format!(
+                    "{} trailing element(s)",
+                    self.iter.len() + 1
+                ))
+                .context_loc(x.start, x.end))
+            }
+            _ => Ok(value),
+        }
+    }
+}
+
 impl<'a, 'de> SeqAccess<'de> for SeqDeserializer<'a, 'de> {
     type Error = crate::error::Error;
 
@@ -303,7 +1320,10 @@ impl<'a, 'de> SeqAccess<'de> for SeqDeserializer<'a, 'de> {
     {
         match self.iter.next() {
             Some(x) => seed
-                .deserialize(RonDeserializer { extensions: self.extensions, expr: x })
+                .deserialize(RonDeserializer {
+                    shared: self.shared.clone(),
+                    expr: x,
+                })
                 .map(Some)
                 .map_err(|e| e.context_loc(x.start.into(), x.end.into())),
             None => Ok(None),
@@ -312,11 +1332,27 @@ impl<'a, 'de> SeqAccess<'de> for SeqDeserializer<'a, 'de> {
 }
 
 struct StructDeserializer<'a, 'de> {
-    extensions: Extensions,
+    shared: Rc<Shared<'de>>,
     iter: std::slice::IterMut<'a, ast::Spanned<ast::KeyValue<'de, ast::Ident<'de>>>>,
     value: Option<&'a mut ast::Spanned<ast::Expr<'de>>>,
 }
 
+impl<'a, 'de> StructDeserializer<'a, 'de> {
+    fn visit<V>(mut self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.shared.options.check_duplicate_keys(self.iter.as_slice())?;
+        if self.shared.options.sort_keys {
+            let fields = std::mem::take(&mut self.iter).into_slice();
+            fields.sort_by(|a, b| a.value.key.value.0.cmp(&b.value.key.value.0));
+            self.iter = fields.iter_mut();
+        }
+
+        visitor.visit_map(&mut self)
+    }
+}
+
 impl<'a, 'de> MapAccess<'de> for StructDeserializer<'a, 'de> {
     type Error = crate::error::Error;
 
@@ -347,9 +1383,12 @@ impl<'a, 'de> MapAccess<'de> for StructDeserializer<'a, 'de> {
         let x = self
             .value
             .take()
-            .expect("called next_value_seed before next_key_seed");
-        seed.deserialize(RonDeserializer { extensions: self.extensions, expr: x })
-            .map_err(|e| e.context_loc(x.start.into(), x.end.into()))
+            .ok_or_else(|| Error::custom("called next_value_seed before next_key_seed"))?;
+        seed.deserialize(RonDeserializer {
+            shared: self.shared.clone(),
+            expr: x,
+        })
+        .map_err(|e| e.context_loc(x.start.into(), x.end.into()))
     }
 
     fn next_entry_seed<K, V>(
@@ -369,7 +1408,7 @@ impl<'a, 'de> MapAccess<'de> for StructDeserializer<'a, 'de> {
                     })
                     .map_err(|e| e.context_loc(x.start.into(), x.end.into()))?;
                 let value = vseed.deserialize(RonDeserializer {
-                    extensions: self.extensions,
+                    shared: self.shared.clone(),
                     expr: &mut x.value.value,
                 })?;
 
@@ -380,16 +1419,32 @@ impl<'a, 'de> MapAccess<'de> for StructDeserializer<'a, 'de> {
     }
 
     fn size_hint(&self) -> Option<usize> {
-        Some(self.iter.size_hint().0)
+        Some(self.iter.len())
     }
 }
 
 struct MapDeserializer<'a, 'de> {
-    extensions: Extensions,
+    shared: Rc<Shared<'de>>,
     iter: std::slice::IterMut<'a, ast::Spanned<ast::KeyValue<'de, ast::Expr<'de>>>>,
     value: Option<&'a mut ast::Spanned<ast::Expr<'de>>>,
 }
 
+impl<'a, 'de> MapDeserializer<'a, 'de> {
+    fn visit<V>(mut self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.shared.options.check_duplicate_keys(self.iter.as_slice())?;
+        if self.shared.options.sort_keys {
+            let entries = std::mem::take(&mut self.iter).into_slice();
+            entries.sort_by(|a, b| compare_map_keys(&a.value.key.value, &b.value.key.value));
+            self.iter = entries.iter_mut();
+        }
+
+        visitor.visit_map(&mut self)
+    }
+}
+
 impl<'a, 'de> MapAccess<'de> for MapDeserializer<'a, 'de> {
     type Error = crate::error::Error;
 
@@ -405,7 +1460,7 @@ impl<'a, 'de> MapAccess<'de> for MapDeserializer<'a, 'de> {
                 self.value = Some(&mut x.value.value);
 
                 seed.deserialize(RonDeserializer {
-                    extensions: self.extensions,
+                    shared: self.shared.clone(),
                     expr: &mut x.value.key,
                 })
                 .map(Some)
@@ -422,9 +1477,12 @@ impl<'a, 'de> MapAccess<'de> for MapDeserializer<'a, 'de> {
         let x = self
             .value
             .take()
-            .expect("called next_value_seed before next_key_seed");
-        seed.deserialize(RonDeserializer { extensions: self.extensions, expr: x })
-            .map_err(|e| e.context_loc(x.start.into(), x.end.into()))
+            .ok_or_else(|| Error::custom("called next_value_seed before next_key_seed"))?;
+        seed.deserialize(RonDeserializer {
+            shared: self.shared.clone(),
+            expr: x,
+        })
+        .map_err(|e| e.context_loc(x.start.into(), x.end.into()))
     }
 
     fn next_entry_seed<K, V>(
@@ -440,13 +1498,13 @@ impl<'a, 'de> MapAccess<'de> for MapDeserializer<'a, 'de> {
             Some(x) => {
                 let key = kseed
                     .deserialize(RonDeserializer {
-                        extensions: self.extensions,
+                        shared: self.shared.clone(),
                         expr: &mut x.value.key,
                     })
                     .map_err(|e| e.context_loc(x.start.into(), x.end.into()))?;
                 let value = vseed
                     .deserialize(RonDeserializer {
-                        extensions: self.extensions,
+                        shared: self.shared.clone(),
                         expr: &mut x.value.value,
                     })
                     .map_err(|e| e.context_loc(x.start.into(), x.end.into()))?;
@@ -458,7 +1516,7 @@ impl<'a, 'de> MapAccess<'de> for MapDeserializer<'a, 'de> {
     }
 
     fn size_hint(&self) -> Option<usize> {
-        Some(self.iter.size_hint().0)
+        Some(self.iter.len())
     }
 }
 
@@ -476,9 +1534,7 @@ impl<'a, 'de> Deserializer<'de> for IdentDeserializer<'a, 'de> {
         let start_loc = self.ident.start;
         let end_loc = self.ident.end;
 
-        visitor
-            .visit_borrowed_str(self.ident.value.0)
-            .map_err(|e: Error| e.context_loc(start_loc, end_loc))
+        visit_ident(&self.ident.value, visitor).map_err(|e: Error| e.context_loc(start_loc, end_loc))
     }
 
     forward_to_deserialize_any! {
@@ -488,8 +1544,169 @@ impl<'a, 'de> Deserializer<'de> for IdentDeserializer<'a, 'de> {
     }
 }
 
+/// `Some(..)` or `None` as the variant of an enum, e.g. one with a variant
+/// named `Some`
+fn optional_variant<'de>(
+    inner: Option<Box<ast::Spanned<ast::Expr<'de>>>>,
+    start: Location,
+    end: Location,
+) -> ast::Tagged<'de> {
+    let (name, untagged) = match inner {
+        Some(inner) => ("Some", Untagged::Tuple(ast::Tuple { elements: vec![*inner] })),
+        None => ("None", Untagged::Unit),
+    };
+    let ident_end = start.add_columns(name.len());
+
+    ast::Tagged {
+        ident: ast::Spanned {
+            start,
+            value: ast::Ident::from_str(name),
+            end: ident_end,
+        },
+        untagged: ast::Spanned {
+            start: ident_end,
+            value: untagged,
+            end,
+        },
+    }
+}
+
+/// The name of a section, the lines and columns of its start and end, and
+/// then its value, for [`TaggedSection`](super::TaggedSection)
+struct SectionAccess<'a, 'de> {
+    ident: ast::Spanned<ast::Ident<'de>>,
+    value: Option<RonDeserializer<'a, 'de>>,
+    next: usize,
+}
+
+impl<'a, 'de> SeqAccess<'de> for SectionAccess<'a, 'de> {
+    type Error = crate::error::Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        let (start, end) = (self.ident.start, self.ident.end);
+        self.next += 1;
+        let number = match self.next - 1 {
+            0 => {
+                return seed
+                    .deserialize(IdentDeserializer {
+                        ident: &mut self.ident,
+                    })
+                    .map(Some)
+            }
+            1 => start.line,
+            2 => start.column,
+            3 => end.line,
+            4 => end.column,
+            _ => {
+                return self
+                    .value
+                    .take()
+                    .map(|value| seed.deserialize(SectionValueDeserializer(value)))
+                    .transpose()
+            }
+        };
+
+        seed.deserialize(U32Deserializer::new(number)).map(Some)
+    }
+}
+
+/// A single value as a list of one, for [`OneOrMany`](super::OneOrMany)
+struct OneAccess<'a, 'de>(Option<RonDeserializer<'a, 'de>>);
+
+impl<'a, 'de> SeqAccess<'de> for OneAccess<'a, 'de> {
+    type Error = crate::error::Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        self.0.take().map(|value| seed.deserialize(value)).transpose()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.0.iter().len())
+    }
+}
+
+/// The value of a section, deserialized as if it were written without the
+/// name, or with the name of the struct it's deserialized into
+struct SectionValueDeserializer<'a, 'de>(RonDeserializer<'a, 'de>);
+
+impl<'a, 'de> SectionValueDeserializer<'a, 'de> {
+    fn rename(self, name: &'static str) -> RonDeserializer<'a, 'de> {
+        if let Tagged(t) = &mut self.0.expr.value {
+            t.ident.value = ast::Ident::from_str(name);
+        }
+
+        self.0
+    }
+}
+
+impl<'a, 'de> Deserializer<'de> for SectionValueDeserializer<'a, 'de> {
+    type Error = crate::error::Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let de = self.0;
+        if let Tagged(t) = &mut de.expr.value {
+            de.expr.value = match t.untagged.value.take() {
+                Untagged::Unit => Unit,
+                Untagged::Struct(s) => Struct(s),
+                Untagged::Tuple(t) => Tuple(t),
+            };
+        }
+
+        de.deserialize_any(visitor)
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.rename(name).deserialize_struct(name, fields, visitor)
+    }
+
+    fn deserialize_unit_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.rename(name).deserialize_unit_struct(name, visitor)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.rename(name).deserialize_newtype_struct(name, visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit seq tuple tuple_struct map enum identifier
+        ignored_any
+    }
+}
+
 struct EnumDeserializer<'a, 'de> {
-    extensions: Extensions,
+    shared: Rc<Shared<'de>>,
     tagged: &'a mut ast::Tagged<'de>,
 }
 
@@ -508,7 +1725,7 @@ impl<'a, 'de> EnumAccess<'de> for EnumDeserializer<'a, 'de> {
         Ok((
             variant_ident,
             UntaggedDeserializer {
-                extensions: self.extensions,
+                shared: self.shared.clone(),
                 untagged: &mut self.tagged.untagged,
             },
         ))
@@ -516,7 +1733,7 @@ impl<'a, 'de> EnumAccess<'de> for EnumDeserializer<'a, 'de> {
 }
 
 struct UntaggedDeserializer<'a, 'de> {
-    extensions: Extensions,
+    shared: Rc<Shared<'de>>,
     untagged: &'a mut ast::Spanned<ast::Untagged<'de>>,
 }
 
@@ -525,9 +1742,10 @@ impl<'a, 'de> VariantAccess<'de> for UntaggedDeserializer<'a, 'de> {
 
     fn unit_variant(self) -> Result<(), Self::Error> {
         match self.untagged.value.take() {
-            Untagged::Struct(_) => todo!(),
-            Untagged::Tuple(_) => todo!(),
+            // `Variant()`
+            Untagged::Tuple(t) if t.elements.is_empty() => Ok(()),
             Untagged::Unit => Ok(()),
+            found => Err(self.mismatch(&found, "unit variant")),
         }
     }
 
@@ -536,12 +1754,11 @@ impl<'a, 'de> VariantAccess<'de> for UntaggedDeserializer<'a, 'de> {
         T: DeserializeSeed<'de>,
     {
         match self.untagged.value.take() {
-            Untagged::Struct(_) => todo!(),
             Untagged::Tuple(mut t) => seed.deserialize(RonDeserializer {
-                extensions: self.extensions,
+                shared: self.shared.clone(),
                 expr: t.elements.iter_mut().next().ok_or_else(|| Error::custom("invalid enum variant, got zero tuple elements, but expected one (newtype variant)"))?
             }),
-            Untagged::Unit => todo!(),
+            found => Err(self.mismatch(&found, "newtype variant")),
         }
     }
 
@@ -550,31 +1767,51 @@ impl<'a, 'de> VariantAccess<'de> for UntaggedDeserializer<'a, 'de> {
         V: Visitor<'de>,
     {
         match self.untagged.value.take() {
-            Untagged::Struct(_) => todo!(),
-            Untagged::Tuple(mut t) => visitor.visit_seq(SeqDeserializer {
-                extensions: self.extensions,
+            Untagged::Tuple(mut t) => SeqDeserializer {
+                shared: self.shared.clone(),
                 iter: t.elements.iter_mut(),
-            }),
-            Untagged::Unit => todo!(),
+            }
+            .visit(visitor),
+            found => Err(self.mismatch(&found, "tuple variant")),
         }
     }
 
     fn struct_variant<V>(
         self,
-        _fields: &'static [&'static str],
+        fields: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
         match self.untagged.value.take() {
-            Untagged::Struct(mut s) => visitor.visit_map(StructDeserializer {
-                extensions: self.extensions,
-                iter: s.fields.iter_mut(),
-                value: None,
-            }),
-            Untagged::Tuple(_) => todo!(),
-            Untagged::Unit => todo!(),
+            Untagged::Struct(mut s) => {
+                self.shared.options.match_fields(&mut s, fields);
+
+                StructDeserializer {
+                    shared: self.shared.clone(),
+                    iter: s.fields.iter_mut(),
+                    value: None,
+                }
+                .visit(visitor)
+            }
+            found => Err(self.mismatch(&found, "struct variant")),
         }
     }
 }
+
+impl UntaggedDeserializer<'_, '_> {
+    /// The error for a variant written as `found` that is deserialized as
+    /// the `expected` kind of variant
+    fn mismatch(&self, found: &Untagged, expected: &str) -> Error {
+        let unexpected = match found {
+            Untagged::Unit => Unexpected::UnitVariant,
+            Untagged::Tuple(t) if t.elements.len() == 1 => Unexpected::NewtypeVariant,
+            Untagged::Tuple(_) => Unexpected::TupleVariant,
+            Untagged::Struct(_) => Unexpected::StructVariant,
+        };
+
+        Error::invalid_type(unexpected, &expected)
+            .context_loc(self.untagged.start, self.untagged.end)
+    }
+}
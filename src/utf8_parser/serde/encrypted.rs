@@ -0,0 +1,118 @@
+//! Encrypted values, written as `Encrypted("<base64 ciphertext>")`
+//!
+//! Secrets can be committed encrypted and decrypted at load time by passing a
+//! [`DecryptInterceptor`] to
+//! [`from_str_with_interceptor`](super::from_str_with_interceptor). Until
+//! then they are ordinary tagged tuples, so formatting and validating a
+//! document never needs the key. How the ciphertext is decrypted is up to
+//! the [`Decryptor`]; this module only finds and decodes it.
+
+use std::borrow::Cow;
+
+use crate::{
+    ast::{Expr, Untagged},
+    error::{Error, ErrorKind},
+    utf8_parser::serde::{PathSegment, ValueInterceptor},
+};
+
+/// The tag of encrypted values
+pub const ENCRYPTED_TAG: &str = "Encrypted";
+
+/// The base64 text of `expr` if it is an encrypted value like
+/// `Encrypted("...")`
+pub fn encrypted_payload<'b>(expr: &'b Expr) -> Option<Cow<'b, str>> {
+    let elements = match expr {
+        Expr::Tagged(t) if t.ident.value.0 == ENCRYPTED_TAG => match &t.untagged.value {
+            Untagged::Tuple(t) => &t.elements,
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    match elements.as_slice() {
+        [e] => match &e.value {
+            Expr::Str(s) => Some(Cow::Borrowed(s)),
+            Expr::String(s) => Some(Cow::Borrowed(s)),
+            Expr::EscapedStr(s) => Some(s.unescape()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Decrypts the ciphertext of encrypted values, e.g. with a key from the
+/// environment
+///
+/// Also implemented for closures with the signature of [`decrypt`](Decryptor::decrypt).
+pub trait Decryptor {
+    /// The plaintext of the value at `path`; errors without a location are
+    /// reported at the value.
+    fn decrypt(&mut self, path: &[PathSegment], ciphertext: &[u8]) -> Result<String, Error>;
+}
+
+impl<F> Decryptor for F
+where
+    F: FnMut(&[PathSegment], &[u8]) -> Result<String, Error>,
+{
+    fn decrypt(&mut self, path: &[PathSegment], ciphertext: &[u8]) -> Result<String, Error> {
+        self(path, ciphertext)
+    }
+}
+
+/// Replaces encrypted values with their plaintext as string, which is what
+/// they are deserialized as
+pub struct DecryptInterceptor<D> {
+    decryptor: D,
+}
+
+impl<D: Decryptor> DecryptInterceptor<D> {
+    pub fn new(decryptor: D) -> Self {
+        DecryptInterceptor { decryptor }
+    }
+}
+
+impl<D: Decryptor> ValueInterceptor for DecryptInterceptor<D> {
+    fn intercept(&mut self, path: &[PathSegment], expr: &mut Expr) -> Result<(), Error> {
+        let ciphertext = match encrypted_payload(expr) {
+            Some(payload) => decode_base64(&payload).ok_or_else(|| Error {
+                kind: ErrorKind::Custom("the encrypted value isn't valid base64".to_owned()),
+                context: None,
+            })?,
+            None => return Ok(()),
+        };
+        *expr = Expr::String(self.decryptor.decrypt(path, &ciphertext)?);
+
+        Ok(())
+    }
+}
+
+/// Decodes standard base64 (RFC 4648), with or without padding
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let text = text.trim_end_matches('=');
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let mut bits = 0u32;
+    let mut len = 0;
+
+    for c in text.bytes() {
+        let sextet = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        bits = bits << 6 | u32::from(sextet);
+        len += 6;
+        if len >= 8 {
+            len -= 8;
+            bytes.push((bits >> len) as u8);
+        }
+    }
+
+    // A single character left over can't encode a byte
+    match len < 6 {
+        true => Some(bytes),
+        false => None,
+    }
+}
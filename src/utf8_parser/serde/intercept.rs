@@ -0,0 +1,108 @@
+use crate::{
+    ast::{Expr, List, Ron, Spanned, Tuple, Untagged},
+    error::Error,
+    path::Step,
+};
+
+/// One step of the path from the root to a value
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PathSegment<'a> {
+    /// Field of a struct
+    Field(&'a str),
+    /// Element of a list or tuple
+    Index(usize),
+    /// Value of a map entry with this key
+    Key(&'a Expr<'a>),
+}
+
+impl PathSegment<'_> {
+    /// This segment as a step of a [`ValuePath`](crate::path::ValuePath),
+    /// unless it is the value of a map key paths can't address
+    pub fn to_step(&self) -> Option<Step> {
+        match self {
+            PathSegment::Field(field) => Some(Step::Key((*field).to_owned())),
+            PathSegment::Index(i) => Some(Step::Index(*i)),
+            PathSegment::Key(key) => Step::from_key(key),
+        }
+    }
+}
+
+/// Hook that can inspect and replace values before they are deserialized,
+/// e.g. to decrypt secrets, convert units or collect metrics
+///
+/// Also implemented for closures with the signature of [`intercept`](ValueInterceptor::intercept).
+pub trait ValueInterceptor {
+    /// Called for every value, parents before their children; the children
+    /// visited are those of the value after this call.
+    ///
+    /// Errors without a location are reported at the value.
+    fn intercept(&mut self, path: &[PathSegment], expr: &mut Expr) -> Result<(), Error>;
+}
+
+impl<F> ValueInterceptor for F
+where
+    F: FnMut(&[PathSegment], &mut Expr) -> Result<(), Error>,
+{
+    fn intercept(&mut self, path: &[PathSegment], expr: &mut Expr) -> Result<(), Error> {
+        self(path, expr)
+    }
+}
+
+/// Passes every value of `ron` to `interceptor`.
+pub(crate) fn intercept(
+    ron: &mut Ron,
+    interceptor: &mut dyn ValueInterceptor,
+) -> Result<(), Error> {
+    visit(&mut ron.expr, &mut vec![], interceptor)
+}
+
+fn visit<'x, 'de: 'x>(
+    expr: &'x mut Spanned<Expr<'de>>,
+    path: &mut Vec<PathSegment<'x>>,
+    interceptor: &mut dyn ValueInterceptor,
+) -> Result<(), Error> {
+    interceptor
+        .intercept(path, &mut expr.value)
+        .map_err(|e| e.context_loc(expr.start, expr.end))?;
+
+    let mut visit_child = |segment, child, path: &mut Vec<PathSegment<'x>>| {
+        path.push(segment);
+        let res = visit(child, path, interceptor);
+        path.pop();
+
+        res
+    };
+
+    let (fields, elements) = match &mut expr.value {
+        Expr::Optional(Some(inner)) => return visit(inner, path, interceptor),
+        Expr::Map(m) => {
+            return m.entries.iter_mut().try_for_each(|entry| {
+                let entry = &mut entry.value;
+                visit_child(PathSegment::Key(&entry.key.value), &mut entry.value, path)
+            })
+        }
+        Expr::Struct(s) => (Some(s), None),
+        Expr::Tuple(Tuple { elements }) | Expr::List(List { elements }) => (None, Some(elements)),
+        Expr::Tagged(t) => match &mut t.untagged.value {
+            Untagged::Struct(s) => (Some(s), None),
+            Untagged::Tuple(t) => (None, Some(&mut t.elements)),
+            Untagged::Unit => return Ok(()),
+        },
+        _ => return Ok(()),
+    };
+
+    match (fields, elements) {
+        (Some(s), _) => s.fields.iter_mut().try_for_each(|field| {
+            visit_child(
+                PathSegment::Field(field.value.key.value.as_str()),
+                &mut field.value.value,
+                path,
+            )
+        }),
+        (_, Some(elements)) => elements
+            .iter_mut()
+            .enumerate()
+            .try_for_each(|(i, e)| visit_child(PathSegment::Index(i), e, path)),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,49 @@
+use crate::{
+    ast::{walk_expr, Expr, Ron, Spanned, Visitor},
+    utf8_parser::Timings,
+};
+
+/// What deserializing a document took, see
+/// [`from_str_with_metrics`](super::from_str_with_metrics) and
+/// [`Scope::with_metrics`](super::Scope::with_metrics)
+///
+/// The counts are of the values written in the document, including those of
+/// fields the deserialized type ignores. They are zero if it couldn't be
+/// parsed.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Metrics {
+    /// The length of the document
+    pub bytes: usize,
+    /// Structs, with or without name
+    pub structs: usize,
+    /// Fields of all structs
+    pub fields: usize,
+    pub strings: usize,
+    /// Integers and floats
+    pub numbers: usize,
+    /// Time spent in each phase; [`Timings::total`] is the elapsed time
+    pub timings: Timings,
+}
+
+impl Metrics {
+    /// Adds the values of `ron` to the counts
+    pub(crate) fn count(&mut self, ron: &Ron) {
+        self.visit_ron(ron);
+    }
+}
+
+impl<'a> Visitor<'a> for Metrics {
+    fn visit_expr(&mut self, expr: &Spanned<Expr<'a>>) {
+        if let Some(s) = expr.value.as_struct() {
+            self.structs += 1;
+            self.fields += s.fields.len();
+        }
+        match &expr.value {
+            Expr::Str(_) | Expr::String(_) | Expr::EscapedStr(_) => self.strings += 1,
+            Expr::Integer(_) | Expr::Decimal(_) => self.numbers += 1,
+            _ => {}
+        }
+
+        walk_expr(self, expr);
+    }
+}
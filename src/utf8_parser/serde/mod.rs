@@ -1,13 +1,59 @@
-use std::{fs::File, io::Read, path::Path};
+use std::{
+    fs::File,
+    io::{BufWriter, Read, Write},
+    path::Path,
+};
 
-use serde::de::DeserializeOwned;
+#[cfg(feature = "async_reader")]
+use futures_util::io::{AsyncRead, AsyncReadExt};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
-pub use self::de::from_str;
+pub use self::{
+    adapters::{OneOrMany, StringOrStruct},
+    de::{
+        from_str, from_str_all_errors, from_str_timed, from_str_versioned,
+        from_str_with_extensions, from_str_with_interceptor, from_str_with_metrics,
+        from_str_with_options, Options, Scope,
+    },
+    intercept::{PathSegment, ValueInterceptor},
+    metrics::Metrics,
+    raw::RawRon,
+    section::TaggedSection,
+    ser::{
+        to_string, to_string_pretty, to_writer, to_writer_pretty, FormatVersion, KeyComparator,
+        MapKeyOrder, OptionStyle, PrettyConfig, Serializer, StructNames,
+    },
+};
 use crate::Error;
 
+mod adapters;
 mod de;
+pub mod encrypted;
+mod intercept;
+mod metrics;
+mod none_probe;
+mod raw;
+mod section;
+mod ser;
 #[cfg(test)]
 mod tests;
+#[cfg(feature = "unit_literals")]
+pub mod units;
+
+/// Deserializes `bytes`, e.g. of a memory-mapped file, which have to be UTF-8
+///
+/// Unlike [`from_reader`], this doesn't copy the input, so `T` may borrow from
+/// it.
+pub fn from_bytes<'a, T: Deserialize<'a>>(bytes: &'a [u8]) -> Result<T, Error> {
+    let s = std::str::from_utf8(bytes).map_err(|e| Error::invalid_utf8(bytes, e.valid_up_to()))?;
+
+    from_str(s)
+}
+
+/// Same as [`from_bytes`]
+pub fn from_slice<'a, T: Deserialize<'a>>(bytes: &'a [u8]) -> Result<T, Error> {
+    from_bytes(bytes)
+}
 
 pub fn from_reader<R: Read, T: DeserializeOwned>(mut reader: R) -> Result<T, Error> {
     let mut buf = String::new();
@@ -17,6 +63,20 @@ pub fn from_reader<R: Read, T: DeserializeOwned>(mut reader: R) -> Result<T, Err
     from_str(&buf)
 }
 
+/// Like [`from_reader`], but reads without blocking the executor
+#[cfg(feature = "async_reader")]
+pub async fn from_async_reader<R, T>(mut reader: R) -> Result<T, Error>
+where
+    R: AsyncRead + Unpin,
+    T: DeserializeOwned,
+{
+    let mut buf = String::new();
+
+    reader.read_to_string(&mut buf).await.map_err(Error::from)?;
+
+    from_str(&buf)
+}
+
 pub fn from_file<T: DeserializeOwned>(path: impl AsRef<Path>) -> Result<T, Error> {
     let path = path.as_ref();
 
@@ -25,3 +85,35 @@ pub fn from_file<T: DeserializeOwned>(path: impl AsRef<Path>) -> Result<T, Error
         .and_then(from_reader)
         .map_err(|e| e.context_file_name(path.display().to_string()))
 }
+
+/// Serializes `value` as compact RON into the file at `path`, which is
+/// created or truncated
+pub fn to_file<T: ?Sized + Serialize>(path: impl AsRef<Path>, value: &T) -> Result<(), Error> {
+    write_file(path.as_ref(), |writer| to_writer(writer, value))
+}
+
+/// Serializes `value` as pretty-printed RON into the file at `path`, which is
+/// created or truncated
+pub fn to_file_pretty<T: ?Sized + Serialize>(
+    path: impl AsRef<Path>,
+    value: &T,
+    config: PrettyConfig,
+) -> Result<(), Error> {
+    write_file(path.as_ref(), |writer| {
+        to_writer_pretty(writer, value, config)
+    })
+}
+
+fn write_file(
+    path: &Path,
+    write: impl FnOnce(&mut BufWriter<File>) -> Result<(), Error>,
+) -> Result<(), Error> {
+    File::create(path)
+        .map_err(Error::from)
+        .and_then(|file| {
+            let mut writer = BufWriter::new(file);
+            write(&mut writer)?;
+            writer.flush().map_err(Error::from)
+        })
+        .map_err(|e| e.context_file_name(path.display().to_string()))
+}
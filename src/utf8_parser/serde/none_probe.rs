@@ -0,0 +1,120 @@
+use std::fmt;
+
+use serde::{ser::Impossible, Serialize, Serializer};
+
+use crate::utf8_parser::serde::raw::RAW_RON;
+
+/// Whether `value` serializes as `None`, as the serializer would write it
+///
+/// Only the first call `value` makes to the serializer is looked at, so this
+/// costs the same however large `value` is.
+pub fn is_none<T: ?Sized + Serialize>(value: &T, unwrap_newtypes: bool) -> bool {
+    value.serialize(NoneProbe { unwrap_newtypes }).is_ok()
+}
+
+/// A serializer that only succeeds for `None`, and newtypes the serializer
+/// writes as their content
+struct NoneProbe {
+    unwrap_newtypes: bool,
+}
+
+/// What [`NoneProbe`] stops with at the first call that isn't `None`
+#[derive(Debug)]
+struct NotNone;
+
+impl fmt::Display for NotNone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("not `None`")
+    }
+}
+
+impl std::error::Error for NotNone {}
+
+impl serde::ser::Error for NotNone {
+    fn custom<T: fmt::Display>(_msg: T) -> Self {
+        NotNone
+    }
+}
+
+macro_rules! not_none {
+    ($($method:ident($($arg:ty),*) -> $ok:ty;)*) => {
+        $(
+            fn $method(self, $(_: $arg),*) -> Result<$ok, NotNone> {
+                Err(NotNone)
+            }
+        )*
+    };
+}
+
+impl Serializer for NoneProbe {
+    type Ok = ();
+    type Error = NotNone;
+    type SerializeSeq = Impossible<(), NotNone>;
+    type SerializeTuple = Impossible<(), NotNone>;
+    type SerializeTupleStruct = Impossible<(), NotNone>;
+    type SerializeTupleVariant = Impossible<(), NotNone>;
+    type SerializeMap = Impossible<(), NotNone>;
+    type SerializeStruct = Impossible<(), NotNone>;
+    type SerializeStructVariant = Impossible<(), NotNone>;
+
+    not_none! {
+        serialize_bool(bool) -> ();
+        serialize_i8(i8) -> ();
+        serialize_i16(i16) -> ();
+        serialize_i32(i32) -> ();
+        serialize_i64(i64) -> ();
+        serialize_i128(i128) -> ();
+        serialize_u8(u8) -> ();
+        serialize_u16(u16) -> ();
+        serialize_u32(u32) -> ();
+        serialize_u64(u64) -> ();
+        serialize_u128(u128) -> ();
+        serialize_f32(f32) -> ();
+        serialize_f64(f64) -> ();
+        serialize_char(char) -> ();
+        serialize_str(&str) -> ();
+        serialize_bytes(&[u8]) -> ();
+        serialize_unit() -> ();
+        serialize_unit_struct(&'static str) -> ();
+        serialize_unit_variant(&'static str, u32, &'static str) -> ();
+        serialize_seq(Option<usize>) -> Self::SerializeSeq;
+        serialize_tuple(usize) -> Self::SerializeTuple;
+        serialize_tuple_struct(&'static str, usize) -> Self::SerializeTupleStruct;
+        serialize_tuple_variant(&'static str, u32, &'static str, usize)
+            -> Self::SerializeTupleVariant;
+        serialize_map(Option<usize>) -> Self::SerializeMap;
+        serialize_struct(&'static str, usize) -> Self::SerializeStruct;
+        serialize_struct_variant(&'static str, u32, &'static str, usize)
+            -> Self::SerializeStructVariant;
+    }
+
+    fn serialize_none(self) -> Result<(), NotNone> {
+        Ok(())
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, _value: &T) -> Result<(), NotNone> {
+        Err(NotNone)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<(), NotNone> {
+        // Written as their content, so `None` inside them is written as is
+        match name == RAW_RON || self.unwrap_newtypes {
+            true => value.serialize(self),
+            false => Err(NotNone),
+        }
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<(), NotNone> {
+        Err(NotNone)
+    }
+}
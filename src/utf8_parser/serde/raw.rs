@@ -0,0 +1,208 @@
+use std::fmt;
+
+use serde::{
+    de::{
+        value::{BorrowedStrDeserializer, U32Deserializer},
+        DeserializeSeed, Error as _, SeqAccess, Visitor,
+    },
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::{
+    error::Error,
+    location::{LineIndex, Location},
+    utf8_parser::{ast_from_str, serde::from_str},
+};
+
+/// The newtype struct name [`RawRon`] is serialized and deserialized with,
+/// which the RON serializer and deserializer recognize
+pub(super) const RAW_RON: &str = "$ron_reboot::RawRon";
+
+/// A RON value as it is written, e.g. a section of a document only a plugin
+/// knows the type of
+///
+/// Deserializing a `RawRon` borrows the value's text from the document
+/// instead of parsing it into a type, so it can be deserialized later with
+/// [`parse`](RawRon::parse) or written back untouched: serializing it writes
+/// the text verbatim instead of as a string. This is similar to
+/// `serde_json::value::RawValue`.
+///
+/// ```
+/// use ron_reboot::{
+///     utf8_parser::serde::{from_str, to_string},
+///     Location, RawRon,
+/// };
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Deserialize, Serialize)]
+/// struct Scene<'a> {
+///     name: String,
+///     #[serde(borrow)]
+///     physics: RawRon<'a>,
+/// }
+///
+/// let scene: Scene = from_str("(name: \"a\", physics: Gravity(y: -9.81 /* m/s² */))").unwrap();
+/// assert_eq!(scene.physics.get(), "Gravity(y: -9.81 /* m/s² */)");
+/// assert_eq!(scene.physics.start(), Location { line: 1, column: 22 });
+/// assert_eq!(
+///     to_string(&scene).unwrap(),
+///     "(name:\"a\",physics:Gravity(y: -9.81 /* m/s² */))"
+/// );
+///
+/// let fragment = RawRon::new("Point(x: 1, y: 2)").unwrap();
+/// assert_eq!(to_string(&vec![fragment]).unwrap(), "[Point(x: 1, y: 2)]");
+/// assert!(RawRon::new("Point(x: 1,").is_err());
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RawRon<'a> {
+    ron: &'a str,
+    start: Location,
+    end: Location,
+}
+
+impl<'a> RawRon<'a> {
+    /// Checks that `ron` is a single RON value without attributes such as
+    /// `#![enable(..)]`, which can only be at the start of a document
+    ///
+    /// Whitespace and comments around the value are dropped; the value
+    /// itself is written as it is, so multi-line values aren't indented by
+    /// pretty printing.
+    pub fn new(ron: &'a str) -> Result<Self, Error> {
+        let ast = ast_from_str(ron)?;
+        if !ast.attributes.is_empty() {
+            return Err(Error::custom(
+                "raw RON cannot have attributes such as `#![enable(..)]`",
+            ));
+        }
+
+        Ok(RawRon::from_span(
+            &LineIndex::new(ron),
+            ast.expr.start,
+            ast.expr.end,
+        ))
+    }
+
+    /// The value spanning `start..end` of the indexed source
+    pub(super) fn from_span(source: &LineIndex<'a>, start: Location, end: Location) -> Self {
+        RawRon {
+            ron: source.slice(start, end),
+            start,
+            end,
+        }
+    }
+
+    pub fn get(&self) -> &'a str {
+        self.ron
+    }
+
+    /// Where the value starts in the document it was deserialized from, or
+    /// in the string given to [`new`](RawRon::new)
+    pub fn start(&self) -> Location {
+        self.start
+    }
+
+    /// Where the value ends, see [`start`](RawRon::start)
+    pub fn end(&self) -> Location {
+        self.end
+    }
+
+    /// Deserializes the value, e.g. once the type of a section is known
+    ///
+    /// The locations of errors are relative to the value, not to the
+    /// document it is part of.
+    pub fn parse<T: Deserialize<'a>>(&self) -> Result<T, Error> {
+        from_str(self.ron)
+    }
+}
+
+impl Serialize for RawRon<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(RAW_RON, self.ron)
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for RawRon<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_newtype_struct(RAW_RON, RawRonVisitor)
+    }
+}
+
+struct RawRonVisitor;
+
+impl<'de> Visitor<'de> for RawRonVisitor {
+    type Value = RawRon<'de>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a RON value")
+    }
+
+    /// Other formats only have the text, which is checked like
+    /// [`RawRon::new`]
+    fn visit_borrowed_str<E: serde::de::Error>(self, v: &'de str) -> Result<Self::Value, E> {
+        RawRon::new(v).map_err(E::custom)
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_str(self)
+    }
+
+    /// The RON deserializer gives the text and the locations, see
+    /// [`RawRonAccess`]
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let missing = || A::Error::custom("missing part of raw RON");
+        let ron = seq.next_element()?.ok_or_else(missing)?;
+        let mut location = || -> Result<Location, A::Error> {
+            Ok(Location {
+                line: seq.next_element()?.ok_or_else(missing)?,
+                column: seq.next_element()?.ok_or_else(missing)?,
+            })
+        };
+        let (start, end) = (location()?, location()?);
+        let raw = RawRon::new(ron).map_err(A::Error::custom)?;
+
+        Ok(RawRon { start, end, ..raw })
+    }
+}
+
+/// The text of a value, followed by the lines and columns of its start and
+/// end, for [`RawRonVisitor`]
+pub(super) struct RawRonAccess<'de> {
+    raw: RawRon<'de>,
+    next: usize,
+}
+
+impl<'de> RawRonAccess<'de> {
+    pub(super) fn new(raw: RawRon<'de>) -> Self {
+        RawRonAccess { raw, next: 0 }
+    }
+}
+
+impl<'de> SeqAccess<'de> for RawRonAccess<'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        let RawRon { ron, start, end } = self.raw;
+        let number = match self.next {
+            0 => None,
+            1 => Some(start.line),
+            2 => Some(start.column),
+            3 => Some(end.line),
+            4 => Some(end.column),
+            _ => return Ok(None),
+        };
+        self.next += 1;
+
+        match number {
+            None => seed
+                .deserialize(BorrowedStrDeserializer::new(ron))
+                .map(Some),
+            Some(number) => seed.deserialize(U32Deserializer::new(number)).map(Some),
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use std::{fmt, fmt::Display, marker::PhantomData};
+
+use serde::{
+    de::{Error as _, SeqAccess, Visitor},
+    Deserialize, Deserializer,
+};
+
+use crate::{error::Error, location::Location};
+
+/// The newtype struct name [`TaggedSection`] is deserialized with, which the
+/// RON deserializer recognizes
+pub(super) const TAGGED_SECTION: &str = "$ron_reboot::TaggedSection";
+
+/// A value written with a name, e.g. `Physics(gravity: -9.81)`, whose name
+/// is kept instead of checked against the type of the value
+///
+/// This is for configs made of sections that an application dispatches on
+/// by name, e.g. to plugins, where the names aren't known in advance. The
+/// value is deserialized as if it were written without the name, so any
+/// struct (or tuple, or `()` for just `Name`) can hold it. With
+/// [`RawRon`](super::RawRon) as `T`, the value is the whole section
+/// including the name, to be deserialized once its type is known.
+///
+/// Only the RON deserializer can deserialize a `TaggedSection`.
+///
+/// ```
+/// use ron_reboot::{utf8_parser::serde::from_str, Location, TaggedSection};
+/// use serde::Deserialize;
+///
+/// #[derive(Debug, Deserialize, PartialEq)]
+/// struct Settings {
+///     volume: u8,
+/// }
+///
+/// let sections: Vec<TaggedSection<Settings>> =
+///     from_str("[Audio(volume: 7), Voice(volume: 3)]").unwrap();
+/// assert_eq!(sections[1].name, "Voice");
+/// assert_eq!(sections[1].start, Location { line: 1, column: 20 });
+/// assert_eq!(sections[1].value, Settings { volume: 3 });
+///
+/// let e = sections[1].error("unknown section `Voice`");
+/// assert_eq!(e.start(), Some(Location { line: 1, column: 20 }));
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TaggedSection<T> {
+    /// The name the value is written with
+    pub name: String,
+    /// Where the name starts
+    pub start: Location,
+    /// Where the name ends
+    pub end: Location,
+    pub value: T,
+}
+
+impl<T> TaggedSection<T> {
+    /// An error pointing at the name of the section, e.g. for names the
+    /// application doesn't know
+    pub fn error(&self, message: impl Display) -> Error {
+        Error::custom(message).context_loc(self.start, self.end)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for TaggedSection<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_newtype_struct(TAGGED_SECTION, TaggedSectionVisitor(PhantomData))
+    }
+}
+
+struct TaggedSectionVisitor<T>(PhantomData<T>);
+
+impl<'de, T: Deserialize<'de>> Visitor<'de> for TaggedSectionVisitor<T> {
+    type Value = TaggedSection<T>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a section like `Name(..)`")
+    }
+
+    /// The RON deserializer gives the name, the lines and columns of its
+    /// start and end, and then the value
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let missing = || A::Error::custom("missing part of a tagged section");
+        let name = seq.next_element()?.ok_or_else(missing)?;
+        let mut location = || -> Result<Location, A::Error> {
+            Ok(Location {
+                line: seq.next_element()?.ok_or_else(missing)?,
+                column: seq.next_element()?.ok_or_else(missing)?,
+            })
+        };
+        let (start, end) = (location()?, location()?);
+        let value = seq.next_element()?.ok_or_else(missing)?;
+
+        Ok(TaggedSection {
+            name,
+            start,
+            end,
+            value,
+        })
+    }
+}
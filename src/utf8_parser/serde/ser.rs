@@ -0,0 +1,1045 @@
+use std::{cmp::Ordering, fmt, io::Write, mem::take, str::FromStr, sync::Arc};
+
+use serde::{
+    ser::{
+        Error as SerdeErrorTrait, SerializeMap, SerializeSeq, SerializeStruct,
+        SerializeStructVariant, SerializeTuple, SerializeTupleStruct, SerializeTupleVariant,
+    },
+    Serialize,
+};
+
+use crate::{
+    ast::Extension,
+    error::Error,
+    str::{escape, EscapePolicy},
+    utf8_parser::{
+        primitive::ident::{is_valid_ident, is_valid_raw_ident},
+        serde::{none_probe::is_none, raw::RAW_RON},
+    },
+};
+
+/// Serializes `value` into a compact RON string (no whitespace).
+pub fn to_string<T>(value: &T) -> Result<String, Error>
+where
+    T: ?Sized + Serialize,
+{
+    let mut output = vec![];
+    to_writer(&mut output, value)?;
+
+    Ok(String::from_utf8(output).expect("serializer only writes UTF-8"))
+}
+
+/// Serializes `value` into a pretty-printed RON string.
+pub fn to_string_pretty<T>(value: &T, config: PrettyConfig) -> Result<String, Error>
+where
+    T: ?Sized + Serialize,
+{
+    let mut output = vec![];
+    to_writer_pretty(&mut output, value, config)?;
+
+    Ok(String::from_utf8(output).expect("serializer only writes UTF-8"))
+}
+
+/// Serializes `value` as compact RON directly into `writer`.
+///
+/// Nothing is buffered, so consider passing a `BufWriter`.
+pub fn to_writer<W, T>(writer: W, value: &T) -> Result<(), Error>
+where
+    W: Write,
+    T: ?Sized + Serialize,
+{
+    value.serialize(&mut Serializer::new(writer, None))
+}
+
+/// Serializes `value` as pretty-printed RON directly into `writer`.
+///
+/// Nothing is buffered, so consider passing a `BufWriter`.
+pub fn to_writer_pretty<W, T>(writer: W, value: &T, config: PrettyConfig) -> Result<(), Error>
+where
+    W: Write,
+    T: ?Sized + Serialize,
+{
+    value.serialize(&mut Serializer::new(writer, Some(config)))
+}
+
+/// Which structs are written with their name
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StructNames {
+    /// `Config(window: Window(width: 1))`
+    Always,
+    /// `(window: (width: 1))`
+    Never,
+    /// `Config(window: (width: 1))`
+    TopLevelOnly,
+}
+
+/// How options are written, e.g. to keep configs with many optional fields
+/// small
+///
+/// Every style deserializes back to the same value: struct fields that are
+/// left out deserialize as `None`, and the output of
+/// [`ImplicitSome`](OptionStyle::ImplicitSome) enables the extension it
+/// relies on (see [`PrettyConfig::enable_extensions`]).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OptionStyle {
+    /// `(a: None, b: Some(1))`
+    Explicit,
+    /// `(b: Some(1))`, struct fields that are `None` are left out
+    SkipNone,
+    /// `#![enable(implicit_some)] (b: 1)`, like [`SkipNone`](OptionStyle::SkipNone)
+    /// with `Some` left out too
+    ///
+    /// `Some` is still written around values that would be read as options
+    /// themselves, e.g. `Some(None)`.
+    ImplicitSome,
+}
+
+/// Order in which map entries are written
+#[derive(Clone, Debug, PartialEq)]
+pub enum MapKeyOrder {
+    /// The order the map iterates in, which differs between runs for e.g.
+    /// `HashMap`s
+    Unchanged,
+    /// Sorted by the written keys
+    Sorted,
+    /// Sorted by the written keys with a custom comparator
+    Custom(KeyComparator),
+}
+
+type CompareFn = dyn Fn(&str, &str) -> Ordering + Send + Sync;
+
+/// Compares the written (RON) text of two map keys
+#[derive(Clone)]
+pub struct KeyComparator(Arc<CompareFn>);
+
+impl KeyComparator {
+    pub fn new(compare: impl Fn(&str, &str) -> Ordering + Send + Sync + 'static) -> Self {
+        KeyComparator(Arc::new(compare))
+    }
+}
+
+impl fmt::Debug for KeyComparator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "KeyComparator(..)")
+    }
+}
+
+impl PartialEq for KeyComparator {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// A version of the output of [`to_string_pretty`], for files that are
+/// committed formatted
+///
+/// The output of a version stays the same byte for byte, so that updating
+/// the crate doesn't change formatted files: changes to the output come as a
+/// new version, which the default ([`FormatVersion::LATEST`]) only moves to
+/// in a breaking release. Pin a version with
+/// [`PrettyConfig::format_version`] to keep it across those too.
+///
+/// `tests/golden` holds the output of every version.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum FormatVersion {
+    V1,
+}
+
+impl FormatVersion {
+    /// The version [`PrettyConfig`] uses by default
+    pub const LATEST: Self = FormatVersion::V1;
+
+    pub fn variants() -> [&'static str; 1] {
+        ["1"]
+    }
+}
+
+impl fmt::Display for FormatVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatVersion::V1 => write!(f, "1"),
+        }
+    }
+}
+
+impl FromStr for FormatVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "1" => Ok(FormatVersion::V1),
+            _ => Err(format!(
+                "valid values: {}",
+                Self::variants().to_vec().join(", ")
+            )),
+        }
+    }
+}
+
+/// Output options, mirroring `ron::ser::PrettyConfig`
+///
+/// The struct name and enum options also apply to the compact output of
+/// [`to_string`], which uses the defaults.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct PrettyConfig {
+    /// Collections nested deeper than this are written on a single line
+    pub depth_limit: usize,
+    /// Line ending, `"\n"` on all platforms unless set to e.g. `"\r\n"`
+    pub new_line: String,
+    pub indentor: String,
+    pub struct_names: StructNames,
+    /// Write unit variants as `Variant` instead of `Variant()`
+    pub enum_unit_as_ident: bool,
+    /// Escape all non-ASCII chars as `\u{..}` instead of writing them literally
+    pub escape_unicode: bool,
+    /// Write strings containing at least this many `"` and `\` as raw strings
+    /// (`r#"..."#`) instead of escaping them
+    ///
+    /// Strings with control chars other than newlines and tabs are always escaped.
+    pub raw_string_threshold: Option<usize>,
+    pub map_key_order: MapKeyOrder,
+    /// Write floats with exactly this many fractional digits instead of the
+    /// shortest representation that parses back to the same value
+    ///
+    /// At most 19 digits are written, and floats of 2^64 and more are written
+    /// with an exponent, e.g. `1.000e20`, so that they can be parsed again.
+    pub float_precision: Option<usize>,
+    pub option_style: OptionStyle,
+    /// Write newtype structs as the value they wrap, e.g. `5` instead of
+    /// `Meters(5)`, which relies on the `unwrap_newtypes` extension
+    pub unwrap_newtypes: bool,
+    /// Start the output with `#![enable(..)]` for the extensions it relies
+    /// on (see [`option_style`](PrettyConfig::option_style) and
+    /// [`unwrap_newtypes`](PrettyConfig::unwrap_newtypes)), so that it
+    /// parses without enabling them some other way
+    ///
+    /// Turn this off for values written into a document that enables them,
+    /// e.g. after its attributes.
+    pub enable_extensions: bool,
+    /// The version of the output, see [`FormatVersion`]
+    pub format_version: FormatVersion,
+}
+
+impl PrettyConfig {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    #[must_use]
+    pub fn depth_limit(mut self, depth_limit: usize) -> Self {
+        self.depth_limit = depth_limit;
+
+        self
+    }
+
+    #[must_use]
+    pub fn new_line(mut self, new_line: String) -> Self {
+        self.new_line = new_line;
+
+        self
+    }
+
+    #[must_use]
+    pub fn indentor(mut self, indentor: String) -> Self {
+        self.indentor = indentor;
+
+        self
+    }
+
+    #[must_use]
+    pub fn struct_names(mut self, struct_names: StructNames) -> Self {
+        self.struct_names = struct_names;
+
+        self
+    }
+
+    #[must_use]
+    pub fn enum_unit_as_ident(mut self, enum_unit_as_ident: bool) -> Self {
+        self.enum_unit_as_ident = enum_unit_as_ident;
+
+        self
+    }
+
+    #[must_use]
+    pub fn escape_unicode(mut self, escape_unicode: bool) -> Self {
+        self.escape_unicode = escape_unicode;
+
+        self
+    }
+
+    #[must_use]
+    pub fn raw_string_threshold(mut self, raw_string_threshold: Option<usize>) -> Self {
+        self.raw_string_threshold = raw_string_threshold;
+
+        self
+    }
+
+    #[must_use]
+    pub fn map_key_order(mut self, map_key_order: MapKeyOrder) -> Self {
+        self.map_key_order = map_key_order;
+
+        self
+    }
+
+    #[must_use]
+    pub fn float_precision(mut self, float_precision: Option<usize>) -> Self {
+        self.float_precision = float_precision;
+
+        self
+    }
+
+    #[must_use]
+    pub fn option_style(mut self, option_style: OptionStyle) -> Self {
+        self.option_style = option_style;
+
+        self
+    }
+
+    #[must_use]
+    pub fn unwrap_newtypes(mut self, unwrap_newtypes: bool) -> Self {
+        self.unwrap_newtypes = unwrap_newtypes;
+
+        self
+    }
+
+    #[must_use]
+    pub fn enable_extensions(mut self, enable_extensions: bool) -> Self {
+        self.enable_extensions = enable_extensions;
+
+        self
+    }
+
+    #[must_use]
+    pub fn format_version(mut self, format_version: FormatVersion) -> Self {
+        self.format_version = format_version;
+
+        self
+    }
+}
+
+impl Default for PrettyConfig {
+    fn default() -> Self {
+        PrettyConfig {
+            depth_limit: usize::MAX,
+            new_line: "\n".to_owned(),
+            indentor: "    ".to_owned(),
+            struct_names: StructNames::Never,
+            enum_unit_as_ident: true,
+            escape_unicode: false,
+            raw_string_threshold: None,
+            map_key_order: MapKeyOrder::Unchanged,
+            float_precision: None,
+            option_style: OptionStyle::Explicit,
+            unwrap_newtypes: false,
+            enable_extensions: true,
+            format_version: FormatVersion::LATEST,
+        }
+    }
+}
+
+pub struct Serializer<W> {
+    output: W,
+    config: PrettyConfig,
+    pretty: bool,
+    /// Number of collections the value currently being written is nested in
+    depth: usize,
+    /// `indentor` repeated for the deepest level written so far
+    indent: String,
+    /// Whether the next string is [`RawRon`](super::RawRon)
+    raw: bool,
+    /// Whether the extensions the output relies on still have to be enabled
+    /// before the value, see [`PrettyConfig::enable_extensions`]
+    attributes: bool,
+}
+
+impl<W: Write> Serializer<W> {
+    /// Creates a serializer writing compact output if `config` is `None`.
+    pub fn new(writer: W, config: Option<PrettyConfig>) -> Self {
+        Serializer {
+            output: writer,
+            attributes: config
+                .as_ref()
+                .is_some_and(|config| config.enable_extensions),
+            pretty: config.is_some(),
+            config: config.unwrap_or_default(),
+            depth: 0,
+            indent: String::new(),
+            raw: false,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.output
+    }
+
+    fn write(&mut self, s: &str) -> Result<(), Error> {
+        self.write_attributes()?;
+        self.output.write_all(s.as_bytes()).map_err(Error::from)
+    }
+
+    fn write_fmt(&mut self, args: fmt::Arguments) -> Result<(), Error> {
+        self.write_attributes()?;
+        self.output.write_fmt(args).map_err(Error::from)
+    }
+
+    /// Enables the extensions the output relies on, before the value
+    fn write_attributes(&mut self) -> Result<(), Error> {
+        if !take(&mut self.attributes) {
+            return Ok(());
+        }
+
+        let extensions = [
+            (
+                Extension::ImplicitSome,
+                self.config.option_style == OptionStyle::ImplicitSome,
+            ),
+            (Extension::UnwrapNewtypes, self.config.unwrap_newtypes),
+        ];
+        let names = extensions
+            .iter()
+            .filter(|(_, used)| *used)
+            .map(|(extension, _)| extension.name())
+            .collect::<Vec<_>>();
+        if !names.is_empty() {
+            write!(
+                self.output,
+                "#![enable({})]{}",
+                names.join(", "),
+                self.config.new_line
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Serializes `value` into a separate string
+    fn capture<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<String, Error> {
+        let mut captured = Serializer {
+            output: vec![],
+            config: take(&mut self.config),
+            pretty: self.pretty,
+            depth: self.depth,
+            indent: take(&mut self.indent),
+            raw: false,
+            attributes: false,
+        };
+        let res = value.serialize(&mut captured);
+        self.config = captured.config;
+        self.indent = captured.indent;
+        res?;
+
+        Ok(String::from_utf8(captured.output).expect("serializer only writes UTF-8"))
+    }
+
+    /// Writes `ident` as a raw identifier (`r#..`) if it isn't a valid
+    /// identifier, e.g. a field renamed to `kebab-case`
+    fn write_ident(&mut self, ident: &str) -> Result<(), Error> {
+        if is_valid_ident(ident) {
+            self.write(ident)
+        } else if is_valid_raw_ident(ident) {
+            write!(self, "r#{}", ident)
+        } else {
+            Err(Error::custom(format!(
+                "cannot serialize `{}` as an identifier",
+                ident
+            )))
+        }
+    }
+
+    fn write_struct_name(&mut self, name: &str) -> Result<bool, Error> {
+        let write = match self.config.struct_names {
+            StructNames::Always => true,
+            StructNames::Never => false,
+            StructNames::TopLevelOnly => self.depth == 0,
+        };
+        if write {
+            self.write_ident(name)?;
+        }
+
+        Ok(write)
+    }
+
+    fn write_str(&mut self, s: &str) -> Result<(), Error> {
+        if self.use_raw_str(s) {
+            return self.write_raw_str(s);
+        }
+
+        let policy = match self.config.escape_unicode {
+            true => EscapePolicy::Ascii,
+            false => EscapePolicy::Minimal,
+        };
+
+        self.write("\"")?;
+        self.write(&escape(s, policy))?;
+        self.write("\"")
+    }
+
+    fn use_raw_str(&self, s: &str) -> bool {
+        let threshold = match self.config.raw_string_threshold {
+            Some(threshold) => threshold,
+            None => return false,
+        };
+
+        s.chars().filter(|&c| c == '"' || c == '\\').count() >= threshold
+            && s.chars().all(|c| c == '\n' || c == '\t' || !c.is_control())
+            && (s.is_ascii() || !self.config.escape_unicode)
+    }
+
+    fn write_raw_str(&mut self, s: &str) -> Result<(), Error> {
+        // One more `#` than the longest run of `#` following a `"`
+        let hashes = s
+            .match_indices('"')
+            .map(|(i, _)| s[i + 1..].chars().take_while(|&c| c == '#').count() + 1)
+            .max()
+            .unwrap_or(0);
+        let hashes = "#".repeat(hashes);
+
+        write!(self, "r{}\"{}\"{}", hashes, s, hashes)
+    }
+
+    /// Writes the shortest representation that parses back to `v`
+    /// (or `v` with `float_precision` fractional digits).
+    ///
+    /// The parser reads the whole and fractional digits of decimals as `u64`s,
+    /// so with a precision, values of 2^64 and more are written with an
+    /// exponent, e.g. `1.000e20`, and at most 19 fractional digits are written.
+    fn write_float<F>(&mut self, v: F, finite: bool) -> Result<(), Error>
+    where
+        F: ryu::Float + fmt::Display + fmt::LowerExp + Into<f64>,
+    {
+        /// 2^64, the smallest whole part that doesn't fit a `u64`
+        const WHOLE_LIMIT: f64 = 18_446_744_073_709_551_616.0;
+        /// The most digits that always fit a `u64`
+        const MAX_FRACTIONAL_DIGITS: usize = 19;
+
+        if cfg!(feature = "integers_only") {
+            return Err(Error::custom(
+                "cannot serialize floats, the `integers_only` feature is enabled",
+            ));
+        }
+        if !finite {
+            return Err(Error::custom(format!(
+                "cannot serialize non-finite float `{}`",
+                v
+            )));
+        }
+
+        let mut buffer = ryu::Buffer::new();
+        let precise;
+        let formatted = match self.config.float_precision {
+            Some(precision) => {
+                let precision = precision.min(MAX_FRACTIONAL_DIGITS);
+                precise = match v.into().abs() < WHOLE_LIMIT {
+                    true => format!("{:.*}", precision, v),
+                    false => format!("{:.*e}", precision, v),
+                };
+                &precise
+            }
+            None => buffer.format_finite(v),
+        };
+
+        // RON decimals need a fractional part, e.g. `1e20` is written as `1.0e20`
+        match formatted.find('e') {
+            Some(exp) if !formatted[..exp].contains('.') => {
+                write!(self, "{}.0{}", &formatted[..exp], &formatted[exp..])
+            }
+            Some(_) => self.write(formatted),
+            None if !formatted.contains('.') => write!(self, "{}.0", formatted),
+            None => self.write(formatted),
+        }
+    }
+
+    fn write_indent(&mut self) -> Result<(), Error> {
+        let len = self.depth * self.config.indentor.len();
+        while self.indent.len() < len {
+            self.indent.push_str(&self.config.indentor);
+        }
+
+        self.output.write_all(self.config.new_line.as_bytes())?;
+        self.output.write_all(&self.indent.as_bytes()[..len])?;
+
+        Ok(())
+    }
+
+    /// Writes `open` and returns the state for writing the elements.
+    ///
+    /// `multiline` collections put each element on its own line when pretty printing.
+    fn start(
+        &mut self,
+        open: &str,
+        close: &'static str,
+        multiline: bool,
+    ) -> Result<Compound<'_, W>, Error> {
+        self.write(open)?;
+        self.depth += 1;
+        let multiline = multiline && self.pretty && self.depth <= self.config.depth_limit;
+
+        Ok(Compound {
+            ser: self,
+            first: true,
+            multiline,
+            close,
+            sorted_entries: None,
+        })
+    }
+}
+
+#[doc(hidden)]
+pub struct Compound<'a, W> {
+    ser: &'a mut Serializer<W>,
+    first: bool,
+    multiline: bool,
+    close: &'static str,
+    /// Written keys and values of a map, buffered until all entries are known
+    /// if they have to be sorted
+    sorted_entries: Option<Vec<(String, String)>>,
+}
+
+impl<W: Write> Compound<'_, W> {
+    fn element_start(&mut self) -> Result<(), Error> {
+        if self.multiline {
+            self.ser.write_indent()?;
+        } else if !self.first {
+            self.ser.write(if self.ser.pretty { ", " } else { "," })?;
+        }
+        self.first = false;
+
+        Ok(())
+    }
+
+    fn element_end(&mut self) -> Result<(), Error> {
+        match self.multiline {
+            true => self.ser.write(","),
+            false => Ok(()),
+        }
+    }
+
+    fn key_value_separator(&mut self) -> Result<(), Error> {
+        self.ser.write(if self.ser.pretty { ": " } else { ":" })
+    }
+
+    fn element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.element_start()?;
+        value.serialize(&mut *self.ser)?;
+        self.element_end()
+    }
+
+    fn field<T: ?Sized + Serialize>(&mut self, key: &str, value: &T) -> Result<(), Error> {
+        if self.ser.config.option_style != OptionStyle::Explicit
+            && is_none(value, self.ser.config.unwrap_newtypes)
+        {
+            return Ok(());
+        }
+
+        self.element_start()?;
+        self.ser.write_ident(key)?;
+        self.key_value_separator()?;
+        value.serialize(&mut *self.ser)?;
+        self.element_end()
+    }
+
+    /// Serializes `value` into a separate string
+    fn capture<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<String, Error> {
+        self.ser.capture(value)
+    }
+
+    fn finish(mut self) -> Result<(), Error> {
+        if let Some(mut entries) = self.sorted_entries.take() {
+            match &self.ser.config.map_key_order {
+                MapKeyOrder::Unchanged => {}
+                MapKeyOrder::Sorted => entries.sort_by(|a, b| a.0.cmp(&b.0)),
+                MapKeyOrder::Custom(KeyComparator(compare)) => {
+                    entries.sort_by(|a, b| compare(&a.0, &b.0))
+                }
+            }
+
+            for (key, value) in entries {
+                self.element_start()?;
+                self.ser.write(&key)?;
+                self.key_value_separator()?;
+                self.ser.write(&value)?;
+                self.element_end()?;
+            }
+        }
+
+        self.ser.depth -= 1;
+        if self.multiline && !self.first {
+            self.ser.write_indent()?;
+        }
+
+        self.ser.write(self.close)
+    }
+}
+
+impl<'a, W: Write> serde::Serializer for &'a mut Serializer<W> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Compound<'a, W>;
+    type SerializeTuple = Compound<'a, W>;
+    type SerializeTupleStruct = Compound<'a, W>;
+    type SerializeTupleVariant = Compound<'a, W>;
+    type SerializeMap = Compound<'a, W>;
+    type SerializeStruct = Compound<'a, W>;
+    type SerializeStructVariant = Compound<'a, W>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), Error> {
+        self.write(if v { "true" } else { "false" })
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), Error> {
+        write!(self, "{}", v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), Error> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), Error> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), Error> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), Error> {
+        write!(self, "{}", v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), Error> {
+        self.write_float(v, v.is_finite())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), Error> {
+        self.write_float(v, v.is_finite())
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), Error> {
+        self.write_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), Error> {
+        if self.raw {
+            self.raw = false;
+            return self.write(v);
+        }
+
+        self.write_str(v)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+        let mut seq = self.start("[", "]", true)?;
+        for byte in v {
+            seq.element(byte)?;
+        }
+
+        seq.finish()
+    }
+
+    fn serialize_none(self) -> Result<(), Error> {
+        self.write("None")
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        if self.config.option_style == OptionStyle::ImplicitSome {
+            // Values that would be read as options themselves keep their
+            // `Some`, e.g. `Some(None)` or a variant named `None`
+            let value = self.capture(value)?;
+            return match starts_with_option(&value) {
+                true => write!(self, "Some({})", value),
+                false => self.write(&value),
+            };
+        }
+
+        self.write("Some(")?;
+        value.serialize(&mut *self)?;
+        self.write(")")
+    }
+
+    fn serialize_unit(self) -> Result<(), Error> {
+        self.write("()")
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<(), Error> {
+        if !self.write_struct_name(name)? {
+            self.write("()")?;
+        }
+
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<(), Error> {
+        self.write_ident(variant)?;
+        match self.config.enum_unit_as_ident {
+            true => Ok(()),
+            false => self.write("()"),
+        }
+    }
+
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        if name == RAW_RON {
+            self.raw = true;
+            return value.serialize(self);
+        }
+
+        if self.config.unwrap_newtypes {
+            return value.serialize(self);
+        }
+
+        self.write_struct_name(name)?;
+        let mut compound = self.start("(", ")", false)?;
+        compound.element(value)?;
+
+        compound.finish()
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.write_ident(variant)?;
+        let mut compound = self.start("(", ")", false)?;
+        compound.element(value)?;
+
+        compound.finish()
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Compound<'a, W>, Error> {
+        self.start("[", "]", true)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Compound<'a, W>, Error> {
+        self.start("(", ")", false)
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        name: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a, W>, Error> {
+        self.write_struct_name(name)?;
+
+        self.start("(", ")", false)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a, W>, Error> {
+        self.write_ident(variant)?;
+
+        self.start("(", ")", false)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Compound<'a, W>, Error> {
+        let sort = self.config.map_key_order != MapKeyOrder::Unchanged;
+        let mut compound = self.start("{", "}", true)?;
+        if sort {
+            compound.sorted_entries = Some(vec![]);
+        }
+
+        Ok(compound)
+    }
+
+    fn serialize_struct(self, name: &'static str, _len: usize) -> Result<Compound<'a, W>, Error> {
+        self.write_struct_name(name)?;
+
+        self.start("(", ")", true)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a, W>, Error> {
+        self.write_ident(variant)?;
+
+        self.start("(", ")", true)
+    }
+}
+
+impl<W: Write> SerializeSeq for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<W: Write> SerializeTuple for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<W: Write> SerializeTupleStruct for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<W: Write> SerializeTupleVariant for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<W: Write> SerializeMap for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        if self.sorted_entries.is_some() {
+            let key = self.capture(key)?;
+            if let Some(entries) = &mut self.sorted_entries {
+                entries.push((key, String::new()));
+            }
+
+            return Ok(());
+        }
+
+        self.element_start()?;
+        key.serialize(&mut *self.ser)
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        if self.sorted_entries.is_some() {
+            let value = self.capture(value)?;
+            if let Some((_, entry_value)) = self.sorted_entries.as_mut().and_then(|e| e.last_mut())
+            {
+                *entry_value = value;
+            }
+
+            return Ok(());
+        }
+
+        self.key_value_separator()?;
+        value.serialize(&mut *self.ser)?;
+        self.element_end()
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<W: Write> SerializeStruct for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<W: Write> SerializeStructVariant for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+/// Whether `ron` starts with `Some` or `None`, which the deserializer reads
+/// as an option even with `implicit_some`
+fn starts_with_option(ron: &str) -> bool {
+    ["None", "Some"].iter().any(|keyword| {
+        ron.strip_prefix(keyword)
+            .is_some_and(|rest| !rest.starts_with(|c: char| c.is_alphanumeric() || c == '_'))
+    })
+}
@@ -1,8 +1,18 @@
-use std::{collections::HashMap, hash::Hash, iter::FromIterator};
+use std::{borrow::Cow, collections::HashMap, hash::Hash, iter::FromIterator};
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::{error::ErrorKind::*, utf8_parser::serde::from_str as normal_from_str};
+use crate::{
+    ast::{Decimal, Expr, UnsignedInteger},
+    error::{Error, ErrorKind::*},
+    location::Location,
+    utf8_parser::serde::{
+        from_bytes, from_str as normal_from_str, from_str_timed, from_str_with_extensions,
+        from_str_with_interceptor, from_str_with_metrics, from_str_with_options, to_string,
+        to_string_pretty, to_writer_pretty, KeyComparator, MapKeyOrder, OptionStyle, Options,
+        PathSegment, PrettyConfig, RawRon, StructNames, TaggedSection,
+    },
+};
 
 // Custom wrapper for tests to make error easier to read
 pub fn from_str<'a, T>(s: &'a str) -> Result<T, crate::error::Error>
@@ -100,6 +110,34 @@ StructVariant (
     );
 }
 
+#[test]
+fn mixed_enum_mismatch() {
+    let message = |s: &str| from_str::<MyEnum>(s).unwrap_err().kind.to_string();
+
+    assert_eq!(
+        message("UnitVariant1(1, 2)"),
+        "invalid type: tuple variant, expected unit variant"
+    );
+    assert_eq!(
+        message("NewtypeVariant"),
+        "invalid type: unit variant, expected newtype variant"
+    );
+    assert_eq!(
+        message("TupleVariant(a: 1)"),
+        "invalid type: struct variant, expected tuple variant"
+    );
+    assert_eq!(
+        message("StructVariant(true)"),
+        "invalid type: newtype variant, expected struct variant"
+    );
+    assert_eq!(
+        from_str::<MyEnum>("\n    UnitVariant2(a: 1)")
+            .unwrap_err()
+            .start(),
+        Some(Location { line: 2, column: 17 })
+    );
+}
+
 #[derive(Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd)]
 struct MyStruct {
     x: bool,
@@ -258,7 +296,7 @@ fn zero_copy_strs() {
     assert_eq!(from_str::<&str>(r#" "😀😀" "#), Ok("😀😀"));
     assert_eq!(
         from_str::<&str>(r#"  "Escapes are \\ fun but not available here :|" "#).unwrap_err().kind,
-        Custom(r#"invalid type: string "Escapes are \\ fun but not available here :|", expected a borrowed string"#.to_owned()),
+        crate::ErrorKind::Deserialize(r#"invalid type: string "Escapes are \\ fun but not available here :|", expected a borrowed string"#.to_owned()),
     );
 }
 
@@ -273,3 +311,1915 @@ fn lists() {
         Ok(vec![false, false, false])
     );
 }
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+struct Window {
+    title: String,
+    size: (u32, u32),
+    scale: f32,
+    mode: Mode,
+    layers: Vec<Layer>,
+    icon: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+struct Layer {
+    name: String,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+enum Mode {
+    Windowed,
+    Fullscreen(u8),
+    Borderless { monitor: u8 },
+}
+
+fn window() -> Window {
+    Window {
+        title: "Hello \"RON\"\n".to_owned(),
+        size: (1920, 1080),
+        scale: 1.0,
+        mode: Mode::Windowed,
+        layers: vec![Layer {
+            name: "ui".to_owned(),
+        }],
+        icon: None,
+    }
+}
+
+#[test]
+fn ser_compact() {
+    assert_eq!(
+        to_string(&window()).unwrap(),
+        r#"(title:"Hello \"RON\"\n",size:(1920,1080),scale:1.0,mode:Windowed,layers:[(name:"ui")],icon:None)"#
+    );
+    assert_eq!(
+        to_string(&vec![Mode::Fullscreen(1), Mode::Borderless { monitor: 2 }]).unwrap(),
+        "[Fullscreen(1),Borderless(monitor:2)]"
+    );
+    assert_eq!(to_string(&Some(-3i8)).unwrap(), "Some(-3)");
+    assert!(to_string(&f64::NAN).is_err());
+}
+
+#[test]
+fn raw_identifiers() {
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    #[serde(rename = "my-config")]
+    struct Config {
+        r#type: u8,
+        #[serde(rename = "max-size")]
+        max_size: u8,
+        format: Format,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    enum Format {
+        #[serde(rename = "v1.0")]
+        V1,
+    }
+
+    let config = Config {
+        r#type: 1,
+        max_size: 2,
+        format: Format::V1,
+    };
+    let ron = to_string_pretty(
+        &config,
+        PrettyConfig::new().struct_names(StructNames::Always),
+    )
+    .unwrap();
+    assert_eq!(
+        ron,
+        "r#my-config(\n    type: 1,\n    r#max-size: 2,\n    format: r#v1.0,\n)"
+    );
+    assert_eq!(from_str::<Config>(&ron), Ok(config));
+    assert_eq!(
+        from_str::<Config>("(r#type: 1, r#max-size: 2, format: r#v1.0)").map(|c| c.r#type),
+        Ok(1)
+    );
+
+    #[derive(Serialize)]
+    struct Invalid {
+        #[serde(rename = "with space")]
+        field: u8,
+    }
+    assert!(to_string(&Invalid { field: 1 }).is_err());
+}
+
+#[test]
+fn error_classes() {
+    use crate::ErrorClass;
+
+    assert_eq!(
+        from_str::<u8>("(1,").unwrap_err().class(),
+        ErrorClass::Parse
+    );
+    assert_eq!(
+        from_str::<u8>("#![enable(color_literals)] #ff8000")
+            .unwrap_err()
+            .class(),
+        ErrorClass::Deserialize
+    );
+    assert_eq!(
+        from_str::<u8>("\"1\"").unwrap_err().class(),
+        ErrorClass::Deserialize
+    );
+    assert_eq!(
+        to_string(&f64::NAN).unwrap_err().class(),
+        ErrorClass::Serialize
+    );
+    assert_eq!(
+        from_bytes::<u8>(b"\xff").unwrap_err().class(),
+        ErrorClass::Io
+    );
+}
+
+#[test]
+fn raw_ron() {
+    #[derive(Serialize)]
+    struct Scene<'a> {
+        name: &'a str,
+        #[serde(borrow)]
+        camera: RawRon<'a>,
+    }
+
+    let scene = Scene {
+        name: "r#\"",
+        camera: RawRon::new("/* generated */ Camera(fov: 90.0, pos: (0, 1, 2)) // by hand\n")
+            .unwrap(),
+    };
+    assert_eq!(
+        to_string(&scene).unwrap(),
+        r#"(name:"r#\"",camera:Camera(fov: 90.0, pos: (0, 1, 2)))"#
+    );
+    assert_eq!(
+        to_string_pretty(&scene, PrettyConfig::new()).unwrap(),
+        "(\n    name: \"r#\\\"\",\n    camera: Camera(fov: 90.0, pos: (0, 1, 2)),\n)"
+    );
+    let multi_line = RawRon::new("[\n  1, // one\n  2,\n]").unwrap();
+    assert_eq!(multi_line.get(), "[\n  1, // one\n  2,\n]");
+    assert_eq!(
+        from_str::<Vec<Vec<u8>>>(&to_string(&vec![multi_line]).unwrap()),
+        Ok(vec![vec![1, 2]])
+    );
+
+    assert!(RawRon::new("(x: 1,").is_err());
+    assert!(RawRon::new("1 2").is_err());
+    assert!(RawRon::new("#![enable(implicit_some)] 1").is_err());
+}
+
+#[test]
+fn raw_ron_deserialize() {
+    #[derive(Debug, Deserialize, Serialize)]
+    struct Plugins<'a> {
+        #[serde(borrow)]
+        plugins: HashMap<&'a str, RawRon<'a>>,
+        fallback: Option<RawRon<'a>>,
+    }
+
+    let source = "(\n    plugins: {\n        \"audio\": Audio(\n            volume: 0.5, // loud\n        ),\n        \"net\": [1, 2],\n    },\n    fallback: Some(\"none\"),\n)";
+    let config: Plugins = from_str(source).unwrap();
+
+    let audio = config.plugins["audio"];
+    assert_eq!(
+        audio.get(),
+        "Audio(\n            volume: 0.5, // loud\n        )"
+    );
+    assert_eq!(
+        (audio.start(), audio.end()),
+        (
+            Location {
+                line: 3,
+                column: 18
+            },
+            Location {
+                line: 5,
+                column: 10
+            }
+        )
+    );
+    assert_eq!(config.plugins["net"].parse::<Vec<u8>>(), Ok(vec![1, 2]));
+    assert_eq!(config.fallback.map(|raw| raw.get()), Some("\"none\""));
+
+    // Written back untouched
+    assert_eq!(
+        to_string(&config.plugins["audio"]).unwrap(),
+        "Audio(\n            volume: 0.5, // loud\n        )"
+    );
+
+    // Without the source, e.g. from an AST, there's nothing to borrow
+    let mut ron = crate::utf8_parser::ast_from_str("[1]").unwrap();
+    let deserializer = crate::utf8_parser::serde::de::RonDeserializer::from_ron_with_extensions(
+        &mut ron,
+        &crate::utf8_parser::extension::ExtensionRegistry::new(),
+    );
+    assert!(RawRon::deserialize(deserializer).is_err());
+}
+
+#[test]
+fn tagged_sections() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Physics {
+        gravity: f32,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Config<'a> {
+        #[serde(borrow)]
+        plugins: Vec<TaggedSection<RawRon<'a>>>,
+    }
+
+    let source = "(
+    plugins: [
+        Physics(gravity: -9.5),
+        Grid(4, 4),
+        Debug,
+    ],
+)";
+    let config: Config = from_str(source).unwrap();
+    let names = config
+        .plugins
+        .iter()
+        .map(|section| section.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["Physics", "Grid", "Debug"]);
+    assert_eq!(config.plugins[1].start, Location { line: 4, column: 9 });
+    assert_eq!(
+        config.plugins[1].end,
+        Location {
+            line: 4,
+            column: 13
+        }
+    );
+    // The raw value is the whole section, so it can be deserialized later
+    assert_eq!(
+        config.plugins[0].value.parse::<Physics>(),
+        Ok(Physics { gravity: -9.5 })
+    );
+
+    // The name of the section doesn't have to match the struct
+    let section: TaggedSection<Physics> = from_str("Moon(gravity: -1.5)").unwrap();
+    assert_eq!(section.name, "Moon");
+    assert_eq!(section.value, Physics { gravity: -1.5 });
+    let section: TaggedSection<(u8, u8)> = from_str("Grid(4, 4)").unwrap();
+    assert_eq!(section.value, (4, 4));
+    let section: TaggedSection<()> = from_str("Debug").unwrap();
+    assert_eq!(section.value, ());
+    let section: TaggedSection<HashMap<String, f32>> = from_str("Moon(gravity: -1.5)").unwrap();
+    assert_eq!(section.value["gravity"], -1.5);
+
+    assert_eq!(
+        section.error("unknown section").start(),
+        Some(Location { line: 1, column: 1 })
+    );
+    assert!(from_str::<TaggedSection<Physics>>("(gravity: -1.5)").is_err());
+    let e = from_str::<TaggedSection<Physics>>("Moon(gravity: true)").unwrap_err();
+    assert_eq!(
+        e.start(),
+        Some(Location {
+            line: 1,
+            column: 15
+        })
+    );
+}
+
+#[test]
+fn one_or_many() {
+    use crate::utf8_parser::serde::OneOrMany;
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Rule {
+        paths: OneOrMany<String>,
+        ports: OneOrMany<(u16, u16)>,
+    }
+
+    let rule: Rule = from_str(r#"(paths: "/a", ports: (80, 81))"#).unwrap();
+    assert_eq!(*rule.paths, ["/a"]);
+    assert_eq!(*rule.ports, [(80, 81)]);
+    assert_eq!(to_string(&rule).unwrap(), r#"(paths:"/a",ports:(80,81))"#);
+
+    let rule: Rule = from_str(r#"(paths: [], ports: [(1, 2), (3, 4)])"#).unwrap();
+    assert!(rule.paths.is_empty());
+    assert_eq!(rule.ports.into_vec(), vec![(1, 2), (3, 4)]);
+
+    // Errors point at the value, in a list or not
+    let start = |s| from_str::<Rule>(s).unwrap_err().start();
+    assert_eq!(
+        start(r#"(paths: 1, ports: [])"#),
+        Some(Location { line: 1, column: 9 })
+    );
+    assert_eq!(
+        start(r#"(paths: ["a", 1], ports: [])"#),
+        Some(Location {
+            line: 1,
+            column: 15
+        })
+    );
+}
+
+#[test]
+fn string_or_struct() {
+    use std::{net::Ipv4Addr, str::FromStr};
+
+    use crate::utf8_parser::serde::StringOrStruct;
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Addr {
+        ip: Ipv4Addr,
+        port: u16,
+    }
+
+    impl FromStr for Addr {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, String> {
+            let (ip, port) = s.split_once(':').ok_or("expected `ip:port`")?;
+
+            Ok(Addr {
+                ip: ip.parse().map_err(|e| format!("{}", e))?,
+                port: port.parse().map_err(|e| format!("{}", e))?,
+            })
+        }
+    }
+
+    let addr = Addr {
+        ip: Ipv4Addr::LOCALHOST,
+        port: 8080,
+    };
+    let parse = |s| from_str::<Vec<StringOrStruct<Addr>>>(s);
+    let parsed = parse(
+        r#"["127.0.0.1:8080", (ip: "127.0.0.1", port: 8080), Addr(ip: "127.0.0.1", port: 8080)]"#,
+    )
+    .unwrap();
+    assert!(parsed.iter().all(|parsed| **parsed == addr));
+    assert_eq!(
+        to_string(&parsed[0]).unwrap(),
+        r#"(ip:"127.0.0.1",port:8080)"#
+    );
+
+    let e = parse(r#"[(ip: "127.0.0.1", port: 80), "localhost"]"#).unwrap_err();
+    assert_eq!(e.kind.to_string(), "expected `ip:port`");
+    assert_eq!(
+        e.start(),
+        Some(Location {
+            line: 1,
+            column: 31
+        })
+    );
+    assert!(parse("[1]")
+        .unwrap_err()
+        .kind
+        .to_string()
+        .ends_with("expected a string or a struct"));
+}
+
+#[test]
+fn ser_pretty() {
+    assert_eq!(
+        to_string_pretty(&window(), PrettyConfig::new()).unwrap(),
+        r#"(
+    title: "Hello \"RON\"\n",
+    size: (1920, 1080),
+    scale: 1.0,
+    mode: Windowed,
+    layers: [
+        (
+            name: "ui",
+        ),
+    ],
+    icon: None,
+)"#
+    );
+    assert_eq!(
+        to_string_pretty(&window(), PrettyConfig::new().depth_limit(1)).unwrap(),
+        r#"(
+    title: "Hello \"RON\"\n",
+    size: (1920, 1080),
+    scale: 1.0,
+    mode: Windowed,
+    layers: [(name: "ui")],
+    icon: None,
+)"#
+    );
+    assert_eq!(
+        to_string_pretty(&Vec::<u8>::new(), PrettyConfig::new()).unwrap(),
+        "[]"
+    );
+}
+
+#[test]
+fn ser_struct_names() {
+    let compact = |struct_names| {
+        let config = PrettyConfig::new()
+            .struct_names(struct_names)
+            .depth_limit(0);
+
+        to_string_pretty(&window(), config).unwrap()
+    };
+
+    assert!(compact(StructNames::Always).starts_with("Window(title:"));
+    assert!(compact(StructNames::Always).contains("layers: [Layer(name: \"ui\")]"));
+    assert!(compact(StructNames::TopLevelOnly).starts_with("Window(title:"));
+    assert!(compact(StructNames::TopLevelOnly).contains("layers: [(name: \"ui\")]"));
+    assert!(compact(StructNames::Never).starts_with("(title:"));
+}
+
+#[test]
+fn ser_enum_unit_as_ident() {
+    let config = PrettyConfig::new().enum_unit_as_ident(false);
+    assert_eq!(
+        to_string_pretty(&Mode::Windowed, config).unwrap(),
+        "Windowed()"
+    );
+    assert_eq!(from_str::<Mode>("Windowed()"), Ok(Mode::Windowed));
+}
+
+#[test]
+fn ser_roundtrip() {
+    for config in [
+        PrettyConfig::new(),
+        PrettyConfig::new().struct_names(StructNames::Always),
+        PrettyConfig::new()
+            .struct_names(StructNames::TopLevelOnly)
+            .enum_unit_as_ident(false),
+    ] {
+        let ron = to_string_pretty(&window(), config).unwrap();
+        assert_eq!(from_str::<Window>(&ron), Ok(window()));
+    }
+
+    let ron = to_string(&window()).unwrap();
+    assert_eq!(from_str::<Window>(&ron), Ok(window()));
+}
+
+#[test]
+fn ser_escapes() {
+    let s = "Grüße\t\u{7}";
+    assert_eq!(to_string(&s).unwrap(), r#""Grüße\t\u{7}""#);
+    assert_eq!(
+        to_string_pretty(&s, PrettyConfig::new().escape_unicode(true)).unwrap(),
+        r#""Gr\u{fc}\u{df}e\t\u{7}""#
+    );
+    assert_eq!(from_str::<String>(r#""Gr\u{fc}\u{df}e\t\u{7}""#), Ok(s.to_owned()));
+}
+
+#[test]
+fn ser_raw_strings() {
+    let config = || PrettyConfig::new().raw_string_threshold(Some(2));
+
+    let path = r"C:\Users\ron";
+    assert_eq!(to_string_pretty(&path, config()).unwrap(), r#"r"C:\Users\ron""#);
+    assert_eq!(
+        to_string_pretty(&r#"say "hi""#, config()).unwrap(),
+        r##"r#"say "hi""#"##
+    );
+    assert_eq!(
+        to_string_pretty(&"\"#x\"", config()).unwrap(),
+        r###"r##""#x""##"###
+    );
+    // Below the threshold
+    assert_eq!(to_string_pretty(&r"a\b", config()).unwrap(), r#""a\\b""#);
+    // Control chars can't be written raw
+    assert_eq!(
+        to_string_pretty(&"\\\\\u{7}", config()).unwrap(),
+        r#""\\\\\u{7}""#
+    );
+
+    for s in [path, r#"say "hi""#, "\"#x\"", "multi\nline \"raw\""] {
+        let ron = to_string_pretty(&s, config()).unwrap();
+        assert_eq!(from_str::<String>(&ron), Ok(s.to_owned()));
+    }
+}
+
+#[test]
+fn ser_new_line() {
+    let config = PrettyConfig::new().new_line("\r\n".to_owned());
+    assert_eq!(
+        to_string_pretty(&vec![1, 2], config).unwrap(),
+        "[\r\n    1,\r\n    2,\r\n]"
+    );
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+struct Tile {
+    x: i32,
+    y: i32,
+}
+
+#[test]
+fn ser_map_keys() {
+    let tiles: HashMap<(i32, i32), &str> = HashMap::from_iter(vec![((0, 1), "grass")]);
+    assert_eq!(to_string(&tiles).unwrap(), r#"{(0,1):"grass"}"#);
+    assert_eq!(from_str(r#"{(0,1):"grass"}"#), Ok(tiles));
+
+    let tiles: HashMap<Tile, Mode> = HashMap::from_iter(vec![(Tile { x: 0, y: -1 }, Mode::Fullscreen(1))]);
+    let config = PrettyConfig::new().struct_names(StructNames::Always);
+    let ron = to_string_pretty(&tiles, config).unwrap();
+    assert_eq!(
+        ron,
+        "{\n    Tile(\n        x: 0,\n        y: -1,\n    ): Fullscreen(1),\n}"
+    );
+    assert_eq!(from_str(&ron), Ok(tiles));
+}
+
+#[test]
+fn ser_map_key_order() {
+    let map: HashMap<&str, u8> = HashMap::from_iter(vec![("b", 2), ("c", 3), ("a", 1)]);
+    let ser = |order| {
+        to_string_pretty(&map, PrettyConfig::new().map_key_order(order).depth_limit(0)).unwrap()
+    };
+
+    assert_eq!(ser(MapKeyOrder::Sorted), r#"{"a": 1, "b": 2, "c": 3}"#);
+    assert_eq!(
+        ser(MapKeyOrder::Custom(KeyComparator::new(|a, b| b.cmp(a)))),
+        r#"{"c": 3, "b": 2, "a": 1}"#
+    );
+
+    let nested: HashMap<u8, HashMap<&str, u8>> = HashMap::from_iter(vec![(2, map.clone()), (1, map)]);
+    let ron = to_string_pretty(&nested, PrettyConfig::new().map_key_order(MapKeyOrder::Sorted))
+        .unwrap();
+    assert_eq!(
+        ron,
+        "{\n    1: {\n        \"a\": 1,\n        \"b\": 2,\n        \"c\": 3,\n    },\n    2: {\n        \"a\": 1,\n        \"b\": 2,\n        \"c\": 3,\n    },\n}"
+    );
+    assert_eq!(from_str(&ron), Ok(nested));
+}
+
+#[test]
+fn ser_option_styles() {
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Window {
+        title: Option<String>,
+        size: Option<(u32, u32)>,
+        parent: Option<Option<u8>>,
+        tags: Vec<Option<u8>>,
+    }
+
+    let window = Window {
+        title: None,
+        size: Some((800, 600)),
+        parent: Some(None),
+        tags: vec![Some(1), None],
+    };
+    let ser = |style| {
+        to_string_pretty(&window, PrettyConfig::new().option_style(style).depth_limit(0)).unwrap()
+    };
+
+    assert_eq!(
+        ser(OptionStyle::Explicit),
+        "(title: None, size: Some((800, 600)), parent: Some(None), tags: [Some(1), None])"
+    );
+    assert_eq!(
+        ser(OptionStyle::SkipNone),
+        "(size: Some((800, 600)), parent: Some(None), tags: [Some(1), None])"
+    );
+    assert_eq!(
+        ser(OptionStyle::ImplicitSome),
+        "#![enable(implicit_some)]\n(size: (800, 600), parent: Some(None), tags: [1, None])"
+    );
+    for style in [OptionStyle::Explicit, OptionStyle::SkipNone, OptionStyle::ImplicitSome] {
+        assert_eq!(from_str::<Window>(&ser(style)).as_ref(), Ok(&window), "{:?}", style);
+    }
+
+    // Only `None` is left out, not variants named like it
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    enum Filter {
+        None,
+        Linear,
+    }
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Texture {
+        filter: Filter,
+        mipmaps: Option<Filter>,
+    }
+
+    let texture = Texture {
+        filter: Filter::None,
+        mipmaps: Some(Filter::Linear),
+    };
+    let ron = to_string_pretty(
+        &texture,
+        PrettyConfig::new().option_style(OptionStyle::ImplicitSome),
+    )
+    .unwrap();
+    assert_eq!(
+        ron,
+        "#![enable(implicit_some)]\n(\n    filter: None,\n    mipmaps: Linear,\n)"
+    );
+
+    // Newtypes are only left out if they are written as the `None` inside them
+    #[derive(Serialize)]
+    struct Parent(Option<u8>);
+    #[derive(Serialize)]
+    struct Node {
+        parent: Parent,
+        depth: u8,
+    }
+
+    let node = Node {
+        parent: Parent(None),
+        depth: 0,
+    };
+    let config = PrettyConfig::new()
+        .option_style(OptionStyle::SkipNone)
+        .depth_limit(0);
+    assert_eq!(
+        to_string_pretty(&node, config.clone()).unwrap(),
+        "(parent: (None), depth: 0)"
+    );
+    assert_eq!(
+        to_string_pretty(&node, config.unwrap_newtypes(true)).unwrap(),
+        "#![enable(unwrap_newtypes)]\n(depth: 0)"
+    );
+}
+
+#[test]
+fn ser_enable_extensions() {
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Meters(f32);
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Path {
+        points: Vec<(Meters, Meters)>,
+        width: Option<Meters>,
+    }
+
+    let path = Path {
+        points: vec![(Meters(0.5), Meters(1.0))],
+        width: Some(Meters(2.0)),
+    };
+    let config = PrettyConfig::new().depth_limit(0);
+    let ser = |config: PrettyConfig| to_string_pretty(&path, config).unwrap();
+
+    assert_eq!(
+        ser(config.clone()),
+        "(points: [((0.5), (1.0))], width: Some((2.0)))"
+    );
+    let unwrapped = ser(config.clone().unwrap_newtypes(true));
+    assert_eq!(
+        unwrapped,
+        "#![enable(unwrap_newtypes)]\n(points: [(0.5, 1.0)], width: Some(2.0))"
+    );
+    assert_eq!(from_str::<Path>(&unwrapped).as_ref(), Ok(&path));
+    let both = ser(config
+        .clone()
+        .unwrap_newtypes(true)
+        .option_style(OptionStyle::ImplicitSome));
+    assert_eq!(
+        both,
+        "#![enable(implicit_some, unwrap_newtypes)]\n(points: [(0.5, 1.0)], width: 2.0)"
+    );
+    assert_eq!(from_str::<Path>(&both).as_ref(), Ok(&path));
+
+    // Left to the document the value is written into
+    let ron = ser(config.unwrap_newtypes(true).enable_extensions(false));
+    assert_eq!(ron, "(points: [(0.5, 1.0)], width: Some(2.0))");
+    assert!(from_str::<Path>(&ron).is_err());
+}
+
+#[test]
+fn ser_floats() {
+    assert_eq!(to_string(&(0.1 + 0.2)).unwrap(), "0.30000000000000004");
+    assert_eq!(to_string(&1.0).unwrap(), "1.0");
+    assert_eq!(to_string(&1e20).unwrap(), "1.0e20");
+    assert_eq!(to_string(&-1.5e-7).unwrap(), "-1.5e-7");
+    assert_eq!(to_string(&0.1f32).unwrap(), "0.1");
+
+    let config = PrettyConfig::new().float_precision(Some(3));
+    assert_eq!(to_string_pretty(&(2.0f32 / 3.0), config.clone()).unwrap(), "0.667");
+    assert_eq!(
+        to_string_pretty(&5.0, config.float_precision(Some(0))).unwrap(),
+        "5.0"
+    );
+}
+
+#[test]
+fn ser_floats_roundtrip() {
+    let floats = [
+        0.1 + 0.2,
+        1.0 / 3.0,
+        -2.0 / 3.0,
+        std::f64::consts::PI,
+        1e20,
+        1.7976931348623157e308,
+        5e-324,
+        2.2250738585072014e-308,
+        123456.789e-10,
+        0.0,
+    ];
+    for &float in &floats {
+        let ron = to_string(&float).unwrap();
+        assert_eq!(from_str::<f64>(&ron), Ok(float), "{}", ron);
+    }
+
+    for &float in &[0.1f32, 1.0 / 3.0, 3.4028235e38, 1e-45, 16777217.0] {
+        let ron = to_string(&float).unwrap();
+        assert_eq!(from_str::<f32>(&ron), Ok(float), "{}", ron);
+    }
+}
+
+#[test]
+fn ser_floats_precision_roundtrip() {
+    let floats = [
+        (3, 1e20, "1.000e20"),
+        (3, -1.5e300, "-1.500e300"),
+        (0, 1.8e19, "18000000000000000000.0"),
+        (2, 1.9e19, "1.90e19"),
+        (3, 1e-30, "0.000"),
+        (3, -2.5e-300, "-0.000"),
+        (30, 1.0 / 3.0, "0.3333333333333333148"),
+        (30, 1e30, "1.0000000000000000199e30"),
+    ];
+    for &(precision, float, written) in &floats {
+        let config = PrettyConfig::new().float_precision(Some(precision));
+        let ron = to_string_pretty(&float, config).unwrap();
+        assert_eq!(ron, written);
+        assert_eq!(from_str::<f64>(&ron), Ok(written.parse().unwrap()), "{}", ron);
+    }
+
+    let config = PrettyConfig::new().float_precision(Some(2));
+    assert_eq!(to_string_pretty(&3.4028235e38f32, config).unwrap(), "3.40e38");
+}
+
+#[test]
+fn ser_to_writer() {
+    let mut output = vec![];
+    to_writer_pretty(&mut output, &window(), PrettyConfig::new()).unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        to_string_pretty(&window(), PrettyConfig::new()).unwrap()
+    );
+
+    // Write errors are passed on
+    let mut output = [0u8; 8];
+    let err = to_writer_pretty(&mut output[..], &window(), PrettyConfig::new()).unwrap_err();
+    assert!(matches!(err.kind, IoError(_)));
+    assert_eq!(&output, b"(\n    ti");
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Point {
+    x: f32,
+    y: f32,
+}
+
+#[test]
+fn strict_lenient_by_default() {
+    assert_eq!(
+        from_str("(x: 16777217, y: 0.5)"),
+        Ok(Point {
+            x: 16777216.0,
+            y: 0.5
+        })
+    );
+    assert_eq!(from_str("(1, 2, 3)"), Ok((1, 2)));
+    assert_eq!(
+        from_str::<HashMap<i32, bool>>("{1: true, 1: false}"),
+        Ok(HashMap::from_iter([(1, false)]))
+    );
+}
+
+#[test]
+fn strict() {
+    fn strict<'a, T: Deserialize<'a>>(s: &'a str) -> Result<T, String> {
+        from_str_with_options(s, Options::strict()).map_err(|e| e.kind.to_string())
+    }
+
+    assert_eq!(
+        strict("Point(x: 1.5, y: -16777216)"),
+        Ok(Point {
+            x: 1.5,
+            y: -16777216.0
+        })
+    );
+    assert_eq!(
+        strict::<Point>("(x: 1.5, y: 2.0)"),
+        Err("missing struct name, expected `Point`".to_owned())
+    );
+    assert_eq!(
+        strict::<Point>("Point(x: 16777217, y: 0.5)"),
+        Err(
+            "number can't be represented exactly as `f32`, the closest value is `16777216`"
+                .to_owned()
+        )
+    );
+    assert_eq!(
+        strict::<Point>("Point(x: 0.10000000001, y: 0.5)"),
+        Err(
+            "number can't be represented exactly as `f32`, the closest value is `0.1`"
+                .to_owned()
+        )
+    );
+    assert_eq!(strict("0.10000000001"), Ok(0.10000000001f64));
+    assert_eq!(
+        strict::<Point>("Point(x: 1.0, x: 2.0, y: 0.5)"),
+        Err("duplicate key".to_owned())
+    );
+    assert_eq!(
+        strict::<HashMap<i32, bool>>("{1: true, 1: false}"),
+        Err("duplicate key".to_owned())
+    );
+    assert_eq!(
+        strict::<(i32, i32)>("(1, 2, 3)"),
+        Err("1 trailing element(s)".to_owned())
+    );
+
+    let err = from_str_with_options::<Point>("Point(x: 1.0, y: 0.5, x: 2.0)", Options::strict())
+        .unwrap_err();
+    assert_eq!(err.start().map(|l| l.column), Some(23));
+    assert_eq!(err.labels()[0].start.column, 7);
+    assert_eq!(err.labels()[0].message, "first used here");
+}
+
+#[test]
+fn misspelled_keywords() {
+    #[derive(Debug, Deserialize)]
+    struct Config {
+        #[allow(dead_code)]
+        a: Option<u32>,
+        #[allow(dead_code)]
+        b: bool,
+    }
+    let label = |input: &str| {
+        let err = from_str::<Config>(input).unwrap_err();
+        let label = &err.labels()[0];
+        (label.message.clone(), label.start.column, label.end.column)
+    };
+
+    assert_eq!(
+        label("(a: Nonee, b: true)"),
+        ("did you mean `None`?".to_owned(), 5, 10)
+    );
+    assert_eq!(
+        label("(a: Smoe(1), b: true)"),
+        ("did you mean `Some`?".to_owned(), 5, 9)
+    );
+    assert_eq!(
+        label("(a: None, b: False)"),
+        ("did you mean `false`?".to_owned(), 14, 19)
+    );
+
+    // Errors inside a value aren't about its name
+    let err = from_str::<HashMap<String, bool>>("Nome(a: 1)").unwrap_err();
+    assert_eq!(err.start().map(|l| l.column), Some(9));
+    assert!(err.labels().is_empty());
+}
+
+#[test]
+fn numbers_as_str() {
+    let options = Options::new().numbers_as_str(true);
+
+    assert_eq!(
+        from_str_with_options::<Vec<String>>(
+            "[1.50, 0x2A, -7, 18446744073709551615, .5e-3, \"a\"]",
+            options.clone()
+        ),
+        Ok(vec![
+            "1.50".to_owned(),
+            "42".to_owned(),
+            "-7".to_owned(),
+            "18446744073709551615".to_owned(),
+            ".5e-3".to_owned(),
+            "a".to_owned()
+        ])
+    );
+    assert_eq!(
+        from_str_with_options::<Point>("Point(x: 1.5, y: 2)", options.clone()),
+        Ok(Point { x: 1.5, y: 2.0 })
+    );
+    assert!(from_str::<Vec<String>>("[1.50]").is_err());
+
+    // Numbers too large for a `u64` or an `f64`, e.g. for big integers
+    assert_eq!(
+        from_str_with_options::<Vec<String>>(
+            "[123456789012345678901234567890, -99999999999999999999, +7, 1.12345678901234567890123, 123456789012345678901.5e-2]",
+            options.clone()
+        ),
+        Ok(vec![
+            "123456789012345678901234567890".to_owned(),
+            "-99999999999999999999".to_owned(),
+            "7".to_owned(),
+            "1.12345678901234567890123".to_owned(),
+            "123456789012345678901.5e-2".to_owned(),
+        ])
+    );
+    assert_eq!(
+        from_str_with_options::<(i128, f64)>(
+            "(-99999999999999999999, 123456789012345678901.5e-2)",
+            options.clone()
+        ),
+        Ok((-99999999999999999999, 1234567890123456789.015))
+    );
+    assert!(from_str_with_options::<u64>("99999999999999999999", options.clone()).is_err());
+    assert!(from_str::<Vec<String>>("[99999999999999999999]").is_err());
+
+    // Values an interceptor replaced are written like `Decimal`s unless they
+    // were parsed, the others as they are
+    let mut replace = |_: &[PathSegment], expr: &mut Expr| {
+        if let Expr::Decimal(d) = expr {
+            *expr = match d.text {
+                Some("1.50") => Expr::Decimal(Box::new(Decimal::new(None, Some(2), 25, 2, None))),
+                _ => crate::utf8_parser::ast_from_str("2.250").unwrap().expr.value,
+            };
+        }
+        Ok(())
+    };
+    assert_eq!(
+        from_str_with_interceptor::<Vec<String>>("[1.50, 2.25]", options, &mut replace),
+        Ok(vec!["2.25e0".to_owned(), "2.250".to_owned()])
+    );
+}
+
+#[test]
+fn deny_non_finite() {
+    if cfg!(feature = "integers_only") {
+        return;
+    }
+    let strict = Options::new().deny_non_finite(true);
+
+    let e = from_str_with_options::<f64>("1.0e400", strict.clone()).unwrap_err();
+    assert_eq!(e.kind, NonFiniteFloat);
+    assert_eq!(
+        from_str_with_options::<f64>("1.0e400", Options::new()),
+        Ok(f64::INFINITY)
+    );
+
+    // In range for `f64`, but not for `f32`
+    let e = from_str_with_options::<Point>("Point(x: 1.0e39, y: 0.0)", strict.clone()).unwrap_err();
+    assert_eq!(
+        e.kind,
+        Deserialize("number is out of range for `f32`, only finite floats are allowed".to_owned())
+    );
+    assert_eq!(
+        (e.start().unwrap().column, e.end().unwrap().column),
+        (10, 16)
+    );
+    assert!(from_str_with_options::<f64>("1.0e39", strict).is_ok());
+
+    assert!(Options::strict().deny_non_finite);
+    assert!(to_string(&f64::NAN).is_err());
+}
+
+#[test]
+fn deny_floats() {
+    let integers_only = Options::new().deny_floats(true);
+
+    // Float fields still take integers
+    assert_eq!(
+        from_str_with_options("Point(x: 1, y: -2)", integers_only.clone()),
+        Ok(Point { x: 1.0, y: -2.0 })
+    );
+    let e = from_str_with_options::<Point>("Point(x: 1, y: -2.5)", integers_only).unwrap_err();
+    assert_eq!(e.kind, FloatNotAllowed);
+    assert_eq!(e.class(), crate::ErrorClass::Parse);
+    assert!(!Options::strict().deny_floats);
+    assert_eq!(to_string(&1.5).is_err(), cfg!(feature = "integers_only"));
+}
+
+#[test]
+fn field_matching() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Window {
+        width: u32,
+        #[serde(default)]
+        color: u8,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    enum Shape {
+        Rect { width: u32 },
+    }
+
+    let source = "Window(Width: 800, colour: 3)";
+    assert!(from_str::<Window>(source).is_err());
+
+    let options = Options::new()
+        .case_insensitive_fields(true)
+        .field_aliases([("colour", "color"), ("size", "width")]);
+    let expected = Window {
+        width: 800,
+        color: 3,
+    };
+    assert_eq!(from_str_with_options(source, options.clone()), Ok(expected));
+    assert_eq!(
+        from_str_with_options("Rect(WIDTH: 4)", options.clone()),
+        Ok(Shape::Rect { width: 4 })
+    );
+
+    // An old name next to the new one is a duplicate
+    let e = from_str_with_options::<Window>("(width: 1, size: 2)", options.clone()).unwrap_err();
+    assert!(e.to_string().contains("duplicate field `width`"), "{}", e);
+    // Aliases whose new name isn't a field of the struct are ignored
+    assert_eq!(
+        from_str_with_options("Rect(size: 1, colour: 2)", options.clone()),
+        Ok(Shape::Rect { width: 1 })
+    );
+    assert_eq!(
+        from_str_with_options("(width: 1, Color: 2)", options.case_insensitive_fields(false)),
+        Ok(Window {
+            width: 1,
+            color: 0
+        })
+    );
+
+    // Aliases read at runtime, e.g. from a config, don't have to outlive
+    // the options
+    let options = {
+        let aliases = "colour=color".to_owned();
+        let (old, new) = aliases.split_once('=').unwrap();
+
+        Options::new().field_aliases([(old, new)])
+    };
+    assert_eq!(
+        from_str_with_options("(width: 1, colour: 2)", options),
+        Ok(Window { width: 1, color: 2 })
+    );
+}
+
+#[test]
+fn key_order() {
+    use serde::de::{IgnoredAny, MapAccess, Visitor};
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(untagged)]
+    enum Key {
+        Bool(bool),
+        Int(i64),
+        Str(std::string::String),
+    }
+
+    /// The keys of a map or struct in the order they are visited
+    struct Keys(Vec<Key>);
+
+    impl<'de> Deserialize<'de> for Keys {
+        fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            struct KeysVisitor;
+
+            impl<'de> Visitor<'de> for KeysVisitor {
+                type Value = Keys;
+
+                fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                    f.write_str("a map")
+                }
+
+                fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Keys, A::Error> {
+                    let mut keys = vec![];
+                    while let Some((key, IgnoredAny)) = map.next_entry()? {
+                        keys.push(key);
+                        assert_eq!(map.size_hint(), Some(3 - keys.len()));
+                    }
+
+                    Ok(Keys(keys))
+                }
+            }
+
+            deserializer.deserialize_map(KeysVisitor)
+        }
+    }
+
+    let keys = |s: &str, sort_keys: bool| {
+        from_str_with_options::<Keys>(s, Options::new().sort_keys(sort_keys))
+            .unwrap()
+            .0
+    };
+    let str = |s: &str| Key::Str(s.to_owned());
+
+    // The order they are written in by default
+    assert_eq!(
+        keys("(c: 1, a: 2, b: 3)", false),
+        [str("c"), str("a"), str("b")]
+    );
+    assert_eq!(
+        keys(r#"{"b": 1, 10: 2, -2: 3}"#, false),
+        [str("b"), Key::Int(10), Key::Int(-2)]
+    );
+
+    assert_eq!(
+        keys("(c: 1, a: 2, b: 3)", true),
+        [str("a"), str("b"), str("c")]
+    );
+    assert_eq!(
+        keys(r#"{"b": 1, 10: 2, -2: 3}"#, true),
+        [Key::Int(-2), Key::Int(10), str("b")]
+    );
+    assert_eq!(
+        keys(r#"{"b": 1, "a": 2, true: 3}"#, true),
+        [Key::Bool(true), str("a"), str("b")]
+    );
+}
+
+#[test]
+fn lazy_escapes() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Config<'a> {
+        name: &'a str,
+        escaped: String,
+    }
+
+    let options = Options::new().lazy_escapes(true);
+    let source = r#"(name: "plain", escaped: "a\tb", ignored: "\u{1F980}\n")"#;
+    assert_eq!(
+        from_str_with_options(source, options.clone()),
+        Ok(Config {
+            name: "plain",
+            escaped: "a\tb".to_owned(),
+        })
+    );
+    assert_eq!(
+        from_str_with_options::<HashMap<String, u8>>(r#"{"\"a\"": 1}"#, options),
+        Ok(HashMap::from_iter(vec![("\"a\"".to_owned(), 1)]))
+    );
+}
+
+#[test]
+fn borrowed_cows() {
+    #[derive(Debug, Deserialize)]
+    struct Labels<'a> {
+        #[serde(borrow)]
+        plain: Cow<'a, str>,
+        #[serde(borrow)]
+        escaped: Cow<'a, str>,
+        #[serde(borrow)]
+        raw: Cow<'a, str>,
+        #[serde(borrow)]
+        bytes: Cow<'a, [u8]>,
+        #[serde(borrow)]
+        nested: Vec<Cow<'a, str>>,
+    }
+
+    // The result can't outlive the source it borrows from
+    fn labels<'a>(source: &'a str, options: Options) -> Labels<'a> {
+        from_str_with_options(source, options).unwrap()
+    }
+
+    let source = r##"(
+    plain: "plain",
+    escaped: "tab\tbed",
+    raw: r#"C:\no\escapes"#,
+    bytes: "bytes",
+    nested: ["a"],
+)"##;
+    for options in [Options::default(), Options::new().lazy_escapes(true)] {
+        let labels = labels(source, options);
+        assert!(matches!(labels.plain, Cow::Borrowed("plain")));
+        assert!(matches!(labels.escaped, Cow::Owned(ref s) if s == "tab\tbed"));
+        assert!(matches!(labels.raw, Cow::Borrowed("C:\\no\\escapes")));
+        assert!(matches!(labels.bytes, Cow::Borrowed(b"bytes")));
+        // serde's `Cow` impl always owns, `#[serde(borrow)]` only applies to
+        // fields that are a `Cow` themselves
+        assert!(matches!(labels.nested[0], Cow::Owned(_)));
+    }
+
+    // `&str` can only borrow, so escapes are an error pointing at the string
+    #[derive(Debug, Deserialize)]
+    struct Label<'a> {
+        #[allow(dead_code)]
+        text: &'a str,
+    }
+    let e = from_str::<Label>("(text: \"a\\nb\")").unwrap_err();
+    assert_eq!(
+        e.kind,
+        crate::ErrorKind::Deserialize(
+            r#"invalid type: string "a\nb", expected a borrowed string"#.to_owned()
+        )
+    );
+    assert_eq!(e.start(), Some(Location { line: 1, column: 8 }));
+}
+
+#[cfg(feature = "unicode_ident")]
+#[test]
+fn unicode_idents() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Config {
+        #[serde(rename = "größe")]
+        size: u8,
+    }
+
+    let source = "(größe: 3)";
+    assert!(from_str::<Config>(source).is_err());
+    assert!(from_str_with_options::<Config>(source, Options::strict()).is_err());
+    assert_eq!(
+        from_str_with_options(source, Options::new().unicode_idents(true)),
+        Ok(Config { size: 3 })
+    );
+}
+
+#[test]
+fn keyword_names() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct None;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Some(u8);
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Point {
+        x: u8,
+    }
+
+    let message = |s: &str| from_str::<Option<Point>>(s).unwrap_err().kind.to_string();
+
+    assert_eq!(
+        message("Some(x: 1)"),
+        "`Some` takes exactly one value, e.g. `Some(5)` or `Some((x: 1))`"
+    );
+    assert_eq!(message("None(x: 1)"), "`None` takes no value");
+    assert_eq!(
+        from_str::<Option<Point>>("Some((x: 1))"),
+        Ok(Option::Some(Point { x: 1 }))
+    );
+
+    assert_eq!(from_str("None"), Ok(None));
+    assert_eq!(from_str("Some(3)"), Ok(Some(3)));
+}
+
+#[test]
+fn enum_variants_named_some_and_none() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    enum Lookup {
+        Some(String),
+        None,
+        Many(Vec<String>),
+    }
+
+    assert_eq!(
+        from_str(r#"Some("a")"#),
+        Ok(Lookup::Some("a".to_owned()))
+    );
+    assert_eq!(from_str("None"), Ok(Lookup::None));
+    assert_eq!(
+        from_str(r#"[Some("a"), None, Many(["b"])]"#),
+        Ok(vec![
+            Lookup::Some("a".to_owned()),
+            Lookup::None,
+            Lookup::Many(vec!["b".to_owned()])
+        ])
+    );
+
+    // Still options where an option is expected
+    assert_eq!(
+        from_str(r#"[Some(Some("a")), Some(None), None]"#),
+        Ok(vec![
+            Option::Some(Lookup::Some("a".to_owned())),
+            Option::Some(Lookup::None),
+            Option::None
+        ])
+    );
+}
+
+#[test]
+fn timed() {
+    let (value, timings) = from_str_timed::<Vec<u8>>("[1, 2, 3]", Options::default());
+    assert_eq!(value, Ok(vec![1, 2, 3]));
+    assert_eq!(
+        timings.total(),
+        timings.parse + timings.convert + timings.deserialize
+    );
+
+    let (value, timings) = from_str_timed::<Vec<u8>>("[1, 2", Options::default());
+    assert!(value.is_err());
+    assert_eq!(timings.convert + timings.deserialize, Default::default());
+}
+
+#[test]
+fn complex_map_keys() {
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+    struct Pair(i32, i32);
+
+    #[derive(Debug, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+    struct Unit;
+
+    #[derive(Debug, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+    enum Key {
+        A,
+        B(i32),
+        C(i32, i32),
+        D { x: i32 },
+    }
+
+    fn map<K: Ord, V>(entries: impl IntoIterator<Item = (K, V)>) -> BTreeMap<K, V> {
+        entries.into_iter().collect()
+    }
+
+    assert_eq!(
+        from_str("{(1, 2): 3, (0, 1): 4}"),
+        Ok(map(vec![((1, 2), 3), ((0, 1), 4)]))
+    );
+    assert_eq!(
+        from_str("{(x: 1, y: 2): 3, Point(x: 0, y: 1): 4}"),
+        Ok(map(vec![
+            (Point { x: 1, y: 2 }, 3),
+            (Point { x: 0, y: 1 }, 4)
+        ]))
+    );
+    assert_eq!(
+        from_str("{Pair(1, 2): 3, (0, 1): 4}"),
+        Ok(map(vec![(Pair(1, 2), 3), (Pair(0, 1), 4)]))
+    );
+    assert_eq!(from_str("{Unit: 3}"), Ok(map(vec![(Unit, 3)])));
+    assert_eq!(from_str("{(): 3}"), Ok(map(vec![(Unit, 3)])));
+    assert_eq!(
+        from_str("{A: 1, B(2): 3, C(1, 2): 4, D(x: 5): 6}"),
+        Ok(map(vec![
+            (Key::A, 1),
+            (Key::B(2), 3),
+            (Key::C(1, 2), 4),
+            (Key::D { x: 5 }, 6)
+        ]))
+    );
+    assert_eq!(
+        from_str(r#"{("a", B(1)): 3}"#),
+        Ok(map(vec![(("a".to_owned(), Key::B(1)), 3)]))
+    );
+    assert_eq!(
+        from_str("{Some((x: 1, y: 2)): 3, None: 4}"),
+        Ok(map(vec![(Some(Point { x: 1, y: 2 }), 3), (None, 4)]))
+    );
+    assert_eq!(
+        from_str(r#"{"a": {[1, 2]: {(x: 1, y: 2): 3}}}"#),
+        Ok(map(vec![(
+            "a".to_owned(),
+            map(vec![(vec![1u8, 2], map(vec![(Point { x: 1, y: 2 }, 3)]))])
+        )]))
+    );
+
+    // The name is checked like that of structs
+    assert!(from_str::<BTreeMap<Unit, u8>>("{Other: 3}")
+        .unwrap_err()
+        .to_string()
+        .contains("invalid unit struct type: `Other`, expected `Unit`"));
+    assert!(from_str::<Unit>("Unit").is_ok());
+}
+
+#[test]
+fn interceptor() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Server {
+        timeout: u64,
+        password: String,
+        ports: Vec<u16>,
+    }
+
+    fn convert(path: &[PathSegment], expr: &mut Expr) -> Result<(), Error> {
+        match (path, &*expr) {
+            // Durations like `"10s"` as seconds
+            ([PathSegment::Field("timeout")], Expr::Str(s)) => {
+                let secs = s.strip_suffix('s').and_then(|s| s.parse().ok()).ok_or(Error {
+                    kind: Custom("invalid duration".to_owned()),
+                    context: None,
+                })?;
+                *expr = UnsignedInteger::new(secs).to_expr();
+            }
+            ([PathSegment::Field("password")], Expr::Str(s)) => {
+                *expr = Expr::String(s.chars().rev().collect());
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    let mut paths = vec![];
+    let mut interceptor = |path: &[PathSegment], expr: &mut Expr| {
+        paths.push(
+            path.iter()
+                .map(|segment| match segment {
+                    PathSegment::Field(field) => field.to_string(),
+                    PathSegment::Index(i) => i.to_string(),
+                    PathSegment::Key(_) => "key".to_owned(),
+                })
+                .collect::<Vec<_>>()
+                .join("."),
+        );
+
+        convert(path, expr)
+    };
+
+    assert_eq!(
+        from_str_with_interceptor(
+            r#"(timeout: "10s", password: "terces", ports: [80, 443])"#,
+            Options::default(),
+            &mut interceptor
+        ),
+        Ok(Server {
+            timeout: 10,
+            password: "secret".to_owned(),
+            ports: vec![80, 443]
+        })
+    );
+    assert_eq!(
+        paths,
+        ["", "timeout", "password", "ports", "ports.0", "ports.1"]
+    );
+
+    let e = from_str_with_interceptor::<Server>(
+        r#"(timeout: "10m", password: "", ports: [])"#,
+        Options::default(),
+        &mut convert,
+    )
+    .unwrap_err();
+    assert_eq!(e.kind, Custom("invalid duration".to_owned()));
+    assert_eq!(
+        e.context.unwrap().start_end.map(|(start, end)| (start.column, end.column)),
+        Some((11, 16))
+    );
+}
+
+#[test]
+fn encrypted() {
+    use crate::utf8_parser::serde::encrypted::DecryptInterceptor;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Db {
+        user: String,
+        password: String,
+    }
+
+    // XOR with the key, standing in for real encryption
+    let mut decrypt = DecryptInterceptor::new(|_: &[PathSegment], ciphertext: &[u8]| {
+        String::from_utf8(ciphertext.iter().map(|b| b ^ 0x2a).collect()).map_err(|_| Error {
+            kind: Custom("wrong key".to_owned()),
+            context: None,
+        })
+    });
+
+    // "secret" XOR 0x2a
+    assert_eq!(
+        from_str_with_interceptor(
+            r#"(user: "admin", password: Encrypted("WU9JWE9e"))"#,
+            Options::default(),
+            &mut decrypt
+        ),
+        Ok(Db {
+            user: "admin".to_owned(),
+            password: "secret".to_owned()
+        })
+    );
+
+    let e = from_str_with_interceptor::<Db>(
+        r#"(user: "admin", password: Encrypted("WU9J*"))"#,
+        Options::default(),
+        &mut decrypt,
+    )
+    .unwrap_err();
+    assert_eq!(
+        e.kind,
+        Custom("the encrypted value isn't valid base64".to_owned())
+    );
+    assert_eq!(e.start().map(|start| start.column), Some(27));
+}
+
+#[test]
+fn versioned() {
+    use crate::{
+        ast::{Ident, Ron},
+        migrate::Migrations,
+        utf8_parser::serde::from_str_versioned,
+    };
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Size {
+        width: u32,
+        height: u32,
+    }
+
+    /// `size: 2` became `width: 2, height: 2`
+    fn migrate_v1_to_v2(ron: &mut Ron) {
+        if let Expr::Struct(s) = &mut ron.expr.value {
+            let mut height = s.fields[0].clone();
+            s.fields[0].value.key.value = Ident::from_str("width");
+            height.value.key.value = Ident::from_str("height");
+            s.fields.push(height);
+        }
+    }
+
+    let migrations = Migrations::new().with(1, migrate_v1_to_v2);
+    let size = Size {
+        width: 2,
+        height: 2,
+    };
+    assert_eq!(from_str_versioned("(size: 2)", &migrations), Ok(size));
+    assert_eq!(
+        from_str_versioned("#![version(2)] (width: 3, height: 4)", &migrations),
+        Ok(Size {
+            width: 3,
+            height: 4
+        })
+    );
+
+    let e = from_str_versioned::<Size>("#![version(3)] (width: 3, height: 4)", &migrations)
+        .unwrap_err();
+    assert_eq!(
+        e.kind,
+        UnsupportedVersion {
+            version: 3,
+            latest: 2
+        }
+    );
+    assert_eq!(e.start().map(|start| start.column), Some(12));
+}
+
+#[test]
+fn interceptor_value_paths() {
+    use crate::path::ValuePath;
+
+    let mut paths = vec![];
+    let mut interceptor = |path: &[PathSegment], _: &mut Expr| {
+        let steps: Option<Vec<_>> = path.iter().map(|segment| segment.to_step()).collect();
+        paths.push(steps.map(|steps| ValuePath::from(steps).to_string()));
+
+        Ok(())
+    };
+
+    from_str_with_interceptor::<HashMap<String, (u8, Option<u8>)>>(
+        r#"{"a.b": (1, Some(2))}"#,
+        Options::default(),
+        &mut interceptor,
+    )
+    .unwrap();
+    assert_eq!(
+        paths.into_iter().flatten().collect::<Vec<_>>(),
+        ["", "\"a.b\"", "\"a.b\".0", "\"a.b\".1", "\"a.b\".1"]
+    );
+}
+
+#[test]
+fn color_literals() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Rgba(u8, u8, u8, u8);
+
+    assert_eq!(
+        from_str("#![enable(color_literals)] (#ff8000, #0000ff80)"),
+        Ok((Rgba(255, 128, 0, 255), [0u8, 0, 255, 128]))
+    );
+    assert!(from_str_with_options::<(u8, u8, u8)>(
+        "#![enable(color_literals)] #ff8000",
+        Options::new().deny_trailing_content(true)
+    )
+    .is_err());
+}
+
+#[cfg(feature = "unit_literals")]
+#[test]
+fn unit_literals() {
+    use std::time::Duration;
+
+    use crate::utf8_parser::serde::units;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Limits {
+        #[serde(deserialize_with = "units::duration")]
+        timeout: Duration,
+        #[serde(deserialize_with = "units::duration")]
+        retry: Duration,
+        #[serde(deserialize_with = "units::bytes")]
+        max_body: u64,
+        name: String,
+    }
+
+    assert_eq!(
+        from_str(
+            r#"#![enable(unit_literals)]
+            (timeout: 1.5s, retry: "250ms", max_body: 64KiB, name: 10MB)"#
+        ),
+        Ok(Limits {
+            timeout: Duration::from_millis(1500),
+            retry: Duration::from_millis(250),
+            max_body: 65536,
+            name: "10MB".to_owned(),
+        })
+    );
+    assert_eq!(
+        from_str::<Limits>("(timeout: 30, retry: 0, max_body: 4096, name: \"\")")
+            .map(|limits| (limits.timeout, limits.max_body)),
+        Ok((Duration::from_secs(30), 4096))
+    );
+
+    let e = from_str::<Limits>(
+        "#![enable(unit_literals)] (timeout: 1s, retry: 1KB, max_body: 1, name: \"\")",
+    )
+    .unwrap_err();
+    assert_eq!(
+        e.kind,
+        crate::ErrorKind::Deserialize(
+            "invalid value: string \"1KB\", expected a duration like `250ms`".to_owned()
+        )
+    );
+    let e = from_str::<Limits>(
+        "#![enable(unit_literals)] (timeout: 1s, retry: 1s, max_body: 0.5B, name: \"\")",
+    )
+    .unwrap_err();
+    assert_eq!(
+        e.kind,
+        crate::ErrorKind::Deserialize(
+            "size `0.5B` is too large or not a whole number of bytes".to_owned()
+        )
+    );
+}
+
+#[test]
+fn registered_extension() {
+    use crate::utf8_parser::extension::{DeserializeFlags, ExtensionRegistry, RonExtension};
+
+    /// Turns on both deserializer flags
+    struct Lenient;
+
+    impl RonExtension for Lenient {
+        fn name(&self) -> &str {
+            "lenient"
+        }
+
+        fn deserialize_flags(&self) -> DeserializeFlags {
+            DeserializeFlags {
+                implicit_some: true,
+                unwrap_newtypes: true,
+            }
+        }
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Meters(f32);
+
+    let registry = ExtensionRegistry::new().with(Lenient);
+    assert_eq!(
+        from_str_with_extensions(
+            "#![enable(lenient)] (5, 2.5)",
+            Options::default(),
+            &registry
+        ),
+        Ok((Some(5u32), Meters(2.5)))
+    );
+    assert!(from_str::<(Option<u32>, Meters)>("#![enable(lenient)] (5, 2.5)").is_err());
+}
+
+#[test]
+fn scoped() {
+    use std::thread;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    // One policy per tenant, used from several threads at the same time
+    let lenient = Options::default().scoped();
+    let strict = Options::strict().scoped();
+    let source = "(x: 1, y: 2)";
+    thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|| {
+                assert!(lenient.clone().from_str::<Point>(source).is_ok());
+                assert!(strict.from_str::<Point>(source).is_err());
+            });
+        }
+    });
+    assert_eq!(strict.options(), &Options::strict());
+
+    let scope = Options::default()
+        .scoped()
+        .with_extensions(crate::utf8_parser::extension::ExtensionRegistry::new());
+    let mut count = 0;
+    let mut interceptor = |_: &[PathSegment], _: &mut Expr| {
+        count += 1;
+        Ok(())
+    };
+    assert_eq!(
+        scope.from_str_with_interceptor("(x: 1, y: 2)", &mut interceptor),
+        Ok(Point { x: 1, y: 2 })
+    );
+    assert_eq!(count, 3);
+}
+
+#[test]
+fn metrics() {
+    use std::sync::{Arc, Mutex};
+
+    use crate::utf8_parser::serde::Metrics;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Config {
+        name: String,
+        sizes: Vec<f32>,
+    }
+
+    let source = r#"Config(name: "a", sizes: [1, 2.5], extra: (x: "b"))"#;
+    let mut reported = vec![];
+    let config =
+        from_str_with_metrics::<Config>(source, Options::default(), &mut |metrics: &Metrics| {
+            reported.push(*metrics)
+        });
+    assert!(config.is_ok());
+    assert_eq!(reported.len(), 1);
+    let metrics = reported[0];
+    assert_eq!(
+        (
+            metrics.bytes,
+            metrics.structs,
+            metrics.fields,
+            metrics.strings,
+            metrics.numbers
+        ),
+        (source.len(), 2, 4, 2, 2)
+    );
+    assert!(metrics.timings.total() >= metrics.timings.parse);
+
+    // Failed documents are reported too
+    let last = Arc::new(Mutex::new(None));
+    let scope = Options::default().scoped().with_metrics({
+        let last = last.clone();
+        move |metrics| *last.lock().unwrap() = Some(*metrics)
+    });
+    assert!(scope.from_str::<Config>("(name: ").is_err());
+    assert_eq!(last.lock().unwrap().map(|metrics| metrics.bytes), Some(7));
+    assert!(scope.from_str::<Config>(source).is_ok());
+    assert_eq!(last.lock().unwrap().map(|metrics| metrics.fields), Some(4));
+}
+
+#[test]
+fn bytes() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Asset<'a> {
+        name: &'a str,
+        size: u32,
+    }
+
+    let bytes = br#"(name: "tree", size: 3)"#;
+    assert_eq!(
+        from_bytes(bytes),
+        Ok(Asset {
+            name: "tree",
+            size: 3
+        })
+    );
+
+    let err = from_bytes::<Asset>(b"(name: \"tr\xffe\", size: 3)").unwrap_err();
+    assert_eq!(err.kind, InvalidUtf8);
+    assert_eq!(
+        err.start(),
+        Some(Location {
+            line: 1,
+            column: 11
+        })
+    );
+}
+
+#[cfg(feature = "async_reader")]
+#[test]
+fn async_reader() {
+    use futures_executor::block_on;
+
+    use crate::utf8_parser::serde::from_async_reader;
+
+    let reader = &b"(1, [2, 3])"[..];
+    assert_eq!(
+        block_on(from_async_reader(reader)),
+        Ok((1u8, vec![2u16, 3]))
+    );
+
+    let err = block_on(from_async_reader::<_, (u8, u8)>(&b"(1, \xff)"[..])).unwrap_err();
+    assert!(matches!(err.kind, IoError(_)));
+}
+
+#[test]
+fn column_modes() {
+    use crate::ColumnMode;
+
+    // `e\u{301}` is `é` written with a combining accent
+    let source = "[\"e\u{301}ä\", 1]";
+    let start = |mode| {
+        from_str_with_options::<(String, String)>(source, Options::new().column_mode(mode))
+            .unwrap_err()
+            .start()
+            .unwrap()
+            .column
+    };
+
+    assert_eq!(start(ColumnMode::Chars), 9);
+    assert_eq!(start(ColumnMode::Bytes), 11);
+    #[cfg(feature = "unicode_segmentation")]
+    assert_eq!(start(ColumnMode::Graphemes), 8);
+}
+
+#[test]
+fn files() {
+    use crate::utf8_parser::serde::to_file_pretty;
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Config {
+        name: String,
+        sizes: Vec<u8>,
+    }
+
+    let dir = std::env::temp_dir().join(format!("ron-reboot-files-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("config.ron");
+    let config = Config {
+        name: "a".to_owned(),
+        sizes: vec![1, 2],
+    };
+
+    crate::to_file(&path, &config).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "(name:\"a\",sizes:[1,2])"
+    );
+    assert_eq!(crate::from_file::<Config>(&path), Ok(config));
+
+    to_file_pretty(&path, &[1, 2], PrettyConfig::default()).unwrap();
+    let reader = std::fs::File::open(&path).unwrap();
+    assert_eq!(crate::from_reader::<_, Vec<u8>>(reader), Ok(vec![1, 2]));
+
+    let e = crate::to_file(dir.join("missing/config.ron"), &1).unwrap_err();
+    assert!(matches!(e.kind, IoError(_)));
+    let file_name = e.context.and_then(|context| context.file_name).unwrap();
+    assert!(file_name.ends_with("config.ron"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn all_errors() {
+    use crate::utf8_parser::serde::from_str_all_errors;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Window {
+        width: u32,
+        height: u32,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Config {
+        name: String,
+        window: Window,
+        sizes: Vec<u8>,
+        #[serde(default)]
+        fullscreen: bool,
+    }
+
+    let errors = |s| {
+        from_str_all_errors::<Config>(s, Options::default())
+            .unwrap_err()
+            .into_iter()
+            .map(|e| (e.start().map(|l| (l.line, l.column)), e.kind))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        errors("(name: 1, window: (width: \"a\", height: -1), sizes: [1, 256], fullscreen: 0)"),
+        vec![
+            (
+                Some((1, 8)),
+                Deserialize("invalid type: integer `1`, expected a string".to_owned())
+            ),
+            (
+                Some((1, 27)),
+                Deserialize("invalid type: string \"a\", expected u32".to_owned())
+            ),
+            (
+                Some((1, 40)),
+                Deserialize("invalid value: integer `-1`, expected u32".to_owned())
+            ),
+            (
+                Some((1, 56)),
+                Deserialize("invalid value: integer `256`, expected u8".to_owned())
+            ),
+            (
+                Some((1, 74)),
+                Deserialize("invalid type: integer `0`, expected a boolean".to_owned())
+            ),
+        ]
+    );
+    assert_eq!(
+        errors("(window: (width: 1), sizes: [])"),
+        vec![
+            (
+                Some((1, 10)),
+                Deserialize("missing field `height`".to_owned())
+            ),
+            (Some((1, 1)), Deserialize("missing field `name`".to_owned())),
+        ]
+    );
+    assert_eq!(errors("[]").len(), 1);
+    assert_eq!(
+        from_str_all_errors(
+            "(name: \"a\", window: (width: 1, height: 2), sizes: [3])",
+            Options::default()
+        ),
+        Ok(Config {
+            name: "a".to_owned(),
+            window: Window {
+                width: 1,
+                height: 2
+            },
+            sizes: vec![3],
+            fullscreen: false
+        })
+    );
+}
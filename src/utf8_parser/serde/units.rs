@@ -0,0 +1,76 @@
+//! Deserializers for unit literals, e.g. `timeout: 250ms` or `max_size: 64KiB`
+//!
+//! Use them with `#[serde(deserialize_with = "ron_reboot::utf8_parser::serde::units::duration")]`.
+//! Strings like `"250ms"` and integers (seconds or bytes) are accepted as well.
+
+use std::{fmt, time::Duration};
+
+use serde::{
+    de::{Error as SerdeErrorTrait, Unexpected, Visitor},
+    Deserializer,
+};
+
+use crate::{
+    ast::{Amount, Quantity, Unit, UnitKind, UnsignedInteger},
+    utf8_parser::{primitive::quantity, util::parse_all},
+};
+
+/// Deserializes a duration like `250ms`, `1.5h` or `30` (seconds)
+pub fn duration<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let quantity = deserializer.deserialize_any(QuantityVisitor(UnitKind::Duration))?;
+
+    quantity
+        .to_duration()
+        .ok_or_else(|| D::Error::custom(format!("duration `{}` is too long", quantity)))
+}
+
+/// Deserializes a size like `64KiB`, `1.5GB` or `4096` (bytes) as number of bytes
+pub fn bytes<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let quantity = deserializer.deserialize_any(QuantityVisitor(UnitKind::Size))?;
+
+    quantity.to_bytes().ok_or_else(|| {
+        D::Error::custom(format!(
+            "size `{}` is too large or not a whole number of bytes",
+            quantity
+        ))
+    })
+}
+
+/// Visits a quantity of the given kind
+struct QuantityVisitor(UnitKind);
+
+impl<'de> Visitor<'de> for QuantityVisitor {
+    type Value = Quantity<'static>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            UnitKind::Duration => write!(f, "a duration like `250ms`"),
+            UnitKind::Size => write!(f, "a size like `64KiB`"),
+        }
+    }
+
+    fn visit_u64<E: SerdeErrorTrait>(self, v: u64) -> Result<Self::Value, E> {
+        let unit = match self.0 {
+            UnitKind::Duration => Unit::Seconds,
+            UnitKind::Size => Unit::Bytes,
+        };
+
+        Ok(Quantity {
+            amount: Amount::Integer(UnsignedInteger::new(v)),
+            unit,
+        })
+    }
+
+    fn visit_str<E: SerdeErrorTrait>(self, v: &str) -> Result<Self::Value, E> {
+        match parse_all(quantity, v).map(|q| Quantity::from(q).into_owned()) {
+            Some(quantity) if quantity.unit.kind() == self.0 => Ok(quantity),
+            _ => Err(E::invalid_value(Unexpected::Str(v), &self)),
+        }
+    }
+}
@@ -0,0 +1,367 @@
+//! A syntax check that scans the document without building a parse tree,
+//! see [`validate_str`](super::validate_str)
+//!
+//! It only knows the common subset of RON and answers whether a document is
+//! valid for sure. Everything else, including all invalid documents, is left
+//! to the parser, which finds the errors.
+
+use crate::lex::{block_comment, is_ws, line_comment_len};
+
+/// Nesting depth up to which values are scanned, deeper documents are parsed
+const MAX_DEPTH: usize = 128;
+
+/// Decimal digits of a number part which surely fit into a `u64`
+const MAX_DIGITS: usize = 18;
+
+/// Whether `source` surely is a valid document, which contains no floats
+/// unless `floats`
+///
+/// `false` doesn't mean it's invalid: attributes, raw strings and
+/// identifiers, extension literals, radix integers and `\u{..}` escapes
+/// aren't scanned, for example.
+pub fn is_valid(source: &str, floats: bool) -> bool {
+    let mut scanner = Scanner {
+        bytes: source.as_bytes(),
+        pos: 0,
+        floats,
+    };
+
+    scanner.document().is_some()
+}
+
+/// `None` if the scanned value isn't known to be valid
+type Scan = Option<()>;
+
+struct Scanner<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    floats: bool,
+}
+
+impl Scanner<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn starts_with(&self, s: &[u8]) -> bool {
+        self.bytes[self.pos..].starts_with(s)
+    }
+
+    fn eat(&mut self, b: u8) -> bool {
+        let eaten = self.peek() == Some(b);
+        self.pos += eaten as usize;
+
+        eaten
+    }
+
+    fn expect(&mut self, b: u8) -> Scan {
+        self.eat(b).then_some(())
+    }
+
+    /// Skips bytes matching `f`, returning how many
+    fn skip_while(&mut self, f: impl Fn(u8) -> bool) -> usize {
+        let start = self.pos;
+        while self.peek().is_some_and(&f) {
+            self.pos += 1;
+        }
+
+        self.pos - start
+    }
+
+    fn document(&mut self) -> Scan {
+        self.ws()?;
+        // Attributes and `#` literals are left to the parser
+        if self.peek() == Some(b'#') {
+            return None;
+        }
+        self.value(0)?;
+        self.ws()?;
+
+        (self.pos == self.bytes.len()).then_some(())
+    }
+
+    /// Skips whitespace and comments
+    fn ws(&mut self) -> Scan {
+        loop {
+            self.skip_while(is_ws);
+            let rest = &self.bytes[self.pos..];
+            if rest.starts_with(b"//") {
+                self.pos += line_comment_len(rest);
+            } else if rest.starts_with(b"/*") {
+                let comment = block_comment(rest);
+                self.pos += comment.len;
+                comment.closed.then_some(())?;
+            } else {
+                return Some(());
+            }
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Scan {
+        if depth >= MAX_DEPTH {
+            return None;
+        }
+
+        match self.peek()? {
+            b'(' => {
+                self.pos += 1;
+                self.parenthesized(depth)
+            }
+            b'[' => self.list(depth),
+            b'{' => self.map(depth),
+            b'"' => self.string(),
+            b'0'..=b'9' | b'+' | b'-' | b'.' => self.number(),
+            b if is_ident_first(b) => self.tagged(depth),
+            _ => None,
+        }
+    }
+
+    /// `true`, `false`, a unit like `None`, or a named struct or tuple
+    fn tagged(&mut self, depth: usize) -> Scan {
+        let ident = self.ident()?;
+        match ident {
+            b"true" | b"false" => return Some(()),
+            // Values like `foo` are taken for a mistyped bool by the parser
+            [b't' | b'f', ..] => return None,
+            _ => {}
+        }
+
+        self.ws()?;
+        match self.eat(b'(') {
+            true => self.parenthesized(depth),
+            false => Some(()),
+        }
+    }
+
+    fn ident(&mut self) -> Option<&[u8]> {
+        let start = self.pos;
+        if !self.peek().is_some_and(is_ident_first) {
+            return None;
+        }
+        self.skip_while(is_ident_other);
+
+        Some(&self.bytes[start..self.pos])
+    }
+
+    /// The fields of a struct or elements of a tuple, after the `(`
+    fn parenthesized(&mut self, depth: usize) -> Scan {
+        // The parser rejects whitespace in `( )`
+        if self.eat(b')') {
+            return Some(());
+        }
+        self.ws()?;
+
+        let start = self.pos;
+        let is_struct = self.ident().is_some() && self.ws().is_some() && self.eat(b':');
+        self.pos = start;
+
+        match is_struct {
+            true => self.comma_list(b')', true, |scanner| {
+                // The parser reports keywords as field names
+                if let b"true" | b"false" = scanner.ident()? {
+                    return None;
+                }
+                scanner.ws()?;
+                scanner.expect(b':')?;
+                scanner.ws()?;
+                scanner.value(depth + 1)
+            }),
+            // The parser rejects whitespace in `(1, )`
+            false => self.comma_list(b')', false, |scanner| scanner.value(depth + 1)),
+        }
+    }
+
+    fn list(&mut self, depth: usize) -> Scan {
+        self.pos += 1;
+        self.ws()?;
+        if self.eat(b']') {
+            return Some(());
+        }
+
+        self.comma_list(b']', true, |scanner| scanner.value(depth + 1))
+    }
+
+    fn map(&mut self, depth: usize) -> Scan {
+        self.pos += 1;
+        self.ws()?;
+        if self.eat(b'}') {
+            return Some(());
+        }
+
+        self.comma_list(b'}', true, |scanner| {
+            scanner.value(depth + 1)?;
+            scanner.ws()?;
+            scanner.expect(b':')?;
+            scanner.ws()?;
+            scanner.value(depth + 1)
+        })
+    }
+
+    /// At least one `element`, separated by commas and ending with `end`,
+    /// which may follow a trailing comma (and whitespace, if `trailing_ws`)
+    fn comma_list(
+        &mut self,
+        end: u8,
+        trailing_ws: bool,
+        mut element: impl FnMut(&mut Self) -> Scan,
+    ) -> Scan {
+        loop {
+            element(self)?;
+            self.ws()?;
+            if !self.eat(b',') {
+                return self.expect(end);
+            }
+            if !trailing_ws && self.eat(end) {
+                return Some(());
+            }
+            self.ws()?;
+            if trailing_ws && self.eat(end) {
+                return Some(());
+            }
+        }
+    }
+
+    fn string(&mut self) -> Scan {
+        self.pos += 1;
+        loop {
+            match self.peek()? {
+                b'"' => {
+                    self.pos += 1;
+                    return Some(());
+                }
+                b'\\' => {
+                    self.pos += 1;
+                    match self.peek()? {
+                        b'n' | b'r' | b't' | b'b' | b'f' | b'\\' | b'/' | b'"' => self.pos += 1,
+                        _ => return None,
+                    }
+                }
+                // Multi-byte chars never contain `"` or `\`
+                _ => self.pos += 1,
+            }
+        }
+    }
+
+    /// An integer or decimal; what may follow it is checked by the caller
+    fn number(&mut self) -> Scan {
+        let signed = self.eat(b'+') || self.eat(b'-');
+        if !signed && self.peek() == Some(b'.') {
+            return self.fraction();
+        }
+
+        // Leading zeros are only allowed after a sign
+        let leading_zero =
+            self.starts_with(b"0") && self.bytes.get(self.pos + 1).is_some_and(u8::is_ascii_digit);
+        if leading_zero && !signed {
+            return None;
+        }
+        self.digits()?;
+
+        match self.peek() {
+            Some(b'.') => self.fraction(),
+            _ => Some(()),
+        }
+    }
+
+    /// The `.` and fractional part of a decimal, with its exponent
+    fn fraction(&mut self) -> Scan {
+        if !self.floats {
+            return None;
+        }
+        self.pos += 1;
+        self.digits()?;
+
+        if self.eat(b'e') || self.eat(b'E') {
+            let _ = self.eat(b'+') || self.eat(b'-');
+            self.digits()?;
+        }
+
+        Some(())
+    }
+
+    fn digits(&mut self) -> Scan {
+        let n = self.skip_while(|b| b.is_ascii_digit());
+
+        (1..=MAX_DIGITS).contains(&n).then_some(())
+    }
+}
+
+fn is_ident_first(b: u8) -> bool {
+    b.is_ascii_alphabetic() || b == b'_'
+}
+
+fn is_ident_other(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utf8_parser::ast_from_str;
+
+    const DOCUMENTS: &[&str] = &[
+        r#"Config(
+    name: "main", // the default
+    size: (1920, 1080),
+    scale: -1.5e3,
+    /* nested /* block */ comment */
+    tags: ["a", "b\n\"c\"",],
+    lookup: {"x": Some(.5), (1, 2): None, [] : { }},
+    mode: Fullscreen,
+    _: Point ( x: +01, y: 0 ),
+    unit: (),
+)"#,
+        "[true, false, Truthy, bar(), Baz(1,), Qux(a: 1,)]",
+        " 42 ",
+    ];
+
+    #[test]
+    fn valid_documents() {
+        for document in DOCUMENTS {
+            assert!(is_valid(document, true), "{}", document);
+            assert!(ast_from_str(document).is_ok());
+        }
+
+        assert!(!is_valid("1.5", false));
+        assert!(is_valid("15", false));
+    }
+
+    #[test]
+    fn left_to_parser() {
+        for document in [
+            "#![enable(implicit_some)] 1",
+            "r\"raw\"",
+            "(r#type: 1)",
+            "(a: 1, true: 2)",
+            "0x1f",
+            "\"\\u{1F600}\"",
+            "250ms",
+            "99999999999999999999",
+            &"[".repeat(MAX_DEPTH + 1),
+        ] {
+            assert!(!is_valid(document, true), "{}", document);
+        }
+    }
+
+    /// Mutations of valid documents are only accepted if the parser accepts
+    /// them too
+    #[test]
+    fn never_accepts_invalid() {
+        let check = |document: &str| {
+            if is_valid(document, true) {
+                assert!(ast_from_str(document).is_ok(), "accepted `{}`", document);
+            }
+        };
+
+        for document in DOCUMENTS {
+            for (i, c) in document.char_indices() {
+                let (before, after) = (&document[..i], &document[i + c.len_utf8()..]);
+                check(before);
+                check(&format!("{}{}", before, after));
+                for inserted in " ,:()[]{}/*\"\\#.e+-0ftr_".chars() {
+                    check(&format!("{}{}{}", before, inserted, &document[i..]));
+                }
+            }
+        }
+    }
+}
@@ -14,12 +14,9 @@ macro_rules! eval {
 #[cfg(test)]
 pub(crate) use eval;
 
+pub use crate::util::TestMockNew;
 use crate::{print_error, utf8_parser::ok::IOk};
 
-pub trait TestMockNew {
-    fn new_mocked() -> Self;
-}
-
 pub fn unwrap_pr1<T>(r: Result<IOk<T>, InputParseErr>) -> T {
     match r {
         Ok(ok) => ok.parsed,
@@ -22,13 +22,18 @@ fn trailing_commas() {
 #[test]
 fn missing_colon() {
     let input = "Transform(pos 5)";
-    assert_eq!(eval!(@result expr, input).unwrap().remaining.len(), 7);
+    let e = ast_from_str(input).unwrap_err();
+    assert_eq!(e.start().map(|l| (l.line, l.column)), Some((1, 15)));
+    assert_eq!(e.severity(), Some(crate::Severity::Fatal));
 }
 
 #[test]
 fn exprs_struct() {
     let input = "Pos(x:-3,y:4)";
-    assert_eq!(Expr::Tagged(eval!(tagged, input)), eval!(expr, input));
+    assert_eq!(
+        Expr::Tagged(Box::new(eval!(tagged, input))),
+        eval!(expr, input)
+    );
 }
 
 #[test]
@@ -81,7 +86,6 @@ fn lists() {
             UnsignedInteger::new(2).to_expr()
         ])
     );
-    // TODO: find out what lookahead is missing
     assert_eq!(
         eval!(list, "[1,]"),
         List::new_test(vec![UnsignedInteger::new(1).to_expr(),])
@@ -134,9 +138,9 @@ fn maps() {
     let basic_map = Map::new_test(vec![
         (
             Expr::Str("my map key :)"),
-            Expr::Tagged(basic_struct.clone()),
+            Expr::Tagged(Box::new(basic_struct.clone())),
         ),
-        (Expr::Tagged(basic_struct), Expr::Bool(false)),
+        (Expr::Tagged(Box::new(basic_struct)), Expr::Bool(false)),
     ]);
 
     assert_eq!(
@@ -201,3 +205,1055 @@ fn excl_mark() {
     expected one of an ascii letter or '_' at 3:23 (`!`)"#
     );
 }
+
+#[test]
+fn field_docs() {
+    let input = "Config(\n    /// Window width\n    /// in px\n    width: 1920,\n    // not a doc\n    height: 1080,\n    inner: (\n        /// Nested\n        a: {\n            /// Map key\n            \"b\": 1,\n        },\n    ),\n)";
+    let ast = ast_from_str(input).unwrap();
+    let fields = &ast.expr.value.as_struct().unwrap().fields;
+
+    assert_eq!(fields[0].value.doc, vec!["Window width", "in px"]);
+    assert_eq!(
+        fields[0].value.doc_string().as_deref(),
+        Some("Window width\nin px")
+    );
+    assert_eq!(fields[1].value.doc_string(), None);
+
+    let inner = match &fields[2].value.value.value {
+        ast::Expr::Struct(s) => &s.fields[0].value,
+        _ => unreachable!(),
+    };
+    assert_eq!(inner.doc, vec!["Nested"]);
+    match &inner.value.value {
+        ast::Expr::Map(m) => assert_eq!(m.entries[0].value.doc, vec!["Map key"]),
+        _ => unreachable!(),
+    }
+
+    let ast = ast_from_input(Input::new(input).with_doc_comments(false)).unwrap();
+    let fields = &ast.expr.value.as_struct().unwrap().fields;
+    assert!(fields[0].value.doc.is_empty());
+}
+
+#[test]
+fn collection_iterators() {
+    let ast = ast_from_str(r#"(a: [1, 2], b: {"k": (3, 4)}, c: ())"#).unwrap();
+    let s = ast.expr.value.as_struct().unwrap();
+    assert_eq!(s.len(), 3);
+    let keys: Vec<_> = s.fields().map(|(key, _)| key.value.as_str()).collect();
+    assert_eq!(keys, ["a", "b", "c"]);
+
+    let mut fields = s.fields().map(|(_, value)| &value.value);
+    match fields.next() {
+        Some(ast::Expr::List(l)) => {
+            assert_eq!(l.len(), 2);
+            assert_eq!(l.iter().count(), 2);
+        }
+        _ => unreachable!(),
+    }
+    match fields.next() {
+        Some(ast::Expr::Map(m)) => {
+            let (key, value) = m.entries().next().unwrap();
+            assert_eq!(key.value, ast::Expr::Str("k"));
+            assert_eq!(value.value.as_tuple().map(ast::Tuple::len), Some(2));
+            assert!(!m.is_empty());
+        }
+        _ => unreachable!(),
+    }
+    assert!(matches!(fields.next(), Some(ast::Expr::Unit)));
+}
+
+#[test]
+fn attribute_checks() {
+    use crate::ErrorKind;
+
+    let kind_and_start = |input: &str| {
+        ast_from_str(input)
+            .map(|_| ())
+            .map_err(|e| (e.kind.clone(), e.start().map(|l| (l.line, l.column))))
+    };
+
+    assert_eq!(
+        kind_and_start("#![enable(implicit_some)]\n#![enable(unwrap_newtypes)]\n5"),
+        Err((ErrorKind::DuplicateEnableAttribute, Some((2, 1))))
+    );
+    assert_eq!(
+        kind_and_start("#![enable(implicit_some, implicit_some)] 5"),
+        Err((
+            ErrorKind::DuplicateExtension("implicit_some".to_owned()),
+            Some((1, 26))
+        ))
+    );
+    assert_eq!(
+        kind_and_start("5\n#![enable(implicit_some)]"),
+        Err((ErrorKind::AttributeAfterExpression, Some((2, 1))))
+    );
+    assert_eq!(
+        kind_and_start("#![enable(implicit_some, nope)] 5"),
+        Err((
+            ErrorKind::UnknownExtension("nope".to_owned()),
+            Some((1, 26))
+        ))
+    );
+    assert_eq!(
+        kind_and_start("#![enable(implicit_some, unwrap_newtypes)] 5"),
+        Ok(())
+    );
+    assert_eq!(
+        kind_and_start("#![version(1)]\n#![enable(implicit_some)]\n#![version(2)] 5"),
+        Err((ErrorKind::DuplicateVersionAttribute, Some((3, 1))))
+    );
+    assert_eq!(
+        kind_and_start("#![version(2)] #![enable(implicit_some)] 5"),
+        Ok(())
+    );
+
+    // The first occurrence is labeled
+    let label = |input: &str| {
+        let err = ast_from_str(input).unwrap_err();
+        let label = &err.labels()[0];
+        (label.message.clone(), label.start.line, label.start.column)
+    };
+    assert_eq!(
+        label("#![enable(implicit_some)]\n#![enable(unwrap_newtypes)]\n5"),
+        ("first `enable` attribute".to_owned(), 1, 1)
+    );
+    assert_eq!(
+        label("#![enable(implicit_some, implicit_some)] 5"),
+        ("first enabled here".to_owned(), 1, 11)
+    );
+    assert_eq!(
+        label("#![version(1)]\n#![version(2)]\n5"),
+        ("first `version` attribute".to_owned(), 1, 1)
+    );
+}
+
+#[test]
+fn headers() {
+    use crate::{
+        ast::{Attribute, Extension},
+        ErrorKind,
+    };
+
+    // The body isn't parsed
+    let source = "// Config\n#![enable(implicit_some, color_literals)]\n  #![version(3)]\n[1, 2";
+    let (attributes, rest) = parse_header(source).unwrap();
+    assert_eq!(attributes.len(), 2);
+    match &attributes[0].value {
+        Attribute::Enable(extensions) => assert_eq!(
+            extensions
+                .value
+                .iter()
+                .map(|e| e.value.clone())
+                .collect::<Vec<_>>(),
+            vec![Extension::ImplicitSome, Extension::ColorLiterals]
+        ),
+        other => panic!("{:?}", other),
+    }
+    assert_eq!(
+        (attributes[1].start.line, attributes[1].start.column),
+        (3, 3)
+    );
+    assert_eq!(&source[rest..], "[1, 2");
+
+    assert_eq!(parse_header("[1]").unwrap(), (vec![], 0));
+    assert_eq!(parse_header("#ff8000").unwrap(), (vec![], 0));
+    // Extensions may be registered later
+    assert!(parse_header("#![enable(nope)] 5").is_ok());
+    assert_eq!(
+        parse_header("#![version(1)] #![version(2)]")
+            .unwrap_err()
+            .kind,
+        ErrorKind::DuplicateVersionAttribute
+    );
+    assert!(parse_header("#![enable(implicit_some) 5").is_err());
+}
+
+#[test]
+fn unclosed_labels() {
+    let labels = |input: &str| {
+        ast_from_str(input)
+            .unwrap_err()
+            .labels()
+            .iter()
+            .map(|label| {
+                format!(
+                    "{}:{} {}",
+                    label.start.line, label.start.column, label.message
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(labels("[1, 2"), ["1:1 unclosed `[` opened here"]);
+    assert_eq!(
+        labels("(a: 1,\n b: [1, 2\n"),
+        ["2:5 unclosed `[` opened here"]
+    );
+    assert_eq!(labels("{\"a\": 1"), ["1:1 unclosed `{` opened here"]);
+    assert_eq!(labels("[\"abc"), ["1:2 unclosed string starts here"]);
+
+    // Errors before the end of the input aren't about unclosed brackets
+    assert!(labels("[1 2]").is_empty());
+    assert!(labels("(a: )").is_empty());
+}
+
+#[test]
+fn partial() {
+    let rest = |input: &'static str| {
+        let (_, len) = ast_from_str_partial(input).unwrap();
+        &input[len..]
+    };
+
+    assert_eq!(rest("5"), "");
+    assert_eq!(rest("[1, 2] [3]"), " [3]");
+    assert_eq!(rest("Point(x: 1) // comment"), " // comment");
+    assert_eq!(rest("#![enable(implicit_some)]\n(a: 1)\n---\n"), "\n---\n");
+    assert_eq!(rest("None, Some(1)"), ", Some(1)");
+    assert_eq!(rest("Unit next"), " next");
+    assert_eq!(rest("\"a\"b"), "b");
+
+    let (ron, _) = ast_from_str_partial("/* a */ (x: 2.5) rest").unwrap();
+    assert_eq!((ron.expr.start.column, ron.expr.end.column), (9, 17));
+
+    let err = ast_from_str_partial("(x: ture) rest").unwrap_err();
+    assert_eq!(err.start().map(|l| l.column), Some(5));
+    assert!(ast_from_str_partial("").is_err());
+    assert!(ast_from_str_partial("#![enable(unknown)] 5").is_err());
+}
+
+#[test]
+fn misspelled_keyword_labels() {
+    let label = |input: &str| {
+        let err = ast_from_str(input).unwrap_err();
+        let label = &err.labels()[0];
+        (label.message.clone(), label.start.column, label.end.column)
+    };
+
+    assert_eq!(
+        label("(a: ture)"),
+        ("did you mean `true`?".to_owned(), 5, 9)
+    );
+    assert_eq!(
+        label("[1, flase]"),
+        ("did you mean `false`?".to_owned(), 5, 10)
+    );
+    assert!(ast_from_str("(a: )").unwrap_err().labels().is_empty());
+}
+
+#[test]
+fn unclosed_delimiters() {
+    let unclosed = |input: Input| {
+        let err = ast_from_input(input).unwrap_err();
+        let message = err.kind.to_string();
+        let line = message.lines().last().unwrap().trim().to_owned();
+
+        (line, err.start().map(|l| (l.line, l.column)))
+    };
+    let message = |input: &str| unclosed(Input::new(input)).0;
+
+    assert_eq!(
+        unclosed(Input::new("(a: 1,\n b: [1, 2,\n")),
+        (
+            "unclosed '[' opened at 2:5, expected a value at 3:1".to_owned(),
+            Some((3, 1))
+        )
+    );
+    assert_eq!(
+        message("Foo(1, 2"),
+        "unclosed '(' opened at 1:4, expected ',' at 1:9"
+    );
+    assert_eq!(
+        message("{\"a\": 1 /* end */"),
+        "unclosed '{' opened at 1:1, expected ',' at 1:18"
+    );
+    // The innermost one
+    assert_eq!(
+        message("[(1, [2]"),
+        "unclosed '(' opened at 1:2, expected ',' at 1:9"
+    );
+    assert_eq!(message("[1, 2"), "unclosed '[' opened at 1:1, expected ',' at 1:6");
+
+    // Deeply nested and recovering lists
+    let deep = format!("{}1", "[".repeat(DEFAULT_MAX_DEPTH));
+    assert!(message(&deep).starts_with("unclosed '[' opened at 1:128"));
+    assert_eq!(
+        unclosed(Input::new("[1, (a: 2), 3").with_recovery(true)).0,
+        "unclosed '[' opened at 1:1, expected ']' at 1:14"
+    );
+
+    // Unclosed brackets are fatal and keep the expected tokens
+    let err = ast_from_str("(a: 1").unwrap_err();
+    assert_eq!(err.severity(), Some(crate::Severity::Fatal));
+    assert_eq!(err.expected_tokens(), vec![crate::ExpectedToken::Char(',')]);
+}
+
+#[test]
+fn unclosed_block_comments() {
+    // These used to panic instead of returning an error
+    for input in ["/*", "/* é", "/*é", "(a: 1) /* *", "[1, /*/"] {
+        let err = ast_from_str(input).unwrap_err();
+        assert_eq!(err.severity(), Some(crate::Severity::Fatal), "{:?}", input);
+    }
+}
+
+#[test]
+fn severity() {
+    use crate::{utf8_parser::ron::ron, Severity};
+
+    let severity = |input: &str| ast_from_str(input).unwrap_err().severity();
+
+    assert_eq!(severity(":"), Some(Severity::Recoverable));
+    assert_eq!(severity("\"abc"), Some(Severity::Fatal));
+    assert_eq!(severity("(a: 1) (b: 2)"), Some(Severity::Fatal));
+    assert_eq!(
+        ast_from_str("(a: true(1))").unwrap_err().severity(),
+        None,
+        "not a parsing error"
+    );
+
+    let e = ron(Input::new("(a: 1) (b: 2)")).unwrap_err();
+    assert_eq!(format!("{:#}", e), format!("fatal: {}", e));
+}
+
+#[test]
+fn keyword_names() {
+    use crate::ErrorKind;
+
+    let kind_and_start = |input: &str| {
+        ast_from_str(input)
+            .map(|_| ())
+            .map_err(|e| (e.kind.clone(), e.start().map(|l| (l.line, l.column))))
+    };
+
+    assert_eq!(
+        kind_and_start("(a: 1, b: true(1))"),
+        Err((ErrorKind::KeywordAsName("true".to_owned()), Some((1, 11))))
+    );
+    assert_eq!(
+        kind_and_start("false(x: 1)"),
+        Err((ErrorKind::KeywordAsName("false".to_owned()), Some((1, 1))))
+    );
+    assert_eq!(
+        kind_and_start("(a: 1, true: 2)"),
+        Err((ErrorKind::KeywordAsName("true".to_owned()), Some((1, 8))))
+    );
+    assert_eq!(
+        kind_and_start("Point(x: 1, false : 2)"),
+        Err((ErrorKind::KeywordAsName("false".to_owned()), Some((1, 13))))
+    );
+    // Fine as raw identifiers, and `None` and `Some` as field names
+    assert_eq!(
+        kind_and_start("(r#true: 1, None: 2, Some: 3, trueish: 4)"),
+        Ok(())
+    );
+    assert_eq!(kind_and_start("r#true(1)"), Ok(()));
+    assert_eq!(kind_and_start("(a: true, b: false)"), Ok(()));
+}
+
+#[test]
+fn deny_non_finite() {
+    use crate::ErrorKind;
+
+    let kind_and_span = |input: &str, deny_non_finite: bool| {
+        ast_from_input(Input::new(input).with_deny_non_finite(deny_non_finite))
+            .map(|_| ())
+            .map_err(|e| {
+                let span = e.start().zip(e.end()).map(|(s, e)| (s.column, e.column));
+                (e.kind.clone(), span)
+            })
+    };
+
+    if cfg!(feature = "integers_only") {
+        return;
+    }
+    assert_eq!(
+        kind_and_span("[1.5, (a: -1.0e400), 3]", true),
+        Err((ErrorKind::NonFiniteFloat, Some((11, 19))))
+    );
+    assert_eq!(
+        kind_and_span("#![enable(unit_literals)]\n(t: 1.0e400s)", true),
+        Err((ErrorKind::NonFiniteFloat, Some((5, 13))))
+    );
+    assert_eq!(kind_and_span("[1.0e400, 1.7e308]", false), Ok(()));
+    assert_eq!(kind_and_span("[1.7e308, -0.0e400]", true), Ok(()));
+
+    let (valid, _) = validate_input_timed(
+        Input::new("(a: 1.0e400)").with_deny_non_finite(true),
+        &extension::ExtensionRegistry::new(),
+    );
+    assert!(valid.is_err());
+}
+
+#[test]
+fn deny_floats() {
+    use crate::ErrorKind;
+
+    let kind_and_span = |input: &str, deny_floats: bool| {
+        ast_from_input(Input::new(input).with_deny_floats(deny_floats))
+            .map(|_| ())
+            .map_err(|e| {
+                let span = e.start().zip(e.end()).map(|(s, e)| (s.column, e.column));
+                (e.kind.clone(), span)
+            })
+    };
+
+    assert_eq!(
+        kind_and_span("[1, (a: -2.5e3), 3]", true),
+        Err((ErrorKind::FloatNotAllowed, Some((9, 15))))
+    );
+    assert_eq!(
+        kind_and_span("#![enable(unit_literals)]\n(t: 2.5s)", true),
+        Err((ErrorKind::FloatNotAllowed, Some((5, 9))))
+    );
+    assert_eq!(
+        kind_and_span(
+            "#![enable(unit_literals)]\n(t: 250ms, n: -3, s: \"1.5\")",
+            true
+        ),
+        Ok(())
+    );
+    // Always rejected with the `integers_only` feature
+    assert_eq!(
+        kind_and_span("1.5", false).is_err(),
+        cfg!(feature = "integers_only")
+    );
+}
+
+#[test]
+fn furthest_alt_error() {
+    use crate::utf8_parser::{basic::tag, combinators::alt2};
+
+    fn deep(input: Input) -> IResultLookahead<char> {
+        lookahead(preceded(tag("ab"), one_char('c')))(input)
+    }
+
+    fn shallow(input: Input) -> IResultLookahead<char> {
+        lookahead(preceded(tag("a"), one_char('x')))(input)
+    }
+
+    let column = |res: IResultLookahead<char>| match res {
+        Err(InputParseErr::Recoverable(e)) | Err(InputParseErr::Fatal(e)) => {
+            Error::from(e).start().map(|l| l.column)
+        }
+        Ok(_) => None,
+    };
+    assert_eq!(column(eval!(@result alt2(deep, shallow), "abd")), Some(3));
+    assert_eq!(column(eval!(@result alt2(shallow, deep), "abd")), Some(3));
+
+    // Chosen by offset, not by where the inputs are in memory
+    let (first, second) = (String::from("abd"), String::from("abd"));
+    fn error(input: &str, offset: usize) -> InputParseError<'_> {
+        ErrorTree::expected(Input::new(input).slice(offset..), Expectation::Eof)
+    }
+    for (deep, shallow) in [(&first, &second), (&second, &first)] {
+        let tree = ErrorTree::alt(error(deep, 2), error(shallow, 1));
+        assert_eq!(Error::from(tree).start().map(|l| l.column), Some(3));
+    }
+}
+
+#[test]
+fn alt_errors() {
+    let message = |alt_errors| {
+        let input = Input::new("[[[(a: 1 b)]]]").with_alt_errors(alt_errors);
+        let err = ast_from_input(input).unwrap_err();
+        assert_eq!(err.start().map(|l| l.column), Some(10));
+
+        err.kind.to_string()
+    };
+
+    // The tuple branch gave up at `1`, long before the struct branch
+    assert_eq!(
+        message(AltErrors::Furthest),
+        r#"parsing error: could not match "expression" at 1:4 because
+could not match "untagged struct" at 1:4 because
+    expected ',' at 1:10"#
+    );
+    assert!(message(AltErrors::All).contains(r#"could not match "tuple" at 1:4"#));
+}
+
+#[test]
+fn error_tree_visitor() {
+    #[derive(Default)]
+    struct Labels(Vec<String>);
+
+    impl ErrorTreeVisitor<crate::Location> for Labels {
+        fn visit_base(&mut self, location: &crate::Location, kind: &BaseErrorKind) {
+            self.0.push(format!("{} {}", location, kind));
+        }
+
+        fn visit_context(&mut self, location: &crate::Location, context: StackContext) {
+            self.0.push(format!("{} {}", location, context));
+        }
+    }
+
+    let labels = |alt_errors| {
+        let input = Input::new("[[[(a: 1 b)]]]").with_alt_errors(alt_errors);
+        let err = ast_from_input(input).unwrap_err();
+        let tree = err.error_tree().unwrap();
+        assert_eq!(Some(*tree.max_location()), err.start());
+
+        let mut labels = Labels::default();
+        labels.visit_tree(tree);
+        labels.0
+    };
+
+    assert_eq!(
+        labels(AltErrors::Furthest),
+        [
+            r#"1:4 could not match "expression""#,
+            r#"1:4 could not match "untagged struct""#,
+            "1:10 expected ','",
+        ]
+    );
+    // All alternatives are visited
+    let labels = labels(AltErrors::All);
+    assert!(labels.contains(&r#"1:4 could not match "tuple""#.to_owned()));
+    assert!(labels.contains(&"1:10 expected ','".to_owned()));
+
+    // Only parsing errors have a tree
+    assert!(ast_from_str("#![enable(unknown)] 1")
+        .unwrap_err()
+        .error_tree()
+        .is_none());
+}
+
+#[test]
+fn nested_sequences() {
+    use crate::utf8_parser::containers::nested_sequence;
+
+    for source in [
+        "[]",
+        "[ 1 , () , [ [ ], ], ]",
+        "((1, [2]), (a: [3]), Some([4]))",
+        "[/* a */ [1] // b\n]",
+    ] {
+        assert_eq!(eval!(nested_sequence, source), eval!(expr, source));
+    }
+
+    let err = Error::from(match eval!(@result nested_sequence, "[(1, 2 3)]") {
+        Err(InputParseErr::Fatal(e)) => e,
+        _ => unreachable!(),
+    });
+    assert_eq!(err.start().map(|l| l.column), Some(8));
+    let message = err.kind.to_string();
+    assert!(message.contains(r#"could not match "tuple" at 1:2"#));
+
+    // Far deeper than the call stack of a test thread allows for recursive descent
+    let depth = 2000;
+    let source = format!("{}(1,){}", "[".repeat(depth), "]".repeat(depth));
+    let ok = expr(Input::new(&source).with_max_depth(depth + 1)).unwrap();
+    assert!(ok.remaining.is_empty());
+    let err = eval!(@result expr, &source).unwrap_err().into_error();
+    assert_eq!(err.kind, ErrorKind::NestingTooDeep(DEFAULT_MAX_DEPTH));
+    assert_eq!(err.start().map(|l| l.column), Some(130));
+}
+
+#[test]
+fn recovering_lists() {
+    let columns = |source: &str| {
+        ast_from_str_recovering(source)
+            .unwrap_err()
+            .iter()
+            .map(|e| e.start().unwrap().column)
+            .collect::<Vec<_>>()
+    };
+
+    assert!(ast_from_str_recovering("[1, [2, 3], (a: 4)]").is_ok());
+    assert_eq!(columns("[1, (a: ), 3, {4}, 5]"), [9, 17]);
+    // Missing commas, nested lists, and brackets in strings and comments
+    assert_eq!(columns("[1 2, [3, -], \"]\", /* ] */ (, 4]"), [4, 12, 29]);
+    // An unclosed list can't be continued after
+    assert_eq!(columns("[1, (a: ), 3"), [13]);
+    // Other errors end parsing
+    assert_eq!(columns("(a: [1, 2], b: )"), [16]);
+
+    // Deeply nested lists recover the same way
+    let source = format!("{}[1, }}, 2]{}", "[".repeat(100), "]".repeat(100));
+    assert_eq!(columns(&source), [105]);
+
+    let elements = (0..2_000)
+        .map(|i| match i % 200 {
+            199 => "(x: 1 y: 2)",
+            _ => "(x: 1, y: 2)",
+        })
+        .collect::<Vec<_>>();
+    let errors = ast_from_str_recovering(&format!("[{}]", elements.join(", "))).unwrap_err();
+    assert_eq!(errors.len(), 10);
+
+    // Without recovery, only the first error is reported
+    let err = ast_from_str("[1, (a: ), 3, {4}, 5]").unwrap_err();
+    assert_eq!(err.start().map(|l| l.column), Some(9));
+}
+
+#[test]
+fn expr_children() {
+    use crate::ast::ExprKind;
+
+    fn count(expr: &ast::Expr, kind: ExprKind) -> usize {
+        let children: usize = expr.children().map(|c| count(&c.value, kind)).sum();
+
+        children + (expr.kind() == kind) as usize
+    }
+
+    let source = r#"Config(a: Some([1, 2]), b: {"k": (3, [])}, c: Unit)"#;
+    let mut ron = ast_from_str(source).unwrap();
+    assert_eq!(count(&ron.expr.value, ExprKind::List), 2);
+    assert_eq!(count(&ron.expr.value, ExprKind::Integer), 3);
+    assert_eq!(
+        ron.expr
+            .value
+            .children()
+            .map(|c| c.value.kind())
+            .collect::<Vec<_>>(),
+        [ExprKind::Optional, ExprKind::Map, ExprKind::Tagged]
+    );
+
+    ron.expr
+        .value
+        .children_mut()
+        .for_each(|c| c.value = ast::Expr::Unit);
+    assert_eq!(count(&ron.expr.value, ExprKind::Integer), 0);
+    assert_eq!(count(&ron.expr.value, ExprKind::Unit), 3);
+}
+
+#[test]
+fn visitor() {
+    use crate::ast::{walk_expr, Expr, Spanned, Visitor};
+
+    /// The strings outside of lists
+    #[derive(Default)]
+    struct Strings<'a>(Vec<&'a str>);
+
+    impl<'a> Visitor<'a> for Strings<'a> {
+        fn visit_expr(&mut self, expr: &Spanned<Expr<'a>>) {
+            match &expr.value {
+                Expr::Str(s) => self.0.push(s),
+                Expr::List(_) => {}
+                _ => walk_expr(self, expr),
+            }
+        }
+    }
+
+    let ron = ast_from_str(r#"(a: "x", b: ["y"], c: Some({"z": 1}))"#).unwrap();
+    let mut strings = Strings::default();
+    strings.visit_ron(&ron);
+    assert_eq!(strings.0, ["x", "z"]);
+}
+
+#[test]
+fn required_extensions() {
+    use crate::ast::{required_extensions, Extension};
+
+    let required = |input: &str| required_extensions(&ast_from_str(input).unwrap());
+
+    assert_eq!(required("(a: [1, 2], b: Some(\"#ff\"))"), []);
+    assert_eq!(
+        required("#![enable(color_literals, unit_literals)] [(#ff8000, 2s), #000000]"),
+        [Extension::UnitLiterals, Extension::ColorLiterals]
+    );
+    // Only used literals count, not enabled extensions
+    assert_eq!(
+        required("#![enable(implicit_some, color_literals)] (c: 1)"),
+        []
+    );
+}
+
+#[test]
+fn input_window() {
+    let source = "let config = (width: 1, height: true);";
+    let input = Input::new_window(source, 13..37).unwrap();
+    assert!(ast_from_input(input).is_ok());
+
+    let input = Input::new_window(source, 13..36).unwrap();
+    let err = ast_from_input(input).unwrap_err();
+    assert_eq!(err.start().map(|l| (l.line, l.column)), Some((1, 37)));
+
+    let err = ast_from_input(Input::new_relative("(width: 1, height: )")).unwrap_err();
+    assert_eq!(err.start().map(|l| (l.line, l.column)), Some((1, 20)));
+}
+
+#[test]
+fn lazy_escapes() {
+    use crate::ast::{EscapedStr, Expr};
+
+    let source = r#"["plain", "tab\t, quote \" and \u{1F980}", "line \
+        continued", "\\"]"#;
+    let strings = |lazy: bool| -> Vec<std::string::String> {
+        let ron = ast_from_input(Input::new(source).with_lazy_escapes(lazy)).unwrap();
+        ron.expr
+            .value
+            .children()
+            .map(|child| match &child.value {
+                Expr::Str(s) => s.to_string(),
+                Expr::String(s) if !lazy => s.clone(),
+                Expr::EscapedStr(s) if lazy => s.unescape().into_owned(),
+                other => panic!("unexpected {:?}", other),
+            })
+            .collect()
+    };
+    assert_eq!(strings(true), strings(false));
+    assert_eq!(strings(true)[1], "tab\t, quote \" and \u{1F980}");
+
+    assert_eq!(EscapedStr("no escapes").unescape(), "no escapes");
+    assert!(matches!(
+        EscapedStr("no escapes").unescape(),
+        std::borrow::Cow::Borrowed(_)
+    ));
+
+    // Escapes are still checked
+    let input = Input::new(r#""\q""#).with_lazy_escapes(true);
+    assert!(ast_from_input(input).is_err());
+}
+
+#[cfg(feature = "unicode_ident")]
+#[test]
+fn unicode_idents() {
+    let source = "Größe(breite: 1, höhe_2: Ä, 名前: \"x\")";
+    assert!(ast_from_str(source).is_err());
+
+    let ron = ast_from_input(Input::new(source).with_unicode_idents(true)).unwrap();
+    match ron.expr.value {
+        crate::ast::Expr::Tagged(t) => assert_eq!(t.ident.value.0, "Größe"),
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // Only letters and digits, like Rust identifiers
+    let input = Input::new("(a→b: 1)").with_unicode_idents(true);
+    assert!(ast_from_input(input).is_err());
+}
+
+#[test]
+fn registered_extension() {
+    use crate::{
+        utf8_parser::extension::{ExtensionRegistry, RonExtension},
+        Error, ErrorKind,
+    };
+
+    /// `12px` as integer, only allowed in lists
+    struct Pixels;
+
+    impl RonExtension for Pixels {
+        fn name(&self) -> &str {
+            "pixels"
+        }
+
+        fn parse_literal<'a>(&self, literal: &'a str) -> Option<Result<ast::Expr<'a>, String>> {
+            let number = literal.strip_suffix("px")?;
+
+            Some(
+                number
+                    .parse()
+                    .map(|number| ast::UnsignedInteger::new(number).to_expr())
+                    .map_err(|_| "expected a whole number of pixels".to_owned()),
+            )
+        }
+
+        fn transform(&self, ron: &mut ast::Ron) -> Result<(), Error> {
+            match ron.expr.value {
+                ast::Expr::List(_) => Ok(()),
+                _ => Err(Error {
+                    kind: ErrorKind::Custom("expected a list".to_owned()),
+                    context: None,
+                }),
+            }
+        }
+    }
+
+    let registry = ExtensionRegistry::new().with(Pixels);
+    assert!(registry.names().any(|name| name == "pixels"));
+
+    let ron = ast_from_str_with_extensions("#![enable(pixels)] [12px, 3]", &registry).unwrap();
+    assert_eq!(
+        ron.expr.value,
+        ast::Expr::List(ast::List::new_test(vec![
+            ast::Expr::Integer(ast::Integer::new_test(None, 12)),
+            ast::Expr::Integer(ast::Integer::new_test(None, 3)),
+        ]))
+    );
+
+    let kind = |input: &str| {
+        ast_from_str_with_extensions(input, &registry)
+            .unwrap_err()
+            .kind
+    };
+    assert_eq!(
+        kind("#![enable(pixels)] 12px"),
+        ErrorKind::Custom("expected a list".to_owned())
+    );
+    assert_eq!(
+        kind("[12px]"),
+        ErrorKind::ExtensionNotEnabled("pixels".to_owned())
+    );
+    assert_eq!(
+        kind("#![enable(pixels)] [1.5px]"),
+        ErrorKind::Custom("invalid literal `1.5px`, expected a whole number of pixels".to_owned())
+    );
+    assert_eq!(
+        kind("#![enable(pixels)] [12em]"),
+        ErrorKind::UnsupportedLiteral("12em".to_owned())
+    );
+    assert_eq!(
+        ast_from_str("#![enable(pixels)] [12px]").unwrap_err().kind,
+        ErrorKind::UnknownExtension("pixels".to_owned())
+    );
+}
+
+#[test]
+fn unit_literals() {
+    use std::time::Duration;
+
+    use crate::ErrorKind;
+
+    let ron =
+        ast_from_str("#![enable(unit_literals)] [250ms, 1.5min, 64KiB, 1.5GB, 0.5B]").unwrap();
+    let quantities = match ron.expr.value {
+        ast::Expr::List(l) => l
+            .elements
+            .into_iter()
+            .map(|e| match e.value {
+                ast::Expr::Quantity(q) => q,
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>(),
+        _ => unreachable!(),
+    };
+    assert_eq!(
+        quantities[0].to_duration(),
+        Some(Duration::from_millis(250))
+    );
+    assert_eq!(quantities[1].to_duration(), Some(Duration::from_secs(90)));
+    assert_eq!(quantities[1].to_bytes(), None);
+    assert_eq!(quantities[2].to_bytes(), Some(65536));
+    assert_eq!(quantities[3].to_bytes(), Some(1_500_000_000));
+    assert_eq!(quantities[3].to_string(), "1.5GB");
+    assert_eq!(quantities[4].to_bytes(), None);
+
+    let err = ast_from_str("(timeout: [1, 250ms])").unwrap_err();
+    assert_eq!(
+        err.kind,
+        ErrorKind::ExtensionNotEnabled("unit_literals".to_owned())
+    );
+    assert_eq!(err.start().map(|l| (l.line, l.column)), Some((1, 15)));
+
+    assert!(ast_from_str("#![enable(unit_literals)] 10sec").is_err());
+    assert!(ast_from_str("[1, 0x1F, .5, 2.0e3]").is_ok());
+}
+
+#[test]
+fn color_literals() {
+    use crate::ErrorKind;
+
+    let ron = ast_from_str("#![enable(color_literals)]\n[#ff8000, #00000080]").unwrap();
+    match ron.expr.value {
+        ast::Expr::List(l) => assert_eq!(
+            l.elements
+                .iter()
+                .map(|e| match &e.value {
+                    ast::Expr::Color(c) => c.to_string(),
+                    _ => unreachable!(),
+                })
+                .collect::<Vec<_>>(),
+            ["#ff8000", "#00000080"]
+        ),
+        _ => unreachable!(),
+    }
+    assert!(ast_from_str("#![enable(color_literals)] #ff80").is_err());
+    assert!(ast_from_str("#![enable(color_literals)] #ff80008").is_err());
+
+    let err = ast_from_str("#ff8000").unwrap_err();
+    assert_eq!(
+        err.kind,
+        ErrorKind::ExtensionNotEnabled("color_literals".to_owned())
+    );
+    assert_eq!(err.start().map(|l| (l.line, l.column)), Some((1, 1)));
+}
+
+#[test]
+fn expected_tokens() {
+    use crate::ExpectedToken;
+
+    let expected = |input: &str| ast_from_str(input).unwrap_err().expected_tokens();
+
+    assert_eq!(expected("(a: 1"), vec![ExpectedToken::Char(',')]);
+    assert_eq!(
+        expected("(a 1)"),
+        vec![ExpectedToken::Char(':'), ExpectedToken::Char(',')]
+    );
+    assert_eq!(
+        expected(""),
+        vec![ExpectedToken::Letter, ExpectedToken::Char('_')]
+    );
+    assert_eq!(expected("[1, 2] 3"), vec![ExpectedToken::Eof]);
+}
+
+#[test]
+fn columns_after_multi_byte_lines() {
+    use crate::Location;
+
+    // The `ä` on the first line doesn't shift columns on the second
+    let err = ast_from_str("\"ä\"\n  :").unwrap_err();
+    assert_eq!(err.start(), Some(Location { line: 2, column: 3 }));
+}
+
+#[test]
+fn tagged_spans_exclude_trailing_comments() {
+    use crate::Location;
+
+    for (source, end) in [("A(1) // c", 5), ("A (x: 1) /**/", 9), ("A\n// c", 2)] {
+        let ron = ast_from_str(source).unwrap();
+        assert_eq!(
+            ron.expr.end,
+            Location {
+                line: 1,
+                column: end
+            },
+            "{}",
+            source
+        );
+    }
+}
+
+#[test]
+fn max_error_contexts() {
+    use crate::{utf8_parser::error::Expectation, Error};
+
+    let message = |max_error_contexts| {
+        let input =
+            Input::new("[[[[[[[[[[1 2]]]]]]]]]]").with_max_error_contexts(max_error_contexts);
+        let e = (0..10).rev().fold(
+            ErrorTree::expected(input.slice(12..), Expectation::Char(',')),
+            |e, depth| InputParseError::add_context(input.slice(depth..), "list", false, e),
+        );
+
+        Error::from(e).kind.to_string()
+    };
+
+    assert_eq!(
+        message(Some(DEFAULT_MAX_ERROR_CONTEXTS)),
+        r#"parsing error: could not match "list" at 1:1 because
+could not match "list" at 1:2 because
+... 6 more contexts ...
+could not match "list" at 1:9 because
+could not match "list" at 1:10 because
+    expected ',' at 1:13"#
+    );
+    assert_eq!(message(None).lines().count(), 11);
+    assert!(message(Some(1)).starts_with(
+        "parsing error: could not match \"list\" at 1:1 because\n... 9 more contexts ...\n"
+    ));
+}
+
+#[test]
+fn verbose_expectations() {
+    let message = |source, verbose| {
+        let input = Input::new(source).with_verbose_expectations(verbose);
+        let err = ast_from_input(input).unwrap_err();
+        assert_eq!(err.expected_tokens().len(), 2);
+
+        err.kind.to_string()
+    };
+
+    assert_eq!(
+        message("(a: )", false),
+        "parsing error: expected a value at 1:5"
+    );
+    assert_eq!(
+        message("(a: )", true),
+        r#"parsing error: could not match "expression" at 1:5 because
+    expected one of an ascii letter or '_' at 1:5"#
+    );
+    assert!(message("(a: 1, : 2)", false).ends_with("expected an identifier at 1:8"));
+}
+
+#[test]
+fn from_str_owned() {
+    use std::convert::TryFrom;
+
+    use crate::ast::{Expr, Ident, Ron, Untagged};
+
+    let source = r#"#![enable(implicit_some)]
+(
+    /// Name
+    a: "x",
+    b: "\u{e9}",
+)"#
+    .to_owned();
+    let owned: Ron<'static> = source.parse().unwrap();
+    assert_eq!(
+        owned.attributes,
+        Ron::try_from(source.as_str()).unwrap().attributes
+    );
+    drop(source);
+
+    let fields = match owned.expr.value {
+        Expr::Struct(s) => s.fields,
+        other => panic!("expected a struct, got {:?}", other),
+    };
+    assert_eq!(fields[0].value.key.value, Ident::from_str("a"));
+    assert_eq!(fields[0].value.doc, vec!["Name"]);
+    assert_eq!(fields[0].value.value.value, Expr::String("x".to_owned()));
+    assert_eq!(fields[1].value.value.value, Expr::String("é".to_owned()));
+
+    let expr: Expr<'static> = "Point(1, 2)".parse().unwrap();
+    assert!(matches!(expr, Expr::Tagged(t) if matches!(t.untagged.value, Untagged::Tuple(_))));
+    assert_eq!(Expr::try_from("\"y\""), Ok(Expr::Str("y")));
+    assert!("(a: ".parse::<Expr>().is_err());
+}
+
+/// Lists and fields hold their expressions inline, so every variant adds to
+/// the size of all of them
+#[test]
+#[cfg(target_pointer_width = "64")]
+fn expr_sizes() {
+    use std::mem::size_of;
+
+    assert_eq!(size_of::<ast::Expr>(), 32);
+    assert_eq!(size_of::<ast::Spanned<ast::Expr>>(), 48);
+    assert_eq!(size_of::<Expr>(), 32);
+}
+
+#[test]
+fn backtracking() {
+    use crate::utf8_parser::extension::ExtensionRegistry;
+
+    let backtracked = |s: &str| {
+        ast_from_input_timed(Input::new(s), &ExtensionRegistry::new())
+            .1
+            .backtracked
+    };
+
+    let config = "Config(size: (1, 2), tags: [\"a\"])";
+    assert!(backtracked(config) < config.len());
+    // Each parse counts its own bytes, whatever parsed before
+    let counted = backtracked(config);
+    assert!(counted > 0);
+    backtracked(&"A(".repeat(12));
+    assert_eq!(backtracked(config), counted);
+    // Nesting that every alternative has to look into reads each byte a
+    // bounded number of times, whether it parses or not
+    for (open, inner) in [
+        ("A(", "x"),
+        ("A(", "x y"),
+        ("A(b:", "1 2"),
+        ("Some(", "1 2"),
+        ("{A(", "1 2"),
+        ("[A(", "x y"),
+        ("A(1,", ""),
+    ] {
+        for n in [4, 8, 12] {
+            let s = format!("{}{}", open.repeat(n), inner);
+            assert!(backtracked(&s) <= 3 * s.len(), "{:?} * {}", open, n);
+        }
+    }
+
+    let s = "A(".repeat(10) + "x";
+    let e = ast_from_input(Input::new(&s).with_backtrack_budget(Some(5))).unwrap_err();
+    assert_eq!(e.kind, ErrorKind::BacktrackBudgetExceeded(5));
+    assert!(matches!(
+        ast_from_input(Input::new(&s).with_backtrack_budget(Some(100))),
+        Err(Error {
+            kind: ErrorKind::ParseError(_),
+            ..
+        })
+    ));
+}
@@ -0,0 +1,36 @@
+use std::time::{Duration, Instant};
+
+/// Time spent in each phase of loading a document, see
+/// [`ast_from_input_timed`](super::ast_from_input_timed) and
+/// [`from_str_timed`](super::serde::from_str_timed)
+///
+/// Whitespace and comments are skipped while parsing the expressions around
+/// them, so their time is part of [`parse`](Timings::parse).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Timings {
+    /// Parsing the text into a parse tree
+    pub parse: Duration,
+    /// Checking attributes, resolving extensions and converting the parse tree
+    /// to the AST
+    pub convert: Duration,
+    /// Deserializing the AST, zero if the document is only parsed
+    pub deserialize: Duration,
+    /// Bytes that parsers read before failing and leaving them to another
+    /// parser, see [`Input::with_backtrack_budget`](super::Input::with_backtrack_budget)
+    pub backtracked: usize,
+}
+
+impl Timings {
+    pub fn total(&self) -> Duration {
+        self.parse + self.convert + self.deserialize
+    }
+}
+
+/// Runs `f`, adding the time it takes to `duration`
+pub(crate) fn timed<T>(duration: &mut Duration, f: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let t = f();
+    *duration += start.elapsed();
+
+    t
+}
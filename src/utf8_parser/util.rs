@@ -19,6 +19,17 @@ pub fn base_err_res<T>(input: Input, expectation: Expectation) -> OutputResult<T
     )))
 }
 
+/// Runs `parser` on all of `s`, `None` if it fails or doesn't consume everything
+pub fn parse_all<'a, O>(
+    mut parser: impl FnMut(Input<'a>) -> IResultLookahead<'a, O>,
+    s: &'a str,
+) -> Option<O> {
+    match parser(Input::new(s)) {
+        Ok(ok) if ok.remaining.is_empty() => Some(ok.parsed),
+        _ => None,
+    }
+}
+
 #[cfg(never)]
 pub fn dbg<'a, F: 'a, O: std::fmt::Debug + 'a>(
     s: &'static str,
@@ -1,26 +1,29 @@
 use std::{fmt, fmt::Formatter};
 
+/// Placeholder values for tests, e.g. locations of spans that aren't compared
+#[cfg(any(test, feature = "test"))]
+pub trait TestMockNew {
+    fn new_mocked() -> Self;
+}
+
 pub fn write_pretty_list<T>(
     f: &mut Formatter<'_>,
-    mut i: impl Iterator<Item = T> + Clone,
+    i: impl Iterator<Item = T>,
     mut write_t: impl FnMut(&mut Formatter<'_>, T) -> fmt::Result,
 ) -> fmt::Result {
-    let char_count = i.clone().count();
-    if char_count == 0 {
-        return write!(f, "<empty list>");
-    }
-    if char_count == 1 {
-        return write_t(f, i.next().unwrap());
-    }
-
-    let i = &mut i;
+    let mut items = i.collect::<Vec<_>>();
+    let (last, second_last) = match (items.pop(), items.pop()) {
+        (None, _) => return write!(f, "<empty list>"),
+        (Some(last), None) => return write_t(f, last),
+        (Some(last), Some(second_last)) => (last, second_last),
+    };
 
     write!(f, "one of ")?;
-    i.take(char_count - 2).try_for_each(|c| {
-        write_t(f, c)?;
-        write!(f, ", ")
-    })?;
-    write_t(f, i.next().unwrap())?;
+    for item in items {
+        write_t(f, item)?;
+        write!(f, ", ")?;
+    }
+    write_t(f, second_last)?;
     write!(f, " or ")?;
-    write_t(f, i.next().unwrap())
+    write_t(f, last)
 }
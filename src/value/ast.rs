@@ -7,17 +7,81 @@ use crate::{
     Value,
 };
 
+/// Which enums of the standard library [`Value::from_ast_with`] recognizes,
+/// all of them by default
+///
+/// ```
+/// use ron_reboot::{utf8_parser::ast_from_str, StdEnums, Value};
+///
+/// let ast = ast_from_str("[Some(1), Ok(a: 1)]").unwrap();
+/// assert_eq!(
+///     Value::from_ast_with(ast.clone(), StdEnums::default()),
+///     Value::List(vec![
+///         Value::Option(Some(Box::new(Value::Number(1.into())))),
+///         Value::Tuple(
+///             Some("Ok".to_owned()),
+///             vec![Value::Struct(None, vec![("a".to_owned(), Value::Number(1.into()))])],
+///         ),
+///     ])
+/// );
+/// assert_eq!(
+///     Value::from_ast_with(ast, StdEnums::NONE),
+///     Value::List(vec![
+///         Value::Tuple(Some("Some".to_owned()), vec![Value::Number(1.into())]),
+///         Value::Struct(Some("Ok".to_owned()), vec![("a".to_owned(), Value::Number(1.into()))]),
+///     ])
+/// );
+/// ```
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct StdEnums {
+    /// `Some(..)` and `None` as [`Value::Option`], otherwise as the tagged
+    /// tuple and unit they're written as
+    pub option: bool,
+    /// `Ok(..)` and `Err(..)` as tagged tuples of one element, like the
+    /// newtype variants of `Result`, also when written without the inner
+    /// parentheses: `Ok(a: 1)` as `Ok((a: 1))` and `Ok(1, 2)` as `Ok((1, 2))`
+    ///
+    /// Otherwise they're converted like any other variant, e.g. for an enum
+    /// of the document's own with an `Ok(i32, i32)` variant.
+    pub result: bool,
+}
+
+impl StdEnums {
+    /// Converts all variants as they're written
+    pub const NONE: StdEnums = StdEnums {
+        option: false,
+        result: false,
+    };
+}
+
+impl Default for StdEnums {
+    fn default() -> Self {
+        StdEnums {
+            option: true,
+            result: true,
+        }
+    }
+}
+
 impl Value {
     pub fn from_ast(ast: ast::Ron) -> Self {
         ast.into()
     }
+
+    /// Converts `ast`, recognizing the variants of `std_enums`; [`from_ast`]
+    /// recognizes all of them
+    ///
+    /// [`from_ast`]: Value::from_ast
+    pub fn from_ast_with(ast: ast::Ron, std_enums: StdEnums) -> Self {
+        from_expr(ast.expr.value, std_enums)
+    }
 }
 
 #[cfg(feature = "utf8_parser")]
 impl std::str::FromStr for Value {
     type Err = crate::Error;
 
-    /// Creates a value from a string reference.
+    /// Parses a document, unlike `From<&str>`, which makes a string value
     fn from_str(s: &str) -> Result<Self, crate::Error> {
         crate::utf8_parser::ast_from_str(s).map(Into::into)
     }
@@ -31,47 +95,87 @@ impl<'a> From<ast::Ron<'a>> for Value {
 
 impl<'a> From<ast::Expr<'a>> for Value {
     fn from(e: ast::Expr) -> Self {
-        match e {
-            Expr::Unit => Value::Unit(None),
-            Expr::Optional(o) => Value::Option(o.map(|s| s.value.into()).map(Box::new)),
-            Expr::Tagged(ast::Tagged { ident, untagged }) => match untagged.value {
-                Untagged::Unit => Value::Unit(Some(ident.value.0.to_owned())),
+        from_expr(e, StdEnums::default())
+    }
+}
+
+fn from_expr(e: ast::Expr, std_enums: StdEnums) -> Value {
+    let convert = move |e: ast::Spanned<ast::Expr>| from_expr(e.value, std_enums);
+
+    match e {
+        Expr::Unit => Value::Unit(None),
+        Expr::Optional(o) if std_enums.option => {
+            Value::Option(o.map(|s| convert(*s)).map(Box::new))
+        }
+        Expr::Optional(Some(inner)) => Value::Tuple(Some("Some".to_owned()), vec![convert(*inner)]),
+        Expr::Optional(None) => Value::Unit(Some("None".to_owned())),
+        Expr::Tagged(t) => {
+            let ast::Tagged { ident, untagged } = *t;
+            let result = std_enums.result && matches!(&*ident.value.0, "Ok" | "Err");
+            match untagged.value {
+                Untagged::Struct(s) if result => Value::Tuple(
+                    Some(ident.value.into_string()),
+                    vec![from_expr(Expr::Struct(s), std_enums)],
+                ),
+                Untagged::Tuple(t) if result && t.elements.len() != 1 => Value::Tuple(
+                    Some(ident.value.into_string()),
+                    vec![from_expr(Expr::Tuple(t), std_enums)],
+                ),
+                Untagged::Unit => Value::Unit(Some(ident.value.into_string())),
                 Untagged::Struct(s) => Value::Struct(
-                    Some(ident.value.0.to_owned()),
+                    Some(ident.value.into_string()),
                     s.fields
                         .into_iter()
                         .map(|s| (s.value.key.value, s.value.value.value))
-                        .map(|(k, v)| (k.into_string(), v.into()))
+                        .map(|(k, v)| (k.into_string(), from_expr(v, std_enums)))
                         .collect(),
                 ),
                 Untagged::Tuple(t) => Value::Tuple(
-                    Some(ident.value.0.to_owned()),
-                    t.elements.into_iter().map(Into::into).collect(),
+                    Some(ident.value.into_string()),
+                    t.elements.into_iter().map(convert).collect(),
                 ),
-            },
-            Expr::Bool(b) => Value::Bool(b),
-            Expr::Tuple(t) => Value::Tuple(None, t.elements.into_iter().map(Into::into).collect()),
-            Expr::List(l) => Value::List(l.elements.into_iter().map(Into::into).collect()),
-            Expr::Map(m) => Value::Map(
-                m.entries
-                    .into_iter()
-                    .map(|s| (s.value.key.value, s.value.value.value))
-                    .map(|(k, v)| (k.into(), v.into()))
-                    .collect(),
-            ),
-            Expr::Struct(s) => Value::Struct(
-                None,
-                s.fields
-                    .into_iter()
-                    .map(|s| (s.value.key.value, s.value.value.value))
-                    .map(|(k, v)| (k.into_string(), v.into()))
-                    .collect(),
-            ),
-            Expr::Integer(i) => Value::Number(Number::Integer(i.into_i64())),
-            Expr::Str(s) => Value::String(s.to_owned()),
-            Expr::String(s) => Value::String(s),
-            Expr::Decimal(d) => Value::Number(Number::Float(Float::new(d.into()))),
+            }
         }
+        Expr::Bool(b) => Value::Bool(b),
+        Expr::Tuple(t) => Value::Tuple(None, t.elements.into_iter().map(convert).collect()),
+        Expr::List(l) => Value::List(l.elements.into_iter().map(convert).collect()),
+        Expr::Map(m) => Value::Map(
+            m.entries
+                .into_iter()
+                .map(|s| (s.value.key.value, s.value.value.value))
+                .map(|(k, v)| (from_expr(k, std_enums), from_expr(v, std_enums)))
+                .collect(),
+        ),
+        Expr::Struct(s) => Value::Struct(
+            None,
+            s.fields
+                .into_iter()
+                .map(|s| (s.value.key.value, s.value.value.value))
+                .map(|(k, v)| (k.into_string(), from_expr(v, std_enums)))
+                .collect(),
+        ),
+        // Like `From<u64> for Number`, integers out of the range of `i64` are
+        // converted to floats
+        Expr::Integer(i) => Value::Number(match i.clone().into_i64() {
+            Some(i) => Number::Integer(i),
+            None => Number::new(
+                i.to_string()
+                    .parse::<f64>()
+                    .expect("integer is always a valid float"),
+            ),
+        }),
+        Expr::Str(s) => Value::String(s.to_owned()),
+        Expr::String(s) => Value::String(s),
+        Expr::EscapedStr(s) => Value::String(s.unescape().into_owned()),
+        Expr::Decimal(d) => Value::Number(Number::Float(Float::new((*d).into()))),
+        Expr::Quantity(q) => Value::String(q.to_string()),
+        Expr::Color(c) => Value::Tuple(
+            None,
+            c.to_array()
+                .iter()
+                .map(|&channel| Value::Number(Number::Integer(channel.into())))
+                .collect(),
+        ),
     }
 }
 
@@ -0,0 +1,169 @@
+//! Implements Rust values -> Value, e.g. for building values with
+//! [`value!`](crate::value!)
+
+use std::iter::FromIterator;
+
+use crate::{value::Number, Value};
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+impl From<char> for Value {
+    fn from(c: char) -> Self {
+        Value::Char(c)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(s.to_owned())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::String(s)
+    }
+}
+
+impl From<()> for Value {
+    fn from(_: ()) -> Self {
+        Value::Unit(None)
+    }
+}
+
+impl From<Number> for Value {
+    fn from(n: Number) -> Self {
+        Value::Number(n)
+    }
+}
+
+macro_rules! impl_from_number {
+    ($via:ident: $($ty:ty),*) => {
+        $(
+            impl From<$ty> for Value {
+                fn from(n: $ty) -> Self {
+                    Value::Number(Number::from($via::from(n)))
+                }
+            }
+        )*
+    };
+}
+
+// Like `Number::from(u64)`, integers that don't fit into an `i64` are floats
+impl_from_number!(i64: i8, i16, i32, i64);
+impl_from_number!(u64: u8, u16, u32, u64);
+impl_from_number!(f64: f32, f64);
+
+impl From<isize> for Value {
+    fn from(n: isize) -> Self {
+        Value::Number(Number::from(n as i64))
+    }
+}
+
+impl From<usize> for Value {
+    fn from(n: usize) -> Self {
+        Value::Number(Number::from(n as u64))
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(o: Option<T>) -> Self {
+        Value::Option(o.map(|v| Box::new(v.into())))
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(v: Vec<T>) -> Self {
+        v.into_iter().collect()
+    }
+}
+
+/// Collects a [`Value::List`]
+///
+/// ```
+/// # use ron_reboot::Value;
+/// let value: Value = (1..=3).collect();
+/// assert_eq!(value, "[1, 2, 3]".parse().unwrap());
+/// ```
+impl<T: Into<Value>> FromIterator<T> for Value {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Value::List(iter.into_iter().map(Into::into).collect())
+    }
+}
+
+/// Collects a [`Value::Map`], keeping the order of the entries
+///
+/// ```
+/// # use ron_reboot::Value;
+/// let value: Value = vec![("a", 1), ("b", 2)].into_iter().collect();
+/// assert_eq!(value, r#"{"a": 1, "b": 2}"#.parse().unwrap());
+/// ```
+impl<K: Into<Value>, V: Into<Value>> FromIterator<(K, V)> for Value {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Value::Map(
+            iter.into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(all(test, feature = "utf8_parser"))]
+mod tests {
+    use crate::{value, Value};
+
+    fn parse(s: &str) -> Value {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn from_iter() {
+        let list: Value = vec![Some(1), None].into_iter().collect();
+        assert_eq!(list, parse("[Some(1), None]"));
+        let map: Value = (0u8..2).map(|i| (i.to_string(), i)).collect();
+        assert_eq!(map, parse(r#"{"0": 0, "1": 1}"#));
+        assert_eq!(Value::from(vec![true]), parse("[true]"));
+        assert_eq!(Value::from(u64::MAX), parse("18446744073709551615.0"));
+    }
+
+    #[test]
+    fn value_macro() {
+        let (x, name) = (-1, "b");
+        let value = value!([
+            1,
+            x + 1,
+            2.5,
+            name,
+            (),
+            [],
+            {},
+            (1, [x]),
+            (a: 1, b: None),
+            Some(Some([])),
+            Empty(),
+            Point(x, 2),
+            Named(x: 1),
+            Braced { x: Some(x), y: { name: 1, (1, 2): "c" } },
+            vec![1, 2],
+        ]);
+
+        assert_eq!(
+            value,
+            parse(
+                r#"[
+                    1, 0, 2.5, "b", (), [], {}, (1, [-1]), (a: 1, b: None),
+                    Some(Some([])), Empty(), Point(-1, 2), Named(x: 1),
+                    Braced(x: Some(-1), y: {"b": 1, (1, 2): "c"}), [1, 2],
+                ]"#
+            )
+        );
+        assert_eq!(
+            value!(Some(x)),
+            Value::Option(Some(Box::new(Value::from(-1))))
+        );
+    }
+}
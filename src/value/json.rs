@@ -0,0 +1,133 @@
+//! Implements Value <-> `serde_json::Value`
+//!
+//! JSON has fewer types than RON, so converting to JSON loses information:
+//!
+//! * struct and tuple names are dropped, structs become objects and tuples
+//!   arrays
+//! * `None` and units become `null`, `Some(x)` becomes `x`
+//! * chars become strings
+//! * map keys other than strings become strings of their JSON text, e.g. `1`
+//!   becomes `"1"`
+//! * floats that aren't finite become `null`
+//!
+//! Converting back, `null` becomes `None` and objects become maps.
+
+use serde_json::{Map, Number as JsonNumber, Value as Json};
+
+use crate::value::{Number, Value};
+
+impl From<Value> for Json {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Bool(b) => Json::Bool(b),
+            Value::Char(c) => Json::String(c.to_string()),
+            Value::Map(entries) => Json::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| {
+                        let key = match key {
+                            Value::String(key) => key,
+                            key => Json::from(key).to_string(),
+                        };
+
+                        (key, value.into())
+                    })
+                    .collect(),
+            ),
+            Value::Number(Number::Integer(i)) => Json::Number(i.into()),
+            Value::Number(Number::Float(f)) => {
+                JsonNumber::from_f64(f.get()).map_or(Json::Null, Json::Number)
+            }
+            Value::Option(option) => option.map_or(Json::Null, |value| (*value).into()),
+            Value::String(s) => Json::String(s),
+            Value::List(elements) | Value::Tuple(_, elements) => {
+                Json::Array(elements.into_iter().map(Into::into).collect())
+            }
+            Value::Unit(_) => Json::Null,
+            Value::Struct(_, fields) => Json::Object(
+                fields
+                    .into_iter()
+                    .map(|(name, value)| (name, value.into()))
+                    .collect::<Map<_, _>>(),
+            ),
+        }
+    }
+}
+
+impl From<Json> for Value {
+    fn from(json: Json) -> Self {
+        match json {
+            Json::Null => Value::Option(None),
+            Json::Bool(b) => Value::Bool(b),
+            Json::Number(n) => Value::Number(match (n.as_i64(), n.as_u64()) {
+                (Some(i), _) => Number::new(i),
+                (None, Some(u)) => Number::new(u),
+                (None, None) => Number::new(n.as_f64().unwrap_or(f64::NAN)),
+            }),
+            Json::String(s) => Value::String(s),
+            Json::Array(elements) => Value::List(elements.into_iter().map(Into::into).collect()),
+            Json::Object(entries) => Value::Map(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (Value::String(key), value.into()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "utf8_parser"))]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn value(s: &str) -> Value {
+        crate::utf8_parser::ast_from_str(s).unwrap().into()
+    }
+
+    #[test]
+    fn to_json() {
+        let ron = value(
+            r#"Room(
+                name: "hall",
+                size: (10, 5.5),
+                door: Some("north"),
+                light: None,
+                kind: Open,
+                items: {"lamp": 1, 2: [true], (1, 2): inf},
+            )"#,
+        );
+
+        assert_eq!(
+            Json::from(ron),
+            json!({
+                "name": "hall",
+                "size": [10, 5.5],
+                "door": "north",
+                "light": null,
+                "kind": null,
+                "items": {"lamp": 1, "2": [true], "[1,2]": null},
+            })
+        );
+        assert_eq!(Json::from(Value::Char('n')), json!("n"));
+    }
+
+    #[test]
+    fn from_json() {
+        let json = json!({"a": [1, -2, 1.5], "b": null, "c": "text"});
+
+        assert_eq!(
+            Value::from(json),
+            value(r#"{"a": [1, -2, 1.5], "b": None, "c": "text"}"#)
+        );
+        assert_eq!(
+            Value::from(json!(u64::MAX)),
+            Value::Number(Number::new(u64::MAX))
+        );
+        assert_eq!(
+            Value::from(Json::from(value("[1, 2.5]"))),
+            value("[1, 2.5]")
+        );
+    }
+}
@@ -0,0 +1,146 @@
+/// Builds a [`Value`](crate::Value) from RON-like syntax, e.g. for test
+/// fixtures or generated configs
+///
+/// Lists, maps, tuples, structs, `Some(..)`, `None` and `()` are written like
+/// in RON, with struct names in front of tuples and structs as in
+/// `Pos(1, 2)`, or with Rust's braces as in `Pos { x: 1, y: 2 }`. Anything
+/// else is a Rust expression converted with `Value::from`, so variables can
+/// be interpolated. A name followed by parentheses or braces is always a
+/// RON value, so function calls have to be bound to a variable first, and a
+/// name on its own is a variable: named units are written as
+/// `Value::Unit(Some(..))`.
+///
+/// ```
+/// use ron_reboot::{value, Value};
+///
+/// let speed = 2.5;
+/// let value = value!(Player {
+///     pos: Pos(1, -2),
+///     speed: speed,
+///     inventory: ["sword", "shield"],
+///     stats: { "hp": 10, (1, 2): None },
+///     pet: Some((name: "Rex")),
+/// });
+///
+/// let parsed: Value = r#"Player(
+///     pos: Pos(1, -2),
+///     speed: 2.5,
+///     inventory: ["sword", "shield"],
+///     stats: { "hp": 10, (1, 2): None },
+///     pet: Some((name: "Rex")),
+/// )"#
+/// .parse()
+/// .unwrap();
+/// assert_eq!(value, parsed);
+/// ```
+#[macro_export]
+macro_rules! value {
+    // Elements of lists and tuples, each collected until the next comma
+    (@elements [$($done:expr,)*]) => {
+        ::std::vec![$($done),*]
+    };
+    (@elements [$($done:expr,)*] $next:tt , $($rest:tt)*) => {
+        $crate::value!(@elements [$($done,)* $crate::value!($next),] $($rest)*)
+    };
+    (@elements [$($done:expr,)*] $($rest:tt)+) => {
+        $crate::value!(@element [$($done,)*] [] $($rest)+)
+    };
+    (@element [$($done:expr,)*] [$($current:tt)+] , $($rest:tt)*) => {
+        $crate::value!(@elements [$($done,)* $crate::value!($($current)+),] $($rest)*)
+    };
+    (@element [$($done:expr,)*] [$($current:tt)+]) => {
+        $crate::value!(@elements [$($done,)* $crate::value!($($current)+),])
+    };
+    (@element [$($done:expr,)*] [$($current:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::value!(@element [$($done,)*] [$($current)* $next] $($rest)*)
+    };
+
+    // Entries of maps (`map`) and fields of structs (`field`), each key
+    // collected until the next colon and each value until the next comma
+    (@entries $kind:ident [$($done:expr,)*]) => {
+        ::std::vec![$($done),*]
+    };
+    (@entries $kind:ident [$($done:expr,)*] $($rest:tt)+) => {
+        $crate::value!(@key $kind [$($done,)*] [] $($rest)+)
+    };
+    (@key $kind:ident [$($done:expr,)*] [$($key:tt)+] : $($rest:tt)*) => {
+        $crate::value!(@entry $kind [$($done,)*] [$($key)+] [] $($rest)*)
+    };
+    (@key $kind:ident [$($done:expr,)*] [$($key:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::value!(@key $kind [$($done,)*] [$($key)* $next] $($rest)*)
+    };
+    (@entry $kind:ident [$($done:expr,)*] [$($key:tt)+] [$($value:tt)+] , $($rest:tt)*) => {
+        $crate::value!(
+            @entries $kind
+            [$($done,)* $crate::value!(@pair $kind [$($key)+] [$($value)+]),]
+            $($rest)*
+        )
+    };
+    (@entry $kind:ident [$($done:expr,)*] [$($key:tt)+] [$($value:tt)+]) => {
+        $crate::value!(
+            @entries $kind
+            [$($done,)* $crate::value!(@pair $kind [$($key)+] [$($value)+]),]
+        )
+    };
+    (@entry $kind:ident [$($done:expr,)*] [$($key:tt)+] [$($value:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::value!(@entry $kind [$($done,)*] [$($key)+] [$($value)* $next] $($rest)*)
+    };
+    (@pair map [$($key:tt)+] [$($value:tt)+]) => {
+        ($crate::value!($($key)+), $crate::value!($($value)+))
+    };
+    (@pair field [$field:ident] [$($value:tt)+]) => {
+        (
+            ::std::string::String::from(::std::stringify!($field)),
+            $crate::value!($($value)+),
+        )
+    };
+
+    // Options before names, which `Some` would be taken for
+    (None) => {
+        $crate::Value::Option(::std::option::Option::None)
+    };
+    (Some($($value:tt)+)) => {
+        $crate::Value::Option(::std::option::Option::Some(::std::boxed::Box::new(
+            $crate::value!($($value)+),
+        )))
+    };
+    (()) => {
+        $crate::Value::Unit(::std::option::Option::None)
+    };
+    ($name:ident { $($fields:tt)* }) => {
+        $crate::Value::Struct(
+            ::std::option::Option::Some(::std::string::String::from(::std::stringify!($name))),
+            $crate::value!(@entries field [] $($fields)*),
+        )
+    };
+    ($name:ident ( $field:ident : $($fields:tt)* )) => {
+        $crate::value!($name { $field : $($fields)* })
+    };
+    ($name:ident ( $($elements:tt)* )) => {
+        $crate::Value::Tuple(
+            ::std::option::Option::Some(::std::string::String::from(::std::stringify!($name))),
+            $crate::value!(@elements [] $($elements)*),
+        )
+    };
+    (( $field:ident : $($fields:tt)* )) => {
+        $crate::Value::Struct(
+            ::std::option::Option::None,
+            $crate::value!(@entries field [] $field : $($fields)*),
+        )
+    };
+    (( $($elements:tt)* )) => {
+        $crate::Value::Tuple(
+            ::std::option::Option::None,
+            $crate::value!(@elements [] $($elements)*),
+        )
+    };
+    ([ $($elements:tt)* ]) => {
+        $crate::Value::List($crate::value!(@elements [] $($elements)*))
+    };
+    ({ $($entries:tt)* }) => {
+        $crate::Value::Map($crate::value!(@entries map [] $($entries)*))
+    };
+    ($value:expr) => {
+        $crate::Value::from($value)
+    };
+}
@@ -8,9 +8,15 @@ use std::{
 };
 
 mod ast;
+mod from;
+#[cfg(feature = "value_json")]
+mod json;
+mod macros;
 #[cfg(feature = "value_serde1")]
 mod ser_de;
 
+pub use self::ast::StdEnums;
+
 /// A wrapper for a number, which can be either `f64` or `i64`.
 #[derive(Copy, Clone, Debug, PartialEq, PartialOrd, Eq, Hash, Ord)]
 pub enum Number {
@@ -222,6 +228,163 @@ pub enum Value {
     Struct(Option<String>, Vec<(String, Value)>),
 }
 
+/// How [`Value::eq_with`] and [`Value::hash_with`] compare numbers if either
+/// is a float, e.g. to ignore last-bit differences from exporters
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FloatEq {
+    /// Equal bits, so `0.0` and `-0.0` differ
+    Bitwise,
+    /// A difference of at most the epsilon
+    ///
+    /// As this isn't transitive, [`Value::hash_with`] ignores floats.
+    Epsilon(f64),
+    /// Equal when rounded to this many significant digits
+    Digits(u8),
+}
+
+impl FloatEq {
+    fn eq(self, a: f64, b: f64) -> bool {
+        match self {
+            FloatEq::Bitwise => a.to_bits() == b.to_bits(),
+            FloatEq::Epsilon(epsilon) => {
+                a.is_nan() && b.is_nan() || a == b || (a - b).abs() <= epsilon
+            }
+            FloatEq::Digits(digits) => round(a, digits) == round(b, digits),
+        }
+    }
+
+    fn hash<H: Hasher>(self, f: f64, state: &mut H) {
+        match self {
+            FloatEq::Bitwise => f.to_bits().hash(state),
+            FloatEq::Epsilon(_) => {}
+            FloatEq::Digits(digits) => round(f, digits).hash(state),
+        }
+    }
+}
+
+/// `f` in scientific notation with `digits` significant digits
+fn round(f: f64, digits: u8) -> String {
+    format!("{:.*e}", usize::from(digits.max(1)) - 1, f)
+}
+
+impl Value {
+    /// Whether `self` and `other` are equal, except for numbers that differ by
+    /// at most `epsilon` if either is a float.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use ron_reboot::Value;
+    /// let a: Value = "(x: 0.3, y: [1, 2.0])".parse().unwrap();
+    /// let b: Value = "(x: 0.30000001, y: [1, 2])".parse().unwrap();
+    /// assert!(a.approx_eq(&b, 1e-6));
+    /// assert!(!a.approx_eq(&b, 0.0));
+    /// ```
+    pub fn approx_eq(&self, other: &Value, epsilon: f64) -> bool {
+        self.eq_with(other, FloatEq::Epsilon(epsilon))
+    }
+
+    /// Whether `self` and `other` are equal, comparing numbers as `float_eq`
+    /// says if either is a float.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use ron_reboot::{FloatEq, Value};
+    /// let a: Value = "[0.1, 2.0]".parse().unwrap();
+    /// let b: Value = "[0.10000000000000002, 2]".parse().unwrap();
+    /// assert!(a.eq_with(&b, FloatEq::Digits(15)));
+    /// assert!(!a.eq_with(&b, FloatEq::Bitwise));
+    /// ```
+    pub fn eq_with(&self, other: &Value, float_eq: FloatEq) -> bool {
+        fn all_eq<'a, T: 'a>(a: &'a [T], b: &'a [T], eq: impl Fn(&'a T, &'a T) -> bool) -> bool {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| eq(a, b))
+        }
+
+        match (self, other) {
+            (Value::Number(Number::Integer(a)), Value::Number(Number::Integer(b))) => a == b,
+            (Value::Number(a), Value::Number(b)) => float_eq.eq(a.into_f64(), b.into_f64()),
+            (Value::Map(a), Value::Map(b)) => all_eq(a, b, |(ak, av), (bk, bv)| {
+                ak.eq_with(bk, float_eq) && av.eq_with(bv, float_eq)
+            }),
+            (Value::Option(Some(a)), Value::Option(Some(b))) => a.eq_with(b, float_eq),
+            (Value::List(a), Value::List(b)) => all_eq(a, b, |a, b| a.eq_with(b, float_eq)),
+            (Value::Tuple(a_name, a), Value::Tuple(b_name, b)) => {
+                a_name == b_name && all_eq(a, b, |a, b| a.eq_with(b, float_eq))
+            }
+            (Value::Struct(a_name, a), Value::Struct(b_name, b)) => {
+                a_name == b_name
+                    && all_eq(a, b, |(ak, av), (bk, bv)| {
+                        ak == bk && av.eq_with(bv, float_eq)
+                    })
+            }
+            (a, b) => a == b,
+        }
+    }
+
+    /// Hashes `self` such that values equal by [`eq_with`](Value::eq_with)
+    /// hash the same, e.g. for canonical hashes of assets
+    pub fn hash_with<H: Hasher>(&self, float_eq: FloatEq, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            // Integers equal floats they convert to
+            Value::Number(n) => float_eq.hash(n.into_f64(), state),
+            Value::Map(entries) => {
+                entries.len().hash(state);
+                for (key, value) in entries {
+                    key.hash_with(float_eq, state);
+                    value.hash_with(float_eq, state);
+                }
+            }
+            Value::Option(Some(value)) => value.hash_with(float_eq, state),
+            Value::List(elements) => {
+                elements.len().hash(state);
+                elements.iter().for_each(|e| e.hash_with(float_eq, state));
+            }
+            Value::Tuple(name, elements) => {
+                name.hash(state);
+                elements.len().hash(state);
+                elements.iter().for_each(|e| e.hash_with(float_eq, state));
+            }
+            Value::Struct(name, fields) => {
+                name.hash(state);
+                fields.len().hash(state);
+                for (field, value) in fields {
+                    field.hash(state);
+                    value.hash_with(float_eq, state);
+                }
+            }
+            value => value.hash(state),
+        }
+    }
+
+    /// The number of elements of a list, map, tuple or struct; 0 for other
+    /// values.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use ron_reboot::Value;
+    /// let value: Value = "Point(x: 1, y: 2)".parse().unwrap();
+    /// assert_eq!(value.len(), 2);
+    /// assert_eq!(Value::Bool(true).len(), 0);
+    /// ```
+    pub fn len(&self) -> usize {
+        match self {
+            Value::Map(entries) => entries.len(),
+            Value::List(elements) | Value::Tuple(_, elements) => elements.len(),
+            Value::Struct(_, fields) => fields.len(),
+            _ => 0,
+        }
+    }
+
+    /// Whether [`len`](Value::len) is 0, which is also the case for values
+    /// other than lists, maps, tuples and structs
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
 #[cfg(all(test, feature = "value_serde1", feature = "utf8_parser_serde1"))]
 mod tests {
     use std::{collections::BTreeMap, fmt::Debug};
@@ -257,6 +420,126 @@ mod tests {
         assert_eq!(direct, value, "T::deserialize(str) and T::deserialize(Value::from(ast_from_str(str))) is not the same for {:?}", s);
     }
 
+    #[test]
+    fn approx_eq() {
+        let value = |s: &str| s.parse::<Value>().unwrap();
+
+        let a = value("Room(size: (10.0, 5.5), doors: [1, 2], lights: {\"a\": 0.25})");
+        let b = value("Room(size: (10, 5.5000001), doors: [1, 2], lights: {\"a\": 0.25})");
+        assert!(a.approx_eq(&b, 1e-6));
+        assert!(!a.approx_eq(&b, 1e-9));
+        assert!(a.approx_eq(&a, 0.0));
+
+        // Only numbers are compared approximately
+        assert!(!value("[1]").approx_eq(&value("[2]"), 0.5));
+        assert!(value("[1.0]").approx_eq(&value("[1.5]"), 0.5));
+        assert!(!a.approx_eq(&value("Hall(size: (10.0, 5.5))"), 1.0));
+        assert!(!value("[1.0]").approx_eq(&value("[1.0, 2.0]"), 1.0));
+        assert!(value("NaN").approx_eq(&value("NaN"), 0.0));
+    }
+
+    #[test]
+    fn std_enums() {
+        use crate::utf8_parser::ast_from_str;
+
+        let value = |s: &str, std_enums| Value::from_ast_with(ast_from_str(s).unwrap(), std_enums);
+        let tagged = |tag: &str, elements| Value::Tuple(Some(tag.to_owned()), elements);
+        let one = Value::Number(1.into());
+
+        // `Ok` and `Err` always hold one value
+        assert_eq!(
+            value("Ok(1)", StdEnums::default()),
+            tagged("Ok", vec![one.clone()])
+        );
+        assert_eq!(
+            value("Err(1, 2)", StdEnums::default()),
+            tagged("Err", vec![Value::Tuple(None, vec![one.clone(), Value::Number(2.into())])])
+        );
+        assert_eq!(
+            value("Ok(())", StdEnums::default()),
+            tagged("Ok", vec![Value::Unit(None)])
+        );
+        assert_eq!(value("Ok", StdEnums::default()), Value::Unit(Some("Ok".to_owned())));
+        // Only the names of `Result`'s variants
+        assert_eq!(
+            value("Okay(1, 2)", StdEnums::default()),
+            tagged("Okay", vec![one.clone(), Value::Number(2.into())])
+        );
+
+        let written = StdEnums::NONE;
+        assert_eq!(
+            value("Err(1, 2)", written),
+            tagged("Err", vec![one.clone(), Value::Number(2.into())])
+        );
+        assert_eq!(value("None", written), Value::Unit(Some("None".to_owned())));
+        assert_eq!(
+            value("[Some(1)]", StdEnums { option: false, ..StdEnums::default() }),
+            Value::List(vec![tagged("Some", vec![one])])
+        );
+
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct A {
+            a: i32,
+        }
+
+        let result: Result<A, String> = value("Ok(a: 1)", StdEnums::default())
+            .into_rust_serde()
+            .unwrap();
+        assert_eq!(result, Ok(A { a: 1 }));
+    }
+
+    #[test]
+    fn float_eq() {
+        use std::collections::hash_map::DefaultHasher;
+
+        let value = |s: &str| s.parse::<Value>().unwrap();
+        let hash = |value: &Value, float_eq| {
+            let mut hasher = DefaultHasher::new();
+            value.hash_with(float_eq, &mut hasher);
+            hasher.finish()
+        };
+
+        let a = value("Mesh(scale: 0.1, size: [2, 1.5])");
+        let b = value("Mesh(scale: 0.10000000000000002, size: [2.0, 1.5])");
+        assert!(!a.eq_with(&b, FloatEq::Bitwise));
+        assert!(a.eq_with(&b, FloatEq::Digits(15)));
+        assert!(!a.eq_with(&b, FloatEq::Digits(17)));
+        assert!(a.eq_with(&b, FloatEq::Epsilon(1e-12)));
+
+        assert!(a.eq_with(&a, FloatEq::Bitwise));
+        assert!(value("0.0").eq_with(&value("0.0"), FloatEq::Bitwise));
+        assert!(!value("0.0").eq_with(&value("-0.0"), FloatEq::Bitwise));
+        assert!(value("[1.04]").eq_with(&value("[1]"), FloatEq::Digits(2)));
+        assert!(!value("[1.04]").eq_with(&value("[1]"), FloatEq::Digits(3)));
+
+        // Equal values hash the same
+        for float_eq in [FloatEq::Digits(15), FloatEq::Epsilon(1e-12)] {
+            assert_eq!(hash(&a, float_eq), hash(&b, float_eq));
+        }
+        assert_eq!(
+            hash(&value("[2]"), FloatEq::Bitwise),
+            hash(&value("[2.0]"), FloatEq::Bitwise)
+        );
+        assert_ne!(hash(&a, FloatEq::Bitwise), hash(&b, FloatEq::Bitwise));
+        assert_ne!(
+            hash(&value("[1, 2]"), FloatEq::Digits(15)),
+            hash(&value("[[1], 2]"), FloatEq::Digits(15))
+        );
+    }
+
+    #[test]
+    fn len() {
+        let value = |s: &str| s.parse::<Value>().unwrap();
+
+        assert_eq!(value("[1, 2, 3]").len(), 3);
+        assert_eq!(value("{1: 2}").len(), 1);
+        assert_eq!(value("(1, 2)").len(), 2);
+        assert_eq!(value("Point(x: 1, y: 2)").len(), 2);
+        assert!(value("[]").is_empty());
+        assert!(value("\"text\"").is_empty());
+        assert_eq!(value("Some([1])").len(), 0);
+    }
+
     #[test]
     fn boolean() {
         assert_same::<bool>("true");
@@ -282,6 +565,12 @@ mod tests {
         assert_same::<char>("'c'");
     }
 
+    #[test]
+    fn color() {
+        assert_same::<(u8, u8, u8, u8)>("#![enable(color_literals)] #ff800080");
+        assert_same::<[u8; 4]>("#![enable(color_literals)] #FF8000");
+    }
+
     #[test]
     fn map() {
         assert_same::<BTreeMap<String, String>>(
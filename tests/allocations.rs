@@ -0,0 +1,118 @@
+//! Upper bounds of allocations for parsing and deserializing, so that changes
+//! which make them allocate more show up
+//!
+//! Lower a bound when a change makes it allocate less; raise it only with a
+//! reason.
+
+use ron_reboot::{
+    alloc_counter::{count_allocations, is_counting, AllocStats, CountingAllocator},
+    utf8_parser::{
+        ast_from_str,
+        serde::{from_str, from_str_with_options, Options},
+        validate_str,
+    },
+};
+use serde::{de::IgnoredAny, Deserialize};
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct Config<'a> {
+    name: &'a str,
+    size: (u32, u32),
+    tags: Vec<&'a str>,
+    notes: String,
+}
+
+const CONFIG: &str = r#"Config(
+    name: "main",
+    size: (1920, 1080),
+    tags: ["a", "b", "c"],
+    notes: "line\nbreak",
+    ignored: ["x\ty", "z\tw"],
+)"#;
+
+fn assert_at_most(what: &str, stats: AllocStats, max: u64) {
+    assert!(
+        is_counting(),
+        "`CountingAllocator` isn't the global allocator"
+    );
+    assert!(
+        stats.allocations <= max,
+        "{} allocated {} times, at most {} expected",
+        what,
+        stats.allocations,
+        max
+    );
+}
+
+#[test]
+fn parsing() {
+    for (source, max) in [("1", 20), ("\"abc\"", 15), ("[1, 2, 3]", 45), (CONFIG, 105)] {
+        let (ron, stats) = count_allocations(|| ast_from_str(source));
+        assert!(ron.is_ok());
+        assert_at_most(source, stats, max);
+    }
+}
+
+#[test]
+fn validating() {
+    for source in ["1", "\"abc\"", "[1, 2, 3]", CONFIG] {
+        let (result, stats) = count_allocations(|| validate_str(source));
+        assert!(result.is_ok());
+        assert_at_most(source, stats, 0);
+    }
+}
+
+#[test]
+fn deserializing() {
+    let (config, stats) = count_allocations(|| from_str::<Config>(CONFIG));
+    assert!(config.is_ok());
+    assert_at_most("deserializing `Config`", stats, 106);
+
+    // Ignored strings with escapes aren't unescaped
+    let options = Options::new().lazy_escapes(true);
+    let (config, lazy) = count_allocations(|| from_str_with_options::<Config>(CONFIG, options));
+    assert!(config.is_ok());
+    assert_at_most("deserializing `Config` lazily", lazy, stats.allocations - 2);
+}
+
+#[test]
+fn lists_allocate_per_element() {
+    let list = |len: usize| format!("[{}]", "1, ".repeat(len));
+    let (small, large) = (list(1000), list(2000));
+    let (_, small) = count_allocations(|| from_str::<IgnoredAny>(&small));
+    let (_, large) = count_allocations(|| from_str::<IgnoredAny>(&large));
+
+    assert_at_most(
+        "1000 more list elements",
+        AllocStats {
+            allocations: large.allocations - small.allocations,
+            ..large
+        },
+        8 * 1000,
+    );
+}
+
+#[test]
+fn escaped_strings_allocate_once() {
+    let per_element = |element: &str| {
+        let list = |len: usize| format!("[{}]", element.repeat(len));
+        let (small, large) = (list(1000), list(2000));
+        let (_, small) = count_allocations(|| ast_from_str(&small));
+        let (_, large) = count_allocations(|| ast_from_str(&large));
+
+        (
+            large.allocations - small.allocations,
+            large.reallocations - small.reallocations,
+        )
+    };
+
+    // Only the unescaped `String`, which is allocated at its final size
+    let (borrowed, _) = per_element("\"short\", ");
+    let (escaped, reallocations) = per_element("\"a\\tb\\n\", ");
+    assert_eq!(escaped, borrowed + 1000);
+    assert!(reallocations <= 1, "{} reallocations", reallocations);
+}
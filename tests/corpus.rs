@@ -0,0 +1,102 @@
+//! Parses the regression corpus in `tests/corpus`, see its README
+
+use std::{
+    fs::{read_dir, read_to_string},
+    path::{Path, PathBuf},
+};
+
+use ron_reboot::{
+    utf8_parser::{ast_from_str, ast_from_str_recovering, validate_str},
+    Error,
+};
+
+fn corpus_files(dir: &str) -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/corpus")
+        .join(dir);
+    let mut files = read_dir(&dir)
+        .unwrap_or_else(|e| panic!("cannot read {}: {}", dir.display(), e))
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "ron"))
+        .collect::<Vec<_>>();
+    files.sort();
+
+    files
+}
+
+/// The kind of `e` without its details and where it starts, e.g.
+/// `ParseError 1:5`
+fn describe(e: &Error) -> String {
+    let kind = format!("{:?}", e.kind);
+    let kind = kind.split('(').next().unwrap_or_default();
+
+    match e.start() {
+        Some(start) => format!("{} {}", kind, start),
+        None => kind.to_owned(),
+    }
+}
+
+#[test]
+fn valid() {
+    let failures = corpus_files("valid")
+        .into_iter()
+        .filter_map(|path| {
+            let source = read_to_string(&path).unwrap();
+            ast_from_str(&source)
+                .err()
+                .map(|e| format!("{}: {}", path.display(), e))
+        })
+        .collect::<Vec<_>>();
+
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+#[test]
+fn invalid() {
+    let failures = corpus_files("invalid")
+        .into_iter()
+        .filter_map(|path| {
+            let source = read_to_string(&path).unwrap();
+            let expected = read_to_string(path.with_extension("expected")).ok();
+            match (ast_from_str(&source), expected) {
+                (Ok(_), _) => Some(format!("{}: parsed", path.display())),
+                (Err(e), Some(expected)) => {
+                    let expected = expected.trim();
+                    let actual = describe(&e);
+                    let matches = actual == expected
+                        || actual
+                            .strip_prefix(expected)
+                            .is_some_and(|loc| loc.starts_with(' '));
+
+                    (!matches).then(|| {
+                        format!(
+                            "{}: expected `{}`, got `{}`",
+                            path.display(),
+                            expected,
+                            actual
+                        )
+                    })
+                }
+                (Err(_), None) => None,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+#[test]
+fn validation_agrees_with_parsing() {
+    let failures = corpus_files("valid")
+        .into_iter()
+        .chain(corpus_files("invalid"))
+        .filter_map(|path| {
+            let source = read_to_string(&path).unwrap();
+            let parsed = ast_from_str_recovering(&source).map(|_| ());
+
+            (validate_str(&source) != parsed).then(|| path.display().to_string())
+        })
+        .collect::<Vec<_>>();
+
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
@@ -0,0 +1,52 @@
+//! Checks that output doesn't depend on the run, e.g. on the random order of
+//! hash maps, see "Deterministic output" in the README
+
+use std::collections::{BTreeMap, HashMap};
+
+use ron_reboot::{
+    utf8_parser::{
+        ast_from_input,
+        serde::{to_string_pretty, MapKeyOrder, PrettyConfig},
+        AltErrors, Input,
+    },
+    Error,
+};
+
+#[test]
+fn hash_maps_sorted() {
+    let entries: Vec<(String, BTreeMap<u8, bool>)> = (0..32)
+        .map(|i| {
+            (
+                format!("key_{}", i),
+                (0..3).map(|j| (j, i % 2 == 0)).collect(),
+            )
+        })
+        .collect();
+    let output = || {
+        // A new `RandomState` each time, so the maps iterate differently
+        let map: HashMap<_, _> = entries.iter().cloned().collect();
+        to_string_pretty(&map, PrettyConfig::new().map_key_order(MapKeyOrder::Sorted)).unwrap()
+    };
+
+    let first = output();
+    for _ in 0..16 {
+        assert_eq!(output(), first);
+    }
+    assert!(first.find("key_0").unwrap() < first.find("key_1").unwrap());
+}
+
+#[test]
+fn errors() {
+    let error = |source: &str| -> Error {
+        ast_from_input(Input::new(source).with_alt_errors(AltErrors::All)).unwrap_err()
+    };
+
+    for source in ["(a: [1, 2,", "{1: 2 3}", "Foo(a: tru)", "#![enable(x)] "] {
+        let first = error(source);
+        for _ in 0..4 {
+            let e = error(source);
+            assert_eq!(e.to_string(), first.to_string());
+            assert_eq!(e.expected_tokens(), first.expected_tokens());
+        }
+    }
+}
@@ -0,0 +1,128 @@
+//! Checks that every `FormatVersion` still formats like its files in
+//! `tests/golden`, see its README
+
+use std::{
+    collections::BTreeMap,
+    fs::{create_dir_all, read_to_string, write},
+    path::Path,
+};
+
+use ron_reboot::utf8_parser::serde::{
+    from_str, to_string_pretty, FormatVersion, MapKeyOrder, PrettyConfig, StructNames,
+};
+use serde::{Deserialize, Serialize};
+
+/// Every version, each with a directory in `tests/golden`
+const VERSIONS: [FormatVersion; 1] = [FormatVersion::V1];
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+struct Scene {
+    name: String,
+    version: (u8, u8, u8),
+    camera: Camera,
+    layers: Vec<Layer>,
+    tags: BTreeMap<String, Option<String>>,
+    empty: Vec<u8>,
+    nothing: (),
+    path: String,
+    note: String,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+struct Camera {
+    fov: f32,
+    position: [f64; 3],
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+enum Layer {
+    Background,
+    Tiles(u32, i64),
+    Sprites { count: u16, visible: bool },
+}
+
+fn scene() -> Scene {
+    let tags = [("b", Some("größe")), ("a", None), ("c", Some("x"))]
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.map(str::to_owned)))
+        .collect();
+
+    Scene {
+        name: "Level \"1\"".to_owned(),
+        version: (1, 0, 12),
+        camera: Camera {
+            fov: 90.0,
+            position: [0.5, -1.25, 1e6],
+        },
+        layers: vec![
+            Layer::Background,
+            Layer::Tiles(64, -3),
+            Layer::Sprites {
+                count: 2,
+                visible: true,
+            },
+        ],
+        tags,
+        empty: vec![],
+        nothing: (),
+        path: r"C:\levels\one.ron".to_owned(),
+        note: "line 1\nline 2\ttabbed".to_owned(),
+    }
+}
+
+/// The configurations whose output is checked, by golden file name
+fn configs() -> Vec<(&'static str, PrettyConfig)> {
+    vec![
+        ("default", PrettyConfig::new()),
+        (
+            "struct_names",
+            PrettyConfig::new()
+                .struct_names(StructNames::Always)
+                .enum_unit_as_ident(false)
+                .depth_limit(2),
+        ),
+        (
+            "strings_and_numbers",
+            PrettyConfig::new()
+                .indentor("  ".to_owned())
+                .escape_unicode(true)
+                .raw_string_threshold(Some(2))
+                .map_key_order(MapKeyOrder::Sorted)
+                .float_precision(Some(3)),
+        ),
+    ]
+}
+
+#[test]
+fn golden() {
+    let scene = scene();
+    let mut failures = vec![];
+    for version in VERSIONS {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/golden")
+            .join(format!("v{}", version));
+        for (name, config) in configs() {
+            let path = dir.join(name).with_extension("ron");
+            let output = to_string_pretty(&scene, config.format_version(version)).unwrap() + "\n";
+            match read_to_string(&path) {
+                Ok(golden) if golden == output => {
+                    assert_eq!(from_str::<Scene>(&golden).as_ref(), Ok(&scene));
+                }
+                Ok(golden) => failures.push(format!(
+                    "{} changed, which needs a new `FormatVersion`:\n{}\nis now\n{}",
+                    path.display(),
+                    golden,
+                    output
+                )),
+                // A new version or configuration
+                Err(_) => {
+                    create_dir_all(&dir).unwrap();
+                    write(&path, output).unwrap();
+                    failures.push(format!("{} written, commit it", path.display()));
+                }
+            }
+        }
+    }
+
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
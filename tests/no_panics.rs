@@ -0,0 +1,222 @@
+//! Mutates documents and checks that parsing, validating, deserializing and
+//! rendering errors never panic, see "Panics" in the README
+//!
+//! The mutations are deterministic, so a failure names an input that can be
+//! added to `tests/corpus`.
+
+use std::{
+    collections::HashMap,
+    fs::{read_dir, read_to_string},
+    panic::{catch_unwind, AssertUnwindSafe},
+    path::Path,
+};
+
+use ron_reboot::{
+    from_str_serde,
+    utf8_parser::{ast_from_str, ast_from_str_recovering, validate_str, DEFAULT_MAX_DEPTH},
+    write_error, Error, ErrorKind,
+};
+use serde::{de::IgnoredAny, Deserialize};
+
+/// Characters that open, close or separate something
+const SYNTAX: &[&str] = &[
+    "(", ")", "[", "]", "{", "}", ",", ":", "\"", "'", "#", "r#\"", "/*", "*/", "//", "\n", "-",
+    ".", "e", "0x", "é", "\\", "\\u{", "#![", "Some(",
+];
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+enum Shape {
+    Unit,
+    Newtype(u8),
+    Tuple(i32, f64),
+    Struct { name: String, tags: Vec<char> },
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+struct Document {
+    shapes: Vec<Shape>,
+    map: HashMap<String, Option<bool>>,
+    bytes: Vec<u8>,
+}
+
+/// A linear congruential generator, good enough to pick mutations
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self, bound: usize) -> usize {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+
+        (self.0 >> 33) as usize % bound.max(1)
+    }
+}
+
+fn seeds() -> Vec<String> {
+    let corpus = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus");
+    let mut files = ["valid", "invalid"]
+        .iter()
+        .flat_map(|dir| read_dir(corpus.join(dir)).unwrap())
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "ron"))
+        .collect::<Vec<_>>();
+    files.sort();
+
+    let mut seeds = files
+        .iter()
+        .map(|path| read_to_string(path).unwrap())
+        .collect::<Vec<_>>();
+    seeds.push(
+        r##"#![enable(implicit_some)]
+Document(
+    shapes: [Unit, Newtype(7), Tuple(-1, 2.5e3), Struct(name: r#"a "b""#, tags: ['x', '\u{e9}'])],
+    map: {"a": Some(true), "b": None, /* c */ "d": false},
+    bytes: [0x1, 0b10, 0o7, 255],
+)"##
+        .to_owned(),
+    );
+
+    seeds
+}
+
+/// Char boundaries of `s`, including its end
+fn boundaries(s: &str) -> Vec<usize> {
+    s.char_indices()
+        .map(|(i, _)| i)
+        .chain(Some(s.len()))
+        .collect()
+}
+
+fn mutations(seed: &str, rng: &mut Lcg) -> Vec<String> {
+    let bounds = boundaries(seed);
+    let mut inputs = bounds
+        .iter()
+        .step_by(bounds.len() / 64 + 1)
+        .map(|&end| seed[..end].to_owned())
+        .collect::<Vec<_>>();
+
+    for _ in 0..256 {
+        let mut input = seed.to_owned();
+        for _ in 0..1 + rng.next(3) {
+            let bounds = boundaries(&input);
+            let at = bounds[rng.next(bounds.len())];
+            match rng.next(3) {
+                0 => input.insert_str(at, SYNTAX[rng.next(SYNTAX.len())]),
+                1 => {
+                    let len = rng.next(8);
+                    let end = bounds
+                        .iter()
+                        .copied()
+                        .find(|&end| end > at + len)
+                        .unwrap_or(input.len());
+                    input.replace_range(at..end, "");
+                }
+                _ => {
+                    let from = bounds[rng.next(bounds.len())];
+                    let len = rng.next(16);
+                    let end = bounds
+                        .iter()
+                        .copied()
+                        .find(|&end| end > from + len)
+                        .unwrap_or(input.len());
+                    let copied = input[from..end].to_owned();
+                    input.insert_str(at, &copied);
+                }
+            }
+        }
+        inputs.push(input);
+    }
+
+    inputs
+}
+
+fn render(e: &Error) {
+    let mut out = Vec::new();
+    write_error(&mut out, e).unwrap();
+    let _ = format!("{} {:#} {:?}", e, e, e);
+}
+
+fn exercise(input: &str) {
+    if let Err(e) = ast_from_str(input) {
+        render(&e);
+    }
+    if let Err(errors) = ast_from_str_recovering(input) {
+        errors.iter().for_each(render);
+    }
+    let _ = validate_str(input);
+    if let Err(e) = from_str_serde::<IgnoredAny>(input) {
+        render(&e);
+    }
+    if let Err(e) = from_str_serde::<Document>(input) {
+        render(&e);
+    }
+    let _ = from_str_serde::<Vec<Shape>>(input);
+    let _ = from_str_serde::<Vec<i64>>(input);
+}
+
+#[test]
+fn mutated_documents() {
+    let mut rng = Lcg(0x5eed);
+    let failures = seeds()
+        .iter()
+        .flat_map(|seed| mutations(seed, &mut rng))
+        .filter(|input| catch_unwind(AssertUnwindSafe(|| exercise(input))).is_err())
+        .map(|input| format!("{:?}", input))
+        .collect::<Vec<_>>();
+
+    assert!(failures.is_empty(), "panicked on\n{}", failures.join("\n"));
+}
+
+#[test]
+fn integers_out_of_range() {
+    assert_eq!(
+        from_str_serde::<i64>("-9223372036854775808").unwrap(),
+        i64::MIN
+    );
+    assert!(from_str_serde::<i64>("-10000000000000000000").is_err());
+    assert!(from_str_serde::<u64>("-10000000000000000000").is_err());
+    assert_eq!(
+        from_str_serde::<i128>("-10000000000000000000").unwrap(),
+        -10_000_000_000_000_000_000
+    );
+}
+
+/// Values nested as deep as the parser allows are converted, deserialized and
+/// dropped on the stack of a test thread, deeper ones are rejected at the
+/// column of the first value too deep
+#[test]
+fn deep_nesting() {
+    // Map keys are values too
+    let cases = [
+        ("(a:", ")", 388),
+        ("{1:", "}", 386),
+        ("[", "]", 130),
+        ("Some(", ")", 646),
+    ];
+    for (open, close, column) in cases {
+        let nested = |depth: usize| open.repeat(depth) + "1" + &close.repeat(depth);
+
+        let deepest = nested(DEFAULT_MAX_DEPTH);
+        assert!(ast_from_str(&deepest).is_ok(), "{}", open);
+        assert!(from_str_serde::<IgnoredAny>(&deepest).is_ok(), "{}", open);
+        #[cfg(feature = "value_serde1")]
+        assert!(
+            from_str_serde::<ron_reboot::Value>(&deepest).is_ok(),
+            "{}",
+            open
+        );
+
+        for depth in [DEFAULT_MAX_DEPTH + 1, 300, 1_000, 10_000] {
+            for input in [nested(depth), open.repeat(depth)] {
+                let e = ast_from_str(&input).unwrap_err();
+                assert_eq!(e.kind, ErrorKind::NestingTooDeep(DEFAULT_MAX_DEPTH));
+                assert_eq!(e.start().map(|start| start.column), Some(column));
+
+                exercise(&input);
+            }
+        }
+    }
+}